@@ -0,0 +1,108 @@
+//! Example to echo user text messages, driving the client manually from a custom event loop.
+//!
+//! Unlike the `echo` example, no tasks are spawned. The network is only driven by calling
+//! `Client::step`, which makes it possible to embed the client in an existing event loop.
+//!
+//! The `TG_ID` and `TG_HASH` environment variables must be set (learn how to do it for
+//! [Windows](https://ss64.com/nt/set.html) or [Linux](https://ss64.com/bash/export.html))
+//! to Telegram's API ID and API hash respectively.
+//!
+//! Then, run it as:
+//!
+//! ```sh
+//! cargo run --example echo-manual -- BOT_TOKEN
+//! ```
+
+use futures_util::future::{select, Either};
+use grammers_client::session::Session;
+use grammers_client::{Client, Config, InitParams, Update};
+use simple_logger::SimpleLogger;
+use std::env;
+use std::pin::pin;
+use tokio::runtime;
+use tokio::time::sleep_until;
+
+type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+
+const SESSION_FILE: &str = "echo.session";
+
+async fn handle_update(client: &Client, update: Update) -> Result {
+    match update {
+        Update::NewMessage(message) if !message.outgoing() => {
+            let chat = message.chat();
+            println!("Responding to {}", chat.name());
+            client.send_message(&chat, message.text()).await?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+async fn async_main() -> Result {
+    SimpleLogger::new()
+        .with_level(log::LevelFilter::Debug)
+        .init()
+        .unwrap();
+
+    let api_id = env!("TG_ID").parse().expect("TG_ID invalid");
+    let api_hash = env!("TG_HASH").to_string();
+    let token = env::args().nth(1).expect("token missing");
+
+    println!("Connecting to Telegram...");
+    let client = Client::connect(Config {
+        session: Session::load_file_or_create(SESSION_FILE)?,
+        api_id,
+        api_hash: api_hash.clone(),
+        params: InitParams {
+            // Fetch the updates we missed while we were offline
+            catch_up: true,
+            ..Default::default()
+        },
+    })
+    .await?;
+    println!("Connected!");
+
+    if !client.is_authorized().await? {
+        println!("Signing in...");
+        client.bot_sign_in(&token).await?;
+        client.session().save_to_file(SESSION_FILE)?;
+        println!("Signed in!");
+    }
+
+    println!("Waiting for messages...");
+
+    // Each iteration performs a single network step, bounded by the next deadline the client
+    // cares about. In a real-world scenario, this deadline would be fed into the timer of the
+    // event loop the client is embedded in.
+    loop {
+        let exit = pin!(async { tokio::signal::ctrl_c().await });
+        let deadline = client.next_deadline();
+        let sleep = pin!(async { sleep_until(deadline.into()).await });
+        let step = pin!(async { client.step().await });
+
+        match select(exit, select(sleep, step)).await {
+            Either::Left(_) => break,
+            Either::Right((Either::Left(_), _)) => {}
+            Either::Right((Either::Right((step, _)), _)) => step?,
+        }
+
+        while let Some(update) = client.try_next_update().await? {
+            if let Err(e) = handle_update(&client, update).await {
+                eprintln!("Error handling updates!: {e}");
+            }
+        }
+    }
+
+    println!("Saving session file and exiting...");
+    client.session().save_to_file(SESSION_FILE)?;
+    Ok(())
+}
+
+fn main() -> Result {
+    runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(async_main())
+}
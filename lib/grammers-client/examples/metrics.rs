@@ -0,0 +1,111 @@
+//! This example collects metrics about the network operations of the client, and prints them
+//! after sending a few pings.
+//!
+//! ```sh
+//! cargo run --example metrics
+//! ```
+//!
+//! The counters are kept in memory for simplicity. In a real application, the same hooks can be
+//! wired to a metrics library instead, such as the `metrics` crate:
+//!
+//! ```ignore
+//! fn on_response(&self, constructor_id: u32, latency: Duration) {
+//!     let name = tl::name_for_id(constructor_id);
+//!     metrics::histogram!("telegram_rpc_latency_seconds", "method" => name).record(latency);
+//! }
+//! ```
+
+use grammers_client::session::Session;
+use grammers_client::{Client, Config, InitParams, Instrumentation};
+use grammers_tl_types as tl;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::runtime;
+
+type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+
+#[derive(Default)]
+struct Metrics {
+    bytes_sent: AtomicUsize,
+    bytes_received: AtomicUsize,
+    requests_in_flight: AtomicUsize,
+    reconnects: AtomicUsize,
+    latencies: Mutex<HashMap<u32, Vec<Duration>>>,
+}
+
+impl Instrumentation for Metrics {
+    fn on_bytes_sent(&self, len: usize) {
+        self.bytes_sent.fetch_add(len, Ordering::Relaxed);
+    }
+
+    fn on_bytes_received(&self, len: usize) {
+        self.bytes_received.fetch_add(len, Ordering::Relaxed);
+    }
+
+    fn on_response(&self, constructor_id: u32, latency: Duration) {
+        self.latencies
+            .lock()
+            .unwrap()
+            .entry(constructor_id)
+            .or_default()
+            .push(latency);
+    }
+
+    fn on_requests_in_flight(&self, count: usize) {
+        self.requests_in_flight.store(count, Ordering::Relaxed);
+    }
+
+    fn on_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+async fn async_main() -> Result {
+    let metrics = Arc::new(Metrics::default());
+
+    println!("Connecting to Telegram...");
+    let client = Client::connect(Config {
+        session: Session::load_file_or_create("metrics.session")?,
+        api_id: 1, // not actually logging in, but has to look real
+        api_hash: "".to_string(),
+        params: InitParams {
+            instrumentation: Some(metrics.clone()),
+            ..Default::default()
+        },
+    })
+    .await?;
+    println!("Connected!");
+
+    for ping_id in 0..5 {
+        client.invoke(&tl::functions::Ping { ping_id }).await?;
+    }
+
+    println!(
+        "Sent {} bytes, received {} bytes, {} request(s) in flight, {} reconnection(s)",
+        metrics.bytes_sent.load(Ordering::Relaxed),
+        metrics.bytes_received.load(Ordering::Relaxed),
+        metrics.requests_in_flight.load(Ordering::Relaxed),
+        metrics.reconnects.load(Ordering::Relaxed),
+    );
+    for (constructor_id, latencies) in metrics.latencies.lock().unwrap().iter() {
+        let total = latencies.iter().sum::<Duration>();
+        println!(
+            "{}: {} response(s), {:?} on average",
+            tl::name_for_id(*constructor_id),
+            latencies.len(),
+            total / latencies.len() as u32
+        );
+    }
+
+    Ok(())
+}
+
+fn main() -> Result {
+    runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(async_main())
+}
@@ -0,0 +1,634 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Methods related to the profile of the logged-in account.
+
+use super::chats::{local_error, ChatPhotoFile};
+use super::Client;
+use crate::types::{Authorization, Photo, User};
+use crate::utils;
+use grammers_crypto::two_factor_auth::{check_p_and_g, compute_password_hash, new_salt1};
+pub use grammers_mtsender::InvocationError;
+use grammers_tl_types as tl;
+use std::fmt;
+
+const MAX_ABOUT_LENGTH: usize = 70;
+const MAX_PREMIUM_ABOUT_LENGTH: usize = 140;
+const USERNAME_LENGTH: std::ops::RangeInclusive<usize> = 5..=32;
+
+/// The error type which is returned when checking or changing a username fails.
+#[derive(Debug)]
+pub enum UsernameError {
+    /// The username is not valid. Usernames must be 5 to 32 characters long, start with a
+    /// letter, and only contain letters, digits and underscores.
+    UsernameInvalid,
+    /// The username is already taken by someone else.
+    UsernameOccupied,
+    /// The username is taken, but can be bought on [Fragment](https://fragment.com).
+    PurchaseAvailable,
+    Other(InvocationError),
+}
+
+impl fmt::Display for UsernameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UsernameInvalid => write!(f, "username error: username invalid"),
+            Self::UsernameOccupied => write!(f, "username error: username occupied"),
+            Self::PurchaseAvailable => write!(f, "username error: username can be purchased"),
+            Self::Other(e) => write!(f, "username error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for UsernameError {}
+
+impl From<InvocationError> for UsernameError {
+    fn from(error: InvocationError) -> Self {
+        if error.is("USERNAME_INVALID") {
+            Self::UsernameInvalid
+        } else if error.is("USERNAME_OCCUPIED") {
+            Self::UsernameOccupied
+        } else if error.is("USERNAME_PURCHASE_AVAILABLE") {
+            Self::PurchaseAvailable
+        } else {
+            Self::Other(error)
+        }
+    }
+}
+
+/// The error type which is returned when terminating sessions fails.
+#[derive(Debug)]
+pub enum TerminateSessionError {
+    /// The logged-in session is too new to terminate other sessions. Telegram only allows it
+    /// once the session has been logged in for at least 24 hours.
+    FreshResetForbidden,
+    Other(InvocationError),
+}
+
+impl fmt::Display for TerminateSessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FreshResetForbidden => write!(
+                f,
+                "terminate session error: the current session is too new to terminate others"
+            ),
+            Self::Other(e) => write!(f, "terminate session error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for TerminateSessionError {}
+
+impl From<InvocationError> for TerminateSessionError {
+    fn from(error: InvocationError) -> Self {
+        if error.is("FRESH_RESET_AUTHORISATION_FORBIDDEN") {
+            Self::FreshResetForbidden
+        } else {
+            Self::Other(error)
+        }
+    }
+}
+
+/// The error type which is returned when changing the two-factor authentication password fails.
+#[derive(Debug)]
+pub enum PasswordError {
+    /// The current password is incorrect, or it was not provided but the account has one.
+    InvalidPassword,
+    /// The recovery email is not valid.
+    EmailInvalid,
+    /// The password was changed, but it won't be used until the recovery email is confirmed
+    /// with [`Client::confirm_password_email`], using the code of the given length sent to it.
+    EmailUnconfirmed {
+        /// The length of the code sent to the recovery email.
+        code_length: usize,
+    },
+    /// The code used to confirm the recovery email is incorrect.
+    InvalidCode,
+    Other(InvocationError),
+}
+
+impl fmt::Display for PasswordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidPassword => write!(f, "password error: invalid password"),
+            Self::EmailInvalid => write!(f, "password error: email invalid"),
+            Self::EmailUnconfirmed { code_length } => write!(
+                f,
+                "password error: email unconfirmed, a code of length {code_length} was sent"
+            ),
+            Self::InvalidCode => write!(f, "password error: invalid code"),
+            Self::Other(e) => write!(f, "password error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for PasswordError {}
+
+impl From<InvocationError> for PasswordError {
+    fn from(error: InvocationError) -> Self {
+        match error {
+            InvocationError::Rpc(rpc) if rpc.is("PASSWORD_HASH_INVALID") => Self::InvalidPassword,
+            InvocationError::Rpc(rpc) if rpc.is("EMAIL_INVALID") => Self::EmailInvalid,
+            InvocationError::Rpc(rpc) if rpc.is("EMAIL_UNCONFIRMED") => Self::EmailUnconfirmed {
+                code_length: rpc.value.unwrap_or(0) as usize,
+            },
+            InvocationError::Rpc(rpc) if rpc.is("CODE_INVALID") => Self::InvalidCode,
+            error => Self::Other(error),
+        }
+    }
+}
+
+/// Check the rules usernames must follow, so that invalid ones fail without a request.
+pub(crate) fn check_username(username: &str) -> Result<(), UsernameError> {
+    let mut chars = username.chars();
+    if USERNAME_LENGTH.contains(&username.len())
+        && chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        Ok(())
+    } else {
+        Err(UsernameError::UsernameInvalid)
+    }
+}
+
+/// A builder to change the profile of the logged-in account.
+///
+/// Only the fields which are set are changed. It will be executed once
+/// [`ProfileBuilder::send`] is `.await`-ed.
+pub struct ProfileBuilder {
+    client: Client,
+    first_name: Option<String>,
+    last_name: Option<String>,
+    about: Option<String>,
+}
+
+impl ProfileBuilder {
+    fn new(client: &Client) -> Self {
+        Self {
+            client: client.clone(),
+            first_name: None,
+            last_name: None,
+            about: None,
+        }
+    }
+
+    /// The new first name, which cannot be empty.
+    pub fn first_name<T: Into<String>>(mut self, first_name: T) -> Self {
+        self.first_name = Some(first_name.into());
+        self
+    }
+
+    /// The new last name. An empty last name removes it.
+    pub fn last_name<T: Into<String>>(mut self, last_name: T) -> Self {
+        self.last_name = Some(last_name.into());
+        self
+    }
+
+    /// The new bio. An empty bio removes it.
+    ///
+    /// The bio can be up to 70 characters long, or 140 for premium accounts.
+    pub fn about<T: Into<String>>(mut self, about: T) -> Self {
+        self.about = Some(about.into());
+        self
+    }
+
+    /// Change the profile, returning the updated logged-in user.
+    pub async fn send(self) -> Result<User, InvocationError> {
+        if let Some(about) = &self.about {
+            let premium = self
+                .client
+                .0
+                .state
+                .read()
+                .unwrap()
+                .me
+                .as_ref()
+                .is_some_and(|me| me.raw.premium);
+            let limit = if premium {
+                MAX_PREMIUM_ABOUT_LENGTH
+            } else {
+                MAX_ABOUT_LENGTH
+            };
+            if about.chars().count() > limit {
+                return Err(local_error("ABOUT_TOO_LONG"));
+            }
+        }
+
+        let user = User::from_raw(
+            self.client
+                .invoke(&tl::functions::account::UpdateProfile {
+                    first_name: self.first_name,
+                    last_name: self.last_name,
+                    about: self.about,
+                })
+                .await?,
+        );
+        self.client.0.state.write().unwrap().me = Some(user.clone());
+        Ok(user)
+    }
+}
+
+/// Method implementations related to the profile of the logged-in account.
+impl Client {
+    /// Change the name or bio of the logged-in account.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let me = client
+    ///     .update_profile()
+    ///     .first_name("Ferris")
+    ///     .about("Rustacean")
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn update_profile(&self) -> ProfileBuilder {
+        ProfileBuilder::new(self)
+    }
+
+    /// Check whether a username is available to be used by the logged-in account.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// if client.check_username("ferris_the_crab").await? {
+    ///     client.set_username(Some("ferris_the_crab")).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn check_username(&self, username: &str) -> Result<bool, UsernameError> {
+        check_username(username)?;
+        match self
+            .invoke(&tl::functions::account::CheckUsername {
+                username: username.to_string(),
+            })
+            .await
+        {
+            Ok(available) => Ok(available),
+            Err(err) if err.is("USERNAME_OCCUPIED") => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Change the username of the logged-in account, returning the updated user.
+    ///
+    /// `None` removes the username.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.set_username(None).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_username(&self, username: Option<&str>) -> Result<User, UsernameError> {
+        if let Some(username) = username {
+            check_username(username)?;
+        }
+        let user = match self
+            .invoke(&tl::functions::account::UpdateUsername {
+                username: username.unwrap_or_default().to_string(),
+            })
+            .await
+        {
+            Ok(user) => User::from_raw(user),
+            Err(err) if err.is("USERNAME_NOT_MODIFIED") => return Ok(self.get_me().await?),
+            Err(err) => return Err(err.into()),
+        };
+        self.0.state.write().unwrap().me = Some(user.clone());
+        Ok(user)
+    }
+
+    /// Set, change or remove the two-factor authentication password of the logged-in account.
+    ///
+    /// `current` must be the current password, if the account has one. Using `None` as the `new`
+    /// password removes it, along with its `hint`.
+    ///
+    /// If a recovery `email` is given, Telegram sends a code to it, and this method fails with
+    /// [`PasswordError::EmailUnconfirmed`]. The new password won't be used until the code is
+    /// given to [`Client::confirm_password_email`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::client::PasswordError;
+    ///
+    /// fn ask_code() -> String {
+    ///     unimplemented!()
+    /// }
+    ///
+    /// match client
+    ///     .set_password(Some("hunter2"), Some("correct horse"), Some("horse"), Some("me@example.com"))
+    ///     .await
+    /// {
+    ///     Ok(()) => println!("Password changed"),
+    ///     Err(PasswordError::EmailUnconfirmed { .. }) => {
+    ///         client.confirm_password_email(&ask_code()).await?;
+    ///     }
+    ///     Err(e) => return Err(e.into()),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_password(
+        &self,
+        current: Option<&str>,
+        new: Option<&str>,
+        hint: Option<&str>,
+        email: Option<&str>,
+    ) -> Result<(), PasswordError> {
+        let password_info = self.get_password_information().await?.password;
+
+        let password = if password_info.current_algo.is_some() {
+            match current {
+                Some(current) => self.password_check(password_info.clone(), current).await?,
+                None => return Err(PasswordError::InvalidPassword),
+            }
+        } else {
+            tl::enums::InputCheckPasswordSrp::InputCheckPasswordEmpty
+        };
+
+        let new_settings = match new {
+            Some(new) => {
+                let (salt1, salt2, p, g) =
+                    utils::extract_password_parameters(&password_info.new_algo);
+                if !check_p_and_g(p, g) {
+                    panic!("Failed to get correct password information from Telegram")
+                }
+
+                let salt1 = new_salt1(salt1);
+                let new_password_hash = compute_password_hash(&salt1, salt2, p, g, new);
+
+                tl::types::account::PasswordInputSettings {
+                    new_algo: Some(
+                        tl::types::PasswordKdfAlgoSha256Sha256Pbkdf2Hmacsha512iter100000Sha256ModPow {
+                            salt1,
+                            salt2: salt2.clone(),
+                            g: *g,
+                            p: p.clone(),
+                        }
+                        .into(),
+                    ),
+                    new_password_hash: Some(new_password_hash.to_vec()),
+                    hint: Some(hint.unwrap_or_default().to_string()),
+                    email: email.map(|email| email.to_string()),
+                    new_secure_settings: None,
+                }
+            }
+            // "To remove the password, pass passwordKdfAlgoUnknown, an empty hash and an empty hint."
+            None => tl::types::account::PasswordInputSettings {
+                new_algo: Some(tl::enums::PasswordKdfAlgo::Unknown),
+                new_password_hash: Some(Vec::new()),
+                hint: Some(String::new()),
+                email: None,
+                new_secure_settings: None,
+            },
+        };
+
+        self.invoke(&tl::functions::account::UpdatePasswordSettings {
+            password,
+            new_settings: new_settings.into(),
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Confirm the recovery email of a new two-factor authentication password, with the code
+    /// sent to it after [`Client::set_password`] failed with [`PasswordError::EmailUnconfirmed`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.confirm_password_email("12345").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn confirm_password_email(&self, code: &str) -> Result<(), PasswordError> {
+        self.invoke(&tl::functions::account::ConfirmPasswordEmail {
+            code: code.to_string(),
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Change whether the logged-in account appears online or offline to others.
+    ///
+    /// Being connected makes the account appear online. User accounts which should not appear
+    /// online while running can call this with `true` after connecting.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.set_offline(true).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_offline(&self, offline: bool) -> Result<(), InvocationError> {
+        self.invoke(&tl::functions::account::UpdateStatus { offline })
+            .await
+            .map(drop)
+    }
+
+    /// Get the sessions logged in to the account, including the one used by this client.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// for session in client.get_authorizations().await? {
+    ///     println!("{} on {} from {}", session.app_name(), session.device_model(), session.ip());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_authorizations(&self) -> Result<Vec<Authorization>, InvocationError> {
+        let tl::enums::account::Authorizations::Authorizations(authorizations) = self
+            .invoke(&tl::functions::account::GetAuthorizations {})
+            .await?;
+
+        Ok(authorizations
+            .authorizations
+            .into_iter()
+            .map(Authorization::from_raw)
+            .collect())
+    }
+
+    /// Terminate a different session logged in to the account, by its [`Authorization::hash`].
+    ///
+    /// To log out of the session used by this client, use [`Client::sign_out`] instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// for session in client.get_authorizations().await? {
+    ///     if !session.is_current() && !session.is_official_app() {
+    ///         client.terminate_session(session.hash()).await?;
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn terminate_session(&self, hash: i64) -> Result<(), TerminateSessionError> {
+        self.invoke(&tl::functions::account::ResetAuthorization { hash })
+            .await?;
+        Ok(())
+    }
+
+    /// Terminate every session logged in to the account, except the one used by this client.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::client::TerminateSessionError;
+    ///
+    /// match client.terminate_all_other_sessions().await {
+    ///     Ok(()) => println!("Logged out everywhere else"),
+    ///     Err(TerminateSessionError::FreshResetForbidden) => {
+    ///         println!("Try again once this session is at least a day old")
+    ///     }
+    ///     Err(e) => return Err(e.into()),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn terminate_all_other_sessions(&self) -> Result<(), TerminateSessionError> {
+        self.invoke(&tl::functions::auth::ResetAuthorizations {})
+            .await?;
+        Ok(())
+    }
+
+    /// Set after how many days of inactivity sessions are terminated automatically.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.set_session_ttl(180).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_session_ttl(&self, days: i32) -> Result<(), InvocationError> {
+        self.invoke(&tl::functions::account::SetAuthorizationTtl {
+            authorization_ttl_days: days,
+        })
+        .await
+        .map(drop)
+    }
+
+    /// Set a new profile photo for the logged-in account, returning it.
+    ///
+    /// The photo may be a path to a file, which will be uploaded first, or a file which has
+    /// already been uploaded. Previous photos are kept, and can be found with
+    /// [`Client::iter_profile_photos`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let photo = client.set_profile_photo("me.jpg").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_profile_photo<F: Into<ChatPhotoFile>>(
+        &self,
+        photo: F,
+    ) -> Result<Photo, InvocationError> {
+        let file = self.upload_chat_photo_file(photo.into()).await?;
+        let tl::enums::photos::Photo::Photo(photo) = self
+            .invoke(&tl::functions::photos::UploadProfilePhoto {
+                fallback: false,
+                bot: None,
+                file: Some(file),
+                video: None,
+                video_start_ts: None,
+                video_emoji_markup: None,
+            })
+            .await?;
+
+        {
+            let mut state = self.0.state.write().unwrap();
+            // Telegram can return peers without hash (e.g. Users with 'min: true')
+            let _ = state.chat_hashes.extend(&photo.users, &[]);
+            // The photo of the logged-in user changed.
+            state.me = None;
+        }
+
+        Ok(Photo::from_raw(photo.photo))
+    }
+
+    /// Delete profile photos of the logged-in account, returning how many were deleted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let me = client.get_me().await?;
+    /// let mut photos = client.iter_profile_photos(&me);
+    ///
+    /// let mut old = Vec::new();
+    /// while let Some(photo) = photos.next().await? {
+    ///     old.push(photo);
+    /// }
+    /// client.delete_profile_photos(&old[1..]).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn delete_profile_photos(&self, photos: &[Photo]) -> Result<usize, InvocationError> {
+        let deleted = self
+            .invoke(&tl::functions::photos::DeletePhotos {
+                id: photos
+                    .iter()
+                    .map(|photo| photo.to_raw_input_media().id)
+                    .collect(),
+            })
+            .await?;
+
+        self.0.state.write().unwrap().me = None;
+        Ok(deleted.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_username_rules() {
+        assert!(check_username("grammers").is_ok());
+        assert!(check_username("Gram_mers_2").is_ok());
+        assert!(check_username("abcd").is_err());
+        assert!(check_username(&"a".repeat(33)).is_err());
+        assert!(check_username("1grammers").is_err());
+        assert!(check_username("_grammers").is_err());
+        assert!(check_username("gram-mers").is_err());
+        assert!(check_username("grammérs").is_err());
+    }
+
+    #[test]
+    fn email_unconfirmed_carries_code_length() {
+        let error = InvocationError::Rpc(grammers_mtsender::RpcError::from(tl::types::RpcError {
+            error_code: 400,
+            error_message: "EMAIL_UNCONFIRMED_6".into(),
+        }));
+        assert!(matches!(
+            PasswordError::from(error),
+            PasswordError::EmailUnconfirmed { code_length: 6 }
+        ));
+    }
+}
@@ -5,11 +5,13 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
-use super::net::connect_sender;
+use super::chats::local_error;
+use super::net::save_session;
 use super::Client;
-use crate::types::{LoginToken, PasswordToken, TermsOfService, User};
+use crate::types::{LoginToken, PasswordToken, SentCode, TermsOfService, User};
 use crate::utils;
 use grammers_crypto::two_factor_auth::{calculate_2fa, check_p_and_g};
+use grammers_mtsender::RpcError;
 pub use grammers_mtsender::{AuthorizationError, InvocationError};
 use grammers_tl_types as tl;
 use std::fmt;
@@ -24,6 +26,8 @@ pub enum SignInError {
     PasswordRequired(PasswordToken),
     InvalidCode,
     InvalidPassword,
+    /// The bot token is malformed, or was revoked.
+    InvalidToken,
     Other(InvocationError),
 }
 
@@ -33,13 +37,11 @@ impl fmt::Display for SignInError {
         match self {
             SignUpRequired {
                 terms_of_service: tos,
-            } => write!(
-                f,
-                "sign in error: sign up with official client required: {tos:?}"
-            ),
+            } => write!(f, "sign in error: sign up required: {tos:?}"),
             PasswordRequired(_password) => write!(f, "2fa password required"),
             InvalidCode => write!(f, "sign in error: invalid code"),
             InvalidPassword => write!(f, "invalid password"),
+            InvalidToken => write!(f, "sign in error: invalid bot token"),
             Other(e) => write!(f, "sign in error: {e}"),
         }
     }
@@ -96,7 +98,15 @@ impl Client {
                 .session
                 .set_user(user.id(), state.dc_id, user.is_bot());
 
+            if auth.future_auth_token.is_some() {
+                self.0
+                    .config
+                    .session
+                    .set_future_auth_token(auth.future_auth_token);
+            }
+
             state.chat_hashes.set_self_user(user.pack());
+            state.me = Some(user.clone());
             if let Some(us) = update_state {
                 state.message_box.set_state(us);
                 true
@@ -108,6 +118,7 @@ impl Client {
         if sync_state {
             self.sync_update_state();
         }
+        save_session(&self.0.config).await;
 
         Ok(user)
     }
@@ -140,7 +151,11 @@ impl Client {
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn bot_sign_in(&self, token: &str) -> Result<User, AuthorizationError> {
+    pub async fn bot_sign_in(&self, token: &str) -> Result<User, SignInError> {
+        if !is_valid_bot_token(token) {
+            return Err(SignInError::InvalidToken);
+        }
+
         let request = tl::functions::auth::ImportBotAuthorization {
             flags: 0,
             api_id: self.0.config.api_id,
@@ -148,25 +163,12 @@ impl Client {
             bot_auth_token: token.to_string(),
         };
 
-        let result = match self.invoke(&request).await {
-            Ok(x) => x,
-            Err(InvocationError::Rpc(err)) if err.code == 303 => {
-                let dc_id = err.value.unwrap() as i32;
-                let (sender, request_tx) = connect_sender(dc_id, &self.0.config).await?;
-                {
-                    *self.0.conn.sender.lock().await = sender;
-                    *self.0.conn.request_tx.write().unwrap() = request_tx;
-                    let mut state = self.0.state.write().unwrap();
-                    state.dc_id = dc_id;
-                }
-                self.invoke(&request).await?
-            }
-            Err(e) => return Err(e.into()),
-        };
+        // If the bot lives in a different datacenter, `invoke` takes care of moving there.
+        let result = self.invoke(&request).await.map_err(bot_sign_in_error)?;
 
         match result {
             tl::enums::auth::Authorization::Authorization(x) => {
-                self.complete_login(x).await.map_err(Into::into)
+                self.complete_login(x).await.map_err(SignInError::Other)
             }
             tl::enums::auth::Authorization::SignUpRequired(_) => {
                 panic!("API returned SignUpRequired even though we're logging in as a bot");
@@ -209,7 +211,7 @@ impl Client {
                 allow_app_hash: false,
                 allow_missed_call: false,
                 allow_firebase: false,
-                logout_tokens: None,
+                logout_tokens: self.0.config.session.future_auth_token().map(|t| vec![t]),
                 token: None,
                 app_sandbox: None,
                 unknown_number: false,
@@ -223,34 +225,79 @@ impl Client {
             Ok(x) => match x {
                 SC::Code(code) => code,
                 SC::Success(_) => panic!("should not have logged in yet"),
+                // Telegram wants a Premium subscription bought through an app store.
+                SC::PaymentRequired(_) => return Err(local_error("PAYMENT_REQUIRED").into()),
             },
-            Err(InvocationError::Rpc(err)) if err.code == 303 => {
-                // Since we are not logged in (we're literally requesting for
-                // the code to login now), there's no need to export the current
-                // authorization and re-import it at a different datacenter.
-                //
-                // Just connect and generate a new authorization key with it
-                // before trying again.
-                let dc_id = err.value.unwrap() as i32;
-                let (sender, request_tx) = connect_sender(dc_id, &self.0.config).await?;
-                {
-                    *self.0.conn.sender.lock().await = sender;
-                    *self.0.conn.request_tx.write().unwrap() = request_tx;
-                    let mut state = self.0.state.write().unwrap();
-                    state.dc_id = dc_id;
-                }
-                match self.invoke(&request).await? {
-                    SC::Code(code) => code,
-                    SC::Success(_) => panic!("should not have logged in yet"),
-                }
-            }
             Err(e) => return Err(e.into()),
         };
 
         Ok(LoginToken {
             phone: phone.to_string(),
-            phone_code_hash: sent_code.phone_code_hash,
+            sent_code: SentCode { raw: sent_code },
+        })
+    }
+
+    /// Send the login code again, using the next delivery method (for example, via SMS instead
+    /// of the Telegram application).
+    ///
+    /// [`SentCode::next_type`] tells which method will be used, and [`SentCode::timeout`] how
+    /// long to wait before calling this method. The returned token must be used to sign in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// # const PHONE: &str = "";
+    /// let token = client.request_login_code(PHONE).await?;
+    /// if let Some(timeout) = token.sent_code().timeout() {
+    ///     println!("You can request the code again in {}s", timeout.as_secs());
+    /// }
+    ///
+    /// // ...after waiting for the timeout...
+    /// let token = client.resend_code(&token).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn resend_code(&self, token: &LoginToken) -> Result<LoginToken, InvocationError> {
+        use tl::enums::auth::SentCode as SC;
+
+        let request = tl::functions::auth::ResendCode {
+            phone_number: token.phone.clone(),
+            phone_code_hash: token.phone_code_hash().to_string(),
+            reason: None,
+        };
+        let sent_code = match self.invoke(&request).await? {
+            SC::Code(code) => code,
+            SC::Success(_) => panic!("should not have logged in yet"),
+            SC::PaymentRequired(_) => return Err(local_error("PAYMENT_REQUIRED")),
+        };
+
+        Ok(LoginToken {
+            phone: token.phone.clone(),
+            sent_code: SentCode { raw: sent_code },
+        })
+    }
+
+    /// Cancel the login code, so that it can no longer be used to sign in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// # const PHONE: &str = "";
+    /// let token = client.request_login_code(PHONE).await?;
+    /// // The user changed their mind.
+    /// client.cancel_code(token).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn cancel_code(&self, token: LoginToken) -> Result<(), InvocationError> {
+        self.invoke(&tl::functions::auth::CancelCode {
+            phone_number: token.phone,
+            phone_code_hash: token.sent_code.raw.phone_code_hash,
         })
+        .await
+        .map(drop)
     }
 
     /// Signs in to the user account.
@@ -294,7 +341,7 @@ impl Client {
         match self
             .invoke(&tl::functions::auth::SignIn {
                 phone_number: token.phone.clone(),
-                phone_code_hash: token.phone_code_hash.clone(),
+                phone_code_hash: token.phone_code_hash().to_string(),
                 phone_code: Some(code.to_string()),
                 email_verification: None,
             })
@@ -320,9 +367,83 @@ impl Client {
         }
     }
 
+    /// Signs up a new user account, after [`Client::sign_in`] failed with
+    /// [`SignInError::SignUpRequired`] because the phone number is not registered yet.
+    ///
+    /// The same login token and the user's chosen name are used to create the account. If terms
+    /// of service were returned along the error, they should be shown to the user, and accepted
+    /// with [`Client::accept_terms_of_service`] once signed up.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use grammers_client::SignInError;
+    ///
+    ///  async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// # const PHONE: &str = "";
+    /// # fn ask_code_to_user() -> String { unimplemented!() }
+    /// let token = client.request_login_code(PHONE).await?;
+    /// let code = ask_code_to_user();
+    ///
+    /// let user = match client.sign_in(&token, &code).await {
+    ///     Ok(user) => user,
+    ///     Err(SignInError::SignUpRequired { terms_of_service }) => {
+    ///         let user = client.sign_up(&token, "Ferris", "").await?;
+    ///         if let Some(tos) = terms_of_service {
+    ///             println!("{}", tos.text());
+    ///             client.accept_terms_of_service(&tos).await?;
+    ///         }
+    ///         user
+    ///     }
+    ///     Err(err) => return Err(err.into()),
+    /// };
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn sign_up(
+        &self,
+        token: &LoginToken,
+        first_name: &str,
+        last_name: &str,
+    ) -> Result<User, SignInError> {
+        match self
+            .invoke(&tl::functions::auth::SignUp {
+                no_joined_notifications: false,
+                phone_number: token.phone.clone(),
+                phone_code_hash: token.phone_code_hash().to_string(),
+                first_name: first_name.to_string(),
+                last_name: last_name.to_string(),
+            })
+            .await
+        {
+            Ok(tl::enums::auth::Authorization::Authorization(x)) => {
+                self.complete_login(x).await.map_err(SignInError::Other)
+            }
+            Ok(tl::enums::auth::Authorization::SignUpRequired(_)) => {
+                panic!("API returned SignUpRequired even though we're signing up");
+            }
+            Err(err) if err.is("PHONE_CODE_*") => Err(SignInError::InvalidCode),
+            Err(error) => Err(SignInError::Other(error)),
+        }
+    }
+
+    /// Accepts the terms of service returned in [`SignInError::SignUpRequired`].
+    ///
+    /// This must be done after signing up with [`Client::sign_up`].
+    pub async fn accept_terms_of_service(
+        &self,
+        terms_of_service: &TermsOfService,
+    ) -> Result<(), InvocationError> {
+        self.invoke(&tl::functions::help::AcceptTermsOfService {
+            id: terms_of_service.raw.id.clone(),
+        })
+        .await
+        .map(drop)
+    }
+
     /// Extract information needed for the two-factor authentication
     /// It's called automatically when we get SESSION_PASSWORD_NEEDED error during sign in.
-    async fn get_password_information(&self) -> Result<PasswordToken, InvocationError> {
+    pub(crate) async fn get_password_information(&self) -> Result<PasswordToken, InvocationError> {
         let request = tl::functions::account::GetPassword {};
 
         let password: tl::types::account::Password = self.invoke(&request).await?.into();
@@ -330,6 +451,51 @@ impl Client {
         Ok(PasswordToken::new(password))
     }
 
+    /// Compute the SRP check proving knowledge of the two-factor authentication password, as
+    /// required by sign in and other sensitive requests.
+    ///
+    /// Fails with `PASSWORD_MISSING` if the account has no password set.
+    pub(crate) async fn password_check(
+        &self,
+        mut password_info: tl::types::account::Password,
+        password: impl AsRef<[u8]>,
+    ) -> Result<tl::enums::InputCheckPasswordSrp, InvocationError> {
+        let Some(current_algo) = password_info.current_algo.as_ref() else {
+            return Err(InvocationError::Rpc(RpcError {
+                code: 400,
+                name: "PASSWORD_MISSING".to_string(),
+                value: None,
+                caused_by: None,
+            }));
+        };
+        let params = utils::extract_password_parameters(current_algo);
+
+        // Telegram sent us incorrect parameters, trying to get them again
+        if !check_p_and_g(params.2, params.3) {
+            password_info = self.get_password_information().await?.password;
+            let params =
+                utils::extract_password_parameters(password_info.current_algo.as_ref().unwrap());
+            if !check_p_and_g(params.2, params.3) {
+                panic!("Failed to get correct password information from Telegram")
+            }
+        }
+
+        let (salt1, salt2, p, g) =
+            utils::extract_password_parameters(password_info.current_algo.as_ref().unwrap());
+
+        let g_b = password_info.srp_b.clone().unwrap();
+        let a = password_info.secure_random.clone();
+
+        let (m1, g_a) = calculate_2fa(salt1, salt2, p, g, g_b, a, password);
+
+        Ok(tl::types::InputCheckPasswordSrp {
+            srp_id: password_info.srp_id.unwrap(),
+            a: g_a.to_vec(),
+            m1: m1.to_vec(),
+        }
+        .into())
+    }
+
     /// Sign in using two-factor authentication (user password).
     ///
     /// [`PasswordToken`] can be obtained from [`SignInError::PasswordRequired`] error after the
@@ -373,38 +539,11 @@ impl Client {
         password_token: PasswordToken,
         password: impl AsRef<[u8]>,
     ) -> Result<User, SignInError> {
-        let mut password_info = password_token.password;
-        let current_algo = password_info.current_algo.unwrap();
-        let mut params = utils::extract_password_parameters(&current_algo);
-
-        // Telegram sent us incorrect parameters, trying to get them again
-        if !check_p_and_g(params.2, params.3) {
-            password_info = self
-                .get_password_information()
-                .await
-                .map_err(SignInError::Other)?
-                .password;
-            params =
-                utils::extract_password_parameters(password_info.current_algo.as_ref().unwrap());
-            if !check_p_and_g(params.2, params.3) {
-                panic!("Failed to get correct password information from Telegram")
-            }
-        }
-
-        let (salt1, salt2, p, g) = params;
-
-        let g_b = password_info.srp_b.unwrap();
-        let a: Vec<u8> = password_info.secure_random;
-
-        let (m1, g_a) = calculate_2fa(salt1, salt2, p, g, g_b, a, password);
-
-        let check_password = tl::functions::auth::CheckPassword {
-            password: tl::enums::InputCheckPasswordSrp::Srp(tl::types::InputCheckPasswordSrp {
-                srp_id: password_info.srp_id.unwrap(),
-                a: g_a.to_vec(),
-                m1: m1.to_vec(),
-            }),
-        };
+        let password = self
+            .password_check(password_token.password, password)
+            .await
+            .map_err(SignInError::Other)?;
+        let check_password = tl::functions::auth::CheckPassword { password };
 
         match self.invoke(&check_password).await {
             Ok(tl::enums::auth::Authorization::Authorization(x)) => {
@@ -420,7 +559,13 @@ impl Client {
     ///
     /// If the client was not logged in, this method returns false.
     ///
-    /// The client is not disconnected after signing out.
+    /// On success, the authorization is removed from the session, so the session (and any file
+    /// it was saved to) can no longer be used to act on behalf of the account. If Telegram
+    /// provides a token to log in again faster next time, it is kept in the session and used by
+    /// [`Client::request_login_code`].
+    ///
+    /// The client is not disconnected after signing out. Use [`Client::sign_out_disconnect`] to
+    /// also close the connection.
     ///
     /// Note that after using this method you will have to sign in again. If all you want to do
     /// is disconnect, simply [`drop`] the [`Client`] instance.
@@ -438,7 +583,19 @@ impl Client {
     /// # }
     /// ```
     pub async fn sign_out(&self) -> Result<tl::enums::auth::LoggedOut, InvocationError> {
-        self.invoke(&tl::functions::auth::LogOut {}).await
+        let result = self.invoke(&tl::functions::auth::LogOut {}).await;
+        self.0.state.write().unwrap().me = None;
+
+        if let Ok(tl::enums::auth::LoggedOut::Out(logged_out)) = &result {
+            let session = &self.0.config.session;
+            session.clear_authorization();
+            if logged_out.future_auth_token.is_some() {
+                session.set_future_auth_token(logged_out.future_auth_token.clone());
+            }
+            save_session(&self.0.config).await;
+        }
+
+        result
     }
 
     /// Synchronize all state to the session file and provide mutable access to it.
@@ -451,11 +608,88 @@ impl Client {
         &self.0.config.session
     }
 
+    /// Synchronize all state to the session and save it to the
+    /// [`InitParams::session_store`](crate::InitParams::session_store).
+    ///
+    /// The session is already saved whenever something important changes, but saving it before
+    /// the client is dropped ensures the update state and the cached chats are as recent as
+    /// possible.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.save_session().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn save_session(&self) -> std::io::Result<()> {
+        self.sync_update_state();
+        match &self.0.config.params.session_store {
+            Some(store) => self.0.config.session.save_to(store.as_ref()).await,
+            None => Ok(()),
+        }
+    }
+
     /// Calls [`Client::sign_out`] and disconnects.
     ///
-    /// The client will be disconnected even if signing out fails.
+    /// The client will be disconnected even if signing out fails. Any task waiting for updates
+    /// or running [`Client::run_until_disconnected`] stops, and requests made afterwards fail
+    /// with [`InvocationError::Dropped`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.sign_out_disconnect().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
     pub async fn sign_out_disconnect(&self) -> Result<(), InvocationError> {
-        let _res = self.invoke(&tl::functions::auth::LogOut {}).await;
-        panic!("disconnect now only works via dropping");
+        let result = self.sign_out().await;
+        self.disconnect().await;
+        result.map(drop)
+    }
+}
+
+/// Check the format of a bot token, `<bot id>:<secret>`, so that malformed tokens fail without
+/// a request.
+fn is_valid_bot_token(token: &str) -> bool {
+    match token.split_once(':') {
+        Some((id, secret)) => {
+            !id.is_empty()
+                && id.chars().all(|c| c.is_ascii_digit())
+                && !secret.is_empty()
+                && secret
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        }
+        None => false,
+    }
+}
+
+fn bot_sign_in_error(error: InvocationError) -> SignInError {
+    if error.is("ACCESS_TOKEN_INVALID") || error.is("ACCESS_TOKEN_EXPIRED") {
+        SignInError::InvalidToken
+    } else {
+        SignInError::Other(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_bot_token_format() {
+        assert!(is_valid_bot_token(
+            "776609994:AAFXAy5-PawQlnYywUlZ_b_GOXgarR3ah_yq"
+        ));
+        assert!(!is_valid_bot_token(""));
+        assert!(!is_valid_bot_token("776609994"));
+        assert!(!is_valid_bot_token("776609994:"));
+        assert!(!is_valid_bot_token(":AAFXAy5"));
+        assert!(!is_valid_bot_token("bot776609994:AAFXAy5"));
+        assert!(!is_valid_bot_token("776609994:AAF XAy5"));
     }
 }
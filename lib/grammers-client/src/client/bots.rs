@@ -41,6 +41,7 @@ impl InlineResult {
                 schedule_date: None,
                 send_as: None,
                 quick_reply_shortcut: None,
+                allow_paid_stars: None,
             })
             .await
             .map(drop)
@@ -171,6 +172,7 @@ impl Client {
                     no_webpage: !message.link_preview,
                     reply_markup: message.reply_markup,
                     invert_media: message.invert_media,
+                    rich_message: None,
                 },
                 dc_id,
             )
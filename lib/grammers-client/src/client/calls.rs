@@ -0,0 +1,79 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Methods related to phone calls.
+use super::Client;
+use crate::types::PhoneCall;
+pub use grammers_mtsender::InvocationError;
+use grammers_tl_types as tl;
+
+/// Method implementations related to phone calls.
+///
+/// Only the signaling is supported, which is enough to log or discard calls, but not to take
+/// part in them.
+impl Client {
+    /// Fetch the configuration for phone calls, as a JSON-encoded string.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = client.get_call_config().await?;
+    /// println!("Call config: {config}");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_call_config(&self) -> Result<String, InvocationError> {
+        let tl::enums::DataJson::Json(json) =
+            self.invoke(&tl::functions::phone::GetCallConfig {}).await?;
+        Ok(json.data)
+    }
+
+    /// Discard a phone call, such as declining it while it's ringing, or hanging it up.
+    ///
+    /// Calls which have already been discarded are left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::types::{CallState, Chat};
+    /// use grammers_client::Update;
+    /// use grammers_tl_types as tl;
+    ///
+    /// // Decline incoming calls from anyone who is not a contact.
+    /// if let Update::PhoneCall(call) = client.next_update().await? {
+    ///     let from_contact = matches!(call.caller(), Some(Chat::User(u)) if u.contact());
+    ///     if call.state() == CallState::Requested && !from_contact {
+    ///         client.discard_call(&call, tl::enums::PhoneCallDiscardReason::Busy).await?;
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn discard_call(
+        &self,
+        call: &PhoneCall,
+        reason: tl::enums::PhoneCallDiscardReason,
+    ) -> Result<(), InvocationError> {
+        let peer = match call.to_input() {
+            Some(peer) => peer,
+            None => return Ok(()),
+        };
+
+        self.invoke(&tl::functions::phone::DiscardCall {
+            video: call.is_video(),
+            peer,
+            duration: 0,
+            reason,
+            connection_id: 0,
+        })
+        .await?;
+        Ok(())
+    }
+}
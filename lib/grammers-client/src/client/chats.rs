@@ -8,23 +8,454 @@
 
 //! Methods related to users, groups and channels.
 
+use super::account::{check_username, UsernameError};
 use super::Client;
 use crate::types::{
-    chats::AdminRightsBuilderInner, chats::BannedRightsBuilderInner, AdminRightsBuilder,
-    BannedRightsBuilder, Chat, ChatMap, IterBuffer, Message, Participant, Photo, User,
+    chats::AdminRightsBuilderInner, chats::BannedRightsBuilderInner, full_chat::RawFullChat,
+    AdminLogEvent, AdminRightsBuilder, AllowedReactions, BannedRightsBuilder, Channel, Chat,
+    ChatMap, FullChat, Group, InvitePreview, IterBuffer, Message, Participant, Photo, Role,
+    Uploaded, User,
 };
-use grammers_mtsender::RpcError;
 pub use grammers_mtsender::{AuthorizationError, InvocationError};
+use grammers_mtsender::{ReadError, RpcError};
 use grammers_session::{PackedChat, PackedType};
 use grammers_tl_types as tl;
-use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::future::Future;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 const MAX_PARTICIPANT_LIMIT: usize = 200;
 const MAX_PHOTO_LIMIT: usize = 100;
+const MAX_ADMIN_LOG_LIMIT: usize = 100;
+const MAX_COMMON_CHAT_LIMIT: usize = 100;
 const KICK_BAN_DURATION: i32 = 60; // in seconds, in case the second request fails
+const MAX_TITLE_LENGTH: usize = 128;
+const MAX_ABOUT_LENGTH: usize = 255;
+const SLOW_MODE_SECONDS: [u64; 7] = [0, 10, 30, 60, 300, 900, 3600];
+const JOIN_LINK_HOSTS: [&str; 5] = [
+    "t.me",
+    "telegram.me",
+    "telegram.dog",
+    "tg.dev",
+    "telesco.pe",
+];
+
+/// The error type which is returned when kicking, banning or unbanning a participant fails.
+#[derive(Debug)]
+pub enum ModerationError {
+    /// The participant is an administrator who cannot be restricted by the logged-in account.
+    UserIsAdmin,
+    /// The logged-in account is not an administrator with the rights needed to do this.
+    AdminRequired,
+    Other(InvocationError),
+}
+
+impl fmt::Display for ModerationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UserIsAdmin => write!(f, "moderation error: user is an admin"),
+            Self::AdminRequired => write!(f, "moderation error: admin rights required"),
+            Self::Other(e) => write!(f, "moderation error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ModerationError {}
+
+impl From<InvocationError> for ModerationError {
+    fn from(error: InvocationError) -> Self {
+        if error.is("USER_ADMIN_INVALID") {
+            Self::UserIsAdmin
+        } else if error.is("CHAT_ADMIN_REQUIRED") {
+            Self::AdminRequired
+        } else {
+            Self::Other(error)
+        }
+    }
+}
+
+/// The error type which is returned when resolving a username or link fails.
+#[derive(Debug)]
+pub enum ResolveUsernameError {
+    /// The username is not valid, or the link does not point to a public chat or message.
+    UsernameInvalid,
+    Other(InvocationError),
+}
+
+impl fmt::Display for ResolveUsernameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UsernameInvalid => write!(f, "resolve username error: username invalid"),
+            Self::Other(e) => write!(f, "resolve username error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ResolveUsernameError {}
+
+impl From<InvocationError> for ResolveUsernameError {
+    fn from(error: InvocationError) -> Self {
+        if error.is("USERNAME_INVALID") {
+            Self::UsernameInvalid
+        } else {
+            Self::Other(error)
+        }
+    }
+}
+
+/// The error type which is returned when transferring the ownership of a chat fails.
+#[derive(Debug)]
+pub enum TransferOwnershipError {
+    /// The account has no two-factor authentication password set, which is required.
+    PasswordMissing,
+    /// The two-factor authentication password was set too recently, and the given time must
+    /// pass before ownership can be transferred.
+    PasswordTooFresh(Duration),
+    /// The session was logged in too recently, and the given time must pass before ownership
+    /// can be transferred.
+    SessionTooFresh(Duration),
+    /// The two-factor authentication password is incorrect.
+    InvalidPassword,
+    Other(InvocationError),
+}
+
+impl fmt::Display for TransferOwnershipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PasswordMissing => write!(f, "transfer ownership error: password missing"),
+            Self::PasswordTooFresh(wait) => write!(
+                f,
+                "transfer ownership error: password too fresh, retry in {} seconds",
+                wait.as_secs()
+            ),
+            Self::SessionTooFresh(wait) => write!(
+                f,
+                "transfer ownership error: session too fresh, retry in {} seconds",
+                wait.as_secs()
+            ),
+            Self::InvalidPassword => write!(f, "transfer ownership error: invalid password"),
+            Self::Other(e) => write!(f, "transfer ownership error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for TransferOwnershipError {}
+
+impl From<InvocationError> for TransferOwnershipError {
+    fn from(error: InvocationError) -> Self {
+        let wait = |rpc: &RpcError| Duration::from_secs(rpc.value.unwrap_or(0) as u64);
+        match error {
+            InvocationError::Rpc(rpc) if rpc.is("PASSWORD_MISSING") => Self::PasswordMissing,
+            InvocationError::Rpc(rpc) if rpc.is("PASSWORD_TOO_FRESH") => {
+                Self::PasswordTooFresh(wait(&rpc))
+            }
+            InvocationError::Rpc(rpc) if rpc.is("SESSION_TOO_FRESH") => {
+                Self::SessionTooFresh(wait(&rpc))
+            }
+            InvocationError::Rpc(rpc) if rpc.is("PASSWORD_HASH_INVALID") => Self::InvalidPassword,
+            error => Self::Other(error),
+        }
+    }
+}
+
+/// Optional parameters used when creating a channel.
+///
+/// See [`Client::create_channel_with_params`].
+#[derive(Clone, Debug, Default)]
+pub struct CreateChannelParams {
+    /// Whether the channel is meant to import messages from a foreign chat service.
+    pub for_import: bool,
+    /// Whether the supergroup should be a forum, with its messages organized into topics.
+    pub forum: bool,
+    /// The location the channel is bound to, making it a location-based group.
+    pub geo_point: Option<tl::enums::InputGeoPoint>,
+    /// The address of the location the channel is bound to.
+    pub address: Option<String>,
+}
+
+/// The file to use as a chat photo, as accepted by [`Client::set_chat_photo`] and
+/// [`Client::set_chat_video`].
+///
+/// Files given by path are uploaded before being set, while already-[`Uploaded`] files can be
+/// reused as many times as needed.
+#[derive(Clone, Debug)]
+pub enum ChatPhotoFile {
+    /// A file on the local filesystem, which still needs to be uploaded.
+    Path(PathBuf),
+    /// A file which has already been uploaded with [`Client::upload_file`] or similar.
+    Uploaded(Uploaded),
+}
+
+impl From<Uploaded> for ChatPhotoFile {
+    fn from(uploaded: Uploaded) -> Self {
+        Self::Uploaded(uploaded)
+    }
+}
+
+impl From<PathBuf> for ChatPhotoFile {
+    fn from(path: PathBuf) -> Self {
+        Self::Path(path)
+    }
+}
+
+impl From<&Path> for ChatPhotoFile {
+    fn from(path: &Path) -> Self {
+        Self::Path(path.to_path_buf())
+    }
+}
+
+impl From<&str> for ChatPhotoFile {
+    fn from(path: &str) -> Self {
+        Self::Path(path.into())
+    }
+}
+
+impl From<String> for ChatPhotoFile {
+    fn from(path: String) -> Self {
+        Self::Path(path.into())
+    }
+}
+
+/// A group or channel to join with [`Client::join_chat`].
+///
+/// Strings are parsed into either a username or an invite hash. Links to `t.me` (and its
+/// aliases) are understood, as well as bare usernames with or without the leading `@`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JoinTarget {
+    /// A chat which is already known.
+    Chat(PackedChat),
+    /// The username of a public group or channel, without the leading `@`.
+    Username(String),
+    /// The hash of a private invite link, such as the `AbCdEf` in `https://t.me/+AbCdEf`.
+    InviteHash(String),
+}
+
+/// The outcome of [`Client::join_chat`].
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum JoinChatResult {
+    /// The chat was joined, or the logged-in account was already a member of it.
+    Joined(Chat),
+    /// The chat requires new members to be approved, and a request to join was sent to its
+    /// administrators.
+    Requested,
+}
+
+/// The chat or message a link resolved to, as returned by [`Client::resolve_link`].
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum ResolvedLink {
+    /// A link to a public chat, such as `https://t.me/username`.
+    Chat(Chat),
+    /// A link to a message in a public chat, such as `https://t.me/username/123`.
+    Message { chat: Chat, message_id: i32 },
+    /// A link to a message in a private channel or supergroup, such as `https://t.me/c/123/456`.
+    ///
+    /// These links only work for members of the chat, and only contain the identifier of the
+    /// chat, so they can only be resolved if the chat is already known to the client.
+    PrivateMessage { chat: PackedChat, message_id: i32 },
+}
+
+/// A reference to a chat, by either its identifier or its username, as accepted by
+/// [`Client::resolve_peer`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PeerRef<'a> {
+    Id(i64),
+    Username(&'a str),
+}
+
+impl From<i64> for PeerRef<'_> {
+    fn from(id: i64) -> Self {
+        Self::Id(id)
+    }
+}
+
+impl<'a> From<&'a str> for PeerRef<'a> {
+    fn from(username: &'a str) -> Self {
+        Self::Username(username)
+    }
+}
+
+/// A link which can be resolved without joining anything, before reaching Telegram.
+#[derive(Clone, Debug, PartialEq)]
+enum LinkTarget {
+    Username(String),
+    Message { username: String, message_id: i32 },
+    PrivateMessage { channel_id: i64, message_id: i32 },
+}
+
+/// Strip the scheme and host of a link to Telegram, if any, as well as its query and fragment.
+fn link_path(link: &str) -> &str {
+    let link = link.trim();
+    let rest = link
+        .strip_prefix("https://")
+        .or_else(|| link.strip_prefix("http://"))
+        .unwrap_or(link);
+    let rest = rest.strip_prefix("www.").unwrap_or(rest);
+    let path = JOIN_LINK_HOSTS
+        .iter()
+        .find_map(|host| rest.strip_prefix(host)?.strip_prefix('/'))
+        .unwrap_or(rest);
+    path.split(['?', '#']).next().unwrap_or_default()
+}
+
+impl LinkTarget {
+    fn parse(link: &str) -> Option<Self> {
+        let mut segments = link_path(link).split('/').filter(|s| !s.is_empty());
+        let first = segments.next()?.trim_start_matches('@');
+        let second = segments.next();
+
+        if first == "c" {
+            let channel_id = second?.parse().ok()?;
+            let message_id = segments.next()?.parse().ok()?;
+            return Some(Self::PrivateMessage {
+                channel_id,
+                message_id,
+            });
+        }
+        if first.is_empty()
+            || first == "joinchat"
+            || !first.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return None;
+        }
+
+        match second.map(str::parse) {
+            Some(Ok(message_id)) => Some(Self::Message {
+                username: first.to_string(),
+                message_id,
+            }),
+            _ => Some(Self::Username(first.to_string())),
+        }
+    }
+}
+
+impl JoinTarget {
+    fn parse(target: &str) -> Self {
+        let mut segments = link_path(target).split('/');
+        let first = segments.next().unwrap_or_default();
+        if let Some(hash) = first.strip_prefix('+') {
+            Self::InviteHash(hash.to_string())
+        } else if first == "joinchat" {
+            Self::InviteHash(segments.next().unwrap_or_default().to_string())
+        } else {
+            Self::Username(first.trim_start_matches('@').to_string())
+        }
+    }
+}
+
+impl From<&str> for JoinTarget {
+    fn from(target: &str) -> Self {
+        Self::parse(target)
+    }
+}
+
+impl From<String> for JoinTarget {
+    fn from(target: String) -> Self {
+        Self::parse(&target)
+    }
+}
+
+impl From<PackedChat> for JoinTarget {
+    fn from(chat: PackedChat) -> Self {
+        Self::Chat(chat)
+    }
+}
+
+impl From<Chat> for JoinTarget {
+    fn from(chat: Chat) -> Self {
+        Self::Chat(chat.pack())
+    }
+}
+
+impl From<&Chat> for JoinTarget {
+    fn from(chat: &Chat) -> Self {
+        Self::Chat(chat.pack())
+    }
+}
+
+impl From<Group> for JoinTarget {
+    fn from(group: Group) -> Self {
+        Self::Chat(group.into())
+    }
+}
+
+impl From<&Group> for JoinTarget {
+    fn from(group: &Group) -> Self {
+        Self::Chat(group.into())
+    }
+}
+
+impl From<Channel> for JoinTarget {
+    fn from(channel: Channel) -> Self {
+        Self::Chat(channel.into())
+    }
+}
+
+impl From<&Channel> for JoinTarget {
+    fn from(channel: &Channel) -> Self {
+        Self::Chat(channel.into())
+    }
+}
+
+/// The characters searched for when iterating participants aggressively.
+const AGGRESSIVE_SEARCH_QUERIES: &str = "abcdefghijklmnopqrstuvwxyz0123456789";
+
+/// Which participants of a chat should be returned by a [`ParticipantIter`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParticipantFilter {
+    /// All the participants, most recent first.
+    Recent,
+    /// Only the administrators, including the creator.
+    Admins,
+    /// Only the bot accounts.
+    Bots,
+    /// Only the restricted participants whose name matches the query, which may be empty.
+    Banned(String),
+    /// Only the participants removed from the chat whose name matches the query, which may be
+    /// empty.
+    Kicked(String),
+    /// Only the participants whose name or username matches the query.
+    Search(String),
+}
+
+impl ParticipantFilter {
+    fn to_raw(&self) -> tl::enums::ChannelParticipantsFilter {
+        use tl::enums::ChannelParticipantsFilter as F;
+
+        match self {
+            Self::Recent => F::ChannelParticipantsRecent,
+            Self::Admins => F::ChannelParticipantsAdmins,
+            Self::Bots => F::ChannelParticipantsBots,
+            Self::Banned(q) => tl::types::ChannelParticipantsBanned { q: q.clone() }.into(),
+            Self::Kicked(q) => tl::types::ChannelParticipantsKicked { q: q.clone() }.into(),
+            Self::Search(q) => tl::types::ChannelParticipantsSearch { q: q.clone() }.into(),
+        }
+    }
+
+    /// Whether the participant of a small group chat should be returned.
+    ///
+    /// Small group chats have no list of banned or kicked participants.
+    fn matches(&self, participant: &Participant) -> bool {
+        match self {
+            Self::Recent => true,
+            Self::Admins => matches!(participant.role, Role::Admin(_) | Role::Creator(_)),
+            Self::Bots => participant.user.is_bot(),
+            Self::Banned(_) | Self::Kicked(_) => false,
+            Self::Search(q) => {
+                let q = q.to_lowercase();
+                participant.user.full_name().to_lowercase().contains(&q)
+                    || participant
+                        .user
+                        .username()
+                        .is_some_and(|username| username.to_lowercase().contains(&q))
+            }
+        }
+    }
+}
 
 pub enum ParticipantIter {
     Empty,
@@ -33,8 +464,15 @@ pub enum ParticipantIter {
         chat_id: i64,
         buffer: VecDeque<Participant>,
         total: Option<usize>,
+        filter: ParticipantFilter,
     },
     Channel(IterBuffer<tl::functions::channels::GetParticipants, Participant>),
+    /// Participants of a channel found by searching one query after another.
+    Aggressive {
+        iter: IterBuffer<tl::functions::channels::GetParticipants, Participant>,
+        queries: VecDeque<char>,
+        seen: HashSet<i64>,
+    },
 }
 
 impl ParticipantIter {
@@ -57,6 +495,7 @@ impl ParticipantIter {
                 chat_id,
                 buffer: VecDeque::new(),
                 total: None,
+                filter: ParticipantFilter::Recent,
             }
         } else {
             Self::Empty
@@ -66,6 +505,9 @@ impl ParticipantIter {
     /// Determines how many participants there are in total.
     ///
     /// This only performs a network call if `next` has not been called before.
+    ///
+    /// When iterating aggressively, this is the amount of participants reported by Telegram,
+    /// which may differ from the amount of participants the iterator will actually return.
     pub async fn total(&mut self) -> Result<usize, InvocationError> {
         match self {
             Self::Empty => Ok(0),
@@ -76,7 +518,7 @@ impl ParticipantIter {
                     self.fill_buffer().await
                 }
             }
-            Self::Channel(iter) => {
+            Self::Channel(iter) | Self::Aggressive { iter, .. } => {
                 if let Some(total) = iter.total {
                     Ok(total)
                 } else {
@@ -95,6 +537,7 @@ impl ParticipantIter {
                 chat_id,
                 buffer,
                 total,
+                filter,
             } => {
                 assert!(buffer.is_empty());
                 let tl::enums::messages::ChatFull::Full(full) = client
@@ -124,59 +567,76 @@ impl ParticipantIter {
 
                 // Don't actually care for the chats, just the users.
                 let mut chats = ChatMap::new(full.users, Vec::new());
+                client.cache_chats(chats.iter());
                 let chats = Arc::get_mut(&mut chats).unwrap();
 
                 buffer.extend(
                     participants
                         .into_iter()
-                        .map(|p| Participant::from_raw_chat(chats, p)),
+                        .map(|p| Participant::from_raw_chat(chats, p))
+                        .filter(|p| filter.matches(p)),
                 );
 
                 *total = Some(buffer.len());
                 Ok(buffer.len())
             }
-            Self::Channel(iter) => {
-                assert!(iter.buffer.is_empty());
-                use tl::enums::channels::ChannelParticipants::*;
-
-                iter.request.limit = iter.determine_limit(MAX_PARTICIPANT_LIMIT);
-                let (count, participants, chats, users) =
-                    match iter.client.invoke(&iter.request).await? {
-                        Participants(p) => (p.count, p.participants, p.chats, p.users),
-                        NotModified => {
-                            panic!("API returned Dialogs::NotModified even though hash = 0")
-                        }
-                    };
-
-                {
-                    let mut state = iter.client.0.state.write().unwrap();
-                    // Telegram can return peers without hash (e.g. Users with 'min: true')
-                    let _ = state.chat_hashes.extend(&users, &chats);
-                }
-
-                // Telegram can return less participants than asked for but the count being higher
-                // (for example, count=4825, participants=199, users=200). The missing participant
-                // was an admin bot account, not sure why it's not included.
-                //
-                // In any case we pick whichever size is highest to avoid weird cases like this.
-                iter.last_chunk =
-                    usize::max(participants.len(), users.len()) < iter.request.limit as usize;
-                iter.request.offset += participants.len() as i32;
+            Self::Channel(iter) => Self::fill_channel_buffer(iter, None).await,
+            Self::Aggressive { iter, seen, .. } => {
+                Self::fill_channel_buffer(iter, Some(seen)).await
+            }
+        }
+    }
 
-                // Don't actually care for the chats, just the users.
-                let mut chats = ChatMap::new(users, Vec::new());
-                let chats = Arc::get_mut(&mut chats).unwrap();
+    /// Fills the buffer of a channel iterator, and returns the total count.
+    ///
+    /// If `seen` is present, participants already seen are skipped.
+    async fn fill_channel_buffer(
+        iter: &mut IterBuffer<tl::functions::channels::GetParticipants, Participant>,
+        seen: Option<&mut HashSet<i64>>,
+    ) -> Result<usize, InvocationError> {
+        assert!(iter.buffer.is_empty());
+        use tl::enums::channels::ChannelParticipants::*;
+
+        iter.request.limit = iter.determine_limit(MAX_PARTICIPANT_LIMIT);
+        let (count, participants, chats, users) = match iter.client.invoke(&iter.request).await? {
+            Participants(p) => (p.count, p.participants, p.chats, p.users),
+            NotModified => {
+                panic!("API returned Dialogs::NotModified even though hash = 0")
+            }
+        };
 
-                iter.buffer.extend(
-                    participants
-                        .into_iter()
-                        .map(|p| Participant::from_raw_channel(chats, p)),
-                );
+        {
+            let mut state = iter.client.0.state.write().unwrap();
+            // Telegram can return peers without hash (e.g. Users with 'min: true')
+            let _ = state.chat_hashes.extend(&users, &chats);
+        }
 
-                iter.total = Some(count as usize);
-                Ok(count as usize)
-            }
+        // Telegram can return less participants than asked for but the count being higher
+        // (for example, count=4825, participants=199, users=200). The missing participant
+        // was an admin bot account, not sure why it's not included.
+        //
+        // In any case we pick whichever size is highest to avoid weird cases like this.
+        iter.last_chunk = usize::max(participants.len(), users.len()) < iter.request.limit as usize;
+        iter.request.offset += participants.len() as i32;
+
+        // Don't actually care for the chats, just the users.
+        let mut chats = ChatMap::new(users, Vec::new());
+        iter.client.cache_chats(chats.iter());
+        let chats = Arc::get_mut(&mut chats).unwrap();
+
+        let participants = participants
+            .into_iter()
+            .map(|p| Participant::from_raw_channel(chats, p));
+
+        match seen {
+            Some(seen) => iter
+                .buffer
+                .extend(participants.filter(|p| seen.insert(p.user.id()))),
+            None => iter.buffer.extend(participants),
         }
+
+        // The first count is kept, as later searches only count their own matches.
+        Ok(*iter.total.get_or_insert(count as usize))
     }
 
     /// Return the next `Participant` from the internal buffer, filling the buffer previously if
@@ -198,6 +658,25 @@ impl ParticipantIter {
                 }
                 self.fill_buffer().await?;
             }
+            Self::Aggressive { .. } => loop {
+                let Self::Aggressive { iter, queries, .. } = self else {
+                    unreachable!()
+                };
+                match iter.next_raw() {
+                    // The current query is exhausted, so move on to the next one.
+                    Some(Ok(None)) if !iter.limit_reached() && !queries.is_empty() => {
+                        let q = queries.pop_front().unwrap();
+                        iter.request.filter =
+                            tl::types::ChannelParticipantsSearch { q: q.to_string() }.into();
+                        iter.request.offset = 0;
+                        iter.last_chunk = false;
+                    }
+                    Some(result) => return result,
+                    None => {
+                        self.fill_buffer().await?;
+                    }
+                }
+            },
         }
 
         match self {
@@ -210,6 +689,7 @@ impl ParticipantIter {
                 Ok(result)
             }
             Self::Channel(iter) => Ok(iter.pop_item()),
+            Self::Aggressive { .. } => unreachable!(),
         }
     }
 
@@ -223,6 +703,50 @@ impl ParticipantIter {
             _ => self,
         }
     }
+
+    /// Only return the participants matching the filter.
+    ///
+    /// Unlike [`ParticipantIter::filter`], this also works for small group chats, where the
+    /// filter is applied locally.
+    pub fn filter_by(mut self, filter: ParticipantFilter) -> Self {
+        match &mut self {
+            Self::Empty => {}
+            Self::Chat { filter: f, .. } => *f = filter,
+            Self::Channel(iter) | Self::Aggressive { iter, .. } => {
+                iter.request.filter = filter.to_raw()
+            }
+        }
+        self
+    }
+
+    /// Work around the limit on how many participants Telegram returns when enumerating the
+    /// participants of large channels (around 10 000).
+    ///
+    /// After the usual enumeration, participants are searched by every letter and digit, and
+    /// those not seen before are returned. This requires many more requests, and participants
+    /// whose name contains none of the characters searched may still be missed.
+    ///
+    /// This has no effect on small group chats, or if a filter other than
+    /// [`ParticipantFilter::Recent`] was set.
+    pub fn aggressive(self) -> Self {
+        match self {
+            Self::Channel(mut iter)
+                if matches!(
+                    iter.request.filter,
+                    tl::enums::ChannelParticipantsFilter::ChannelParticipantsRecent
+                ) =>
+            {
+                iter.request.filter =
+                    tl::types::ChannelParticipantsSearch { q: String::new() }.into();
+                Self::Aggressive {
+                    iter,
+                    queries: AGGRESSIVE_SEARCH_QUERIES.chars().collect(),
+                    seen: HashSet::new(),
+                }
+            }
+            iter => iter,
+        }
+    }
 }
 
 pub enum ProfilePhotoIter {
@@ -293,8 +817,7 @@ impl ProfilePhotoIter {
                     iter.request.offset += photos.len() as i32;
                 }
 
-                iter.buffer
-                    .extend(photos.into_iter().map(|x| Photo::from_raw(x)));
+                iter.buffer.extend(photos.into_iter().map(Photo::from_raw));
 
                 Ok(total)
             }
@@ -336,10 +859,243 @@ impl ProfilePhotoIter {
     }
 }
 
+pub type AdminLogIter = IterBuffer<tl::functions::channels::GetAdminLog, AdminLogEvent>;
+
+impl AdminLogIter {
+    fn new(client: &Client, channel: tl::enums::InputChannel) -> Self {
+        Self::from_request(
+            client,
+            MAX_ADMIN_LOG_LIMIT,
+            tl::functions::channels::GetAdminLog {
+                channel,
+                q: String::new(),
+                events_filter: None,
+                admins: None,
+                max_id: 0,
+                min_id: 0,
+                limit: 0,
+            },
+        )
+    }
+
+    fn events_filter(&mut self) -> &mut tl::types::ChannelAdminLogEventsFilter {
+        let tl::enums::ChannelAdminLogEventsFilter::Filter(filter) =
+            self.request.events_filter.get_or_insert_with(|| {
+                tl::types::ChannelAdminLogEventsFilter {
+                    join: false,
+                    leave: false,
+                    invite: false,
+                    ban: false,
+                    unban: false,
+                    kick: false,
+                    unkick: false,
+                    promote: false,
+                    demote: false,
+                    info: false,
+                    settings: false,
+                    pinned: false,
+                    edit: false,
+                    delete: false,
+                    group_call: false,
+                    invites: false,
+                    send: false,
+                    forums: false,
+                    sub_extend: false,
+                    edit_rank: false,
+                }
+                .into()
+            });
+        filter
+    }
+
+    /// Include users joining the chat.
+    ///
+    /// Once any event type is chosen, only the chosen types are returned.
+    pub fn joins(mut self) -> Self {
+        self.events_filter().join = true;
+        self
+    }
+
+    /// Include users leaving the chat.
+    pub fn leaves(mut self) -> Self {
+        self.events_filter().leave = true;
+        self
+    }
+
+    /// Include users being banned, unbanned, kicked or restricted.
+    pub fn bans(mut self) -> Self {
+        let filter = self.events_filter();
+        filter.ban = true;
+        filter.unban = true;
+        filter.kick = true;
+        filter.unkick = true;
+        self
+    }
+
+    /// Include users being promoted or demoted.
+    pub fn promotions(mut self) -> Self {
+        let filter = self.events_filter();
+        filter.promote = true;
+        filter.demote = true;
+        self
+    }
+
+    /// Include messages being edited.
+    pub fn edits(mut self) -> Self {
+        self.events_filter().edit = true;
+        self
+    }
+
+    /// Include messages being deleted.
+    pub fn deletes(mut self) -> Self {
+        self.events_filter().delete = true;
+        self
+    }
+
+    /// Include messages being pinned or unpinned.
+    pub fn pins(mut self) -> Self {
+        self.events_filter().pinned = true;
+        self
+    }
+
+    /// Include users being invited, and invite links being edited, revoked or deleted.
+    pub fn invites(mut self) -> Self {
+        let filter = self.events_filter();
+        filter.invite = true;
+        filter.invites = true;
+        self
+    }
+
+    /// Include changes to the information of the chat, such as its title, description or photo.
+    pub fn info(mut self) -> Self {
+        self.events_filter().info = true;
+        self
+    }
+
+    /// Include changes to the settings of the chat, such as slow mode or default permissions.
+    pub fn settings(mut self) -> Self {
+        self.events_filter().settings = true;
+        self
+    }
+
+    /// Only include actions performed by the given administrators.
+    pub fn admins<C: Into<PackedChat> + Clone>(mut self, admins: &[C]) -> Self {
+        self.request.admins = Some(
+            admins
+                .iter()
+                .filter_map(|admin| admin.clone().into().try_to_input_user())
+                .collect(),
+        );
+        self
+    }
+
+    /// Only include actions matching the given text query.
+    pub fn search<Q: Into<String>>(mut self, query: Q) -> Self {
+        self.request.q = query.into();
+        self
+    }
+
+    /// Return the next `AdminLogEvent` from the internal buffer, filling the buffer previously
+    /// if it's empty.
+    ///
+    /// Returns `None` if the `limit` is reached or there are no events left.
+    pub async fn next(&mut self) -> Result<Option<AdminLogEvent>, InvocationError> {
+        if let Some(result) = self.next_raw() {
+            return result;
+        }
+
+        self.request.limit = self.determine_limit(MAX_ADMIN_LOG_LIMIT);
+        let tl::enums::channels::AdminLogResults::Results(results) =
+            self.client.invoke(&self.request).await?;
+
+        {
+            let mut state = self.client.0.state.write().unwrap();
+            // Telegram can return peers without hash (e.g. Users with 'min: true')
+            let _ = state.chat_hashes.extend(&results.users, &results.chats);
+        }
+
+        self.last_chunk = results.events.len() < self.request.limit as usize;
+
+        let chats = ChatMap::new(results.users, results.chats);
+        self.client.cache_chats(chats.iter());
+        let client = self.client.clone();
+        self.buffer.extend(
+            results
+                .events
+                .into_iter()
+                .map(|event| AdminLogEvent::from_raw(&client, event, &chats)),
+        );
+
+        // Don't bother updating offsets if this is the last time stuff has to be fetched.
+        if !self.last_chunk && !self.buffer.is_empty() {
+            self.request.max_id = self.buffer[self.buffer.len() - 1].id();
+        }
+
+        Ok(self.pop_item())
+    }
+}
+
+pub type CommonChatIter = IterBuffer<tl::functions::messages::GetCommonChats, Chat>;
+
+impl CommonChatIter {
+    fn new(client: &Client, user_id: tl::enums::InputUser) -> Self {
+        Self::from_request(
+            client,
+            MAX_COMMON_CHAT_LIMIT,
+            tl::functions::messages::GetCommonChats {
+                user_id,
+                max_id: 0,
+                limit: 0,
+            },
+        )
+    }
+
+    /// Return the next `Chat` from the internal buffer, filling the buffer previously if it's
+    /// empty.
+    ///
+    /// Returns `None` if the `limit` is reached or there are no chats left.
+    pub async fn next(&mut self) -> Result<Option<Chat>, InvocationError> {
+        if let Some(result) = self.next_raw() {
+            return result;
+        }
+
+        self.request.limit = self.determine_limit(MAX_COMMON_CHAT_LIMIT);
+        let chats = match self.client.invoke(&self.request).await? {
+            tl::enums::messages::Chats::Chats(chats) => {
+                self.last_chunk = true;
+                self.total = Some(chats.chats.len());
+                chats.chats
+            }
+            tl::enums::messages::Chats::Slice(chats) => {
+                self.last_chunk = chats.chats.len() < self.request.limit as usize;
+                self.total = Some(chats.count as usize);
+                chats.chats
+            }
+        };
+
+        {
+            let mut state = self.client.0.state.write().unwrap();
+            // Telegram can return peers without hash (e.g. Users with 'min: true')
+            let _ = state.chat_hashes.extend(&[], &chats);
+        }
+
+        self.buffer.extend(chats.into_iter().map(Chat::from_raw));
+
+        // Don't bother updating offsets if this is the last time stuff has to be fetched.
+        if !self.last_chunk && !self.buffer.is_empty() {
+            self.request.max_id = self.buffer[self.buffer.len() - 1].id();
+        }
+
+        Ok(self.pop_item())
+    }
+}
+
 /// Method implementations related to dealing with chats or other users.
 impl Client {
     /// Resolves a username into the chat that owns it, if any.
     ///
+    /// The username may be prefixed by `@`, or be a link such as `https://t.me/username`.
+    ///
     /// Note that this method is expensive to call, and can quickly cause long flood waits.
     ///
     /// # Examples
@@ -352,16 +1108,134 @@ impl Client {
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn resolve_username(&self, username: &str) -> Result<Option<Chat>, InvocationError> {
-        let tl::types::contacts::ResolvedPeer { peer, users, chats } = match self
-            .invoke(&tl::functions::contacts::ResolveUsername {
-                username: username.into(),
-            })
-            .await
-        {
-            Ok(tl::enums::contacts::ResolvedPeer::Peer(p)) => p,
-            Err(err) if err.is("USERNAME_NOT_OCCUPIED") => return Ok(None),
-            Err(err) => return Err(err),
+    pub async fn resolve_username(
+        &self,
+        username: &str,
+    ) -> Result<Option<Chat>, ResolveUsernameError> {
+        match LinkTarget::parse(username) {
+            Some(LinkTarget::Username(username)) | Some(LinkTarget::Message { username, .. }) => {
+                self.resolve_bare_username(username).await
+            }
+            _ => Err(ResolveUsernameError::UsernameInvalid),
+        }
+    }
+
+    /// Resolves a chat by its identifier or its username, looking for it in the chats cached by
+    /// the session before making any request.
+    ///
+    /// Every chat the client comes across is cached in the session, so chats seen before a
+    /// restart can still be used afterwards, as long as the session was saved. The cached chat
+    /// only contains the information needed to use it, such as its access hash.
+    ///
+    /// Usernames that are not cached are resolved with [`Client::resolve_username`]. Chats can't
+    /// be fetched by their identifier alone, so `None` is returned for unknown identifiers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// if let Some(chat) = client.resolve_peer(123456789).await? {
+    ///     client.send_message(&chat, "Hello again!").await?;
+    /// }
+    /// if let Some(chat) = client.resolve_peer("username").await? {
+    ///     println!("Found chat!: {}", chat.id());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn resolve_peer<'a, P: Into<PeerRef<'a>>>(
+        &self,
+        peer: P,
+    ) -> Result<Option<Chat>, ResolveUsernameError> {
+        let session = &self.0.config.session;
+        match peer.into() {
+            PeerRef::Id(id) => Ok(match session.cached_peer(id) {
+                Some(peer) => Some(Chat::from_cached(peer)),
+                None => {
+                    let state = self.0.state.read().unwrap();
+                    state.chat_hashes.get(id).map(Chat::unpack)
+                }
+            }),
+            PeerRef::Username(username) => {
+                let bare = username.strip_prefix('@').unwrap_or(username);
+                match session.cached_peer_by_username(bare) {
+                    Some(peer) => Ok(Some(Chat::from_cached(peer))),
+                    None => self.resolve_username(username).await,
+                }
+            }
+        }
+    }
+
+    /// Resolves a link to a public chat, or to a message in a chat.
+    ///
+    /// Links to messages in private chats, such as `https://t.me/c/123/456`, can only be
+    /// resolved if the chat is already known to the client, and `None` is returned otherwise.
+    ///
+    /// Invite links cannot be resolved with this method. Use [`Client::check_invite_link`]
+    /// instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::client::chats::ResolvedLink;
+    ///
+    /// match client.resolve_link("https://t.me/username/123").await? {
+    ///     Some(ResolvedLink::Message { chat, message_id }) => {
+    ///         println!("Message {} in {}", message_id, chat.name());
+    ///     }
+    ///     Some(_) => println!("Not a message link"),
+    ///     None => println!("Nothing found"),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn resolve_link(
+        &self,
+        link: &str,
+    ) -> Result<Option<ResolvedLink>, ResolveUsernameError> {
+        Ok(
+            match LinkTarget::parse(link).ok_or(ResolveUsernameError::UsernameInvalid)? {
+                LinkTarget::Username(username) => self
+                    .resolve_bare_username(username)
+                    .await?
+                    .map(ResolvedLink::Chat),
+                LinkTarget::Message {
+                    username,
+                    message_id,
+                } => self
+                    .resolve_bare_username(username)
+                    .await?
+                    .map(|chat| ResolvedLink::Message { chat, message_id }),
+                LinkTarget::PrivateMessage {
+                    channel_id,
+                    message_id,
+                } => {
+                    let state = self.0.state.read().unwrap();
+                    state
+                        .chat_hashes
+                        .get(channel_id)
+                        .filter(|chat| chat.is_channel())
+                        .map(|chat| ResolvedLink::PrivateMessage { chat, message_id })
+                }
+            },
+        )
+    }
+
+    async fn resolve_bare_username(
+        &self,
+        username: String,
+    ) -> Result<Option<Chat>, ResolveUsernameError> {
+        let tl::types::contacts::ResolvedPeer { peer, users, chats } = match self
+            .invoke(&tl::functions::contacts::ResolveUsername {
+                username,
+                referer: None,
+            })
+            .await
+        {
+            Ok(tl::enums::contacts::ResolvedPeer::Peer(p)) => p,
+            Err(err) if err.is("USERNAME_NOT_OCCUPIED") => return Ok(None),
+            Err(err) => return Err(err.into()),
         };
 
         {
@@ -370,7 +1244,7 @@ impl Client {
             let _ = state.chat_hashes.extend(&users, &chats);
         }
 
-        Ok(match peer {
+        let chat = match peer {
             tl::enums::Peer::User(tl::types::PeerUser { user_id }) => users
                 .into_iter()
                 .map(Chat::from_user)
@@ -382,12 +1256,33 @@ impl Client {
                 .into_iter()
                 .map(Chat::from_raw)
                 .find(|chat| chat.id() == chat_id),
-        })
+        };
+        self.cache_chats(&chat);
+        Ok(chat)
+    }
+
+    /// Remember the given chats in the session, so that [`Client::resolve_peer`] can find them
+    /// even after a restart.
+    ///
+    /// The session is not saved right away. The cache is saved along with the rest of the
+    /// session, which happens periodically while updates are being received.
+    pub(crate) fn cache_chats<'a, I: IntoIterator<Item = &'a Chat>>(&self, chats: I) {
+        self.0
+            .config
+            .session
+            .cache_peers(chats.into_iter().map(Chat::to_cached));
     }
 
     /// Fetch full information about the currently logged-in user.
     ///
-    /// Although this method is cheap to call, you might want to cache the results somewhere.
+    /// The user is cached after it is first fetched (or after signing in), so only the first
+    /// call results in a request. The cache is dropped when an update about the logged-in user
+    /// arrives, such as a change in their name, and the next call fetches the user again.
+    ///
+    /// If the session did not know who the logged-in user is, as happens with sessions imported
+    /// from other libraries, the user is also stored in the session.
+    ///
+    /// See also: [`Client::self_id`] and [`Client::is_bot`], which never make a request.
     ///
     /// # Examples
     ///
@@ -399,6 +1294,10 @@ impl Client {
     /// # }
     /// ```
     pub async fn get_me(&self) -> Result<User, InvocationError> {
+        if let Some(me) = self.0.state.read().unwrap().me.clone() {
+            return Ok(me);
+        }
+
         let mut res = self
             .invoke(&tl::functions::users::GetUsers {
                 id: vec![tl::enums::InputUser::UserSelf],
@@ -409,7 +1308,52 @@ impl Client {
             panic!("fetching only one user should exactly return one user");
         }
 
-        Ok(User::from_raw(res.pop().unwrap()))
+        let me = User::from_raw(res.pop().unwrap());
+        let mut state = self.0.state.write().unwrap();
+        // Sessions imported from other libraries may not know who the logged-in user is.
+        if self.0.config.session.get_user().is_none() {
+            self.0
+                .config
+                .session
+                .set_user(me.id(), state.dc_id, me.is_bot());
+            state.chat_hashes.set_self_user(me.pack());
+        }
+        state.me = Some(me.clone());
+        Ok(me)
+    }
+
+    /// The identifier of the logged-in user, or `None` if not signed in yet.
+    ///
+    /// This never makes a request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f(message: grammers_client::types::Message, client: grammers_client::Client) {
+    /// if message.sender().map(|s| s.id()) == client.self_id() {
+    ///     println!("That was me!");
+    /// }
+    /// # }
+    /// ```
+    pub fn self_id(&self) -> Option<i64> {
+        self.0.state.read().unwrap().chat_hashes.try_self_id()
+    }
+
+    /// Whether the logged-in account is a bot.
+    ///
+    /// This never makes a request. If not signed in yet, `false` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f(client: grammers_client::Client) {
+    /// if client.is_bot() {
+    ///     println!("Running as a bot");
+    /// }
+    /// # }
+    /// ```
+    pub fn is_bot(&self) -> bool {
+        self.0.state.read().unwrap().chat_hashes.is_self_bot()
     }
 
     /// Iterate over the participants of a chat.
@@ -418,6 +1362,10 @@ impl Client {
     ///
     /// When used to iterate the participants of "user", the iterator won't produce values.
     ///
+    /// Use [`ParticipantIter::filter_by`] to only fetch some of the participants, and
+    /// [`ParticipantIter::aggressive`] to fetch more participants than Telegram would normally
+    /// allow in large channels.
+    ///
     /// # Examples
     ///
     /// ```
@@ -460,7 +1408,7 @@ impl Client {
         &self,
         chat: C,
         user: U,
-    ) -> Result<(), InvocationError> {
+    ) -> Result<(), ModerationError> {
         let chat = chat.into();
         let user = user.into();
         if let Some(channel) = chat.try_to_input_channel() {
@@ -468,15 +1416,14 @@ impl Client {
             let self_id = { self.0.state.read().unwrap().chat_hashes.self_id() };
             if user.id == self_id {
                 self.invoke(&tl::functions::channels::LeaveChannel { channel })
-                    .await
-                    .map(drop)
+                    .await?;
             } else {
                 self.set_banned_rights(chat, user)
                     .view_messages(false)
                     .duration(Duration::from_secs(KICK_BAN_DURATION as u64))
                     .await?;
 
-                self.set_banned_rights(chat, user).await
+                self.set_banned_rights(chat, user).await?;
             }
         } else if let Some(chat_id) = chat.try_to_chat_id() {
             self.invoke(&tl::functions::messages::DeleteChatUser {
@@ -484,10 +1431,115 @@ impl Client {
                 user_id: user.to_input_user_lossy(),
                 revoke_history: false,
             })
-            .await
-            .map(drop)
-        } else {
-            Ok(())
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Bans the participant from the chat, so that they cannot join it again.
+    ///
+    /// The ban lasts for the given duration, or forever if it's `None`. To only take away some
+    /// of the rights of the participant, use [`Client::set_banned_rights`] instead.
+    ///
+    /// Small group chats have no concept of bans, so the participant is simply kicked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, user: grammers_client::types::User, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::ModerationError;
+    /// use std::time::Duration;
+    ///
+    /// // Keep them out for a day.
+    /// match client.ban_participant(&chat, &user, Some(Duration::from_secs(24 * 60 * 60))).await {
+    ///     Ok(_) => println!("see you tomorrow"),
+    ///     Err(ModerationError::UserIsAdmin) => println!("can't ban an admin!"),
+    ///     Err(e) => return Err(e.into()),
+    /// };
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn ban_participant<C: Into<PackedChat>, U: Into<PackedChat>>(
+        &self,
+        chat: C,
+        user: U,
+        duration: Option<Duration>,
+    ) -> Result<(), ModerationError> {
+        let mut rights = self.set_banned_rights(chat, user).view_messages(false);
+        if let Some(duration) = duration {
+            rights = rights.duration(duration);
+        }
+        Ok(rights.await?)
+    }
+
+    /// Lifts every restriction applied to the participant, including bans, so that they are
+    /// able to join the chat again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, user: grammers_client::types::User, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.unban_participant(&chat, &user).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn unban_participant<C: Into<PackedChat>, U: Into<PackedChat>>(
+        &self,
+        chat: C,
+        user: U,
+    ) -> Result<(), ModerationError> {
+        Ok(self.set_banned_rights(chat, user).await?)
+    }
+
+    /// Delete every message sent by a participant in a supergroup.
+    ///
+    /// The participant may be a user, or a channel which sent messages as itself. Telegram
+    /// deletes large histories in several steps, which are all performed before returning.
+    ///
+    /// Returns how many messages were deleted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, user: grammers_client::types::User, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let count = client.delete_participant_history(&chat, &user).await?;
+    /// println!("Deleted {count} spam messages");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn delete_participant_history<C: Into<PackedChat>, P: Into<PackedChat>>(
+        &self,
+        chat: C,
+        participant: P,
+    ) -> Result<usize, ModerationError> {
+        let chat = chat.into();
+        let channel = chat
+            .try_to_input_channel()
+            .ok_or_else(|| local_error("CHANNEL_INVALID"))?;
+        let participant = participant.into().to_input_peer();
+
+        let mut deleted = 0;
+        loop {
+            let tl::enums::messages::AffectedHistory::History(affected) = self
+                .invoke(&tl::functions::channels::DeleteParticipantHistory {
+                    channel: channel.clone(),
+                    participant: participant.clone(),
+                })
+                .await?;
+
+            self.process_affected_messages(
+                chat,
+                &tl::types::messages::AffectedMessages {
+                    pts: affected.pts,
+                    pts_count: affected.pts_count,
+                }
+                .into(),
+            );
+            deleted += affected.pts_count as usize;
+
+            if affected.offset == 0 {
+                break Ok(deleted);
+            }
         }
     }
 
@@ -537,6 +1589,41 @@ impl Client {
         )
     }
 
+    /// Set the default rights of every non-admin participant in a chat.
+    ///
+    /// Returns a new [`BannedRightsBuilder`] instance, just like [`Client::set_banned_rights`].
+    /// Taking away a permission here takes it away from everyone in the chat, which is useful
+    /// to temporarily lock a chat. Use [`BannedRightsBuilder::load_current`] to start from the
+    /// current defaults, which can also be read with [`Chat::default_banned_rights`].
+    ///
+    /// Awaiting the builder fails with `PEER_ID_INVALID` without making any request if the chat
+    /// is a private conversation, which has no default rights.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// // Night mode: nobody can send media until the morning.
+    /// client
+    ///     .set_default_permissions(&chat)
+    ///     .load_current()
+    ///     .await?
+    ///     .send_media(false)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_default_permissions<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+    ) -> BannedRightsBuilder<impl Future<Output = Result<(), InvocationError>>> {
+        BannedRightsBuilder::new_default(
+            self.clone(),
+            chat.into(),
+            BannedRightsBuilderInner::invoke_default,
+        )
+    }
+
     /// Set the administrator rights for a specific user.
     ///
     /// Returns a new [`AdminRightsBuilder`] instance. Check out the documentation for that
@@ -670,82 +1757,294 @@ impl Client {
         })
     }
 
-    /// Get permissions of participant `user` from chat `chat`.
+    /// Transfer the ownership of a group or channel to another user.
     ///
-    /// # Panics
-    /// Panics if chat isn't channel or chat, and if user isn't user
-    pub async fn get_permissions<C: Into<PackedChat>, U: Into<PackedChat>>(
+    /// This is a sensitive action, so the two-factor authentication password of the logged-in
+    /// account is required. Telegram also refuses to transfer ownership if the password or the
+    /// session are too recent, in which case the time left to wait is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, user: grammers_client::types::User, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::TransferOwnershipError;
+    ///
+    /// match client.transfer_ownership(&chat, &user, "hunter2").await {
+    ///     Ok(()) => println!("Done!"),
+    ///     Err(TransferOwnershipError::SessionTooFresh(wait)) => {
+    ///         println!("Try again in {} hours", wait.as_secs() / 3600)
+    ///     }
+    ///     Err(e) => return Err(e.into()),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn transfer_ownership<C: Into<PackedChat>, U: Into<PackedChat>>(
         &self,
         chat: C,
         user: U,
-    ) -> Result<ParticipantPermissions, InvocationError> {
+        password: impl AsRef<[u8]>,
+    ) -> Result<(), TransferOwnershipError> {
+        let peer = chat.into().to_input_peer();
+        let user_id = user
+            .into()
+            .try_to_input_user()
+            .ok_or_else(|| local_error("USER_ID_INVALID"))?;
+
+        let password_info = self.get_password_information().await?.password;
+        let password = self.password_check(password_info, password).await?;
+
+        self.invoke(&tl::functions::messages::EditChatCreator {
+            peer,
+            user_id,
+            password,
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Get a single participant of a group or channel.
+    ///
+    /// Returns `None` if the user is not a participant of the chat.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, user: grammers_client::types::User, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::types::Role;
+    ///
+    /// match client.get_participant(&chat, &user).await? {
+    ///     Some(participant) if matches!(participant.role, Role::Admin(_)) => println!("an admin"),
+    ///     Some(_) => println!("a participant"),
+    ///     None => println!("not a participant"),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_participant<C: Into<PackedChat>, U: Into<PackedChat>>(
+        &self,
+        chat: C,
+        user: U,
+    ) -> Result<Option<Participant>, InvocationError> {
         let chat = chat.into();
         let user = user.into();
-        if user.try_to_input_user().is_none() {
-            panic!("User parameter not user!");
-        }
 
-        // Get by chat
         if let Some(chat_id) = chat.try_to_chat_id() {
-            // Get user id
-            let user = user.try_to_input_user().unwrap();
-            let user_id = match user {
-                tl::enums::InputUser::User(user) => user.user_id,
-                tl::enums::InputUser::FromMessage(user) => user.user_id,
-                tl::enums::InputUser::UserSelf => {
-                    let me = self.get_me().await?;
-                    me.id()
-                }
-                tl::enums::InputUser::Empty => unreachable!(),
-            };
-
-            // Get chat and find user
-            let chat = self
+            let tl::enums::messages::ChatFull::Full(full) = self
                 .invoke(&tl::functions::messages::GetFullChat { chat_id })
                 .await?;
-            let tl::enums::messages::ChatFull::Full(chat) = chat;
-            if let tl::enums::ChatFull::Full(chat) = chat.full_chat {
-                if let tl::enums::ChatParticipants::Participants(participants) = chat.participants {
-                    for participant in participants.participants {
-                        if participant.user_id() == user_id {
-                            return Ok(ParticipantPermissions::Chat(participant));
-                        }
-                    }
-                }
-            }
-            return Err(InvocationError::Rpc(RpcError {
-                code: 400,
-                name: "USER_NOT_PARTICIPANT".to_string(),
-                value: None,
-                caused_by: None,
-            }));
+            let participants = match full.full_chat {
+                tl::enums::ChatFull::Full(tl::types::ChatFull {
+                    participants: tl::enums::ChatParticipants::Participants(participants),
+                    ..
+                }) => participants.participants,
+                _ => return Ok(None),
+            };
+
+            let mut chats = ChatMap::new(full.users, Vec::new());
+            self.cache_chats(chats.iter());
+            let chats = Arc::get_mut(&mut chats).unwrap();
+            return Ok(participants
+                .into_iter()
+                .find(|participant| participant.user_id() == user.id)
+                .map(|participant| Participant::from_raw_chat(chats, participant)));
         }
 
-        // Get by channel
-        let participant = self
+        let channel = chat
+            .try_to_input_channel()
+            .ok_or_else(|| local_error("CHANNEL_INVALID"))?;
+        let tl::enums::channels::ChannelParticipant::Participant(participant) = match self
             .invoke(&tl::functions::channels::GetParticipant {
-                channel: chat.try_to_input_channel().unwrap(),
+                channel,
                 participant: user.to_input_peer(),
             })
-            .await?;
-        let tl::enums::channels::ChannelParticipant::Participant(participant) = participant;
-        let permissions = ParticipantPermissions::Channel(participant.participant);
-        Ok(permissions)
-    }
+            .await
+        {
+            Ok(participant) => participant,
+            Err(err) if err.is("USER_NOT_PARTICIPANT") => return Ok(None),
+            Err(err) => return Err(err),
+        };
 
-    #[cfg(feature = "parse_invite_link")]
-    fn parse_invite_link(invite_link: &str) -> Option<String> {
-        let url_parse_result = url::Url::parse(invite_link);
-        if url_parse_result.is_err() {
-            return None;
+        {
+            let mut state = self.0.state.write().unwrap();
+            // Telegram can return peers without hash (e.g. Users with 'min: true')
+            let _ = state
+                .chat_hashes
+                .extend(&participant.users, &participant.chats);
         }
 
-        let url_parse = url_parse_result.unwrap();
-        let scheme = url_parse.scheme();
-        let path = url_parse.path();
-        if url_parse.host_str().is_none() || !vec!["https", "http"].contains(&scheme) {
-            return None;
-        }
+        let mut chats = ChatMap::new(participant.users, participant.chats);
+        self.cache_chats(chats.iter());
+        let chats = Arc::get_mut(&mut chats).unwrap();
+        Ok(Some(Participant::from_raw_channel(
+            chats,
+            participant.participant,
+        )))
+    }
+
+    /// Get the administrators of a group or channel, including its creator.
+    ///
+    /// If [`InitParams::participant_cache_ttl`] is set, the result is cached for that long, so
+    /// that calling this method often (for example, on every incoming message) does not result
+    /// in a request each time.
+    ///
+    /// [`InitParams::participant_cache_ttl`]: crate::InitParams::participant_cache_ttl
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(message: grammers_client::types::Message, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let admins = client.get_admins(&message.chat()).await?;
+    /// if admins.iter().any(|admin| Some(admin.user.id()) == message.sender().map(|s| s.id())) {
+    ///     println!("message sent by an admin");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_admins<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+    ) -> Result<Vec<Participant>, InvocationError> {
+        let chat = chat.into();
+        let ttl = self.0.config.params.participant_cache_ttl;
+        if let Some(admins) = cached(&self.0.state.read().unwrap().admin_cache, chat.id, ttl) {
+            return Ok(admins);
+        }
+
+        let mut admins = Vec::new();
+        let mut iter = self
+            .iter_participants(chat)
+            .filter_by(ParticipantFilter::Admins);
+        while let Some(participant) = iter.next().await? {
+            admins.push(participant);
+        }
+
+        if ttl.is_some() {
+            let mut state = self.0.state.write().unwrap();
+            state
+                .admin_cache
+                .insert(chat.id, (Instant::now(), admins.clone()));
+        }
+        Ok(admins)
+    }
+
+    /// Get the bots which are members of a group or channel.
+    ///
+    /// The result is cached in the same way as [`Client::get_admins`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// for bot in client.get_bots(&chat).await? {
+    ///     println!("{} is in the chat", bot.first_name());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_bots<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+    ) -> Result<Vec<User>, InvocationError> {
+        let chat = chat.into();
+        let ttl = self.0.config.params.participant_cache_ttl;
+        if let Some(bots) = cached(&self.0.state.read().unwrap().bot_cache, chat.id, ttl) {
+            return Ok(bots);
+        }
+
+        let mut bots = Vec::new();
+        let mut iter = self
+            .iter_participants(chat)
+            .filter_by(ParticipantFilter::Bots);
+        while let Some(participant) = iter.next().await? {
+            bots.push(participant.user);
+        }
+
+        if ttl.is_some() {
+            let mut state = self.0.state.write().unwrap();
+            state
+                .bot_cache
+                .insert(chat.id, (Instant::now(), bots.clone()));
+        }
+        Ok(bots)
+    }
+
+    /// Get permissions of participant `user` from chat `chat`.
+    ///
+    /// # Panics
+    /// Panics if chat isn't channel or chat, and if user isn't user
+    pub async fn get_permissions<C: Into<PackedChat>, U: Into<PackedChat>>(
+        &self,
+        chat: C,
+        user: U,
+    ) -> Result<ParticipantPermissions, InvocationError> {
+        let chat = chat.into();
+        let user = user.into();
+        if user.try_to_input_user().is_none() {
+            panic!("User parameter not user!");
+        }
+
+        // Get by chat
+        if let Some(chat_id) = chat.try_to_chat_id() {
+            // Get user id
+            let user = user.try_to_input_user().unwrap();
+            let user_id = match user {
+                tl::enums::InputUser::User(user) => user.user_id,
+                tl::enums::InputUser::FromMessage(user) => user.user_id,
+                tl::enums::InputUser::UserSelf => {
+                    let me = self.get_me().await?;
+                    me.id()
+                }
+                tl::enums::InputUser::Empty => unreachable!(),
+            };
+
+            // Get chat and find user
+            let chat = self
+                .invoke(&tl::functions::messages::GetFullChat { chat_id })
+                .await?;
+            let tl::enums::messages::ChatFull::Full(chat) = chat;
+            if let tl::enums::ChatFull::Full(chat) = chat.full_chat {
+                if let tl::enums::ChatParticipants::Participants(participants) = chat.participants {
+                    for participant in participants.participants {
+                        if participant.user_id() == user_id {
+                            return Ok(ParticipantPermissions::Chat(participant));
+                        }
+                    }
+                }
+            }
+            return Err(InvocationError::Rpc(RpcError {
+                code: 400,
+                name: "USER_NOT_PARTICIPANT".to_string(),
+                value: None,
+                caused_by: None,
+            }));
+        }
+
+        // Get by channel
+        let participant = self
+            .invoke(&tl::functions::channels::GetParticipant {
+                channel: chat.try_to_input_channel().unwrap(),
+                participant: user.to_input_peer(),
+            })
+            .await?;
+        let tl::enums::channels::ChannelParticipant::Participant(participant) = participant;
+        let permissions = ParticipantPermissions::Channel(participant.participant);
+        Ok(permissions)
+    }
+
+    #[cfg(feature = "parse_invite_link")]
+    fn parse_invite_link(invite_link: &str) -> Option<String> {
+        let url_parse_result = url::Url::parse(invite_link);
+        if url_parse_result.is_err() {
+            return None;
+        }
+
+        let url_parse = url_parse_result.unwrap();
+        let scheme = url_parse.scheme();
+        let path = url_parse.path();
+        if url_parse.host_str().is_none() || !["https", "http"].contains(&scheme) {
+            return None;
+        }
         let host = url_parse.host_str().unwrap();
         let hosts = [
             "t.me",
@@ -759,94 +2058,1028 @@ impl Client {
         if !hosts.contains(&host) {
             return None;
         }
-        let paths = path.split("/").skip(1).collect::<Vec<&str>>();
+        let paths = path.split("/").skip(1).collect::<Vec<&str>>();
+
+        if paths.len() == 1 {
+            if paths[0].starts_with("+") {
+                return Some(paths[0].replace("+", ""));
+            }
+            return None;
+        }
+
+        if paths.len() > 1 {
+            if paths[0].starts_with("joinchat") {
+                return Some(paths[1].to_string());
+            }
+            if paths[0].starts_with("+") {
+                return Some(paths[0].replace("+", ""));
+            }
+            return None;
+        }
+
+        None
+    }
+
+    /// Accept an invite link to join the corresponding private chat.
+    ///
+    /// If the chat is public (has a public username), [`Client::join_chat`](Client::join_chat) should be used instead.
+    ///
+    /// If a bot has to approve the request first, this fails with `INVITE_REQUEST_SENT`.
+    #[cfg(feature = "parse_invite_link")]
+    pub async fn accept_invite_link(
+        &self,
+        invite_link: &str,
+    ) -> Result<tl::enums::Updates, InvocationError> {
+        match Self::parse_invite_link(invite_link) {
+            Some(hash) => match self
+                .invoke(&tl::functions::messages::ImportChatInvite { hash })
+                .await?
+            {
+                tl::enums::messages::ChatInviteJoinResult::Ok(result) => Ok(result.updates),
+                // A bot has to approve the request through a web view first.
+                tl::enums::messages::ChatInviteJoinResult::WebView(_) => {
+                    Err(local_error("INVITE_REQUEST_SENT"))
+                }
+            },
+            None => Err(InvocationError::Rpc(RpcError {
+                code: 400,
+                name: "INVITE_HASH_INVALID".to_string(),
+                value: None,
+                caused_by: None,
+            })),
+        }
+    }
+
+    /// Create a new small group chat with the given title and initial members.
+    ///
+    /// The created chat is returned, along with the users who could not be added to it (for
+    /// example, due to their privacy settings). Failing to add some of the users does not cause
+    /// the whole call to fail.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(friend: grammers_client::types::User, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let (group, missing) = client.create_group("Weekend plans", &[friend.pack()]).await?;
+    /// if !missing.is_empty() {
+    ///     println!("{} people could not be invited", missing.len());
+    /// }
+    /// client.send_message(&group, "Hello everyone!").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn create_group<T: Into<String>>(
+        &self,
+        title: T,
+        users: &[PackedChat],
+    ) -> Result<(Chat, Vec<tl::types::MissingInvitee>), InvocationError> {
+        use tl::enums::Updates;
+
+        let tl::enums::messages::InvitedUsers::Users(invited) = self
+            .invoke(&tl::functions::messages::CreateChat {
+                users: users.iter().map(|u| u.to_input_user_lossy()).collect(),
+                title: title.into(),
+                ttl_period: None,
+            })
+            .await?;
+
+        let (users, chats) = match invited.updates {
+            Updates::Combined(updates) => (updates.users, updates.chats),
+            Updates::Updates(updates) => (updates.users, updates.chats),
+            _ => (Vec::new(), Vec::new()),
+        };
+
+        {
+            let mut state = self.0.state.write().unwrap();
+            // Telegram can return peers without hash (e.g. Users with 'min: true')
+            let _ = state.chat_hashes.extend(&users, &chats);
+        }
+
+        let chat = chats
+            .into_iter()
+            .find(|chat| matches!(chat, tl::enums::Chat::Chat(_)))
+            .map(Chat::from_raw)
+            .expect("API returned no chat even though messages::CreateChat succeeded");
+
+        let missing = invited
+            .missing_invitees
+            .into_iter()
+            .map(|tl::enums::MissingInvitee::Invitee(invitee)| invitee)
+            .collect();
+
+        Ok((chat, missing))
+    }
+
+    /// Upgrade a small group chat to a supergroup, returning the new supergroup.
+    ///
+    /// The returned chat can be used right away. The old group is deactivated, and messages can
+    /// no longer be sent to it. Fetching the old group again will reveal the new supergroup via
+    /// [`Group::migrated_to`].
+    ///
+    /// Only the creator of the group can migrate it.
+    ///
+    /// [`Group::migrated_to`]: crate::types::Group::migrated_to
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(group: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let supergroup = client.migrate_chat(&group).await?;
+    /// client.send_message(&supergroup, "Welcome to the supergroup!").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn migrate_chat<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+    ) -> Result<Chat, InvocationError> {
+        use tl::enums::Updates;
+
+        let chat_id = chat
+            .into()
+            .try_to_chat_id()
+            .ok_or_else(|| local_error("CHAT_ID_INVALID"))?;
+
+        let updates = self
+            .invoke(&tl::functions::messages::MigrateChat { chat_id })
+            .await?;
+
+        let (users, chats) = match updates {
+            Updates::Combined(updates) => (updates.users, updates.chats),
+            Updates::Updates(updates) => (updates.users, updates.chats),
+            _ => (Vec::new(), Vec::new()),
+        };
+
+        {
+            let mut state = self.0.state.write().unwrap();
+            // Telegram can return peers without hash (e.g. Users with 'min: true')
+            let _ = state.chat_hashes.extend(&users, &chats);
+        }
+
+        Ok(chats
+            .into_iter()
+            .find(|chat| matches!(chat, tl::enums::Chat::Channel(_)))
+            .map(Chat::from_raw)
+            .expect("API returned no channel even though messages::MigrateChat succeeded"))
+    }
+
+    /// Create a new broadcast channel, or a supergroup if `megagroup` is `true`.
+    ///
+    /// The returned chat can be used right away, without having to resolve it first.
+    ///
+    /// See also: [`Client::create_supergroup`] and [`Client::create_channel_with_params`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let channel = client.create_channel("News", "The latest news", false).await?;
+    /// client.send_message(&channel, "First post!").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn create_channel<T: Into<String>, A: Into<String>>(
+        &self,
+        title: T,
+        about: A,
+        megagroup: bool,
+    ) -> Result<Chat, InvocationError> {
+        self.create_channel_with_params(title, about, megagroup, Default::default())
+            .await
+    }
+
+    /// Create a new supergroup.
+    ///
+    /// Shorthand for [`Client::create_channel`] with `megagroup` set to `true`.
+    pub async fn create_supergroup<T: Into<String>, A: Into<String>>(
+        &self,
+        title: T,
+        about: A,
+    ) -> Result<Chat, InvocationError> {
+        self.create_channel(title, about, true).await
+    }
+
+    /// Create a new broadcast channel or supergroup, with additional parameters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::client::chats::CreateChannelParams;
+    ///
+    /// let group = client
+    ///     .create_channel_with_params(
+    ///         "Support",
+    ///         "Ask your questions here",
+    ///         true,
+    ///         CreateChannelParams {
+    ///             forum: true,
+    ///             ..Default::default()
+    ///         },
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn create_channel_with_params<T: Into<String>, A: Into<String>>(
+        &self,
+        title: T,
+        about: A,
+        megagroup: bool,
+        params: CreateChannelParams,
+    ) -> Result<Chat, InvocationError> {
+        use tl::enums::Updates;
+
+        let located = params.geo_point.is_some() || params.address.is_some();
+        let updates = self
+            .invoke(&tl::functions::channels::CreateChannel {
+                broadcast: !megagroup,
+                megagroup,
+                for_import: params.for_import,
+                forum: params.forum,
+                title: title.into(),
+                about: about.into(),
+                geo_point: located
+                    .then(|| params.geo_point.unwrap_or(tl::enums::InputGeoPoint::Empty)),
+                address: located.then(|| params.address.unwrap_or_default()),
+                ttl_period: None,
+            })
+            .await?;
+
+        let (users, chats) = match updates {
+            Updates::Combined(updates) => (updates.users, updates.chats),
+            Updates::Updates(updates) => (updates.users, updates.chats),
+            _ => (Vec::new(), Vec::new()),
+        };
+
+        {
+            let mut state = self.0.state.write().unwrap();
+            // Telegram can return peers without hash (e.g. Users with 'min: true')
+            let _ = state.chat_hashes.extend(&users, &chats);
+        }
+
+        Ok(chats
+            .into_iter()
+            .find(|chat| matches!(chat, tl::enums::Chat::Channel(_)))
+            .map(Chat::from_raw)
+            .expect("API returned no channel even though channels::CreateChannel succeeded"))
+    }
+
+    /// Join a group or channel.
+    ///
+    /// The target can be a chat that is already known, the username of a public chat (such as
+    /// `"@username"` or `"https://t.me/username"`), or a private invite link (such as
+    /// `"https://t.me/+AbCdEf"`).
+    ///
+    /// Joining a chat the logged-in account is already a member of is not considered an error.
+    /// If the chat requires administrators to approve new members, [`JoinChatResult::Requested`]
+    /// is returned instead.
+    ///
+    /// See also: [`Client::check_invite_link`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::client::chats::JoinChatResult;
+    ///
+    /// match client.join_chat("https://t.me/+AbCdEf").await? {
+    ///     JoinChatResult::Joined(chat) => println!("Joined {}", chat.name()),
+    ///     JoinChatResult::Requested => println!("Waiting for approval"),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn join_chat<T: Into<JoinTarget>>(
+        &self,
+        target: T,
+    ) -> Result<JoinChatResult, InvocationError> {
+        let chat = match target.into() {
+            JoinTarget::Chat(chat) => chat,
+            JoinTarget::Username(username) => match self.resolve_bare_username(username).await {
+                Ok(Some(chat)) => chat.pack(),
+                Ok(None) => return Err(local_error("USERNAME_NOT_OCCUPIED")),
+                Err(ResolveUsernameError::UsernameInvalid) => {
+                    return Err(local_error("USERNAME_INVALID"))
+                }
+                Err(ResolveUsernameError::Other(e)) => return Err(e),
+            },
+            JoinTarget::InviteHash(hash) => return self.import_chat_invite(hash).await,
+        };
+
+        let channel = chat
+            .try_to_input_channel()
+            .ok_or_else(|| local_error("CHANNEL_INVALID"))?;
+        match self
+            .invoke(&tl::functions::channels::JoinChannel { channel })
+            .await
+        {
+            Ok(tl::enums::messages::ChatInviteJoinResult::Ok(result)) => {
+                if let Some(joined) = self.chat_from_updates(result.updates, Some(chat.id)) {
+                    return Ok(JoinChatResult::Joined(joined));
+                }
+            }
+            // A bot has to approve the request through a web view first.
+            Ok(tl::enums::messages::ChatInviteJoinResult::WebView(_)) => {
+                return Ok(JoinChatResult::Requested)
+            }
+            Err(err) if err.is("USER_ALREADY_PARTICIPANT") => {}
+            Err(err) if err.is("INVITE_REQUEST_SENT") => return Ok(JoinChatResult::Requested),
+            Err(err) => return Err(err),
+        }
+
+        Ok(JoinChatResult::Joined(self.unpack_chat(chat).await?))
+    }
+
+    /// Preview the group or channel behind an invite link, without joining it.
+    ///
+    /// Both full links (such as `"https://t.me/+AbCdEf"`) and bare hashes are accepted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::types::InvitePreview;
+    ///
+    /// if let InvitePreview::Invite(invite) = client.check_invite_link("https://t.me/+AbCdEf").await? {
+    ///     println!("{} has {} members", invite.title(), invite.participants_count());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn check_invite_link(&self, link: &str) -> Result<InvitePreview, InvocationError> {
+        let hash = match JoinTarget::from(link) {
+            JoinTarget::InviteHash(hash) => hash,
+            _ => link.trim().to_string(),
+        };
+        let invite = self
+            .invoke(&tl::functions::messages::CheckChatInvite { hash })
+            .await?;
+        Ok(InvitePreview::from_raw(invite))
+    }
+
+    async fn import_chat_invite(&self, hash: String) -> Result<JoinChatResult, InvocationError> {
+        match self
+            .invoke(&tl::functions::messages::ImportChatInvite { hash: hash.clone() })
+            .await
+        {
+            Ok(tl::enums::messages::ChatInviteJoinResult::Ok(result)) => {
+                if let Some(joined) = self.chat_from_updates(result.updates, None) {
+                    return Ok(JoinChatResult::Joined(joined));
+                }
+            }
+            // A bot has to approve the request through a web view first.
+            Ok(tl::enums::messages::ChatInviteJoinResult::WebView(_)) => {
+                return Ok(JoinChatResult::Requested)
+            }
+            Err(err) if err.is("USER_ALREADY_PARTICIPANT") => {}
+            Err(err) if err.is("INVITE_REQUEST_SENT") => return Ok(JoinChatResult::Requested),
+            Err(err) => return Err(err),
+        }
+
+        match self
+            .invoke(&tl::functions::messages::CheckChatInvite { hash })
+            .await?
+        {
+            tl::enums::ChatInvite::Already(invite) => {
+                Ok(JoinChatResult::Joined(Chat::from_raw(invite.chat)))
+            }
+            tl::enums::ChatInvite::Peek(invite) => {
+                Ok(JoinChatResult::Joined(Chat::from_raw(invite.chat)))
+            }
+            tl::enums::ChatInvite::Invite(_) => Err(local_error("INVITE_HASH_INVALID")),
+        }
+    }
+
+    /// Find the group or channel with the given identifier (or any, if `None`) among the chats
+    /// of the updates returned after joining one, and save its hash.
+    fn chat_from_updates(&self, updates: tl::enums::Updates, id: Option<i64>) -> Option<Chat> {
+        use tl::enums::Updates;
+
+        let (users, chats) = match updates {
+            Updates::Combined(updates) => (updates.users, updates.chats),
+            Updates::Updates(updates) => (updates.users, updates.chats),
+            _ => return None,
+        };
+
+        {
+            let mut state = self.0.state.write().unwrap();
+            // Telegram can return peers without hash (e.g. Users with 'min: true')
+            let _ = state.chat_hashes.extend(&users, &chats);
+        }
+
+        chats
+            .into_iter()
+            .find(|chat| {
+                matches!(chat, tl::enums::Chat::Chat(_) | tl::enums::Chat::Channel(_))
+                    && id.is_none_or(|id| chat.id() == id)
+            })
+            .map(Chat::from_raw)
+    }
+
+    /// Enable or disable content protection in a group or channel.
+    ///
+    /// While enabled, messages in the chat cannot be forwarded or saved. This requires the
+    /// logged-in account to be an administrator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.toggle_no_forwards(&chat, true).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn toggle_no_forwards<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+        enabled: bool,
+    ) -> Result<(), InvocationError> {
+        self.invoke(&tl::functions::messages::ToggleNoForwards {
+            peer: chat.into().to_input_peer(),
+            enabled,
+            request_msg_id: None,
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Iterate over the administrator log ("recent actions") of a group or channel, from the
+    /// most recent event to the oldest.
+    ///
+    /// The logged-in account must be an administrator of the chat. Only the events of the last
+    /// 48 hours are kept by Telegram.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::types::AdminLogAction;
+    ///
+    /// let mut events = client.iter_admin_log(&chat).edits().deletes();
+    ///
+    /// while let Some(event) = events.next().await? {
+    ///     if let AdminLogAction::EditMessage { prev, new } = event.action() {
+    ///         println!("{:?} became {:?}", prev.text(), new.text());
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn iter_admin_log<C: Into<PackedChat>>(&self, chat: C) -> AdminLogIter {
+        let channel = chat
+            .into()
+            .try_to_input_channel()
+            .unwrap_or(tl::enums::InputChannel::Empty);
+        AdminLogIter::new(self, channel)
+    }
+
+    /// Iterate over the groups and channels the logged-in account has in common with a user.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(user: grammers_client::types::User, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut chats = client.iter_common_chats(&user);
+    ///
+    /// while let Some(chat) = chats.next().await? {
+    ///     println!("{} is also in {}", user.full_name(), chat.name());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn iter_common_chats<C: Into<PackedChat>>(&self, user: C) -> CommonChatIter {
+        let user_id = user
+            .into()
+            .try_to_input_user()
+            .unwrap_or(tl::enums::InputUser::Empty);
+        CommonChatIter::new(self, user_id)
+    }
+
+    /// How many groups and channels the logged-in account has in common with a user.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(user: grammers_client::types::User, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let count = client.common_chats_count(&user).await?;
+    /// println!("{} chats in common with {}", count, user.full_name());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn common_chats_count<C: Into<PackedChat>>(
+        &self,
+        user: C,
+    ) -> Result<usize, InvocationError> {
+        let id = user
+            .into()
+            .try_to_input_user()
+            .ok_or_else(|| local_error("USER_ID_INVALID"))?;
+        let tl::enums::users::UserFull::Full(full) = self
+            .invoke(&tl::functions::users::GetFullUser { id })
+            .await?;
+        let tl::enums::UserFull::Full(full_user) = full.full_user;
+        Ok(full_user.common_chats_count as usize)
+    }
+
+    /// Fetch everything known about a user, group or channel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let full = client.get_chat(&chat).await?;
+    /// if let Some(about) = full.about() {
+    ///     println!("{} is about: {}", full.chat().name(), about);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_chat<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+    ) -> Result<FullChat, InvocationError> {
+        let chat = chat.into();
+        let (raw, users, chats) = if let Some(id) = chat.try_to_input_user() {
+            let tl::enums::users::UserFull::Full(full) = self
+                .invoke(&tl::functions::users::GetFullUser { id })
+                .await?;
+            let tl::enums::UserFull::Full(raw) = full.full_user;
+            (RawFullChat::User(raw), full.users, full.chats)
+        } else {
+            let tl::enums::messages::ChatFull::Full(full) =
+                if let Some(channel) = chat.try_to_input_channel() {
+                    self.invoke(&tl::functions::channels::GetFullChannel { channel })
+                        .await?
+                } else if let Some(chat_id) = chat.try_to_chat_id() {
+                    self.invoke(&tl::functions::messages::GetFullChat { chat_id })
+                        .await?
+                } else {
+                    return Err(local_error("PEER_ID_INVALID"));
+                };
+            let raw = match full.full_chat {
+                tl::enums::ChatFull::Full(group) => RawFullChat::Group(group),
+                tl::enums::ChatFull::ChannelFull(channel) => RawFullChat::Channel(channel),
+            };
+            (raw, full.users, full.chats)
+        };
+
+        {
+            let mut state = self.0.state.write().unwrap();
+            // Telegram can return peers without hash (e.g. Users with 'min: true')
+            let _ = state.chat_hashes.extend(&users, &chats);
+        }
+
+        let chats = ChatMap::new(users, chats);
+        self.cache_chats(chats.iter());
+        let full_chat = match chats.get(&chat.to_peer()) {
+            Some(full_chat) => full_chat.clone(),
+            None => self.unpack_chat(chat).await?,
+        };
+
+        Ok(FullChat {
+            raw,
+            chat: full_chat,
+            chats,
+        })
+    }
+
+    /// Change the title of a group or channel.
+    ///
+    /// Works on small group chats, supergroups and broadcast channels alike. Setting the same
+    /// title the chat already has is not considered an error.
+    ///
+    /// The title must not be empty nor longer than 128 characters, or this method will fail with
+    /// `CHAT_TITLE_EMPTY` or `CHAT_TITLE_TOO_LONG` respectively.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.set_chat_title(&chat, "New title").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_chat_title<C: Into<PackedChat>, T: Into<String>>(
+        &self,
+        chat: C,
+        title: T,
+    ) -> Result<(), InvocationError> {
+        let chat = chat.into();
+        let title = title.into();
+        let title_length = title.chars().count();
+        if title_length == 0 {
+            return Err(local_error("CHAT_TITLE_EMPTY"));
+        } else if title_length > MAX_TITLE_LENGTH {
+            return Err(local_error("CHAT_TITLE_TOO_LONG"));
+        }
 
-        if paths.len() == 1 {
-            if paths[0].starts_with("+") {
-                return Some(paths[0].replace("+", ""));
-            }
-            return None;
+        let result = if let Some(channel) = chat.try_to_input_channel() {
+            self.invoke(&tl::functions::channels::EditTitle { channel, title })
+                .await
+        } else if let Some(chat_id) = chat.try_to_chat_id() {
+            self.invoke(&tl::functions::messages::EditChatTitle { chat_id, title })
+                .await
+        } else {
+            return Err(local_error("PEER_ID_INVALID"));
+        };
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(err) if err.is("CHAT_NOT_MODIFIED") => Ok(()),
+            Err(err) => Err(err),
         }
+    }
 
-        if paths.len() > 1 {
-            if paths[0].starts_with("joinchat") {
-                return Some(paths[1].to_string());
-            }
-            if paths[0].starts_with("+") {
-                return Some(paths[0].replace("+", ""));
-            }
-            return None;
+    /// Change the description ("about" text) of a group or channel.
+    ///
+    /// Works on small group chats, supergroups and broadcast channels alike. Setting the same
+    /// description the chat already has is not considered an error. An empty description
+    /// removes it.
+    ///
+    /// The description must not be longer than 255 characters, or this method will fail with
+    /// `CHAT_ABOUT_TOO_LONG`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.set_chat_description(&chat, "A place to talk about Rust").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_chat_description<C: Into<PackedChat>, A: Into<String>>(
+        &self,
+        chat: C,
+        about: A,
+    ) -> Result<(), InvocationError> {
+        let chat = chat.into();
+        let about = about.into();
+        if about.chars().count() > MAX_ABOUT_LENGTH {
+            return Err(local_error("CHAT_ABOUT_TOO_LONG"));
+        }
+        if chat.is_user() {
+            return Err(local_error("PEER_ID_INVALID"));
         }
 
-        None
+        match self
+            .invoke(&tl::functions::messages::EditChatAbout {
+                peer: chat.to_input_peer(),
+                about,
+            })
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(err) if err.is("CHAT_NOT_MODIFIED") || err.is("CHAT_ABOUT_NOT_MODIFIED") => Ok(()),
+            Err(err) => Err(err),
+        }
     }
 
-    /// Accept an invite link to join the corresponding private chat.
+    /// Enable, change or disable slow mode in a supergroup.
     ///
-    /// If the chat is public (has a public username), [`Client::join_chat`](Client::join_chat) should be used instead.
-    #[cfg(feature = "parse_invite_link")]
-    pub async fn accept_invite_link(
+    /// While enabled, members must wait the given delay between sending messages. Only delays
+    /// of 10, 30 or 60 seconds, 5 or 15 minutes, or 1 hour are allowed, or this method will fail
+    /// with `SECONDS_INVALID`. A zero delay disables slow mode.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::time::Duration;
+    ///
+    /// client.set_slow_mode(&chat, Duration::from_secs(30)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_slow_mode<C: Into<PackedChat>>(
         &self,
-        invite_link: &str,
-    ) -> Result<tl::enums::Updates, InvocationError> {
-        match Self::parse_invite_link(invite_link) {
-            Some(hash) => {
-                self.invoke(&tl::functions::messages::ImportChatInvite { hash })
-                    .await
-            }
-            None => Err(InvocationError::Rpc(RpcError {
-                code: 400,
-                name: "INVITE_HASH_INVALID".to_string(),
-                value: None,
-                caused_by: None,
-            })),
+        chat: C,
+        delay: Duration,
+    ) -> Result<(), InvocationError> {
+        let seconds = delay.as_secs();
+        if delay.subsec_nanos() != 0 || !SLOW_MODE_SECONDS.contains(&seconds) {
+            return Err(local_error("SECONDS_INVALID"));
         }
+        let channel = chat
+            .into()
+            .try_to_input_channel()
+            .ok_or_else(|| local_error("CHANNEL_INVALID"))?;
+
+        self.invoke(&tl::functions::channels::ToggleSlowMode {
+            channel,
+            seconds: seconds as i32,
+        })
+        .await
+        .map(drop)
+        .or_else(ignore_not_modified)
     }
 
-    #[cfg_attr(
-        not(feature = "parse_invite_link"),
-        allow(rustdoc::broken_intra_doc_links)
-    )]
-    /// Join a public group or channel.
+    /// Check whether a username is available to be used by a group or channel.
+    ///
+    /// # Examples
     ///
-    /// A channel is public if it has a username.
-    /// To join private chats, [`Client::accept_invite_link`](Client::accept_invite_link) should be used instead.
-    pub async fn join_chat<C: Into<PackedChat>>(
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// if client.check_chat_username(&chat, "grammers_chat").await? {
+    ///     client.set_chat_username(&chat, Some("grammers_chat")).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn check_chat_username<C: Into<PackedChat>>(
         &self,
         chat: C,
-    ) -> Result<Option<Chat>, InvocationError> {
-        use tl::enums::Updates;
+        username: &str,
+    ) -> Result<bool, UsernameError> {
+        check_username(username)?;
+        let channel = chat
+            .into()
+            .try_to_input_channel()
+            .ok_or_else(|| local_error("CHANNEL_INVALID"))?;
+        match self
+            .invoke(&tl::functions::channels::CheckUsername {
+                channel,
+                username: username.to_string(),
+            })
+            .await
+        {
+            Ok(available) => Ok(available),
+            Err(err) if err.is("USERNAME_OCCUPIED") => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
 
-        let chat = chat.into();
-        let update_chat = match self
-            .invoke(&tl::functions::channels::JoinChannel {
-                channel: chat.try_to_input_channel().unwrap(),
+    /// Change the username of a group or channel, making it public.
+    ///
+    /// `None` removes the username, making the chat private.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.set_chat_username(&chat, None).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_chat_username<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+        username: Option<&str>,
+    ) -> Result<(), UsernameError> {
+        if let Some(username) = username {
+            check_username(username)?;
+        }
+        let channel = chat
+            .into()
+            .try_to_input_channel()
+            .ok_or_else(|| local_error("CHANNEL_INVALID"))?;
+        match self
+            .invoke(&tl::functions::channels::UpdateUsername {
+                channel,
+                username: username.unwrap_or_default().to_string(),
             })
-            .await?
+            .await
         {
-            Updates::Combined(updates) => Some(
-                updates
-                    .chats
-                    .into_iter()
-                    .filter(|x| x.id() == chat.id)
-                    .collect::<Vec<tl::enums::Chat>>(),
-            ),
-            Updates::Updates(updates) => Some(
-                updates
-                    .chats
-                    .into_iter()
-                    .filter(|x| x.id() == chat.id)
-                    .collect::<Vec<tl::enums::Chat>>(),
-            ),
-            _ => None,
-        };
+            Ok(_) => Ok(()),
+            Err(err) if err.is("USERNAME_NOT_MODIFIED") => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Change the photo of a group or channel.
+    ///
+    /// The photo may be a path to a file, which will be uploaded first, or a file which has
+    /// already been uploaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.set_chat_photo(&chat, "photo.jpg").await?;
+    ///
+    /// // Upload once, reuse many times.
+    /// let uploaded = client.upload_file("logo.png").await?;
+    /// client.set_chat_photo(&chat, uploaded.clone()).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_chat_photo<C: Into<PackedChat>, F: Into<ChatPhotoFile>>(
+        &self,
+        chat: C,
+        photo: F,
+    ) -> Result<(), InvocationError> {
+        let file = self.upload_chat_photo_file(photo.into()).await?;
+        self.edit_chat_photo(
+            chat.into(),
+            tl::types::InputChatUploadedPhoto {
+                file: Some(file),
+                video: None,
+                video_start_ts: None,
+                video_emoji_markup: None,
+            }
+            .into(),
+        )
+        .await
+    }
+
+    /// Change the photo of a group or channel to an animated one, using a short square video.
+    ///
+    /// `start` is the timestamp, in seconds, of the frame used as the static preview of the
+    /// video. If `None`, the first frame is used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.set_chat_video(&chat, "animation.mp4", Some(1.5)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_chat_video<C: Into<PackedChat>, F: Into<ChatPhotoFile>>(
+        &self,
+        chat: C,
+        video: F,
+        start: Option<f64>,
+    ) -> Result<(), InvocationError> {
+        let file = self.upload_chat_photo_file(video.into()).await?;
+        self.edit_chat_photo(
+            chat.into(),
+            tl::types::InputChatUploadedPhoto {
+                file: None,
+                video: Some(file),
+                video_start_ts: start,
+                video_emoji_markup: None,
+            }
+            .into(),
+        )
+        .await
+    }
+
+    /// Remove the current photo of a group or channel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.delete_chat_photo(&chat).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn delete_chat_photo<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+    ) -> Result<(), InvocationError> {
+        self.edit_chat_photo(chat.into(), tl::enums::InputChatPhoto::Empty)
+            .await
+    }
+
+    pub(crate) async fn upload_chat_photo_file(
+        &self,
+        file: ChatPhotoFile,
+    ) -> Result<tl::enums::InputFile, InvocationError> {
+        match file {
+            ChatPhotoFile::Path(path) => self
+                .upload_file(path)
+                .await
+                .map(|uploaded| uploaded.raw)
+                .map_err(|e| InvocationError::Read(ReadError::Io(e))),
+            ChatPhotoFile::Uploaded(uploaded) => Ok(uploaded.raw),
+        }
+    }
 
-        match update_chat {
-            Some(chats) if !chats.is_empty() => Ok(Some(Chat::from_raw(chats[0].clone()))),
-            Some(chats) if chats.is_empty() => Ok(None),
-            None => Ok(None),
-            Some(_) => Ok(None),
+    async fn edit_chat_photo(
+        &self,
+        chat: PackedChat,
+        photo: tl::enums::InputChatPhoto,
+    ) -> Result<(), InvocationError> {
+        if let Some(channel) = chat.try_to_input_channel() {
+            self.invoke(&tl::functions::channels::EditPhoto { channel, photo })
+                .await?;
+        } else if let Some(chat_id) = chat.try_to_chat_id() {
+            self.invoke(&tl::functions::messages::EditChatPhoto { chat_id, photo })
+                .await?;
+        } else {
+            return Err(local_error("PEER_ID_INVALID"));
         }
+        Ok(())
+    }
+
+    /// Enable or disable signing the posts of a broadcast channel with the name of the
+    /// administrator who published them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.toggle_signatures(&chat, true).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn toggle_signatures<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+        enabled: bool,
+    ) -> Result<(), InvocationError> {
+        let channel = chat
+            .into()
+            .try_to_input_channel()
+            .ok_or_else(|| local_error("CHANNEL_INVALID"))?;
+        self.invoke(&tl::functions::channels::ToggleSignatures {
+            signatures_enabled: enabled,
+            profiles_enabled: false,
+            channel,
+        })
+        .await
+        .map(drop)
+        .or_else(ignore_not_modified)
+    }
+
+    /// Enable or disable requiring users to join a discussion group before they can send
+    /// messages to it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.toggle_join_to_send(&chat, true).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn toggle_join_to_send<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+        enabled: bool,
+    ) -> Result<(), InvocationError> {
+        let channel = chat
+            .into()
+            .try_to_input_channel()
+            .ok_or_else(|| local_error("CHANNEL_INVALID"))?;
+        self.invoke(&tl::functions::channels::ToggleJoinToSend { channel, enabled })
+            .await
+            .map(drop)
+            .or_else(ignore_not_modified)
+    }
+
+    /// Enable or disable requiring administrators to approve users who want to join a
+    /// supergroup.
+    ///
+    /// See also: [`Client::approve_join_request`], [`Client::decline_join_request`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.toggle_join_request(&chat, true).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn toggle_join_request<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+        enabled: bool,
+    ) -> Result<(), InvocationError> {
+        let channel = chat
+            .into()
+            .try_to_input_channel()
+            .ok_or_else(|| local_error("CHANNEL_INVALID"))?;
+        self.invoke(&tl::functions::channels::ToggleJoinRequest {
+            channel,
+            enabled,
+            apply_to_invites: false,
+            guard_bot: None,
+        })
+        .await
+        .map(drop)
+        .or_else(ignore_not_modified)
+    }
+
+    /// Change which reactions members can use in a group or channel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::types::AllowedReactions;
+    ///
+    /// client.set_reactions(&chat, vec!["👍", "👎"]).await?;
+    /// client.set_reactions(&chat, AllowedReactions::None).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_reactions<C: Into<PackedChat>, R: Into<AllowedReactions>>(
+        &self,
+        chat: C,
+        allowed: R,
+    ) -> Result<(), InvocationError> {
+        self.invoke(&tl::functions::messages::SetChatAvailableReactions {
+            peer: chat.into().to_input_peer(),
+            available_reactions: allowed.into().to_raw(),
+            reactions_limit: None,
+            paid_enabled: None,
+        })
+        .await
+        .map(drop)
+        .or_else(ignore_not_modified)
     }
 
     /// Send a message action (such as typing, uploading photo, or viewing an emoji interaction)
@@ -897,6 +3130,20 @@ impl Client {
     /// # }
     /// ```
     ///
+    /// **Use the shorthands for common actions**
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.action(&chat).choose_sticker().send().await?;
+    ///
+    /// let (photo, _) = client
+    ///     .action(&chat)
+    ///     .upload_photo(0)
+    ///     .repeat(client.upload_file("photo.jpg"))
+    ///     .await;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
     /// **Cancel any actions**
     /// ```
     /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
@@ -974,3 +3221,112 @@ impl ParticipantPermissions {
         }
     }
 }
+
+/// Treat errors caused by a setting already having the desired value as success.
+fn ignore_not_modified(error: InvocationError) -> Result<(), InvocationError> {
+    if error.is("CHAT_NOT_MODIFIED") {
+        Ok(())
+    } else {
+        Err(error)
+    }
+}
+
+/// Get a copy of the cached entry for the chat, if there is one and it is recent enough.
+fn cached<T: Clone>(
+    cache: &HashMap<i64, (Instant, Vec<T>)>,
+    chat_id: i64,
+    ttl: Option<Duration>,
+) -> Option<Vec<T>> {
+    let ttl = ttl?;
+    cache
+        .get(&chat_id)
+        .filter(|(fetched, _)| fetched.elapsed() < ttl)
+        .map(|(_, items)| items.clone())
+}
+
+/// Build an error for a request that was rejected locally, without reaching Telegram.
+pub(crate) fn local_error(name: &str) -> InvocationError {
+    InvocationError::Rpc(RpcError {
+        code: 400,
+        name: name.to_string(),
+        value: None,
+        caused_by: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn username(name: &str) -> JoinTarget {
+        JoinTarget::Username(name.to_string())
+    }
+
+    fn invite(hash: &str) -> JoinTarget {
+        JoinTarget::InviteHash(hash.to_string())
+    }
+
+    #[test]
+    fn check_join_target_usernames() {
+        assert_eq!(JoinTarget::from("grammers"), username("grammers"));
+        assert_eq!(JoinTarget::from("@grammers"), username("grammers"));
+        assert_eq!(JoinTarget::from("t.me/grammers"), username("grammers"));
+        assert_eq!(
+            JoinTarget::from("https://t.me/grammers/"),
+            username("grammers")
+        );
+        assert_eq!(
+            JoinTarget::from("https://t.me/grammers/123"),
+            username("grammers")
+        );
+        assert_eq!(
+            JoinTarget::from("http://telegram.me/grammers"),
+            username("grammers")
+        );
+    }
+
+    #[test]
+    fn check_link_targets() {
+        let username = |u: &str| Some(LinkTarget::Username(u.to_string()));
+        assert_eq!(LinkTarget::parse("grammers"), username("grammers"));
+        assert_eq!(LinkTarget::parse("@grammers"), username("grammers"));
+        assert_eq!(LinkTarget::parse("t.me/grammers"), username("grammers"));
+        assert_eq!(
+            LinkTarget::parse("https://telegram.me/grammers/"),
+            username("grammers")
+        );
+        assert_eq!(
+            LinkTarget::parse("https://t.me/grammers/123?single"),
+            Some(LinkTarget::Message {
+                username: "grammers".to_string(),
+                message_id: 123
+            })
+        );
+        assert_eq!(
+            LinkTarget::parse("https://t.me/c/1234/56"),
+            Some(LinkTarget::PrivateMessage {
+                channel_id: 1234,
+                message_id: 56
+            })
+        );
+        assert_eq!(LinkTarget::parse("https://t.me/c/1234"), None);
+        assert_eq!(LinkTarget::parse("https://t.me/+AbCdEf"), None);
+        assert_eq!(LinkTarget::parse("https://t.me/joinchat/AbCdEf"), None);
+        assert_eq!(LinkTarget::parse(""), None);
+    }
+
+    #[test]
+    fn check_join_target_invites() {
+        assert_eq!(JoinTarget::from("+AbCdEf"), invite("AbCdEf"));
+        assert_eq!(JoinTarget::from("https://t.me/+AbCdEf"), invite("AbCdEf"));
+        assert_eq!(JoinTarget::from("t.me/+AbCdEf?start=1"), invite("AbCdEf"));
+        assert_eq!(
+            JoinTarget::from("https://t.me/joinchat/AbCdEf"),
+            invite("AbCdEf")
+        );
+        assert_eq!(
+            JoinTarget::from("https://www.telegram.dog/+AbCdEf"),
+            invite("AbCdEf")
+        );
+    }
+}
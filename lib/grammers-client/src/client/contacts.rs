@@ -0,0 +1,290 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Methods related to the contacts of the logged-in user.
+
+use super::chats::local_error;
+use super::Client;
+use crate::types::{Chat, ChatMap, User};
+use crate::utils;
+pub use grammers_mtsender::InvocationError;
+use grammers_session::PackedChat;
+use grammers_tl_types as tl;
+use std::collections::HashSet;
+
+/// The outcome of [`Client::import_contacts`].
+#[derive(Clone, Debug)]
+pub struct ImportedContacts {
+    /// The users that were imported, which can be used right away.
+    pub users: Vec<User>,
+    /// The phone numbers which do not belong to any Telegram user.
+    pub not_found: Vec<String>,
+    /// The phone numbers which could not be imported at this time, because too many contacts
+    /// were imported recently. They should be imported again later.
+    pub retry: Vec<String>,
+}
+
+/// Method implementations related to contacts.
+impl Client {
+    /// Get the contacts of the logged-in user.
+    ///
+    /// The contacts are cached after being fetched, and Telegram is asked to only send them again
+    /// if they changed since.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// for contact in client.get_contacts().await? {
+    ///     println!("{}", contact.full_name());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_contacts(&self) -> Result<Vec<User>, InvocationError> {
+        let hash = {
+            let state = self.0.state.read().unwrap();
+            state.contacts.as_ref().map_or(0, |contacts| {
+                let mut ids = contacts.iter().map(User::id).collect::<Vec<_>>();
+                ids.sort_unstable();
+                utils::hash_ids(ids)
+            })
+        };
+
+        match self
+            .invoke(&tl::functions::contacts::GetContacts { hash })
+            .await?
+        {
+            tl::enums::contacts::Contacts::NotModified => {
+                let state = self.0.state.read().unwrap();
+                Ok(state.contacts.clone().unwrap_or_default())
+            }
+            tl::enums::contacts::Contacts::Contacts(contacts) => {
+                let ids = contacts
+                    .contacts
+                    .iter()
+                    .map(|tl::enums::Contact::Contact(c)| c.user_id)
+                    .collect::<HashSet<_>>();
+
+                let mut state = self.0.state.write().unwrap();
+                // Telegram can return peers without hash (e.g. Users with 'min: true')
+                let _ = state.chat_hashes.extend(&contacts.users, &[]);
+
+                let users = contacts
+                    .users
+                    .into_iter()
+                    .map(User::from_raw)
+                    .filter(|user| ids.contains(&user.id()))
+                    .collect::<Vec<_>>();
+                state.contacts = Some(users.clone());
+                Ok(users)
+            }
+        }
+    }
+
+    /// Add the given phone numbers to the contacts of the logged-in user.
+    ///
+    /// Each contact is given as its phone number, first name and last name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let imported = client.import_contacts(&[("+1234567890", "John", "Doe")]).await?;
+    /// for phone in imported.not_found {
+    ///     println!("{} is not on Telegram", phone);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn import_contacts(
+        &self,
+        contacts: &[(&str, &str, &str)],
+    ) -> Result<ImportedContacts, InvocationError> {
+        let tl::enums::contacts::ImportedContacts::Contacts(imported) = self
+            .invoke(&tl::functions::contacts::ImportContacts {
+                contacts: contacts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (phone, first_name, last_name))| {
+                        tl::types::InputPhoneContact {
+                            client_id: i as i64,
+                            phone: phone.to_string(),
+                            first_name: first_name.to_string(),
+                            last_name: last_name.to_string(),
+                            note: None,
+                        }
+                        .into()
+                    })
+                    .collect(),
+            })
+            .await?;
+
+        {
+            let mut state = self.0.state.write().unwrap();
+            // Telegram can return peers without hash (e.g. Users with 'min: true')
+            let _ = state.chat_hashes.extend(&imported.users, &[]);
+            // The contacts changed, so they must be fetched again.
+            state.contacts = None;
+        }
+
+        let found = imported
+            .imported
+            .iter()
+            .map(|tl::enums::ImportedContact::Contact(c)| c.client_id)
+            .collect::<HashSet<_>>();
+        let retry = imported
+            .retry_contacts
+            .iter()
+            .copied()
+            .collect::<HashSet<_>>();
+        let phones = |keep: &dyn Fn(i64) -> bool| {
+            contacts
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| keep(*i as i64))
+                .map(|(_, (phone, _, _))| phone.to_string())
+                .collect()
+        };
+
+        Ok(ImportedContacts {
+            users: imported.users.into_iter().map(User::from_raw).collect(),
+            not_found: phones(&|i| !found.contains(&i) && !retry.contains(&i)),
+            retry: phones(&|i| retry.contains(&i)),
+        })
+    }
+
+    /// Add a user to the contacts of the logged-in user, returning the updated user.
+    ///
+    /// No phone number is needed, so users found in groups can be added too. If `share_phone`
+    /// is `true`, the user will be able to see the phone number of the logged-in user, even if
+    /// it is hidden by their privacy settings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(user: grammers_client::types::User, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let user = client.add_contact(&user, "Ferris", "", false).await?;
+    /// assert!(user.contact());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn add_contact<C: Into<PackedChat>>(
+        &self,
+        user: C,
+        first_name: &str,
+        last_name: &str,
+        share_phone: bool,
+    ) -> Result<User, InvocationError> {
+        let user = user.into();
+        let id = user
+            .try_to_input_user()
+            .ok_or_else(|| local_error("USER_ID_INVALID"))?;
+
+        let updates = self
+            .invoke(&tl::functions::contacts::AddContact {
+                add_phone_privacy_exception: share_phone,
+                id,
+                first_name: first_name.to_string(),
+                last_name: last_name.to_string(),
+                phone: String::new(),
+                note: None,
+            })
+            .await?;
+
+        let users = self.users_from_contact_updates(updates);
+        users
+            .into_iter()
+            .map(User::from_raw)
+            .find(|u| u.id() == user.id)
+            .ok_or_else(|| local_error("USER_ID_INVALID"))
+    }
+
+    /// Remove users from the contacts of the logged-in user.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(user: grammers_client::types::User, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.delete_contacts(&[user.pack()]).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn delete_contacts(&self, users: &[PackedChat]) -> Result<(), InvocationError> {
+        let updates = self
+            .invoke(&tl::functions::contacts::DeleteContacts {
+                id: users.iter().filter_map(|u| u.try_to_input_user()).collect(),
+            })
+            .await?;
+
+        self.users_from_contact_updates(updates);
+        Ok(())
+    }
+
+    /// Remember the users returned after changing the contacts, which now have updated
+    /// contact flags, and forget the cached contacts.
+    fn users_from_contact_updates(&self, updates: tl::enums::Updates) -> Vec<tl::enums::User> {
+        let (users, chats) = match updates {
+            tl::enums::Updates::Combined(updates) => (updates.users, updates.chats),
+            tl::enums::Updates::Updates(updates) => (updates.users, updates.chats),
+            _ => (Vec::new(), Vec::new()),
+        };
+
+        let mut state = self.0.state.write().unwrap();
+        // Telegram can return peers without hash (e.g. Users with 'min: true')
+        let _ = state.chat_hashes.extend(&users, &chats);
+        // The contacts changed, so they must be fetched again.
+        state.contacts = None;
+        users
+    }
+
+    /// Search for users, groups and channels by their name or username.
+    ///
+    /// The contacts and chats of the logged-in user which match are returned first, followed by
+    /// the global results.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// for chat in client.search_contacts("grammers", 10).await? {
+    ///     println!("{}", chat.name());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn search_contacts(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<Chat>, InvocationError> {
+        let tl::enums::contacts::Found::Found(found) = self
+            .invoke(&tl::functions::contacts::Search {
+                q: query.to_string(),
+                limit: limit as i32,
+                broadcasts: false,
+                bots: false,
+            })
+            .await?;
+
+        {
+            let mut state = self.0.state.write().unwrap();
+            // Telegram can return peers without hash (e.g. Users with 'min: true')
+            let _ = state.chat_hashes.extend(&found.users, &found.chats);
+        }
+
+        let chats = ChatMap::new(found.users, found.chats);
+        self.cache_chats(chats.iter());
+        Ok(found
+            .my_results
+            .iter()
+            .chain(found.results.iter())
+            .filter_map(|peer| chats.get(peer).cloned())
+            .collect())
+    }
+}
@@ -0,0 +1,386 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Methods to hold multi-step conversations in a chat.
+
+use super::{Client, SendMessageError};
+use crate::types::{CallbackQuery, InputMessage, Message};
+use crate::Update;
+use grammers_mtsender::InvocationError;
+use grammers_session::PackedChat;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+static NEXT_CONVERSATION_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A conversation registered in a chat, which receives its updates instead of
+/// [`Client::next_update`].
+pub(crate) struct ConversationSlot {
+    id: u64,
+    shared: bool,
+    sender: mpsc::UnboundedSender<Update>,
+}
+
+/// The error type which is returned by the methods of a [`Conversation`].
+#[derive(Debug)]
+pub enum ConversationError {
+    /// There is already a conversation in the chat, and at least one of them is not shared.
+    AlreadyActive,
+    /// No matching update arrived in time. The conversation is closed after this.
+    Timeout,
+    /// The conversation was closed, because waiting for an update timed out before.
+    Closed,
+    Other(InvocationError),
+}
+
+impl fmt::Display for ConversationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlreadyActive => write!(
+                f,
+                "conversation error: there is already a conversation in the chat"
+            ),
+            Self::Timeout => write!(f, "conversation error: timed out waiting for an update"),
+            Self::Closed => write!(f, "conversation error: the conversation is closed"),
+            Self::Other(e) => write!(f, "conversation error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ConversationError {}
+
+impl From<InvocationError> for ConversationError {
+    fn from(error: InvocationError) -> Self {
+        Self::Other(error)
+    }
+}
+
+/// Optional parameters used when starting a conversation.
+///
+/// See [`Client::conversation_with_params`].
+#[derive(Clone, Debug, Default)]
+pub struct ConversationParams {
+    /// Whether other shared conversations may be held in the same chat at the same time.
+    ///
+    /// Every shared conversation in a chat receives each of its updates. By default, only one
+    /// conversation is allowed per chat.
+    pub shared: bool,
+}
+
+/// A multi-step conversation in a chat, started with [`Client::conversation`].
+///
+/// While the conversation is alive, incoming messages and callback queries in its chat are
+/// delivered to it rather than being returned by [`Client::next_update`]. Updates in other chats
+/// are not affected, so conversations in different chats can be held concurrently.
+///
+/// The updates are still received through [`Client::next_update`], which must keep being
+/// called (for example, by the task running the update loop) for the conversation to receive
+/// anything.
+///
+/// The conversation ends when it is dropped, or when waiting for an update times out.
+pub struct Conversation {
+    client: Client,
+    chat: PackedChat,
+    id: u64,
+    receiver: mpsc::UnboundedReceiver<Update>,
+    // Updates which were received while waiting for a different kind.
+    pending: VecDeque<Update>,
+    closed: bool,
+}
+
+impl Conversation {
+    /// The chat where this conversation is held.
+    pub fn chat(&self) -> PackedChat {
+        self.chat
+    }
+
+    /// Send a message to the chat of this conversation.
+    pub async fn send_message<M: Into<InputMessage>>(
+        &self,
+        message: M,
+    ) -> Result<Message, SendMessageError> {
+        self.client.send_message(self.chat, message).await
+    }
+
+    /// Wait for the next incoming message in the chat, up to the given timeout.
+    ///
+    /// Outgoing messages, including those sent by the logged-in account from other devices, are
+    /// never considered a response.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::time::Duration;
+    ///
+    /// let mut conv = client.conversation(&chat)?;
+    /// conv.send_message("Send me the title").await?;
+    /// let title = conv.get_response(Duration::from_secs(60)).await?;
+    /// conv.send_message("Now send the photo").await?;
+    /// let photo = conv.get_response(Duration::from_secs(60)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_response(&mut self, timeout: Duration) -> Result<Message, ConversationError> {
+        self.next_matching(timeout, |update| match update {
+            // Messages sent by the logged-in account, even from another device, are not responses.
+            Update::NewMessage(message) if !message.outgoing() => Ok(message),
+            update => Err(Box::new(update)),
+        })
+        .await
+    }
+
+    /// Wait for the next callback query in the chat, up to the given timeout.
+    pub async fn get_callback_query(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<CallbackQuery, ConversationError> {
+        self.next_matching(timeout, |update| match update {
+            Update::CallbackQuery(query) => Ok(query),
+            update => Err(Box::new(update)),
+        })
+        .await
+    }
+
+    /// Mark the messages in the chat of this conversation as read.
+    pub async fn mark_read(&self) -> Result<(), ConversationError> {
+        self.client.mark_as_read(self.chat).await?;
+        Ok(())
+    }
+
+    async fn next_matching<T>(
+        &mut self,
+        timeout: Duration,
+        extract: fn(Update) -> Result<T, Box<Update>>,
+    ) -> Result<T, ConversationError> {
+        if self.closed {
+            return Err(ConversationError::Closed);
+        }
+
+        let mut i = 0;
+        while let Some(update) = self.pending.remove(i) {
+            match extract(update) {
+                Ok(value) => return Ok(value),
+                Err(update) => {
+                    self.pending.insert(i, *update);
+                    i += 1;
+                }
+            }
+        }
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match tokio::time::timeout(remaining, self.receiver.recv()).await {
+                Ok(Some(update)) => match extract(update) {
+                    Ok(value) => return Ok(value),
+                    Err(update) => self.pending.push_back(*update),
+                },
+                Ok(None) => {
+                    self.close();
+                    return Err(ConversationError::Closed);
+                }
+                Err(_) => {
+                    self.close();
+                    return Err(ConversationError::Timeout);
+                }
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        self.receiver.close();
+
+        let mut state = self.client.0.state.write().unwrap();
+        if let Some(slots) = state.conversations.get_mut(&self.chat.id) {
+            slots.retain(|slot| slot.id != self.id);
+            if slots.is_empty() {
+                state.conversations.remove(&self.chat.id);
+            }
+        }
+    }
+}
+
+impl Drop for Conversation {
+    fn drop(&mut self) {
+        if !self.closed {
+            self.close();
+        }
+    }
+}
+
+impl fmt::Debug for Conversation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Conversation")
+            .field("chat", &self.chat)
+            .field("closed", &self.closed)
+            .finish()
+    }
+}
+
+impl Client {
+    /// Start a conversation in the given chat, to wait for the responses to the messages sent.
+    ///
+    /// Only one conversation is allowed in a chat at a time. Use
+    /// [`Client::conversation_with_params`] to hold several.
+    ///
+    /// See [`Conversation::get_response`] for an example.
+    pub fn conversation<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+    ) -> Result<Conversation, ConversationError> {
+        self.conversation_with_params(chat, ConversationParams::default())
+    }
+
+    /// Start a conversation in the given chat, with additional parameters.
+    pub fn conversation_with_params<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+        params: ConversationParams,
+    ) -> Result<Conversation, ConversationError> {
+        let chat = chat.into();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let id = NEXT_CONVERSATION_ID.fetch_add(1, Ordering::Relaxed);
+
+        let mut state = self.0.state.write().unwrap();
+        let slots = state.conversations.entry(chat.id).or_default();
+        slots.retain(|slot| !slot.sender.is_closed());
+        let allowed = slots.is_empty() || (params.shared && slots.iter().all(|slot| slot.shared));
+        if !allowed {
+            return Err(ConversationError::AlreadyActive);
+        }
+        slots.push(ConversationSlot {
+            id,
+            shared: params.shared,
+            sender,
+        });
+        drop(state);
+
+        Ok(Conversation {
+            client: self.clone(),
+            chat,
+            id,
+            receiver,
+            pending: VecDeque::new(),
+            closed: false,
+        })
+    }
+
+    /// Deliver the update to the conversations in its chat, if any, returning it otherwise.
+    pub(crate) fn route_to_conversation(&self, update: Update) -> Option<Update> {
+        let chat_id = match &update {
+            Update::NewMessage(message) if !message.outgoing() => message.chat().id(),
+            Update::CallbackQuery(query) => query.chat().id(),
+            _ => return Some(update),
+        };
+
+        let mut state = self.0.state.write().unwrap();
+        let slots = match state.conversations.get_mut(&chat_id) {
+            Some(slots) => slots,
+            None => return Some(update),
+        };
+        slots.retain(|slot| !slot.sender.is_closed());
+        if slots.is_empty() {
+            state.conversations.remove(&chat_id);
+            return Some(update);
+        }
+
+        for slot in slots.iter() {
+            let _ = slot.sender.send(update.clone());
+        }
+        None
+    }
+}
+
+#[cfg(all(test, feature = "test-utils"))]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockSender;
+    use crate::Config;
+    use grammers_session::{PackedType, Session};
+    use std::future::Future;
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn client() -> Client {
+        block_on(Client::with_mock(
+            Config {
+                session: Session::new(),
+                api_id: 0,
+                api_hash: String::new(),
+                params: Default::default(),
+            },
+            MockSender::new(),
+        ))
+    }
+
+    fn chat(id: i64) -> PackedChat {
+        PackedChat {
+            ty: PackedType::User,
+            id,
+            access_hash: Some(0),
+        }
+    }
+
+    #[test]
+    fn one_conversation_per_chat() {
+        let client = client();
+        let conv = client.conversation(chat(1)).unwrap();
+        assert!(matches!(
+            client.conversation(chat(1)),
+            Err(ConversationError::AlreadyActive)
+        ));
+        assert!(client.conversation(chat(2)).is_ok());
+
+        drop(conv);
+        assert!(client.conversation(chat(1)).is_ok());
+    }
+
+    #[test]
+    fn shared_conversations() {
+        let client = client();
+        let shared = ConversationParams { shared: true };
+        let _first = client
+            .conversation_with_params(chat(1), shared.clone())
+            .unwrap();
+        let _second = client
+            .conversation_with_params(chat(1), shared.clone())
+            .unwrap();
+        assert!(matches!(
+            client.conversation(chat(1)),
+            Err(ConversationError::AlreadyActive)
+        ));
+    }
+
+    #[test]
+    fn timeout_closes_the_conversation() {
+        let client = client();
+        block_on(async {
+            let mut conv = client.conversation(chat(1)).unwrap();
+            assert!(matches!(
+                conv.get_response(Duration::ZERO).await,
+                Err(ConversationError::Timeout)
+            ));
+            assert!(matches!(
+                conv.get_callback_query(Duration::ZERO).await,
+                Err(ConversationError::Closed)
+            ));
+            assert!(client.conversation(chat(1)).is_ok());
+        });
+    }
+}
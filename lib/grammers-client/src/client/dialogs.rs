@@ -89,6 +89,7 @@ impl DialogIter {
         }
 
         let chats = ChatMap::new(users, chats);
+        self.client.cache_chats(chats.iter());
         let mut messages = messages
             .into_iter()
             .flat_map(|m| Message::from_raw(&self.client, m, &chats))
@@ -192,8 +193,10 @@ impl Client {
             })
             .await
             .map(drop)
+        } else if self.is_bot() {
+            // Bots cannot delete the history of private conversations.
+            Ok(())
         } else {
-            // TODO only do this if we're not a bot
             self.invoke(&tl::functions::messages::DeleteHistory {
                 just_clear: false,
                 revoke: false,
@@ -227,12 +230,58 @@ impl Client {
                 .await
                 .map(drop)
         } else {
-            self.invoke(&tl::functions::messages::ReadHistory {
-                peer: chat.to_input_peer(),
-                max_id: 0,
+            let affected = self
+                .invoke(&tl::functions::messages::ReadHistory {
+                    peer: chat.to_input_peer(),
+                    max_id: 0,
+                })
+                .await?;
+            self.process_affected_messages(chat, &affected);
+            Ok(())
+        }
+    }
+
+    /// Mark the contents of the given messages as read.
+    ///
+    /// This is what official clients do when a voice note or a round video is played, or when
+    /// self-destructing media is opened. Unlike [`Client::mark_as_read`], it does not affect the
+    /// unread count of the chat, but it will clear the [`Message::media_unread`] flag, and the
+    /// mention if the messages mentioned you.
+    ///
+    /// The new update state returned by Telegram is processed, so that no gap is detected later.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let message_ids = [123, 456];
+    /// client.read_message_contents(&chat, &message_ids).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Message::media_unread`]: crate::types::Message::media_unread
+    pub async fn read_message_contents<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+        message_ids: &[i32],
+    ) -> Result<(), InvocationError> {
+        let chat = chat.into();
+        if let Some(channel) = chat.try_to_input_channel() {
+            self.invoke(&tl::functions::channels::ReadMessageContents {
+                channel,
+                id: message_ids.to_vec(),
             })
             .await
             .map(drop)
+        } else {
+            let affected = self
+                .invoke(&tl::functions::messages::ReadMessageContents {
+                    id: message_ids.to_vec(),
+                })
+                .await?;
+            self.process_affected_messages(chat, &affected);
+            Ok(())
         }
     }
 
@@ -6,17 +6,20 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::types::{photo_sizes::PhotoSize, Downloadable, Media, Uploaded};
+use super::chats::local_error;
+use crate::types::{media::MediaOrigin, photo_sizes::PhotoSize, Chat, Downloadable, Uploaded};
 use crate::utils::generate_random_id;
 use crate::Client;
-use futures_util::stream::{FuturesUnordered, StreamExt as _};
+use futures_util::future::{BoxFuture, FutureExt as _};
+use futures_util::stream::{FuturesOrdered, FuturesUnordered, StreamExt as _};
 use grammers_mtsender::InvocationError;
 use grammers_tl_types as tl;
+use std::ops::{Bound, RangeBounds};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::{io::SeekFrom, path::Path, sync::Arc};
-use tokio::sync::mpsc::unbounded_channel;
 use tokio::{
     fs,
-    io::{self, AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+    io::{self, AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt},
     sync::Mutex as AsyncMutex,
 };
 
@@ -24,18 +27,39 @@ pub const MIN_CHUNK_SIZE: i32 = 4 * 1024;
 pub const MAX_CHUNK_SIZE: i32 = 512 * 1024;
 const FILE_MIGRATE_ERROR: i32 = 303;
 const BIG_FILE_SIZE: usize = 10 * 1024 * 1024;
+const MIN_UPLOAD_PART_SIZE: usize = 128 * 1024;
+const MAX_UPLOAD_PARTS: usize = 3000;
 const WORKER_COUNT: usize = 4;
+const MAX_DOWNLOAD_CONNECTIONS: usize = 8;
+const MAX_UPLOAD_CONNECTIONS: usize = 8;
+const UPLOAD_PART_ATTEMPTS: usize = 3;
 
 pub struct DownloadIter {
     client: Client,
     done: bool,
+    size: Option<usize>,
+    // The datacenter where the file lives, once Telegram has told which one it is.
+    dc: Option<i32>,
     request: tl::functions::upload::GetFile,
     photo_size_data: Option<Vec<u8>>,
+    connections: usize,
+    // Chunks being fetched ahead of time, in the order they must be returned.
+    pending: FuturesOrdered<BoxFuture<'static, Result<Vec<u8>, InvocationError>>>,
+    scheduled: usize,
+    progress: Option<ProgressCallback>,
+    downloaded: u64,
+    // Bytes to drop from the start of the next chunk, and to return before stopping, when only
+    // a range of the file is wanted.
+    skip_bytes: usize,
+    remaining_bytes: Option<u64>,
+    // The message where the file was found, to get a fresh file reference once if it expires.
+    origin: Option<MediaOrigin>,
+    refreshed: bool,
 }
 
 impl DownloadIter {
     fn new(client: &Client, downloadable: &Downloadable) -> Self {
-        match downloadable {
+        let download = match downloadable {
             Downloadable::PhotoSize(photo_size)
                 if !matches!(photo_size, PhotoSize::Size(_) | PhotoSize::Progressive(_)) =>
             {
@@ -44,6 +68,12 @@ impl DownloadIter {
             _ => {
                 Self::new_from_file_location(client, downloadable.to_raw_input_location().unwrap())
             }
+        };
+        Self {
+            size: downloadable.size(),
+            dc: downloadable.dc_id(),
+            origin: downloadable.origin().cloned(),
+            ..download
         }
     }
 
@@ -53,6 +83,8 @@ impl DownloadIter {
         Self {
             client: client.clone(),
             done: false,
+            size: None,
+            dc: None,
             request: tl::functions::upload::GetFile {
                 precise: false,
                 cdn_supported: false,
@@ -61,6 +93,15 @@ impl DownloadIter {
                 limit: MAX_CHUNK_SIZE,
             },
             photo_size_data: None,
+            connections: 1,
+            pending: FuturesOrdered::new(),
+            scheduled: 0,
+            progress: None,
+            downloaded: 0,
+            skip_bytes: 0,
+            remaining_bytes: None,
+            origin: None,
+            refreshed: false,
         }
     }
 
@@ -68,6 +109,8 @@ impl DownloadIter {
         Self {
             client: client.clone(),
             done: false,
+            size: None,
+            dc: None,
             // request is not needed, so fake one
             request: tl::functions::upload::GetFile {
                 precise: false,
@@ -84,32 +127,149 @@ impl DownloadIter {
                 limit: MAX_CHUNK_SIZE,
             },
             photo_size_data: Some(data),
+            connections: 1,
+            pending: FuturesOrdered::new(),
+            scheduled: 0,
+            progress: None,
+            downloaded: 0,
+            skip_bytes: 0,
+            remaining_bytes: None,
+            origin: None,
+            refreshed: false,
         }
     }
 
+    /// The total size of the file in bytes, if known before downloading it.
+    ///
+    /// Useful to show the progress of the download.
+    pub fn size(&self) -> Option<usize> {
+        self.size
+    }
+
     /// Changes the chunk size, in bytes, used to make requests. Useful if you only need to get a
     /// small part of a file. By default, `MAX_CHUNK_SIZE` is used.
     ///
     /// # Panics
     ///
-    /// Panics if `size` is not divisible by `MIN_CHUNK_SIZE`, or if `size` is not in contained in
-    /// the range `MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE`.
+    /// Panics if `size` is not a power of two in the range `MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE`,
+    /// which Telegram requires so that no chunk crosses a 1 MiB boundary.
     pub fn chunk_size(mut self, size: i32) -> Self {
-        assert!((MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&size) && size % MIN_CHUNK_SIZE == 0);
+        assert!((MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&size) && size.count_ones() == 1);
         self.request.limit = size;
         self
     }
 
+    /// Download up to `n` chunks at once, each through a different connection to the
+    /// datacenter where the file lives. This can greatly speed up the download of big files.
+    ///
+    /// Chunks are still returned in order, and at most `n` are kept in memory at a time.
+    ///
+    /// Files whose size is unknown, or which are not larger than 10 MiB, are always downloaded
+    /// through a single connection. At most 8 connections are used.
+    pub fn connections(mut self, n: usize) -> Self {
+        self.connections = download_connections(n, self.size);
+        self
+    }
+
     /// Skips `n` chunks to start downloading a different offset from the file. If you want to
     /// skip less data, modify the `chunk_size` before calling this method, and then reset it to
     /// any value you want.
     pub fn skip_chunks(mut self, n: i32) -> Self {
         self.request.offset += (self.request.limit * n) as i64;
+        self.downloaded += (self.request.limit * n) as u64;
+        self
+    }
+
+    /// Only download the given range of bytes from the file, for example to resume an
+    /// interrupted download, or to serve an HTTP range request.
+    ///
+    /// Telegram only allows downloading from offsets which are a multiple of the chunk size, so
+    /// the chunks around the start and end of the range are fetched in full and trimmed. If you
+    /// want to change the chunk size, do so before calling this method.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(downloadable: grammers_client::types::Downloadable, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// // Resume a download which was interrupted after 1000 bytes.
+    /// let mut download = client.iter_download(&downloadable).range(1000..);
+    /// while let Some(chunk) = download.next().await? {
+    ///     // Append the chunk to the partially downloaded file.
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn range<R: RangeBounds<u64>>(mut self, range: R) -> Self {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => Some(end + 1),
+            Bound::Excluded(&end) => Some(end),
+            Bound::Unbounded => None,
+        };
+
+        let (offset, skip_bytes) = align_offset(start, self.request.limit);
+        self.request.offset = offset as i64;
+        self.skip_bytes = skip_bytes;
+        self.remaining_bytes = end.map(|end| end.saturating_sub(start));
+        self.downloaded = start;
+        if self.remaining_bytes == Some(0) {
+            self.done = true;
+        }
+        self
+    }
+
+    /// Call `callback` with the position in the file reached so far, and the total size, every
+    /// time a chunk is downloaded. The total size is `0` if it is not known.
+    ///
+    /// The callback runs as part of [`DownloadIter::next`], so it should be cheap. Anything slow
+    /// (such as redrawing a user interface) should be sent elsewhere, for example through a
+    /// channel. The callback stops being called once the download is dropped.
+    pub fn progress<F: Fn(u64, u64) + Send + 'static>(mut self, callback: F) -> Self {
+        self.progress = Some(Box::new(callback));
         self
     }
 
     /// Fetch and return the next chunk.
     pub async fn next(&mut self) -> Result<Option<Vec<u8>>, InvocationError> {
+        let chunk = loop {
+            let mut chunk = match self.next_chunk().await? {
+                Some(chunk) => chunk,
+                None => return Ok(None),
+            };
+
+            if self.skip_bytes != 0 {
+                let skip = self.skip_bytes.min(chunk.len());
+                chunk.drain(..skip);
+                self.skip_bytes -= skip;
+            }
+            if let Some(remaining) = self.remaining_bytes {
+                if chunk.len() as u64 >= remaining {
+                    chunk.truncate(remaining as usize);
+                    self.done = true;
+                    self.pending = FuturesOrdered::new();
+                }
+                self.remaining_bytes = Some(remaining - chunk.len() as u64);
+            }
+
+            if !chunk.is_empty() {
+                break chunk;
+            } else if self.done {
+                return Ok(None);
+            }
+        };
+
+        self.downloaded += chunk.len() as u64;
+        if let Some(callback) = &self.progress {
+            callback(self.downloaded, self.size.unwrap_or(0) as u64);
+        }
+        Ok(Some(chunk))
+    }
+
+    async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, InvocationError> {
         if self.done {
             return Ok(None);
         }
@@ -119,15 +279,18 @@ impl DownloadIter {
             return Ok(Some(data.clone()));
         }
 
+        if self.scheduled != 0 {
+            return self.next_concurrent().await;
+        }
+
         use tl::enums::upload::File;
 
         // TODO handle maybe FILEREF_UPGRADE_NEEDED
-        let mut dc: Option<u32> = None;
         loop {
-            let result = match dc.take() {
-                None => self.client.invoke(&self.request).await,
-                Some(dc) => self.client.invoke_in_dc(&self.request, dc as i32).await,
-            };
+            let dc = self
+                .dc
+                .unwrap_or_else(|| self.client.0.state.read().unwrap().dc_id);
+            let result = self.client.invoke_file_request(&self.request, dc, 0).await;
 
             break match result {
                 Ok(File::File(f)) => {
@@ -139,21 +302,165 @@ impl DownloadIter {
                     }
 
                     self.request.offset += self.request.limit as i64;
+                    if self.connections > 1 && !self.done {
+                        // The first chunk tells in which datacenter the rest should be fetched.
+                        self.dc = self.dc.or(Some(self.client.0.state.read().unwrap().dc_id));
+                        self.scheduled = 1;
+                    }
                     Ok(Some(f.bytes))
                 }
-                Ok(File::CdnRedirect(_)) => {
-                    panic!("API returned File::CdnRedirect even though cdn_supported = false");
-                }
+                // Should not happen because `cdn_supported` is `false`.
+                Ok(File::CdnRedirect(_)) => Err(local_error("CDN_NOT_SUPPORTED")),
                 Err(InvocationError::Rpc(err)) if err.code == FILE_MIGRATE_ERROR => {
-                    dc = err.value;
+                    self.dc = err.value.map(|dc| dc as i32);
                     continue;
                 }
+                Err(e) if e.is("FILE_REFERENCE_EXPIRED") => {
+                    if self.refresh_file_reference().await? {
+                        continue;
+                    }
+                    Err(e)
+                }
                 Err(e) => Err(e),
             };
         }
     }
 }
 
+impl DownloadIter {
+    async fn next_concurrent(&mut self) -> Result<Option<Vec<u8>>, InvocationError> {
+        // No more chunks are needed past the end of the file or of the wanted range.
+        let mut end = self.size.unwrap_or(0) as i64;
+        if let Some(remaining) = self.remaining_bytes {
+            end = end.min((self.downloaded + remaining) as i64);
+        }
+        let dc = self.dc.unwrap();
+        loop {
+            while self.pending.len() < self.connections && self.request.offset < end {
+                let connection = self.scheduled % self.connections;
+                self.pending.push_back(
+                    fetch_chunk(self.client.clone(), self.request.clone(), dc, connection).boxed(),
+                );
+                self.request.offset += self.request.limit as i64;
+                self.scheduled += 1;
+            }
+
+            break match self.pending.next().await {
+                Some(Ok(bytes)) => {
+                    if bytes.len() < self.request.limit as usize {
+                        self.done = true;
+                        self.pending = FuturesOrdered::new();
+                        if bytes.is_empty() {
+                            return Ok(None);
+                        }
+                    }
+                    Ok(Some(bytes))
+                }
+                Some(Err(e)) if e.is("FILE_REFERENCE_EXPIRED") => {
+                    // Every chunk after the failed one was requested with the same expired
+                    // reference, so they are all requested again.
+                    let failed_offset = self.request.offset
+                        - (self.pending.len() as i64 + 1) * self.request.limit as i64;
+                    self.pending = FuturesOrdered::new();
+                    if self.refresh_file_reference().await? {
+                        self.request.offset = failed_offset;
+                        continue;
+                    }
+                    self.done = true;
+                    Err(e)
+                }
+                Some(Err(e)) => {
+                    self.done = true;
+                    self.pending = FuturesOrdered::new();
+                    Err(e)
+                }
+                None => {
+                    self.done = true;
+                    Ok(None)
+                }
+            };
+        }
+    }
+
+    /// Fetch the message where the file was found again, and continue the download with the
+    /// fresh file reference of its media.
+    ///
+    /// Returns `false` if the message is not known, was already fetched again once, or no
+    /// longer contains the file (for example, because it was deleted).
+    async fn refresh_file_reference(&mut self) -> Result<bool, InvocationError> {
+        let origin = match self.origin.as_ref() {
+            Some(origin) if !self.refreshed => origin,
+            _ => return Ok(false),
+        };
+        self.refreshed = true;
+
+        use tl::enums::InputFileLocation as L;
+        let id = match &self.request.location {
+            L::InputPhotoFileLocation(location) => location.id,
+            L::InputDocumentFileLocation(location) => location.id,
+            _ => return Ok(false),
+        };
+
+        let file_reference = self
+            .client
+            .get_messages_by_id(origin.chat, &[origin.message_id])
+            .await?
+            .pop()
+            .flatten()
+            .and_then(|message| message.media())
+            .and_then(|media| media.file_reference(id));
+
+        match (file_reference, &mut self.request.location) {
+            (Some(file_reference), L::InputPhotoFileLocation(location)) => {
+                location.file_reference = file_reference;
+                Ok(true)
+            }
+            (Some(file_reference), L::InputDocumentFileLocation(location)) => {
+                location.file_reference = file_reference;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+/// Fetch a single chunk through the given connection to a datacenter, waiting out any flood
+/// wait rather than failing the whole download.
+async fn fetch_chunk(
+    client: Client,
+    request: tl::functions::upload::GetFile,
+    dc: i32,
+    connection: usize,
+) -> Result<Vec<u8>, InvocationError> {
+    use tl::enums::upload::File;
+
+    loop {
+        match client.invoke_file_request(&request, dc, connection).await {
+            Ok(File::File(f)) => break Ok(f.bytes),
+            Ok(File::CdnRedirect(_)) => break Err(local_error("CDN_NOT_SUPPORTED")),
+            Err(InvocationError::Flood { wait, .. }) => {
+                tokio::time::sleep(wait).await;
+            }
+            Err(e) => break Err(e),
+        }
+    }
+}
+
+/// Align a byte offset to the chunk size Telegram requires, returning the offset to download
+/// from and how many bytes to drop from the first chunk.
+fn align_offset(offset: u64, chunk_size: i32) -> (u64, usize) {
+    let skip = offset % chunk_size as u64;
+    (offset - skip, skip as usize)
+}
+
+/// How many connections to download a file of the given size with.
+fn download_connections(requested: usize, size: Option<usize>) -> usize {
+    match size {
+        Some(size) if size > BIG_FILE_SIZE => requested.clamp(1, MAX_DOWNLOAD_CONNECTIONS),
+        _ => 1,
+    }
+}
+
 /// Method implementations related to uploading or downloading files.
 impl Client {
     /// Returns a new iterator over the contents of a media document that will be downloaded.
@@ -163,7 +470,9 @@ impl Client {
     /// ```
     /// # async fn f(downloadable: grammers_client::types::Downloadable, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
     /// let mut file_bytes = Vec::new();
-    /// let mut download = client.iter_download(&downloadable);
+    /// let mut download = client
+    ///     .iter_download(&downloadable)
+    ///     .progress(|done, total| println!("Downloaded {done} out of {total} bytes"));
     ///
     /// while let Some(chunk) = download.next().await? {
     ///     file_bytes.extend(chunk);
@@ -197,17 +506,6 @@ impl Client {
         downloadable: &Downloadable,
         path: P,
     ) -> Result<(), io::Error> {
-        // Concurrent downloader
-        if let Downloadable::Media(media) = downloadable {
-            if let Media::Document(document) = media {
-                if document.size() as usize > BIG_FILE_SIZE {
-                    return self
-                        .download_media_concurrent(media, path, WORKER_COUNT)
-                        .await;
-                }
-            }
-        }
-
         if downloadable.to_raw_input_location().is_none() {
             let data = match downloadable {
                 Downloadable::PhotoSize(photo_size)
@@ -216,10 +514,7 @@ impl Client {
                     photo_size.data()
                 }
                 _ => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        "media not downloadable",
-                    ));
+                    return Err(io::Error::other("media not downloadable"));
                 }
             };
 
@@ -231,122 +526,93 @@ impl Client {
             return Ok(());
         }
 
-        let mut download = self.iter_download(downloadable);
+        // Big files are downloaded through several connections at once.
+        let mut download = self.iter_download(downloadable).connections(WORKER_COUNT);
         Client::load(path, &mut download).await
     }
 
-    async fn load<P: AsRef<Path>>(path: P, download: &mut DownloadIter) -> Result<(), io::Error> {
-        let mut file = fs::File::create(path).await?;
-        while let Some(chunk) = download
-            .next()
-            .await
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
-        {
-            file.write_all(&chunk).await?;
-        }
+    /// Downloads the current profile photo of a user, group or channel into the specified path.
+    ///
+    /// If `big` is `true`, the photo is downloaded in its largest size (640x640). Otherwise,
+    /// the small preview (160x160) is downloaded.
+    ///
+    /// The photo is fetched from the datacenter where it lives, which may not be the one the
+    /// client is connected to.
+    ///
+    /// Returns the identifier of the downloaded photo, which can be compared with the one of
+    /// a later photo to avoid downloading it again, or `None` if the chat has no photo, in
+    /// which case nothing is written.
+    ///
+    /// Previous profile photos can be found with [`Client::iter_profile_photos`], and downloaded
+    /// with [`Client::download_media`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// if client.download_profile_photo(&chat, "/home/username/photos/chat.jpg", true).await?.is_none() {
+    ///     println!("{} has no photo", chat.name());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn download_profile_photo<P: AsRef<Path>>(
+        &self,
+        chat: &Chat,
+        path: P,
+        big: bool,
+    ) -> Result<Option<i64>, io::Error> {
+        let downloadable = match chat.photo_downloadable(big) {
+            Some(downloadable) => downloadable,
+            None => return Ok(None),
+        };
+        let photo_id = match &downloadable {
+            Downloadable::UserProfilePhoto(photo) => photo.raw.photo_id,
+            Downloadable::ChatPhoto(photo) => photo.raw.photo_id,
+            _ => unreachable!(),
+        };
 
-        Ok(())
+        let mut download = self.iter_download(&downloadable);
+        Client::load(path, &mut download).await?;
+        Ok(Some(photo_id))
     }
 
-    /// Downloads a `Document` to specified path using multiple connections
-    async fn download_media_concurrent<P: AsRef<Path>>(
+    /// Downloads a media file into any writer, such as a socket or an in-memory buffer.
+    ///
+    /// The file is downloaded one chunk at a time, so memory use does not depend on its size.
+    /// Use [`Client::iter_download`] for more control over the download.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(downloadable: grammers_client::types::Downloadable, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut file_bytes = Vec::new();
+    /// client.download_to(&downloadable, &mut file_bytes).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn download_to<W: AsyncWrite + Unpin>(
         &self,
-        media: &Media,
-        path: P,
-        workers: usize,
+        downloadable: &Downloadable,
+        writer: &mut W,
     ) -> Result<(), io::Error> {
-        let document = match media {
-            Media::Document(document) => document,
-            _ => panic!("Only Document type is supported!"),
-        };
-        let size = document.size();
-        let location = media.to_raw_input_location().unwrap();
-        // Allocate
-        let mut file = fs::File::create(path).await?;
-        file.set_len(size as u64).await?;
-        file.seek(SeekFrom::Start(0)).await?;
-
-        // Start workers
-        let (tx, mut rx) = unbounded_channel();
-        let part_index = Arc::new(tokio::sync::Mutex::new(0));
-        let mut tasks = vec![];
-        for _ in 0..workers {
-            let location = location.clone();
-            let tx = tx.clone();
-            let part_index = part_index.clone();
-            let client = self.clone();
-            let task = tokio::task::spawn(async move {
-                let mut retry_offset = None;
-                let mut dc = None;
-                loop {
-                    // Calculate file offset
-                    let offset: i64 = {
-                        if let Some(offset) = retry_offset {
-                            retry_offset = None;
-                            offset
-                        } else {
-                            let mut i = part_index.lock().await;
-                            *i += 1;
-                            (MAX_CHUNK_SIZE * (*i - 1)) as i64
-                        }
-                    };
-                    if offset > size {
-                        break;
-                    }
-                    // Fetch from telegram
-                    let request = &tl::functions::upload::GetFile {
-                        precise: true,
-                        cdn_supported: false,
-                        location: location.clone(),
-                        offset,
-                        limit: MAX_CHUNK_SIZE,
-                    };
-                    let res = match dc {
-                        None => client.invoke(request).await,
-                        Some(dc) => client.invoke_in_dc(request, dc as i32).await,
-                    };
-                    match res {
-                        Ok(tl::enums::upload::File::File(file)) => {
-                            tx.send((offset as u64, file.bytes)).unwrap();
-                        }
-                        Ok(tl::enums::upload::File::CdnRedirect(_)) => {
-                            panic!(
-                                "API returned File::CdnRedirect even though cdn_supported = false"
-                            );
-                        }
-                        Err(InvocationError::Rpc(err)) => {
-                            if err.code == FILE_MIGRATE_ERROR {
-                                dc = err.value;
-                                retry_offset = Some(offset);
-                                continue;
-                            }
-                            return Err(InvocationError::Rpc(err));
-                        }
-                        Err(e) => return Err(e),
-                    }
-                }
-                Ok::<(), InvocationError>(())
-            });
-            tasks.push(task);
-        }
-        drop(tx);
+        let mut download = self.iter_download(downloadable);
+        Client::write_download(writer, &mut download).await
+    }
 
-        // File write loop
-        let mut pos = 0;
-        while let Some((offset, data)) = rx.recv().await {
-            if offset != pos {
-                file.seek(SeekFrom::Start(offset)).await?;
-            }
-            file.write_all(&data).await?;
-            pos = offset + data.len() as u64;
-        }
+    async fn load<P: AsRef<Path>>(path: P, download: &mut DownloadIter) -> Result<(), io::Error> {
+        let mut file = fs::File::create(path).await?;
+        Client::write_download(&mut file, download).await
+    }
 
-        // Check if all tasks finished succesfully
-        for task in tasks {
-            task.await?
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    async fn write_download<W: AsyncWrite + Unpin>(
+        writer: &mut W,
+        download: &mut DownloadIter,
+    ) -> Result<(), io::Error> {
+        while let Some(chunk) = download.next().await.map_err(io::Error::other)? {
+            writer.write_all(&chunk).await?;
         }
-        Ok(())
+        writer.flush().await
     }
 
     /// Uploads an async stream to Telegram servers.
@@ -360,6 +626,10 @@ impl Client {
     /// process the entire async stream to determine its size, and then use the size and the
     /// downloaded buffer.
     ///
+    /// The stream is read one part at a time, so the file never needs to be fully in memory.
+    /// Parts are sized according to the total size, and files larger than 10 MiB are uploaded
+    /// as "big" files, using several parts at once.
+    ///
     /// The stream size may be less or equal to the actual length of the stream, but not more.
     /// If it's less, you may continue to read from the stream after the method returns.
     /// If it's more, the method will fail because it does not have enough data to read.
@@ -393,47 +663,186 @@ impl Client {
         size: usize,
         name: String,
     ) -> Result<Uploaded, io::Error> {
+        self.upload(stream, size, name).send().await
+    }
+
+    /// Prepares the upload of an async stream to Telegram servers, which starts once
+    /// [`UploadBuilder::send`] is called.
+    ///
+    /// This behaves like [`Client::upload_stream`], but the upload can be configured first,
+    /// for example to report its progress.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut file = tokio::fs::File::open("video.mp4").await?;
+    /// let size = file.metadata().await?.len() as usize;
+    /// let uploaded_file = client
+    ///     .upload(&mut file, size, "video.mp4".to_string())
+    ///     .concurrency(8)
+    ///     .progress(|done, total| println!("Uploaded {done} out of {total} bytes"))
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn upload<'a, S: AsyncRead + Unpin>(
+        &self,
+        stream: &'a mut S,
+        size: usize,
+        name: String,
+    ) -> UploadBuilder<'a, S> {
+        UploadBuilder {
+            client: self.clone(),
+            stream,
+            size,
+            name,
+            concurrency: WORKER_COUNT,
+            progress: None,
+        }
+    }
+
+    /// Send a single part of a file being uploaded, retrying a few times if it fails.
+    ///
+    /// Saving the same part more than once is harmless, so retrying is always safe.
+    async fn save_file_part<R: tl::RemoteCall<Return = bool>>(
+        &self,
+        request: &R,
+        dc_id: i32,
+        connection: usize,
+    ) -> Result<(), io::Error> {
+        let mut attempt = 1;
+        loop {
+            let error = match self.invoke_file_request(request, dc_id, connection).await {
+                Ok(true) => break Ok(()),
+                Ok(false) => io::Error::other("server failed to store uploaded data"),
+                Err(InvocationError::Flood { wait, .. }) => {
+                    tokio::time::sleep(wait).await;
+                    continue;
+                }
+                Err(e) => io::Error::other(e),
+            };
+            if attempt >= UPLOAD_PART_ATTEMPTS {
+                break Err(error);
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Uploads a local file to Telegram servers.
+    ///
+    /// The file is not sent to any chat, but can be used as media when sending messages for a
+    /// certain period of time (less than a day). You can use this uploaded file multiple times.
+    ///
+    /// Refer to [`InputMessage`] to learn more uses for `uploaded_file`.
+    ///
+    /// If you need more control over the uploaded data, such as performing only a partial upload
+    /// or with a different name, use [`Client::upload_stream`] instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::InputMessage;
+    ///
+    /// let uploaded_file = client.upload_file("/home/username/photos/holidays.jpg").await?;
+    ///
+    /// client.send_message(&chat, InputMessage::text("Check this out!").photo(uploaded_file)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`InputMessage`]: crate::InputMessage
+    pub async fn upload_file<P: AsRef<Path>>(&self, path: P) -> Result<Uploaded, io::Error> {
+        let path = path.as_ref();
+
+        let mut file = fs::File::open(path).await?;
+        let size = file.seek(SeekFrom::End(0)).await? as usize;
+        file.seek(SeekFrom::Start(0)).await?;
+
+        // File name will only be `None` for `..` path, and directories cannot be uploaded as
+        // files, so it's fine to unwrap.
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+
+        self.upload_stream(&mut file, size, name).await
+    }
+}
+
+/// An upload being configured, created by [`Client::upload`].
+pub struct UploadBuilder<'a, S: AsyncRead + Unpin> {
+    client: Client,
+    stream: &'a mut S,
+    size: usize,
+    name: String,
+    concurrency: usize,
+    progress: Option<ProgressCallback>,
+}
+
+impl<S: AsyncRead + Unpin> UploadBuilder<'_, S> {
+    /// How many parts of big files (larger than 10 MiB) are sent at the same time, each
+    /// through a different connection. Smaller files are always sent one part at a time.
+    ///
+    /// By default, 4 parts are sent at once. At most 8 connections are used.
+    pub fn concurrency(mut self, n: usize) -> Self {
+        self.concurrency = n;
+        self
+    }
+
+    /// Call `callback` with the amount of bytes uploaded so far, and the total size, every time
+    /// a part is stored.
+    ///
+    /// The callback runs as part of the upload, so it should be cheap. Anything slow (such as
+    /// redrawing a user interface) should be sent elsewhere, for example through a channel.
+    /// The callback stops being called once the upload future is dropped.
+    pub fn progress<F: Fn(u64, u64) + Send + 'static>(mut self, callback: F) -> Self {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Upload the stream, returning the file once all of its parts are stored.
+    ///
+    /// A part which fails to upload is retried a few times before failing the whole upload.
+    pub async fn send(self) -> Result<Uploaded, io::Error> {
+        let client = &self.client;
+        let size = self.size;
+        let progress = Progress::new(self.progress, size as u64);
         let file_id = generate_random_id();
-        let name = if name.is_empty() {
+        let name = if self.name.is_empty() {
             "a".to_string()
         } else {
-            name
+            self.name
         };
 
         let big_file = size > BIG_FILE_SIZE;
-        let parts = PartStream::new(stream, size);
+        let parts = PartStream::new(self.stream, size, upload_part_size(size));
         let total_parts = parts.total_parts();
 
         if big_file {
+            let dc_id = client.0.state.read().unwrap().dc_id;
             let parts = Arc::new(parts);
             let mut tasks = FuturesUnordered::new();
-            for _ in 0..WORKER_COUNT {
-                let handle = self.clone();
+            for connection in 0..self.concurrency.clamp(1, MAX_UPLOAD_CONNECTIONS) {
+                let progress = &progress;
                 let parts = Arc::clone(&parts);
                 let task = async move {
                     while let Some((part, bytes)) = parts.next_part().await? {
-                        let ok = handle
-                            .invoke(&tl::functions::upload::SaveBigFilePart {
-                                file_id,
-                                file_part: part,
-                                file_total_parts: total_parts,
-                                bytes,
-                            })
-                            .await
-                            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-
-                        if !ok {
-                            return Err(io::Error::new(
-                                io::ErrorKind::Other,
-                                "server failed to store uploaded data",
-                            ));
-                        }
+                        let len = bytes.len();
+                        let request = tl::functions::upload::SaveBigFilePart {
+                            file_id,
+                            file_part: part,
+                            file_total_parts: total_parts,
+                            bytes,
+                        };
+                        client.save_file_part(&request, dc_id, connection).await?;
+                        progress.advance(len);
                     }
-                    Ok(())
+                    Ok::<(), io::Error>(())
                 };
                 tasks.push(task);
             }
 
+            // Every part must be stored before the file can be used.
             while let Some(res) = tasks.next().await {
                 res?;
             }
@@ -447,24 +856,18 @@ impl Client {
                 .into(),
             ))
         } else {
+            let dc_id = client.0.state.read().unwrap().dc_id;
             let mut md5 = md5::Context::new();
             while let Some((part, bytes)) = parts.next_part().await? {
                 md5.consume(&bytes);
-                let ok = self
-                    .invoke(&tl::functions::upload::SaveFilePart {
-                        file_id,
-                        file_part: part,
-                        bytes,
-                    })
-                    .await
-                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-
-                if !ok {
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        "server failed to store uploaded data",
-                    ));
-                }
+                let len = bytes.len();
+                let request = tl::functions::upload::SaveFilePart {
+                    file_id,
+                    file_part: part,
+                    bytes,
+                };
+                client.save_file_part(&request, dc_id, 0).await?;
+                progress.advance(len);
             }
             Ok(Uploaded::from_raw(
                 tl::types::InputFile {
@@ -477,46 +880,46 @@ impl Client {
             ))
         }
     }
+}
 
-    /// Uploads a local file to Telegram servers.
-    ///
-    /// The file is not sent to any chat, but can be used as media when sending messages for a
-    /// certain period of time (less than a day). You can use this uploaded file multiple times.
-    ///
-    /// Refer to [`InputMessage`] to learn more uses for `uploaded_file`.
-    ///
-    /// If you need more control over the uploaded data, such as performing only a partial upload
-    /// or with a different name, use [`Client::upload_stream`] instead.
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
-    /// use grammers_client::InputMessage;
-    ///
-    /// let uploaded_file = client.upload_file("/home/username/photos/holidays.jpg").await?;
-    ///
-    /// client.send_message(&chat, InputMessage::text("Check this out!").photo(uploaded_file)).await?;
-    /// # Ok(())
-    /// # }
-    /// ```
-    ///
-    /// [`InputMessage`]: crate::InputMessage
-    pub async fn upload_file<P: AsRef<Path>>(&self, path: P) -> Result<Uploaded, io::Error> {
-        let path = path.as_ref();
+type ProgressCallback = Box<dyn Fn(u64, u64) + Send>;
 
-        let mut file = fs::File::open(path).await?;
-        let size = file.seek(SeekFrom::End(0)).await? as usize;
-        file.seek(SeekFrom::Start(0)).await?;
+/// Keeps track of the bytes transferred so far, to report them to a progress callback.
+struct Progress {
+    callback: Option<ProgressCallback>,
+    done: AtomicU64,
+    total: u64,
+}
 
-        // File name will only be `None` for `..` path, and directories cannot be uploaded as
-        // files, so it's fine to unwrap.
-        let name = path.file_name().unwrap().to_string_lossy().to_string();
+impl Progress {
+    fn new(callback: Option<ProgressCallback>, total: u64) -> Self {
+        Self {
+            callback,
+            done: AtomicU64::new(0),
+            total,
+        }
+    }
 
-        self.upload_stream(&mut file, size, name).await
+    fn advance(&self, len: usize) {
+        let done = self.done.fetch_add(len as u64, Ordering::Relaxed) + len as u64;
+        if let Some(callback) = &self.callback {
+            callback(done, self.total);
+        }
     }
 }
 
+/// The size of the parts a file of the given size is uploaded in.
+///
+/// Telegram requires a power of two no larger than 512 KiB, and limits how many parts a file
+/// can have, so larger files need larger parts.
+fn upload_part_size(size: usize) -> usize {
+    let mut part_size = MIN_UPLOAD_PART_SIZE;
+    while part_size < MAX_CHUNK_SIZE as usize && size.div_ceil(part_size) > MAX_UPLOAD_PARTS {
+        part_size *= 2;
+    }
+    part_size
+}
+
 struct PartStreamInner<'a, S: AsyncRead + Unpin> {
     stream: &'a mut S,
     current_part: i32,
@@ -524,17 +927,19 @@ struct PartStreamInner<'a, S: AsyncRead + Unpin> {
 
 struct PartStream<'a, S: AsyncRead + Unpin> {
     inner: AsyncMutex<PartStreamInner<'a, S>>,
+    part_size: usize,
     total_parts: i32,
 }
 
 impl<'a, S: AsyncRead + Unpin> PartStream<'a, S> {
-    fn new(stream: &'a mut S, size: usize) -> Self {
-        let total_parts = ((size + MAX_CHUNK_SIZE as usize - 1) / MAX_CHUNK_SIZE as usize) as i32;
+    fn new(stream: &'a mut S, size: usize, part_size: usize) -> Self {
+        let total_parts = size.div_ceil(part_size) as i32;
         Self {
             inner: AsyncMutex::new(PartStreamInner {
                 stream,
                 current_part: 0,
             }),
+            part_size,
             total_parts,
         }
     }
@@ -549,7 +954,7 @@ impl<'a, S: AsyncRead + Unpin> PartStream<'a, S> {
             return Ok(None);
         }
         let mut read = 0;
-        let mut buffer = vec![0; MAX_CHUNK_SIZE as usize];
+        let mut buffer = vec![0; self.part_size];
 
         while read != buffer.len() {
             let n = lock.stream.read(&mut buffer[read..]).await?;
@@ -577,3 +982,61 @@ impl<'a, S: AsyncRead + Unpin> PartStream<'a, S> {
         res
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_upload_part_size() {
+        const MIB: usize = 1024 * 1024;
+
+        assert_eq!(upload_part_size(0), 128 * 1024);
+        assert_eq!(upload_part_size(10 * MIB), 128 * 1024);
+        assert_eq!(upload_part_size(375 * MIB), 128 * 1024);
+        assert_eq!(upload_part_size(375 * MIB + 1), 256 * 1024);
+        assert_eq!(upload_part_size(750 * MIB + 1), 512 * 1024);
+        assert_eq!(upload_part_size(4000 * MIB), 512 * 1024);
+    }
+
+    #[test]
+    fn check_align_offset() {
+        assert_eq!(align_offset(0, MAX_CHUNK_SIZE), (0, 0));
+        assert_eq!(align_offset(1000, MAX_CHUNK_SIZE), (0, 1000));
+        assert_eq!(align_offset(512 * 1024, MAX_CHUNK_SIZE), (512 * 1024, 0));
+        assert_eq!(
+            align_offset(600 * 1024, MAX_CHUNK_SIZE),
+            (512 * 1024, 88 * 1024)
+        );
+        assert_eq!(align_offset(5000, MIN_CHUNK_SIZE), (4096, 904));
+    }
+
+    #[test]
+    fn check_download_connections() {
+        const MIB: usize = 1024 * 1024;
+
+        assert_eq!(download_connections(4, None), 1);
+        assert_eq!(download_connections(4, Some(10 * MIB)), 1);
+        assert_eq!(download_connections(4, Some(10 * MIB + 1)), 4);
+        assert_eq!(download_connections(0, Some(100 * MIB)), 1);
+        assert_eq!(download_connections(100, Some(100 * MIB)), 8);
+    }
+
+    #[test]
+    fn check_parts_with_short_last_part() {
+        let data = vec![1u8; 300 * 1024];
+        let mut stream = data.as_slice();
+        let parts = PartStream::new(&mut stream, data.len(), 128 * 1024);
+        assert_eq!(parts.total_parts(), 3);
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let mut sizes = Vec::new();
+        while let Some((part, bytes)) = runtime.block_on(parts.next_part()).unwrap() {
+            assert_eq!(part, sizes.len() as i32);
+            sizes.push(bytes.len());
+        }
+        assert_eq!(sizes, [128 * 1024, 128 * 1024, 44 * 1024]);
+    }
+}
@@ -0,0 +1,520 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Methods related to the invite links of groups and channels.
+
+use super::chats::local_error;
+use super::Client;
+use crate::types::{ChatMap, InviteLink, InviteLinkJoiner, IterBuffer};
+pub use grammers_mtsender::InvocationError;
+use grammers_session::PackedChat;
+use grammers_tl_types as tl;
+use std::sync::Arc;
+
+const MAX_INVITE_LIMIT: usize = 100;
+const MAX_JOINER_LIMIT: usize = 100;
+
+/// An invite link builder, used to create a new link or edit an existing one.
+///
+/// It will be executed once [`InviteLinkBuilder::send`] is `.await`-ed.
+pub struct InviteLinkBuilder {
+    client: Client,
+    peer: tl::enums::InputPeer,
+    link: Option<String>,
+    expire_date: Option<i32>,
+    usage_limit: Option<i32>,
+    request_needed: Option<bool>,
+    title: Option<String>,
+}
+
+impl InviteLinkBuilder {
+    fn new(client: &Client, chat: PackedChat, link: Option<String>) -> Self {
+        Self {
+            client: client.clone(),
+            peer: chat.to_input_peer(),
+            link,
+            expire_date: None,
+            usage_limit: None,
+            request_needed: None,
+            title: None,
+        }
+    }
+
+    /// When the link should stop working.
+    pub fn expire_date(mut self, date: chrono::DateTime<chrono::Utc>) -> Self {
+        self.expire_date = Some(date.timestamp() as i32);
+        self
+    }
+
+    /// How many users at most can join through the link.
+    ///
+    /// This cannot be combined with [`InviteLinkBuilder::request_needed`].
+    pub fn usage_limit(mut self, limit: i32) -> Self {
+        self.usage_limit = Some(limit);
+        self
+    }
+
+    /// Whether users joining through the link need to be approved by an administrator first.
+    ///
+    /// See [`Client::approve_join_request`] and [`Client::decline_join_request`].
+    pub fn request_needed(mut self, needed: bool) -> Self {
+        self.request_needed = Some(needed);
+        self
+    }
+
+    /// A title for the link, only visible to administrators, to tell it apart from others.
+    pub fn title<T: Into<String>>(mut self, title: T) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Create or edit the link, returning its new state.
+    pub async fn send(self) -> Result<InviteLink, InvocationError> {
+        let invite = match self.link {
+            Some(link) => {
+                match self
+                    .client
+                    .invoke(&tl::functions::messages::EditExportedChatInvite {
+                        revoked: false,
+                        peer: self.peer,
+                        link,
+                        expire_date: self.expire_date,
+                        usage_limit: self.usage_limit,
+                        request_needed: self.request_needed,
+                        title: self.title,
+                    })
+                    .await?
+                {
+                    tl::enums::messages::ExportedChatInvite::Invite(invite) => invite.invite,
+                    tl::enums::messages::ExportedChatInvite::Replaced(invite) => invite.new_invite,
+                }
+            }
+            None => {
+                self.client
+                    .invoke(&tl::functions::messages::ExportChatInvite {
+                        legacy_revoke_permanent: false,
+                        request_needed: self.request_needed.unwrap_or(false),
+                        peer: self.peer,
+                        expire_date: self.expire_date,
+                        usage_limit: self.usage_limit,
+                        title: self.title,
+                        subscription_pricing: None,
+                    })
+                    .await?
+            }
+        };
+
+        Ok(InviteLink::from_raw(invite)
+            .expect("API returned ChatInvitePublicJoinRequests for an exported invite"))
+    }
+}
+
+/// Iterator over the invite links created by an administrator, returned by
+/// [`Client::iter_invite_links`].
+///
+/// Links which still work are returned first, followed by the revoked ones.
+pub type InviteLinkIter = IterBuffer<tl::functions::messages::GetExportedChatInvites, InviteLink>;
+
+impl InviteLinkIter {
+    fn new(client: &Client, chat: PackedChat, admin: tl::enums::InputUser) -> Self {
+        Self::from_request(
+            client,
+            MAX_INVITE_LIMIT,
+            tl::functions::messages::GetExportedChatInvites {
+                revoked: false,
+                peer: chat.to_input_peer(),
+                admin_id: admin,
+                offset_date: None,
+                offset_link: None,
+                limit: 0,
+            },
+        )
+    }
+
+    /// Return the next `InviteLink` from the internal buffer, filling the buffer previously if
+    /// it's empty.
+    ///
+    /// Returns `None` if the `limit` is reached or there are no links left.
+    pub async fn next(&mut self) -> Result<Option<InviteLink>, InvocationError> {
+        loop {
+            match self.next_raw() {
+                // Once the links still in use are exhausted, move on to the revoked ones.
+                Some(Ok(None)) if !self.request.revoked && !self.limit_reached() => {
+                    self.request.revoked = true;
+                    self.request.offset_date = None;
+                    self.request.offset_link = None;
+                    self.last_chunk = false;
+                    continue;
+                }
+                Some(result) => return result,
+                None => {}
+            }
+
+            self.request.limit = self.determine_limit(MAX_INVITE_LIMIT);
+            let tl::enums::messages::ExportedChatInvites::Invites(invites) =
+                self.client.invoke(&self.request).await?;
+
+            self.last_chunk = invites.invites.len() < self.request.limit as usize;
+            self.buffer
+                .extend(invites.invites.into_iter().filter_map(InviteLink::from_raw));
+
+            if let Some(last) = self.buffer.back() {
+                self.request.offset_date = Some(last.raw.date);
+                self.request.offset_link = Some(last.raw.link.clone());
+            }
+        }
+    }
+}
+
+/// Iterator over the users who joined through an invite link, returned by
+/// [`Client::iter_invite_link_joiners`].
+pub type InviteLinkJoinerIter =
+    IterBuffer<tl::functions::messages::GetChatInviteImporters, InviteLinkJoiner>;
+
+impl InviteLinkJoinerIter {
+    fn new(client: &Client, chat: PackedChat, link: Option<String>) -> Self {
+        Self::from_request(
+            client,
+            MAX_JOINER_LIMIT,
+            tl::functions::messages::GetChatInviteImporters {
+                requested: false,
+                subscription_expired: false,
+                peer: chat.to_input_peer(),
+                link,
+                q: None,
+                offset_date: 0,
+                offset_user: tl::enums::InputUser::Empty,
+                limit: 0,
+            },
+        )
+    }
+
+    /// Only return the users who requested to join and are still pending approval, instead of
+    /// those who already joined.
+    pub fn requested(mut self) -> Self {
+        self.request.requested = true;
+        self
+    }
+
+    /// Only return the users whose name or username contains the given query.
+    pub fn search<Q: Into<String>>(mut self, query: Q) -> Self {
+        self.request.q = Some(query.into());
+        self
+    }
+
+    /// Determines how many users there are in total.
+    ///
+    /// This only performs a network call if `next` has not been called before.
+    pub async fn total(&mut self) -> Result<usize, InvocationError> {
+        if let Some(total) = self.total {
+            return Ok(total);
+        }
+        self.request.limit = 1;
+        let tl::enums::messages::ChatInviteImporters::Importers(importers) =
+            self.client.invoke(&self.request).await?;
+        let total = importers.count as usize;
+        self.total = Some(total);
+        Ok(total)
+    }
+
+    /// Return the next `InviteLinkJoiner` from the internal buffer, filling the buffer
+    /// previously if it's empty.
+    ///
+    /// Returns `None` if the `limit` is reached or there are no users left.
+    pub async fn next(&mut self) -> Result<Option<InviteLinkJoiner>, InvocationError> {
+        if let Some(result) = self.next_raw() {
+            return result;
+        }
+
+        self.request.limit = self.determine_limit(MAX_JOINER_LIMIT);
+        let tl::enums::messages::ChatInviteImporters::Importers(importers) =
+            self.client.invoke(&self.request).await?;
+
+        {
+            let mut state = self.client.0.state.write().unwrap();
+            // Telegram can return peers without hash (e.g. Users with 'min: true')
+            let _ = state.chat_hashes.extend(&importers.users, &[]);
+        }
+
+        self.last_chunk = importers.importers.len() < self.request.limit as usize;
+        self.total = Some(importers.count as usize);
+
+        let mut chats = ChatMap::new(importers.users, Vec::new());
+        self.client.cache_chats(chats.iter());
+        let chats = Arc::get_mut(&mut chats).unwrap();
+        self.buffer
+            .extend(importers.importers.into_iter().filter_map(|importer| {
+                let tl::enums::ChatInviteImporter::Importer(raw) = importer;
+                chats
+                    .remove_user(raw.user_id)
+                    .map(|user| InviteLinkJoiner { raw, user })
+            }));
+
+        // Don't bother updating offsets if this is the last time stuff has to be fetched.
+        if !self.last_chunk && !self.buffer.is_empty() {
+            let last = &self.buffer[self.buffer.len() - 1];
+            self.request.offset_date = last.raw.date;
+            self.request.offset_user = last
+                .user
+                .pack()
+                .try_to_input_user()
+                .unwrap_or(tl::enums::InputUser::Empty);
+        }
+
+        Ok(self.pop_item())
+    }
+}
+
+/// Method implementations related to the invite links of groups and channels.
+impl Client {
+    /// Get the primary invite link of a group or channel, creating it if it doesn't exist yet.
+    ///
+    /// The logged-in account must be an administrator with the rights to invite users.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let invite = client.export_invite_link(&chat).await?;
+    /// println!("Join us at {}", invite.link());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn export_invite_link<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+    ) -> Result<InviteLink, InvocationError> {
+        let chat = chat.into();
+        let full = if let Some(channel) = chat.try_to_input_channel() {
+            self.invoke(&tl::functions::channels::GetFullChannel { channel })
+                .await?
+        } else if let Some(chat_id) = chat.try_to_chat_id() {
+            self.invoke(&tl::functions::messages::GetFullChat { chat_id })
+                .await?
+        } else {
+            return Err(local_error("PEER_ID_INVALID"));
+        };
+
+        let tl::enums::messages::ChatFull::Full(full) = full;
+        let exported = match full.full_chat {
+            tl::enums::ChatFull::Full(chat) => chat.exported_invite,
+            tl::enums::ChatFull::ChannelFull(channel) => channel.exported_invite,
+        };
+
+        if let Some(invite) = exported.and_then(InviteLink::from_raw) {
+            return Ok(invite);
+        }
+
+        // Without a primary link yet, the legacy behaviour of this request creates one.
+        let invite = self
+            .invoke(&tl::functions::messages::ExportChatInvite {
+                legacy_revoke_permanent: true,
+                request_needed: false,
+                peer: chat.to_input_peer(),
+                expire_date: None,
+                usage_limit: None,
+                title: None,
+                subscription_pricing: None,
+            })
+            .await?;
+
+        Ok(InviteLink::from_raw(invite)
+            .expect("API returned ChatInvitePublicJoinRequests for an exported invite"))
+    }
+
+    /// Create a new additional invite link to a group or channel.
+    ///
+    /// The link will be created once the returned builder is sent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let invite = client
+    ///     .create_invite_link(&chat)
+    ///     .title("Newsletter")
+    ///     .usage_limit(100)
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_invite_link<C: Into<PackedChat>>(&self, chat: C) -> InviteLinkBuilder {
+        InviteLinkBuilder::new(self, chat.into(), None)
+    }
+
+    /// Edit an existing invite link to a group or channel.
+    ///
+    /// Only the options set on the returned builder are changed once it is sent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client
+    ///     .edit_invite_link(&chat, "https://t.me/+AbCdEf")
+    ///     .request_needed(true)
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn edit_invite_link<C: Into<PackedChat>, L: Into<String>>(
+        &self,
+        chat: C,
+        link: L,
+    ) -> InviteLinkBuilder {
+        InviteLinkBuilder::new(self, chat.into(), Some(link.into()))
+    }
+
+    /// Revoke an invite link to a group or channel, so that it can no longer be used to join.
+    ///
+    /// If the revoked link was the primary link of the chat, the new primary link that replaces
+    /// it is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.revoke_invite_link(&chat, "https://t.me/+AbCdEf").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn revoke_invite_link<C: Into<PackedChat>, L: Into<String>>(
+        &self,
+        chat: C,
+        link: L,
+    ) -> Result<Option<InviteLink>, InvocationError> {
+        let result = self
+            .invoke(&tl::functions::messages::EditExportedChatInvite {
+                revoked: true,
+                peer: chat.into().to_input_peer(),
+                link: link.into(),
+                expire_date: None,
+                usage_limit: None,
+                request_needed: None,
+                title: None,
+            })
+            .await?;
+
+        Ok(match result {
+            tl::enums::messages::ExportedChatInvite::Invite(_) => None,
+            tl::enums::messages::ExportedChatInvite::Replaced(invite) => {
+                InviteLink::from_raw(invite.new_invite)
+            }
+        })
+    }
+
+    /// Iterate over the invite links to a group or channel created by the given administrator,
+    /// including those which have been revoked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let me = client.get_me().await?;
+    /// let mut links = client.iter_invite_links(&chat, &me);
+    ///
+    /// while let Some(invite) = links.next().await? {
+    ///     println!("{} was used {} times", invite.link(), invite.usage());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn iter_invite_links<C: Into<PackedChat>, A: Into<PackedChat>>(
+        &self,
+        chat: C,
+        admin: A,
+    ) -> InviteLinkIter {
+        let admin = admin
+            .into()
+            .try_to_input_user()
+            .unwrap_or(tl::enums::InputUser::Empty);
+        InviteLinkIter::new(self, chat.into(), admin)
+    }
+
+    /// Iterate over the users who joined a group or channel through the given invite link.
+    ///
+    /// Use [`InviteLinkJoinerIter::requested`] to instead get the users whose join requests are
+    /// still pending.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut joiners = client.iter_invite_link_joiners(&chat, "https://t.me/+AbCdEf");
+    ///
+    /// while let Some(joiner) = joiners.next().await? {
+    ///     println!("{} joined on {}", joiner.user.full_name(), joiner.date());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn iter_invite_link_joiners<C: Into<PackedChat>, L: Into<String>>(
+        &self,
+        chat: C,
+        link: L,
+    ) -> InviteLinkJoinerIter {
+        InviteLinkJoinerIter::new(self, chat.into(), Some(link.into()))
+    }
+
+    /// Approve the pending request of a user to join a group or channel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, user: grammers_client::types::User, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.approve_join_request(&chat, &user).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn approve_join_request<C: Into<PackedChat>, U: Into<PackedChat>>(
+        &self,
+        chat: C,
+        user: U,
+    ) -> Result<(), InvocationError> {
+        self.hide_join_request(chat.into(), user.into(), true).await
+    }
+
+    /// Decline the pending request of a user to join a group or channel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, user: grammers_client::types::User, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.decline_join_request(&chat, &user).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn decline_join_request<C: Into<PackedChat>, U: Into<PackedChat>>(
+        &self,
+        chat: C,
+        user: U,
+    ) -> Result<(), InvocationError> {
+        self.hide_join_request(chat.into(), user.into(), false)
+            .await
+    }
+
+    async fn hide_join_request(
+        &self,
+        chat: PackedChat,
+        user: PackedChat,
+        approved: bool,
+    ) -> Result<(), InvocationError> {
+        let user_id = user
+            .try_to_input_user()
+            .ok_or_else(|| local_error("USER_ID_INVALID"))?;
+        self.invoke(&tl::functions::messages::HideChatJoinRequest {
+            approved,
+            peer: chat.to_input_peer(),
+            user_id,
+        })
+        .await?;
+        Ok(())
+    }
+}
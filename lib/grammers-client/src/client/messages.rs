@@ -7,7 +7,8 @@
 // except according to those terms.
 
 //! Methods related to sending messages.
-use crate::types::{InputReactions, IterBuffer, Message};
+use crate::types::input_media::AlbumKind;
+use crate::types::{AlbumStrategy, EditCaption, InputReactions, IterBuffer, Message};
 use crate::utils::{generate_random_id, generate_random_ids};
 use crate::{types, ChatMap, Client, InputMedia};
 use chrono::{DateTime, FixedOffset};
@@ -15,8 +16,177 @@ pub use grammers_mtsender::{AuthorizationError, InvocationError};
 use grammers_session::PackedChat;
 use grammers_tl_types as tl;
 use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::time::Duration;
 use tl::enums::InputPeer;
 
+/// The error type which is returned when sending an album fails.
+#[derive(Debug)]
+pub enum SendAlbumError {
+    /// The medias cannot be sent in the same album, because their kinds can't be mixed.
+    ///
+    /// The indices are those of the medias which are not compatible with the first one.
+    IncompatibleAlbum {
+        indices: Vec<usize>,
+    },
+    Other(InvocationError),
+}
+
+impl fmt::Display for SendAlbumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IncompatibleAlbum { indices } => write!(
+                f,
+                "send album error: medias at {indices:?} cannot be mixed with the first media"
+            ),
+            Self::Other(e) => write!(f, "send album error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for SendAlbumError {}
+
+impl From<InvocationError> for SendAlbumError {
+    fn from(error: InvocationError) -> Self {
+        Self::Other(error)
+    }
+}
+
+/// The error type which is returned when editing a message fails.
+#[derive(Debug)]
+pub enum EditMessageError {
+    /// The message was not sent by the logged-in account, which also lacks the rights to edit
+    /// messages from others.
+    AuthorRequired,
+    /// The message can no longer be edited, because too much time has passed since it was sent.
+    TimeExpired,
+    /// The new media cannot replace the media of a message which is part of an album, because
+    /// it cannot be mixed with the rest of medias in the album.
+    GroupedMediaInvalid,
+    Other(InvocationError),
+}
+
+impl fmt::Display for EditMessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AuthorRequired => write!(f, "edit message error: message author required"),
+            Self::TimeExpired => write!(f, "edit message error: edit time expired"),
+            Self::GroupedMediaInvalid => {
+                write!(f, "edit message error: media cannot be part of the album")
+            }
+            Self::Other(e) => write!(f, "edit message error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for EditMessageError {}
+
+impl From<InvocationError> for EditMessageError {
+    fn from(error: InvocationError) -> Self {
+        if error.is("MESSAGE_AUTHOR_REQUIRED") {
+            Self::AuthorRequired
+        } else if error.is("MESSAGE_EDIT_TIME_EXPIRED") {
+            Self::TimeExpired
+        } else if error.is("MEDIA_GROUPED_INVALID") {
+            Self::GroupedMediaInvalid
+        } else {
+            Self::Other(error)
+        }
+    }
+}
+
+/// The error type which is returned when forwarding messages fails.
+#[derive(Debug)]
+pub enum ForwardMessagesError {
+    /// The content of the source chat is protected, so its messages cannot be forwarded.
+    NoForwards,
+    Other(InvocationError),
+}
+
+impl fmt::Display for ForwardMessagesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoForwards => write!(f, "forward messages error: chat forwards restricted"),
+            Self::Other(e) => write!(f, "forward messages error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ForwardMessagesError {}
+
+impl From<InvocationError> for ForwardMessagesError {
+    fn from(error: InvocationError) -> Self {
+        if error.is("CHAT_FORWARDS_RESTRICTED") {
+            Self::NoForwards
+        } else {
+            Self::Other(error)
+        }
+    }
+}
+
+/// The error type which is returned when sending a message fails.
+#[derive(Debug)]
+pub enum SendMessageError {
+    /// Slow mode is enabled in the chat, and the given time must pass before another message
+    /// can be sent.
+    SlowModeWait(Duration),
+    /// Telegram could not fetch the media from the external URL, or the URL did not point to
+    /// valid media. The file may still be downloaded and uploaded by other means.
+    ExternalMediaUnavailable,
+    Other(InvocationError),
+}
+
+impl fmt::Display for SendMessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SlowModeWait(wait) => write!(
+                f,
+                "send message error: slow mode requires waiting {} seconds",
+                wait.as_secs()
+            ),
+            Self::ExternalMediaUnavailable => write!(
+                f,
+                "send message error: the media could not be fetched from the external url"
+            ),
+            Self::Other(e) => write!(f, "send message error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for SendMessageError {}
+
+impl From<InvocationError> for SendMessageError {
+    fn from(error: InvocationError) -> Self {
+        match error {
+            InvocationError::Rpc(rpc) if rpc.is("SLOWMODE_WAIT") => {
+                Self::SlowModeWait(Duration::from_secs(rpc.value.unwrap_or(0) as u64))
+            }
+            InvocationError::Rpc(rpc)
+                if rpc.is("WEBPAGE_CURL_FAILED")
+                    || rpc.is("WEBPAGE_MEDIA_EMPTY")
+                    || rpc.is("MEDIA_EMPTY") =>
+            {
+                Self::ExternalMediaUnavailable
+            }
+            error => Self::Other(error),
+        }
+    }
+}
+
+/// Split the kinds into the longest runs of consecutive kinds which can be sent as one album.
+fn album_runs(kinds: &[AlbumKind]) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    for i in 1..=kinds.len() {
+        if i == kinds.len() || kinds[i] != kinds[start] {
+            runs.push(start..i);
+            start = i;
+        }
+    }
+    runs
+}
+
 fn map_random_ids_to_messages(
     client: &Client,
     random_ids: &[i64],
@@ -31,6 +201,7 @@ fn map_random_ids_to_messages(
             seq: _,
         }) => {
             let chats = ChatMap::new(users, chats);
+            client.cache_chats(chats.iter());
 
             let rnd_to_id = updates
                 .iter()
@@ -163,6 +334,7 @@ impl<R: tl::RemoteCall<Return = tl::enums::messages::Messages>> IterBuffer<R, Me
         }
 
         let chats = ChatMap::new(users, chats);
+        self.client.cache_chats(chats.iter());
 
         let client = self.client.clone();
         self.buffer.extend(
@@ -379,6 +551,8 @@ impl GlobalSearchIter {
                 offset_id: 0,
                 limit: 0,
                 broadcasts_only: false,
+                groups_only: false,
+                users_only: false,
             },
         )
     }
@@ -434,6 +608,68 @@ impl GlobalSearchIter {
     }
 }
 
+pub type ReplyIter = IterBuffer<tl::functions::messages::GetReplies, Message>;
+
+impl ReplyIter {
+    fn new(client: &Client, peer: PackedChat, msg_id: i32) -> Self {
+        Self::from_request(
+            client,
+            MAX_LIMIT,
+            tl::functions::messages::GetReplies {
+                peer: peer.to_input_peer(),
+                msg_id,
+                offset_id: 0,
+                offset_date: 0,
+                add_offset: 0,
+                limit: 0,
+                max_id: 0,
+                min_id: 0,
+                hash: 0,
+            },
+        )
+    }
+
+    pub fn offset_id(mut self, offset: i32) -> Self {
+        self.request.offset_id = offset;
+        self
+    }
+
+    pub fn max_date(mut self, offset: i32) -> Self {
+        self.request.offset_date = offset;
+        self
+    }
+
+    /// Determines how many replies there are in total.
+    ///
+    /// This only performs a network call if `next` has not been called before.
+    pub async fn total(&mut self) -> Result<usize, InvocationError> {
+        self.request.limit = 1;
+        self.get_total().await
+    }
+
+    /// Return the next `Message` from the internal buffer, filling the buffer previously if it's
+    /// empty.
+    ///
+    /// Returns `None` if the `limit` is reached or there are no replies left.
+    pub async fn next(&mut self) -> Result<Option<Message>, InvocationError> {
+        if let Some(result) = self.next_raw() {
+            return result;
+        }
+
+        self.request.limit = self.determine_limit(MAX_LIMIT);
+        self.fill_buffer(self.request.limit).await?;
+
+        // Don't bother updating offsets if this is the last time stuff has to be fetched.
+        if !self.last_chunk && !self.buffer.is_empty() {
+            let last = &self.buffer[self.buffer.len() - 1];
+            self.request.offset_id = last.raw.id;
+            self.request.offset_date = last.raw.date;
+        }
+
+        Ok(self.pop_item())
+    }
+}
+
 /// Method implementations related to sending, modifying or getting messages.
 impl Client {
     /// Sends a message to the desired chat.
@@ -446,6 +682,11 @@ impl Client {
     /// Refer to [`InputMessage`] to learn more formatting options, such as using markdown or
     /// adding buttons under your message (if you're logged in as a bot).
     ///
+    /// If slow mode is enabled in the chat and the logged-in account sent a message too
+    /// recently, [`SendMessageError::SlowModeWait`] is returned with the time left to wait.
+    /// If the media is fetched by Telegram from an external URL which cannot be downloaded,
+    /// [`SendMessageError::ExternalMediaUnavailable`] is returned.
+    ///
     /// See also: [`Message::respond`], [`Message::reply`].
     ///
     /// # Examples
@@ -466,7 +707,7 @@ impl Client {
         &self,
         chat: C,
         message: M,
-    ) -> Result<Message, InvocationError> {
+    ) -> Result<Message, SendMessageError> {
         let chat = chat.into();
         let message = message.into();
         let random_id = generate_random_id();
@@ -485,6 +726,9 @@ impl Client {
                         quote_text: None,
                         quote_entities: None,
                         quote_offset: None,
+                        monoforum_peer_id: None,
+                        todo_item_id: None,
+                        poll_option: None,
                     }
                     .into()
                 }),
@@ -500,6 +744,10 @@ impl Client {
                 invert_media: message.invert_media,
                 quick_reply_shortcut: None,
                 effect: None,
+                allow_paid_floodskip: false,
+                schedule_repeat_period: None,
+                allow_paid_stars: None,
+                suggested_post: None,
             })
             .await
         } else {
@@ -517,6 +765,9 @@ impl Client {
                         quote_text: None,
                         quote_entities: None,
                         quote_offset: None,
+                        monoforum_peer_id: None,
+                        todo_item_id: None,
+                        poll_option: None,
                     }
                     .into()
                 }),
@@ -531,6 +782,11 @@ impl Client {
                 invert_media: message.invert_media,
                 quick_reply_shortcut: None,
                 effect: None,
+                allow_paid_floodskip: false,
+                schedule_repeat_period: None,
+                allow_paid_stars: None,
+                suggested_post: None,
+                rich_message: None,
             })
             .await
         }?;
@@ -555,6 +811,11 @@ impl Client {
     ///
     /// Refer to [`InputMedia`] to learn more formatting options, such as using markdown.
     ///
+    /// Telegram only allows photos and videos to be mixed in the same album, and otherwise all
+    /// medias must be documents, or all must be audio files. If this is not the case, no media
+    /// will be sent, and [`SendAlbumError::IncompatibleAlbum`] will be returned instead. Use
+    /// [`Client::send_album_with_strategy`] to split such albums automatically.
+    ///
     /// See also: [`Message::respond_album`], [`Message::reply_album`].
     ///
     /// # Examples
@@ -572,9 +833,81 @@ impl Client {
     pub async fn send_album<C: Into<PackedChat>>(
         &self,
         chat: C,
+        medias: Vec<InputMedia>,
+    ) -> Result<Vec<Option<Message>>, SendAlbumError> {
+        self.send_album_with_strategy(chat, medias, AlbumStrategy::Strict)
+            .await
+    }
+
+    /// Sends a album to the desired chat, using the given strategy to deal with medias that
+    /// cannot be part of the same album.
+    ///
+    /// With [`AlbumStrategy::SplitIncompatible`], the medias are split into the longest runs of
+    /// compatible medias, each of which is sent as a separate album in order. Only the `reply_to`
+    /// of the first media in each run is used. The returned messages are in the same order as
+    /// the input medias.
+    ///
+    /// See [`Client::send_album`] for more details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::types::AlbumStrategy;
+    /// use grammers_client::InputMedia;
+    ///
+    /// let photo = client.upload_file("cat.jpg").await?;
+    /// let song = client.upload_file("song.mp3").await?;
+    ///
+    /// // Sent as two albums: one with the photo, and another with the song.
+    /// client
+    ///     .send_album_with_strategy(
+    ///         &chat,
+    ///         vec![
+    ///             InputMedia::caption("").photo(photo),
+    ///             InputMedia::caption("").document(song),
+    ///         ],
+    ///         AlbumStrategy::SplitIncompatible,
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send_album_with_strategy<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+        medias: Vec<InputMedia>,
+        strategy: AlbumStrategy,
+    ) -> Result<Vec<Option<Message>>, SendAlbumError> {
+        let chat = chat.into();
+        let kinds = medias.iter().map(|m| m.album_kind()).collect::<Vec<_>>();
+        let runs = album_runs(&kinds);
+
+        if runs.len() <= 1 {
+            return Ok(self.send_multi_media(chat, medias).await?);
+        }
+
+        match strategy {
+            AlbumStrategy::Strict => Err(SendAlbumError::IncompatibleAlbum {
+                indices: (1..kinds.len()).filter(|&i| kinds[i] != kinds[0]).collect(),
+            }),
+            AlbumStrategy::SplitIncompatible => {
+                let mut result = Vec::with_capacity(medias.len());
+                let mut medias = medias.into_iter();
+                for run in runs {
+                    let album = medias.by_ref().take(run.len()).collect();
+                    result.extend(self.send_multi_media(chat, album).await?);
+                }
+                Ok(result)
+            }
+        }
+    }
+
+    async fn send_multi_media(
+        &self,
+        chat: PackedChat,
         mut medias: Vec<InputMedia>,
     ) -> Result<Vec<Option<Message>>, InvocationError> {
-        let chat = chat.into();
         let random_ids = generate_random_ids(medias.len());
 
         // Upload external files
@@ -618,6 +951,9 @@ impl Client {
                         quote_text: None,
                         quote_entities: None,
                         quote_offset: None,
+                        monoforum_peer_id: None,
+                        todo_item_id: None,
+                        poll_option: None,
                     }
                     .into()
                 }),
@@ -643,6 +979,8 @@ impl Client {
                 invert_media: false,
                 quick_reply_shortcut: None,
                 effect: None,
+                allow_paid_floodskip: false,
+                allow_paid_stars: None,
             })
             .await?;
 
@@ -654,14 +992,25 @@ impl Client {
     /// Similar to [`Client::send_message`], advanced formatting can be achieved with the
     /// options offered by [`InputMessage`].
     ///
+    /// Messages sent by others can only be edited in channels where the logged-in account has
+    /// the rights to do so, and messages can only be edited for a limited time after being sent.
+    /// These cases are reported as [`EditMessageError::AuthorRequired`] and
+    /// [`EditMessageError::TimeExpired`] respectively.
+    ///
     /// See also: [`Message::edit`].
     ///
     /// # Examples
     ///
     /// ```
     /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::EditMessageError;
+    ///
     /// let old_message_id = 123;
-    /// client.edit_message(&chat, old_message_id, "New text message").await?;
+    /// match client.edit_message(&chat, old_message_id, "New text message").await {
+    ///     Ok(()) => {}
+    ///     Err(EditMessageError::TimeExpired) => println!("Too late to edit the message"),
+    ///     Err(e) => return Err(e.into()),
+    /// }
     /// # Ok(())
     /// # }
     /// ```
@@ -673,7 +1022,7 @@ impl Client {
         chat: C,
         message_id: i32,
         new_message: M,
-    ) -> Result<(), InvocationError> {
+    ) -> Result<(), EditMessageError> {
         let new_message = new_message.into();
         let entities = parse_mention_entities(self, new_message.entities);
         self.invoke(&tl::functions::messages::EditMessage {
@@ -687,6 +1036,148 @@ impl Client {
             entities,
             schedule_date: new_message.schedule_date,
             quick_reply_shortcut_id: None,
+            schedule_repeat_period: None,
+            rich_message: None,
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Replaces the media of an existing message, such as to update an image in-place.
+    ///
+    /// Unlike deleting the message and sending a new one, this does not notify anyone.
+    ///
+    /// If the media is a local file, it must be uploaded with [`Client::upload_file`] first.
+    /// Whether the caption of the message is also changed is determined by `caption`.
+    ///
+    /// If the message is part of an album, the new media must be compatible with the rest
+    /// of medias in the album, or [`EditMessageError::GroupedMediaInvalid`] will be returned.
+    ///
+    /// See also: [`Message::edit_media`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::types::EditCaption;
+    /// use grammers_client::InputMedia;
+    ///
+    /// let message_id = 123;
+    /// let chart = client.upload_file("chart.png").await?;
+    /// client
+    ///     .edit_message_media(
+    ///         &chat,
+    ///         message_id,
+    ///         InputMedia::caption("").photo(chart),
+    ///         EditCaption::Keep,
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn edit_message_media<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+        message_id: i32,
+        media: InputMedia,
+        caption: EditCaption,
+    ) -> Result<(), EditMessageError> {
+        let (message, entities) = match caption {
+            EditCaption::Keep => (None, None),
+            EditCaption::Replace => (
+                Some(media.caption),
+                parse_mention_entities(self, media.entities),
+            ),
+        };
+
+        self.invoke(&tl::functions::messages::EditMessage {
+            no_webpage: false,
+            invert_media: false,
+            peer: chat.into().to_input_peer(),
+            id: message_id,
+            message,
+            media: media.media,
+            reply_markup: None,
+            entities,
+            schedule_date: None,
+            quick_reply_shortcut_id: None,
+            schedule_repeat_period: None,
+            rich_message: None,
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Updates the position of a live location sent with [`InputMessage::geo_live`].
+    ///
+    /// The live location can only be updated until its period expires or it is stopped.
+    ///
+    /// [`InputMessage::geo_live`]: crate::InputMessage::geo_live
+    pub async fn edit_live_location<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+        message_id: i32,
+        latitude: f64,
+        longitude: f64,
+    ) -> Result<(), EditMessageError> {
+        self.edit_geo_live(
+            chat.into(),
+            message_id,
+            tl::types::InputMediaGeoLive {
+                stopped: false,
+                geo_point: types::input_message::geo_point(latitude, longitude, None),
+                heading: None,
+                period: None,
+                proximity_notification_radius: None,
+            },
+        )
+        .await
+    }
+
+    /// Stops sharing a live location sent with [`InputMessage::geo_live`], leaving the last
+    /// position in the message.
+    ///
+    /// [`InputMessage::geo_live`]: crate::InputMessage::geo_live
+    pub async fn stop_live_location<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+        message_id: i32,
+    ) -> Result<(), EditMessageError> {
+        self.edit_geo_live(
+            chat.into(),
+            message_id,
+            tl::types::InputMediaGeoLive {
+                stopped: true,
+                geo_point: tl::enums::InputGeoPoint::Empty,
+                heading: None,
+                period: None,
+                proximity_notification_radius: None,
+            },
+        )
+        .await
+    }
+
+    async fn edit_geo_live(
+        &self,
+        chat: PackedChat,
+        message_id: i32,
+        media: tl::types::InputMediaGeoLive,
+    ) -> Result<(), EditMessageError> {
+        self.invoke(&tl::functions::messages::EditMessage {
+            no_webpage: false,
+            invert_media: false,
+            peer: chat.to_input_peer(),
+            id: message_id,
+            message: None,
+            media: Some(media.into()),
+            reply_markup: None,
+            entities: None,
+            schedule_date: None,
+            quick_reply_shortcut_id: None,
+            schedule_repeat_period: None,
+            rich_message: None,
         })
         .await?;
 
@@ -728,21 +1219,23 @@ impl Client {
         chat: C,
         message_ids: &[i32],
     ) -> Result<usize, InvocationError> {
-        let tl::enums::messages::AffectedMessages::Messages(affected) =
-            if let Some(channel) = chat.into().try_to_input_channel() {
-                self.invoke(&tl::functions::channels::DeleteMessages {
-                    channel,
-                    id: message_ids.to_vec(),
-                })
-                .await
-            } else {
-                self.invoke(&tl::functions::messages::DeleteMessages {
-                    revoke: true,
-                    id: message_ids.to_vec(),
-                })
-                .await
-            }?;
+        let chat = chat.into();
+        let affected = if let Some(channel) = chat.try_to_input_channel() {
+            self.invoke(&tl::functions::channels::DeleteMessages {
+                channel,
+                id: message_ids.to_vec(),
+            })
+            .await
+        } else {
+            self.invoke(&tl::functions::messages::DeleteMessages {
+                revoke: true,
+                id: message_ids.to_vec(),
+            })
+            .await
+        }?;
 
+        self.process_affected_messages(chat, &affected);
+        let tl::enums::messages::AffectedMessages::Messages(affected) = affected;
         Ok(affected.pts_count as usize)
     }
 
@@ -756,8 +1249,13 @@ impl Client {
     /// message IDs, and the indices from the list of IDs map to the indices in the result so
     /// you can find which messages were forwarded and which message they became.
     ///
+    /// If the source chat has protected content (see [`Chat::noforwards`]), this method fails
+    /// with [`ForwardMessagesError::NoForwards`].
+    ///
     /// See also: [`Message::forward_to`].
     ///
+    /// [`Chat::noforwards`]: types::Chat::noforwards
+    ///
     /// # Examples
     ///
     /// ```
@@ -775,7 +1273,7 @@ impl Client {
         destination: C,
         message_ids: &[i32],
         source: S,
-    ) -> Result<Vec<Option<Message>>, InvocationError> {
+    ) -> Result<Vec<Option<Message>>, ForwardMessagesError> {
         // TODO let user customize more options
         let request = tl::functions::messages::ForwardMessages {
             silent: false,
@@ -792,11 +1290,66 @@ impl Client {
             send_as: None,
             noforwards: false,
             quick_reply_shortcut: None,
+            allow_paid_floodskip: false,
+            reply_to: None,
+            schedule_repeat_period: None,
+            effect: None,
+            video_timestamp: None,
+            allow_paid_stars: None,
+            suggested_post: None,
         };
         let result = self.invoke(&request).await?;
         Ok(map_random_ids_to_messages(self, &request.random_id, result))
     }
 
+    /// Get the view and forward counters of up to 100 messages using their ID.
+    ///
+    /// The counters are returned in the same order as the input message IDs. If `increment` is
+    /// `true`, the messages are also marked as viewed by the logged-in account, which is what
+    /// official clients do when displaying channel posts.
+    ///
+    /// See also: [`Message::view_count`] and [`Message::forward_count`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(channel: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let message_ids = [123, 456, 789];
+    ///
+    /// let views = client.get_messages_views(&channel, &message_ids, false).await?;
+    /// for (id, counters) in message_ids.iter().zip(views) {
+    ///     println!("Post {} has {:?} views", id, counters.views);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_messages_views<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+        message_ids: &[i32],
+        increment: bool,
+    ) -> Result<Vec<tl::types::MessageViews>, InvocationError> {
+        let tl::enums::messages::MessageViews::Views(result) = self
+            .invoke(&tl::functions::messages::GetMessagesViews {
+                peer: chat.into().to_input_peer(),
+                id: message_ids.to_vec(),
+                increment,
+            })
+            .await?;
+
+        {
+            let mut state = self.0.state.write().unwrap();
+            // Telegram can return peers without hash (e.g. Users with 'min: true')
+            let _ = state.chat_hashes.extend(&result.users, &result.chats);
+        }
+
+        Ok(result
+            .views
+            .into_iter()
+            .map(|tl::enums::MessageViews::Views(views)| views)
+            .collect())
+    }
+
     /// Gets the [`Message`] to which the input message is replying to.
     ///
     /// See also: [`Message::get_reply`].
@@ -869,6 +1422,7 @@ impl Client {
         };
 
         let chats = ChatMap::new(users, chats);
+        self.cache_chats(chats.iter());
         Ok(messages
             .into_iter()
             .flat_map(|m| Message::from_raw(self, m, &chats))
@@ -938,6 +1492,74 @@ impl Client {
         GlobalSearchIter::new(self)
     }
 
+    /// Iterate over the replies to a message, from most recent to oldest.
+    ///
+    /// For channel posts, these are the comments in the linked discussion group. For messages in
+    /// a group, these are the messages in its reply thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let message_id = 123;
+    /// let mut replies = client.iter_replies(&chat, message_id);
+    ///
+    /// while let Some(reply) = replies.next().await? {
+    ///     println!("{}", reply.text());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn iter_replies<C: Into<PackedChat>>(&self, chat: C, message_id: i32) -> ReplyIter {
+        ReplyIter::new(self, chat.into(), message_id)
+    }
+
+    /// Get the message which starts the discussion thread of a message.
+    ///
+    /// For channel posts, this is the copy of the post in the linked discussion group, under
+    /// which comments are left. Returns `None` if the message has no discussion thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(channel: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let post_id = 123;
+    /// if let Some(discussion) = client.get_discussion_message(&channel, post_id).await? {
+    ///     discussion.reply("First!").await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_discussion_message<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+        message_id: i32,
+    ) -> Result<Option<Message>, InvocationError> {
+        let tl::enums::messages::DiscussionMessage::Message(discussion) = self
+            .invoke(&tl::functions::messages::GetDiscussionMessage {
+                peer: chat.into().to_input_peer(),
+                msg_id: message_id,
+            })
+            .await?;
+
+        {
+            let mut state = self.0.state.write().unwrap();
+            // Telegram can return peers without hash (e.g. Users with 'min: true')
+            let _ = state
+                .chat_hashes
+                .extend(&discussion.users, &discussion.chats);
+        }
+
+        // Albums return every message in the group, the oldest one being the thread's start.
+        let chats = ChatMap::new(discussion.users, discussion.chats);
+        self.cache_chats(chats.iter());
+        Ok(discussion
+            .messages
+            .into_iter()
+            .flat_map(|m| Message::from_raw(self, m, &chats))
+            .min_by_key(|m| m.raw.id))
+    }
+
     /// Get up to 100 messages using their ID.
     ///
     /// Returns the new retrieved messages in a list. Those messages that could not be retrieved
@@ -986,6 +1608,7 @@ impl Client {
         };
 
         let chats = ChatMap::new(users, chats);
+        self.cache_chats(chats.iter());
         let mut map = messages
             .into_iter()
             .flat_map(|m| Message::from_raw(self, m, &chats))
@@ -1015,7 +1638,6 @@ impl Client {
         chat: C,
     ) -> Result<Option<Message>, InvocationError> {
         let chat = chat.into();
-        // TODO return types::Message and print its text in the example
         let id = vec![tl::enums::InputMessage::Pinned];
 
         let result = if let Some(channel) = chat.try_to_input_channel() {
@@ -1036,12 +1658,44 @@ impl Client {
         };
 
         let chats = ChatMap::new(users, chats);
+        self.cache_chats(chats.iter());
         Ok(messages
             .into_iter()
             .flat_map(|m| Message::from_raw(self, m, &chats))
             .find(|m| m.chat().pack() == chat))
     }
 
+    /// Get all the messages currently pinned in a chat, starting with the most recent pin.
+    ///
+    /// If only the latest pin is needed, [`Client::get_pinned_message`] is cheaper.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// for message in client.get_pinned_messages(&chat).await? {
+    ///     if let Some(sender) = message.sender() {
+    ///         println!("{} sent a message that is now pinned: {}", sender.name(), message.text());
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_pinned_messages<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+    ) -> Result<Vec<Message>, InvocationError> {
+        let mut messages = self
+            .search_messages(chat)
+            .filter(tl::enums::MessagesFilter::InputMessagesFilterPinned);
+
+        let mut result = Vec::new();
+        while let Some(message) = messages.next().await? {
+            result.push(message);
+        }
+        Ok(result)
+    }
+
     /// Pin a message in the chat. This will not notify any users.
     ///
     /// # Examples
@@ -1115,6 +1769,7 @@ impl Client {
         self.invoke(&tl::functions::messages::UnpinAllMessages {
             peer: chat.into().to_input_peer(),
             top_msg_id: None,
+            saved_peer_id: None,
         })
         .await?;
         Ok(())
@@ -1181,3 +1836,41 @@ impl Client {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_album_runs() {
+        use AlbumKind::*;
+
+        assert_eq!(album_runs(&[]), Vec::<Range<usize>>::new());
+        assert_eq!(album_runs(&[Visual, Visual]), vec![0..2]);
+        assert_eq!(
+            album_runs(&[Visual, Document, Document, Audio, Visual]),
+            vec![0..1, 1..3, 3..4, 4..5]
+        );
+    }
+
+    #[test]
+    fn check_external_media_error() {
+        let rpc = |name: &str| {
+            InvocationError::Rpc(grammers_mtsender::RpcError {
+                code: 400,
+                name: name.to_string(),
+                value: None,
+                caused_by: None,
+            })
+        };
+
+        assert!(matches!(
+            SendMessageError::from(rpc("WEBPAGE_CURL_FAILED")),
+            SendMessageError::ExternalMediaUnavailable
+        ));
+        assert!(matches!(
+            SendMessageError::from(rpc("PEER_ID_INVALID")),
+            SendMessageError::Other(_)
+        ));
+    }
+}
@@ -5,17 +5,37 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
+pub mod account;
 pub mod auth;
 pub mod bots;
+pub mod calls;
 pub mod chats;
 #[allow(clippy::module_inception)]
 pub mod client;
+pub mod contacts;
+pub mod conversation;
 pub mod dialogs;
 pub mod files;
+pub mod invite_links;
 pub mod messages;
 pub mod net;
+#[cfg(feature = "secret-chats")]
+pub mod secret_chats;
+#[cfg(feature = "secret-chats")]
+mod secret_layer;
+pub mod stats;
+pub mod stickers;
+pub mod takeout;
 pub mod updates;
 
+pub use account::{PasswordError, TerminateSessionError, UsernameError};
 pub use auth::SignInError;
+pub use chats::{ModerationError, ResolveUsernameError, TransferOwnershipError};
 pub(crate) use client::ClientInner;
-pub use client::{Client, Config, InitParams};
+pub use client::{Client, Config, InitParams, Proxy, RetryPolicy, UpdateOverflowPolicy};
+pub use conversation::{Conversation, ConversationError, ConversationParams};
+pub use messages::{EditMessageError, ForwardMessagesError, SendAlbumError, SendMessageError};
+pub use net::{CancelHandle, InvokeFuture};
+#[cfg(feature = "secret-chats")]
+pub use secret_chats::SecretChatError;
+pub use takeout::{TakeoutClient, TakeoutError, TakeoutOptions};
@@ -6,21 +6,31 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 use super::client::{ClientState, Connection};
-use super::{Client, ClientInner, Config};
+use super::takeout::InvokeInTakeout;
+use super::{Client, ClientInner, Config, InitParams, Proxy};
 use crate::utils;
+use futures_util::future::{select, BoxFuture, Either};
 use grammers_mtproto::mtp;
-use grammers_mtproto::transport;
-use grammers_mtsender::{self as sender, AuthorizationError, InvocationError, RpcError, Sender};
-use grammers_session::{ChatHashCache, MessageBox};
+use grammers_mtproto::transport::{self, Transport};
+use grammers_mtsender::{
+    self as sender, AuthorizationError, InvocationError, Latency, RpcError, Sender,
+};
+use grammers_session::{ChatHashCache, MessageBox, ServerSalt};
 use grammers_tl_types::{self as tl, Deserializable};
-use log::{debug, info};
+use log::{debug, info, warn};
 use sender::Enqueuer;
 use std::collections::{HashMap, VecDeque};
-use std::net::{Ipv4Addr, SocketAddr};
-use std::sync::atomic::{AtomicU32, Ordering};
-use std::sync::{Arc, RwLock};
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::{pin, Pin};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::oneshot::error::TryRecvError;
-use tokio::sync::{Mutex as AsyncMutex, RwLock as AsyncRwLock};
+use tokio::sync::{Mutex as AsyncMutex, Notify, RwLock as AsyncRwLock};
 
 /// Socket addresses to Telegram datacenters, where the index into this array
 /// represents the data center ID.
@@ -36,18 +46,186 @@ const DC_ADDRESSES: [(Ipv4Addr, u16); 6] = [
     (Ipv4Addr::new(91, 108, 56, 190), 443),
 ];
 
-const DEFAULT_DC: i32 = 2;
+/// Like [`DC_ADDRESSES`], but with the IPv6 addresses of the datacenters.
+const DC_ADDRESSES_V6: [(Ipv6Addr, u16); 6] = [
+    (Ipv6Addr::UNSPECIFIED, 0),
+    (
+        Ipv6Addr::new(0x2001, 0xb28, 0xf23d, 0xf001, 0, 0, 0, 0xa),
+        443,
+    ),
+    (
+        Ipv6Addr::new(0x2001, 0x67c, 0x4e8, 0xf002, 0, 0, 0, 0xa),
+        443,
+    ),
+    (
+        Ipv6Addr::new(0x2001, 0xb28, 0xf23d, 0xf003, 0, 0, 0, 0xa),
+        443,
+    ),
+    (
+        Ipv6Addr::new(0x2001, 0x67c, 0x4e8, 0xf004, 0, 0, 0, 0xa),
+        443,
+    ),
+    (
+        Ipv6Addr::new(0x2001, 0xb28, 0xf23f, 0xf005, 0, 0, 0, 0xa),
+        443,
+    ),
+];
+
+/// Like [`DC_ADDRESSES`], but for the test servers, which only have three datacenters.
+const TEST_DC_ADDRESSES: [(Ipv4Addr, u16); 4] = [
+    (Ipv4Addr::new(0, 0, 0, 0), 0),
+    (Ipv4Addr::new(149, 154, 175, 10), 443),
+    (Ipv4Addr::new(149, 154, 167, 40), 443),
+    (Ipv4Addr::new(149, 154, 175, 117), 443),
+];
+
+/// Like [`TEST_DC_ADDRESSES`], but with the IPv6 addresses of the datacenters.
+const TEST_DC_ADDRESSES_V6: [(Ipv6Addr, u16); 4] = [
+    (Ipv6Addr::UNSPECIFIED, 0),
+    (
+        Ipv6Addr::new(0x2001, 0xb28, 0xf23d, 0xf001, 0, 0, 0, 0xe),
+        443,
+    ),
+    (
+        Ipv6Addr::new(0x2001, 0x67c, 0x4e8, 0xf002, 0, 0, 0, 0xe),
+        443,
+    ),
+    (
+        Ipv6Addr::new(0x2001, 0xb28, 0xf23d, 0xf003, 0, 0, 0, 0xe),
+        443,
+    ),
+];
+
+/// The obfuscated transport identifies test datacenters by adding this to their ID.
+const TEST_DC_OFFSET: i16 = 10000;
+
+pub(crate) const DEFAULT_DC: i32 = 2;
+
+/// The error code Telegram uses when a request must be made in a different datacenter.
+const MIGRATE_ERROR_CODE: i32 = 303;
+
+/// The error code Telegram uses when a request must wait before being made again.
+const FLOOD_WAIT_ERROR_CODE: i32 = 420;
+
+/// The addresses of a datacenter, in the order in which they should be tried.
+pub(crate) fn dc_addresses(dc_id: i32, params: &InitParams) -> Vec<SocketAddr> {
+    if let Some(addr) = params.dc_addresses.get(&dc_id) {
+        return vec![*addr];
+    }
+    if let Some(addr) = params.server_addr {
+        return vec![addr];
+    }
+
+    let (v4, v6) = if params.test_dc {
+        (&TEST_DC_ADDRESSES[..], &TEST_DC_ADDRESSES_V6[..])
+    } else {
+        (&DC_ADDRESSES[..], &DC_ADDRESSES_V6[..])
+    };
+    let index = usize::try_from(dc_id).ok().filter(|&i| i != 0);
+    let v4 = index
+        .and_then(|i| v4.get(i))
+        .filter(|_| !params.force_ipv6)
+        .map(|&addr| SocketAddr::from(addr));
+    let v6 = index
+        .and_then(|i| v6.get(i))
+        .map(|&addr| SocketAddr::from(addr));
+
+    if params.prefer_ipv6 || params.force_ipv6 {
+        v6.into_iter().chain(v4).collect()
+    } else {
+        v4.into_iter().chain(v6).collect()
+    }
+}
+
+/// Save the session to the [`InitParams::session_store`], if any.
+///
+/// Failing to save is not fatal, as the session will be saved again on the next change.
+pub(crate) async fn save_session(config: &Config) {
+    if let Some(store) = &config.params.session_store {
+        if let Err(e) = config.session.save_to(store.as_ref()).await {
+            warn!("failed to save session: {}", e);
+        }
+    }
+}
 
+/// Connect to a datacenter, trying each of its addresses until one works.
+///
+/// The time offset is used when no new authorization key needs to be generated, which would
+/// otherwise provide its own.
 pub(crate) async fn connect_sender(
     dc_id: i32,
     config: &Config,
-) -> Result<(Sender<transport::Full, mtp::Encrypted>, Enqueuer), AuthorizationError> {
-    let transport = transport::Full::new();
+    time_offset: i32,
+) -> Result<(Sender<Box<dyn Transport + Send>, mtp::Encrypted>, Enqueuer), AuthorizationError> {
+    let mut result = Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no address known for dc {dc_id}"),
+    )
+    .into());
 
-    let addr: SocketAddr = if let Some(ip) = config.params.server_addr {
-        ip
-    } else {
-        DC_ADDRESSES[dc_id as usize].into()
+    for addr in dc_addresses(dc_id, &config.params) {
+        result = connect_sender_to(dc_id, addr, config, time_offset).await;
+        match &result {
+            Ok(_) => break,
+            Err(e) => warn!("failed to connect to dc {} at {:?}: {}", dc_id, addr, e),
+        }
+    }
+    result
+}
+
+async fn connect_sender_to(
+    dc_id: i32,
+    addr: SocketAddr,
+    config: &Config,
+    time_offset: i32,
+) -> Result<(Sender<Box<dyn Transport + Send>, mtp::Encrypted>, Enqueuer), AuthorizationError> {
+    let mut connector = config.params.connector.clone();
+    // MTProxy servers are connected to directly, and find the datacenter from the transport.
+    let (transport, connect_addr): (Box<dyn Transport + Send>, _) = match &config.params.proxy {
+        Some(Proxy::Mtproto { host, port, secret }) => {
+            let secret = parse_proxy_secret(secret)?;
+            let proxy_addr = tokio::net::lookup_host((host.as_str(), *port))
+                .await?
+                .next()
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("proxy host did not return any ip address: {host}"),
+                    )
+                })?;
+
+            let dc_id = if config.params.test_dc {
+                TEST_DC_OFFSET + dc_id as i16
+            } else {
+                dc_id as i16
+            };
+            let transport: Box<dyn Transport + Send> = match secret {
+                ProxySecret::Plain(key) => Box::new(transport::Obfuscated::new(
+                    transport::Intermediate::new(),
+                    dc_id,
+                    Some(key),
+                )),
+                ProxySecret::Padded(key) => Box::new(transport::Obfuscated::new(
+                    transport::PaddedIntermediate::new(),
+                    dc_id,
+                    Some(key),
+                )),
+                ProxySecret::FakeTls(key, domain) => {
+                    let mut fake_tls = sender::FakeTlsConnector::new(key, domain);
+                    if let Some(connector) = connector {
+                        fake_tls = fake_tls.with_connector(connector);
+                    }
+                    connector = Some(Arc::new(fake_tls));
+                    Box::new(transport::Obfuscated::new(
+                        transport::PaddedIntermediate::new(),
+                        dc_id,
+                        Some(key),
+                    ))
+                }
+            };
+            (transport, proxy_addr)
+        }
+        _ => (Box::new(transport::Full::new()), addr),
     };
 
     let (mut sender, request_tx) = if let Some(auth_key) = config.session.dc_auth_key(dc_id) {
@@ -57,7 +235,16 @@ pub(crate) async fn connect_sender(
         );
 
         #[cfg(feature = "proxy")]
-        if let Some(url) = config.params.proxy_url.as_ref() {
+        if let Some(connector) = connector {
+            sender::connect_via_connector_with_auth(
+                transport,
+                connect_addr,
+                auth_key,
+                connector,
+                config.params.reconnection_policy,
+            )
+            .await?
+        } else if let Some(Proxy::Socks5 { url }) = config.params.proxy.as_ref() {
             sender::connect_via_proxy_with_auth(
                 transport,
                 addr,
@@ -67,35 +254,84 @@ pub(crate) async fn connect_sender(
             )
             .await?
         } else {
-            sender::connect_with_auth(transport, addr, auth_key, config.params.reconnection_policy)
-                .await?
+            sender::connect_with_auth(
+                transport,
+                connect_addr,
+                auth_key,
+                config.params.reconnection_policy,
+            )
+            .await?
         }
 
         #[cfg(not(feature = "proxy"))]
-        sender::connect_with_auth(transport, addr, auth_key, config.params.reconnection_policy)
+        if let Some(connector) = connector {
+            sender::connect_via_connector_with_auth(
+                transport,
+                connect_addr,
+                auth_key,
+                connector,
+                config.params.reconnection_policy,
+            )
+            .await?
+        } else {
+            sender::connect_with_auth(
+                transport,
+                connect_addr,
+                auth_key,
+                config.params.reconnection_policy,
+            )
             .await?
+        }
     } else {
         info!(
             "creating a new sender and auth key in dc {} {:?}",
             dc_id, addr
         );
 
+        let policy = config.params.reconnection_policy;
         #[cfg(feature = "proxy")]
-        let (sender, tx) = if let Some(url) = config.params.proxy_url.as_ref() {
-            sender::connect_via_proxy(transport, addr, url, config.params.reconnection_policy)
-                .await?
+        let (sender, tx) = if let Some(connector) = connector {
+            sender::connect_via_connector(transport, connect_addr, connector, policy).await?
+        } else if let Some(Proxy::Socks5 { url }) = config.params.proxy.as_ref() {
+            sender::connect_via_proxy(transport, addr, url, policy).await?
         } else {
-            sender::connect(transport, addr, config.params.reconnection_policy).await?
+            sender::connect(transport, connect_addr, policy).await?
         };
 
         #[cfg(not(feature = "proxy"))]
-        let (sender, tx) =
-            sender::connect(transport, addr, config.params.reconnection_policy).await?;
+        let (sender, tx) = if let Some(connector) = connector {
+            sender::connect_via_connector(transport, connect_addr, connector, policy).await?
+        } else {
+            sender::connect(transport, connect_addr, policy).await?
+        };
 
         config.session.insert_dc(dc_id, addr, sender.auth_key());
+        save_session(config).await;
         (sender, tx)
     };
 
+    // Generating a new authorization key already finds out the offset to the server's time.
+    if request_tx.time_offset() == 0 {
+        sender.set_time_offset(time_offset);
+    }
+    sender.set_keepalive(config.params.ping_interval, config.params.ping_timeout);
+    sender.set_instrumentation(config.params.instrumentation.clone());
+    sender.set_compression_threshold(config.params.compression_threshold);
+    sender.set_batch_delay(config.params.batch_delay);
+    sender.set_ack_delay(config.params.ack_delay);
+    sender.set_salts(
+        config
+            .session
+            .dc_salts(dc_id)
+            .into_iter()
+            .map(|salt| tl::types::FutureSalt {
+                valid_since: salt.valid_since,
+                valid_until: salt.valid_until,
+                salt: salt.salt,
+            })
+            .collect(),
+    );
+
     // TODO handle -404 (we had a previously-valid authkey, but server no longer knows about it)
     // TODO all up-to-date server addresses should be stored in the session for future initial connections
     let _remote_config = sender
@@ -109,7 +345,16 @@ pub(crate) async fn connect_sender(
                 system_lang_code: config.params.system_lang_code.clone(),
                 lang_pack: "".into(),
                 lang_code: config.params.lang_code.clone(),
-                proxy: None,
+                proxy: match &config.params.proxy {
+                    Some(Proxy::Mtproto { host, port, .. }) => Some(
+                        tl::types::InputClientProxy {
+                            address: host.clone(),
+                            port: *port as i32,
+                        }
+                        .into(),
+                    ),
+                    _ => None,
+                },
                 params: None,
                 query: tl::functions::help::GetConfig {},
             },
@@ -119,6 +364,69 @@ pub(crate) async fn connect_sender(
     Ok((sender, request_tx))
 }
 
+/// The secret of an MTProxy server, which determines how to talk to it.
+#[derive(Debug, PartialEq)]
+enum ProxySecret {
+    Plain([u8; 16]),
+    /// Starts with `dd`, and requires random padding.
+    Padded([u8; 16]),
+    /// Starts with `ee`, and requires the connection to look like TLS to the given domain.
+    FakeTls([u8; 16], String),
+}
+
+/// Parse the secret of an MTProxy server, given in hexadecimal or base64.
+fn parse_proxy_secret(secret: &str) -> Result<ProxySecret, io::Error> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "proxy secret must be a 16-byte key in hexadecimal or base64, optionally prefixed \
+            with dd, or prefixed with ee and followed by a domain",
+        )
+    };
+
+    let bytes = grammers_crypto::hex::opt_from_hex(secret)
+        .or_else(|| base64_decode(secret))
+        .ok_or_else(invalid)?;
+    match bytes.as_slice() {
+        [0xee, rest @ ..] if rest.len() > 16 => {
+            let (key, domain) = rest.split_at(16);
+            let domain = std::str::from_utf8(domain).map_err(|_| invalid())?;
+            Ok(ProxySecret::FakeTls(
+                key.try_into().unwrap(),
+                domain.to_string(),
+            ))
+        }
+        [0xdd, key @ ..] if key.len() == 16 => Ok(ProxySecret::Padded(key.try_into().unwrap())),
+        key if key.len() == 16 => Ok(ProxySecret::Plain(key.try_into().unwrap())),
+        _ => Err(invalid()),
+    }
+}
+
+/// Decode base64 in either the standard or the URL-safe alphabet, with optional padding.
+fn base64_decode(data: &str) -> Option<Vec<u8>> {
+    let mut result = Vec::with_capacity(data.len() * 3 / 4);
+    let mut bits = 0u32;
+    let mut bit_count = 0;
+    for c in data.trim_end_matches('=').bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        bits = (bits << 6) | value as u32;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            result.push((bits >> bit_count) as u8);
+        }
+    }
+    // A single character left over can't encode a byte.
+    (bit_count < 6).then_some(result)
+}
+
 /// Method implementations directly related with network connectivity.
 impl Client {
     /// Creates and returns a new client instance upon successful connection to Telegram.
@@ -149,14 +457,36 @@ impl Client {
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn connect(mut config: Config) -> Result<Self, AuthorizationError> {
-        let dc_id = config
-            .session
-            .get_user()
-            .map(|u| u.dc)
-            .unwrap_or(DEFAULT_DC);
-        let (sender, request_tx) = connect_sender(dc_id, &config).await?;
-        let message_box = if config.params.catch_up {
+    pub async fn connect(config: Config) -> Result<Self, AuthorizationError> {
+        let dc_id = config.session.home_dc().unwrap_or(DEFAULT_DC);
+        let (sender, request_tx) = connect_sender(dc_id, &config, 0).await?;
+        let client = Self::from_connection(config, dc_id, Connection::new(sender, request_tx));
+
+        // Don't bother getting pristine update state if we're not logged in.
+        let should_get_state = client.0.state.read().unwrap().message_box.is_empty()
+            && client.0.config.session.signed_in();
+
+        if should_get_state {
+            match client.invoke(&tl::functions::updates::GetState {}).await {
+                Ok(state) => {
+                    {
+                        client.0.state.write().unwrap().message_box.set_state(state);
+                    }
+                    client.sync_update_state();
+                }
+                Err(_err) => {
+                    // The account may no longer actually be logged in, or it can rarely fail.
+                    // `message_box` will try to correct its state as updates arrive.
+                }
+            }
+        }
+
+        Ok(client)
+    }
+
+    /// Build a client around an already-established connection to its home datacenter.
+    pub(crate) fn from_connection(config: Config, dc_id: i32, conn: Connection) -> Self {
+        let mut message_box = if config.params.catch_up {
             if let Some(state) = config.session.get_state() {
                 MessageBox::load(state)
             } else {
@@ -167,6 +497,7 @@ impl Client {
             // pristine state instead.
             MessageBox::new()
         };
+        message_box.set_channel_diff_limit(config.params.channel_difference_limit);
 
         // Pre-allocate the right `VecDeque` size if a limit is given.
         let updates = if let Some(limit) = config.params.update_queue_limit {
@@ -175,47 +506,43 @@ impl Client {
             VecDeque::new()
         };
 
-        // "Remove" the limit to avoid checking for it (and avoid warning).
-        if let Some(0) = config.params.update_queue_limit {
-            config.params.update_queue_limit = None;
-        }
-
         let self_user = config.session.get_user();
+        let mut chat_hashes = ChatHashCache::new(self_user.map(|u| (u.id, u.bot)));
+        config
+            .session
+            .cached_peers()
+            .into_iter()
+            .for_each(|peer| chat_hashes.insert(peer.chat));
 
-        // Don't bother getting pristine update state if we're not logged in.
-        let should_get_state = message_box.is_empty() && config.session.signed_in();
-
-        // TODO Sender doesn't have a way to handle backpressure yet
-        let client = Self(Arc::new(ClientInner {
-            id: utils::generate_random_id(),
-            config,
-            conn: Connection::new(sender, request_tx),
-            state: RwLock::new(ClientState {
-                dc_id,
-                message_box,
-                chat_hashes: ChatHashCache::new(self_user.map(|u| (u.id, u.bot))),
-                last_update_limit_warn: None,
-                updates,
+        Self(
+            Arc::new(ClientInner {
+                id: utils::generate_random_id(),
+                config,
+                conn,
+                state: RwLock::new(ClientState {
+                    dc_id,
+                    message_box,
+                    chat_hashes,
+                    last_update_limit_warn: None,
+                    dropped_updates: 0,
+                    update_filter: crate::types::UpdateFilter::new(),
+                    last_state_sync: Instant::now(),
+                    pending_update: None,
+                    updates,
+                    admin_cache: HashMap::new(),
+                    bot_cache: HashMap::new(),
+                    me: None,
+                    contacts: None,
+                    installed_sticker_sets: None,
+                    conversations: HashMap::new(),
+                    #[cfg(feature = "secret-chats")]
+                    secret_resends: HashMap::new(),
+                }),
+                downloader_map: AsyncRwLock::new(HashMap::new()),
+                update_room: Notify::new(),
             }),
-            downloader_map: AsyncRwLock::new(HashMap::new()),
-        }));
-
-        if should_get_state {
-            match client.invoke(&tl::functions::updates::GetState {}).await {
-                Ok(state) => {
-                    {
-                        client.0.state.write().unwrap().message_box.set_state(state);
-                    }
-                    client.sync_update_state();
-                }
-                Err(_err) => {
-                    // The account may no longer actually be logged in, or it can rarely fail.
-                    // `message_box` will try to correct its state as updates arrive.
-                }
-            }
-        }
-
-        Ok(client)
+            None,
+        )
     }
 
     /// Invoke a raw API call. This directly sends the request to Telegram's servers.
@@ -231,6 +558,19 @@ impl Client {
     ///
     /// </div>
     ///
+    /// If Telegram answers that the request must be made in a different datacenter, the request
+    /// is sent there and retried automatically:
+    ///
+    /// * `PHONE_MIGRATE`, `NETWORK_MIGRATE` and `USER_MIGRATE` move the client to the new
+    ///   datacenter, which becomes its home datacenter from then on.
+    /// * `FILE_MIGRATE` sends the request to the datacenter where the file lives, as done by
+    ///   [`Client::invoke_in_dc`].
+    ///
+    /// If [`InitParams::request_timeout`] is set, the request fails with
+    /// [`InvocationError::Timeout`] if the response takes longer than that. Dropping the returned
+    /// future before it completes cancels the request, and Telegram is told to not send its
+    /// response if it was already sent.
+    ///
     /// # Examples
     ///
     /// ```
@@ -244,15 +584,184 @@ impl Client {
     pub async fn invoke<R: tl::RemoteCall>(
         &self,
         request: &R,
+    ) -> Result<R::Return, InvocationError> {
+        match self.1 {
+            Some(takeout_id) => {
+                self.invoke_with_params(&InvokeInTakeout::new(takeout_id, request))
+                    .await
+            }
+            None => self.invoke_with_params(request).await,
+        }
+    }
+
+    /// Invoke a request with the timeout set in the [`InitParams`].
+    async fn invoke_with_params<R: tl::RemoteCall>(
+        &self,
+        request: &R,
+    ) -> Result<R::Return, InvocationError> {
+        match self.0.config.params.request_timeout {
+            Some(timeout) => self.invoke_with_timeout(request, timeout).await,
+            None => self.invoke_with_migrations(request).await,
+        }
+    }
+
+    /// Like [`Client::invoke`], but failing with [`InvocationError::Timeout`] if the response
+    /// takes longer than the given duration, instead of using the [`InitParams::request_timeout`].
+    ///
+    /// The timeout covers the whole call, including any time spent sleeping on flood waits or
+    /// moving to a different datacenter. Once it expires, the request is cancelled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_tl_types as tl;
+    /// use std::time::Duration;
+    ///
+    /// let ping = tl::functions::Ping { ping_id: 0 };
+    /// dbg!(client.invoke_with_timeout(&ping, Duration::from_secs(5)).await?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn invoke_with_timeout<R: tl::RemoteCall>(
+        &self,
+        request: &R,
+        timeout: Duration,
+    ) -> Result<R::Return, InvocationError> {
+        tokio::time::timeout(timeout, self.invoke_with_migrations(request))
+            .await
+            .unwrap_or(Err(InvocationError::Timeout))
+    }
+
+    /// Like [`Client::invoke`], but returning a [`CancelHandle`] which can be used to cancel the
+    /// request while waiting for its response.
+    ///
+    /// Once cancelled, the returned future resolves to [`InvocationError::Cancelled`], even if
+    /// the response was arriving at the same time, and Telegram is told to not send the response
+    /// if the request was already sent. This is useful to abort requests that can take a long
+    /// time to complete on Telegram's side, such as searches in very large chats.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::InvocationError;
+    /// use grammers_tl_types as tl;
+    ///
+    /// let ping = tl::functions::Ping { ping_id: 0 };
+    /// let (response, cancel) = client.invoke_cancellable(&ping);
+    ///
+    /// // Usually done from a different task, for example, when the user gives up waiting.
+    /// cancel.cancel();
+    ///
+    /// assert!(matches!(response.await, Err(InvocationError::Cancelled)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn invoke_cancellable<'a, R>(
+        &'a self,
+        request: &'a R,
+    ) -> (InvokeFuture<'a, R::Return>, CancelHandle)
+    where
+        R: tl::RemoteCall + Sync,
+        R::Return: Send,
+    {
+        let handle = CancelHandle::default();
+        let future = InvokeFuture::new(self.invoke(request), handle.clone());
+        (future, handle)
+    }
+
+    /// Invoke a request in the current home datacenter, handling migrations.
+    async fn invoke_with_migrations<R: tl::RemoteCall>(
+        &self,
+        request: &R,
+    ) -> Result<R::Return, InvocationError> {
+        match self.invoke_in_home_dc(request).await {
+            Err(InvocationError::Rpc(err)) if err.code == MIGRATE_ERROR_CODE => {
+                let dc_id = match err.value {
+                    Some(dc_id) => dc_id as i32,
+                    None => return Err(InvocationError::Rpc(err)),
+                };
+                if err.is("PHONE_MIGRATE") || err.is("NETWORK_MIGRATE") || err.is("USER_MIGRATE") {
+                    self.switch_dc(dc_id).await?;
+                    self.invoke_in_home_dc(request).await
+                } else if err.is("FILE_MIGRATE") {
+                    self.invoke_in_dc_inner(request, dc_id).await
+                } else {
+                    Err(InvocationError::Rpc(err))
+                }
+            }
+            result => result,
+        }
+    }
+
+    /// Invoke a request in the current home datacenter, without handling migrations.
+    async fn invoke_in_home_dc<R: tl::RemoteCall>(
+        &self,
+        request: &R,
     ) -> Result<R::Return, InvocationError> {
         self.0
             .conn
-            .invoke(
-                request,
-                self.0.config.params.flood_sleep_threshold,
-                |updates| self.process_socket_updates(updates),
-            )
+            .invoke(request, &self.0.config.params, |updates| {
+                self.process_socket_updates(updates)
+            })
+            .await
+    }
+
+    /// Move the client to a different home datacenter.
+    ///
+    /// If logged in, the authorization is exported from the current datacenter and imported
+    /// into the new one, so the account remains logged in.
+    async fn switch_dc(&self, dc_id: i32) -> Result<(), InvocationError> {
+        info!("switching home datacenter to {}", dc_id);
+        #[cfg(feature = "test-utils")]
+        if self.0.conn.mock.is_some() {
+            self.0.state.write().unwrap().dc_id = dc_id;
+            return Ok(());
+        }
+
+        // Connect before exporting, so no authorization is exported to an unreachable datacenter
+        // (such as those that don't exist in the test servers).
+        let (sender, request_tx) =
+            match connect_sender(dc_id, &self.0.config, self.time_offset()).await {
+                Ok(connection) => connection,
+                Err(AuthorizationError::Invoke(e)) => return Err(e),
+                Err(AuthorizationError::Gen(e)) => {
+                    panic!("authorization key generation failed: {e}")
+                }
+            };
+        let authorization = if self.0.config.session.signed_in() {
+            Some(self.export_authorization(dc_id).await?)
+        } else {
+            None
+        };
+        {
+            *self.0.conn.sender.lock().await = sender;
+            *self.0.conn.request_tx.write().unwrap() = request_tx;
+            self.0.state.write().unwrap().dc_id = dc_id;
+        }
+
+        if let Some(authorization) = authorization {
+            self.invoke_in_home_dc(&tl::functions::auth::ImportAuthorization {
+                id: authorization.id,
+                bytes: authorization.bytes,
+            })
+            .await?;
+
+            let session = &self.0.config.session;
+            if let Some(user) = session.get_user() {
+                session.set_user(user.id, dc_id, user.bot);
+            }
+            save_session(&self.0.config).await;
+        }
+
+        // A connection made to this datacenter before is no longer needed.
+        self.0
+            .downloader_map
+            .write()
             .await
+            .retain(|&(dc, _), _| dc != dc_id);
+        Ok(())
     }
 
     async fn export_authorization(
@@ -262,7 +771,7 @@ impl Client {
         let request = tl::functions::auth::ExportAuthorization {
             dc_id: target_dc_id,
         };
-        match self.invoke(&request).await {
+        match self.invoke_in_home_dc(&request).await {
             Ok(tl::enums::auth::ExportedAuthorization::Authorization(exported_auth)) => {
                 Ok(exported_auth)
             }
@@ -272,8 +781,18 @@ impl Client {
 
     async fn connect_sender(&self, dc_id: i32) -> Result<Arc<Connection>, InvocationError> {
         let mut mutex = self.0.downloader_map.write().await;
+        // A different task may have connected while waiting for the lock.
+        if let Some(downloader) = mutex.get(&(dc_id, 0)) {
+            return Ok(downloader.clone());
+        }
         debug!("Connecting new datacenter {}", dc_id);
-        match connect_sender(dc_id, &self.0.config).await {
+        #[cfg(feature = "test-utils")]
+        if let Some(mock) = &self.0.conn.mock {
+            let new_downloader = Arc::new(mock.connection().await);
+            mutex.insert((dc_id, 0), new_downloader.clone());
+            return Ok(new_downloader);
+        }
+        match connect_sender(dc_id, &self.0.config, self.time_offset()).await {
             Ok((new_sender, new_tx)) => {
                 let new_downloader = Arc::new(Connection::new(new_sender, new_tx));
 
@@ -286,10 +805,10 @@ impl Client {
                     bytes: authorization.bytes,
                 };
                 new_downloader
-                    .invoke(&request, self.0.config.params.flood_sleep_threshold, drop)
+                    .invoke(&request, &self.0.config.params, drop)
                     .await?;
 
-                mutex.insert(dc_id, new_downloader.clone());
+                mutex.insert((dc_id, 0), new_downloader.clone());
                 Ok(new_downloader.clone())
             }
             Err(AuthorizationError::Invoke(e)) => Err(e),
@@ -299,27 +818,279 @@ impl Client {
         }
     }
 
-    async fn get_downloader(&self, dc_id: i32) -> Result<Option<Arc<Connection>>, InvocationError> {
-        return Ok({
+    /// Connect an additional sender to a datacenter, which reuses the authorization of the first
+    /// connection made to it.
+    async fn connect_extra_sender(
+        &self,
+        dc_id: i32,
+        connection: usize,
+    ) -> Result<Arc<Connection>, InvocationError> {
+        // The authorization key of the datacenter must be logged in before it can be reused.
+        let home_dc_id = self.0.state.read().unwrap().dc_id;
+        if dc_id != home_dc_id && self.get_downloader(dc_id, 0).await?.is_none() {
+            self.connect_sender(dc_id).await?;
+        }
+
+        let mut mutex = self.0.downloader_map.write().await;
+        if let Some(downloader) = mutex.get(&(dc_id, connection)) {
+            return Ok(downloader.clone());
+        }
+        debug!(
+            "Connecting extra sender {} to datacenter {}",
+            connection, dc_id
+        );
+        #[cfg(feature = "test-utils")]
+        if let Some(mock) = &self.0.conn.mock {
+            let new_downloader = Arc::new(mock.connection().await);
+            mutex.insert((dc_id, connection), new_downloader.clone());
+            return Ok(new_downloader);
+        }
+        match connect_sender(dc_id, &self.0.config, self.time_offset()).await {
+            Ok((new_sender, new_tx)) => {
+                let new_downloader = Arc::new(Connection::new(new_sender, new_tx));
+                mutex.insert((dc_id, connection), new_downloader.clone());
+                Ok(new_downloader)
+            }
+            Err(AuthorizationError::Invoke(e)) => Err(e),
+            Err(AuthorizationError::Gen(e)) => {
+                panic!("authorization key generation failed: {e}")
+            }
+        }
+    }
+
+    async fn get_downloader(
+        &self,
+        dc_id: i32,
+        connection: usize,
+    ) -> Result<Option<Arc<Connection>>, InvocationError> {
+        Ok({
             let guard = self.0.downloader_map.read().await;
-            guard.get(&dc_id).cloned()
-        });
+            guard.get(&(dc_id, connection)).cloned()
+        })
     }
 
+    /// Invoke a raw API call in a specific datacenter, rather than the home datacenter.
+    ///
+    /// The first time a datacenter is used, a new connection is made to it, and the
+    /// authorization of the logged-in account is exported and imported into it. The connection
+    /// is then reused for any later call. Concurrent calls to the same datacenter wait for the
+    /// first one to connect, so the authorization is only exported once.
+    ///
+    /// The [`InitParams::request_timeout`] applies as well, and covers the time spent
+    /// connecting.
+    ///
+    /// If the datacenter is the home datacenter, this is the same as [`Client::invoke`].
+    ///
+    /// <div class="stab unstable">
+    ///
+    /// **Warning**: this method is **not** part of the stability guarantees of semantic
+    /// versioning. It **may** break during *minor* version changes (but not on patch version
+    /// changes). Use with care.
+    ///
+    /// </div>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_tl_types as tl;
+    ///
+    /// dbg!(client.invoke_in_dc(&tl::functions::Ping { ping_id: 0 }, 4).await?);
+    /// # Ok(())
+    /// # }
+    /// ```
     pub async fn invoke_in_dc<R: tl::RemoteCall>(
         &self,
         request: &R,
         dc_id: i32,
     ) -> Result<R::Return, InvocationError> {
-        let downloader = match self.get_downloader(dc_id).await? {
+        match self.1 {
+            Some(takeout_id) => {
+                self.invoke_in_dc_with_params(&InvokeInTakeout::new(takeout_id, request), dc_id)
+                    .await
+            }
+            None => self.invoke_in_dc_with_params(request, dc_id).await,
+        }
+    }
+
+    /// Invoke a request in a datacenter with the timeout set in the [`InitParams`].
+    async fn invoke_in_dc_with_params<R: tl::RemoteCall>(
+        &self,
+        request: &R,
+        dc_id: i32,
+    ) -> Result<R::Return, InvocationError> {
+        match self.0.config.params.request_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.invoke_in_dc_inner(request, dc_id))
+                .await
+                .unwrap_or(Err(InvocationError::Timeout)),
+            None => self.invoke_in_dc_inner(request, dc_id).await,
+        }
+    }
+
+    async fn invoke_in_dc_inner<R: tl::RemoteCall>(
+        &self,
+        request: &R,
+        dc_id: i32,
+    ) -> Result<R::Return, InvocationError> {
+        if dc_id == self.0.state.read().unwrap().dc_id {
+            return self.invoke_in_home_dc(request).await;
+        }
+        let downloader = match self.get_downloader(dc_id, 0).await? {
             None => self.connect_sender(dc_id).await?,
             Some(fd) => fd,
         };
         downloader
-            .invoke(request, self.0.config.params.flood_sleep_threshold, drop)
+            .invoke(request, &self.0.config.params, drop)
             .await
     }
 
+    /// Invoke a request through one of several connections to a datacenter, so that big
+    /// transfers are not limited by the latency of a single connection.
+    ///
+    /// Connection `0` is the one used by [`Client::invoke_in_dc`]. Any other connection is made
+    /// the first time it is used, and kept for later calls.
+    pub(crate) async fn invoke_in_dc_connection<R: tl::RemoteCall>(
+        &self,
+        request: &R,
+        dc_id: i32,
+        connection: usize,
+    ) -> Result<R::Return, InvocationError> {
+        if connection == 0 {
+            return self.invoke_in_dc_inner(request, dc_id).await;
+        }
+        let downloader = match self.get_downloader(dc_id, connection).await? {
+            None => self.connect_extra_sender(dc_id, connection).await?,
+            Some(fd) => fd,
+        };
+        downloader
+            .invoke(request, &self.0.config.params, drop)
+            .await
+    }
+
+    /// Invoke a request made by a file transfer through the auxiliary connections to a
+    /// datacenter, so that it does not delay the regular requests.
+    ///
+    /// Transfers using several connections at once pick which one to use with `lane`. Without
+    /// auxiliary connections, this is the same as [`Client::invoke_in_dc_connection`].
+    pub(crate) async fn invoke_file_request<R: tl::RemoteCall>(
+        &self,
+        request: &R,
+        dc_id: i32,
+        lane: usize,
+    ) -> Result<R::Return, InvocationError> {
+        match self.1 {
+            Some(takeout_id) => {
+                self.invoke_file_request_in_pool(
+                    &InvokeInTakeout::new(takeout_id, request),
+                    dc_id,
+                    lane,
+                )
+                .await
+            }
+            None => self.invoke_file_request_in_pool(request, dc_id, lane).await,
+        }
+    }
+
+    async fn invoke_file_request_in_pool<R: tl::RemoteCall>(
+        &self,
+        request: &R,
+        dc_id: i32,
+        lane: usize,
+    ) -> Result<R::Return, InvocationError> {
+        let file_senders = self.0.config.params.file_senders;
+        if file_senders == 0 {
+            return self.invoke_in_dc_connection(request, dc_id, lane).await;
+        }
+        self.close_idle_file_senders().await;
+
+        // Connection `0` is never used for files, as it's the one used by regular requests.
+        let connection = 1 + lane % file_senders;
+        let downloader = {
+            let guard = self.0.downloader_map.read().await;
+            guard.get(&(dc_id, connection)).cloned().inspect(|d| {
+                // Marked while the map is locked so it can't be closed before being used.
+                *d.last_used.lock().unwrap() = Instant::now();
+            })
+        };
+        let downloader = match downloader {
+            None => self.connect_extra_sender(dc_id, connection).await?,
+            Some(fd) => fd,
+        };
+        downloader
+            .invoke(request, &self.0.config.params, drop)
+            .await
+    }
+
+    /// Close the auxiliary connections used for file transfers which have not been used for
+    /// longer than [`InitParams::file_sender_idle_timeout`].
+    ///
+    /// If the connections are busy being used by someone else, they are left alone until the
+    /// next call.
+    async fn close_idle_file_senders(&self) {
+        let idle_timeout = self.0.config.params.file_sender_idle_timeout;
+        let idle = match self.0.downloader_map.try_write() {
+            Ok(mut guard) => {
+                let idle = guard
+                    .iter()
+                    .filter(|(&(_, connection), downloader)| {
+                        connection != 0
+                            && downloader.last_used.lock().unwrap().elapsed() > idle_timeout
+                    })
+                    .map(|(&key, _)| key)
+                    .collect::<Vec<_>>();
+                idle.into_iter()
+                    .filter_map(|key| guard.remove(&key).map(|d| (key, d)))
+                    .collect::<Vec<_>>()
+            }
+            Err(_) => return,
+        };
+        for ((dc_id, connection), downloader) in idle {
+            debug!(
+                "Closing idle extra sender {} to datacenter {}",
+                connection, dc_id
+            );
+            downloader.disconnect().await;
+        }
+    }
+
+    /// Store the server salts of the open connections in the session, so that they can be
+    /// reused when connecting again.
+    ///
+    /// Connections busy being driven by someone else are skipped until the next call.
+    pub(crate) fn sync_salts(&self) {
+        let dc_id = self.0.state.read().unwrap().dc_id;
+        let mut senders = vec![(dc_id, &self.0.conn)];
+        let downloaders = self.0.downloader_map.try_read();
+        if let Ok(downloaders) = downloaders.as_ref() {
+            senders.extend(
+                downloaders
+                    .iter()
+                    .filter(|(&(dc, _), _)| dc != dc_id)
+                    .map(|(&(dc, _), downloader)| (dc, downloader.as_ref())),
+            );
+        }
+
+        for (dc_id, connection) in senders {
+            // Salts belong to the authorization key, so they're useless without it.
+            if self.0.config.session.dc_auth_key(dc_id).is_none() {
+                continue;
+            }
+            if let Ok(sender) = connection.sender.try_lock() {
+                let salts = sender
+                    .salts()
+                    .into_iter()
+                    .map(|salt| ServerSalt {
+                        dc: dc_id,
+                        valid_since: salt.valid_since,
+                        valid_until: salt.valid_until,
+                        salt: salt.salt,
+                    })
+                    .collect();
+                self.0.config.session.set_dc_salts(dc_id, salts);
+            }
+        }
+    }
+
     /// Perform a single network step.
     ///
     /// Most commonly, you will want to use the higher-level abstraction [`Client::next_update`]
@@ -337,11 +1108,102 @@ impl Client {
     /// # }
     /// ```
     pub async fn step(&self) -> Result<(), sender::ReadError> {
+        self.wait_for_update_room().await;
         let updates = self.0.conn.step().await?;
         self.process_socket_updates(updates);
+        self.close_idle_file_senders().await;
         Ok(())
     }
 
+    /// Returns the instant at which [`Client::step`] should be called again, even if no network
+    /// activity has occurred by then.
+    ///
+    /// This accounts for both the keep-alive pings and the deadlines used to detect gaps in the
+    /// updates. Together with [`Client::step`] and [`Client::try_next_update`], it allows driving
+    /// the client from a custom event loop, rather than relying on [`Client::next_update`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use futures_util::future::{select, Either};
+    /// use std::pin::pin;
+    ///
+    /// loop {
+    ///     let deadline = client.next_deadline();
+    ///     let sleep = pin!(async { tokio::time::sleep_until(deadline.into()).await });
+    ///     let step = pin!(async { client.step().await });
+    ///     if let Either::Right((step, _)) = select(sleep, step).await {
+    ///         step?;
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn next_deadline(&self) -> Instant {
+        let deadline = self.0.state.write().unwrap().message_box.check_deadlines();
+        match self.0.conn.sender.try_lock() {
+            Ok(sender) => match sender.next_deadline() {
+                Some(ping) => deadline.min(ping.into_std()),
+                None => deadline,
+            },
+            // A different task is driving IO, and will take care of the pings.
+            Err(_) => deadline,
+        }
+    }
+
+    /// The round-trip time to the home datacenter, as measured by the keep-alive pings.
+    ///
+    /// Returns `None` until the first ping is answered, which never occurs if
+    /// [`InitParams::ping_interval`] is `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f(client: grammers_client::Client) {
+    /// if let Some(latency) = client.latency() {
+    ///     println!("last ping took {:?} ({:?} on average)", latency.last, latency.average);
+    /// }
+    /// # }
+    /// ```
+    pub fn latency(&self) -> Option<Latency> {
+        self.0.conn.request_tx.read().unwrap().latency()
+    }
+
+    /// The difference, in seconds, between the time of Telegram's servers and the system time.
+    ///
+    /// Telegram rejects requests when the system clock is off, so the offset is corrected as
+    /// soon as the server's messages show it is.
+    pub fn time_offset(&self) -> i32 {
+        self.0.conn.request_tx.read().unwrap().time_offset()
+    }
+
+    /// The current time of Telegram's servers, which is the system time corrected by the
+    /// [`Client::time_offset`].
+    ///
+    /// This should be preferred over the system time when comparing against the dates sent by
+    /// Telegram, such as when a message will self-destruct.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f(client: grammers_client::Client) {
+    /// use std::time::UNIX_EPOCH;
+    ///
+    /// let now = client.server_time().duration_since(UNIX_EPOCH).unwrap();
+    /// println!("it is {} seconds since epoch in telegram", now.as_secs());
+    /// # }
+    /// ```
+    pub fn server_time(&self) -> SystemTime {
+        let now = SystemTime::now();
+        let offset = self.time_offset();
+        if offset >= 0 {
+            now + Duration::from_secs(offset as u64)
+        } else {
+            now - Duration::from_secs(offset.unsigned_abs() as u64)
+        }
+    }
+
     /// Run the client by repeatedly calling [`Client::step`] until a graceful disconnection
     /// occurs, or a network error occurs. Incoming updates are ignored and simply dropped.
     /// instead.
@@ -355,55 +1217,218 @@ impl Client {
     /// # }
     /// ```
     pub async fn run_until_disconnected(self) -> Result<(), sender::ReadError> {
-        loop {
-            // TODO review doc comments regarding disconnects
+        while !self.0.conn.is_disconnected() {
             self.step().await?;
         }
+        Ok(())
+    }
+
+    /// Gracefully close the connections to Telegram, including those to other datacenters.
+    ///
+    /// Tasks waiting on [`Client::step`] or updates are woken up, and any request made after
+    /// this fails with [`InvocationError::Dropped`].
+    pub(crate) async fn disconnect(&self) {
+        self.sync_update_state();
+        self.0.conn.disconnect().await;
+        let downloaders = self
+            .0
+            .downloader_map
+            .write()
+            .await
+            .drain()
+            .map(|(_, downloader)| downloader)
+            .collect::<Vec<_>>();
+        for downloader in downloaders {
+            downloader.disconnect().await;
+        }
+    }
+}
+
+/// A handle to cancel a request made with [`Client::invoke_cancellable`].
+///
+/// The handle can be cloned, and cancelling the request through any of the clones cancels it.
+#[derive(Clone, Debug, Default)]
+pub struct CancelHandle(Arc<CancelState>);
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelHandle {
+    /// Cancel the request, unless its future already completed.
+    ///
+    /// The server is asked to drop the response the next time the client is stepped, which
+    /// happens continuously while updates are handled or other requests are made.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        // Stores a permit if the future is not waiting yet, so the cancellation is not missed.
+        self.0.notify.notify_one();
+    }
+
+    /// Whether [`CancelHandle::cancel`] has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    async fn cancelled(&self) {
+        while !self.is_cancelled() {
+            self.0.notify.notified().await;
+        }
+    }
+}
+
+/// The future returned by [`Client::invoke_cancellable`], which resolves to the response of the
+/// request, or to [`InvocationError::Cancelled`] once its [`CancelHandle`] is used.
+#[must_use = "futures do nothing unless polled"]
+pub struct InvokeFuture<'a, T>(BoxFuture<'a, Result<T, InvocationError>>);
+
+impl<'a, T> InvokeFuture<'a, T> {
+    fn new(
+        invoke: impl Future<Output = Result<T, InvocationError>> + Send + 'a,
+        handle: CancelHandle,
+    ) -> Self {
+        Self(Box::pin(async move {
+            let cancelled = pin!(handle.cancelled());
+            let invoke = pin!(invoke);
+            // The cancellation is checked first, so that it wins over a response arriving at
+            // the same time. Dropping the request makes the sender ask to drop its response.
+            match select(cancelled, invoke).await {
+                Either::Left(_) => Err(InvocationError::Cancelled),
+                Either::Right((result, _)) => result,
+            }
+        }))
+    }
+}
+
+impl<T> Future for InvokeFuture<'_, T> {
+    type Output = Result<T, InvocationError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.as_mut().poll(cx)
+    }
+}
+
+impl<T> fmt::Debug for InvokeFuture<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InvokeFuture").finish_non_exhaustive()
     }
 }
 
 impl Connection {
-    fn new(sender: Sender<transport::Full, mtp::Encrypted>, request_tx: Enqueuer) -> Self {
+    pub(crate) fn new(
+        sender: Sender<Box<dyn Transport + Send>, mtp::Encrypted>,
+        request_tx: Enqueuer,
+    ) -> Self {
         Self {
             sender: AsyncMutex::new(sender),
             request_tx: RwLock::new(request_tx),
             step_counter: AtomicU32::new(0),
+            disconnected: AtomicBool::new(false),
+            disconnect_notify: Notify::new(),
+            flood_until: Mutex::new(HashMap::new()),
+            last_used: Mutex::new(Instant::now()),
+            #[cfg(feature = "test-utils")]
+            mock: None,
         }
     }
 
     pub(crate) async fn invoke<R: tl::RemoteCall, F: Fn(Vec<tl::enums::Updates>)>(
         &self,
         request: &R,
-        flood_sleep_threshold: u32,
+        params: &InitParams,
         on_updates: F,
     ) -> Result<R::Return, InvocationError> {
+        if self.is_disconnected() {
+            return Err(InvocationError::Dropped);
+        }
+        #[cfg(feature = "test-utils")]
+        if let Some(mock) = &self.mock {
+            *self.last_used.lock().unwrap() = Instant::now();
+            return mock.respond(request);
+        }
+        let method = std::any::type_name::<R>();
+        let can_sleep = |wait| params.flood_sleep_threshold.is_some_and(|t| wait <= t);
         let mut slept_flood = false;
+        let mut attempt = 1;
+
+        // Concurrent requests of the same type would only hit the flood wait again.
+        if let Some((wait, error)) = self.flood_wait(method) {
+            if !can_sleep(wait) {
+                return Err(InvocationError::Flood { wait, error });
+            }
+            info!("waiting {:?} for the flood wait of {} to end", wait, method);
+            report_flood_wait(params, request, wait);
+            tokio::time::sleep(wait).await;
+            slept_flood = true;
+        }
 
         let mut rx = { self.request_tx.read().unwrap().enqueue(request) };
         loop {
+            *self.last_used.lock().unwrap() = Instant::now();
             match rx.try_recv() {
                 Ok(response) => match response {
                     Ok(body) => break R::Return::from_bytes(&body).map_err(|e| e.into()),
-                    Err(InvocationError::Rpc(RpcError {
-                        name,
-                        code: 420,
-                        value: Some(seconds),
-                        ..
-                    })) if !slept_flood && seconds <= flood_sleep_threshold => {
-                        let delay = std::time::Duration::from_secs(seconds as _);
-                        info!(
-                            "sleeping on {} for {:?} before retrying {}",
-                            name,
-                            delay,
-                            std::any::type_name::<R>()
-                        );
-                        tokio::time::sleep(delay).await;
-                        slept_flood = true;
-                        rx = self.request_tx.read().unwrap().enqueue(request);
-                        continue;
+                    // `TAKEOUT_INIT_DELAY` shares the code, but is not a flood wait.
+                    Err(InvocationError::Rpc(
+                        err @ RpcError {
+                            code: FLOOD_WAIT_ERROR_CODE,
+                            value: Some(_),
+                            ..
+                        },
+                    )) if !err.is("TAKEOUT_INIT_DELAY") => {
+                        let wait = Duration::from_secs(err.value.unwrap() as _);
+                        let slow_mode = err.is("SLOWMODE_WAIT");
+                        if !slow_mode {
+                            self.flood_until
+                                .lock()
+                                .unwrap()
+                                .insert(method, (Instant::now() + wait, err.clone()));
+                        }
+
+                        if !slept_flood
+                            && can_sleep(wait)
+                            && (!slow_mode || params.sleep_on_slow_mode)
+                        {
+                            warn!(
+                                "sleeping on {} for {:?} before retrying {}",
+                                err.name, wait, method
+                            );
+                            report_flood_wait(params, request, wait);
+                            tokio::time::sleep(wait).await;
+                            slept_flood = true;
+                            rx = self.request_tx.read().unwrap().enqueue(request);
+                            continue;
+                        }
+
+                        break Err(if slow_mode {
+                            InvocationError::Rpc(err)
+                        } else {
+                            InvocationError::Flood { wait, error: err }
+                        });
+                    }
+                    Err(InvocationError::Rpc(err)) => {
+                        match params.retry_policy.retry_delay(&err, attempt) {
+                            Some(delay) => {
+                                let delay = delay + jitter(delay / 2);
+                                warn!(
+                                    "retrying {} in {:?} after transient error: {}",
+                                    method, delay, err
+                                );
+                                tokio::time::sleep(delay).await;
+                                attempt += 1;
+                                rx = self.request_tx.read().unwrap().enqueue(request);
+                                continue;
+                            }
+                            None => break Err(InvocationError::Rpc(err)),
+                        }
                     }
                     Err(e) => break Err(e),
                 },
+                Err(TryRecvError::Empty) if self.is_disconnected() => {
+                    break Err(InvocationError::Dropped);
+                }
                 Err(TryRecvError::Empty) => {
                     on_updates(self.step().await?);
                 }
@@ -414,18 +1439,205 @@ impl Connection {
         }
     }
 
+    /// How long until the given type of request stops being flood-limited, if it is, along with
+    /// the error which caused it (with the seconds left as its value).
+    fn flood_wait(&self, method: &'static str) -> Option<(Duration, RpcError)> {
+        let mut flood_until = self.flood_until.lock().unwrap();
+        let now = Instant::now();
+        flood_until.retain(|_, (until, _)| *until > now);
+        flood_until.get(method).map(|(until, error)| {
+            let wait = *until - now;
+            let error = RpcError {
+                value: Some(wait.as_secs_f64().ceil() as u32),
+                ..error.clone()
+            };
+            (wait, error)
+        })
+    }
+
     async fn step(&self) -> Result<Vec<tl::enums::Updates>, sender::ReadError> {
-        let ticket_number = self.step_counter.load(Ordering::SeqCst);
-        let mut sender = self.sender.lock().await;
-        match self.step_counter.compare_exchange(
-            ticket_number,
-            // As long as the counter's modulo is larger than the amount of concurrent tasks, we're fine.
-            ticket_number.wrapping_add(1),
-            Ordering::SeqCst,
-            Ordering::SeqCst,
-        ) {
-            Ok(_) => sender.step().await, // We're the one to drive IO.
-            Err(_) => Ok(Vec::new()),     // A different task drove IO.
+        // Created before checking the flag so that a disconnect in between is not missed.
+        let disconnected = pin!(self.disconnect_notify.notified());
+        if self.is_disconnected() {
+            return Ok(Vec::new());
         }
+
+        #[cfg(feature = "test-utils")]
+        if let Some(mock) = &self.mock {
+            return match select(disconnected, pin!(mock.next_updates())).await {
+                Either::Left(_) => Ok(Vec::new()),
+                Either::Right((updates, _)) => Ok(updates),
+            };
+        }
+
+        let step = pin!(async {
+            let ticket_number = self.step_counter.load(Ordering::SeqCst);
+            let mut sender = self.sender.lock().await;
+            match self.step_counter.compare_exchange(
+                ticket_number,
+                // As long as the counter's modulo is larger than the amount of concurrent tasks, we're fine.
+                ticket_number.wrapping_add(1),
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => sender.step().await, // We're the one to drive IO.
+                Err(_) => Ok(Vec::new()),     // A different task drove IO.
+            }
+        });
+
+        match select(disconnected, step).await {
+            Either::Left(_) => Ok(Vec::new()),
+            Either::Right((result, _)) => result,
+        }
+    }
+
+    pub(crate) fn is_disconnected(&self) -> bool {
+        self.disconnected.load(Ordering::SeqCst)
+    }
+
+    async fn disconnect(&self) {
+        self.disconnected.store(true, Ordering::SeqCst);
+        self.disconnect_notify.notify_waiters();
+        if let Err(e) = self.sender.lock().await.disconnect().await {
+            info!("failed to gracefully close the connection: {}", e);
+        }
+    }
+}
+
+/// Let the instrumentation know that a request is about to sleep on a flood wait.
+fn report_flood_wait<R: tl::RemoteCall>(params: &InitParams, request: &R, wait: Duration) {
+    if let Some(instrumentation) = &params.instrumentation {
+        let body = request.to_bytes();
+        let constructor_id = u32::from_le_bytes([body[0], body[1], body[2], body[3]]);
+        instrumentation.on_flood_wait(constructor_id, wait);
+    }
+}
+
+/// A random duration up to the given maximum, to spread out retries made at the same time.
+fn jitter(max: Duration) -> Duration {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    max.mul_f64(f64::from(nanos % 1000) / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RetryPolicy;
+
+    #[test]
+    fn check_dc_addresses() {
+        let mut params = InitParams::default();
+        let v4 = SocketAddr::from(DC_ADDRESSES[2]);
+        let v6 = SocketAddr::from(DC_ADDRESSES_V6[2]);
+        assert_eq!(dc_addresses(2, &params), vec![v4, v6]);
+        assert!(dc_addresses(0, &params).is_empty());
+        assert!(dc_addresses(9, &params).is_empty());
+
+        params.prefer_ipv6 = true;
+        assert_eq!(dc_addresses(2, &params), vec![v6, v4]);
+        params.force_ipv6 = true;
+        assert_eq!(dc_addresses(2, &params), vec![v6]);
+
+        let params = params.use_test_dc();
+        assert_eq!(
+            dc_addresses(2, &params),
+            vec![SocketAddr::from(TEST_DC_ADDRESSES_V6[2])]
+        );
+        assert!(dc_addresses(4, &params).is_empty());
+
+        let mut params = InitParams::default();
+        let addr = SocketAddr::from(([127, 0, 0, 1], 443));
+        params.dc_addresses.insert(4, addr);
+        assert_eq!(dc_addresses(4, &params), vec![addr]);
+    }
+
+    #[test]
+    fn check_proxy_secrets() {
+        let key = [0x7f; 16];
+        let hex = "7f".repeat(16);
+        let fake_tls = ProxySecret::FakeTls(key, "telegram.org".to_string());
+        assert_eq!(parse_proxy_secret(&hex).unwrap(), ProxySecret::Plain(key));
+        assert_eq!(
+            parse_proxy_secret(&format!("dd{hex}")).unwrap(),
+            ProxySecret::Padded(key)
+        );
+        assert_eq!(
+            parse_proxy_secret(&format!("ee{hex}74656c656772616d2e6f7267")).unwrap(),
+            fake_tls
+        );
+        // The same secret in base64, both URL-safe and standard.
+        assert_eq!(
+            parse_proxy_secret("7n9_f39_f39_f39_f39_f390ZWxlZ3JhbS5vcmc").unwrap(),
+            fake_tls
+        );
+        assert_eq!(
+            parse_proxy_secret("7n9/f39/f39/f39/f39/f390ZWxlZ3JhbS5vcmc=").unwrap(),
+            fake_tls
+        );
+
+        assert!(parse_proxy_secret(&hex[2..]).is_err());
+        assert!(parse_proxy_secret(&format!("ee{hex}")).is_err());
+        assert!(parse_proxy_secret(&format!("ee{hex}ff")).is_err());
+        assert!(parse_proxy_secret("not a secret").is_err());
+    }
+
+    #[test]
+    fn check_retry_policy() {
+        let error = |code, name: &str| RpcError {
+            code,
+            name: name.to_string(),
+            value: None,
+            caused_by: None,
+        };
+        let policy = RetryPolicy::default();
+        let timeout = error(-503, "TIMEDOUT");
+        assert_eq!(
+            policy.retry_delay(&timeout, 1),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            policy.retry_delay(&timeout, 2),
+            Some(Duration::from_millis(1000))
+        );
+        assert_eq!(policy.retry_delay(&timeout, 3), None);
+        assert!(policy.retry_delay(&error(500, "AUTH_RESTART"), 1).is_some());
+        assert_eq!(policy.retry_delay(&error(400, "PEER_ID_INVALID"), 1), None);
+        assert_eq!(RetryPolicy::None.retry_delay(&timeout, 1), None);
+
+        assert!(jitter(Duration::from_secs(1)) < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn check_invoke_cancellation() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            // Cancelling while waiting for the response.
+            let handle = CancelHandle::default();
+            let future = InvokeFuture::new(std::future::pending::<Result<(), _>>(), handle.clone());
+            let cancel = async {
+                tokio::task::yield_now().await;
+                handle.cancel();
+            };
+            let (result, ()) = futures_util::future::join(future, cancel).await;
+            assert!(matches!(result, Err(InvocationError::Cancelled)));
+
+            // Cancelling wins over a response that's ready at the same time.
+            let handle = CancelHandle::default();
+            let future = InvokeFuture::new(async { Ok(1) }, handle.clone());
+            handle.cancel();
+            assert!(matches!(future.await, Err(InvocationError::Cancelled)));
+
+            // Without cancelling, the response is returned.
+            let handle = CancelHandle::default();
+            let future = InvokeFuture::new(async { Ok(1) }, handle.clone());
+            assert!(matches!(future.await, Ok(1)));
+            assert!(!handle.is_cancelled());
+        });
     }
 }
@@ -0,0 +1,1002 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Methods related to end-to-end encrypted secret chats.
+use super::chats::local_error;
+use super::net::save_session;
+use super::secret_layer::{self, Action, DecryptedMessage, MessageLayer, PlainMessage};
+use super::Client;
+use crate::types::{
+    ChatMap, InputMessage, PackedChat, SecretChat, SecretChatStatus, SecretMessage, Update,
+};
+use crate::utils::generate_random_id;
+use grammers_crypto::{secret_chat as crypto, AuthKey};
+pub use grammers_mtsender::InvocationError;
+use grammers_session::SecretChatState;
+use grammers_tl_types as tl;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How many messages may be sent or received with the same key before a new one is exchanged.
+///
+/// Telegram recommends rekeying after 100 messages or a week of use.
+const REKEY_AFTER_MESSAGES: i32 = 100;
+
+/// How long to wait for missing messages to be resent before giving up on the secret chat.
+const RESEND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The error type which is returned when using a secret chat fails.
+#[derive(Debug)]
+pub enum SecretChatError {
+    /// The key of the secret chat is not known, either because it is not ready yet, or because
+    /// it was not established with this session.
+    NotReady,
+    /// The secret chat was not requested by the other user, so it cannot be accepted.
+    NotRequested,
+    /// The parameters used to exchange the key failed the security checks.
+    KeyExchangeFailed,
+    /// Only text messages can be sent to secret chats.
+    UnsupportedMedia,
+    /// Some messages were missing and were not resent in time, so the secret chat was discarded.
+    MessagesLost,
+    Other(InvocationError),
+}
+
+impl fmt::Display for SecretChatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotReady => write!(f, "secret chat error: key not known"),
+            Self::NotRequested => write!(f, "secret chat error: chat was not requested"),
+            Self::KeyExchangeFailed => write!(f, "secret chat error: key exchange failed"),
+            Self::UnsupportedMedia => write!(f, "secret chat error: media is not supported"),
+            Self::MessagesLost => write!(f, "secret chat error: messages were lost"),
+            Self::Other(e) => write!(f, "secret chat error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for SecretChatError {}
+
+impl From<InvocationError> for SecretChatError {
+    fn from(error: InvocationError) -> Self {
+        Self::Other(error)
+    }
+}
+
+/// Whether the update is about secret chats, and should be handled by `handle_secret_update`.
+pub(crate) fn is_secret_update(update: &tl::enums::Update) -> bool {
+    matches!(
+        update,
+        tl::enums::Update::Encryption(_) | tl::enums::Update::NewEncryptedMessage(_)
+    )
+}
+
+fn auth_key(key: &[u8]) -> Option<AuthKey> {
+    key.try_into().ok().map(AuthKey::from_bytes)
+}
+
+/// The sequence numbers to use in the next message sent in the chat.
+///
+/// Messages sent by the user who created the chat use odd `out_seq_no`, and the ones sent by
+/// the other user use even numbers.
+fn next_seq_numbers(state: &SecretChatState) -> (i32, i32) {
+    let x = state.originator as i32;
+    (2 * state.received + 1 - x, 2 * state.sent + x)
+}
+
+/// Method implementations related to secret chats.
+///
+/// Secret chats are experimental, and only support text messages. Their keys are stored in
+/// the session, and are rekeyed every 100 messages for perfect forward secrecy.
+impl Client {
+    /// Request a new secret chat with a user.
+    ///
+    /// The chat can't be used until the user accepts it, which is signaled by an
+    /// [`Update::SecretChat`] with the [`SecretChatStatus::Ready`] status.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(user: grammers_client::types::User, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let chat = client.request_secret_chat(&user).await?;
+    /// println!("Waiting for {} to accept the secret chat {}", user.first_name(), chat.id());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn request_secret_chat<C: Into<PackedChat>>(
+        &self,
+        user: C,
+    ) -> Result<SecretChat, SecretChatError> {
+        let user = user.into();
+        let input_user = user
+            .try_to_input_user()
+            .ok_or_else(|| local_error("USER_ID_INVALID"))?;
+        let (p, g, random) = self.get_dh_config().await?;
+        let (private_key, g_a) =
+            crypto::generate_key_pair(&p, g, &random).ok_or(SecretChatError::KeyExchangeFailed)?;
+
+        let chat = self
+            .invoke(&tl::functions::messages::RequestEncryption {
+                user_id: input_user,
+                random_id: generate_random_id() as i32,
+                g_a: g_a.to_vec(),
+            })
+            .await?;
+
+        let (id, access_hash) = match chat {
+            tl::enums::EncryptedChat::Waiting(c) => (c.id, c.access_hash),
+            tl::enums::EncryptedChat::Chat(c) => (c.id, c.access_hash),
+            _ => return Err(SecretChatError::NotReady),
+        };
+        self.0.config.session.set_secret_chat(SecretChatState {
+            id,
+            access_hash,
+            originator: true,
+            user_id: user.id,
+            key: None,
+            private_key: Some(private_key.to_vec()),
+            layer: secret_layer::LAYER,
+            received: 0,
+            sent: 0,
+            key_uses: 0,
+            exchange_id: None,
+            exchange_private_key: None,
+            exchange_key: None,
+            previous_key: None,
+        });
+        save_session(&self.0.config).await;
+
+        Ok(SecretChat {
+            id,
+            access_hash,
+            user_id: user.id,
+            outgoing: true,
+            status: SecretChatStatus::Waiting,
+            g_a: None,
+            client: self.clone(),
+            chats: ChatMap::empty(),
+        })
+    }
+
+    /// Accept a secret chat requested by another user.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::types::SecretChatStatus;
+    /// use grammers_client::Update;
+    ///
+    /// if let Update::SecretChat(chat) = client.next_update().await? {
+    ///     if *chat.status() == SecretChatStatus::Requested {
+    ///         client.accept_secret_chat(&chat).await?;
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn accept_secret_chat(
+        &self,
+        chat: &SecretChat,
+    ) -> Result<SecretChat, SecretChatError> {
+        let g_a = match (&chat.status, &chat.g_a) {
+            (SecretChatStatus::Requested, Some(g_a)) => g_a,
+            _ => return Err(SecretChatError::NotRequested),
+        };
+        let (p, g, random) = self.get_dh_config().await?;
+        let (private_key, g_b) =
+            crypto::generate_key_pair(&p, g, &random).ok_or(SecretChatError::KeyExchangeFailed)?;
+        let key = crypto::compute_key(&p, g, g_a, &private_key)
+            .ok_or(SecretChatError::KeyExchangeFailed)?;
+
+        self.invoke(&tl::functions::messages::AcceptEncryption {
+            peer: chat.to_input(),
+            g_b: g_b.to_vec(),
+            key_fingerprint: crypto::key_fingerprint(&key),
+        })
+        .await?;
+
+        self.0.config.session.set_secret_chat(SecretChatState {
+            id: chat.id,
+            access_hash: chat.access_hash,
+            originator: false,
+            user_id: chat.user_id,
+            key: Some(key.to_bytes().to_vec()),
+            private_key: None,
+            layer: secret_layer::LAYER,
+            received: 0,
+            sent: 0,
+            key_uses: 0,
+            exchange_id: None,
+            exchange_private_key: None,
+            exchange_key: None,
+            previous_key: None,
+        });
+        self.send_secret_action(
+            chat.id,
+            Action::NotifyLayer {
+                layer: secret_layer::LAYER,
+            },
+        )
+        .await?;
+
+        Ok(SecretChat {
+            status: SecretChatStatus::Ready,
+            g_a: None,
+            ..chat.clone()
+        })
+    }
+
+    /// Discard a secret chat, either declining it while it's being requested or closing it.
+    ///
+    /// The key of the chat is forgotten, so no more messages can be sent or received in it.
+    pub async fn discard_secret_chat(
+        &self,
+        chat: &SecretChat,
+        delete_history: bool,
+    ) -> Result<(), SecretChatError> {
+        let result = self
+            .invoke(&tl::functions::messages::DiscardEncryption {
+                delete_history,
+                chat_id: chat.id,
+            })
+            .await;
+
+        self.0.config.session.remove_secret_chat(chat.id);
+        save_session(&self.0.config).await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(InvocationError::Rpc(err)) if err.is("ENCRYPTION_ALREADY_DECLINED") => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Send a text message to a secret chat.
+    ///
+    /// The text and its formatting entities are sent, and the message may be silent. Media is
+    /// not supported, and the other options of the message are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::SecretChat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// client.send_secret_message(&chat, "Only we can read this").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send_secret_message<M: Into<InputMessage>>(
+        &self,
+        chat: &SecretChat,
+        message: M,
+    ) -> Result<SecretMessage, SecretChatError> {
+        let message = message.into();
+        if message.media.is_some() {
+            return Err(SecretChatError::UnsupportedMedia);
+        }
+
+        let plain = PlainMessage {
+            silent: message.silent,
+            random_id: generate_random_id(),
+            ttl: 0,
+            message: message.text,
+            has_media: false,
+            entities: message.entities,
+            reply_to_random_id: None,
+        };
+        let date = self
+            .send_decrypted(chat.id, DecryptedMessage::Message(plain.clone()))
+            .await?;
+        self.maybe_rekey(chat.id).await;
+
+        Ok(SecretMessage {
+            chat: chat.clone(),
+            outgoing: true,
+            random_id: plain.random_id,
+            date,
+            silent: plain.silent,
+            ttl: plain.ttl,
+            text: plain.message,
+            entities: plain.entities,
+            has_media: false,
+            reply_to_random_id: None,
+        })
+    }
+
+    /// Returns the secret chats with a known key, which were saved in the session.
+    pub fn get_secret_chats(&self) -> Vec<SecretChat> {
+        self.0
+            .config
+            .session
+            .secret_chats()
+            .into_iter()
+            .filter(|state| state.key.is_some())
+            .map(|state| SecretChat {
+                id: state.id,
+                access_hash: state.access_hash,
+                user_id: state.user_id,
+                outgoing: state.originator,
+                status: SecretChatStatus::Ready,
+                g_a: None,
+                client: self.clone(),
+                chats: ChatMap::empty(),
+            })
+            .collect()
+    }
+
+    /// Fetch the parameters for a Diffie-Hellman key exchange, as `(p, g, random)`.
+    ///
+    /// The parameters are checked to be safe to use when generating or computing keys.
+    async fn get_dh_config(&self) -> Result<(Vec<u8>, i32, Vec<u8>), SecretChatError> {
+        match self
+            .invoke(&tl::functions::messages::GetDhConfig {
+                version: 0,
+                random_length: 256,
+            })
+            .await?
+        {
+            tl::enums::messages::DhConfig::Config(c) => Ok((c.p, c.g, c.random)),
+            _ => Err(SecretChatError::KeyExchangeFailed),
+        }
+    }
+
+    /// Apply a change to the state of a secret chat stored in the session.
+    ///
+    /// The client state is locked meanwhile, so that messages sent and received at the same
+    /// time don't lose each other's changes to the sequence numbers.
+    fn modify_secret_chat<R>(
+        &self,
+        id: i32,
+        f: impl FnOnce(&mut SecretChatState) -> R,
+    ) -> Option<R> {
+        let _state = self.0.state.write().unwrap();
+        let mut chat = self.0.config.session.secret_chat(id)?;
+        let result = f(&mut chat);
+        self.0.config.session.set_secret_chat(chat);
+        Some(result)
+    }
+
+    /// Encrypt and send a message to a secret chat, returning the date it was sent at.
+    async fn send_decrypted(
+        &self,
+        id: i32,
+        message: DecryptedMessage,
+    ) -> Result<i32, SecretChatError> {
+        let random_id = match &message {
+            DecryptedMessage::Message(m) => m.random_id,
+            DecryptedMessage::Service { random_id, .. } => *random_id,
+        };
+        let service = matches!(message, DecryptedMessage::Service { .. });
+
+        let random_bytes = crypto::random_bytes();
+        let (access_hash, data) = self
+            .modify_secret_chat(id, |state| {
+                let key = auth_key(state.key.as_deref()?)?;
+                let (in_seq_no, out_seq_no) = next_seq_numbers(state);
+                let layer = MessageLayer {
+                    random_bytes,
+                    layer: secret_layer::LAYER,
+                    in_seq_no,
+                    out_seq_no,
+                    message,
+                };
+                // The message counts as sent even if sending fails, as it may still arrive.
+                state.sent += 1;
+                state.key_uses += 1;
+                Some((
+                    state.access_hash,
+                    crypto::encrypt(&layer.to_bytes(), &key, state.originator),
+                ))
+            })
+            .flatten()
+            .ok_or(SecretChatError::NotReady)?;
+
+        let peer = tl::types::InputEncryptedChat {
+            chat_id: id,
+            access_hash,
+        }
+        .into();
+        let sent = if service {
+            self.invoke(&tl::functions::messages::SendEncryptedService {
+                peer,
+                random_id,
+                data,
+            })
+            .await
+        } else {
+            self.invoke(&tl::functions::messages::SendEncrypted {
+                silent: false,
+                peer,
+                random_id,
+                data,
+            })
+            .await
+        };
+        save_session(&self.0.config).await;
+
+        Ok(sent?.date())
+    }
+
+    async fn send_secret_action(&self, id: i32, action: Action) -> Result<(), SecretChatError> {
+        self.send_decrypted(
+            id,
+            DecryptedMessage::Service {
+                random_id: generate_random_id(),
+                action,
+            },
+        )
+        .await
+        .map(drop)
+    }
+
+    /// Handle an update about secret chats, returning its friendly variant, if any.
+    ///
+    /// Failing to handle the update is not fatal, as it only affects the secret chat.
+    pub(crate) async fn handle_secret_update(
+        &self,
+        update: tl::enums::Update,
+        chats: Arc<ChatMap>,
+    ) -> Option<Update> {
+        let result = match update {
+            tl::enums::Update::Encryption(u) => self.handle_encryption(u.chat, chats).await,
+            tl::enums::Update::NewEncryptedMessage(u) => {
+                let result = self.handle_encrypted_message(u.message, chats).await;
+                if let Err(e) = self
+                    .invoke(&tl::functions::messages::ReceivedQueue { max_qts: u.qts })
+                    .await
+                {
+                    log::warn!("failed to acknowledge secret chat message: {}", e);
+                }
+                result
+            }
+            _ => Ok(None),
+        };
+
+        result.unwrap_or_else(|e| {
+            log::warn!("failed to handle secret chat update: {}", e);
+            None
+        })
+    }
+
+    async fn handle_encryption(
+        &self,
+        chat: tl::enums::EncryptedChat,
+        chats: Arc<ChatMap>,
+    ) -> Result<Option<Update>, SecretChatError> {
+        use tl::enums::EncryptedChat as C;
+
+        let self_id = self.0.state.read().unwrap().chat_hashes.self_id();
+        let secret_chat = |id, access_hash, user_id, outgoing, status, g_a| SecretChat {
+            id,
+            access_hash,
+            user_id,
+            outgoing,
+            status,
+            g_a,
+            client: self.clone(),
+            chats: chats.clone(),
+        };
+
+        let state = self.0.config.session.secret_chat(chat.id());
+        Ok(Some(Update::SecretChat(match chat {
+            C::Empty(_) => return Ok(None),
+            C::Waiting(c) => secret_chat(
+                c.id,
+                c.access_hash,
+                c.participant_id,
+                true,
+                SecretChatStatus::Waiting,
+                None,
+            ),
+            C::Requested(c) => secret_chat(
+                c.id,
+                c.access_hash,
+                c.admin_id,
+                c.admin_id == self_id,
+                SecretChatStatus::Requested,
+                Some(c.g_a),
+            ),
+            C::Chat(c) => {
+                let state = match state {
+                    Some(state) => state,
+                    // The chat was accepted with another session, which has the key.
+                    None => return Ok(None),
+                };
+                if let Some(private_key) = &state.private_key {
+                    let (p, g, _) = self.get_dh_config().await?;
+                    let key = crypto::compute_key(&p, g, &c.g_a_or_b, private_key)
+                        .filter(|key| crypto::key_fingerprint(key) == c.key_fingerprint);
+                    let key = match key {
+                        Some(key) => key,
+                        None => {
+                            self.invoke(&tl::functions::messages::DiscardEncryption {
+                                delete_history: false,
+                                chat_id: c.id,
+                            })
+                            .await?;
+                            self.0.config.session.remove_secret_chat(c.id);
+                            save_session(&self.0.config).await;
+                            return Err(SecretChatError::KeyExchangeFailed);
+                        }
+                    };
+                    self.modify_secret_chat(c.id, |state| {
+                        state.key = Some(key.to_bytes().to_vec());
+                        state.private_key = None;
+                    });
+                    self.send_secret_action(
+                        c.id,
+                        Action::NotifyLayer {
+                            layer: secret_layer::LAYER,
+                        },
+                    )
+                    .await?;
+                }
+                secret_chat(
+                    c.id,
+                    c.access_hash,
+                    state.user_id,
+                    state.originator,
+                    SecretChatStatus::Ready,
+                    None,
+                )
+            }
+            C::Discarded(c) => {
+                let state = match state {
+                    Some(state) => state,
+                    None => return Ok(None),
+                };
+                self.0.config.session.remove_secret_chat(c.id);
+                save_session(&self.0.config).await;
+                secret_chat(
+                    c.id,
+                    state.access_hash,
+                    state.user_id,
+                    state.originator,
+                    SecretChatStatus::Discarded {
+                        history_deleted: c.history_deleted,
+                    },
+                    None,
+                )
+            }
+        })))
+    }
+
+    async fn handle_encrypted_message(
+        &self,
+        message: tl::enums::EncryptedMessage,
+        chats: Arc<ChatMap>,
+    ) -> Result<Option<Update>, SecretChatError> {
+        let (chat_id, date, bytes) = match message {
+            tl::enums::EncryptedMessage::Message(m) => (m.chat_id, m.date, m.bytes),
+            tl::enums::EncryptedMessage::Service(m) => (m.chat_id, m.date, m.bytes),
+        };
+        let state = self
+            .0
+            .config
+            .session
+            .secret_chat(chat_id)
+            .ok_or(SecretChatError::NotReady)?;
+
+        // Messages may still be encrypted with the previous key, or with the next one if the
+        // other user committed to it before our commit arrived.
+        let fingerprint = bytes
+            .get(..8)
+            .map(|f| i64::from_le_bytes(f.try_into().unwrap()));
+        let key = [&state.key, &state.exchange_key, &state.previous_key]
+            .into_iter()
+            .filter_map(|key| auth_key(key.as_deref()?))
+            .find(|key| Some(crypto::key_fingerprint(key)) == fingerprint)
+            .ok_or(SecretChatError::NotReady)?;
+
+        let layer = crypto::decrypt(&bytes, &key, !state.originator)
+            .ok()
+            .and_then(|data| MessageLayer::from_bytes(&data).ok())
+            .ok_or(SecretChatError::KeyExchangeFailed)?;
+
+        let expected_seq_no = 2 * state.received + !state.originator as i32;
+        let resend = self
+            .0
+            .state
+            .read()
+            .unwrap()
+            .secret_resends
+            .get(&chat_id)
+            .copied();
+        match check_sequence(expected_seq_no, layer.out_seq_no, resend) {
+            Sequence::Repeated => {
+                log::debug!("ignoring repeated secret chat message");
+                return Ok(None);
+            }
+            Sequence::Next => {
+                if resend.is_some_and(|(end_seq_no, _)| layer.out_seq_no >= end_seq_no) {
+                    self.0
+                        .state
+                        .write()
+                        .unwrap()
+                        .secret_resends
+                        .remove(&chat_id);
+                }
+            }
+            Sequence::Resend {
+                start_seq_no,
+                end_seq_no,
+            } => {
+                // The message is dropped, and will be received again along with the missing ones.
+                log::info!(
+                    "requesting {} missing messages in secret chat {}",
+                    (layer.out_seq_no - expected_seq_no) / 2,
+                    chat_id
+                );
+                self.0
+                    .state
+                    .write()
+                    .unwrap()
+                    .secret_resends
+                    .entry(chat_id)
+                    .and_modify(|(end, _)| *end = end_seq_no)
+                    .or_insert((end_seq_no, Instant::now()));
+                self.send_secret_action(
+                    chat_id,
+                    Action::Resend {
+                        start_seq_no,
+                        end_seq_no,
+                    },
+                )
+                .await?;
+                return Ok(None);
+            }
+            Sequence::Waiting => {
+                log::debug!("ignoring secret chat message while waiting for missing ones");
+                return Ok(None);
+            }
+            Sequence::Abort => {
+                log::warn!("missing messages in secret chat {chat_id} were not resent");
+                self.0
+                    .state
+                    .write()
+                    .unwrap()
+                    .secret_resends
+                    .remove(&chat_id);
+                self.invoke(&tl::functions::messages::DiscardEncryption {
+                    delete_history: false,
+                    chat_id,
+                })
+                .await?;
+                self.0.config.session.remove_secret_chat(chat_id);
+                save_session(&self.0.config).await;
+                return Err(SecretChatError::MessagesLost);
+            }
+        }
+        self.modify_secret_chat(chat_id, |state| {
+            state.received = layer.out_seq_no / 2 + 1;
+            state.key_uses += 1;
+            state.layer = layer.layer;
+        });
+        save_session(&self.0.config).await;
+
+        match layer.message {
+            DecryptedMessage::Message(message) => {
+                self.maybe_rekey(chat_id).await;
+                Ok(Some(Update::SecretMessage(SecretMessage {
+                    chat: SecretChat {
+                        id: chat_id,
+                        access_hash: state.access_hash,
+                        user_id: state.user_id,
+                        outgoing: state.originator,
+                        status: SecretChatStatus::Ready,
+                        g_a: None,
+                        client: self.clone(),
+                        chats,
+                    },
+                    outgoing: false,
+                    random_id: message.random_id,
+                    date,
+                    silent: message.silent,
+                    ttl: message.ttl,
+                    text: message.message,
+                    entities: message.entities,
+                    has_media: message.has_media,
+                    reply_to_random_id: message.reply_to_random_id,
+                })))
+            }
+            DecryptedMessage::Service { action, .. } => {
+                self.handle_secret_action(chat_id, action).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn handle_secret_action(&self, id: i32, action: Action) -> Result<(), SecretChatError> {
+        match action {
+            Action::NotifyLayer { layer } => {
+                self.modify_secret_chat(id, |state| state.layer = layer);
+            }
+            Action::RequestKey { exchange_id, g_a } => {
+                // If both users request a new key at the same time, the larger identifier wins.
+                let ours = self
+                    .0
+                    .config
+                    .session
+                    .secret_chat(id)
+                    .and_then(|state| state.exchange_id);
+                if ours.is_some_and(|ours| ours > exchange_id) {
+                    return Ok(());
+                }
+
+                let (p, g, random) = self.get_dh_config().await?;
+                let exchange =
+                    crypto::generate_key_pair(&p, g, &random).and_then(|(private_key, g_b)| {
+                        Some((g_b, crypto::compute_key(&p, g, &g_a, &private_key)?))
+                    });
+                match exchange {
+                    Some((g_b, key)) => {
+                        self.modify_secret_chat(id, |state| {
+                            state.exchange_id = Some(exchange_id);
+                            state.exchange_private_key = None;
+                            state.exchange_key = Some(key.to_bytes().to_vec());
+                        });
+                        self.send_secret_action(
+                            id,
+                            Action::AcceptKey {
+                                exchange_id,
+                                g_b: g_b.to_vec(),
+                                key_fingerprint: crypto::key_fingerprint(&key),
+                            },
+                        )
+                        .await?;
+                    }
+                    None => {
+                        self.send_secret_action(id, Action::AbortKey { exchange_id })
+                            .await?
+                    }
+                }
+            }
+            Action::AcceptKey {
+                exchange_id,
+                g_b,
+                key_fingerprint,
+            } => {
+                let private_key = self
+                    .0
+                    .config
+                    .session
+                    .secret_chat(id)
+                    .filter(|state| state.exchange_id == Some(exchange_id))
+                    .and_then(|state| state.exchange_private_key);
+                let private_key = match private_key {
+                    Some(private_key) => private_key,
+                    None => return Ok(()),
+                };
+
+                let (p, g, _) = self.get_dh_config().await?;
+                match crypto::compute_key(&p, g, &g_b, &private_key)
+                    .filter(|key| crypto::key_fingerprint(key) == key_fingerprint)
+                {
+                    Some(key) => {
+                        // The commit is still sent with the old key, and the new one is used
+                        // from then on.
+                        self.send_secret_action(
+                            id,
+                            Action::CommitKey {
+                                exchange_id,
+                                key_fingerprint,
+                            },
+                        )
+                        .await?;
+                        self.modify_secret_chat(id, |state| {
+                            state.previous_key = state.key.take();
+                            state.key = Some(key.to_bytes().to_vec());
+                            state.key_uses = 0;
+                            state.exchange_id = None;
+                            state.exchange_private_key = None;
+                        });
+                    }
+                    None => {
+                        self.abort_exchange(id);
+                        self.send_secret_action(id, Action::AbortKey { exchange_id })
+                            .await?;
+                    }
+                }
+            }
+            Action::CommitKey {
+                exchange_id,
+                key_fingerprint,
+            } => {
+                let committed = self
+                    .modify_secret_chat(id, |state| {
+                        let key = state.exchange_key.as_deref().and_then(auth_key);
+                        let valid = state.exchange_id == Some(exchange_id)
+                            && key.is_some_and(|key| {
+                                crypto::key_fingerprint(&key) == key_fingerprint
+                            });
+                        if valid {
+                            state.previous_key = state.key.take();
+                            state.key = state.exchange_key.take();
+                            state.key_uses = 0;
+                            state.exchange_id = None;
+                        }
+                        valid
+                    })
+                    .unwrap_or(false);
+
+                if committed {
+                    self.send_secret_action(id, Action::Noop).await?;
+                } else {
+                    self.abort_exchange(id);
+                    self.send_secret_action(id, Action::AbortKey { exchange_id })
+                        .await?;
+                }
+            }
+            Action::AbortKey { exchange_id } => {
+                if self
+                    .0
+                    .config
+                    .session
+                    .secret_chat(id)
+                    .is_some_and(|state| state.exchange_id == Some(exchange_id))
+                {
+                    self.abort_exchange(id);
+                }
+            }
+            action => log::debug!("ignoring secret chat action: {:?}", action),
+        }
+        save_session(&self.0.config).await;
+        Ok(())
+    }
+
+    fn abort_exchange(&self, id: i32) {
+        self.modify_secret_chat(id, |state| {
+            state.exchange_id = None;
+            state.exchange_private_key = None;
+            state.exchange_key = None;
+        });
+    }
+
+    /// Start exchanging a new key if the current one has been used for too many messages.
+    ///
+    /// Failing to start the exchange is not fatal, as it will be retried with the next message.
+    async fn maybe_rekey(&self, id: i32) {
+        let needed = self.0.config.session.secret_chat(id).is_some_and(|state| {
+            state.key.is_some()
+                && state.exchange_id.is_none()
+                && state.key_uses >= REKEY_AFTER_MESSAGES
+        });
+        if !needed {
+            return;
+        }
+
+        let result = async {
+            let (p, g, random) = self.get_dh_config().await?;
+            let (private_key, g_a) = crypto::generate_key_pair(&p, g, &random)
+                .ok_or(SecretChatError::KeyExchangeFailed)?;
+            let exchange_id = generate_random_id();
+            self.modify_secret_chat(id, |state| {
+                state.exchange_id = Some(exchange_id);
+                state.exchange_private_key = Some(private_key.to_vec());
+                state.exchange_key = None;
+            });
+            self.send_secret_action(
+                id,
+                Action::RequestKey {
+                    exchange_id,
+                    g_a: g_a.to_vec(),
+                },
+            )
+            .await
+        }
+        .await;
+
+        if let Err(e) = result {
+            log::warn!("failed to exchange a new key for secret chat {}: {}", id, e);
+            self.abort_exchange(id);
+        }
+    }
+}
+
+/// What to do with a message received in a secret chat, based on its sequence number.
+#[derive(Debug, PartialEq)]
+enum Sequence {
+    /// The message was already received.
+    Repeated,
+    /// The message is the one which was expected next.
+    Next,
+    /// Messages before this one are missing, so they must be resent.
+    Resend { start_seq_no: i32, end_seq_no: i32 },
+    /// Messages before this one are missing, and they were already requested to be resent.
+    Waiting,
+    /// The missing messages were not resent in time, so the chat must be discarded.
+    Abort,
+}
+
+// When there is a gap in the sequence numbers, the missing messages must be requested with
+// `decryptedMessageActionResend`, and the chat aborted if they don't arrive in a reasonable time.
+//
+// `resend` is the last `seq_no` requested to be resent, along with when it was first requested.
+fn check_sequence(
+    expected_seq_no: i32,
+    out_seq_no: i32,
+    resend: Option<(i32, Instant)>,
+) -> Sequence {
+    if out_seq_no < expected_seq_no {
+        return Sequence::Repeated;
+    } else if out_seq_no == expected_seq_no {
+        return Sequence::Next;
+    }
+
+    match resend {
+        Some((_, requested)) if requested.elapsed() >= RESEND_TIMEOUT => Sequence::Abort,
+        Some((end_seq_no, _)) if out_seq_no <= end_seq_no => Sequence::Waiting,
+        _ => Sequence::Resend {
+            start_seq_no: expected_seq_no,
+            end_seq_no: out_seq_no,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(originator: bool, received: i32, sent: i32) -> SecretChatState {
+        SecretChatState {
+            id: 1,
+            access_hash: 2,
+            originator,
+            user_id: 3,
+            key: None,
+            private_key: None,
+            layer: secret_layer::LAYER,
+            received,
+            sent,
+            key_uses: 0,
+            exchange_id: None,
+            exchange_private_key: None,
+            exchange_key: None,
+            previous_key: None,
+        }
+    }
+
+    #[test]
+    fn seq_numbers_have_the_parity_of_the_sender() {
+        // The creator sends odd numbers and expects even ones, and the other user the opposite.
+        assert_eq!(next_seq_numbers(&state(true, 0, 0)), (0, 1));
+        assert_eq!(next_seq_numbers(&state(true, 2, 3)), (4, 7));
+        assert_eq!(next_seq_numbers(&state(false, 0, 0)), (1, 0));
+        assert_eq!(next_seq_numbers(&state(false, 2, 3)), (5, 6));
+    }
+
+    #[test]
+    fn missing_messages_are_requested_once() {
+        let now = Instant::now();
+        assert_eq!(check_sequence(5, 3, None), Sequence::Repeated);
+        assert_eq!(check_sequence(5, 5, None), Sequence::Next);
+        assert_eq!(
+            check_sequence(5, 9, None),
+            Sequence::Resend {
+                start_seq_no: 5,
+                end_seq_no: 9
+            }
+        );
+        assert_eq!(check_sequence(5, 7, Some((9, now))), Sequence::Waiting);
+        assert_eq!(check_sequence(5, 5, Some((9, now))), Sequence::Next);
+        assert_eq!(
+            check_sequence(5, 11, Some((9, now))),
+            Sequence::Resend {
+                start_seq_no: 5,
+                end_seq_no: 11
+            }
+        );
+    }
+
+    #[test]
+    fn missing_messages_not_resent_in_time_abort() {
+        let requested = Instant::now() - RESEND_TIMEOUT;
+        assert_eq!(check_sequence(5, 7, Some((9, requested))), Sequence::Abort);
+        assert_eq!(check_sequence(5, 5, Some((9, requested))), Sequence::Next);
+    }
+}
@@ -0,0 +1,552 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The end-to-end encrypted layer of secret chats.
+//!
+//! These definitions are not part of the API schema, so the small subset needed for text
+//! messages and key exchanges is (de)serialized by hand. Constructors which are not understood
+//! are skipped over where possible, so that the rest of the message can still be used.
+use grammers_tl_types::deserialize::{Buffer, Error, Result};
+use grammers_tl_types::{self as tl, Deserializable, Serializable};
+
+/// The layer of the secret chat schema understood by this module.
+pub(crate) const LAYER: i32 = 101;
+
+const MESSAGE_LAYER: u32 = 0x1be31789;
+const MESSAGE: u32 = 0x91cc4674;
+const MESSAGE_SERVICE: u32 = 0x73164160;
+const MEDIA_EMPTY: u32 = 0x089f5c4a;
+
+const ACTION_SET_MESSAGE_TTL: u32 = 0xa1733aec;
+const ACTION_READ_MESSAGES: u32 = 0x0c4f40be;
+const ACTION_DELETE_MESSAGES: u32 = 0x65614304;
+const ACTION_SCREENSHOT_MESSAGES: u32 = 0x8ac1f475;
+const ACTION_FLUSH_HISTORY: u32 = 0x6719e45c;
+const ACTION_RESEND: u32 = 0x511110b0;
+const ACTION_NOTIFY_LAYER: u32 = 0xf3048883;
+const ACTION_TYPING: u32 = 0xccb27641;
+const ACTION_REQUEST_KEY: u32 = 0xf3c9611b;
+const ACTION_ACCEPT_KEY: u32 = 0x6fe1735b;
+const ACTION_ABORT_KEY: u32 = 0xdd05ec6b;
+const ACTION_COMMIT_KEY: u32 = 0xec2e0b9b;
+const ACTION_NOOP: u32 = 0xa82fdd63;
+
+const ENTITY_UNKNOWN: u32 = 0xbb92ba95;
+const ENTITY_MENTION: u32 = 0xfa04579d;
+const ENTITY_HASHTAG: u32 = 0x6f635b0d;
+const ENTITY_BOT_COMMAND: u32 = 0x6cef8ac7;
+const ENTITY_URL: u32 = 0x6ed02538;
+const ENTITY_EMAIL: u32 = 0x64e475c2;
+const ENTITY_BOLD: u32 = 0xbd610bc9;
+const ENTITY_ITALIC: u32 = 0x826f8b60;
+const ENTITY_CODE: u32 = 0x28a20571;
+const ENTITY_PRE: u32 = 0x73924be0;
+const ENTITY_TEXT_URL: u32 = 0x76a6d327;
+const ENTITY_MENTION_NAME: u32 = 0x352dca58;
+const ENTITY_UNDERLINE: u32 = 0x9c4e7e8b;
+const ENTITY_STRIKE: u32 = 0xbf0693d4;
+const ENTITY_BLOCKQUOTE: u32 = 0x020df5d0;
+
+/// `decryptedMessageLayer`, which wraps every message sent in a secret chat.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct MessageLayer {
+    pub random_bytes: Vec<u8>,
+    pub layer: i32,
+    pub in_seq_no: i32,
+    pub out_seq_no: i32,
+    pub message: DecryptedMessage,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum DecryptedMessage {
+    Message(PlainMessage),
+    Service { random_id: i64, action: Action },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct PlainMessage {
+    pub silent: bool,
+    pub random_id: i64,
+    pub ttl: i32,
+    pub message: String,
+    /// Whether the message had media, which is not understood (and whatever came after it in
+    /// the message, such as the formatting entities, is lost).
+    pub has_media: bool,
+    pub entities: Vec<tl::enums::MessageEntity>,
+    pub reply_to_random_id: Option<i64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Action {
+    SetMessageTtl {
+        ttl_seconds: i32,
+    },
+    ReadMessages {
+        random_ids: Vec<i64>,
+    },
+    DeleteMessages {
+        random_ids: Vec<i64>,
+    },
+    ScreenshotMessages {
+        random_ids: Vec<i64>,
+    },
+    FlushHistory,
+    Resend {
+        start_seq_no: i32,
+        end_seq_no: i32,
+    },
+    NotifyLayer {
+        layer: i32,
+    },
+    /// The typing action itself is not kept, as it may use constructors from old layers.
+    Typing,
+    RequestKey {
+        exchange_id: i64,
+        g_a: Vec<u8>,
+    },
+    AcceptKey {
+        exchange_id: i64,
+        g_b: Vec<u8>,
+        key_fingerprint: i64,
+    },
+    AbortKey {
+        exchange_id: i64,
+    },
+    CommitKey {
+        exchange_id: i64,
+        key_fingerprint: i64,
+    },
+    Noop,
+}
+
+impl MessageLayer {
+    pub fn serialize(&self, buf: &mut impl Extend<u8>) {
+        MESSAGE_LAYER.serialize(buf);
+        self.random_bytes.serialize(buf);
+        self.layer.serialize(buf);
+        self.in_seq_no.serialize(buf);
+        self.out_seq_no.serialize(buf);
+        match &self.message {
+            DecryptedMessage::Message(message) => message.serialize(buf),
+            DecryptedMessage::Service { random_id, action } => {
+                MESSAGE_SERVICE.serialize(buf);
+                random_id.serialize(buf);
+                action.serialize(buf);
+            }
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        self.serialize(&mut buffer);
+        buffer
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let buf = &mut tl::deserialize::Cursor::from_slice(data);
+        expect_constructor(buf, MESSAGE_LAYER)?;
+        let random_bytes = Vec::<u8>::deserialize(buf)?;
+        let layer = i32::deserialize(buf)?;
+        let in_seq_no = i32::deserialize(buf)?;
+        let out_seq_no = i32::deserialize(buf)?;
+        let message = match u32::deserialize(buf)? {
+            MESSAGE => DecryptedMessage::Message(PlainMessage::deserialize(buf)?),
+            MESSAGE_SERVICE => DecryptedMessage::Service {
+                random_id: i64::deserialize(buf)?,
+                action: Action::deserialize(buf)?,
+            },
+            id => return Err(Error::UnexpectedConstructor { id }),
+        };
+        Ok(Self {
+            random_bytes,
+            layer,
+            in_seq_no,
+            out_seq_no,
+            message,
+        })
+    }
+}
+
+impl PlainMessage {
+    fn serialize(&self, buf: &mut impl Extend<u8>) {
+        let entities = self
+            .entities
+            .iter()
+            .filter(|entity| entity_constructor(entity).is_some())
+            .collect::<Vec<_>>();
+        let flags = if self.silent { 1 << 5 } else { 0 }
+            | if entities.is_empty() { 0 } else { 1 << 7 }
+            | if self.reply_to_random_id.is_some() {
+                1 << 3
+            } else {
+                0
+            };
+
+        MESSAGE.serialize(buf);
+        (flags as u32).serialize(buf);
+        self.random_id.serialize(buf);
+        self.ttl.serialize(buf);
+        self.message.serialize(buf);
+        if !entities.is_empty() {
+            0x1cb5c415u32.serialize(buf);
+            (entities.len() as i32).serialize(buf);
+            entities
+                .into_iter()
+                .for_each(|entity| serialize_entity(entity, buf));
+        }
+        if let Some(random_id) = self.reply_to_random_id {
+            random_id.serialize(buf);
+        }
+    }
+
+    fn deserialize(buf: Buffer) -> Result<Self> {
+        let flags = u32::deserialize(buf)?;
+        let mut message = Self {
+            silent: flags & (1 << 5) != 0,
+            random_id: i64::deserialize(buf)?,
+            ttl: i32::deserialize(buf)?,
+            message: String::deserialize(buf)?,
+            has_media: false,
+            entities: Vec::new(),
+            reply_to_random_id: None,
+        };
+
+        if flags & (1 << 9) != 0 && u32::deserialize(buf)? != MEDIA_EMPTY {
+            message.has_media = true;
+            return Ok(message);
+        }
+        if flags & (1 << 7) != 0 {
+            expect_constructor(buf, 0x1cb5c415)?;
+            let len = i32::deserialize(buf)?;
+            for _ in 0..len {
+                match deserialize_entity(buf)? {
+                    Some(entity) => message.entities.push(entity),
+                    // Unknown entities have unknown length, so nothing after them can be read.
+                    None => return Ok(message),
+                }
+            }
+        }
+        if flags & (1 << 11) != 0 {
+            String::deserialize(buf)?;
+        }
+        if flags & (1 << 3) != 0 {
+            message.reply_to_random_id = Some(i64::deserialize(buf)?);
+        }
+        Ok(message)
+    }
+}
+
+impl Action {
+    fn serialize(&self, buf: &mut impl Extend<u8>) {
+        match self {
+            Self::SetMessageTtl { ttl_seconds } => {
+                ACTION_SET_MESSAGE_TTL.serialize(buf);
+                ttl_seconds.serialize(buf);
+            }
+            Self::ReadMessages { random_ids } => {
+                ACTION_READ_MESSAGES.serialize(buf);
+                random_ids.serialize(buf);
+            }
+            Self::DeleteMessages { random_ids } => {
+                ACTION_DELETE_MESSAGES.serialize(buf);
+                random_ids.serialize(buf);
+            }
+            Self::ScreenshotMessages { random_ids } => {
+                ACTION_SCREENSHOT_MESSAGES.serialize(buf);
+                random_ids.serialize(buf);
+            }
+            Self::FlushHistory => ACTION_FLUSH_HISTORY.serialize(buf),
+            Self::Resend {
+                start_seq_no,
+                end_seq_no,
+            } => {
+                ACTION_RESEND.serialize(buf);
+                start_seq_no.serialize(buf);
+                end_seq_no.serialize(buf);
+            }
+            Self::NotifyLayer { layer } => {
+                ACTION_NOTIFY_LAYER.serialize(buf);
+                layer.serialize(buf);
+            }
+            Self::Typing => {
+                ACTION_TYPING.serialize(buf);
+                tl::enums::SendMessageAction::SendMessageTypingAction.serialize(buf);
+            }
+            Self::RequestKey { exchange_id, g_a } => {
+                ACTION_REQUEST_KEY.serialize(buf);
+                exchange_id.serialize(buf);
+                g_a.serialize(buf);
+            }
+            Self::AcceptKey {
+                exchange_id,
+                g_b,
+                key_fingerprint,
+            } => {
+                ACTION_ACCEPT_KEY.serialize(buf);
+                exchange_id.serialize(buf);
+                g_b.serialize(buf);
+                key_fingerprint.serialize(buf);
+            }
+            Self::AbortKey { exchange_id } => {
+                ACTION_ABORT_KEY.serialize(buf);
+                exchange_id.serialize(buf);
+            }
+            Self::CommitKey {
+                exchange_id,
+                key_fingerprint,
+            } => {
+                ACTION_COMMIT_KEY.serialize(buf);
+                exchange_id.serialize(buf);
+                key_fingerprint.serialize(buf);
+            }
+            Self::Noop => ACTION_NOOP.serialize(buf),
+        }
+    }
+
+    fn deserialize(buf: Buffer) -> Result<Self> {
+        Ok(match u32::deserialize(buf)? {
+            ACTION_SET_MESSAGE_TTL => Self::SetMessageTtl {
+                ttl_seconds: i32::deserialize(buf)?,
+            },
+            ACTION_READ_MESSAGES => Self::ReadMessages {
+                random_ids: Vec::<i64>::deserialize(buf)?,
+            },
+            ACTION_DELETE_MESSAGES => Self::DeleteMessages {
+                random_ids: Vec::<i64>::deserialize(buf)?,
+            },
+            ACTION_SCREENSHOT_MESSAGES => Self::ScreenshotMessages {
+                random_ids: Vec::<i64>::deserialize(buf)?,
+            },
+            ACTION_FLUSH_HISTORY => Self::FlushHistory,
+            ACTION_RESEND => Self::Resend {
+                start_seq_no: i32::deserialize(buf)?,
+                end_seq_no: i32::deserialize(buf)?,
+            },
+            ACTION_NOTIFY_LAYER => Self::NotifyLayer {
+                layer: i32::deserialize(buf)?,
+            },
+            ACTION_TYPING => Self::Typing,
+            ACTION_REQUEST_KEY => Self::RequestKey {
+                exchange_id: i64::deserialize(buf)?,
+                g_a: Vec::<u8>::deserialize(buf)?,
+            },
+            ACTION_ACCEPT_KEY => Self::AcceptKey {
+                exchange_id: i64::deserialize(buf)?,
+                g_b: Vec::<u8>::deserialize(buf)?,
+                key_fingerprint: i64::deserialize(buf)?,
+            },
+            ACTION_ABORT_KEY => Self::AbortKey {
+                exchange_id: i64::deserialize(buf)?,
+            },
+            ACTION_COMMIT_KEY => Self::CommitKey {
+                exchange_id: i64::deserialize(buf)?,
+                key_fingerprint: i64::deserialize(buf)?,
+            },
+            ACTION_NOOP => Self::Noop,
+            id => return Err(Error::UnexpectedConstructor { id }),
+        })
+    }
+}
+
+fn expect_constructor(buf: Buffer, expected: u32) -> Result<()> {
+    match u32::deserialize(buf)? {
+        id if id == expected => Ok(()),
+        id => Err(Error::UnexpectedConstructor { id }),
+    }
+}
+
+/// The constructor of the secret chat entity equivalent to the given API entity, if any.
+fn entity_constructor(entity: &tl::enums::MessageEntity) -> Option<u32> {
+    use tl::enums::MessageEntity as E;
+
+    Some(match entity {
+        E::Unknown(_) => ENTITY_UNKNOWN,
+        E::Mention(_) => ENTITY_MENTION,
+        E::Hashtag(_) => ENTITY_HASHTAG,
+        E::BotCommand(_) => ENTITY_BOT_COMMAND,
+        E::Url(_) => ENTITY_URL,
+        E::Email(_) => ENTITY_EMAIL,
+        E::Bold(_) => ENTITY_BOLD,
+        E::Italic(_) => ENTITY_ITALIC,
+        E::Code(_) => ENTITY_CODE,
+        E::Pre(_) => ENTITY_PRE,
+        E::TextUrl(_) => ENTITY_TEXT_URL,
+        E::MentionName(e) if i32::try_from(e.user_id).is_ok() => ENTITY_MENTION_NAME,
+        E::Underline(_) => ENTITY_UNDERLINE,
+        E::Strike(_) => ENTITY_STRIKE,
+        E::Blockquote(_) => ENTITY_BLOCKQUOTE,
+        _ => return None,
+    })
+}
+
+fn serialize_entity(entity: &tl::enums::MessageEntity, buf: &mut impl Extend<u8>) {
+    use tl::enums::MessageEntity as E;
+
+    if let Some(constructor) = entity_constructor(entity) {
+        constructor.serialize(buf);
+        entity.offset().serialize(buf);
+        entity.length().serialize(buf);
+        match entity {
+            E::Pre(e) => e.language.serialize(buf),
+            E::TextUrl(e) => e.url.serialize(buf),
+            E::MentionName(e) => (e.user_id as i32).serialize(buf),
+            _ => {}
+        }
+    }
+}
+
+/// Read an entity, or `None` if its constructor is not known.
+fn deserialize_entity(buf: Buffer) -> Result<Option<tl::enums::MessageEntity>> {
+    use tl::types as t;
+
+    let constructor = u32::deserialize(buf)?;
+    let known = [
+        ENTITY_UNKNOWN,
+        ENTITY_MENTION,
+        ENTITY_HASHTAG,
+        ENTITY_BOT_COMMAND,
+        ENTITY_URL,
+        ENTITY_EMAIL,
+        ENTITY_BOLD,
+        ENTITY_ITALIC,
+        ENTITY_CODE,
+        ENTITY_PRE,
+        ENTITY_TEXT_URL,
+        ENTITY_MENTION_NAME,
+        ENTITY_UNDERLINE,
+        ENTITY_STRIKE,
+        ENTITY_BLOCKQUOTE,
+    ];
+    if !known.contains(&constructor) {
+        return Ok(None);
+    }
+
+    let offset = i32::deserialize(buf)?;
+    let length = i32::deserialize(buf)?;
+    Ok(Some(match constructor {
+        ENTITY_UNKNOWN => t::MessageEntityUnknown { offset, length }.into(),
+        ENTITY_MENTION => t::MessageEntityMention { offset, length }.into(),
+        ENTITY_HASHTAG => t::MessageEntityHashtag { offset, length }.into(),
+        ENTITY_BOT_COMMAND => t::MessageEntityBotCommand { offset, length }.into(),
+        ENTITY_URL => t::MessageEntityUrl { offset, length }.into(),
+        ENTITY_EMAIL => t::MessageEntityEmail { offset, length }.into(),
+        ENTITY_BOLD => t::MessageEntityBold { offset, length }.into(),
+        ENTITY_ITALIC => t::MessageEntityItalic { offset, length }.into(),
+        ENTITY_CODE => t::MessageEntityCode { offset, length }.into(),
+        ENTITY_PRE => t::MessageEntityPre {
+            offset,
+            length,
+            language: String::deserialize(buf)?,
+        }
+        .into(),
+        ENTITY_TEXT_URL => t::MessageEntityTextUrl {
+            offset,
+            length,
+            url: String::deserialize(buf)?,
+        }
+        .into(),
+        ENTITY_MENTION_NAME => t::MessageEntityMentionName {
+            offset,
+            length,
+            user_id: i32::deserialize(buf)? as i64,
+        }
+        .into(),
+        ENTITY_UNDERLINE => t::MessageEntityUnderline { offset, length }.into(),
+        ENTITY_STRIKE => t::MessageEntityStrike { offset, length }.into(),
+        _ => t::MessageEntityBlockquote {
+            collapsed: false,
+            offset,
+            length,
+        }
+        .into(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_round_trips() {
+        let layer = MessageLayer {
+            random_bytes: vec![1; 15],
+            layer: LAYER,
+            in_seq_no: 2,
+            out_seq_no: 3,
+            message: DecryptedMessage::Message(PlainMessage {
+                silent: true,
+                random_id: 4,
+                ttl: 0,
+                message: "hello".to_string(),
+                has_media: false,
+                entities: vec![
+                    tl::types::MessageEntityBold {
+                        offset: 0,
+                        length: 5,
+                    }
+                    .into(),
+                    tl::types::MessageEntityPre {
+                        offset: 1,
+                        length: 2,
+                        language: "rust".to_string(),
+                    }
+                    .into(),
+                ],
+                reply_to_random_id: Some(5),
+            }),
+        };
+
+        assert_eq!(MessageLayer::from_bytes(&layer.to_bytes()), Ok(layer));
+    }
+
+    #[test]
+    fn service_message_round_trips() {
+        let layer = MessageLayer {
+            random_bytes: vec![1; 15],
+            layer: LAYER,
+            in_seq_no: 0,
+            out_seq_no: 1,
+            message: DecryptedMessage::Service {
+                random_id: 2,
+                action: Action::AcceptKey {
+                    exchange_id: 3,
+                    g_b: vec![4; 256],
+                    key_fingerprint: 5,
+                },
+            },
+        };
+
+        assert_eq!(MessageLayer::from_bytes(&layer.to_bytes()), Ok(layer));
+    }
+
+    #[test]
+    fn media_is_skipped() {
+        let mut data = Vec::new();
+        MESSAGE_LAYER.serialize(&mut data);
+        vec![1u8; 15].serialize(&mut data);
+        LAYER.serialize(&mut data);
+        0i32.serialize(&mut data);
+        1i32.serialize(&mut data);
+        MESSAGE.serialize(&mut data);
+        ((1u32 << 9) | (1 << 7)).serialize(&mut data);
+        2i64.serialize(&mut data);
+        0i32.serialize(&mut data);
+        "caption".to_string().serialize(&mut data);
+        // decryptedMessageMediaGeoPoint, followed by data that is not understood.
+        0x35480a59u32.serialize(&mut data);
+        data.extend([0xff; 20]);
+
+        match MessageLayer::from_bytes(&data).unwrap().message {
+            DecryptedMessage::Message(message) => {
+                assert_eq!(message.message, "caption");
+                assert!(message.has_media);
+            }
+            message => panic!("unexpected message: {message:?}"),
+        }
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Methods related to the statistics of channels and their posts.
+
+use super::chats::local_error;
+use super::Client;
+use crate::types::{BroadcastStats, MegagroupStats, MessageStats, StatsGraph};
+pub use grammers_mtsender::InvocationError;
+use grammers_session::PackedChat;
+use grammers_tl_types as tl;
+
+/// Method implementations related to the statistics of channels.
+impl Client {
+    /// Invoke a request which must be sent to the datacenter where the statistics live.
+    ///
+    /// Telegram answers with `STATS_MIGRATE_X` if the request was sent to the wrong datacenter,
+    /// in which case the request is sent again to datacenter `X`.
+    async fn invoke_stats<R: tl::RemoteCall>(
+        &self,
+        request: &R,
+    ) -> Result<R::Return, InvocationError> {
+        match self.invoke(request).await {
+            Err(InvocationError::Rpc(err)) if err.is("STATS_MIGRATE") => {
+                self.invoke_in_dc(request, err.value.unwrap() as i32).await
+            }
+            result => result,
+        }
+    }
+
+    /// Get the statistics of a broadcast channel.
+    ///
+    /// Statistics are only available to administrators of large enough channels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(channel: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let stats = client.get_broadcast_stats(&channel).await?;
+    /// println!("The channel has {} followers", stats.followers().current);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_broadcast_stats<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+    ) -> Result<BroadcastStats, InvocationError> {
+        let channel = chat
+            .into()
+            .try_to_input_channel()
+            .ok_or_else(|| local_error("CHANNEL_INVALID"))?;
+
+        let tl::enums::stats::BroadcastStats::Stats(raw) = self
+            .invoke_stats(&tl::functions::stats::GetBroadcastStats {
+                dark: false,
+                channel,
+            })
+            .await?;
+
+        Ok(BroadcastStats { raw })
+    }
+
+    /// Get the statistics of a supergroup.
+    ///
+    /// Statistics are only available to administrators of large enough groups.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(group: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let stats = client.get_megagroup_stats(&group).await?;
+    /// println!("{} messages were sent", stats.messages().current);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_megagroup_stats<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+    ) -> Result<MegagroupStats, InvocationError> {
+        let channel = chat
+            .into()
+            .try_to_input_channel()
+            .ok_or_else(|| local_error("CHANNEL_INVALID"))?;
+
+        let tl::enums::stats::MegagroupStats::Stats(raw) = self
+            .invoke_stats(&tl::functions::stats::GetMegagroupStats {
+                dark: false,
+                channel,
+            })
+            .await?;
+
+        {
+            let mut state = self.0.state.write().unwrap();
+            // Telegram can return peers without hash (e.g. Users with 'min: true')
+            let _ = state.chat_hashes.extend(&raw.users, &[]);
+        }
+
+        Ok(MegagroupStats { raw })
+    }
+
+    /// Get the statistics of a single post in a broadcast channel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(channel: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let stats = client.get_message_stats(&channel, 123).await?;
+    /// dbg!(stats.views_graph());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_message_stats<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+        message_id: i32,
+    ) -> Result<MessageStats, InvocationError> {
+        let channel = chat
+            .into()
+            .try_to_input_channel()
+            .ok_or_else(|| local_error("CHANNEL_INVALID"))?;
+
+        let tl::enums::stats::MessageStats::Stats(raw) = self
+            .invoke_stats(&tl::functions::stats::GetMessageStats {
+                dark: false,
+                channel,
+                msg_id: message_id,
+            })
+            .await?;
+
+        Ok(MessageStats { raw })
+    }
+
+    /// Load a graph which was not included in the statistics right away, or zoom into one.
+    ///
+    /// The token is the one found in [`StatsGraph::Async`], or the zoom token in
+    /// [`StatsGraph::Loaded`]. When zooming, `x` is the point in time to zoom into, as found in
+    /// the graph data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(channel: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::types::StatsGraph;
+    ///
+    /// let stats = client.get_broadcast_stats(&channel).await?;
+    /// let graph = match stats.languages_graph() {
+    ///     StatsGraph::Async { token } => client.load_async_graph(&token, None).await?,
+    ///     graph => graph,
+    /// };
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn load_async_graph(
+        &self,
+        token: &str,
+        x: Option<i64>,
+    ) -> Result<StatsGraph, InvocationError> {
+        let graph = self
+            .invoke_stats(&tl::functions::stats::LoadAsyncGraph {
+                token: token.to_string(),
+                x,
+            })
+            .await?;
+
+        Ok(StatsGraph::from_raw(&graph))
+    }
+}
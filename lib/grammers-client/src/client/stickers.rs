@@ -0,0 +1,130 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Methods related to stickers and sticker sets.
+
+use super::Client;
+use crate::types::{IterBuffer, StickerSet, StickerSetInfo, StickerSetRef};
+pub use grammers_mtsender::InvocationError;
+use grammers_tl_types as tl;
+
+pub type InstalledStickerSetIter =
+    IterBuffer<tl::functions::messages::GetAllStickers, StickerSetInfo>;
+
+impl InstalledStickerSetIter {
+    fn new(client: &Client) -> Self {
+        Self::from_request(
+            client,
+            0,
+            tl::functions::messages::GetAllStickers { hash: 0 },
+        )
+    }
+
+    /// Return the next `StickerSetInfo` from the internal buffer, filling the buffer previously
+    /// if it's empty.
+    ///
+    /// Returns `None` if the `limit` is reached or there are no sticker sets left.
+    pub async fn next(&mut self) -> Result<Option<StickerSetInfo>, InvocationError> {
+        if let Some(result) = self.next_raw() {
+            return result;
+        }
+
+        // The sets are only fetched again if they changed since the last time.
+        self.request.hash = {
+            let state = self.client.0.state.read().unwrap();
+            state
+                .installed_sticker_sets
+                .as_ref()
+                .map(|(hash, _)| *hash)
+                .unwrap_or(0)
+        };
+
+        let sets = match self.client.invoke(&self.request).await? {
+            tl::enums::messages::AllStickers::Stickers(all) => {
+                let mut state = self.client.0.state.write().unwrap();
+                state.installed_sticker_sets = Some((all.hash, all.sets.clone()));
+                all.sets
+            }
+            tl::enums::messages::AllStickers::NotModified => {
+                let state = self.client.0.state.read().unwrap();
+                state
+                    .installed_sticker_sets
+                    .as_ref()
+                    .map(|(_, sets)| sets.clone())
+                    .unwrap_or_default()
+            }
+        };
+
+        self.last_chunk = true;
+        self.total = Some(sets.len());
+        self.buffer
+            .extend(sets.into_iter().map(StickerSetInfo::from_raw));
+
+        Ok(self.pop_item())
+    }
+}
+
+/// Method implementations related to stickers.
+impl Client {
+    /// Fetch a sticker set along with all of its stickers.
+    ///
+    /// The set can be referred to by its short name, as found in `t.me/addstickers/` links, or
+    /// by a [`StickerSetRef`], such as the one found in a received [`Sticker`].
+    ///
+    /// [`Sticker`]: crate::types::media::Sticker
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::InputMessage;
+    ///
+    /// let set = client.get_sticker_set("Animals").await?;
+    /// println!("{} has {} stickers", set.info().title(), set.info().count());
+    ///
+    /// let document = &set.documents()[0];
+    /// # let chat = client.get_me().await?;
+    /// client.send_message(&chat, InputMessage::text("").sticker(document)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_sticker_set<S: Into<StickerSetRef>>(
+        &self,
+        set: S,
+    ) -> Result<StickerSet, InvocationError> {
+        let set = self
+            .invoke(&tl::functions::messages::GetStickerSet {
+                stickerset: set.into().to_raw(),
+                hash: 0,
+            })
+            .await?;
+
+        Ok(StickerSet::from_raw(set))
+    }
+
+    /// Iterate over the sticker sets installed by the logged-in account.
+    ///
+    /// The sets are remembered by the client, and only fetched again if they changed since the
+    /// last time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut sets = client.iter_installed_sticker_sets();
+    ///
+    /// while let Some(set) = sets.next().await? {
+    ///     println!("{} ({})", set.title(), set.short_name());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn iter_installed_sticker_sets(&self) -> InstalledStickerSetIter {
+        InstalledStickerSetIter::new(self)
+    }
+}
@@ -0,0 +1,265 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Methods related to takeout sessions, used to export the data of an account.
+
+use super::Client;
+use grammers_mtsender::InvocationError;
+use grammers_tl_types as tl;
+use std::fmt;
+use std::ops::Deref;
+use std::time::Duration;
+
+/// The error type which is returned when starting or finishing a takeout session fails.
+#[derive(Debug)]
+pub enum TakeoutError {
+    /// The request can only be made inside a takeout session, or the takeout session used is no
+    /// longer valid.
+    Required,
+    /// Telegram requires some time to pass before the takeout session can be started, which the
+    /// user has to confirm from a different session.
+    ///
+    /// Starting the takeout session again after waiting this long will succeed.
+    InitDelay {
+        wait: Duration,
+    },
+    Other(InvocationError),
+}
+
+impl fmt::Display for TakeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Required => write!(f, "takeout error: a valid takeout session is required"),
+            Self::InitDelay { wait } => write!(
+                f,
+                "takeout error: the takeout session can be started in {wait:?}"
+            ),
+            Self::Other(e) => write!(f, "takeout error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for TakeoutError {}
+
+impl From<InvocationError> for TakeoutError {
+    fn from(error: InvocationError) -> Self {
+        match error {
+            InvocationError::Rpc(err) if err.is("TAKEOUT_INIT_DELAY") => Self::InitDelay {
+                wait: Duration::from_secs(err.value.unwrap_or(0) as u64),
+            },
+            e if e.is("TAKEOUT_REQUIRED") || e.is("TAKEOUT_INVALID") => Self::Required,
+            e => Self::Other(e),
+        }
+    }
+}
+
+/// Which data will be exported through a takeout session.
+///
+/// By default, only the basic information of the account can be exported. Everything else must
+/// be opted into.
+#[derive(Clone, Debug, Default)]
+pub struct TakeoutOptions {
+    contacts: bool,
+    user_messages: bool,
+    chat_messages: bool,
+    megagroup_messages: bool,
+    channel_messages: bool,
+    files: bool,
+    file_max_size: Option<i64>,
+}
+
+impl TakeoutOptions {
+    /// Create new options which export nothing besides the basic information of the account.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the contacts of the account will be exported.
+    pub fn contacts(mut self, contacts: bool) -> Self {
+        self.contacts = contacts;
+        self
+    }
+
+    /// Whether the messages in private conversations will be exported.
+    pub fn user_messages(mut self, user_messages: bool) -> Self {
+        self.user_messages = user_messages;
+        self
+    }
+
+    /// Whether the messages in small groups will be exported.
+    pub fn chat_messages(mut self, chat_messages: bool) -> Self {
+        self.chat_messages = chat_messages;
+        self
+    }
+
+    /// Whether the messages in megagroups (supergroups) will be exported.
+    pub fn megagroup_messages(mut self, megagroup_messages: bool) -> Self {
+        self.megagroup_messages = megagroup_messages;
+        self
+    }
+
+    /// Whether the messages in broadcast channels will be exported.
+    pub fn channel_messages(mut self, channel_messages: bool) -> Self {
+        self.channel_messages = channel_messages;
+        self
+    }
+
+    /// Whether files will be downloaded, only including those up to the given size in bytes,
+    /// if any.
+    pub fn files(mut self, files: bool, max_size: Option<u64>) -> Self {
+        self.files = files;
+        self.file_max_size = max_size.map(|size| size as i64);
+        self
+    }
+}
+
+/// A client whose requests are made inside a takeout session.
+///
+/// It can be used in place of the [`Client`] it was created from, including its iterators,
+/// which will also make their requests inside the takeout session. Telegram applies less strict
+/// limits to the requests made this way.
+///
+/// The session should be ended with [`TakeoutClient::finish`] once the export is done.
+#[derive(Clone)]
+pub struct TakeoutClient {
+    client: Client,
+}
+
+impl TakeoutClient {
+    /// The identifier of the takeout session.
+    pub fn id(&self) -> i64 {
+        self.client
+            .1
+            .expect("takeout client must have a takeout session")
+    }
+
+    /// End the takeout session, telling Telegram whether the export was successful.
+    pub async fn finish(self, success: bool) -> Result<(), TakeoutError> {
+        self.client
+            .invoke(&tl::functions::account::FinishTakeoutSession { success })
+            .await?;
+        Ok(())
+    }
+}
+
+impl Deref for TakeoutClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+impl fmt::Debug for TakeoutClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TakeoutClient")
+            .field("client", &self.client)
+            .field("id", &self.id())
+            .finish()
+    }
+}
+
+/// A request wrapped in `invokeWithTakeout`, without needing to own it.
+pub(crate) struct InvokeInTakeout<'a, R>(tl::functions::InvokeWithTakeout<Borrowed<'a, R>>);
+
+pub(crate) struct Borrowed<'a, R>(&'a R);
+
+impl<'a, R> InvokeInTakeout<'a, R> {
+    pub(crate) fn new(takeout_id: i64, request: &'a R) -> Self {
+        Self(tl::functions::InvokeWithTakeout {
+            takeout_id,
+            query: Borrowed(request),
+        })
+    }
+}
+
+impl<R: tl::Serializable> tl::Serializable for Borrowed<'_, R> {
+    fn serialize(&self, buf: &mut impl Extend<u8>) {
+        self.0.serialize(buf)
+    }
+}
+
+impl<R: tl::RemoteCall> tl::Serializable for InvokeInTakeout<'_, R> {
+    fn serialize(&self, buf: &mut impl Extend<u8>) {
+        self.0.serialize(buf)
+    }
+}
+
+impl<R: tl::RemoteCall> tl::RemoteCall for InvokeInTakeout<'_, R> {
+    type Return = R::Return;
+}
+
+/// Method implementations related to takeout sessions.
+impl Client {
+    /// Start a takeout session to export the data of the logged-in account.
+    ///
+    /// The returned client makes every request inside the takeout session, which Telegram
+    /// limits less strictly than the regular requests.
+    ///
+    /// Telegram may require some time to pass before the session can be started, in which case
+    /// [`TakeoutError::InitDelay`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::TakeoutOptions;
+    ///
+    /// let takeout = client
+    ///     .begin_takeout(TakeoutOptions::new().channel_messages(true))
+    ///     .await?;
+    ///
+    /// let mut messages = takeout.iter_messages(&chat);
+    /// while let Some(message) = messages.next().await? {
+    ///     println!("{}", message.text());
+    /// }
+    ///
+    /// takeout.finish(true).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn begin_takeout(
+        &self,
+        options: TakeoutOptions,
+    ) -> Result<TakeoutClient, TakeoutError> {
+        let tl::enums::account::Takeout::Takeout(takeout) = self
+            .invoke(&tl::functions::account::InitTakeoutSession {
+                contacts: options.contacts,
+                message_users: options.user_messages,
+                message_chats: options.chat_messages,
+                message_megagroups: options.megagroup_messages,
+                message_channels: options.channel_messages,
+                files: options.files,
+                file_max_size: options.file_max_size,
+            })
+            .await?;
+
+        Ok(TakeoutClient {
+            client: Client(self.0.clone(), Some(takeout.id)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tl::Serializable;
+
+    #[test]
+    fn check_takeout_wrapping() {
+        let request = tl::functions::Ping { ping_id: 1 };
+        let expected = tl::functions::InvokeWithTakeout {
+            takeout_id: 7,
+            query: request.clone(),
+        };
+        assert_eq!(
+            InvokeInTakeout::new(7, &request).to_bytes(),
+            expected.to_bytes()
+        );
+    }
+}
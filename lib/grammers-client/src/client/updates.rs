@@ -8,13 +8,15 @@
 
 //! Methods to deal with and offer access to updates.
 
-use super::Client;
-use crate::types::{ChatMap, Update};
+use super::net::save_session;
+use super::{Client, UpdateOverflowPolicy};
+use crate::types::{Chat, ChatMap, RawUpdate, Update, UpdateFilter};
 use futures_util::future::{select, Either};
 pub use grammers_mtsender::{AuthorizationError, InvocationError};
-use grammers_session::channel_id;
+use grammers_session::{channel_id, PackedChat};
 pub use grammers_session::{PrematureEndReason, UpdateState};
 use grammers_tl_types as tl;
+use std::mem;
 use std::pin::pin;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -23,6 +25,9 @@ use tokio::time::sleep_until;
 /// How long to wait after warning the user that the updates limit was exceeded.
 const UPDATE_LIMIT_EXCEEDED_LOG_COOLDOWN: Duration = Duration::from_secs(300);
 
+/// How often the update state is written to the session while updates are being received.
+const STATE_SYNC_INTERVAL: Duration = Duration::from_secs(10);
+
 impl Client {
     /// Returns the next update from the buffer where they are queued until used.
     ///
@@ -47,14 +52,164 @@ impl Client {
     /// ```
     pub async fn next_update(&self) -> Result<Update, InvocationError> {
         loop {
+            if let Some(update) = self.take_pending_update() {
+                if let Some(update) = self.route_to_conversation(update) {
+                    return Ok(update);
+                }
+                continue;
+            }
+
             let (update, chats) = self.next_raw_update().await?;
+            if !self.update_allowed(&update) {
+                continue;
+            }
+            #[cfg(feature = "secret-chats")]
+            if super::secret_chats::is_secret_update(&update) {
+                if let Some(update) = self.handle_secret_update(update, chats).await {
+                    return Ok(update);
+                }
+                continue;
+            }
+            let chats = self.complete_message_chats(&update, chats).await;
 
-            if let Some(update) = Update::new(&self, update, &chats) {
+            if let Some(update) = self
+                .convert_update(update, chats)
+                .and_then(|update| self.route_to_conversation(update))
+            {
                 return Ok(update);
             }
         }
     }
 
+    /// Change which messages are returned by [`Client::next_update`] and
+    /// [`Client::try_next_update`].
+    ///
+    /// The filter takes effect immediately, including for updates that were already received
+    /// but not yet returned. Use [`UpdateFilter::new`] to stop filtering.
+    ///
+    /// [`Client::next_raw_update`] is not affected.
+    pub fn set_update_filter(&self, filter: UpdateFilter) {
+        self.0.state.write().unwrap().update_filter = filter;
+    }
+
+    fn update_allowed(&self, update: &tl::enums::Update) -> bool {
+        self.0.state.read().unwrap().update_filter.allows(update)
+    }
+
+    /// Convert a raw update into its friendly variant.
+    ///
+    /// If [`InitParams::raw_updates`] is enabled, the raw update is returned instead, and the
+    /// conversion is left pending for `take_pending_update`.
+    ///
+    /// [`InitParams::raw_updates`]: crate::InitParams::raw_updates
+    fn convert_update(&self, update: tl::enums::Update, chats: Arc<ChatMap>) -> Option<Update> {
+        if self.0.config.params.raw_updates {
+            self.0.state.write().unwrap().pending_update = Some((update.clone(), chats.clone()));
+            return Some(Update::Raw(RawUpdate::new(update, chats)));
+        }
+        Update::new(self, update, &chats)
+    }
+
+    /// Return the friendly variant of the update last returned in its raw form, if any.
+    fn take_pending_update(&self) -> Option<Update> {
+        let (update, chats) = self.0.state.write().unwrap().pending_update.take()?;
+        match Update::new(self, update, &chats) {
+            // It was already returned as-is.
+            Some(Update::Raw(_)) | None => None,
+            update => update,
+        }
+    }
+
+    /// Fetch the users and small groups referenced by a new or edited message which did not come
+    /// along with it, so that its sender and chat are complete.
+    ///
+    /// This happens with the short updates Telegram sends for messages in private conversations
+    /// and small groups. Failing to fetch them is not fatal, as the message can still be used.
+    async fn complete_message_chats(
+        &self,
+        update: &tl::enums::Update,
+        chats: Arc<ChatMap>,
+    ) -> Arc<ChatMap> {
+        let message = match update {
+            tl::enums::Update::NewMessage(update) => &update.message,
+            tl::enums::Update::NewChannelMessage(update) => &update.message,
+            tl::enums::Update::EditMessage(update) => &update.message,
+            tl::enums::Update::EditChannelMessage(update) => &update.message,
+            _ => return chats,
+        };
+        let peers = match message {
+            tl::enums::Message::Message(m) => [Some(&m.peer_id), m.from_id.as_ref()],
+            tl::enums::Message::Service(m) => [Some(&m.peer_id), m.from_id.as_ref()],
+            tl::enums::Message::Empty(_) => return chats,
+        };
+
+        let mut user_ids = Vec::new();
+        let mut chat_ids = Vec::new();
+        {
+            let state = self.0.state.read().unwrap();
+            for peer in peers.into_iter().flatten() {
+                if chats.get(peer).is_some() {
+                    continue;
+                }
+                match peer {
+                    tl::enums::Peer::User(user) => {
+                        // Users whose hash is not known at all are fetched by getting the
+                        // difference instead.
+                        if let Some(input_user) = state
+                            .chat_hashes
+                            .get(user.user_id)
+                            .and_then(|packed| packed.try_to_input_user())
+                        {
+                            if !user_ids.contains(&input_user) {
+                                user_ids.push(input_user);
+                            }
+                        }
+                    }
+                    tl::enums::Peer::Chat(chat) => chat_ids.push(chat.chat_id),
+                    tl::enums::Peer::Channel(_) => {}
+                }
+            }
+        }
+        if user_ids.is_empty() && chat_ids.is_empty() {
+            return chats;
+        }
+
+        let mut users = Vec::new();
+        if !user_ids.is_empty() {
+            match self
+                .invoke(&tl::functions::users::GetUsers { id: user_ids })
+                .await
+            {
+                Ok(result) => users = result,
+                Err(e) => log::warn!("failed to fetch users of new message: {}", e),
+            }
+        }
+        let mut groups = Vec::new();
+        if !chat_ids.is_empty() {
+            match self
+                .invoke(&tl::functions::messages::GetChats { id: chat_ids })
+                .await
+            {
+                Ok(tl::enums::messages::Chats::Chats(result)) => groups = result.chats,
+                Ok(tl::enums::messages::Chats::Slice(result)) => groups = result.chats,
+                Err(e) => log::warn!("failed to fetch chats of new message: {}", e),
+            }
+        }
+
+        {
+            let mut state = self.0.state.write().unwrap();
+            // Telegram can return peers without hash (e.g. Users with 'min: true')
+            let _ = state.chat_hashes.extend(&users, &groups);
+        }
+
+        chats.extended(
+            users
+                .into_iter()
+                .map(Chat::from_user)
+                .chain(groups.into_iter().map(Chat::from_raw)),
+        )
+    }
+
     /// Returns the next raw update and associated chat map from the buffer where they are queued until used.
     ///
     /// # Example
@@ -78,17 +233,111 @@ impl Client {
         &self,
     ) -> Result<(tl::enums::Update, Arc<ChatMap>), InvocationError> {
         loop {
-            let (deadline, get_diff, get_channel_diff) = {
+            if self.0.conn.is_disconnected() {
+                return Err(InvocationError::Dropped);
+            }
+            let deadline = match self.buffered_raw_update().await? {
+                Ok(update) => return Ok(update),
+                Err(deadline) => deadline,
+            };
+
+            let sleep = pin!(async { sleep_until(deadline.into()).await });
+            let step = pin!(async { self.step().await });
+
+            match select(sleep, step).await {
+                Either::Left(_) => {}
+                Either::Right((step, _)) => step?,
+            }
+        }
+    }
+
+    /// Returns the next update if one is already available, without waiting for new network
+    /// events.
+    ///
+    /// This is meant to be used along [`Client::step`] and [`Client::next_deadline`] when the
+    /// client is driven manually from a custom event loop. Any difference needed to recover from
+    /// gaps in the updates is still fetched, so the method may perform requests.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// loop {
+    ///     client.step().await?;
+    ///     while let Some(update) = client.try_next_update().await? {
+    ///         dbg!(update);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn try_next_update(&self) -> Result<Option<Update>, InvocationError> {
+        loop {
+            if let Some(update) = self.take_pending_update() {
+                if let Some(update) = self.route_to_conversation(update) {
+                    return Ok(Some(update));
+                }
+                continue;
+            }
+
+            let (update, chats) = match self.buffered_raw_update().await? {
+                Ok(update) => update,
+                Err(_) => return Ok(None),
+            };
+            if !self.update_allowed(&update) {
+                continue;
+            }
+            #[cfg(feature = "secret-chats")]
+            if super::secret_chats::is_secret_update(&update) {
+                if let Some(update) = self.handle_secret_update(update, chats).await {
+                    return Ok(Some(update));
+                }
+                continue;
+            }
+
+            if let Some(update) = self
+                .convert_update(update, chats)
+                .and_then(|update| self.route_to_conversation(update))
+            {
+                return Ok(Some(update));
+            }
+        }
+    }
+
+    /// Pop the next raw update from the queue, fetching any pending difference first.
+    ///
+    /// If there are no updates left, the deadline at which the update state should be checked
+    /// again is returned instead.
+    async fn buffered_raw_update(
+        &self,
+    ) -> Result<Result<(tl::enums::Update, Arc<ChatMap>), Instant>, InvocationError> {
+        loop {
+            let (deadline, get_diff, get_channel_diff, state_synced) = {
                 let state = &mut *self.0.state.write().unwrap();
                 if let Some(update) = state.updates.pop_front() {
-                    return Ok(update);
+                    self.0.update_room.notify_waiters();
+                    return Ok(Ok(update));
+                }
+                // Every queued update has been handed out, so the state is safe to persist.
+                let state_synced = state.last_state_sync.elapsed() >= STATE_SYNC_INTERVAL
+                    && self.0.config.session.signed_in();
+                if state_synced {
+                    self.0
+                        .config
+                        .session
+                        .set_state(state.message_box.session_state());
+                    state.last_state_sync = Instant::now();
                 }
                 (
                     state.message_box.check_deadlines(), // first, as it might trigger differences
                     state.message_box.get_difference(),
                     state.message_box.get_channel_difference(&state.chat_hashes),
+                    state_synced,
                 )
             };
+            if state_synced {
+                save_session(&self.0.config).await;
+            }
 
             if let Some(request) = get_diff {
                 let response = self.invoke(&request).await?;
@@ -181,13 +430,7 @@ impl Client {
                 continue;
             }
 
-            let sleep = pin!(async { sleep_until(deadline.into()).await });
-            let step = pin!(async { self.step().await });
-
-            match select(sleep, step).await {
-                Either::Left(_) => {}
-                Either::Right((step, _)) => step?,
-            }
+            return Ok(Err(deadline));
         }
     }
 
@@ -231,28 +474,135 @@ impl Client {
         }
     }
 
+    /// Feed the `pts` contained in a `messages.affectedMessages` response into the update state.
+    ///
+    /// Requests such as deleting messages or reading their contents don't return `Updates`, but
+    /// they still advance the `pts` of the account (or of the channel if `chat` is one). If this
+    /// information is not processed, the next update received will appear to have a gap, and the
+    /// library will needlessly fetch the difference.
+    ///
+    /// The friendly methods already take care of this. This method is only useful when
+    /// [`Client::invoke`] is used to call functions returning `messages.affectedMessages`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_tl_types as tl;
+    ///
+    /// let affected = client
+    ///     .invoke(&tl::functions::messages::ReadMessageContents { id: vec![123] })
+    ///     .await?;
+    ///
+    /// client.process_affected_messages(&chat, &affected);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn process_affected_messages<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+        affected: &tl::enums::messages::AffectedMessages,
+    ) {
+        let tl::enums::messages::AffectedMessages::Messages(affected) = affected;
+        let chat = chat.into();
+
+        // The response is handled as if it was a deletion with no messages, which carries
+        // exactly the same `pts` information (and is what other clients do too).
+        let update = if chat.is_channel() {
+            tl::types::UpdateDeleteChannelMessages {
+                channel_id: chat.id,
+                messages: Vec::new(),
+                pts: affected.pts,
+                pts_count: affected.pts_count,
+            }
+            .into()
+        } else {
+            tl::types::UpdateDeleteMessages {
+                messages: Vec::new(),
+                pts: affected.pts,
+                pts_count: affected.pts_count,
+            }
+            .into()
+        };
+
+        let (updates, users, chats) = {
+            let state = &mut *self.0.state.write().unwrap();
+            match state.message_box.process_updates(
+                tl::types::UpdateShort { update, date: 0 }.into(),
+                &state.chat_hashes,
+            ) {
+                Ok(tup) => tup,
+                Err(_) => return,
+            }
+        };
+
+        // Only the dummy deletion is dropped. Other updates may have been queued while waiting
+        // for this `pts` to fill a possible gap.
+        let updates = updates
+            .into_iter()
+            .filter(|update| match update {
+                tl::enums::Update::DeleteMessages(u) => !u.messages.is_empty(),
+                tl::enums::Update::DeleteChannelMessages(u) => !u.messages.is_empty(),
+                _ => true,
+            })
+            .collect();
+
+        self.extend_update_queue(updates, ChatMap::new(users, chats));
+    }
+
     fn extend_update_queue(&self, mut updates: Vec<tl::enums::Update>, chat_map: Arc<ChatMap>) {
+        self.cache_chats(chat_map.iter());
         let mut state = self.0.state.write().unwrap();
 
+        if let Some(self_id) = state.me.as_ref().map(|me| me.id()) {
+            if updates.iter().any(|u| self_changed(u) == Some(self_id)) {
+                state.me = None;
+            }
+        }
+
+        if !state.admin_cache.is_empty() || !state.bot_cache.is_empty() {
+            for chat_id in updates.iter().filter_map(participants_changed) {
+                state.admin_cache.remove(&chat_id);
+                state.bot_cache.remove(&chat_id);
+            }
+        }
+
         if let Some(limit) = self.0.config.params.update_queue_limit {
-            if let Some(exceeds) = (state.updates.len() + updates.len()).checked_sub(limit + 1) {
-                let exceeds = exceeds + 1;
+            let exceeds = (state.updates.len() + updates.len()).saturating_sub(limit);
+            let dropped = match self.0.config.params.update_overflow_policy {
+                UpdateOverflowPolicy::DropNewest => {
+                    let dropped = exceeds.min(updates.len());
+                    updates.truncate(updates.len() - dropped);
+                    dropped
+                }
+                UpdateOverflowPolicy::DropOldest => {
+                    let from_queue = exceeds.min(state.updates.len());
+                    state.updates.drain(..from_queue);
+                    updates.drain(..exceeds - from_queue);
+                    exceeds
+                }
+                // Reading stops while the queue is full, so these arrived along with a response.
+                UpdateOverflowPolicy::Backpressure if limit > 0 => 0,
+                UpdateOverflowPolicy::Backpressure => mem::take(&mut updates).len(),
+            };
+
+            // With a limit of zero, dropping everything is intended, so there's nothing to warn.
+            if dropped > 0 && limit > 0 {
                 let now = Instant::now();
                 let notify = match state.last_update_limit_warn {
                     None => true,
                     Some(instant) => now - instant > UPDATE_LIMIT_EXCEEDED_LOG_COOLDOWN,
                 };
 
-                updates.truncate(updates.len() - exceeds);
                 if notify {
                     log::warn!(
                         "{} updates were dropped because the update_queue_limit was exceeded",
-                        exceeds
+                        dropped
                     );
                 }
-
                 state.last_update_limit_warn = Some(now);
             }
+            state.dropped_updates += dropped as u64;
         }
 
         state
@@ -260,13 +610,94 @@ impl Client {
             .extend(updates.into_iter().map(|u| (u, chat_map.clone())));
     }
 
-    /// Synchronize the updates state to the session.
+    /// With [`UpdateOverflowPolicy::Backpressure`], wait until there is room in the update queue
+    /// (or the client is disconnected) before more updates are read from the network.
+    pub(crate) async fn wait_for_update_room(&self) {
+        let params = &self.0.config.params;
+        let limit = match (params.update_overflow_policy, params.update_queue_limit) {
+            (UpdateOverflowPolicy::Backpressure, Some(limit)) if limit > 0 => limit,
+            _ => return,
+        };
+
+        loop {
+            // Created before checking so that updates taken in between are not missed.
+            let room = pin!(self.0.update_room.notified());
+            let disconnected = pin!(self.0.conn.disconnect_notify.notified());
+            if self.0.conn.is_disconnected() || self.0.state.read().unwrap().updates.len() < limit {
+                return;
+            }
+            select(room, disconnected).await;
+        }
+    }
+
+    /// How many updates have been dropped so far because the update queue was full.
+    ///
+    /// See [`InitParams::update_queue_limit`] for details.
+    ///
+    /// [`InitParams::update_queue_limit`]: crate::InitParams::update_queue_limit
+    pub fn dropped_updates(&self) -> u64 {
+        self.0.state.read().unwrap().dropped_updates
+    }
+
+    /// Synchronize the updates state and the server salts to the session.
+    ///
+    /// The state is already synchronized periodically while updates are being received, but
+    /// this should still be called before saving the session when the program is closing, so
+    /// that the very latest state is persisted. Storing the salts lets the next connection skip
+    /// the round-trip needed to learn a valid one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn f(client: grammers_client::Client) -> std::io::Result<()> {
+    /// client.sync_update_state();
+    /// client.session().save_to_file("bot.session")?;
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn sync_update_state(&self) {
-        let state = self.0.state.read().unwrap();
+        self.sync_salts();
+        // After signing out, the state no longer belongs to anyone.
+        if !self.0.config.session.signed_in() {
+            return;
+        }
+        let mut state = self.0.state.write().unwrap();
         self.0
             .config
             .session
             .set_state(state.message_box.session_state());
+        state.last_state_sync = Instant::now();
+    }
+}
+
+/// The identifier of the chat whose participants changed according to the update, if any.
+fn participants_changed(update: &tl::enums::Update) -> Option<i64> {
+    use tl::enums::{ChatParticipants, Update};
+
+    match update {
+        Update::ChatParticipantAdmin(u) => Some(u.chat_id),
+        Update::ChatParticipantAdd(u) => Some(u.chat_id),
+        Update::ChatParticipantDelete(u) => Some(u.chat_id),
+        Update::ChatParticipant(u) => Some(u.chat_id),
+        Update::ChatParticipants(u) => Some(match &u.participants {
+            ChatParticipants::Forbidden(p) => p.chat_id,
+            ChatParticipants::Participants(p) => p.chat_id,
+        }),
+        Update::ChannelParticipant(u) => Some(u.channel_id),
+        _ => None,
+    }
+}
+
+/// The identifier of the user whose profile changed according to the update, if any.
+fn self_changed(update: &tl::enums::Update) -> Option<i64> {
+    use tl::enums::Update;
+
+    match update {
+        Update::User(u) => Some(u.user_id),
+        Update::UserName(u) => Some(u.user_id),
+        Update::UserPhone(u) => Some(u.user_id),
+        Update::UserEmojiStatus(u) => Some(u.user_id),
+        _ => None,
     }
 }
 
@@ -46,13 +46,24 @@
 //! [obtain a developer API ID]: https://my.telegram.org/auth
 pub mod client;
 pub mod parsers;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 pub mod types;
 pub(crate) mod utils;
 
-pub use client::{Client, Config, InitParams, SignInError};
+pub use client::{
+    CancelHandle, Client, Config, Conversation, ConversationError, ConversationParams,
+    EditMessageError, ForwardMessagesError, InitParams, InvokeFuture, ModerationError,
+    PasswordError, Proxy, ResolveUsernameError, RetryPolicy, SendAlbumError, SendMessageError,
+    SignInError, TakeoutClient, TakeoutError, TakeoutOptions, TerminateSessionError,
+    TransferOwnershipError, UpdateOverflowPolicy, UsernameError,
+};
 pub use types::{button, reply_markup, ChatMap, InputMedia, InputMessage, Update};
 
 pub use grammers_mtproto::transport;
-pub use grammers_mtsender::{FixedReconnect, InvocationError, NoReconnect, ReconnectionPolicy};
+pub use grammers_mtsender::{
+    Connector, ExponentialReconnect, FixedReconnect, Instrumentation, InvocationError, Latency,
+    NoReconnect, ReconnectionPolicy, Stream,
+};
 pub use grammers_session as session;
 pub use grammers_tl_types;
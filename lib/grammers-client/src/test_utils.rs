@@ -0,0 +1,443 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Utilities to test code built on top of the library without connecting to Telegram.
+//!
+//! A [`MockSender`] answers the requests made by a [`Client`] with scripted responses, rather
+//! than sending them over the network. Together with a session that is never saved to disk,
+//! such as the default one or one backed by a [`MemorySession`], this allows testing the
+//! handling of dialogs, messages and updates offline.
+//!
+//! This module is only available with the `test-utils` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! # async fn f() -> Result<(), Box<dyn std::error::Error>> {
+//! use grammers_client::test_utils::MockSender;
+//! use grammers_client::{Client, Config};
+//! use grammers_session::Session;
+//! use grammers_tl_types as tl;
+//!
+//! let mock = MockSender::new();
+//! mock.expect::<tl::functions::messages::GetDialogs>(
+//!     tl::types::messages::Dialogs {
+//!         dialogs: Vec::new(),
+//!         messages: Vec::new(),
+//!         chats: Vec::new(),
+//!         users: Vec::new(),
+//!     }
+//!     .into(),
+//! );
+//!
+//! let client = Client::with_mock(
+//!     Config {
+//!         session: Session::new(),
+//!         api_id: 0,
+//!         api_hash: String::new(),
+//!         params: Default::default(),
+//!     },
+//!     mock.clone(),
+//! )
+//! .await;
+//!
+//! assert!(client.iter_dialogs().next().await?.is_none());
+//! assert_eq!(mock.pending(), 0);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`MemorySession`]: grammers_session::MemorySession
+use crate::client::client::Connection;
+use crate::{Client, Config};
+use futures_util::future::BoxFuture;
+use grammers_mtproto::transport::{self, Transport};
+use grammers_mtsender::{self as sender, Connector, InvocationError, RpcError, Stream};
+use grammers_tl_types::{self as tl, Deserializable, Identifiable, Serializable};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// The name of the error returned when a request is made without a response scripted for it.
+pub const UNEXPECTED_REQUEST: &str = "MOCK_UNEXPECTED_REQUEST";
+
+/// Answers the requests of a [`Client`] made with [`Client::with_mock`] with scripted responses.
+///
+/// Responses are scripted per request constructor with [`MockSender::expect`] and
+/// [`MockSender::expect_error`], and are used in the same order they were scripted. Each is
+/// only used once. Requests without a response left fail with an [`RpcError`] named
+/// [`UNEXPECTED_REQUEST`], caused by the constructor of the request.
+///
+/// Requests are matched by their outermost constructor, so those made by a
+/// [`crate::TakeoutClient`] must be scripted as [`tl::functions::InvokeWithTakeout`].
+///
+/// Updates are delivered to the client by [`MockSender::push_updates`], as if they arrived from
+/// the network.
+///
+/// The sender is cheap to clone, and clones share the same script. All the connections of the
+/// client, including those to other datacenters, are answered by it.
+#[derive(Clone, Default)]
+pub struct MockSender(Arc<MockState>);
+
+/// A serialized response or the error to fail with.
+type Response = Result<Vec<u8>, RpcError>;
+
+#[derive(Default)]
+struct MockState {
+    responses: Mutex<HashMap<u32, VecDeque<Response>>>,
+    requests: Mutex<Vec<Vec<u8>>>,
+    updates: Mutex<VecDeque<tl::enums::Updates>>,
+    updates_notify: Notify,
+}
+
+impl MockSender {
+    /// Create a new sender without any response scripted.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer the next request of type `R` with the given response.
+    pub fn expect<R>(&self, response: R::Return) -> &Self
+    where
+        R: tl::RemoteCall + Identifiable,
+        R::Return: Serializable,
+    {
+        self.push_response(R::CONSTRUCTOR_ID, Ok(response.to_bytes()))
+    }
+
+    /// Fail the next request of type `R` with the given error.
+    ///
+    /// If the error does not say which request caused it, it is set to `R`.
+    pub fn expect_error<R>(&self, mut error: RpcError) -> &Self
+    where
+        R: tl::RemoteCall + Identifiable,
+    {
+        error.caused_by.get_or_insert(R::CONSTRUCTOR_ID);
+        self.push_response(R::CONSTRUCTOR_ID, Err(error))
+    }
+
+    fn push_response(&self, id: u32, response: Response) -> &Self {
+        self.0
+            .responses
+            .lock()
+            .unwrap()
+            .entry(id)
+            .or_default()
+            .push_back(response);
+        self
+    }
+
+    /// Deliver updates to the client, as if they had been received from Telegram.
+    pub fn push_updates(&self, updates: tl::enums::Updates) {
+        self.0.updates.lock().unwrap().push_back(updates);
+        self.0.updates_notify.notify_one();
+    }
+
+    /// The serialized requests received so far, in the order they were made.
+    ///
+    /// This includes the requests which had no response scripted.
+    pub fn requests(&self) -> Vec<Vec<u8>> {
+        self.0.requests.lock().unwrap().clone()
+    }
+
+    /// How many of the scripted responses have not been used yet.
+    pub fn pending(&self) -> usize {
+        self.0
+            .responses
+            .lock()
+            .unwrap()
+            .values()
+            .map(VecDeque::len)
+            .sum()
+    }
+
+    pub(crate) fn respond<R: tl::RemoteCall>(
+        &self,
+        request: &R,
+    ) -> Result<R::Return, InvocationError> {
+        let body = request.to_bytes();
+        let id = u32::from_le_bytes(body[..4].try_into().unwrap());
+        self.0.requests.lock().unwrap().push(body);
+
+        let response = self
+            .0
+            .responses
+            .lock()
+            .unwrap()
+            .get_mut(&id)
+            .and_then(VecDeque::pop_front);
+        match response {
+            Some(Ok(body)) => R::Return::from_bytes(&body).map_err(|e| e.into()),
+            Some(Err(error)) => Err(InvocationError::Rpc(error)),
+            None => Err(InvocationError::Rpc(RpcError {
+                code: 500,
+                name: UNEXPECTED_REQUEST.to_string(),
+                value: None,
+                caused_by: Some(id),
+            })),
+        }
+    }
+
+    /// Wait until updates are pushed, and take all of them.
+    pub(crate) async fn next_updates(&self) -> Vec<tl::enums::Updates> {
+        loop {
+            let notified = self.0.updates_notify.notified();
+            let updates = self.0.updates.lock().unwrap().drain(..).collect::<Vec<_>>();
+            if !updates.is_empty() {
+                return updates;
+            }
+            notified.await;
+        }
+    }
+
+    /// Create a connection whose requests are answered by this sender.
+    ///
+    /// The underlying sender is connected to an in-memory stream which is never used.
+    pub(crate) async fn connection(&self) -> Connection {
+        let transport: Box<dyn Transport + Send> = Box::new(transport::Full::new());
+        let (sender, request_tx) = sender::connect_via_connector_with_auth(
+            transport,
+            SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+            [0; 256],
+            Arc::new(MockConnector),
+            &sender::NoReconnect,
+        )
+        .await
+        .expect("connecting to an in-memory stream should not fail");
+
+        let mut connection = Connection::new(sender, request_tx);
+        connection.mock = Some(self.clone());
+        connection
+    }
+}
+
+impl fmt::Debug for MockSender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockSender")
+            .field("pending", &self.pending())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Opens in-memory streams with nothing on the other end.
+struct MockConnector;
+
+impl Connector for MockConnector {
+    fn connect(&self, _addr: SocketAddr) -> BoxFuture<'_, io::Result<Box<dyn Stream>>> {
+        Box::pin(async {
+            let (stream, _) = tokio::io::duplex(64);
+            Ok(Box::new(stream) as Box<dyn Stream>)
+        })
+    }
+}
+
+impl Client {
+    /// Create a client whose requests are answered by the given [`MockSender`], rather than by
+    /// Telegram.
+    ///
+    /// No connection is made, and the pristine update state is not fetched, so only the
+    /// requests made after this returns need to be scripted. The client is considered to be
+    /// logged in only if the session says so.
+    ///
+    /// This is only available with the `test-utils` feature. See the [`crate::test_utils`]
+    /// module for an example.
+    pub async fn with_mock(config: Config, mock: MockSender) -> Self {
+        let dc_id = config
+            .session
+            .home_dc()
+            .unwrap_or(crate::client::net::DEFAULT_DC);
+        Self::from_connection(config, dc_id, mock.connection().await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grammers_session::Session;
+    use std::future::Future;
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn config() -> Config {
+        Config {
+            session: Session::new(),
+            api_id: 0,
+            api_hash: String::new(),
+            params: Default::default(),
+        }
+    }
+
+    fn chat(id: i64, title: &str) -> tl::enums::Chat {
+        tl::types::Chat {
+            creator: false,
+            left: false,
+            deactivated: false,
+            call_active: false,
+            call_not_empty: false,
+            noforwards: false,
+            id,
+            title: title.to_string(),
+            photo: tl::enums::ChatPhoto::Empty,
+            participants_count: 2,
+            date: 0,
+            version: 0,
+            migrated_to: None,
+            admin_rights: None,
+            default_banned_rights: None,
+        }
+        .into()
+    }
+
+    fn dialog(peer: tl::enums::Peer) -> tl::enums::Dialog {
+        tl::types::Dialog {
+            pinned: false,
+            unread_mark: false,
+            view_forum_as_messages: false,
+            peer,
+            top_message: 0,
+            read_inbox_max_id: 0,
+            read_outbox_max_id: 0,
+            unread_count: 0,
+            unread_mentions_count: 0,
+            unread_reactions_count: 0,
+            notify_settings: tl::types::PeerNotifySettings {
+                show_previews: None,
+                silent: None,
+                mute_until: None,
+                ios_sound: None,
+                android_sound: None,
+                other_sound: None,
+                stories_muted: None,
+                stories_hide_sender: None,
+                stories_ios_sound: None,
+                stories_android_sound: None,
+                stories_other_sound: None,
+            }
+            .into(),
+            pts: None,
+            draft: None,
+            folder_id: None,
+            ttl_period: None,
+            unread_poll_votes_count: 0,
+        }
+        .into()
+    }
+
+    #[test]
+    fn scripted_dialogs() {
+        block_on(async {
+            let mock = MockSender::new();
+            mock.expect::<tl::functions::messages::GetDialogs>(
+                tl::types::messages::Dialogs {
+                    dialogs: vec![dialog(tl::types::PeerChat { chat_id: 123 }.into())],
+                    messages: Vec::new(),
+                    chats: vec![chat(123, "Test group")],
+                    users: Vec::new(),
+                }
+                .into(),
+            );
+            let client = Client::with_mock(config(), mock.clone()).await;
+
+            let mut dialogs = client.iter_dialogs();
+            let first = dialogs.next().await.unwrap().unwrap();
+            assert_eq!(first.chat().id(), 123);
+            assert_eq!(first.chat().name(), "Test group");
+            assert!(dialogs.next().await.unwrap().is_none());
+
+            assert_eq!(mock.pending(), 0);
+            let requests = mock.requests();
+            assert_eq!(requests.len(), 1);
+            assert_eq!(
+                requests[0][..4],
+                tl::functions::messages::GetDialogs::CONSTRUCTOR_ID.to_le_bytes()
+            );
+        });
+    }
+
+    #[test]
+    fn unexpected_and_failed_requests() {
+        block_on(async {
+            let mock = MockSender::new();
+            mock.expect_error::<tl::functions::Ping>(RpcError {
+                code: 400,
+                name: "PING_INVALID".to_string(),
+                value: None,
+                caused_by: None,
+            });
+            let client = Client::with_mock(config(), mock.clone()).await;
+
+            let ping = tl::functions::Ping { ping_id: 1 };
+            match client.invoke(&ping).await {
+                Err(InvocationError::Rpc(err)) => {
+                    assert!(err.is("PING_INVALID"));
+                    assert_eq!(err.caused_by, Some(tl::functions::Ping::CONSTRUCTOR_ID));
+                }
+                result => panic!("unexpected result: {result:?}"),
+            }
+            match client.invoke(&ping).await {
+                Err(InvocationError::Rpc(err)) => assert!(err.is(UNEXPECTED_REQUEST)),
+                result => panic!("unexpected result: {result:?}"),
+            }
+        });
+    }
+
+    #[test]
+    fn update_queue_backpressure() {
+        use crate::UpdateOverflowPolicy;
+        use futures_util::future::join;
+        use std::time::Duration;
+        use tokio::time::timeout;
+
+        block_on(async {
+            let mock = MockSender::new();
+            let mut config = config();
+            config.params.update_queue_limit = Some(2);
+            config.params.update_overflow_policy = UpdateOverflowPolicy::Backpressure;
+            let client = Client::with_mock(config, mock.clone()).await;
+            let push_update = || {
+                mock.push_updates(
+                    tl::types::UpdateShort {
+                        update: tl::types::UpdateConfig {}.into(),
+                        date: 0,
+                    }
+                    .into(),
+                )
+            };
+
+            // Updates that arrive at once are all queued, even past the limit.
+            for _ in 0..3 {
+                push_update();
+            }
+            client.step().await.unwrap();
+            push_update();
+            assert!(timeout(Duration::from_millis(50), client.step())
+                .await
+                .is_err());
+            assert_eq!(client.0.state.read().unwrap().updates.len(), 3);
+
+            // Reading resumes as soon as there's room in the queue.
+            let consume = async {
+                client.next_raw_update().await.unwrap();
+                client.next_raw_update().await.unwrap();
+            };
+            let (step, ()) = join(timeout(Duration::from_secs(5), client.step()), consume).await;
+            step.unwrap().unwrap();
+            assert_eq!(client.0.state.read().unwrap().updates.len(), 2);
+            assert_eq!(client.dropped_updates(), 0);
+        });
+    }
+}
@@ -10,6 +10,7 @@ use grammers_mtsender::InvocationError;
 use grammers_session::PackedChat;
 use grammers_tl_types as tl;
 use std::future::Future;
+use std::pin::pin;
 use std::time::Duration;
 use tl::enums::SendMessageAction;
 
@@ -17,6 +18,9 @@ use crate::Client;
 
 const DEFAULT_REPEAT_DELAY: Duration = Duration::from_secs(4);
 
+/// A helper to show chat actions, such as the "typing…" indicator, in a chat.
+///
+/// Returned by [`Client::action`].
 pub struct ActionSender {
     client: Client,
     chat: PackedChat,
@@ -46,6 +50,49 @@ impl ActionSender {
         self
     }
 
+    /// Show the "typing…" indicator.
+    pub fn typing(&self) -> Action<'_> {
+        self.with(SendMessageAction::SendMessageTypingAction)
+    }
+
+    /// Show the "uploading photo…" indicator, with the given progress percentage.
+    pub fn upload_photo(&self, progress: i32) -> Action<'_> {
+        self.with(tl::types::SendMessageUploadPhotoAction { progress })
+    }
+
+    /// Show the "uploading video…" indicator, with the given progress percentage.
+    pub fn upload_video(&self, progress: i32) -> Action<'_> {
+        self.with(tl::types::SendMessageUploadVideoAction { progress })
+    }
+
+    /// Show the "uploading file…" indicator, with the given progress percentage.
+    pub fn upload_document(&self, progress: i32) -> Action<'_> {
+        self.with(tl::types::SendMessageUploadDocumentAction { progress })
+    }
+
+    /// Show the "recording video…" indicator.
+    pub fn record_video(&self) -> Action<'_> {
+        self.with(SendMessageAction::SendMessageRecordVideoAction)
+    }
+
+    /// Show the "recording voice message…" indicator.
+    pub fn record_voice(&self) -> Action<'_> {
+        self.with(SendMessageAction::SendMessageRecordAudioAction)
+    }
+
+    /// Show the "choosing sticker…" indicator.
+    pub fn choose_sticker(&self) -> Action<'_> {
+        self.with(SendMessageAction::SendMessageChooseStickerAction)
+    }
+
+    /// Show any other action.
+    pub fn with<A: Into<SendMessageAction>>(&self, action: A) -> Action<'_> {
+        Action {
+            sender: self,
+            action: action.into(),
+        }
+    }
+
     /// Cancel any actions
     pub async fn cancel(&self) -> Result<(), InvocationError> {
         self.oneshot(SendMessageAction::SendMessageCancelAction)
@@ -106,8 +153,9 @@ impl ActionSender {
     pub async fn repeat<A: Into<SendMessageAction>, T>(
         &self,
         action: impl Fn() -> A,
-        mut future: impl Future<Output = T> + Unpin,
+        future: impl Future<Output = T>,
     ) -> (T, Result<(), InvocationError>) {
+        let mut future = pin!(future);
         let mut request_result = Ok(());
 
         let future_output = loop {
@@ -132,3 +180,48 @@ impl ActionSender {
         (future_output, request_result)
     }
 }
+
+/// A chat action chosen through one of the methods of [`ActionSender`].
+pub struct Action<'a> {
+    sender: &'a ActionSender,
+    action: SendMessageAction,
+}
+
+impl Action<'_> {
+    /// Send the action once. Telegram clients will stop showing it after about 5 seconds.
+    pub async fn send(self) -> Result<(), InvocationError> {
+        self.sender.oneshot(self.action).await
+    }
+
+    /// Keep sending the action until the future is done.
+    ///
+    /// Dropping the returned future stops sending the action. The action is not cancelled once
+    /// the future is done, it will just fade away.
+    ///
+    /// See [`ActionSender::repeat`] for more details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// let (answer, _) = client
+    ///     .action(&chat)
+    ///     .typing()
+    ///     .repeat(async {
+    ///         // ...long work...
+    ///         42
+    ///     })
+    ///     .await;
+    ///
+    /// client.send_message(&chat, answer.to_string()).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn repeat<T>(
+        self,
+        future: impl Future<Output = T>,
+    ) -> (T, Result<(), InvocationError>) {
+        let action = self.action;
+        self.sender.repeat(|| action.clone(), future).await
+    }
+}
@@ -0,0 +1,485 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rendering of service messages into human-readable text.
+use crate::types::{Chat, ChatMap};
+use grammers_tl_types as tl;
+use std::time::Duration;
+
+/// Turns the [`MessageAction`] of service messages into human-readable text.
+///
+/// Every method has a default implementation producing English text, as seen in the official
+/// clients, so that implementors only need to override the parts they want to change.
+///
+/// Localizing the text is typically done by overriding [`ActionRenderer::render`], while still
+/// relying on the other methods to resolve names, join lists, and format durations.
+///
+/// [`MessageAction`]: tl::enums::MessageAction
+///
+/// # Examples
+///
+/// ```
+/// use grammers_client::types::ActionRenderer;
+/// use std::time::Duration;
+///
+/// struct Spanish;
+///
+/// impl ActionRenderer for Spanish {
+///     fn others(&self, count: usize) -> String {
+///         if count == 1 {
+///             "1 más".to_string()
+///         } else {
+///             format!("{count} más")
+///         }
+///     }
+///
+///     fn and(&self) -> &str {
+///         "y"
+///     }
+/// }
+///
+/// assert_eq!(
+///     Spanish.list(vec!["Ana".into(), "Bea".into(), "Carla".into()]),
+///     "Ana y 2 más"
+/// );
+/// ```
+pub trait ActionRenderer {
+    /// The name used to refer to the given chat, or to an unknown one if `None`.
+    fn name(&self, chat: Option<&Chat>) -> String {
+        match chat {
+            Some(Chat::User(user)) if user.deleted() => "Deleted Account".to_string(),
+            Some(Chat::User(user)) => user.full_name(),
+            Some(chat) => chat.name().to_string(),
+            None => "Someone".to_string(),
+        }
+    }
+
+    /// The word used to join the last two items of a list.
+    fn and(&self) -> &str {
+        "and"
+    }
+
+    /// The text used to refer to the remaining `count` items of a list which are not named.
+    fn others(&self, count: usize) -> String {
+        if count == 1 {
+            "1 other".to_string()
+        } else {
+            format!("{count} others")
+        }
+    }
+
+    /// Join a list of names, such as "Alice", "Alice and Bob", or "Alice and 2 others".
+    fn list(&self, mut names: Vec<String>) -> String {
+        match names.len() {
+            0 => String::new(),
+            1 => names.pop().unwrap(),
+            2 => format!("{} {} {}", names[0], self.and(), names[1]),
+            n => format!("{} {} {}", names[0], self.and(), self.others(n - 1)),
+        }
+    }
+
+    /// Format a duration using its two largest units, such as "1h 23m" or "45s".
+    fn duration(&self, duration: Duration) -> String {
+        let secs = duration.as_secs();
+        let units = [
+            (secs / 86400, "d"),
+            (secs / 3600 % 24, "h"),
+            (secs / 60 % 60, "m"),
+            (secs % 60, "s"),
+        ];
+
+        let parts = units
+            .iter()
+            .skip_while(|(value, _)| *value == 0)
+            .take(2)
+            .filter(|(value, _)| *value != 0)
+            .map(|(value, unit)| format!("{value}{unit}"))
+            .collect::<Vec<_>>();
+
+        if parts.is_empty() {
+            "0s".to_string()
+        } else {
+            parts.join(" ")
+        }
+    }
+
+    /// Render the action performed by `actor` as text.
+    ///
+    /// The chats are used to resolve the names of the users involved in the action.
+    fn render(
+        &self,
+        actor: Option<&Chat>,
+        action: &tl::enums::MessageAction,
+        chats: &ChatMap,
+    ) -> String {
+        use tl::enums::MessageAction as A;
+
+        let actor_name = self.name(actor);
+        let user = |id: i64| self.name(chats.get(&tl::types::PeerUser { user_id: id }.into()));
+        let users = |ids: &[i64]| self.list(ids.iter().copied().map(user).collect());
+        let duration = |secs: i32| self.duration(Duration::from_secs(secs.max(0) as u64));
+        let is_channel = matches!(actor, Some(Chat::Channel(_)));
+
+        match action {
+            A::Empty => String::new(),
+            A::ChatCreate(a) => format!("{actor_name} created the group \"{}\"", a.title),
+            A::ChannelCreate(a) if is_channel => format!("Channel \"{}\" created", a.title),
+            A::ChannelCreate(a) => format!("{actor_name} created the group \"{}\"", a.title),
+            A::ChatEditTitle(a) if is_channel => {
+                format!("Channel name was changed to \"{}\"", a.title)
+            }
+            A::ChatEditTitle(a) => {
+                format!("{actor_name} changed the group name to \"{}\"", a.title)
+            }
+            A::ChatEditPhoto(_) if is_channel => "Channel photo updated".to_string(),
+            A::ChatEditPhoto(_) => format!("{actor_name} changed the group photo"),
+            A::ChatDeletePhoto if is_channel => "Channel photo removed".to_string(),
+            A::ChatDeletePhoto => format!("{actor_name} removed the group photo"),
+            A::ChatAddUser(a) => {
+                let actor_id = match actor {
+                    Some(Chat::User(user)) => Some(user.id()),
+                    _ => None,
+                };
+                if a.users.len() == 1 && Some(a.users[0]) == actor_id {
+                    format!("{actor_name} joined the group")
+                } else {
+                    format!("{actor_name} added {}", users(&a.users))
+                }
+            }
+            A::ChatDeleteUser(a) => match actor {
+                Some(Chat::User(u)) if u.id() == a.user_id => {
+                    format!("{actor_name} left the group")
+                }
+                _ => format!("{actor_name} removed {}", user(a.user_id)),
+            },
+            A::ChatJoinedByLink(_) => format!("{actor_name} joined the group via invite link"),
+            A::ChatJoinedByRequest => format!("{actor_name} was accepted into the group"),
+            A::ChatMigrateTo(_) => "The group was upgraded to a supergroup".to_string(),
+            A::ChannelMigrateFrom(a) => {
+                format!("Group \"{}\" was upgraded to a supergroup", a.title)
+            }
+            A::PinMessage => format!("{actor_name} pinned a message"),
+            A::HistoryClear => "History was cleared".to_string(),
+            A::GameScore(a) => format!("{actor_name} scored {}", a.score),
+            A::PaymentSentMe(a) => format!(
+                "{actor_name} paid {} {}",
+                format_amount(a.total_amount),
+                a.currency
+            ),
+            A::PaymentSent(a) => {
+                format!("You paid {} {}", format_amount(a.total_amount), a.currency)
+            }
+            A::PaymentRefunded(a) => format!(
+                "{} {} were refunded",
+                format_amount(a.total_amount),
+                a.currency
+            ),
+            A::PhoneCall(a) => {
+                let kind = if a.video { "Video call" } else { "Call" };
+                match (a.duration, &a.reason) {
+                    (Some(secs), _) if secs > 0 => format!("{kind} ({})", duration(secs)),
+                    (_, Some(tl::enums::PhoneCallDiscardReason::Missed)) => {
+                        format!("Missed {}", kind.to_lowercase())
+                    }
+                    (_, Some(tl::enums::PhoneCallDiscardReason::Busy)) => {
+                        format!("Declined {}", kind.to_lowercase())
+                    }
+                    _ => format!("Cancelled {}", kind.to_lowercase()),
+                }
+            }
+            A::ScreenshotTaken => format!("{actor_name} took a screenshot"),
+            A::CustomAction(a) => a.message.clone(),
+            A::BotAllowed(a) => match &a.domain {
+                Some(domain) => {
+                    format!("You allowed this bot to message you when you logged in on {domain}")
+                }
+                None => "You allowed this bot to message you".to_string(),
+            },
+            A::SecureValuesSentMe(_) | A::SecureValuesSent(_) => {
+                format!("{actor_name} shared Telegram Passport data")
+            }
+            A::ContactSignUp => format!("{actor_name} joined Telegram"),
+            A::GeoProximityReached(a) => format!(
+                "{} is now within {} m from {}",
+                self.name(chats.get(&a.from_id)),
+                a.distance,
+                self.name(chats.get(&a.to_id))
+            ),
+            A::GroupCall(a) => match a.duration {
+                Some(secs) => format!("Voice chat ended ({})", duration(secs)),
+                None => format!("{actor_name} started a voice chat"),
+            },
+            A::InviteToGroupCall(a) => {
+                format!("{actor_name} invited {} to the voice chat", users(&a.users))
+            }
+            A::GroupCallScheduled(_) => format!("{actor_name} scheduled a voice chat"),
+            A::SetMessagesTtl(a) if a.period == 0 => {
+                format!("{actor_name} disabled the auto-delete timer")
+            }
+            A::SetMessagesTtl(a) => format!(
+                "{actor_name} set messages to auto-delete in {}",
+                duration(a.period)
+            ),
+            A::SetChatTheme(a) => match &a.theme {
+                tl::enums::ChatTheme::Theme(t) if t.emoticon.is_empty() => {
+                    format!("{actor_name} disabled the chat theme")
+                }
+                tl::enums::ChatTheme::Theme(t) => {
+                    format!("{actor_name} changed the chat theme to {}", t.emoticon)
+                }
+                tl::enums::ChatTheme::UniqueGift(_) => {
+                    format!("{actor_name} changed the chat theme to a gift")
+                }
+            },
+            A::SetChatWallPaper(_) => format!("{actor_name} set a new wallpaper for this chat"),
+            A::WebViewDataSentMe(a) => a.text.clone(),
+            A::WebViewDataSent(a) => format!("Data from the \"{}\" button was sent", a.text),
+            A::GiftPremium(a) => {
+                format!("{actor_name} gifted Telegram Premium for {} days", a.days)
+            }
+            A::GiftCode(a) => format!("{actor_name} sent a gift code for {} days", a.days),
+            A::GiftStars(a) => format!("{actor_name} gifted {} stars", a.stars),
+            A::TopicCreate(a) => format!("{actor_name} created the topic \"{}\"", a.title),
+            A::TopicEdit(a) => match (&a.title, a.closed, a.hidden) {
+                (Some(title), _, _) => format!("{actor_name} renamed the topic to \"{title}\""),
+                (None, Some(true), _) => format!("{actor_name} closed the topic"),
+                (None, Some(false), _) => format!("{actor_name} reopened the topic"),
+                (None, None, Some(true)) => format!("{actor_name} hid the topic"),
+                (None, None, Some(false)) => format!("{actor_name} unhid the topic"),
+                (None, None, None) => format!("{actor_name} changed the topic icon"),
+            },
+            A::SuggestProfilePhoto(_) => format!("{actor_name} suggested a new profile photo"),
+            A::RequestedPeer(a) => format!(
+                "You shared {}",
+                self.list(a.peers.iter().map(|p| self.name(chats.get(p))).collect())
+            ),
+            A::RequestedPeerSentMe(_) => format!("{actor_name} shared a chat"),
+            A::GiveawayLaunch(_) => format!("{actor_name} started a giveaway"),
+            A::GiveawayResults(a) => format!(
+                "{} of the giveaway were selected",
+                match a.winners_count {
+                    1 => "The winner".to_string(),
+                    n => format!("{n} winners"),
+                }
+            ),
+            A::BoostApply(a) => match a.boosts {
+                1 => format!("{actor_name} boosted the group"),
+                n => format!("{actor_name} boosted the group {n} times"),
+            },
+            A::PrizeStars(a) => format!("You won {} stars in a giveaway", a.stars),
+            A::StarGift(_) => format!("{actor_name} sent a gift"),
+            A::StarGiftUnique(a) if a.upgrade => format!("{actor_name} upgraded a gift"),
+            A::StarGiftUnique(_) => format!("{actor_name} sent a unique gift"),
+            A::StarGiftPurchaseOffer(_) => format!("{actor_name} offered to buy a gift"),
+            A::StarGiftPurchaseOfferDeclined(_) => {
+                "The offer to buy a gift was declined".to_string()
+            }
+            A::GiftTon(a) => format!(
+                "{actor_name} gifted {} {}",
+                format_amount(a.amount),
+                a.currency
+            ),
+            A::PaidMessagesRefunded(a) => {
+                format!("{} stars for {} messages were refunded", a.stars, a.count)
+            }
+            A::PaidMessagesPrice(a) if a.stars == 0 => {
+                format!("{actor_name} made messages free")
+            }
+            A::PaidMessagesPrice(a) => {
+                format!(
+                    "{actor_name} set the price of messages to {} stars",
+                    a.stars
+                )
+            }
+            A::ConferenceCall(a) if a.missed => "Missed group call".to_string(),
+            A::ConferenceCall(a) => match a.duration {
+                Some(secs) if secs > 0 => format!("Group call ({})", duration(secs)),
+                _ => "Group call".to_string(),
+            },
+            A::TodoCompletions(_) => format!("{actor_name} updated the checklist"),
+            A::TodoAppendTasks(a) => match a.list.len() {
+                1 => format!("{actor_name} added a task to the checklist"),
+                n => format!("{actor_name} added {n} tasks to the checklist"),
+            },
+            A::SuggestedPostApproval(a) if a.rejected => {
+                format!("{actor_name} rejected the suggested post")
+            }
+            A::SuggestedPostApproval(_) => format!("{actor_name} approved the suggested post"),
+            A::SuggestedPostSuccess(_) => "The suggested post was published".to_string(),
+            A::SuggestedPostRefund(_) => "The suggested post was refunded".to_string(),
+            A::SuggestBirthday(_) => format!("{actor_name} suggested a birthday"),
+            A::NewCreatorPending(a) => {
+                format!("{} will become the new owner", user(a.new_creator_id))
+            }
+            A::ChangeCreator(a) => format!(
+                "{actor_name} transferred ownership to {}",
+                user(a.new_creator_id)
+            ),
+            A::NoForwardsToggle(a) if a.new_value => {
+                format!("{actor_name} restricted saving content")
+            }
+            A::NoForwardsToggle(_) => format!("{actor_name} allowed saving content"),
+            A::NoForwardsRequest(_) => {
+                format!("{actor_name} asked to change whether content can be saved")
+            }
+            A::PollAppendAnswer(_) => format!("{actor_name} added an option to the poll"),
+            A::PollDeleteAnswer(_) => format!("{actor_name} removed an option from the poll"),
+            A::ManagedBotCreated(a) => format!("{actor_name} created {}", user(a.bot_id)),
+        }
+    }
+}
+
+/// The default [`ActionRenderer`], producing English text.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EnglishRenderer;
+
+impl ActionRenderer for EnglishRenderer {}
+
+/// Amounts are in the smallest unit of the currency. Most currencies have two decimal places.
+fn format_amount(amount: i64) -> String {
+    format!("{}.{:02}", amount / 100, (amount % 100).abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: i64, first_name: &str) -> tl::enums::User {
+        tl::types::User {
+            is_self: false,
+            contact: false,
+            mutual_contact: false,
+            deleted: false,
+            bot: false,
+            bot_chat_history: false,
+            bot_nochats: false,
+            verified: false,
+            restricted: false,
+            min: false,
+            bot_inline_geo: false,
+            support: false,
+            scam: false,
+            apply_min_photo: false,
+            fake: false,
+            bot_attach_menu: false,
+            premium: false,
+            attach_menu_enabled: false,
+            bot_can_edit: false,
+            close_friend: false,
+            stories_hidden: false,
+            stories_unavailable: false,
+            contact_require_premium: false,
+            bot_business: false,
+            bot_has_main_app: false,
+            id,
+            access_hash: Some(0),
+            first_name: Some(first_name.to_string()),
+            last_name: None,
+            username: None,
+            phone: None,
+            photo: None,
+            status: None,
+            bot_info_version: None,
+            restriction_reason: None,
+            bot_inline_placeholder: None,
+            lang_code: None,
+            emoji_status: None,
+            usernames: None,
+            stories_max_id: None,
+            color: None,
+            profile_color: None,
+            bot_active_users: None,
+            bot_forum_view: false,
+            bot_forum_can_manage_topics: false,
+            bot_can_manage_bots: false,
+            bot_guestchat: false,
+            bot_guard: false,
+            bot_verification_icon: None,
+            send_paid_messages_stars: None,
+        }
+        .into()
+    }
+
+    #[test]
+    fn check_duration() {
+        let r = EnglishRenderer;
+        assert_eq!(r.duration(Duration::from_secs(0)), "0s");
+        assert_eq!(r.duration(Duration::from_secs(45)), "45s");
+        assert_eq!(r.duration(Duration::from_secs(90)), "1m 30s");
+        assert_eq!(
+            r.duration(Duration::from_secs(3600 + 23 * 60 + 5)),
+            "1h 23m"
+        );
+        assert_eq!(r.duration(Duration::from_secs(86400)), "1d");
+        assert_eq!(r.duration(Duration::from_secs(86400 + 60)), "1d");
+    }
+
+    #[test]
+    fn check_list() {
+        let r = EnglishRenderer;
+        let names = |n: &[&str]| n.iter().map(|s| s.to_string()).collect();
+        assert_eq!(r.list(names(&[])), "");
+        assert_eq!(r.list(names(&["Bob"])), "Bob");
+        assert_eq!(r.list(names(&["Bob", "Carol"])), "Bob and Carol");
+        assert_eq!(r.list(names(&["Bob", "Carol", "Dave"])), "Bob and 2 others");
+    }
+
+    #[test]
+    fn check_render() {
+        let chats = ChatMap::new(
+            vec![user(1, "Alice"), user(2, "Bob"), user(3, "Carol")],
+            Vec::new(),
+        );
+        let alice = chats.get(&tl::types::PeerUser { user_id: 1 }.into());
+        let r = EnglishRenderer;
+
+        assert_eq!(
+            r.render(
+                alice,
+                &tl::types::MessageActionChatAddUser {
+                    users: vec![2, 3, 4],
+                }
+                .into(),
+                &chats
+            ),
+            "Alice added Bob and 2 others"
+        );
+        assert_eq!(
+            r.render(
+                alice,
+                &tl::types::MessageActionChatAddUser { users: vec![1] }.into(),
+                &chats
+            ),
+            "Alice joined the group"
+        );
+        assert_eq!(
+            r.render(
+                None,
+                &tl::types::MessageActionChatDeleteUser { user_id: 4 }.into(),
+                &chats
+            ),
+            "Someone removed Someone"
+        );
+        assert_eq!(
+            r.render(
+                alice,
+                &tl::types::MessageActionGroupCall {
+                    call: tl::types::InputGroupCall {
+                        id: 0,
+                        access_hash: 0,
+                    }
+                    .into(),
+                    duration: Some(3600 + 23 * 60),
+                }
+                .into(),
+                &chats
+            ),
+            "Voice chat ended (1h 23m)"
+        );
+    }
+}
@@ -0,0 +1,247 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use crate::types::{Chat, ChatMap, InviteLink, Message, Participant, Photo};
+use crate::{utils, Client};
+use chrono::{DateTime, Utc};
+use grammers_tl_types as tl;
+use std::fmt;
+use std::sync::Arc;
+
+/// An entry of the administrator log of a group or channel, also known as its "recent actions".
+#[derive(Clone)]
+pub struct AdminLogEvent {
+    pub raw: tl::types::ChannelAdminLogEvent,
+    action: AdminLogAction,
+    chats: Arc<ChatMap>,
+}
+
+/// The action performed in an [`AdminLogEvent`].
+///
+/// Actions which change something carry both the previous and the new value. Actions which are
+/// not wrapped in a more convenient type are kept as [`AdminLogAction::Other`].
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum AdminLogAction {
+    /// The title of the chat was changed.
+    ChangeTitle { prev: String, new: String },
+    /// The description of the chat was changed.
+    ChangeAbout { prev: String, new: String },
+    /// The username of the chat was changed.
+    ChangeUsername { prev: String, new: String },
+    /// The photo of the chat was changed or removed.
+    ChangePhoto {
+        prev: Option<Photo>,
+        new: Option<Photo>,
+    },
+    /// A message was pinned or unpinned.
+    UpdatePinned(Message),
+    /// A message was edited.
+    EditMessage { prev: Message, new: Message },
+    /// A message was deleted.
+    DeleteMessage(Message),
+    /// A user joined the chat.
+    ParticipantJoin,
+    /// A user left the chat.
+    ParticipantLeave,
+    /// A user was invited to the chat.
+    ParticipantInvite(Participant),
+    /// A user joined the chat through an invite link.
+    ParticipantJoinByInvite(InviteLink),
+    /// A user's request to join the chat through an invite link was approved.
+    ParticipantJoinByRequest {
+        invite: InviteLink,
+        approved_by: i64,
+    },
+    /// A participant was banned, unbanned, restricted or unrestricted.
+    ParticipantToggleBan { prev: Participant, new: Participant },
+    /// A participant was promoted or demoted, or their administrator rights changed.
+    ParticipantToggleAdmin { prev: Participant, new: Participant },
+    /// The permissions members have by default were changed.
+    DefaultBannedRights {
+        prev: tl::types::ChatBannedRights,
+        new: tl::types::ChatBannedRights,
+    },
+    /// An invite link was edited.
+    EditInvite { prev: InviteLink, new: InviteLink },
+    /// An invite link was revoked.
+    RevokeInvite(InviteLink),
+    /// A revoked invite link was deleted.
+    DeleteInvite(InviteLink),
+    /// Slow mode was changed, with the delays in seconds (`0` meaning disabled).
+    ToggleSlowMode { prev: i32, new: i32 },
+    /// The linked discussion group or channel was changed, with the identifiers of the chats
+    /// (`0` meaning none).
+    ChangeLinkedChat { prev: i64, new: i64 },
+    /// Any other action.
+    Other(tl::enums::ChannelAdminLogEventAction),
+}
+
+impl AdminLogEvent {
+    pub(crate) fn from_raw(
+        client: &Client,
+        event: tl::enums::ChannelAdminLogEvent,
+        chats: &Arc<ChatMap>,
+    ) -> Self {
+        let tl::enums::ChannelAdminLogEvent::Event(raw) = event;
+        Self {
+            action: AdminLogAction::from_raw(client, &raw.action, chats),
+            raw,
+            chats: Arc::clone(chats),
+        }
+    }
+
+    /// The identifier of the event.
+    pub fn id(&self) -> i64 {
+        self.raw.id
+    }
+
+    /// When the action was performed.
+    pub fn date(&self) -> DateTime<Utc> {
+        utils::date(self.raw.date)
+    }
+
+    /// The identifier of the user who performed the action.
+    pub fn user_id(&self) -> i64 {
+        self.raw.user_id
+    }
+
+    /// The user who performed the action.
+    pub fn user(&self) -> Option<&Chat> {
+        self.chats.get(
+            &tl::types::PeerUser {
+                user_id: self.raw.user_id,
+            }
+            .into(),
+        )
+    }
+
+    /// The action that was performed.
+    pub fn action(&self) -> &AdminLogAction {
+        &self.action
+    }
+}
+
+impl AdminLogAction {
+    fn from_raw(
+        client: &Client,
+        action: &tl::enums::ChannelAdminLogEventAction,
+        chats: &Arc<ChatMap>,
+    ) -> Self {
+        use tl::enums::ChannelAdminLogEventAction as A;
+
+        let message =
+            |message: &tl::enums::Message| Message::from_raw(client, message.clone(), chats);
+        let participant = |participant: &tl::enums::ChannelParticipant| {
+            participant_from_raw(chats, participant.clone())
+        };
+        let invite = |invite: &tl::enums::ExportedChatInvite| InviteLink::from_raw(invite.clone());
+        let photo = |photo: &tl::enums::Photo| match photo {
+            tl::enums::Photo::Empty(_) => None,
+            photo => Some(Photo::from_raw(photo.clone())),
+        };
+
+        let converted = match action {
+            A::ChangeTitle(a) => Some(Self::ChangeTitle {
+                prev: a.prev_value.clone(),
+                new: a.new_value.clone(),
+            }),
+            A::ChangeAbout(a) => Some(Self::ChangeAbout {
+                prev: a.prev_value.clone(),
+                new: a.new_value.clone(),
+            }),
+            A::ChangeUsername(a) => Some(Self::ChangeUsername {
+                prev: a.prev_value.clone(),
+                new: a.new_value.clone(),
+            }),
+            A::ChangePhoto(a) => Some(Self::ChangePhoto {
+                prev: photo(&a.prev_photo),
+                new: photo(&a.new_photo),
+            }),
+            A::UpdatePinned(a) => message(&a.message).map(Self::UpdatePinned),
+            A::EditMessage(a) => message(&a.prev_message)
+                .zip(message(&a.new_message))
+                .map(|(prev, new)| Self::EditMessage { prev, new }),
+            A::DeleteMessage(a) => message(&a.message).map(Self::DeleteMessage),
+            A::ParticipantJoin => Some(Self::ParticipantJoin),
+            A::ParticipantLeave => Some(Self::ParticipantLeave),
+            A::ParticipantInvite(a) => participant(&a.participant).map(Self::ParticipantInvite),
+            A::ParticipantJoinByInvite(a) => invite(&a.invite).map(Self::ParticipantJoinByInvite),
+            A::ParticipantJoinByRequest(a) => {
+                invite(&a.invite).map(|invite| Self::ParticipantJoinByRequest {
+                    invite,
+                    approved_by: a.approved_by,
+                })
+            }
+            A::ParticipantToggleBan(a) => participant(&a.prev_participant)
+                .zip(participant(&a.new_participant))
+                .map(|(prev, new)| Self::ParticipantToggleBan { prev, new }),
+            A::ParticipantToggleAdmin(a) => participant(&a.prev_participant)
+                .zip(participant(&a.new_participant))
+                .map(|(prev, new)| Self::ParticipantToggleAdmin { prev, new }),
+            A::DefaultBannedRights(a) => {
+                let tl::enums::ChatBannedRights::Rights(prev) = a.prev_banned_rights.clone();
+                let tl::enums::ChatBannedRights::Rights(new) = a.new_banned_rights.clone();
+                Some(Self::DefaultBannedRights { prev, new })
+            }
+            A::ExportedInviteEdit(a) => invite(&a.prev_invite)
+                .zip(invite(&a.new_invite))
+                .map(|(prev, new)| Self::EditInvite { prev, new }),
+            A::ExportedInviteRevoke(a) => invite(&a.invite).map(Self::RevokeInvite),
+            A::ExportedInviteDelete(a) => invite(&a.invite).map(Self::DeleteInvite),
+            A::ToggleSlowMode(a) => Some(Self::ToggleSlowMode {
+                prev: a.prev_value,
+                new: a.new_value,
+            }),
+            A::ChangeLinkedChat(a) => Some(Self::ChangeLinkedChat {
+                prev: a.prev_value,
+                new: a.new_value,
+            }),
+            _ => None,
+        };
+
+        converted.unwrap_or_else(|| Self::Other(action.clone()))
+    }
+}
+
+/// Convert a participant found in the log, which may appear more than once in the same event
+/// (for example, before and after being banned).
+fn participant_from_raw(
+    chats: &ChatMap,
+    participant: tl::enums::ChannelParticipant,
+) -> Option<Participant> {
+    use tl::enums::ChannelParticipant as P;
+
+    let peer = match &participant {
+        P::Participant(p) => tl::types::PeerUser { user_id: p.user_id }.into(),
+        P::ParticipantSelf(p) => tl::types::PeerUser { user_id: p.user_id }.into(),
+        P::Creator(p) => tl::types::PeerUser { user_id: p.user_id }.into(),
+        P::Admin(p) => tl::types::PeerUser { user_id: p.user_id }.into(),
+        P::Banned(p) => p.peer.clone(),
+        P::Left(p) => p.peer.clone(),
+    };
+
+    match chats.get(&peer) {
+        Some(chat @ Chat::User(_)) => {
+            let mut single = ChatMap::single(chat.clone());
+            let single = Arc::get_mut(&mut single).unwrap();
+            Some(Participant::from_raw_channel(single, participant))
+        }
+        _ => None,
+    }
+}
+
+impl fmt::Debug for AdminLogEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdminLogEvent")
+            .field("id", &self.id())
+            .field("date", &self.date())
+            .field("user", &self.user())
+            .field("action", &self.action)
+            .finish()
+    }
+}
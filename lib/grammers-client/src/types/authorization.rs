@@ -0,0 +1,97 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use crate::utils;
+use chrono::{DateTime, Utc};
+use grammers_tl_types as tl;
+
+/// A session logged in to the account, as returned by [`Client::get_authorizations`].
+///
+/// [`Client::get_authorizations`]: crate::Client::get_authorizations
+#[derive(Clone, Debug, PartialEq)]
+pub struct Authorization {
+    pub raw: tl::types::Authorization,
+}
+
+impl Authorization {
+    pub(crate) fn from_raw(authorization: tl::enums::Authorization) -> Self {
+        let tl::enums::Authorization::Authorization(raw) = authorization;
+        Self { raw }
+    }
+
+    /// The identifier of the session, used to terminate it with [`Client::terminate_session`].
+    ///
+    /// [`Client::terminate_session`]: crate::Client::terminate_session
+    pub fn hash(&self) -> i64 {
+        self.raw.hash
+    }
+
+    /// Whether this is the session currently in use by the client.
+    pub fn is_current(&self) -> bool {
+        self.raw.current
+    }
+
+    /// Whether the session belongs to an official Telegram application.
+    pub fn is_official_app(&self) -> bool {
+        self.raw.official_app
+    }
+
+    /// Whether the session has not yet entered the two-step verification password.
+    pub fn is_password_pending(&self) -> bool {
+        self.raw.password_pending
+    }
+
+    /// The model of the device the session was created on.
+    pub fn device_model(&self) -> &str {
+        &self.raw.device_model
+    }
+
+    /// The platform of the device, such as "Android" or "Desktop".
+    pub fn platform(&self) -> &str {
+        &self.raw.platform
+    }
+
+    /// The version of the operating system of the device.
+    pub fn system_version(&self) -> &str {
+        &self.raw.system_version
+    }
+
+    /// The name of the application which created the session.
+    pub fn app_name(&self) -> &str {
+        &self.raw.app_name
+    }
+
+    /// The version of the application which created the session.
+    pub fn app_version(&self) -> &str {
+        &self.raw.app_version
+    }
+
+    /// The IP address the session was last used from.
+    pub fn ip(&self) -> &str {
+        &self.raw.ip
+    }
+
+    /// The country the session was last used from.
+    pub fn country(&self) -> &str {
+        &self.raw.country
+    }
+
+    /// The region the session was last used from.
+    pub fn region(&self) -> &str {
+        &self.raw.region
+    }
+
+    /// When the session was created.
+    pub fn date_created(&self) -> DateTime<Utc> {
+        utils::date(self.raw.date_created)
+    }
+
+    /// When the session was last active.
+    pub fn date_active(&self) -> DateTime<Utc> {
+        utils::date(self.raw.date_active)
+    }
+}
@@ -0,0 +1,1239 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Conversions from the types of the library into the JSON objects used by the [Bot API].
+//!
+//! This is useful to feed existing tooling which already understands the Bot API objects.
+//!
+//! The conversion is lossy in both directions. Information present in the Bot API objects which
+//! is not available through MTProto (such as the full replied message) is omitted, and fields
+//! which have no Bot API equivalent are kept under the `x_grammers` key.
+//!
+//! [Bot API]: https://core.telegram.org/bots/api
+pub mod file_id;
+
+use crate::types::{Chat, ChatMap, User};
+use file_id::{FileLocation, FileType};
+use grammers_session::{PackedChat, PackedType};
+use grammers_tl_types as tl;
+use serde_json::{json, Map, Value};
+
+/// Offset added to channel identifiers by the Bot API, before negating them.
+const CHANNEL_ID_OFFSET: i64 = 1_000_000_000_000;
+
+/// Convert the identifier of a chat into the identifier the Bot API would use for it.
+///
+/// Users keep their identifier, small group chats are negated, and channels are prefixed with
+/// `-100`.
+pub fn chat_id(chat: PackedChat) -> i64 {
+    match chat.ty {
+        PackedType::User | PackedType::Bot => chat.id,
+        PackedType::Chat => -chat.id,
+        PackedType::Megagroup | PackedType::Broadcast | PackedType::Gigagroup => {
+            -(CHANNEL_ID_OFFSET + chat.id)
+        }
+    }
+}
+
+fn peer_id(peer: &tl::enums::Peer) -> i64 {
+    match peer {
+        tl::enums::Peer::User(user) => user.user_id,
+        tl::enums::Peer::Chat(chat) => -chat.chat_id,
+        tl::enums::Peer::Channel(channel) => -(CHANNEL_ID_OFFSET + channel.channel_id),
+    }
+}
+
+/// Build an object and skip all the `null` values, as the Bot API omits absent fields.
+fn object<I: IntoIterator<Item = (&'static str, Value)>>(fields: I) -> Value {
+    Value::Object(
+        fields
+            .into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
+
+/// `true` is kept, but `false` is omitted, as the Bot API does for optional flags.
+fn flag(value: bool) -> Value {
+    if value {
+        Value::Bool(true)
+    } else {
+        Value::Null
+    }
+}
+
+pub(crate) fn user(user: &User) -> Value {
+    let raw = &user.raw;
+    object([
+        ("id", json!(raw.id)),
+        ("is_bot", json!(raw.bot)),
+        ("first_name", json!(raw.first_name.as_deref().unwrap_or(""))),
+        ("last_name", json!(raw.last_name)),
+        ("username", json!(user.username())),
+        ("language_code", json!(raw.lang_code)),
+        ("is_premium", flag(raw.premium)),
+    ])
+}
+
+pub(crate) fn chat(chat: &Chat) -> Value {
+    let id = chat_id(chat.pack());
+    match chat {
+        Chat::User(user) => object([
+            ("id", json!(id)),
+            ("type", json!("private")),
+            ("username", json!(user.username())),
+            (
+                "first_name",
+                json!(user.raw.first_name.as_deref().unwrap_or("")),
+            ),
+            ("last_name", json!(user.raw.last_name)),
+        ]),
+        Chat::Group(group) => {
+            let forum = match &group.raw {
+                tl::enums::Chat::Channel(channel) => channel.forum,
+                _ => false,
+            };
+            object([
+                ("id", json!(id)),
+                (
+                    "type",
+                    json!(if group.is_megagroup() {
+                        "supergroup"
+                    } else {
+                        "group"
+                    }),
+                ),
+                ("title", json!(group.title())),
+                ("username", json!(group.username())),
+                ("is_forum", flag(forum)),
+            ])
+        }
+        Chat::Channel(channel) => object([
+            ("id", json!(id)),
+            ("type", json!("channel")),
+            ("title", json!(channel.title())),
+            ("username", json!(channel.username())),
+        ]),
+    }
+}
+
+/// Find the user in the map, or build the smallest valid Bot API user otherwise.
+fn user_by_id(user_id: i64, chats: &ChatMap) -> Value {
+    match chats.get(&tl::types::PeerUser { user_id }.into()) {
+        Some(Chat::User(u)) => user(u),
+        _ => json!({ "id": user_id, "is_bot": false, "first_name": "" }),
+    }
+}
+
+/// Find the chat in the map, or build the smallest valid Bot API chat otherwise.
+fn chat_by_peer(peer: &tl::enums::Peer, chats: &ChatMap) -> Value {
+    match chats.get(peer) {
+        Some(c) => chat(c),
+        None => {
+            let ty = match peer {
+                tl::enums::Peer::User(_) => "private",
+                tl::enums::Peer::Chat(_) => "group",
+                tl::enums::Peer::Channel(_) => "channel",
+            };
+            json!({ "id": peer_id(peer), "type": ty })
+        }
+    }
+}
+
+pub(crate) fn entities(entities: &[tl::enums::MessageEntity]) -> Value {
+    use tl::enums::MessageEntity as E;
+
+    Value::Array(
+        entities
+            .iter()
+            .filter_map(|entity| {
+                let (ty, extra) = match entity {
+                    E::Mention(_) => ("mention", None),
+                    E::Hashtag(_) => ("hashtag", None),
+                    E::Cashtag(_) => ("cashtag", None),
+                    E::BotCommand(_) => ("bot_command", None),
+                    E::Url(_) => ("url", None),
+                    E::Email(_) => ("email", None),
+                    E::Phone(_) => ("phone_number", None),
+                    E::Bold(_) => ("bold", None),
+                    E::Italic(_) => ("italic", None),
+                    E::Underline(_) => ("underline", None),
+                    E::Strike(_) => ("strikethrough", None),
+                    E::Spoiler(_) => ("spoiler", None),
+                    E::Code(_) => ("code", None),
+                    E::Blockquote(e) => (
+                        if e.collapsed {
+                            "expandable_blockquote"
+                        } else {
+                            "blockquote"
+                        },
+                        None,
+                    ),
+                    E::Pre(e) => (
+                        "pre",
+                        Some((
+                            "language",
+                            if e.language.is_empty() {
+                                Value::Null
+                            } else {
+                                json!(e.language)
+                            },
+                        )),
+                    ),
+                    E::TextUrl(e) => ("text_link", Some(("url", json!(e.url)))),
+                    E::MentionName(e) => (
+                        "text_mention",
+                        Some((
+                            "user",
+                            json!({ "id": e.user_id, "is_bot": false, "first_name": "" }),
+                        )),
+                    ),
+                    E::CustomEmoji(e) => (
+                        "custom_emoji",
+                        Some(("custom_emoji_id", json!(e.document_id.to_string()))),
+                    ),
+                    E::Unknown(_)
+                    | E::BankCard(_)
+                    | E::InputMessageEntityMentionName(_)
+                    | E::FormattedDate(_)
+                    | E::DiffInsert(_)
+                    | E::DiffReplace(_)
+                    | E::DiffDelete(_) => return None,
+                };
+
+                let mut fields = vec![
+                    ("type", json!(ty)),
+                    ("offset", json!(entity.offset())),
+                    ("length", json!(entity.length())),
+                ];
+                fields.extend(extra);
+                Some(object(fields))
+            })
+            .collect(),
+    )
+}
+
+fn photo_sizes(photo: &tl::types::Photo) -> Value {
+    Value::Array(
+        photo
+            .sizes
+            .iter()
+            .filter_map(|size| {
+                let (ty, w, h, size) = match size {
+                    tl::enums::PhotoSize::Size(s) => (&s.r#type, s.w, s.h, s.size),
+                    tl::enums::PhotoSize::Progressive(s) => {
+                        (&s.r#type, s.w, s.h, s.sizes.last().copied().unwrap_or(0))
+                    }
+                    _ => return None,
+                };
+                let location = FileLocation {
+                    file_type: FileType::Photo,
+                    dc_id: photo.dc_id,
+                    id: photo.id,
+                    access_hash: photo.access_hash,
+                    file_reference: &photo.file_reference,
+                    thumb_type: Some(ty),
+                };
+                Some(json!({
+                    "file_id": location.file_id(),
+                    "file_unique_id": location.file_unique_id(),
+                    "width": w,
+                    "height": h,
+                    "file_size": size,
+                }))
+            })
+            .collect(),
+    )
+}
+
+/// Returns the name of the field used by the Bot API to store the document, and its value.
+fn document(document: &tl::types::Document) -> (&'static str, Value) {
+    use tl::enums::DocumentAttribute as A;
+
+    let mut file_name = None;
+    let mut image_size = None;
+    let mut animated = false;
+    let mut sticker = None;
+    let mut video = None;
+    let mut audio = None;
+    let mut custom_emoji = None;
+    for attr in document.attributes.iter() {
+        match attr {
+            A::Filename(a) => file_name = Some(a.file_name.as_str()),
+            A::ImageSize(a) => image_size = Some((a.w, a.h)),
+            A::Animated => animated = true,
+            A::Sticker(a) => sticker = Some(a),
+            A::Video(a) => video = Some(a),
+            A::Audio(a) => audio = Some(a),
+            A::CustomEmoji(a) => custom_emoji = Some(a),
+            A::HasStickers => {}
+        }
+    }
+
+    let file_type = if sticker.is_some() || custom_emoji.is_some() {
+        FileType::Sticker
+    } else if animated {
+        FileType::Animation
+    } else if video.is_some_and(|v| v.round_message) {
+        FileType::VideoNote
+    } else if video.is_some() {
+        FileType::Video
+    } else if audio.is_some_and(|a| a.voice) {
+        FileType::Voice
+    } else if audio.is_some() {
+        FileType::Audio
+    } else {
+        FileType::Document
+    };
+
+    let location = FileLocation {
+        file_type,
+        dc_id: document.dc_id,
+        id: document.id,
+        access_hash: document.access_hash,
+        file_reference: &document.file_reference,
+        thumb_type: None,
+    };
+    let mut fields = vec![
+        ("file_id", json!(location.file_id())),
+        ("file_unique_id", json!(location.file_unique_id())),
+    ];
+
+    let (width, height) = video
+        .map(|v| (v.w, v.h))
+        .or(image_size)
+        .map(|(w, h)| (json!(w), json!(h)))
+        .unwrap_or((Value::Null, Value::Null));
+    let duration = video
+        .map(|v| json!(v.duration.round() as i64))
+        .or(audio.map(|a| json!(a.duration)))
+        .unwrap_or(Value::Null);
+
+    let field = match file_type {
+        FileType::Sticker => {
+            let (ty, alt, set) = match (sticker, custom_emoji) {
+                (Some(s), _) => (
+                    if s.mask { "mask" } else { "regular" },
+                    &s.alt,
+                    &s.stickerset,
+                ),
+                (None, Some(e)) => ("custom_emoji", &e.alt, &e.stickerset),
+                (None, None) => unreachable!(),
+            };
+            fields.extend([
+                ("type", json!(ty)),
+                ("width", width),
+                ("height", height),
+                (
+                    "is_animated",
+                    json!(document.mime_type == "application/x-tgsticker"),
+                ),
+                ("is_video", json!(document.mime_type == "video/webm")),
+                (
+                    "emoji",
+                    if alt.is_empty() {
+                        Value::Null
+                    } else {
+                        json!(alt)
+                    },
+                ),
+                (
+                    "set_name",
+                    match set {
+                        tl::enums::InputStickerSet::ShortName(s) => json!(s.short_name),
+                        _ => Value::Null,
+                    },
+                ),
+            ]);
+            "sticker"
+        }
+        FileType::VideoNote => {
+            fields.extend([("length", width), ("duration", duration)]);
+            "video_note"
+        }
+        FileType::Voice => {
+            fields.push(("duration", duration));
+            "voice"
+        }
+        FileType::Audio => {
+            let audio = audio.unwrap();
+            fields.extend([
+                ("duration", duration),
+                ("performer", json!(audio.performer)),
+                ("title", json!(audio.title)),
+                ("file_name", json!(file_name)),
+            ]);
+            "audio"
+        }
+        FileType::Animation | FileType::Video => {
+            fields.extend([
+                ("width", width),
+                ("height", height),
+                ("duration", duration),
+                ("file_name", json!(file_name)),
+            ]);
+            if file_type == FileType::Animation {
+                "animation"
+            } else {
+                "video"
+            }
+        }
+        _ => {
+            fields.push(("file_name", json!(file_name)));
+            "document"
+        }
+    };
+
+    fields.extend([
+        ("mime_type", json!(document.mime_type)),
+        ("file_size", json!(document.size)),
+    ]);
+    // Stickers don't have a mime type in the Bot API.
+    if field == "sticker" {
+        fields.retain(|(key, _)| *key != "mime_type");
+    }
+    (field, object(fields))
+}
+
+fn location(geo: &tl::enums::GeoPoint) -> Value {
+    match geo {
+        tl::enums::GeoPoint::Empty => Value::Null,
+        tl::enums::GeoPoint::Point(p) => object([
+            ("latitude", json!(p.lat)),
+            ("longitude", json!(p.long)),
+            ("horizontal_accuracy", json!(p.accuracy_radius)),
+        ]),
+    }
+}
+
+fn poll(media: &tl::types::MessageMediaPoll) -> Value {
+    let tl::enums::Poll::Poll(poll) = &media.poll;
+    let tl::enums::PollResults::Results(results) = &media.results;
+    let tl::enums::TextWithEntities::Entities(question) = &poll.question;
+
+    let options = poll
+        .answers
+        .iter()
+        .filter_map(|answer| {
+            let answer = match answer {
+                tl::enums::PollAnswer::Answer(answer) => answer,
+                // Only used when creating polls, never received.
+                tl::enums::PollAnswer::InputPollAnswer(_) => return None,
+            };
+            let tl::enums::TextWithEntities::Entities(text) = &answer.text;
+            let voters = results
+                .results
+                .iter()
+                .flatten()
+                .map(|tl::enums::PollAnswerVoters::Voters(v)| v)
+                .find(|v| v.option == answer.option)
+                .and_then(|v| v.voters)
+                .unwrap_or(0);
+            Some(json!({ "text": text.text, "voter_count": voters }))
+        })
+        .collect::<Vec<_>>();
+
+    object([
+        ("id", json!(poll.id.to_string())),
+        ("question", json!(question.text)),
+        ("options", json!(options)),
+        (
+            "total_voter_count",
+            json!(results.total_voters.unwrap_or(0)),
+        ),
+        ("is_closed", json!(poll.closed)),
+        ("is_anonymous", json!(!poll.public_voters)),
+        ("type", json!(if poll.quiz { "quiz" } else { "regular" })),
+        ("allows_multiple_answers", json!(poll.multiple_choice)),
+        ("open_period", json!(poll.close_period)),
+        ("close_date", json!(poll.close_date)),
+    ])
+}
+
+/// Returns the fields used by the Bot API to represent the media, or the name of the media
+/// constructor if it has no equivalent.
+fn media(media: &tl::enums::MessageMedia) -> Result<Vec<(&'static str, Value)>, &'static str> {
+    use tl::enums::MessageMedia as M;
+
+    Ok(match media {
+        M::Photo(m) => match &m.photo {
+            Some(tl::enums::Photo::Photo(photo)) => vec![
+                ("photo", photo_sizes(photo)),
+                ("has_media_spoiler", flag(m.spoiler)),
+            ],
+            _ => return Err("photo_empty"),
+        },
+        M::Document(m) => match &m.document {
+            Some(tl::enums::Document::Document(d)) => {
+                let (field, value) = document(d);
+                vec![(field, value), ("has_media_spoiler", flag(m.spoiler))]
+            }
+            _ => return Err("document_empty"),
+        },
+        M::Contact(m) => vec![(
+            "contact",
+            object([
+                ("phone_number", json!(m.phone_number)),
+                ("first_name", json!(m.first_name)),
+                (
+                    "last_name",
+                    if m.last_name.is_empty() {
+                        Value::Null
+                    } else {
+                        json!(m.last_name)
+                    },
+                ),
+                (
+                    "user_id",
+                    if m.user_id == 0 {
+                        Value::Null
+                    } else {
+                        json!(m.user_id)
+                    },
+                ),
+                (
+                    "vcard",
+                    if m.vcard.is_empty() {
+                        Value::Null
+                    } else {
+                        json!(m.vcard)
+                    },
+                ),
+            ]),
+        )],
+        M::Geo(m) => vec![("location", location(&m.geo))],
+        M::GeoLive(m) => {
+            let mut location = location(&m.geo);
+            if let Value::Object(map) = &mut location {
+                map.insert("live_period".into(), json!(m.period));
+                if let Some(heading) = m.heading {
+                    map.insert("heading".into(), json!(heading));
+                }
+                if let Some(radius) = m.proximity_notification_radius {
+                    map.insert("proximity_alert_radius".into(), json!(radius));
+                }
+            }
+            vec![("location", location)]
+        }
+        M::Venue(m) => {
+            let foursquare = m.provider == "foursquare";
+            let location = location(&m.geo);
+            vec![
+                (
+                    "venue",
+                    object([
+                        ("location", location.clone()),
+                        ("title", json!(m.title)),
+                        ("address", json!(m.address)),
+                        (
+                            "foursquare_id",
+                            if foursquare {
+                                json!(m.venue_id)
+                            } else {
+                                Value::Null
+                            },
+                        ),
+                        (
+                            "foursquare_type",
+                            if foursquare {
+                                json!(m.venue_type)
+                            } else {
+                                Value::Null
+                            },
+                        ),
+                        (
+                            "google_place_id",
+                            if m.provider == "gplaces" {
+                                json!(m.venue_id)
+                            } else {
+                                Value::Null
+                            },
+                        ),
+                    ]),
+                ),
+                // The Bot API includes both fields for backwards compatibility.
+                ("location", location),
+            ]
+        }
+        M::Poll(m) => vec![("poll", poll(m))],
+        M::Dice(m) => vec![("dice", json!({ "emoji": m.emoticon, "value": m.value }))],
+        // Link previews are represented as part of the text in the Bot API.
+        M::WebPage(_) | M::Empty => Vec::new(),
+        M::Unsupported => return Err("unsupported"),
+        M::Game(_) => return Err("game"),
+        M::Invoice(_) => return Err("invoice"),
+        M::Story(_) => return Err("story"),
+        M::Giveaway(_) => return Err("giveaway"),
+        M::GiveawayResults(_) => return Err("giveaway_results"),
+        M::PaidMedia(_) => return Err("paid_media"),
+        M::ToDo(_) => return Err("todo"),
+        M::VideoStream(_) => return Err("video_stream"),
+    })
+}
+
+fn forward_origin(header: &tl::enums::MessageFwdHeader, chats: &ChatMap) -> Value {
+    let tl::enums::MessageFwdHeader::Header(header) = header;
+    let author = json!(header.post_author);
+    match (&header.from_id, header.channel_post) {
+        (Some(peer @ tl::enums::Peer::Channel(_)), Some(message_id)) => object([
+            ("type", json!("channel")),
+            ("date", json!(header.date)),
+            ("chat", chat_by_peer(peer, chats)),
+            ("message_id", json!(message_id)),
+            ("author_signature", author),
+        ]),
+        (Some(tl::enums::Peer::User(user)), _) => object([
+            ("type", json!("user")),
+            ("date", json!(header.date)),
+            ("sender_user", user_by_id(user.user_id, chats)),
+        ]),
+        (Some(peer), _) => object([
+            ("type", json!("chat")),
+            ("date", json!(header.date)),
+            ("sender_chat", chat_by_peer(peer, chats)),
+            ("author_signature", author),
+        ]),
+        (None, _) => object([
+            ("type", json!("hidden_user")),
+            ("date", json!(header.date)),
+            (
+                "sender_user_name",
+                json!(header.from_name.as_deref().unwrap_or("")),
+            ),
+        ]),
+    }
+}
+
+fn inline_keyboard(markup: &tl::enums::ReplyMarkup) -> Value {
+    use tl::enums::KeyboardButton as B;
+
+    let tl::enums::ReplyMarkup::ReplyInlineMarkup(markup) = markup else {
+        return Value::Null;
+    };
+    let rows = markup
+        .rows
+        .iter()
+        .map(|tl::enums::KeyboardButtonRow::Row(row)| {
+            row.buttons
+                .iter()
+                .map(|button| match button {
+                    B::Url(b) => json!({ "text": b.text, "url": b.url }),
+                    B::Callback(b) => json!({
+                        "text": b.text,
+                        "callback_data": String::from_utf8_lossy(&b.data),
+                    }),
+                    B::SwitchInline(b) if b.same_peer => json!({
+                        "text": b.text,
+                        "switch_inline_query_current_chat": b.query,
+                    }),
+                    B::SwitchInline(b) => json!({
+                        "text": b.text,
+                        "switch_inline_query": b.query,
+                    }),
+                    B::Game(b) => json!({ "text": b.text, "callback_game": {} }),
+                    B::Buy(b) => json!({ "text": b.text, "pay": true }),
+                    B::WebView(b) => json!({ "text": b.text, "web_app": { "url": b.url } }),
+                    B::SimpleWebView(b) => json!({ "text": b.text, "web_app": { "url": b.url } }),
+                    B::UrlAuth(b) => json!({ "text": b.text, "login_url": { "url": b.url } }),
+                    button => json!({ "text": button.text() }),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    json!({ "inline_keyboard": rows })
+}
+
+/// Returns the fields used by the Bot API to represent the service action, or the name of the
+/// action constructor if it has no equivalent.
+fn action(
+    action: &tl::enums::MessageAction,
+    message: &tl::types::Message,
+    chats: &ChatMap,
+) -> Result<Vec<(&'static str, Value)>, String> {
+    use tl::enums::MessageAction as A;
+
+    let users = |ids: &[i64]| Value::Array(ids.iter().map(|&id| user_by_id(id, chats)).collect());
+
+    Ok(match action {
+        A::ChatCreate(_) => vec![("group_chat_created", json!(true))],
+        A::ChannelCreate(_) => {
+            if matches!(chats.get(&message.peer_id), Some(Chat::Group(_))) {
+                vec![("supergroup_chat_created", json!(true))]
+            } else {
+                vec![("channel_chat_created", json!(true))]
+            }
+        }
+        A::ChatEditTitle(a) => vec![("new_chat_title", json!(a.title))],
+        A::ChatEditPhoto(a) => match &a.photo {
+            tl::enums::Photo::Photo(photo) => vec![("new_chat_photo", photo_sizes(photo))],
+            tl::enums::Photo::Empty(_) => vec![("delete_chat_photo", json!(true))],
+        },
+        A::ChatDeletePhoto => vec![("delete_chat_photo", json!(true))],
+        A::ChatAddUser(a) => vec![("new_chat_members", users(&a.users))],
+        A::ChatJoinedByLink(_) | A::ChatJoinedByRequest => {
+            let sender = match &message.from_id {
+                Some(tl::enums::Peer::User(u)) => u.user_id,
+                _ => return Err("chat_joined".into()),
+            };
+            vec![("new_chat_members", users(&[sender]))]
+        }
+        A::ChatDeleteUser(a) => vec![("left_chat_member", user_by_id(a.user_id, chats))],
+        A::ChatMigrateTo(a) => vec![(
+            "migrate_to_chat_id",
+            json!(-(CHANNEL_ID_OFFSET + a.channel_id)),
+        )],
+        A::ChannelMigrateFrom(a) => vec![("migrate_from_chat_id", json!(-a.chat_id))],
+        A::PinMessage => match &message.reply_to {
+            Some(tl::enums::MessageReplyHeader::Header(tl::types::MessageReplyHeader {
+                reply_to_msg_id: Some(id),
+                ..
+            })) => vec![(
+                "pinned_message",
+                json!({
+                    "chat": chat_by_peer(&message.peer_id, chats),
+                    "message_id": id,
+                    "date": 0,
+                }),
+            )],
+            _ => return Err("pin_message".into()),
+        },
+        A::SetMessagesTtl(a) => vec![(
+            "message_auto_delete_timer_changed",
+            json!({ "message_auto_delete_time": a.period }),
+        )],
+        action => {
+            // The debug representation starts with the name of the variant.
+            let name = format!("{:?}", action);
+            let name = name.split(['(', ' ']).next().unwrap_or_default();
+            return Err(name.to_string());
+        }
+    })
+}
+
+pub(crate) fn message(
+    message: &tl::types::Message,
+    raw_action: Option<&tl::enums::MessageAction>,
+    chats: &ChatMap,
+) -> Value {
+    let mut fields = vec![
+        ("message_id", json!(message.id)),
+        ("date", json!(message.date)),
+        ("chat", chat_by_peer(&message.peer_id, chats)),
+    ];
+    let mut extension = Map::new();
+
+    match &message.from_id {
+        Some(tl::enums::Peer::User(u)) => fields.push(("from", user_by_id(u.user_id, chats))),
+        Some(peer) => fields.push(("sender_chat", chat_by_peer(peer, chats))),
+        None if message.post => fields.push(("sender_chat", chat_by_peer(&message.peer_id, chats))),
+        None => {
+            // Incoming messages in private conversations don't include `from_id`.
+            if let (false, tl::enums::Peer::User(u)) = (message.out, &message.peer_id) {
+                fields.push(("from", user_by_id(u.user_id, chats)));
+            }
+        }
+    }
+
+    if let Some(header) = &message.fwd_from {
+        fields.push(("forward_origin", forward_origin(header, chats)));
+    }
+
+    if let Some(tl::enums::MessageReplyHeader::Header(reply)) = &message.reply_to {
+        if let Some(top_id) = reply.reply_to_top_id.filter(|_| reply.forum_topic) {
+            fields.push(("message_thread_id", json!(top_id)));
+        }
+        if let Some(id) = reply.reply_to_msg_id {
+            // The Bot API includes the full message, which is not available here.
+            extension.insert("reply_to_message_id".into(), json!(id));
+        }
+    }
+
+    if let Some(bot_id) = message.via_bot_id {
+        fields.push(("via_bot", user_by_id(bot_id, chats)));
+    }
+    fields.extend([
+        ("edit_date", json!(message.edit_date)),
+        ("has_protected_content", flag(message.noforwards)),
+        (
+            "media_group_id",
+            json!(message.grouped_id.map(|id| id.to_string())),
+        ),
+        ("author_signature", json!(message.post_author)),
+    ]);
+
+    let media_fields = match message.media.as_ref().map(media) {
+        Some(Ok(media_fields)) => media_fields,
+        Some(Err(name)) => {
+            extension.insert("media".into(), json!(name));
+            Vec::new()
+        }
+        None => Vec::new(),
+    };
+
+    if !message.message.is_empty() {
+        let (text, entities_key) = if media_fields.is_empty() {
+            ("text", "entities")
+        } else {
+            ("caption", "caption_entities")
+        };
+        fields.push((text, json!(message.message)));
+        if let Some(e) = message.entities.as_ref().filter(|e| !e.is_empty()) {
+            fields.push((entities_key, entities(e)));
+        }
+    }
+    fields.extend(media_fields);
+
+    if let Some(a) = raw_action {
+        match action(a, message, chats) {
+            Ok(action_fields) => fields.extend(action_fields),
+            Err(name) => {
+                extension.insert("action".into(), json!(name));
+            }
+        }
+    }
+
+    if let Some(markup) = &message.reply_markup {
+        fields.push(("reply_markup", inline_keyboard(markup)));
+    }
+
+    for (key, value) in [
+        ("out", flag(message.out)),
+        ("mentioned", flag(message.mentioned)),
+        ("media_unread", flag(message.media_unread)),
+        ("silent", flag(message.silent)),
+        ("pinned", flag(message.pinned)),
+        ("from_scheduled", flag(message.from_scheduled)),
+        ("edit_hide", flag(message.edit_hide)),
+        ("views", json!(message.views)),
+        ("forwards", json!(message.forwards)),
+        ("ttl_period", json!(message.ttl_period)),
+    ] {
+        if !value.is_null() {
+            extension.insert(key.into(), value);
+        }
+    }
+    if !extension.is_empty() {
+        fields.push(("x_grammers", Value::Object(extension)));
+    }
+
+    object(fields)
+}
+
+pub(crate) fn callback_query(query: &tl::types::UpdateBotCallbackQuery, chats: &ChatMap) -> Value {
+    object([
+        ("id", json!(query.query_id.to_string())),
+        ("from", user_by_id(query.user_id, chats)),
+        (
+            "message",
+            // Only the identifier is known, which is what an "inaccessible message" contains.
+            json!({
+                "chat": chat_by_peer(&query.peer, chats),
+                "message_id": query.msg_id,
+                "date": 0,
+            }),
+        ),
+        ("chat_instance", json!(query.chat_instance.to_string())),
+        (
+            "data",
+            json!(query
+                .data
+                .as_ref()
+                .map(|data| String::from_utf8_lossy(data).into_owned())),
+        ),
+        ("game_short_name", json!(query.game_short_name)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: i64, first_name: &str, username: Option<&str>) -> tl::enums::User {
+        tl::types::User {
+            is_self: false,
+            contact: false,
+            mutual_contact: false,
+            deleted: false,
+            bot: false,
+            bot_chat_history: false,
+            bot_nochats: false,
+            verified: false,
+            restricted: false,
+            min: false,
+            bot_inline_geo: false,
+            support: false,
+            scam: false,
+            apply_min_photo: false,
+            fake: false,
+            bot_attach_menu: false,
+            premium: false,
+            attach_menu_enabled: false,
+            bot_can_edit: false,
+            close_friend: false,
+            stories_hidden: false,
+            stories_unavailable: false,
+            contact_require_premium: false,
+            bot_business: false,
+            bot_has_main_app: false,
+            id,
+            access_hash: Some(0),
+            first_name: Some(first_name.to_string()),
+            last_name: None,
+            username: username.map(|u| u.to_string()),
+            phone: None,
+            photo: None,
+            status: None,
+            bot_info_version: None,
+            restriction_reason: None,
+            bot_inline_placeholder: None,
+            lang_code: Some("en".to_string()),
+            emoji_status: None,
+            usernames: None,
+            stories_max_id: None,
+            color: None,
+            profile_color: None,
+            bot_active_users: None,
+            bot_forum_view: false,
+            bot_forum_can_manage_topics: false,
+            bot_can_manage_bots: false,
+            bot_guestchat: false,
+            bot_guard: false,
+            bot_verification_icon: None,
+            send_paid_messages_stars: None,
+        }
+        .into()
+    }
+
+    fn message(id: i32, text: &str) -> tl::types::Message {
+        tl::types::Message {
+            out: false,
+            mentioned: false,
+            media_unread: false,
+            silent: false,
+            post: false,
+            from_scheduled: false,
+            legacy: false,
+            edit_hide: false,
+            pinned: false,
+            noforwards: false,
+            invert_media: false,
+            offline: false,
+            id,
+            from_id: Some(tl::types::PeerUser { user_id: 1 }.into()),
+            from_boosts_applied: None,
+            peer_id: tl::types::PeerChat { chat_id: 2 }.into(),
+            saved_peer_id: None,
+            fwd_from: None,
+            via_bot_id: None,
+            via_business_bot_id: None,
+            reply_to: None,
+            date: 1700000000,
+            message: text.to_string(),
+            media: None,
+            reply_markup: None,
+            entities: None,
+            views: None,
+            forwards: None,
+            replies: None,
+            edit_date: None,
+            post_author: None,
+            grouped_id: None,
+            reactions: None,
+            restriction_reason: None,
+            ttl_period: None,
+            quick_reply_shortcut_id: None,
+            effect: None,
+            factcheck: None,
+            video_processing_pending: false,
+            paid_suggested_post_stars: false,
+            paid_suggested_post_ton: false,
+            from_rank: None,
+            guestchat_via_from: None,
+            report_delivery_until_date: None,
+            paid_message_stars: None,
+            suggested_post: None,
+            schedule_repeat_period: None,
+            summary_from_language: None,
+            rich_message: None,
+        }
+    }
+
+    fn chats() -> std::sync::Arc<ChatMap> {
+        ChatMap::new(
+            vec![user(1, "Alice", Some("alice"))],
+            vec![tl::types::Chat {
+                creator: false,
+                left: false,
+                deactivated: false,
+                call_active: false,
+                call_not_empty: false,
+                noforwards: false,
+                id: 2,
+                title: "Group".to_string(),
+                photo: tl::enums::ChatPhoto::Empty,
+                participants_count: 2,
+                date: 0,
+                version: 0,
+                migrated_to: None,
+                admin_rights: None,
+                default_banned_rights: None,
+            }
+            .into()],
+        )
+    }
+
+    #[test]
+    fn check_chat_id() {
+        let packed = |ty, id| PackedChat {
+            ty,
+            id,
+            access_hash: None,
+        };
+        assert_eq!(chat_id(packed(PackedType::User, 123)), 123);
+        assert_eq!(chat_id(packed(PackedType::Chat, 123)), -123);
+        assert_eq!(chat_id(packed(PackedType::Megagroup, 123)), -1000000000123);
+        assert_eq!(chat_id(packed(PackedType::Broadcast, 123)), -1000000000123);
+    }
+
+    #[test]
+    fn check_text_message() {
+        let mut msg = message(10, "hello /start");
+        msg.entities = Some(vec![
+            tl::types::MessageEntityBold {
+                offset: 0,
+                length: 5,
+            }
+            .into(),
+            tl::types::MessageEntityBotCommand {
+                offset: 6,
+                length: 6,
+            }
+            .into(),
+        ]);
+        msg.pinned = true;
+
+        assert_eq!(
+            super::message(&msg, None, &chats()),
+            json!({
+                "message_id": 10,
+                "date": 1700000000,
+                "chat": { "id": -2, "type": "group", "title": "Group" },
+                "from": {
+                    "id": 1,
+                    "is_bot": false,
+                    "first_name": "Alice",
+                    "username": "alice",
+                    "language_code": "en",
+                },
+                "text": "hello /start",
+                "entities": [
+                    { "type": "bold", "offset": 0, "length": 5 },
+                    { "type": "bot_command", "offset": 6, "length": 6 },
+                ],
+                "x_grammers": { "pinned": true },
+            })
+        );
+    }
+
+    #[test]
+    fn check_photo_message() {
+        let mut msg = message(11, "caption");
+        msg.media = Some(
+            tl::types::MessageMediaPhoto {
+                spoiler: false,
+                photo: Some(
+                    tl::types::Photo {
+                        has_stickers: false,
+                        id: 10,
+                        access_hash: 20,
+                        file_reference: Vec::new(),
+                        date: 0,
+                        sizes: vec![
+                            tl::types::PhotoStrippedSize {
+                                r#type: "i".to_string(),
+                                bytes: vec![1, 2, 3],
+                            }
+                            .into(),
+                            tl::types::PhotoSize {
+                                r#type: "m".to_string(),
+                                w: 320,
+                                h: 240,
+                                size: 1000,
+                            }
+                            .into(),
+                            tl::types::PhotoSizeProgressive {
+                                r#type: "y".to_string(),
+                                w: 1280,
+                                h: 960,
+                                sizes: vec![100, 2000, 8000],
+                            }
+                            .into(),
+                        ],
+                        video_sizes: None,
+                        dc_id: 2,
+                    }
+                    .into(),
+                ),
+                ttl_seconds: None,
+                live_photo: false,
+                video: None,
+            }
+            .into(),
+        );
+
+        let json = super::message(&msg, None, &chats());
+        assert_eq!(json["caption"], "caption");
+        assert!(json.get("text").is_none());
+        assert_eq!(
+            json["photo"],
+            json!([
+                {
+                    "file_id": "AgADAgADCgAHFAAPAQADAgADbQAHMAQ",
+                    "file_unique_id": "AgADCgAH",
+                    "width": 320,
+                    "height": 240,
+                    "file_size": 1000,
+                },
+                {
+                    "file_id": "AgADAgADCgAHFAAPAQADAgADeQAHMAQ",
+                    "file_unique_id": "AgADCgAH",
+                    "width": 1280,
+                    "height": 960,
+                    "file_size": 8000,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn check_sticker_message() {
+        let mut msg = message(12, "");
+        msg.media = Some(
+            tl::types::MessageMediaDocument {
+                nopremium: false,
+                spoiler: false,
+                video: false,
+                round: false,
+                voice: false,
+                document: Some(
+                    tl::types::Document {
+                        id: 30,
+                        access_hash: 40,
+                        file_reference: Vec::new(),
+                        date: 0,
+                        mime_type: "image/webp".to_string(),
+                        size: 5000,
+                        thumbs: None,
+                        video_thumbs: None,
+                        dc_id: 4,
+                        attributes: vec![
+                            tl::types::DocumentAttributeImageSize { w: 512, h: 512 }.into(),
+                            tl::types::DocumentAttributeSticker {
+                                mask: false,
+                                alt: "👍".to_string(),
+                                stickerset: tl::types::InputStickerSetShortName {
+                                    short_name: "pack".to_string(),
+                                }
+                                .into(),
+                                mask_coords: None,
+                            }
+                            .into(),
+                        ],
+                    }
+                    .into(),
+                ),
+                alt_documents: None,
+                ttl_seconds: None,
+                video_cover: None,
+                video_timestamp: None,
+            }
+            .into(),
+        );
+
+        let json = super::message(&msg, None, &chats());
+        assert!(json.get("caption").is_none());
+        assert_eq!(
+            json["sticker"],
+            json!({
+                "file_id": "CAADBAADHgAHKAAHMAQ",
+                "file_unique_id": "AgADHgAH",
+                "type": "regular",
+                "width": 512,
+                "height": 512,
+                "is_animated": false,
+                "is_video": false,
+                "emoji": "👍",
+                "set_name": "pack",
+                "file_size": 5000,
+            })
+        );
+    }
+
+    #[test]
+    fn check_forwarded_message() {
+        let mut msg = message(13, "fwd");
+        msg.fwd_from = Some(
+            tl::types::MessageFwdHeader {
+                imported: false,
+                saved_out: false,
+                from_id: None,
+                from_name: Some("Hidden".to_string()),
+                date: 1600000000,
+                channel_post: None,
+                post_author: None,
+                saved_from_peer: None,
+                saved_from_msg_id: None,
+                saved_from_id: None,
+                saved_from_name: None,
+                saved_date: None,
+                psa_type: None,
+            }
+            .into(),
+        );
+        let json = super::message(&msg, None, &chats());
+        assert_eq!(
+            json["forward_origin"],
+            json!({
+                "type": "hidden_user",
+                "date": 1600000000,
+                "sender_user_name": "Hidden",
+            })
+        );
+
+        msg.fwd_from = Some(
+            tl::types::MessageFwdHeader {
+                imported: false,
+                saved_out: false,
+                from_id: Some(tl::types::PeerChannel { channel_id: 5 }.into()),
+                from_name: None,
+                date: 1600000000,
+                channel_post: Some(7),
+                post_author: Some("Bob".to_string()),
+                saved_from_peer: None,
+                saved_from_msg_id: None,
+                saved_from_id: None,
+                saved_from_name: None,
+                saved_date: None,
+                psa_type: None,
+            }
+            .into(),
+        );
+        let json = super::message(&msg, None, &chats());
+        assert_eq!(
+            json["forward_origin"],
+            json!({
+                "type": "channel",
+                "date": 1600000000,
+                "chat": { "id": -1000000000005i64, "type": "channel" },
+                "message_id": 7,
+                "author_signature": "Bob",
+            })
+        );
+    }
+}
@@ -0,0 +1,181 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Encoder for the `file_id` and `file_unique_id` strings used by the Bot API.
+//!
+//! The format is not documented by Telegram. It is the serialization used by TDLib for its
+//! "persistent" file identifiers, which is what the Bot API server hands out.
+use grammers_tl_types::Serializable;
+
+/// Set in the type when the identifier contains a file reference.
+const FILE_REFERENCE_FLAG: i32 = 1 << 25;
+
+/// Version of the persistent identifier format.
+const PERSISTENT_ID_VERSION: u8 = 4;
+
+/// Sub-version of the format (TDLib's current version at the time of writing).
+const PERSISTENT_ID_SUB_VERSION: u8 = 48;
+
+/// Source of photo sizes where the size is a thumbnail of the photo.
+const PHOTO_SIZE_SOURCE_THUMBNAIL: i32 = 1;
+
+/// Unique type used for photos and documents (they share the same space).
+const UNIQUE_TYPE_DOCUMENT: i32 = 2;
+
+/// The kind of file represented by a `file_id`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(i32)]
+pub enum FileType {
+    Thumbnail = 0,
+    ChatPhoto = 1,
+    Photo = 2,
+    Voice = 3,
+    Video = 4,
+    Document = 5,
+    Sticker = 8,
+    Audio = 9,
+    Animation = 10,
+    VideoNote = 13,
+}
+
+/// A remote file location, as needed to build a `file_id`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileLocation<'a> {
+    pub file_type: FileType,
+    pub dc_id: i32,
+    pub id: i64,
+    pub access_hash: i64,
+    pub file_reference: &'a [u8],
+    /// The type of the photo size (such as `"x"`), only used when `file_type` is a photo.
+    pub thumb_type: Option<&'a str>,
+}
+
+impl FileLocation<'_> {
+    /// Encode this location as a Bot API `file_id`.
+    pub fn file_id(&self) -> String {
+        let mut buf = Vec::new();
+        let mut ty = self.file_type as i32;
+        if !self.file_reference.is_empty() {
+            ty |= FILE_REFERENCE_FLAG;
+        }
+        ty.serialize(&mut buf);
+        self.dc_id.serialize(&mut buf);
+        if !self.file_reference.is_empty() {
+            self.file_reference.to_vec().serialize(&mut buf);
+        }
+        self.id.serialize(&mut buf);
+        self.access_hash.serialize(&mut buf);
+
+        if self.file_type == FileType::Photo {
+            // The legacy `volume_id` and `local_id` are still present, but always zero.
+            0i64.serialize(&mut buf);
+            PHOTO_SIZE_SOURCE_THUMBNAIL.serialize(&mut buf);
+            (FileType::Photo as i32).serialize(&mut buf);
+            let thumb_type = self
+                .thumb_type
+                .and_then(|t| t.bytes().next())
+                .unwrap_or(b'x');
+            (thumb_type as i32).serialize(&mut buf);
+            0i32.serialize(&mut buf);
+        }
+
+        let mut buf = rle_encode(&buf);
+        buf.push(PERSISTENT_ID_SUB_VERSION);
+        buf.push(PERSISTENT_ID_VERSION);
+        base64_url_encode(&buf)
+    }
+
+    /// Encode this location as a Bot API `file_unique_id`.
+    ///
+    /// Unlike the `file_id`, this identifier is the same for every bot, but cannot be used to
+    /// download or resend the file.
+    pub fn file_unique_id(&self) -> String {
+        let mut buf = Vec::new();
+        UNIQUE_TYPE_DOCUMENT.serialize(&mut buf);
+        self.id.serialize(&mut buf);
+        base64_url_encode(&rle_encode(&buf))
+    }
+}
+
+/// Run-length encode the zero bytes in the input, as done by TDLib.
+fn rle_encode(data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len());
+    let mut zeros = 0u8;
+    for &byte in data {
+        if byte == 0 {
+            zeros += 1;
+            if zeros == 250 {
+                result.extend([0, zeros]);
+                zeros = 0;
+            }
+        } else {
+            if zeros != 0 {
+                result.extend([0, zeros]);
+                zeros = 0;
+            }
+            result.push(byte);
+        }
+    }
+    if zeros != 0 {
+        result.extend([0, zeros]);
+    }
+    result
+}
+
+/// URL-safe base64 without padding.
+fn base64_url_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+    let mut result = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+
+        for i in 0..=chunk.len() {
+            result.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_rle_encode() {
+        assert_eq!(rle_encode(&[]), Vec::<u8>::new());
+        assert_eq!(rle_encode(&[1, 0, 0, 0, 2, 0]), vec![1, 0, 3, 2, 0, 1]);
+        assert_eq!(rle_encode(&[0; 251]), vec![0, 250, 0, 1]);
+    }
+
+    #[test]
+    fn check_base64_url_encode() {
+        assert_eq!(base64_url_encode(b""), "");
+        assert_eq!(base64_url_encode(b"f"), "Zg");
+        assert_eq!(base64_url_encode(b"fo"), "Zm8");
+        assert_eq!(base64_url_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_url_encode(&[0xfb, 0xff]), "-_8");
+    }
+
+    #[test]
+    fn check_document_file_id() {
+        let location = FileLocation {
+            file_type: FileType::Document,
+            dc_id: 2,
+            id: 1,
+            access_hash: 2,
+            file_reference: &[],
+            thumb_type: None,
+        };
+        // 05 00 00 00 | 02 00 00 00 | 01 00 .. | 02 00 .. | 30 04
+        assert_eq!(location.file_id(), "BQADAgADAQAHAgAHMAQ");
+        assert_eq!(location.file_unique_id(), "AgADAQAH");
+    }
+}
@@ -43,6 +43,7 @@ pub fn inline<T: Into<String>, B: Into<Vec<u8>>>(text: T, bytes: B) -> Inline {
             text: text.into(),
             data: bytes.into(),
             requires_password: false,
+            style: None,
         }
         .into(),
     }
@@ -59,6 +60,7 @@ pub fn switch_inline<T: Into<String>, Q: Into<String>>(text: T, query: Q) -> Inl
             query: query.into(),
             same_peer: true,
             peer_types: None,
+            style: None,
         }
         .into(),
     }
@@ -76,6 +78,7 @@ pub fn switch_inline_elsewhere<T: Into<String>, Q: Into<String>>(text: T, query:
             query: query.into(),
             same_peer: false,
             peer_types: None,
+            style: None,
         }
         .into(),
     }
@@ -90,6 +93,7 @@ pub fn url<T: Into<String>, U: Into<String>>(text: T, url: U) -> Inline {
         raw: tl::types::KeyboardButtonUrl {
             text: text.into(),
             url: url.into(),
+            style: None,
         }
         .into(),
     }
@@ -101,6 +105,7 @@ pub fn webview<T: Into<String>, U: Into<String>>(text: T, url: U) -> Inline {
         raw: tl::types::KeyboardButtonWebView {
             text: text.into(),
             url: url.into(),
+            style: None,
         }
         .into(),
     }
@@ -111,21 +116,33 @@ pub fn webview<T: Into<String>, U: Into<String>>(text: T, url: U) -> Inline {
 /// When pressed, the button's text will be sent as a normal message, as if the user had typed it.
 pub fn text<T: Into<String>>(text: T) -> Keyboard {
     Keyboard {
-        raw: tl::types::KeyboardButton { text: text.into() }.into(),
+        raw: tl::types::KeyboardButton {
+            text: text.into(),
+            style: None,
+        }
+        .into(),
     }
 }
 
 /// A keyboard button to request the user's contact information (including the phone).
 pub fn request_phone<T: Into<String>>(text: T) -> Keyboard {
     Keyboard {
-        raw: tl::types::KeyboardButtonRequestPhone { text: text.into() }.into(),
+        raw: tl::types::KeyboardButtonRequestPhone {
+            text: text.into(),
+            style: None,
+        }
+        .into(),
     }
 }
 
 /// A keyboard button to request the user's current geo-location.
 pub fn request_geo<T: Into<String>>(text: T) -> Keyboard {
     Keyboard {
-        raw: tl::types::KeyboardButtonRequestGeoLocation { text: text.into() }.into(),
+        raw: tl::types::KeyboardButtonRequestGeoLocation {
+            text: text.into(),
+            style: None,
+        }
+        .into(),
     }
 }
 
@@ -137,6 +154,7 @@ pub fn request_poll<T: Into<String>>(text: T) -> Keyboard {
         raw: tl::types::KeyboardButtonRequestPoll {
             text: text.into(),
             quiz: None,
+            style: None,
         }
         .into(),
     }
@@ -150,6 +168,7 @@ pub fn request_quiz<T: Into<String>>(text: T) -> Keyboard {
         raw: tl::types::KeyboardButtonRequestPoll {
             text: text.into(),
             quiz: Some(true),
+            style: None,
         }
         .into(),
     }
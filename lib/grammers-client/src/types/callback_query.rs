@@ -5,7 +5,8 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
-use crate::{types, Client, InputMessage};
+use crate::client::chats::local_error;
+use crate::{types, Client, EditMessageError, InputMessage, SendMessageError};
 use grammers_mtsender::InvocationError;
 use grammers_tl_types as tl;
 use std::convert::TryInto;
@@ -64,6 +65,11 @@ impl CallbackQuery {
         self.chats.get(&self.raw.peer).unwrap()
     }
 
+    /// The identifier of the message which contains the pressed inline button.
+    pub fn message_id(&self) -> i32 {
+        self.raw.msg_id
+    }
+
     /// They binary payload data contained by the inline button which was pressed.
     ///
     /// This data cannot be faked by the client, since Telegram will only accept "button presses"
@@ -76,19 +82,53 @@ impl CallbackQuery {
         self.raw.data.as_deref().unwrap()
     }
 
+    /// Convert this query into the JSON object the [Bot API] would use to represent it.
+    ///
+    /// Only the identifier of the message is known, so the `message` field will look like an
+    /// inaccessible message. Use [`CallbackQuery::load_message`] to fetch the full message.
+    ///
+    /// [Bot API]: https://core.telegram.org/bots/api#callbackquery
+    #[cfg(feature = "botapi")]
+    pub fn to_bot_api_json(&self) -> serde_json::Value {
+        types::bot_api::callback_query(&self.raw, &self.chats)
+    }
+
     /// Load the `Message` that contains the pressed inline button.
+    ///
+    /// Fails with `MESSAGE_ID_INVALID` if the message was deleted.
     pub async fn load_message(&self) -> Result<types::Message, InvocationError> {
-        Ok(self
-            .client
+        self.client
             .get_messages_by_id(self.chat(), &[self.raw.msg_id])
             .await?
             .pop()
-            .unwrap()
-            .unwrap())
+            .flatten()
+            .ok_or_else(|| local_error("MESSAGE_ID_INVALID"))
+    }
+
+    /// Answer the callback query without any text, and edit the message that contained the
+    /// button.
+    ///
+    /// This is a shorthand for `query.answer().edit(new_message)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(query: grammers_client::types::CallbackQuery) -> Result<(), Box<dyn std::error::Error>> {
+    /// if query.data() == b"next" {
+    ///     query.edit("Page 2").await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn edit<M: Into<InputMessage>>(
+        &self,
+        new_message: M,
+    ) -> Result<(), EditMessageError> {
+        self.answer().edit(new_message).await
     }
 
     /// Answer the callback query.
-    pub fn answer(&self) -> Answer {
+    pub fn answer(&self) -> Answer<'_> {
         Answer {
             request: tl::functions::messages::SetBotCallbackAnswer {
                 alert: false,
@@ -121,6 +161,15 @@ impl<'a> Answer<'a> {
         self
     }
 
+    /// Open the given URL when the answer is received.
+    ///
+    /// Only URLs to the bot's game (`t.me/your_bot?game=short_name`) and links to start the bot
+    /// with a parameter (`t.me/your_bot?start=parameter`) are allowed.
+    pub fn url<T: Into<String>>(mut self, url: T) -> Self {
+        self.request.url = Some(url.into());
+        self
+    }
+
     /// Configure the answer's text.
     ///
     /// The text will be displayed as an alert (popup modal window with the text, which the user
@@ -139,7 +188,7 @@ impl<'a> Answer<'a> {
     }
 
     /// [`Self::send`] the answer, and also edit the message that contained the button.
-    pub async fn edit<M: Into<InputMessage>>(self, new_message: M) -> Result<(), InvocationError> {
+    pub async fn edit<M: Into<InputMessage>>(self, new_message: M) -> Result<(), EditMessageError> {
         self.query.client.invoke(&self.request).await?;
         let chat = self.query.chat();
         let msg_id = self.query.raw.msg_id;
@@ -153,7 +202,7 @@ impl<'a> Answer<'a> {
     pub async fn respond<M: Into<InputMessage>>(
         self,
         message: M,
-    ) -> Result<types::Message, InvocationError> {
+    ) -> Result<types::Message, SendMessageError> {
         self.query.client.invoke(&self.request).await?;
         let chat = self.query.chat();
         self.query.client.send_message(chat, message).await
@@ -163,7 +212,7 @@ impl<'a> Answer<'a> {
     pub async fn reply<M: Into<InputMessage>>(
         self,
         message: M,
-    ) -> Result<types::Message, InvocationError> {
+    ) -> Result<types::Message, SendMessageError> {
         self.query.client.invoke(&self.request).await?;
         let chat = self.query.chat();
         let message = message.into();
@@ -88,6 +88,13 @@ impl Channel {
                             emoji_status: None,
                             level: None,
                             subscription_until_date: None,
+                            autotranslation: false,
+                            broadcast_messages_allowed: false,
+                            monoforum: false,
+                            forum_tabs: false,
+                            bot_verification_icon: None,
+                            send_paid_messages_stars: None,
+                            linked_monoforum_id: None,
                         },
                     }
                 } else {
@@ -120,6 +127,11 @@ impl Channel {
         self.raw.title.as_str()
     }
 
+    /// Whether the content of this channel is protected, and so cannot be forwarded or saved.
+    pub fn noforwards(&self) -> bool {
+        self.raw.noforwards
+    }
+
     /// Return the public @username of this channel, if any.
     ///
     /// The returned username does not contain the "@" prefix.
@@ -158,6 +170,14 @@ impl Channel {
         }
     }
 
+    /// Return the rights every participant lacks by default in this channel, if any.
+    pub fn default_banned_rights(&self) -> Option<&tl::types::ChatBannedRights> {
+        self.raw
+            .default_banned_rights
+            .as_ref()
+            .map(|tl::enums::ChatBannedRights::Rights(rights)| rights)
+    }
+
     /// Return the permissions of the logged-in user in this channel.
     pub fn admin_rights(&self) -> Option<&tl::types::ChatAdminRights> {
         match &self.raw.admin_rights {
@@ -178,6 +198,8 @@ impl Channel {
                 post_stories: true,
                 edit_stories: true,
                 delete_stories: true,
+                manage_direct_messages: false,
+                manage_ranks: false,
             }),
             None => None,
         }
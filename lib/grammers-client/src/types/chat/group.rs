@@ -58,15 +58,7 @@ impl Group {
     /// Note that if this group is migrated to a megagroup, both this group and the new one will
     /// exist as separate chats, with different identifiers.
     pub fn id(&self) -> i64 {
-        use tl::enums::Chat;
-
-        match &self.raw {
-            Chat::Empty(chat) => chat.id,
-            Chat::Chat(chat) => chat.id,
-            Chat::Forbidden(chat) => chat.id,
-            Chat::Channel(chat) => chat.id,
-            Chat::ChannelForbidden(chat) => chat.id,
-        }
+        self.raw.id()
     }
 
     /// Pack this group into a smaller representation that can be loaded later.
@@ -169,6 +161,56 @@ impl Group {
         }
     }
 
+    /// Return the rights every participant lacks by default in this group, if any.
+    pub fn default_banned_rights(&self) -> Option<&tl::types::ChatBannedRights> {
+        use tl::enums::Chat as C;
+
+        let rights = match &self.raw {
+            C::Empty(_) | C::Forbidden(_) | C::ChannelForbidden(_) => None,
+            C::Chat(chat) => chat.default_banned_rights.as_ref(),
+            C::Channel(channel) => channel.default_banned_rights.as_ref(),
+        };
+        rights.map(|tl::enums::ChatBannedRights::Rights(rights)| rights)
+    }
+
+    /// Whether the content of this group is protected, and so cannot be forwarded or saved.
+    pub fn noforwards(&self) -> bool {
+        use tl::enums::Chat as C;
+
+        match &self.raw {
+            C::Empty(_) | C::Forbidden(_) | C::ChannelForbidden(_) => false,
+            C::Chat(chat) => chat.noforwards,
+            C::Channel(channel) => channel.noforwards,
+        }
+    }
+
+    /// The supergroup this small group chat was upgraded to, if it was migrated.
+    ///
+    /// Once a group is migrated, it is deactivated and messages can no longer be sent to it.
+    /// The returned chat should be used instead.
+    pub fn migrated_to(&self) -> Option<PackedChat> {
+        match &self.raw {
+            tl::enums::Chat::Chat(tl::types::Chat {
+                migrated_to: Some(tl::enums::InputChannel::Channel(channel)),
+                ..
+            }) => Some(PackedChat {
+                ty: PackedType::Megagroup,
+                id: channel.channel_id,
+                access_hash: Some(channel.access_hash),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Whether this small group chat has been deactivated, usually because it was migrated to
+    /// a supergroup.
+    pub fn is_deactivated(&self) -> bool {
+        match &self.raw {
+            tl::enums::Chat::Chat(chat) => chat.deactivated,
+            _ => false,
+        }
+    }
+
     /// Returns true if this group is a megagroup (also known as supergroups).
     ///
     /// In case inner type of group is Channel, that means it's a megagroup.
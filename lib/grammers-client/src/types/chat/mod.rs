@@ -9,13 +9,13 @@ mod channel;
 mod group;
 mod user;
 
-use grammers_session::PackedType;
+use grammers_session::{CachedPeer, PackedType};
 use grammers_tl_types as tl;
 
 pub use channel::Channel;
 pub use grammers_session::PackedChat;
 pub use group::Group;
-pub use user::{Platform, RestrictionReason, User};
+pub use user::{Platform, RestrictionReason, User, UserStatus};
 
 /// A chat.
 ///
@@ -102,6 +102,14 @@ impl Chat {
         }
     }
 
+    /// Convert this chat into the JSON object the [Bot API] would use to represent it.
+    ///
+    /// [Bot API]: https://core.telegram.org/bots/api#chat
+    #[cfg(feature = "botapi")]
+    pub fn to_bot_api_json(&self) -> serde_json::Value {
+        crate::types::bot_api::chat(self)
+    }
+
     pub(crate) fn unpack(packed: PackedChat) -> Self {
         match packed.ty {
             PackedType::User => {
@@ -126,6 +134,7 @@ impl Chat {
                     access_hash: packed.access_hash.unwrap_or(0),
                     title: String::new(),
                     until_date: None,
+                    monoforum: false,
                 }
                 .into(),
             )),
@@ -137,12 +146,49 @@ impl Chat {
                     access_hash: packed.access_hash.unwrap_or(0),
                     title: String::new(),
                     until_date: None,
+                    monoforum: false,
                 }
                 .into(),
             )),
         }
     }
 
+    /// Build a chat from what the session remembered about it.
+    ///
+    /// Like [`Chat::unpack`], most of its information is missing.
+    pub(crate) fn from_cached(peer: CachedPeer) -> Self {
+        let mut chat = Self::unpack(peer.chat);
+        if let Chat::User(user) = &mut chat {
+            user.raw.username = peer.username;
+            user.raw.phone = peer.phone;
+        }
+        chat
+    }
+
+    /// What the session should remember about this chat.
+    ///
+    /// The access hash of "min" constructors can't be used outside of the context they were
+    /// received in, so it's not remembered.
+    pub(crate) fn to_cached(&self) -> CachedPeer {
+        let min = match self {
+            Self::User(user) => user.raw.min,
+            Self::Group(group) => matches!(&group.raw, tl::enums::Chat::Channel(c) if c.min),
+            Self::Channel(channel) => channel.raw.min,
+        };
+        let mut chat = self.pack();
+        if min {
+            chat.access_hash = None;
+        }
+        CachedPeer {
+            chat,
+            username: self.username().map(str::to_owned),
+            phone: match self {
+                Self::User(user) => user.phone().map(str::to_owned),
+                _ => None,
+            },
+        }
+    }
+
     /// Return the public @username of this chat, if any.
     ///
     /// The returned username does not contain the "@" prefix.
@@ -171,6 +217,28 @@ impl Chat {
         }
     }
 
+    /// Return the rights every participant lacks by default in this chat, if any.
+    ///
+    /// Private conversations with users have no default rights.
+    pub fn default_banned_rights(&self) -> Option<&tl::types::ChatBannedRights> {
+        match self {
+            Self::User(_) => None,
+            Self::Group(group) => group.default_banned_rights(),
+            Self::Channel(channel) => channel.default_banned_rights(),
+        }
+    }
+
+    /// Whether the content of this chat is protected, and so cannot be forwarded or saved.
+    ///
+    /// Private conversations with users are never protected.
+    pub fn noforwards(&self) -> bool {
+        match self {
+            Self::User(_) => false,
+            Self::Group(group) => group.noforwards(),
+            Self::Channel(channel) => channel.noforwards(),
+        }
+    }
+
     // If `Self` has `min` `access_hash`, returns a mutable reference to both `min` and `access_hash`.
     //
     // This serves as a way of checking "is it min?" and "update the access hash" both in one.
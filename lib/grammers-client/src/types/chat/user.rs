@@ -5,6 +5,8 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
+use crate::utils;
+use chrono::{DateTime, Utc};
 use grammers_session::{PackedChat, PackedType};
 use grammers_tl_types as tl;
 use std::fmt;
@@ -19,6 +21,44 @@ pub enum Platform {
     Other(String),
 }
 
+/// The presence of a user, also known as their "last seen" status.
+///
+/// Users may hide their exact status, in which case only an approximation is known.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UserStatus {
+    /// The user is online, until the given date unless they remain active.
+    Online { until: DateTime<Utc> },
+    /// The user is offline, and was last online at the given date.
+    Offline { was_online: DateTime<Utc> },
+    /// The user was online recently.
+    Recently,
+    /// The user was online within the last week.
+    LastWeek,
+    /// The user was online within the last month.
+    LastMonth,
+    /// The user was not online for a long time, or their status is hidden.
+    Hidden,
+}
+
+impl UserStatus {
+    pub(crate) fn from_raw(status: &tl::enums::UserStatus) -> Self {
+        use tl::enums::UserStatus as S;
+
+        match status {
+            S::Empty => Self::Hidden,
+            S::Online(s) => Self::Online {
+                until: utils::date(s.expires),
+            },
+            S::Offline(s) => Self::Offline {
+                was_online: utils::date(s.was_online),
+            },
+            S::Recently(_) => Self::Recently,
+            S::LastWeek(_) => Self::LastWeek,
+            S::LastMonth(_) => Self::LastMonth,
+        }
+    }
+}
+
 /// Contains the reason why a certain user is restricted.
 pub struct RestrictionReason {
     pub platforms: Vec<Platform>,
@@ -120,6 +160,13 @@ impl User {
                     color: None,
                     profile_color: None,
                     bot_active_users: None,
+                    bot_forum_view: false,
+                    bot_forum_can_manage_topics: false,
+                    bot_can_manage_bots: false,
+                    bot_guestchat: false,
+                    bot_guard: false,
+                    bot_verification_icon: None,
+                    send_paid_messages_stars: None,
                 },
                 tl::enums::User::User(user) => user,
             },
@@ -127,11 +174,11 @@ impl User {
     }
 
     /// Return the user presence status (also known as "last seen").
-    pub fn status(&self) -> &grammers_tl_types::enums::UserStatus {
+    pub fn status(&self) -> UserStatus {
         self.raw
             .status
             .as_ref()
-            .unwrap_or(&grammers_tl_types::enums::UserStatus::Empty)
+            .map_or(UserStatus::Hidden, UserStatus::from_raw)
     }
 
     /// Return the unique identifier for this user.
@@ -156,6 +203,14 @@ impl User {
         }
     }
 
+    /// Convert this user into the JSON object the [Bot API] would use to represent it.
+    ///
+    /// [Bot API]: https://core.telegram.org/bots/api#user
+    #[cfg(feature = "botapi")]
+    pub fn to_bot_api_json(&self) -> serde_json::Value {
+        crate::types::bot_api::user(self)
+    }
+
     /// Return the first name of this user.
     ///
     /// If the account was deleted, the returned string will be empty.
@@ -269,7 +324,7 @@ impl User {
     /// If the current user is a bot, does it have [privacy mode] enabled?
     ///
     /// * Bots with privacy enabled won't see messages in groups unless they are replied or the
-    /// command includes their name (`/command@bot`).
+    ///   command includes their name (`/command@bot`).
     /// * Bots with privacy disabled will be able to see all messages in a group.
     ///
     /// [privacy mode]: https://core.telegram.org/bots#privacy-mode
@@ -11,7 +11,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 /// Hashable `Peer`.
-#[derive(Hash, PartialEq, Eq)]
+#[derive(Clone, Copy, Hash, PartialEq, Eq)]
 pub(crate) enum Peer {
     User(i64),
     Chat(i64),
@@ -65,6 +65,27 @@ impl ChatMap {
         Arc::new(Self { map })
     }
 
+    /// Create a new chat set with the chats of this one along with the given chats.
+    pub(crate) fn extended(&self, chats: impl IntoIterator<Item = Chat>) -> Arc<Self> {
+        Arc::new(Self {
+            map: self
+                .map
+                .iter()
+                .map(|(peer, chat)| (*peer, chat.clone()))
+                .chain(
+                    chats
+                        .into_iter()
+                        .map(|chat| ((&chat.pack().to_peer()).into(), chat)),
+                )
+                .collect(),
+        })
+    }
+
+    /// Iterate over the chats in the set.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Chat> {
+        self.map.values()
+    }
+
     /// Retrieve the full `Chat` object given its `Peer`.
     pub fn get(&self, peer: &tl::enums::Peer) -> Option<&Chat> {
         self.map.get(&peer.into())
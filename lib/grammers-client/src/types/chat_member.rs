@@ -0,0 +1,149 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use super::{Chat, ChatMap, InviteLink, Participant, User};
+use crate::utils;
+use chrono::{DateTime, Utc};
+use grammers_tl_types as tl;
+use std::sync::Arc;
+
+/// Occurs when the membership of a user in a group or channel changes, such as when they join,
+/// leave, are promoted or are banned.
+///
+/// Bots only receive these for the chats they administrate.
+#[derive(Debug, Clone)]
+pub struct ChatMemberUpdate {
+    chat: Option<Chat>,
+    date: i32,
+    actor_id: i64,
+    actor: Option<User>,
+    user: User,
+    old_participant: Option<Participant>,
+    new_participant: Option<Participant>,
+    invite_link: Option<InviteLink>,
+    via_chatlist: bool,
+}
+
+impl ChatMemberUpdate {
+    /// Returns `None` if the user that changed is not known.
+    pub(crate) fn from_raw_chat(
+        update: tl::types::UpdateChatParticipant,
+        chats: &Arc<ChatMap>,
+    ) -> Option<Self> {
+        let user = known_user(chats, update.user_id)?;
+        let participant = |p| Participant::from_raw_chat(&mut user_map(&user), p);
+
+        Some(Self {
+            chat: chats
+                .get(
+                    &tl::types::PeerChat {
+                        chat_id: update.chat_id,
+                    }
+                    .into(),
+                )
+                .cloned(),
+            date: update.date,
+            actor_id: update.actor_id,
+            actor: known_user(chats, update.actor_id),
+            old_participant: update.prev_participant.map(participant),
+            new_participant: update.new_participant.map(participant),
+            user,
+            invite_link: update.invite.and_then(InviteLink::from_raw),
+            via_chatlist: false,
+        })
+    }
+
+    /// Returns `None` if the user that changed is not known.
+    pub(crate) fn from_raw_channel(
+        update: tl::types::UpdateChannelParticipant,
+        chats: &Arc<ChatMap>,
+    ) -> Option<Self> {
+        let user = known_user(chats, update.user_id)?;
+        let participant = |p| Participant::from_raw_channel(&mut user_map(&user), p);
+
+        Some(Self {
+            chat: chats
+                .get(
+                    &tl::types::PeerChannel {
+                        channel_id: update.channel_id,
+                    }
+                    .into(),
+                )
+                .cloned(),
+            date: update.date,
+            actor_id: update.actor_id,
+            actor: known_user(chats, update.actor_id),
+            old_participant: update.prev_participant.map(participant),
+            new_participant: update.new_participant.map(participant),
+            user,
+            invite_link: update.invite.and_then(InviteLink::from_raw),
+            via_chatlist: update.via_chatlist,
+        })
+    }
+
+    /// The group or channel where the change occurred, if Telegram sent it along.
+    pub fn chat(&self) -> Option<&Chat> {
+        self.chat.as_ref()
+    }
+
+    /// The user whose membership changed.
+    pub fn user(&self) -> &User {
+        &self.user
+    }
+
+    /// The user who made the change.
+    ///
+    /// This is the same as [`ChatMemberUpdate::user`] when the user joined or left on their own.
+    pub fn actor(&self) -> Option<&User> {
+        self.actor.as_ref()
+    }
+
+    /// The identifier of the user who made the change.
+    pub fn actor_id(&self) -> i64 {
+        self.actor_id
+    }
+
+    /// The participant before the change, or `None` if the user was not in the chat.
+    pub fn old_participant(&self) -> Option<&Participant> {
+        self.old_participant.as_ref()
+    }
+
+    /// The participant after the change, or `None` if the user is no longer in the chat.
+    pub fn new_participant(&self) -> Option<&Participant> {
+        self.new_participant.as_ref()
+    }
+
+    /// The invite link used by the user to join, if any.
+    pub fn invite_link(&self) -> Option<&InviteLink> {
+        self.invite_link.as_ref()
+    }
+
+    /// Whether the user joined by adding a chat folder which contained the chat.
+    pub fn via_chatlist(&self) -> bool {
+        self.via_chatlist
+    }
+
+    /// The date when the change occurred.
+    pub fn date(&self) -> DateTime<Utc> {
+        utils::date(self.date)
+    }
+}
+
+fn known_user(chats: &ChatMap, user_id: i64) -> Option<User> {
+    match chats.get(&tl::types::PeerUser { user_id }.into()) {
+        Some(Chat::User(user)) => Some(user.clone()),
+        _ => None,
+    }
+}
+
+/// Build a map with only the given user, from which participants referring to them can be
+/// created.
+fn user_map(user: &User) -> ChatMap {
+    Arc::try_unwrap(ChatMap::single(Chat::User(user.clone())))
+        .ok()
+        .expect("chat map was just created")
+}
@@ -21,8 +21,34 @@ use std::{
 };
 
 type BuilderRes = Result<(), InvocationError>;
+
+/// The maximum length of the custom rank of an administrator, in characters.
+const MAX_RANK_LENGTH: usize = 16;
 type AdminFutGen<F> = fn(AdminRightsBuilderInner) -> F;
 
+/// Administrator rights with every permission taken away.
+fn no_admin_rights() -> tl::types::ChatAdminRights {
+    tl::types::ChatAdminRights {
+        anonymous: false,
+        change_info: false,
+        post_messages: false,
+        edit_messages: false,
+        delete_messages: false,
+        ban_users: false,
+        invite_users: false,
+        pin_messages: false,
+        add_admins: false,
+        manage_call: false,
+        other: false,
+        manage_topics: false,
+        post_stories: false,
+        edit_stories: false,
+        delete_stories: false,
+        manage_direct_messages: false,
+        manage_ranks: false,
+    }
+}
+
 pub(crate) struct AdminRightsBuilderInner {
     client: Client,
     chat: PackedChat,
@@ -35,13 +61,22 @@ pub(crate) struct AdminRightsBuilderInner {
 impl AdminRightsBuilderInner {
     // Perform the call.
     pub(crate) async fn invoke(self) -> Result<(), InvocationError> {
+        if self.rank.chars().count() > MAX_RANK_LENGTH {
+            return Err(InvocationError::Rpc(RpcError {
+                code: 400,
+                name: "ADMIN_RANK_INVALID".to_string(),
+                value: None,
+                caused_by: None,
+            }));
+        }
+
         if let Some(chan) = self.chat.try_to_input_channel() {
             self.client
                 .invoke(&tl::functions::channels::EditAdmin {
                     channel: chan,
                     user_id: self.user.clone(),
                     admin_rights: tl::enums::ChatAdminRights::Rights(self.rights.clone()),
-                    rank: self.rank.clone(),
+                    rank: Some(self.rank.clone()),
                 })
                 .await
                 .map(drop)
@@ -55,7 +90,8 @@ impl AdminRightsBuilderInner {
                 || self.rights.invite_users
                 || self.rights.pin_messages
                 || self.rights.add_admins
-                || self.rights.manage_call;
+                || self.rights.manage_call
+                || self.rights.manage_topics;
             self.client
                 .invoke(&tl::functions::messages::EditChatAdmin {
                     chat_id: id,
@@ -117,23 +153,7 @@ impl<F: Future<Output = BuilderRes>> AdminRightsBuilder<F> {
                 peer: user.to_input_peer(),
                 user: user.to_input_user_lossy(),
                 rank: "".into(),
-                rights: tl::types::ChatAdminRights {
-                    anonymous: false,
-                    change_info: false,
-                    post_messages: false,
-                    edit_messages: false,
-                    delete_messages: false,
-                    ban_users: false,
-                    invite_users: false,
-                    pin_messages: false,
-                    add_admins: false,
-                    manage_call: false,
-                    other: false,
-                    manage_topics: false,
-                    post_stories: false,
-                    edit_stories: false,
-                    delete_stories: false,
-                },
+                rights: no_admin_rights(),
             }),
             gen,
             fut: None,
@@ -205,6 +225,8 @@ impl<F: Future<Output = BuilderRes>> AdminRightsBuilder<F> {
                         post_stories: true,
                         edit_stories: true,
                         delete_stories: true,
+                        manage_direct_messages: true,
+                        manage_ranks: true,
                     };
                     break;
                 }
@@ -214,6 +236,18 @@ impl<F: Future<Output = BuilderRes>> AdminRightsBuilder<F> {
         Ok(self)
     }
 
+    /// Take away every administrator right, which demotes the user back to a normal user once
+    /// applied.
+    ///
+    /// This is mostly useful after [`AdminRightsBuilder::load_current`], since no rights are
+    /// granted by default.
+    pub fn none(mut self) -> Self {
+        let s = self.inner_mut();
+        s.rights = no_admin_rights();
+        s.rank.clear();
+        self
+    }
+
     /// Whether the user will remain anonymous when sending messages.
     ///
     /// The sender of the anonymous messages becomes the group itself.
@@ -280,6 +314,12 @@ impl<F: Future<Output = BuilderRes>> AdminRightsBuilder<F> {
         self
     }
 
+    /// Whether the user will be able to create, edit and close the topics of a forum.
+    pub fn manage_topics(mut self, val: bool) -> Self {
+        self.inner_mut().rights.manage_topics = val;
+        self
+    }
+
     /// Whether the user will be able to add other administrators with the same or less
     /// permissions than the user itself.
     pub fn add_admins(mut self, val: bool) -> Self {
@@ -292,6 +332,9 @@ impl<F: Future<Output = BuilderRes>> AdminRightsBuilder<F> {
     /// This text will be shown instead of the "admin" badge.
     ///
     /// When left unspecified or empty, the default localized "admin" badge will be shown.
+    ///
+    /// The rank can be at most 16 characters long, or applying the rights will fail with
+    /// `ADMIN_RANK_INVALID`.
     pub fn rank<S: Into<String>>(mut self, val: S) -> Self {
         self.inner_mut().rank = val.into();
         self
@@ -306,9 +349,31 @@ pub(crate) struct BannedRightsBuilderInner {
     peer: tl::enums::InputPeer,
     user: tl::enums::InputUser,
     rights: tl::types::ChatBannedRights,
+    // Whether the rights are the default ones of the chat, rather than those of the user.
+    default: bool,
 }
 
 impl BannedRightsBuilderInner {
+    // Perform the call to change the default rights of the chat.
+    pub(crate) async fn invoke_default(self) -> Result<(), InvocationError> {
+        if self.chat.is_user() {
+            return Err(InvocationError::Rpc(RpcError {
+                code: 400,
+                name: "PEER_ID_INVALID".to_string(),
+                value: None,
+                caused_by: None,
+            }));
+        }
+
+        self.client
+            .invoke(&tl::functions::messages::EditChatDefaultBannedRights {
+                peer: self.chat.to_input_peer(),
+                banned_rights: tl::enums::ChatBannedRights::Rights(self.rights.clone()),
+            })
+            .await
+            .map(drop)
+    }
+
     // Perform the call.
     pub(crate) async fn invoke(self) -> Result<(), InvocationError> {
         if let Some(chan) = self.chat.try_to_input_channel() {
@@ -355,7 +420,8 @@ pin_project! {
     /// Certain groups (small group chats) only allow banning (disallow `view_messages`). Trying to
     /// disallow other permissions in these groups will fail.
     ///
-    /// Use [`Client::set_banned_rights`] to retrieve an instance of this type.
+    /// Use [`Client::set_banned_rights`] to retrieve an instance of this type, or
+    /// [`Client::set_default_permissions`] to edit the rights of everyone in the chat instead.
     #[must_use = "futures do nothing unless you `.await` or poll them"]
     pub struct BannedRightsBuilder<F: Future<Output = BuilderRes>> {
         inner: Option<BannedRightsBuilderInner>,
@@ -415,7 +481,10 @@ impl<F: Future<Output = BuilderRes>> BannedRightsBuilder<F> {
                     send_docs: false,
                     send_plain: false,
                     until_date: 0,
+                    edit_rank: false,
+                    send_reactions: false,
                 },
+                default: false,
             }),
             gen,
             fut: None,
@@ -423,6 +492,12 @@ impl<F: Future<Output = BuilderRes>> BannedRightsBuilder<F> {
         }
     }
 
+    pub(crate) fn new_default(client: Client, chat: PackedChat, gen: BannedFutGen<F>) -> Self {
+        let mut builder = Self::new(client, chat, chat, gen);
+        builder.inner_mut().default = true;
+        builder
+    }
+
     fn inner_mut(&mut self) -> &mut BannedRightsBuilderInner {
         // Unwrap safety: AdminRightsBuilderInner should never be None unless polled after being
         // resolved
@@ -433,7 +508,12 @@ impl<F: Future<Output = BuilderRes>> BannedRightsBuilder<F> {
     /// permissions without changing any of the previous ones.
     pub async fn load_current(mut self) -> Result<Self, InvocationError> {
         let s = self.inner_mut();
-        if let Some(chan) = s.chat.try_to_input_channel() {
+        if s.default {
+            let chat = s.client.unpack_chat(s.chat).await?;
+            if let Some(rights) = chat.default_banned_rights() {
+                s.rights = rights.clone();
+            }
+        } else if let Some(chan) = s.chat.try_to_input_channel() {
             let tl::enums::channels::ChannelParticipant::Participant(user) = s
                 .client
                 .invoke(&tl::functions::channels::GetParticipant {
@@ -45,6 +45,7 @@ impl ChatPhoto {
     }
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Clone, Debug, PartialEq)]
 #[non_exhaustive]
 pub enum Downloadable {
@@ -65,4 +66,35 @@ impl Downloadable {
             Self::PhotoSize(photo_size) => photo_size.to_raw_input_location(),
         }
     }
+
+    /// The datacenter where the file lives, if known before downloading it.
+    pub(crate) fn dc_id(&self) -> Option<i32> {
+        match self {
+            Self::UserProfilePhoto(user_profile_photo) => Some(user_profile_photo.raw.dc_id),
+            Self::ChatPhoto(chat_photo) => Some(chat_photo.raw.dc_id),
+            _ => None,
+        }
+    }
+
+    /// The message where the file was found, if known.
+    pub(crate) fn origin(&self) -> Option<&crate::types::media::MediaOrigin> {
+        match self {
+            Self::Media(media) => media.origin(),
+            _ => None,
+        }
+    }
+
+    /// The size of the file in bytes, if known before downloading it.
+    pub fn size(&self) -> Option<usize> {
+        use crate::types::Media;
+
+        let size = match self {
+            Self::Media(Media::Photo(photo)) => photo.size() as usize,
+            Self::Media(Media::Document(document)) => document.size() as usize,
+            Self::Media(Media::Sticker(sticker)) => sticker.document.size() as usize,
+            Self::PhotoSize(photo_size) => photo_size.size(),
+            _ => 0,
+        };
+        Some(size).filter(|&size| size != 0)
+    }
 }
@@ -0,0 +1,173 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Typed views over the formatting entities of a message.
+use crate::types::Chat;
+use grammers_tl_types as tl;
+
+/// A mention of a user inside the text of a message.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum Mention<'a> {
+    /// A mention by username, including the leading `@`.
+    Username(&'a str),
+    /// A mention of a user without a username, with the text used to refer to them.
+    User { text: &'a str, user: Chat },
+}
+
+/// A bot command inside the text of a message, such as `/start@my_bot`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BotCommand<'a> {
+    /// The command, without the leading `/`.
+    pub command: &'a str,
+    /// The username of the bot the command was directed to, without the leading `@`, if any.
+    pub bot: Option<&'a str>,
+}
+
+impl<'a> BotCommand<'a> {
+    pub(crate) fn parse(text: &'a str) -> Self {
+        let text = text.strip_prefix('/').unwrap_or(text);
+        match text.split_once('@') {
+            Some((command, bot)) => Self {
+                command,
+                bot: Some(bot),
+            },
+            None => Self {
+                command: text,
+                bot: None,
+            },
+        }
+    }
+}
+
+/// The offset and length of an entity, in UTF-16 code units.
+pub(crate) fn entity_range(entity: &tl::enums::MessageEntity) -> (i32, i32) {
+    use tl::enums::MessageEntity as E;
+
+    match entity {
+        E::Unknown(e) => (e.offset, e.length),
+        E::Mention(e) => (e.offset, e.length),
+        E::Hashtag(e) => (e.offset, e.length),
+        E::BotCommand(e) => (e.offset, e.length),
+        E::Url(e) => (e.offset, e.length),
+        E::Email(e) => (e.offset, e.length),
+        E::Bold(e) => (e.offset, e.length),
+        E::Italic(e) => (e.offset, e.length),
+        E::Code(e) => (e.offset, e.length),
+        E::Pre(e) => (e.offset, e.length),
+        E::TextUrl(e) => (e.offset, e.length),
+        E::MentionName(e) => (e.offset, e.length),
+        E::InputMessageEntityMentionName(e) => (e.offset, e.length),
+        E::Phone(e) => (e.offset, e.length),
+        E::Cashtag(e) => (e.offset, e.length),
+        E::Underline(e) => (e.offset, e.length),
+        E::Strike(e) => (e.offset, e.length),
+        E::BankCard(e) => (e.offset, e.length),
+        E::Spoiler(e) => (e.offset, e.length),
+        E::CustomEmoji(e) => (e.offset, e.length),
+        E::Blockquote(e) => (e.offset, e.length),
+        E::FormattedDate(e) => (e.offset, e.length),
+        E::DiffInsert(e) => (e.offset, e.length),
+        E::DiffReplace(e) => (e.offset, e.length),
+        E::DiffDelete(e) => (e.offset, e.length),
+    }
+}
+
+/// Slice the text using an offset and length measured in UTF-16 code units, as Telegram does.
+///
+/// Returns `None` if the range is out of bounds or does not fall on character boundaries.
+pub(crate) fn utf16_slice(text: &str, offset: i32, length: i32) -> Option<&str> {
+    if offset < 0 || length < 0 {
+        return None;
+    }
+    let (start, end) = (offset as usize, offset as usize + length as usize);
+
+    let mut units = 0;
+    let mut start_byte = None;
+    for (index, c) in text.char_indices() {
+        if units == start {
+            start_byte = Some(index);
+        }
+        if units == end {
+            return start_byte.map(|start| &text[start..index]);
+        }
+        if units > end {
+            return None;
+        }
+        units += c.len_utf16();
+    }
+
+    if units == start {
+        start_byte = Some(text.len());
+    }
+    if units == end {
+        start_byte.map(|start| &text[start..])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_utf16_slice_ascii() {
+        assert_eq!(utf16_slice("hello world", 6, 5), Some("world"));
+        assert_eq!(utf16_slice("hello world", 0, 0), Some(""));
+        assert_eq!(utf16_slice("hello world", 11, 0), Some(""));
+        assert_eq!(utf16_slice("hello world", 6, 6), None);
+        assert_eq!(utf16_slice("hello world", -1, 2), None);
+    }
+
+    #[test]
+    fn check_utf16_slice_emoji() {
+        // Each of these emoji takes two UTF-16 code units and four UTF-8 bytes.
+        let text = "👋🌍 hi https://example.com 🎉";
+        assert_eq!(utf16_slice(text, 0, 2), Some("👋"));
+        assert_eq!(utf16_slice(text, 2, 2), Some("🌍"));
+        assert_eq!(utf16_slice(text, 5, 2), Some("hi"));
+        assert_eq!(utf16_slice(text, 8, 19), Some("https://example.com"));
+        assert_eq!(utf16_slice(text, 28, 2), Some("🎉"));
+    }
+
+    #[test]
+    fn check_utf16_slice_surrogate_boundary() {
+        // Slicing in the middle of a surrogate pair must not panic.
+        assert_eq!(utf16_slice("👋a", 1, 2), None);
+        assert_eq!(utf16_slice("👋a", 0, 1), None);
+    }
+
+    #[test]
+    fn check_utf16_slice_mixed_width() {
+        // `é` is one UTF-16 unit but two bytes, `€` one unit but three bytes.
+        let text = "é€👍 ok";
+        assert_eq!(utf16_slice(text, 0, 1), Some("é"));
+        assert_eq!(utf16_slice(text, 1, 1), Some("€"));
+        assert_eq!(utf16_slice(text, 2, 2), Some("👍"));
+        assert_eq!(utf16_slice(text, 5, 2), Some("ok"));
+    }
+
+    #[test]
+    fn check_bot_command_parse() {
+        assert_eq!(
+            BotCommand::parse("/start"),
+            BotCommand {
+                command: "start",
+                bot: None
+            }
+        );
+        assert_eq!(
+            BotCommand::parse("/start@my_bot"),
+            BotCommand {
+                command: "start",
+                bot: Some("my_bot")
+            }
+        );
+    }
+}
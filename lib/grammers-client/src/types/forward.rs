@@ -0,0 +1,118 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use crate::types::{Chat, ChatMap};
+use crate::{utils, Client};
+use chrono::{DateTime, Utc};
+use grammers_tl_types as tl;
+use std::fmt;
+use std::sync::Arc;
+
+/// Information about the origin of a forwarded message.
+///
+/// The chats referenced by the header are resolved using the same chats that came along the
+/// message, so they can be used right away.
+#[derive(Clone)]
+pub struct Forward {
+    pub raw: tl::types::MessageFwdHeader,
+    pub(crate) client: Client,
+    pub(crate) chats: Arc<ChatMap>,
+}
+
+impl Forward {
+    pub fn from_raw(
+        client: &Client,
+        header: tl::enums::MessageFwdHeader,
+        chats: &Arc<ChatMap>,
+    ) -> Self {
+        let tl::enums::MessageFwdHeader::Header(header) = header;
+        Self {
+            raw: header,
+            client: client.clone(),
+            chats: chats.clone(),
+        }
+    }
+
+    fn resolve(&self, peer: Option<&tl::enums::Peer>) -> Option<Chat> {
+        peer.map(|peer| utils::always_find_entity(peer, &self.chats, &self.client))
+    }
+
+    /// The user or channel who originally sent the message.
+    ///
+    /// This will be `None` if the original sender has hidden their account from forwards, in
+    /// which case only their [`Forward::sender_name`] is known.
+    pub fn sender(&self) -> Option<Chat> {
+        self.resolve(self.raw.from_id.as_ref())
+    }
+
+    /// The name of the original sender, if they have hidden their account from forwards.
+    pub fn sender_name(&self) -> Option<&str> {
+        self.raw.from_name.as_deref()
+    }
+
+    /// The date when the original message was sent.
+    pub fn date(&self) -> DateTime<Utc> {
+        utils::date(self.raw.date)
+    }
+
+    /// The identifier of the original message, if it was a channel post.
+    pub fn channel_post(&self) -> Option<i32> {
+        self.raw.channel_post
+    }
+
+    /// The signature of the author of the original channel post, if any.
+    pub fn post_author(&self) -> Option<&str> {
+        self.raw.post_author.as_deref()
+    }
+
+    /// Whether the message was imported from a foreign chat service.
+    pub fn is_imported(&self) -> bool {
+        self.raw.imported
+    }
+
+    /// The chat where the message was saved from, when it's in "Saved Messages".
+    pub fn saved_from_chat(&self) -> Option<Chat> {
+        self.resolve(self.raw.saved_from_peer.as_ref())
+    }
+
+    /// The identifier of the message in the [`Forward::saved_from_chat`].
+    pub fn saved_from_msg_id(&self) -> Option<i32> {
+        self.raw.saved_from_msg_id
+    }
+
+    /// The sender of the message which was saved to "Saved Messages", if known.
+    pub fn saved_from_sender(&self) -> Option<Chat> {
+        self.resolve(self.raw.saved_from_id.as_ref())
+    }
+
+    /// The name of the sender of the message which was saved to "Saved Messages", if they have
+    /// hidden their account from forwards.
+    pub fn saved_from_name(&self) -> Option<&str> {
+        self.raw.saved_from_name.as_deref()
+    }
+
+    /// The date of the message which was saved to "Saved Messages", if known.
+    pub fn saved_date(&self) -> Option<DateTime<Utc>> {
+        self.raw.saved_date.map(utils::date)
+    }
+
+    /// The type of the public service announcement, if the message was forwarded as one.
+    pub fn psa_type(&self) -> Option<&str> {
+        self.raw.psa_type.as_deref()
+    }
+}
+
+impl fmt::Debug for Forward {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Forward")
+            .field("sender", &self.sender())
+            .field("sender_name", &self.sender_name())
+            .field("date", &self.date())
+            .field("channel_post", &self.channel_post())
+            .finish()
+    }
+}
@@ -0,0 +1,155 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use crate::types::{Chat, ChatMap, Photo};
+use crate::utils;
+use chrono::{DateTime, Utc};
+use grammers_tl_types as tl;
+use std::fmt;
+use std::sync::Arc;
+
+/// The raw full information about a chat, which depends on its kind.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum RawFullChat {
+    User(tl::types::UserFull),
+    Group(tl::types::ChatFull),
+    Channel(tl::types::ChannelFull),
+}
+
+/// Everything known about a user, group or channel, as returned by [`Client::get_chat`].
+///
+/// Unlike [`Chat`], which only contains the information sent along messages and other updates,
+/// this includes details such as the description or the amount of members.
+///
+/// [`Client::get_chat`]: crate::Client::get_chat
+#[derive(Clone)]
+pub struct FullChat {
+    pub raw: RawFullChat,
+    pub(crate) chat: Chat,
+    pub(crate) chats: Arc<ChatMap>,
+}
+
+impl FullChat {
+    /// The chat this information belongs to.
+    pub fn chat(&self) -> &Chat {
+        &self.chat
+    }
+
+    /// The description ("about" text) of the chat, or the bio of the user.
+    ///
+    /// Returns `None` if it is empty.
+    pub fn about(&self) -> Option<&str> {
+        let about = match &self.raw {
+            RawFullChat::User(user) => user.about.as_deref(),
+            RawFullChat::Group(group) => Some(group.about.as_str()),
+            RawFullChat::Channel(channel) => Some(channel.about.as_str()),
+        };
+        about.filter(|about| !about.is_empty())
+    }
+
+    /// How many members the group or channel has.
+    ///
+    /// Returns `None` for users, and for channels where the count is not visible.
+    pub fn participants_count(&self) -> Option<i32> {
+        match &self.raw {
+            RawFullChat::User(_) => None,
+            RawFullChat::Group(group) => match &group.participants {
+                tl::enums::ChatParticipants::Participants(participants) => {
+                    Some(participants.participants.len() as i32)
+                }
+                tl::enums::ChatParticipants::Forbidden(_) => None,
+            },
+            RawFullChat::Channel(channel) => channel.participants_count,
+        }
+    }
+
+    /// How many members of the supergroup are currently online.
+    pub fn online_count(&self) -> Option<i32> {
+        match &self.raw {
+            RawFullChat::Channel(channel) => channel.online_count,
+            _ => None,
+        }
+    }
+
+    /// How many seconds members of the supergroup must wait between sending messages, if slow
+    /// mode is enabled.
+    pub fn slowmode_seconds(&self) -> Option<i32> {
+        match &self.raw {
+            RawFullChat::Channel(channel) => channel.slowmode_seconds,
+            _ => None,
+        }
+    }
+
+    /// When the logged-in account will be able to send another message, if slow mode is
+    /// enabled and a message was sent recently.
+    pub fn slowmode_next_send_date(&self) -> Option<DateTime<Utc>> {
+        match &self.raw {
+            RawFullChat::Channel(channel) => channel.slowmode_next_send_date.map(utils::date),
+            _ => None,
+        }
+    }
+
+    /// The discussion group linked to a broadcast channel, or the channel linked to a discussion
+    /// group.
+    pub fn linked_chat(&self) -> Option<&Chat> {
+        match &self.raw {
+            RawFullChat::Channel(channel) => channel.linked_chat_id.and_then(|channel_id| {
+                self.chats
+                    .get(&tl::types::PeerChannel { channel_id }.into())
+            }),
+            _ => None,
+        }
+    }
+
+    /// The identifier of the pinned message in the chat, if any.
+    pub fn pinned_message_id(&self) -> Option<i32> {
+        match &self.raw {
+            RawFullChat::User(user) => user.pinned_msg_id,
+            RawFullChat::Group(group) => group.pinned_msg_id,
+            RawFullChat::Channel(channel) => channel.pinned_msg_id,
+        }
+    }
+
+    /// The reactions which can be used in the group or channel.
+    pub fn available_reactions(&self) -> Option<&tl::enums::ChatReactions> {
+        match &self.raw {
+            RawFullChat::User(_) => None,
+            RawFullChat::Group(group) => group.available_reactions.as_ref(),
+            RawFullChat::Channel(channel) => channel.available_reactions.as_ref(),
+        }
+    }
+
+    /// The permissions members of the group have by default.
+    ///
+    /// See also: [`Chat::default_banned_rights`].
+    pub fn default_banned_rights(&self) -> Option<&tl::types::ChatBannedRights> {
+        self.chat.default_banned_rights()
+    }
+
+    /// The current photo of the chat, in full.
+    pub fn photo(&self) -> Option<Photo> {
+        let photo = match &self.raw {
+            RawFullChat::User(user) => user.profile_photo.as_ref(),
+            RawFullChat::Group(group) => group.chat_photo.as_ref(),
+            RawFullChat::Channel(channel) => Some(&channel.chat_photo),
+        };
+        match photo {
+            Some(tl::enums::Photo::Photo(_)) => photo.cloned().map(Photo::from_raw),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Debug for FullChat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FullChat")
+            .field("chat", &self.chat)
+            .field("raw", &self.raw)
+            .finish()
+    }
+}
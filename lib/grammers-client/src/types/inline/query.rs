@@ -6,13 +6,20 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use super::super::{Chat, ChatMap, User};
+use super::super::media::{Document, Sticker};
+use super::super::{Chat, ChatMap, Photo, User};
+use crate::client::chats::local_error;
 use crate::{client::Client, utils::generate_random_id, InputMessage};
 use grammers_mtsender::InvocationError;
 use grammers_tl_types as tl;
+use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::fmt;
 use std::sync::Arc;
 
+/// The maximum amount of results Telegram accepts when answering an inline query.
+const MAX_RESULTS: usize = 50;
+
 /// Represents an inline query update, which occurs when you sign in as a bot and a user sends an
 /// inline query such as `@bot query`.
 #[derive(Clone)]
@@ -25,6 +32,7 @@ pub struct InlineQuery {
 /// An inline query answer builder.
 pub struct Answer {
     request: tl::functions::messages::SetInlineBotResults,
+    error: Option<InvocationError>,
     client: Client,
 }
 
@@ -32,11 +40,19 @@ pub struct Answer {
 ///
 /// The following types implement [`Into<InlineResult>`]:
 /// - [`Article`]
-pub struct InlineResult(tl::enums::InputBotInlineResult);
+/// - [`PhotoResult`]
+/// - [`DocumentResult`]
+/// - [`CachedMedia`]
+///
+/// Results that can never be sent, such as an [`Article`] without any text, are reported as an
+/// error when the [`Answer`] is sent, without making any request to Telegram.
+pub struct InlineResult(Result<tl::enums::InputBotInlineResult, &'static str>);
 
-impl From<InlineResult> for tl::enums::InputBotInlineResult {
-    fn from(result: InlineResult) -> Self {
-        result.0
+impl TryFrom<InlineResult> for tl::enums::InputBotInlineResult {
+    type Error = InvocationError;
+
+    fn try_from(result: InlineResult) -> Result<Self, Self::Error> {
+        result.0.map_err(local_error)
     }
 }
 
@@ -53,7 +69,7 @@ impl InlineQuery {
         }
     }
 
-    ///	User that sent the query
+    /// User that sent the query
     pub fn sender(&self) -> &User {
         match self
             .chats
@@ -81,23 +97,88 @@ impl InlineQuery {
     }
 
     /// Answer the inline query.
-    // TODO: add example
+    ///
+    /// At most 50 results can be sent at once, and their identifiers must be unique. Use
+    /// [`Answer::next_offset`] to let the user load more.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(query: grammers_client::types::InlineQuery) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::types::inline::query::{Article, PhotoResult};
+    ///
+    /// query
+    ///     .answer([
+    ///         Article::new("Echo", query.text()).into(),
+    ///         PhotoResult::new("https://example.com/cat.jpg").title("A cat").into(),
+    ///     ])
+    ///     .cache_time(60)
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn answer(&self, results: impl IntoIterator<Item = InlineResult>) -> Answer {
+        let (results, error) = match collect_results(results) {
+            Ok(results) => (results, None),
+            Err(e) => (Vec::new(), Some(e)),
+        };
+
         Answer {
             request: tl::functions::messages::SetInlineBotResults {
                 gallery: false,
                 private: false,
                 query_id: self.raw.query_id,
-                results: results.into_iter().map(Into::into).collect(),
+                results,
                 cache_time: 0,
                 next_offset: None,
                 switch_pm: None,
                 switch_webview: None,
             },
+            error,
             client: self.client.clone(),
         }
     }
 
+    /// Answer the inline query with one page of the given results.
+    ///
+    /// The page is chosen based on the [`InlineQuery::offset`], and [`Answer::next_offset`] is set
+    /// so that the user can load the next page, until there are no results left. Only the results
+    /// in the page are used, so they may be produced lazily.
+    ///
+    /// At most 50 results can be sent at once, so larger page sizes are reduced to that.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(query: grammers_client::types::InlineQuery) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::types::inline::query::Article;
+    ///
+    /// query
+    ///     .answer_paginated(
+    ///         (1..=1000).map(|n| Article::new(n.to_string(), n.to_string()).into()),
+    ///         20,
+    ///     )
+    ///     .cache_time(60)
+    ///     .private()
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn answer_paginated(
+        &self,
+        results: impl IntoIterator<Item = InlineResult>,
+        page_size: usize,
+    ) -> Answer {
+        let (page, next_offset) = paginate(results, self.offset(), page_size);
+        let answer = self.answer(page);
+        match next_offset {
+            Some(next_offset) => answer.next_offset(next_offset),
+            None => answer,
+        }
+    }
+
     /// Type of the chat from which the inline query was sent.
     pub fn peer_type(&self) -> Option<tl::enums::InlineQueryPeerType> {
         self.raw.peer_type.clone()
@@ -150,12 +231,18 @@ impl Answer {
     }
 
     /// Answers the inline query with the given results.
+    ///
+    /// If any of the results was invalid, the error is returned without contacting Telegram.
     pub async fn send(self) -> Result<(), InvocationError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
         self.client.invoke(&self.request).await?;
         Ok(())
     }
 }
 
+/// A result which sends a message, usually text, when chosen.
 pub struct Article {
     id: Option<String>,
     title: String,
@@ -206,39 +293,473 @@ impl Article {
 
 impl From<Article> for InlineResult {
     fn from(article: Article) -> Self {
-        Self(tl::enums::InputBotInlineResult::Result(
-            tl::types::InputBotInlineResult {
-                id: article
-                    .id
-                    .unwrap_or_else(|| generate_random_id().to_string()),
-                r#type: "article".into(),
-                title: Some(article.title),
-                description: article.description,
-                url: article.url,
-                thumb: article.thumb_url.map(|url| {
-                    tl::enums::InputWebDocument::Document(tl::types::InputWebDocument {
-                        url,
-                        size: 0,
-                        mime_type: "image/jpeg".into(),
-                        attributes: vec![],
-                    })
-                }),
-                content: None,
-                // TODO: also allow other types of messages than text
-                send_message: tl::enums::InputBotInlineMessage::Text(
-                    tl::types::InputBotInlineMessageText {
-                        no_webpage: !article.input_message.link_preview,
-                        invert_media: article.input_message.invert_media,
-                        message: article.input_message.text,
-                        entities: Some(article.input_message.entities),
-                        reply_markup: article.input_message.reply_markup,
-                    },
-                ),
-            },
-        ))
+        Self(
+            inline_message(article.input_message, false).map(|send_message| {
+                tl::types::InputBotInlineResult {
+                    id: article.id.unwrap_or_else(random_result_id),
+                    r#type: "article".into(),
+                    title: Some(article.title),
+                    description: article.description,
+                    url: article.url,
+                    thumb: article
+                        .thumb_url
+                        .map(|url| web_document(url, "image/jpeg".into())),
+                    content: None,
+                    send_message,
+                }
+                .into()
+            }),
+        )
+    }
+}
+
+/// A result showing a photo from an URL, which Telegram will download when it's chosen.
+///
+/// By default, choosing the result sends the photo without caption. Use [`PhotoResult::message`]
+/// to set a caption, or to send a different message altogether.
+pub struct PhotoResult {
+    id: Option<String>,
+    url: String,
+    thumb_url: Option<String>,
+    title: Option<String>,
+    description: Option<String>,
+    input_message: InputMessage,
+}
+
+impl PhotoResult {
+    /// Create a new photo result from the URL of a JPEG image.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            id: None,
+            url: url.into(),
+            thumb_url: None,
+            title: None,
+            description: None,
+            input_message: InputMessage::text(""),
+        }
+    }
+
+    /// Unique identifier of the result.
+    ///
+    /// By default, a random string will be used.
+    pub fn id(mut self, result_id: impl Into<String>) -> Self {
+        self.id = Some(result_id.into());
+        self
+    }
+
+    /// URL of the thumbnail for the result.
+    ///
+    /// By default, the photo itself is used.
+    pub fn thumb_url(mut self, thumb_url: impl Into<String>) -> Self {
+        self.thumb_url = Some(thumb_url.into());
+        self
+    }
+
+    /// Title of the result.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Short description of the result.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// The message to send when the result is chosen.
+    ///
+    /// If the message has no media, its text is used as the caption of the photo. Otherwise,
+    /// the message is sent instead of the photo. Only contacts, locations and venues are
+    /// supported as media.
+    pub fn message(mut self, input_message: impl Into<InputMessage>) -> Self {
+        self.input_message = input_message.into();
+        self
+    }
+}
+
+impl From<PhotoResult> for InlineResult {
+    fn from(photo: PhotoResult) -> Self {
+        if photo.url.is_empty() {
+            return Self(Err("PHOTO_CONTENT_URL_EMPTY"));
+        }
+
+        Self(
+            inline_message(photo.input_message, true).map(|send_message| {
+                let thumb_url = photo.thumb_url.unwrap_or_else(|| photo.url.clone());
+                tl::types::InputBotInlineResult {
+                    id: photo.id.unwrap_or_else(random_result_id),
+                    r#type: "photo".into(),
+                    title: photo.title,
+                    description: photo.description,
+                    url: None,
+                    thumb: Some(web_document(thumb_url, "image/jpeg".into())),
+                    content: Some(web_document(photo.url, "image/jpeg".into())),
+                    send_message,
+                }
+                .into()
+            }),
+        )
+    }
+}
+
+/// A result showing a file from an URL, which Telegram will download when it's chosen.
+///
+/// Telegram only accepts PDF and ZIP files (`application/pdf` and `application/zip`), as well as
+/// MP4 videos (`video/mp4`) this way. Use [`CachedMedia`] to send any other file.
+pub struct DocumentResult {
+    id: Option<String>,
+    url: String,
+    mime_type: String,
+    title: String,
+    thumb_url: Option<String>,
+    description: Option<String>,
+    input_message: InputMessage,
+}
+
+impl DocumentResult {
+    /// Create a new document result with the given title from the URL of a file.
+    pub fn new(
+        title: impl Into<String>,
+        url: impl Into<String>,
+        mime_type: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: None,
+            url: url.into(),
+            mime_type: mime_type.into(),
+            title: title.into(),
+            thumb_url: None,
+            description: None,
+            input_message: InputMessage::text(""),
+        }
+    }
+
+    /// Unique identifier of the result.
+    ///
+    /// By default, a random string will be used.
+    pub fn id(mut self, result_id: impl Into<String>) -> Self {
+        self.id = Some(result_id.into());
+        self
+    }
+
+    /// URL of the thumbnail (a JPEG image) for the result.
+    pub fn thumb_url(mut self, thumb_url: impl Into<String>) -> Self {
+        self.thumb_url = Some(thumb_url.into());
+        self
+    }
+
+    /// Short description of the result.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// The message to send when the result is chosen.
+    ///
+    /// Behaves the same as [`PhotoResult::message`].
+    pub fn message(mut self, input_message: impl Into<InputMessage>) -> Self {
+        self.input_message = input_message.into();
+        self
+    }
+}
+
+impl From<DocumentResult> for InlineResult {
+    fn from(document: DocumentResult) -> Self {
+        let r#type = match document.mime_type.as_str() {
+            "application/pdf" | "application/zip" => "file",
+            "video/mp4" => "video",
+            _ => return Self(Err("DOCUMENT_INVALID")),
+        };
+        if document.url.is_empty() {
+            return Self(Err("WEBDOCUMENT_URL_INVALID"));
+        }
+        if document.title.is_empty() {
+            return Self(Err("TITLE_INVALID"));
+        }
+
+        Self(
+            inline_message(document.input_message, true).map(|send_message| {
+                tl::types::InputBotInlineResult {
+                    id: document.id.unwrap_or_else(random_result_id),
+                    r#type: r#type.into(),
+                    title: Some(document.title),
+                    description: document.description,
+                    url: None,
+                    thumb: document
+                        .thumb_url
+                        .map(|url| web_document(url, "image/jpeg".into())),
+                    content: Some(web_document(document.url, document.mime_type)),
+                    send_message,
+                }
+                .into()
+            }),
+        )
     }
 }
 
+/// A result showing a photo or document already stored in Telegram's servers, such as media
+/// from a message or from a sticker set.
+///
+/// No download or upload happens when the result is chosen, so this is the fastest way to send
+/// media, and works for any kind of document (including stickers, voice notes or animations).
+pub struct CachedMedia {
+    id: Option<String>,
+    media: CachedKind,
+    title: Option<String>,
+    description: Option<String>,
+    input_message: InputMessage,
+}
+
+enum CachedKind {
+    Photo(tl::enums::InputPhoto),
+    Document {
+        r#type: &'static str,
+        document: tl::enums::InputDocument,
+    },
+}
+
+impl CachedMedia {
+    fn new(media: CachedKind) -> Self {
+        Self {
+            id: None,
+            media,
+            title: None,
+            description: None,
+            input_message: InputMessage::text(""),
+        }
+    }
+
+    /// Create a new result from an existing photo.
+    pub fn photo(photo: &Photo) -> Self {
+        Self::new(CachedKind::Photo(photo.to_raw_input_media().id))
+    }
+
+    /// Create a new result from an existing document.
+    ///
+    /// The way the result is displayed depends on the kind of document, for example, stickers
+    /// are shown as such.
+    pub fn document(document: &Document) -> Self {
+        let r#type = if Sticker::from_document(document).is_some() {
+            "sticker"
+        } else if document.is_animation() {
+            "gif"
+        } else if document.is_voice() {
+            "voice"
+        } else if document.is_video() {
+            "video"
+        } else if document.title().is_some() || document.performer().is_some() {
+            "audio"
+        } else {
+            "file"
+        };
+
+        Self::new(CachedKind::Document {
+            r#type,
+            document: document.to_raw_input_media().id,
+        })
+    }
+
+    /// Unique identifier of the result.
+    ///
+    /// By default, a random string will be used.
+    pub fn id(mut self, result_id: impl Into<String>) -> Self {
+        self.id = Some(result_id.into());
+        self
+    }
+
+    /// Title of the result. Only shown for documents.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Short description of the result. Only shown for documents.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// The message to send when the result is chosen.
+    ///
+    /// Behaves the same as [`PhotoResult::message`].
+    pub fn message(mut self, input_message: impl Into<InputMessage>) -> Self {
+        self.input_message = input_message.into();
+        self
+    }
+}
+
+impl From<CachedMedia> for InlineResult {
+    fn from(cached: CachedMedia) -> Self {
+        let send_message = match inline_message(cached.input_message, true) {
+            Ok(send_message) => send_message,
+            Err(e) => return Self(Err(e)),
+        };
+        let id = cached.id.unwrap_or_else(random_result_id);
+
+        Self(match cached.media {
+            CachedKind::Photo(tl::enums::InputPhoto::Empty) => Err("PHOTO_INVALID"),
+            CachedKind::Photo(photo) => Ok(tl::types::InputBotInlineResultPhoto {
+                id,
+                r#type: "photo".into(),
+                photo,
+                send_message,
+            }
+            .into()),
+            CachedKind::Document {
+                document: tl::enums::InputDocument::Empty,
+                ..
+            } => Err("DOCUMENT_INVALID"),
+            CachedKind::Document { r#type, document } => {
+                Ok(tl::types::InputBotInlineResultDocument {
+                    id,
+                    r#type: r#type.into(),
+                    title: cached.title,
+                    description: cached.description,
+                    document,
+                    send_message,
+                }
+                .into())
+            }
+        })
+    }
+}
+
+fn random_result_id() -> String {
+    generate_random_id().to_string()
+}
+
+fn web_document(url: String, mime_type: String) -> tl::enums::InputWebDocument {
+    tl::types::InputWebDocument {
+        url,
+        size: 0,
+        mime_type,
+        attributes: vec![],
+    }
+    .into()
+}
+
+/// Build the message sent when an inline result is chosen.
+///
+/// If `media_auto` is set, the result carries media of its own, and a message without media is
+/// used as its caption. Otherwise, a message without media must have text.
+fn inline_message(
+    message: InputMessage,
+    media_auto: bool,
+) -> Result<tl::enums::InputBotInlineMessage, &'static str> {
+    use tl::enums::InputMedia;
+
+    let reply_markup = message.reply_markup;
+    Ok(match message.media {
+        None if media_auto => tl::types::InputBotInlineMessageMediaAuto {
+            invert_media: message.invert_media,
+            message: message.text,
+            entities: Some(message.entities),
+            reply_markup,
+        }
+        .into(),
+        None if message.text.is_empty() => return Err("MESSAGE_EMPTY"),
+        None => tl::types::InputBotInlineMessageText {
+            no_webpage: !message.link_preview,
+            invert_media: message.invert_media,
+            message: message.text,
+            entities: Some(message.entities),
+            reply_markup,
+        }
+        .into(),
+        Some(InputMedia::GeoPoint(geo)) => tl::types::InputBotInlineMessageMediaGeo {
+            geo_point: geo.geo_point,
+            heading: None,
+            period: None,
+            proximity_notification_radius: None,
+            reply_markup,
+        }
+        .into(),
+        Some(InputMedia::GeoLive(geo)) => tl::types::InputBotInlineMessageMediaGeo {
+            geo_point: geo.geo_point,
+            heading: geo.heading,
+            period: geo.period,
+            proximity_notification_radius: geo.proximity_notification_radius,
+            reply_markup,
+        }
+        .into(),
+        Some(InputMedia::Venue(venue)) => tl::types::InputBotInlineMessageMediaVenue {
+            geo_point: venue.geo_point,
+            title: venue.title,
+            address: venue.address,
+            provider: venue.provider,
+            venue_id: venue.venue_id,
+            venue_type: venue.venue_type,
+            reply_markup,
+        }
+        .into(),
+        Some(InputMedia::Contact(contact)) => tl::types::InputBotInlineMessageMediaContact {
+            phone_number: contact.phone_number,
+            first_name: contact.first_name,
+            last_name: contact.last_name,
+            vcard: contact.vcard,
+            reply_markup,
+        }
+        .into(),
+        // Photos and documents must be sent as a `PhotoResult`, `DocumentResult` or `CachedMedia`.
+        Some(_) => return Err("MEDIA_INVALID"),
+    })
+}
+
+/// Take the page of results starting at the given offset, along with the offset of the next
+/// page if there are results left.
+///
+/// Offsets which were not produced by this function are treated as the start.
+fn paginate<T>(
+    results: impl IntoIterator<Item = T>,
+    offset: &str,
+    page_size: usize,
+) -> (Vec<T>, Option<String>) {
+    let offset = offset.parse::<usize>().unwrap_or(0);
+    let page_size = page_size.clamp(1, MAX_RESULTS);
+
+    let mut results = results.into_iter().skip(offset);
+    let page = results.by_ref().take(page_size).collect::<Vec<_>>();
+    let next_offset = results.next().map(|_| (offset + page.len()).to_string());
+
+    (page, next_offset)
+}
+
+/// Convert the results of an answer, failing on the first invalid one, and ensure Telegram
+/// would accept them together.
+fn collect_results(
+    results: impl IntoIterator<Item = InlineResult>,
+) -> Result<Vec<tl::enums::InputBotInlineResult>, InvocationError> {
+    use tl::enums::InputBotInlineResult as R;
+
+    let results = results
+        .into_iter()
+        .map(R::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if results.len() > MAX_RESULTS {
+        return Err(local_error("RESULTS_TOO_MUCH"));
+    }
+
+    let mut ids = HashSet::with_capacity(results.len());
+    for result in results.iter() {
+        let id = match result {
+            R::Result(r) => &r.id,
+            R::Photo(r) => &r.id,
+            R::Document(r) => &r.id,
+            R::Game(r) => &r.id,
+        };
+        if id.is_empty() {
+            return Err(local_error("RESULT_ID_EMPTY"));
+        }
+        if !ids.insert(id) {
+            return Err(local_error("RESULT_ID_DUPLICATE"));
+        }
+    }
+
+    Ok(results)
+}
+
 impl fmt::Debug for InlineQuery {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("InlineQuery")
@@ -261,3 +782,124 @@ impl fmt::Debug for Article {
             .finish()
     }
 }
+
+impl fmt::Debug for PhotoResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PhotoResult")
+            .field("id", &self.id)
+            .field("url", &self.url)
+            .field("thumb_url", &self.thumb_url)
+            .field("title", &self.title)
+            .field("description", &self.description)
+            .finish()
+    }
+}
+
+impl fmt::Debug for DocumentResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DocumentResult")
+            .field("id", &self.id)
+            .field("title", &self.title)
+            .field("url", &self.url)
+            .field("mime_type", &self.mime_type)
+            .field("thumb_url", &self.thumb_url)
+            .field("description", &self.description)
+            .finish()
+    }
+}
+
+impl fmt::Debug for CachedMedia {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachedMedia")
+            .field("id", &self.id)
+            .field("title", &self.title)
+            .field("description", &self.description)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_name(results: Vec<InlineResult>) -> Option<String> {
+        match collect_results(results) {
+            Ok(_) => None,
+            Err(InvocationError::Rpc(e)) => Some(e.name),
+            Err(e) => panic!("unexpected error: {e}"),
+        }
+    }
+
+    #[test]
+    fn check_valid_results() {
+        assert_eq!(
+            error_name(vec![
+                Article::new("title", "text").id("a").into(),
+                PhotoResult::new("https://example.com/a.jpg").id("b").into(),
+                DocumentResult::new("title", "https://example.com/a.pdf", "application/pdf")
+                    .id("c")
+                    .into(),
+            ]),
+            None
+        );
+    }
+
+    #[test]
+    fn check_invalid_results() {
+        assert_eq!(
+            error_name(vec![Article::new("title", "").into()]),
+            Some("MESSAGE_EMPTY".to_string())
+        );
+        assert_eq!(
+            error_name(vec![PhotoResult::new("").into()]),
+            Some("PHOTO_CONTENT_URL_EMPTY".to_string())
+        );
+        assert_eq!(
+            error_name(vec![PhotoResult::new("https://example.com/a.jpg")
+                .message(InputMessage::text("").photo_url("https://example.com/b.jpg"))
+                .into()]),
+            Some("MEDIA_INVALID".to_string())
+        );
+        assert_eq!(
+            error_name(vec![DocumentResult::new(
+                "title",
+                "https://example.com/a.txt",
+                "text/plain"
+            )
+            .into()]),
+            Some("DOCUMENT_INVALID".to_string())
+        );
+        assert_eq!(
+            error_name(vec![
+                Article::new("a", "a").id("x").into(),
+                Article::new("b", "b").id("x").into(),
+            ]),
+            Some("RESULT_ID_DUPLICATE".to_string())
+        );
+        assert_eq!(
+            error_name(
+                (0..=MAX_RESULTS)
+                    .map(|_| Article::new("a", "a").into())
+                    .collect()
+            ),
+            Some("RESULTS_TOO_MUCH".to_string())
+        );
+    }
+
+    #[test]
+    fn paginate_results() {
+        assert_eq!(
+            paginate(0..25, "", 10),
+            ((0..10).collect(), Some("10".into()))
+        );
+        assert_eq!(
+            paginate(0..25, "10", 10),
+            ((10..20).collect(), Some("20".into()))
+        );
+        assert_eq!(paginate(0..25, "20", 10), ((20..25).collect(), None));
+        assert_eq!(paginate(0..20, "10", 10), ((10..20).collect(), None));
+        assert_eq!(paginate(0..25, "30", 10), (Vec::new(), None));
+        assert_eq!(paginate(0..5, "bad", 10), ((0..5).collect(), None));
+        assert_eq!(paginate(0..100, "", 100).0.len(), MAX_RESULTS);
+    }
+}
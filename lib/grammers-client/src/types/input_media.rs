@@ -7,6 +7,7 @@
 // except according to those terms.
 use super::attributes::Attribute;
 use crate::types::{Media, Uploaded};
+use crate::utils;
 use grammers_tl_types as tl;
 
 /// Construct and send albums.
@@ -18,6 +19,78 @@ pub struct InputMedia {
     pub(crate) media: Option<tl::enums::InputMedia>,
     media_ttl: Option<i32>,
     mime_type: Option<String>,
+    copied_kind: Option<AlbumKind>,
+}
+
+/// How [`Client::send_album_with_strategy`] should deal with medias which cannot be part of the
+/// same album.
+///
+/// Telegram only allows photos and videos to be mixed in the same album. Documents can only be
+/// grouped with other documents, and audio files only with other audio files.
+///
+/// [`Client::send_album_with_strategy`]: crate::Client::send_album_with_strategy
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AlbumStrategy {
+    /// Fail with [`SendAlbumError::IncompatibleAlbum`] without sending anything.
+    ///
+    /// [`SendAlbumError::IncompatibleAlbum`]: crate::SendAlbumError::IncompatibleAlbum
+    #[default]
+    Strict,
+    /// Split the medias into the longest runs of compatible medias, and send each run as a
+    /// separate album, one after another.
+    SplitIncompatible,
+}
+
+/// Whether [`Client::edit_message_media`] should also change the caption of the message.
+///
+/// [`Client::edit_message_media`]: crate::Client::edit_message_media
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EditCaption {
+    /// Keep the caption and its formatting entities as they were.
+    #[default]
+    Keep,
+    /// Replace the caption with the one in the [`InputMedia`], even if it's empty.
+    Replace,
+}
+
+/// The kind of media as far as albums are concerned. Only medias of the same kind can be grouped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum AlbumKind {
+    /// Photos and videos.
+    Visual,
+    Document,
+    Audio,
+    /// Anything else, such as polls or stickers, which don't really belong in albums.
+    Other,
+}
+
+impl AlbumKind {
+    fn from_document(
+        mime_type: &str,
+        attributes: &[tl::enums::DocumentAttribute],
+        force_file: bool,
+    ) -> Self {
+        use tl::enums::DocumentAttribute as A;
+
+        if force_file {
+            return Self::Document;
+        }
+        for attr in attributes {
+            match attr {
+                A::Video(video) if !video.round_message => return Self::Visual,
+                A::Audio(audio) if !audio.voice => return Self::Audio,
+                A::Video(_) | A::Audio(_) | A::Sticker(_) => return Self::Other,
+                _ => {}
+            }
+        }
+        if mime_type.starts_with("video/") {
+            Self::Visual
+        } else if mime_type.starts_with("audio/") {
+            Self::Audio
+        } else {
+            Self::Document
+        }
+    }
 }
 
 impl InputMedia {
@@ -49,6 +122,8 @@ impl InputMedia {
                 file: file.raw,
                 stickers: None,
                 ttl_seconds: self.media_ttl,
+                live_photo: false,
+                video: None,
             })
             .into(),
         );
@@ -92,6 +167,8 @@ impl InputMedia {
                 attributes: vec![(tl::types::DocumentAttributeFilename { file_name }).into()],
                 stickers: None,
                 ttl_seconds: self.media_ttl,
+                video_cover: None,
+                video_timestamp: None,
             })
             .into(),
         );
@@ -134,6 +211,8 @@ impl InputMedia {
                 spoiler: false,
                 url: url.into(),
                 ttl_seconds: self.media_ttl,
+                video_cover: None,
+                video_timestamp: None,
             })
             .into(),
         );
@@ -175,6 +254,16 @@ impl InputMedia {
     /// You can use this to send media from another message without re-uploading it.
     pub fn copy_media(mut self, media: &Media) -> Self {
         self.media = media.to_raw_input_media();
+        self.copied_kind = Some(match media {
+            Media::Photo(_) => AlbumKind::Visual,
+            Media::Document(document) => match &document.raw.document {
+                Some(tl::enums::Document::Document(d)) => {
+                    AlbumKind::from_document(&d.mime_type, &d.attributes, false)
+                }
+                _ => AlbumKind::Document,
+            },
+            _ => AlbumKind::Other,
+        });
         self
     }
 
@@ -197,6 +286,8 @@ impl InputMedia {
                 attributes: vec![(tl::types::DocumentAttributeFilename { file_name }).into()],
                 stickers: None,
                 ttl_seconds: self.media_ttl,
+                video_cover: None,
+                video_timestamp: None,
             })
             .into(),
         );
@@ -230,12 +321,30 @@ impl InputMedia {
         self
     }
 
+    /// The kind of this media, used to determine which medias can be sent in the same album.
+    pub(crate) fn album_kind(&self) -> AlbumKind {
+        use tl::enums::InputMedia as M;
+
+        match &self.media {
+            Some(M::UploadedPhoto(_) | M::Photo(_) | M::PhotoExternal(_)) => AlbumKind::Visual,
+            Some(M::UploadedDocument(d)) => {
+                AlbumKind::from_document(&d.mime_type, &d.attributes, d.force_file)
+            }
+            Some(M::DocumentExternal(d)) => match utils::guess_mime_type(&d.url) {
+                Some(mime) => AlbumKind::from_document(mime, &[], false),
+                None => AlbumKind::Document,
+            },
+            Some(M::Document(_)) => self.copied_kind.unwrap_or(AlbumKind::Document),
+            _ => AlbumKind::Other,
+        }
+    }
+
     /// Return the mime type string for the given file.
     fn get_file_mime(&self, file: &Uploaded) -> String {
         if let Some(mime) = self.mime_type.as_ref() {
             mime.clone()
-        } else if let Some(mime) = mime_guess::from_path(file.name()).first() {
-            mime.essence_str().to_string()
+        } else if let Some(mime) = utils::guess_mime_type(file.name()) {
+            mime.to_string()
         } else {
             "application/octet-stream".to_string()
         }
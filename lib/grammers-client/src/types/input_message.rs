@@ -6,13 +6,19 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 use super::attributes::Attribute;
+use crate::types::media::Document;
 use crate::types::{Media, ReplyMarkup, Uploaded};
+use crate::utils;
 use grammers_tl_types as tl;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::convert::TryInto;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 // https://github.com/telegramdesktop/tdesktop/blob/e7fbcce9d9f0a8944eb2c34e74bd01b8776cb891/Telegram/SourceFiles/data/data_scheduled_messages.h#L52
 const SCHEDULE_ONCE_ONLINE: i32 = 0x7ffffffe;
 
+/// The emoji which can be sent as an animated dice, with a random value chosen by the server.
+pub const DICE_EMOJIS: [&str; 6] = ["🎲", "🎯", "🏀", "⚽", "🎳", "🎰"];
+
 /// Construct and send rich text messages with various options.
 #[derive(Default)]
 pub struct InputMessage {
@@ -63,11 +69,35 @@ impl InputMessage {
     /// Whether the link preview be shown for the message.
     ///
     /// This has no effect when sending media, which cannot contain a link preview.
+    ///
+    /// When editing, disabling the link preview removes the preview the message had.
     pub fn link_preview(mut self, link_preview: bool) -> Self {
         self.link_preview = link_preview;
         self
     }
 
+    /// Include a link preview for the given URL in the message, even if the URL is not present
+    /// in the text.
+    ///
+    /// The size of the media in the preview can be forced with `large_media`, or otherwise be
+    /// left for the clients to decide with `None`. Use [`InputMessage::invert_media`] to show
+    /// the preview above the text.
+    ///
+    /// If Telegram cannot generate a preview for the URL, the message is sent without it.
+    pub fn web_page(mut self, url: impl Into<String>, large_media: Option<bool>) -> Self {
+        self.link_preview = true;
+        self.media = Some(
+            (tl::types::InputMediaWebPage {
+                force_large_media: large_media == Some(true),
+                force_small_media: large_media == Some(false),
+                optional: true,
+                url: url.into(),
+            })
+            .into(),
+        );
+        self
+    }
+
     /// Defines the suggested reply markup for the message (such as adding inline buttons).
     /// This will be displayed below the message.
     ///
@@ -138,6 +168,8 @@ impl InputMessage {
                 file: file.raw,
                 stickers: None,
                 ttl_seconds: self.media_ttl,
+                live_photo: false,
+                video: None,
             })
             .into(),
         );
@@ -150,6 +182,13 @@ impl InputMessage {
     /// necessary.
     ///
     /// The text will be the caption of the photo, which may be empty for no caption.
+    ///
+    /// If Telegram cannot fetch the photo, sending the message fails with
+    /// [`SendMessageError::ExternalMediaUnavailable`], in which case the photo can be downloaded
+    /// and uploaded with [`Client::upload_stream`] instead.
+    ///
+    /// [`SendMessageError::ExternalMediaUnavailable`]: crate::client::messages::SendMessageError::ExternalMediaUnavailable
+    /// [`Client::upload_stream`]: crate::Client::upload_stream
     pub fn photo_url(mut self, url: impl Into<String>) -> Self {
         self.media = Some(
             (tl::types::InputMediaPhotoExternal {
@@ -181,6 +220,8 @@ impl InputMessage {
                 attributes: vec![(tl::types::DocumentAttributeFilename { file_name }).into()],
                 stickers: None,
                 ttl_seconds: self.media_ttl,
+                video_cover: None,
+                video_timestamp: None,
             })
             .into(),
         );
@@ -210,6 +251,286 @@ impl InputMessage {
         self
     }
 
+    /// Include the uploaded file as a voice note in the message.
+    ///
+    /// Voice notes should be OGG files encoded with OPUS. If the mime type cannot be inferred
+    /// from the file name, `audio/ogg` is used.
+    ///
+    /// The text will be the caption of the voice note, which may be empty for no caption.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: &mut grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::time::Duration;
+    /// use grammers_client::InputMessage;
+    ///
+    /// let voice = client.upload_file("voice.ogg").await?;
+    /// let message = InputMessage::text("").voice(voice).duration(Duration::from_secs(5));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn voice(self, file: Uploaded) -> Self {
+        self.audio_document(file, true)
+    }
+
+    /// Include the uploaded file as an audio track in the message.
+    ///
+    /// Use [`InputMessage::title`] and [`InputMessage::performer`] to set the metadata shown
+    /// in the music player.
+    ///
+    /// The text will be the caption of the audio, which may be empty for no caption.
+    pub fn audio(self, file: Uploaded) -> Self {
+        self.audio_document(file, false)
+    }
+
+    fn audio_document(mut self, file: Uploaded, voice: bool) -> Self {
+        let mime_type = match self.get_file_mime(&file) {
+            mime if voice && mime == "application/octet-stream" => "audio/ogg".to_string(),
+            mime => mime,
+        };
+        let file_name = file.name().to_string();
+        self.media = Some(
+            (tl::types::InputMediaUploadedDocument {
+                nosound_video: false,
+                force_file: false,
+                spoiler: false,
+                file: file.raw,
+                thumb: None,
+                mime_type,
+                attributes: vec![
+                    (tl::types::DocumentAttributeAudio {
+                        voice,
+                        duration: 0,
+                        title: None,
+                        performer: None,
+                        waveform: None,
+                    })
+                    .into(),
+                    (tl::types::DocumentAttributeFilename { file_name }).into(),
+                ],
+                stickers: None,
+                ttl_seconds: self.media_ttl,
+                video_cover: None,
+                video_timestamp: None,
+            })
+            .into(),
+        );
+        self
+    }
+
+    /// Include the uploaded file as a video in the message.
+    ///
+    /// Set [`InputMessage::supports_streaming`] so that official clients can start playing the
+    /// video before it is fully downloaded. A custom preview can be set with
+    /// [`InputMessage::thumbnail`].
+    ///
+    /// The text will be the caption of the video, which may be empty for no caption.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(client: &mut grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::time::Duration;
+    /// use grammers_client::InputMessage;
+    ///
+    /// let video = client.upload_file("video.mp4").await?;
+    /// let thumb = client.upload_file("thumb.jpg").await?;
+    /// let message = InputMessage::text("")
+    ///     .video(video)
+    ///     .width(1280)
+    ///     .height(720)
+    ///     .duration(Duration::from_secs(30))
+    ///     .supports_streaming(true)
+    ///     .thumbnail(thumb);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn video(mut self, file: Uploaded) -> Self {
+        let mime_type = self.get_file_mime(&file);
+        let file_name = file.name().to_string();
+        self.media = Some(
+            (tl::types::InputMediaUploadedDocument {
+                nosound_video: false,
+                force_file: false,
+                spoiler: false,
+                file: file.raw,
+                thumb: None,
+                mime_type,
+                attributes: vec![
+                    (tl::types::DocumentAttributeVideo {
+                        round_message: false,
+                        supports_streaming: false,
+                        nosound: false,
+                        duration: 0.0,
+                        w: 0,
+                        h: 0,
+                        preload_prefix_size: None,
+                        video_start_ts: None,
+                        video_codec: None,
+                    })
+                    .into(),
+                    (tl::types::DocumentAttributeFilename { file_name }).into(),
+                ],
+                stickers: None,
+                ttl_seconds: self.media_ttl,
+                video_cover: None,
+                video_timestamp: None,
+            })
+            .into(),
+        );
+        self
+    }
+
+    /// Include the uploaded file as an animation (also known as GIF) in the message.
+    ///
+    /// Telegram stores animations as silent MP4 videos, which loop automatically. The
+    /// dimensions and duration can be set with [`InputMessage::width`], [`InputMessage::height`]
+    /// and [`InputMessage::duration`].
+    ///
+    /// The text will be the caption of the animation, which may be empty for no caption.
+    pub fn animation(self, file: Uploaded) -> Self {
+        let mut message = self.video(file).nosound_video(true);
+        if let Some(tl::enums::InputMedia::UploadedDocument(document)) = &mut message.media {
+            document
+                .attributes
+                .insert(0, tl::enums::DocumentAttribute::Animated);
+        }
+        message
+    }
+
+    /// Whether the uploaded video has no sound, so that it is shown as an animation.
+    ///
+    /// This must be called *after* [`InputMessage::video`] or [`InputMessage::animation`].
+    pub fn nosound_video(mut self, nosound_video: bool) -> Self {
+        if let Some(tl::enums::InputMedia::UploadedDocument(document)) = &mut self.media {
+            document.nosound_video = nosound_video;
+        }
+        if let Some(video) = self.video_attribute_mut() {
+            video.nosound = nosound_video;
+        }
+        self
+    }
+
+    /// The video attribute of the uploaded document, if it is a video.
+    fn video_attribute_mut(&mut self) -> Option<&mut tl::types::DocumentAttributeVideo> {
+        match &mut self.media {
+            Some(tl::enums::InputMedia::UploadedDocument(document)) => {
+                document.attributes.iter_mut().find_map(|attr| match attr {
+                    tl::enums::DocumentAttribute::Video(video) => Some(video),
+                    _ => None,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Set the width of the video, in pixels.
+    ///
+    /// This must be called *after* [`InputMessage::video`].
+    pub fn width(mut self, width: i32) -> Self {
+        if let Some(video) = self.video_attribute_mut() {
+            video.w = width;
+        }
+        self
+    }
+
+    /// Set the height of the video, in pixels.
+    ///
+    /// This must be called *after* [`InputMessage::video`].
+    pub fn height(mut self, height: i32) -> Self {
+        if let Some(video) = self.video_attribute_mut() {
+            video.h = height;
+        }
+        self
+    }
+
+    /// Whether the video can be played before it is fully downloaded.
+    ///
+    /// The video must be encoded in a way that allows streaming (for example, an MP4 file with
+    /// the metadata at the start).
+    ///
+    /// This must be called *after* [`InputMessage::video`].
+    pub fn supports_streaming(mut self, supports_streaming: bool) -> Self {
+        if let Some(video) = self.video_attribute_mut() {
+            video.supports_streaming = supports_streaming;
+        }
+        self
+    }
+
+    /// Whether the video should be sent as a round video message (also known as video note).
+    ///
+    /// Round videos should be square and at most one minute long.
+    ///
+    /// This must be called *after* [`InputMessage::video`].
+    pub fn round_message(mut self, round_message: bool) -> Self {
+        if let Some(video) = self.video_attribute_mut() {
+            video.round_message = round_message;
+        }
+        self
+    }
+
+    /// The audio attribute of the uploaded document, if it is an audio or voice note.
+    fn audio_attribute_mut(&mut self) -> Option<&mut tl::types::DocumentAttributeAudio> {
+        match &mut self.media {
+            Some(tl::enums::InputMedia::UploadedDocument(document)) => {
+                document.attributes.iter_mut().find_map(|attr| match attr {
+                    tl::enums::DocumentAttribute::Audio(audio) => Some(audio),
+                    _ => None,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Set the duration of the audio, voice note or video.
+    ///
+    /// This must be called *after* [`InputMessage::audio`], [`InputMessage::voice`] or
+    /// [`InputMessage::video`].
+    pub fn duration(mut self, duration: Duration) -> Self {
+        if let Some(audio) = self.audio_attribute_mut() {
+            audio.duration = duration.as_secs().try_into().unwrap_or(i32::MAX);
+        }
+        if let Some(video) = self.video_attribute_mut() {
+            video.duration = duration.as_secs_f64();
+        }
+        self
+    }
+
+    /// Set the title of the audio.
+    ///
+    /// This must be called *after* [`InputMessage::audio`].
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        if let Some(audio) = self.audio_attribute_mut() {
+            audio.title = Some(title.into());
+        }
+        self
+    }
+
+    /// Set the performer (artist) of the audio.
+    ///
+    /// This must be called *after* [`InputMessage::audio`].
+    pub fn performer(mut self, performer: impl Into<String>) -> Self {
+        if let Some(audio) = self.audio_attribute_mut() {
+            audio.performer = Some(performer.into());
+        }
+        self
+    }
+
+    /// Set the waveform of the voice note, as 5-bit values packed together.
+    ///
+    /// The waveform of a received voice note can be obtained with [`Document::waveform`].
+    ///
+    /// This must be called *after* [`InputMessage::voice`].
+    ///
+    /// [`Document::waveform`]: crate::types::media::Document::waveform
+    pub fn waveform(mut self, waveform: Vec<u8>) -> Self {
+        if let Some(audio) = self.audio_attribute_mut() {
+            audio.waveform = Some(waveform);
+        }
+        self
+    }
+
     /// Include an external file as a document in the message.
     ///
     /// You can use this to send videos, stickers, audios, or uncompressed photos.
@@ -217,12 +538,19 @@ impl InputMessage {
     /// The Telegram server will be the one that downloads and includes the document as media.
     ///
     /// The text will be the caption of the document, which may be empty for no caption.
+    ///
+    /// The mime type is determined by Telegram from the fetched file. If Telegram cannot fetch
+    /// it, sending the message fails with [`SendMessageError::ExternalMediaUnavailable`].
+    ///
+    /// [`SendMessageError::ExternalMediaUnavailable`]: crate::client::messages::SendMessageError::ExternalMediaUnavailable
     pub fn document_url(mut self, url: impl Into<String>) -> Self {
         self.media = Some(
             (tl::types::InputMediaDocumentExternal {
                 spoiler: false,
                 url: url.into(),
                 ttl_seconds: self.media_ttl,
+                video_cover: None,
+                video_timestamp: None,
             })
             .into(),
         );
@@ -259,6 +587,53 @@ impl InputMessage {
         self
     }
 
+    /// Include an existing sticker in the message.
+    ///
+    /// The document can come from a received [`Sticker`] or from a sticker set fetched with
+    /// [`Client::get_sticker_set`]. The sticker is sent without uploading it again.
+    ///
+    /// [`Sticker`]: crate::types::media::Sticker
+    /// [`Client::get_sticker_set`]: crate::Client::get_sticker_set
+    pub fn sticker(mut self, document: &Document) -> Self {
+        let mut media = document.to_raw_input_media();
+        media.ttl_seconds = None;
+        self.media = Some(media.into());
+        self
+    }
+
+    /// Include a sticker in the message by its document identifier, access hash and file
+    /// reference, as found in a [`Document`] previously received.
+    pub fn sticker_by_id(self, id: i64, access_hash: i64, file_reference: Vec<u8>) -> Self {
+        self.document_by_id(id, access_hash, file_reference)
+    }
+
+    /// Include a document the account has seen before in the message, by its identifier,
+    /// access hash and file reference, without uploading it again.
+    ///
+    /// This is how animations from a "GIF keyboard" are sent. The values can be saved from
+    /// [`Document::id`], [`Document::access_hash`] and [`Document::file_reference`]. The file
+    /// reference expires after a while, in which case Telegram responds with
+    /// `FILE_REFERENCE_EXPIRED` and the document must be seen again.
+    pub fn document_by_id(mut self, id: i64, access_hash: i64, file_reference: Vec<u8>) -> Self {
+        self.media = Some(
+            (tl::types::InputMediaDocument {
+                spoiler: false,
+                id: tl::types::InputDocument {
+                    id,
+                    access_hash,
+                    file_reference,
+                }
+                .into(),
+                ttl_seconds: None,
+                query: None,
+                video_cover: None,
+                video_timestamp: None,
+            })
+            .into(),
+        );
+        self
+    }
+
     /// Copy media from an existing message.
     ///
     /// You can use this to send media from another message without re-uploading it.
@@ -286,6 +661,8 @@ impl InputMessage {
                 attributes: vec![(tl::types::DocumentAttributeFilename { file_name }).into()],
                 stickers: None,
                 ttl_seconds: self.media_ttl,
+                video_cover: None,
+                video_timestamp: None,
             })
             .into(),
         );
@@ -295,13 +672,76 @@ impl InputMessage {
     /// Change the media's Time To Live (TTL).
     ///
     /// For example, this enables you to send a `photo` that can only be viewed for a certain
-    /// amount of seconds before it expires.
+    /// amount of seconds before it expires. Self-destructing media can only be sent in private
+    /// chats.
     ///
     /// Not all media supports this feature.
     ///
-    /// This method should be called before setting any media, else it won't have any effect.
+    /// This method can be called before or after setting the media.
     pub fn media_ttl(mut self, seconds: i32) -> Self {
         self.media_ttl = if seconds < 0 { None } else { Some(seconds) };
+        use tl::enums::InputMedia as M;
+        match &mut self.media {
+            Some(M::UploadedPhoto(m)) => m.ttl_seconds = self.media_ttl,
+            Some(M::Photo(m)) => m.ttl_seconds = self.media_ttl,
+            Some(M::PhotoExternal(m)) => m.ttl_seconds = self.media_ttl,
+            Some(M::UploadedDocument(m)) => m.ttl_seconds = self.media_ttl,
+            Some(M::Document(m)) => m.ttl_seconds = self.media_ttl,
+            Some(M::DocumentExternal(m)) => m.ttl_seconds = self.media_ttl,
+            _ => {}
+        }
+        self
+    }
+
+    /// Whether the media should be hidden behind a blur until the user taps on it.
+    ///
+    /// Only photos and documents such as videos can be marked as spoilers.
+    ///
+    /// This must be called *after* setting the media.
+    pub fn spoiler(mut self, spoiler: bool) -> Self {
+        use tl::enums::InputMedia as M;
+        match &mut self.media {
+            Some(M::UploadedPhoto(m)) => m.spoiler = spoiler,
+            Some(M::Photo(m)) => m.spoiler = spoiler,
+            Some(M::PhotoExternal(m)) => m.spoiler = spoiler,
+            Some(M::UploadedDocument(m)) => m.spoiler = spoiler,
+            Some(M::Document(m)) => m.spoiler = spoiler,
+            Some(M::DocumentExternal(m)) => m.spoiler = spoiler,
+            _ => {}
+        }
+        self
+    }
+
+    /// Whether the uploaded document should be sent as a plain file, instead of being shown
+    /// as a photo, video or audio according to its mime type.
+    ///
+    /// This must be called *after* setting a file.
+    pub fn force_file(mut self, force_file: bool) -> Self {
+        if let Some(tl::enums::InputMedia::UploadedDocument(document)) = &mut self.media {
+            document.force_file = force_file;
+        }
+        self
+    }
+
+    /// Change the file name shown for the uploaded document.
+    ///
+    /// By default, the name given when uploading the file is used.
+    ///
+    /// This must be called *after* setting a file.
+    pub fn file_name(mut self, file_name: impl Into<String>) -> Self {
+        if let Some(tl::enums::InputMedia::UploadedDocument(document)) = &mut self.media {
+            let file_name = file_name.into();
+            let attr = document.attributes.iter_mut().find_map(|attr| match attr {
+                tl::enums::DocumentAttribute::Filename(attr) => Some(attr),
+                _ => None,
+            });
+            match attr {
+                Some(attr) => attr.file_name = file_name,
+                None => document
+                    .attributes
+                    .push(tl::types::DocumentAttributeFilename { file_name }.into()),
+            }
+        }
         self
     }
 
@@ -313,9 +753,12 @@ impl InputMessage {
     /// If no mime type is set and it cannot be inferred, the mime type will be
     /// "application/octet-stream".
     ///
-    /// This method should be called before setting any media, else it won't have any effect.
+    /// This method can be called before or after setting the media.
     pub fn mime_type(mut self, mime_type: &str) -> Self {
         self.mime_type = Some(mime_type.to_string());
+        if let Some(tl::enums::InputMedia::UploadedDocument(document)) = &mut self.media {
+            document.mime_type = mime_type.to_string();
+        }
         self
     }
 
@@ -323,8 +766,8 @@ impl InputMessage {
     fn get_file_mime(&self, file: &Uploaded) -> String {
         if let Some(mime) = self.mime_type.as_ref() {
             mime.clone()
-        } else if let Some(mime) = mime_guess::from_path(file.name()).first() {
-            mime.essence_str().to_string()
+        } else if let Some(mime) = utils::guess_mime_type(file.name()) {
+            mime.to_string()
         } else {
             "application/octet-stream".to_string()
         }
@@ -338,6 +781,132 @@ impl InputMessage {
         }
     }
 
+    /// Builds a new message containing an animated dice with the given emoji.
+    ///
+    /// The value rolled is chosen by the server, and can be read from the [`Media::Dice`] of
+    /// the sent message.
+    ///
+    /// Returns `None` if the emoji is not one of the [`DICE_EMOJIS`].
+    pub fn dice(emoji: &str) -> Option<Self> {
+        // Emoji may be followed by the variation selector to request the emoji presentation.
+        let emoticon = emoji.trim_end_matches('\u{fe0f}');
+        if !DICE_EMOJIS.contains(&emoticon) {
+            return None;
+        }
+        Some(Self {
+            media: Some(
+                (tl::types::InputMediaDice {
+                    emoticon: emoticon.to_string(),
+                })
+                .into(),
+            ),
+            ..Self::default()
+        })
+    }
+
+    /// Builds a new message sharing a contact.
+    ///
+    /// The vCard may be empty, in which case official clients show the phone number and name.
+    pub fn contact(
+        phone_number: impl Into<String>,
+        first_name: impl Into<String>,
+        last_name: impl Into<String>,
+        vcard: impl Into<String>,
+    ) -> Self {
+        Self {
+            media: Some(
+                (tl::types::InputMediaContact {
+                    phone_number: phone_number.into(),
+                    first_name: first_name.into(),
+                    last_name: last_name.into(),
+                    vcard: vcard.into(),
+                })
+                .into(),
+            ),
+            ..Self::default()
+        }
+    }
+
+    /// Builds a new message sharing a location, optionally with its accuracy in meters.
+    pub fn geo(latitude: f64, longitude: f64, accuracy_radius: Option<i32>) -> Self {
+        Self {
+            media: Some(
+                (tl::types::InputMediaGeoPoint {
+                    geo_point: geo_point(latitude, longitude, accuracy_radius),
+                })
+                .into(),
+            ),
+            ..Self::default()
+        }
+    }
+
+    /// Builds a new message sharing a venue, such as a shop or a restaurant, at the given
+    /// location.
+    ///
+    /// The provider is the venue database the identifier belongs to (such as `"foursquare"`),
+    /// and can be left empty along with the identifier.
+    pub fn venue(
+        latitude: f64,
+        longitude: f64,
+        title: impl Into<String>,
+        address: impl Into<String>,
+        provider: impl Into<String>,
+        venue_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            media: Some(
+                (tl::types::InputMediaVenue {
+                    geo_point: geo_point(latitude, longitude, None),
+                    title: title.into(),
+                    address: address.into(),
+                    provider: provider.into(),
+                    venue_id: venue_id.into(),
+                    venue_type: String::new(),
+                })
+                .into(),
+            ),
+            ..Self::default()
+        }
+    }
+
+    /// Builds a new message sharing a live location, which can be updated for `period`
+    /// seconds with [`Client::edit_live_location`].
+    ///
+    /// The heading is the direction of movement in degrees (1-360), if known.
+    ///
+    /// [`Client::edit_live_location`]: crate::Client::edit_live_location
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(chat: grammers_client::types::Chat, client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// use grammers_client::InputMessage;
+    ///
+    /// let message = client
+    ///     .send_message(&chat, InputMessage::geo_live(52.52, 13.40, 15 * 60, None))
+    ///     .await?;
+    ///
+    /// client.edit_live_location(&chat, message.id(), 52.53, 13.41).await?;
+    /// client.stop_live_location(&chat, message.id()).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn geo_live(latitude: f64, longitude: f64, period: i32, heading: Option<i32>) -> Self {
+        Self {
+            media: Some(
+                (tl::types::InputMediaGeoLive {
+                    stopped: false,
+                    geo_point: geo_point(latitude, longitude, None),
+                    heading,
+                    period: Some(period),
+                    proximity_notification_radius: None,
+                })
+                .into(),
+            ),
+            ..Self::default()
+        }
+    }
+
     /// Builds a new message from the given markdown-formatted string as the
     /// message contents and entities.
     ///
@@ -373,6 +942,19 @@ impl InputMessage {
     }
 }
 
+pub(crate) fn geo_point(
+    latitude: f64,
+    longitude: f64,
+    accuracy_radius: Option<i32>,
+) -> tl::enums::InputGeoPoint {
+    tl::types::InputGeoPoint {
+        lat: latitude,
+        long: longitude,
+        accuracy_radius,
+    }
+    .into()
+}
+
 impl From<&str> for InputMessage {
     fn from(text: &str) -> Self {
         Self::text(text)
@@ -0,0 +1,188 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use crate::types::{Chat, Photo, User};
+use crate::utils;
+use chrono::{DateTime, Utc};
+use grammers_tl_types as tl;
+
+/// An invite link to a group or channel, created by one of its administrators.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InviteLink {
+    pub raw: tl::types::ChatInviteExported,
+}
+
+/// A user who joined a group or channel through an invite link, or requested to join through
+/// one.
+#[derive(Clone, Debug)]
+pub struct InviteLinkJoiner {
+    pub raw: tl::types::ChatInviteImporter,
+    pub user: User,
+}
+
+/// Information about a group or channel obtained from one of its invite links, which can be
+/// used to preview the chat before joining it.
+#[derive(Clone, Debug)]
+pub enum InvitePreview {
+    /// The logged-in account is already a member of the chat.
+    Joined(Chat),
+    /// The chat can be previewed without joining it, but only until the given date.
+    Peek { chat: Chat, expires: DateTime<Utc> },
+    /// The logged-in account is not a member of the chat yet.
+    Invite(ChatInvite),
+}
+
+/// The information shown about a chat that has not been joined yet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChatInvite {
+    pub raw: tl::types::ChatInvite,
+}
+
+impl InvitePreview {
+    pub(crate) fn from_raw(invite: tl::enums::ChatInvite) -> Self {
+        match invite {
+            tl::enums::ChatInvite::Already(invite) => Self::Joined(Chat::from_raw(invite.chat)),
+            tl::enums::ChatInvite::Peek(invite) => Self::Peek {
+                chat: Chat::from_raw(invite.chat),
+                expires: utils::date(invite.expires),
+            },
+            tl::enums::ChatInvite::Invite(raw) => Self::Invite(ChatInvite { raw }),
+        }
+    }
+}
+
+impl ChatInvite {
+    /// The title of the chat.
+    pub fn title(&self) -> &str {
+        &self.raw.title
+    }
+
+    /// The description of the chat, if any.
+    pub fn about(&self) -> Option<&str> {
+        self.raw.about.as_deref()
+    }
+
+    /// How many members the chat has.
+    pub fn participants_count(&self) -> i32 {
+        self.raw.participants_count
+    }
+
+    /// The photo of the chat, if any.
+    pub fn photo(&self) -> Option<Photo> {
+        match &self.raw.photo {
+            tl::enums::Photo::Empty(_) => None,
+            photo => Some(Photo::from_raw(photo.clone())),
+        }
+    }
+
+    /// Whether the chat is a broadcast channel, as opposed to a group.
+    pub fn is_broadcast(&self) -> bool {
+        self.raw.broadcast
+    }
+
+    /// Whether the chat is a supergroup.
+    pub fn is_megagroup(&self) -> bool {
+        self.raw.megagroup
+    }
+
+    /// Whether the chat is public, meaning it also has a username.
+    pub fn is_public(&self) -> bool {
+        self.raw.public
+    }
+
+    /// Whether joining through this link sends a request which needs to be approved by an
+    /// administrator.
+    pub fn request_needed(&self) -> bool {
+        self.raw.request_needed
+    }
+}
+
+impl InviteLink {
+    pub(crate) fn from_raw(invite: tl::enums::ExportedChatInvite) -> Option<Self> {
+        match invite {
+            tl::enums::ExportedChatInvite::ChatInviteExported(raw) => Some(Self { raw }),
+            tl::enums::ExportedChatInvite::ChatInvitePublicJoinRequests => None,
+        }
+    }
+
+    /// The link itself, in the form `https://t.me/+…`.
+    pub fn link(&self) -> &str {
+        &self.raw.link
+    }
+
+    /// The optional title given to the link to tell it apart from others.
+    pub fn title(&self) -> Option<&str> {
+        self.raw.title.as_deref()
+    }
+
+    /// The identifier of the administrator who created the link.
+    pub fn admin_id(&self) -> i64 {
+        self.raw.admin_id
+    }
+
+    /// When the link was created.
+    pub fn date(&self) -> DateTime<Utc> {
+        utils::date(self.raw.date)
+    }
+
+    /// When the link stops working, if it expires at all.
+    pub fn expire_date(&self) -> Option<DateTime<Utc>> {
+        self.raw.expire_date.map(utils::date)
+    }
+
+    /// How many users can join through the link, if it is limited.
+    pub fn usage_limit(&self) -> Option<i32> {
+        self.raw.usage_limit
+    }
+
+    /// How many users have joined through the link.
+    pub fn usage(&self) -> i32 {
+        self.raw.usage.unwrap_or(0)
+    }
+
+    /// How many users have requested to join through the link and are pending approval.
+    pub fn requested(&self) -> i32 {
+        self.raw.requested.unwrap_or(0)
+    }
+
+    /// Whether the link has been revoked and can no longer be used.
+    pub fn is_revoked(&self) -> bool {
+        self.raw.revoked
+    }
+
+    /// Whether this is the primary link of the chat, which never expires.
+    pub fn is_permanent(&self) -> bool {
+        self.raw.permanent
+    }
+
+    /// Whether users joining through the link need to be approved by an administrator first.
+    pub fn request_needed(&self) -> bool {
+        self.raw.request_needed
+    }
+}
+
+impl InviteLinkJoiner {
+    /// When the user joined, or requested to join.
+    pub fn date(&self) -> DateTime<Utc> {
+        utils::date(self.raw.date)
+    }
+
+    /// Whether the user has only requested to join, and is still pending approval.
+    pub fn is_request(&self) -> bool {
+        self.raw.requested
+    }
+
+    /// The message the user left when requesting to join, if any.
+    pub fn about(&self) -> Option<&str> {
+        self.raw.about.as_deref()
+    }
+
+    /// The identifier of the administrator who approved the join request, if any.
+    pub fn approved_by(&self) -> Option<i64> {
+        self.raw.approved_by
+    }
+}
@@ -47,7 +47,7 @@ impl<R, T> IterBuffer<R, T> {
     }
 
     /// Checks whether the limit has been reached and no more items should be fetched.
-    fn limit_reached(&self) -> bool {
+    pub(crate) fn limit_reached(&self) -> bool {
         if let Some(limit) = self.limit {
             self.fetched >= limit
         } else {
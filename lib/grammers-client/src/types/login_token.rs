@@ -5,8 +5,131 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
+use grammers_tl_types as tl;
+use std::time::Duration;
 
 pub struct LoginToken {
     pub(crate) phone: String,
-    pub(crate) phone_code_hash: String,
+    pub(crate) sent_code: SentCode,
+}
+
+/// Information about how the login code was sent, as found in a [`LoginToken`].
+#[derive(Clone, Debug)]
+pub struct SentCode {
+    pub raw: tl::types::auth::SentCode,
+}
+
+/// The way in which a login code is delivered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CodeType {
+    /// The code was sent as a message from Telegram to the other logged-in applications.
+    App,
+    /// The code was sent via SMS.
+    Sms,
+    /// The code will be read out in a phone call.
+    Call,
+    /// The code is the phone number of an incoming call matching the given pattern.
+    FlashCall { pattern: Option<String> },
+    /// The code is the last digits of the phone number of a missed call with the given prefix.
+    MissedCall { prefix: Option<String> },
+    /// The code was sent to the email matching the given pattern.
+    Email { pattern: String },
+    /// An email address must be set up before the code can be sent.
+    SetUpEmailRequired,
+    /// The code was sent via SMS to an anonymous number bought on Fragment.
+    FragmentSms { url: Option<String> },
+    /// The code was sent via SMS after verification by Firebase.
+    FirebaseSms,
+    /// The code is a word sent via SMS, which may start with the given letters.
+    SmsWord { beginning: Option<String> },
+    /// The code is a phrase sent via SMS, which may start with the given words.
+    SmsPhrase { beginning: Option<String> },
+}
+
+impl LoginToken {
+    /// Information about how the login code was sent.
+    pub fn sent_code(&self) -> &SentCode {
+        &self.sent_code
+    }
+
+    pub(crate) fn phone_code_hash(&self) -> &str {
+        &self.sent_code.raw.phone_code_hash
+    }
+}
+
+impl SentCode {
+    /// How the login code was delivered.
+    pub fn code_type(&self) -> CodeType {
+        use tl::enums::auth::SentCodeType as T;
+
+        match &self.raw.r#type {
+            T::App(_) => CodeType::App,
+            T::Sms(_) => CodeType::Sms,
+            T::Call(_) => CodeType::Call,
+            T::FlashCall(t) => CodeType::FlashCall {
+                pattern: Some(t.pattern.clone()),
+            },
+            T::MissedCall(t) => CodeType::MissedCall {
+                prefix: Some(t.prefix.clone()),
+            },
+            T::EmailCode(t) => CodeType::Email {
+                pattern: t.email_pattern.clone(),
+            },
+            T::SetUpEmailRequired(_) => CodeType::SetUpEmailRequired,
+            T::FragmentSms(t) => CodeType::FragmentSms {
+                url: Some(t.url.clone()),
+            },
+            T::FirebaseSms(_) => CodeType::FirebaseSms,
+            T::SmsWord(t) => CodeType::SmsWord {
+                beginning: t.beginning.clone(),
+            },
+            T::SmsPhrase(t) => CodeType::SmsPhrase {
+                beginning: t.beginning.clone(),
+            },
+        }
+    }
+
+    /// How many characters the login code has, if known.
+    pub fn code_length(&self) -> Option<i32> {
+        use tl::enums::auth::SentCodeType as T;
+
+        match &self.raw.r#type {
+            T::App(t) => Some(t.length),
+            T::Sms(t) => Some(t.length),
+            T::Call(t) => Some(t.length),
+            T::MissedCall(t) => Some(t.length),
+            T::EmailCode(t) => Some(t.length),
+            T::FragmentSms(t) => Some(t.length),
+            T::FirebaseSms(t) => Some(t.length),
+            T::FlashCall(_) | T::SetUpEmailRequired(_) | T::SmsWord(_) | T::SmsPhrase(_) => None,
+        }
+    }
+
+    /// How the login code will be delivered if it is sent again with
+    /// [`Client::resend_code`], if it can be.
+    ///
+    /// [`Client::resend_code`]: crate::Client::resend_code
+    pub fn next_type(&self) -> Option<CodeType> {
+        use tl::enums::auth::CodeType as T;
+
+        self.raw
+            .next_type
+            .as_ref()
+            .map(|next_type| match next_type {
+                T::Sms => CodeType::Sms,
+                T::Call => CodeType::Call,
+                T::FlashCall => CodeType::FlashCall { pattern: None },
+                T::MissedCall => CodeType::MissedCall { prefix: None },
+                T::FragmentSms => CodeType::FragmentSms { url: None },
+            })
+    }
+
+    /// How long to wait before the login code can be sent again with [`Client::resend_code`].
+    ///
+    /// [`Client::resend_code`]: crate::Client::resend_code
+    pub fn timeout(&self) -> Option<Duration> {
+        self.raw
+            .timeout
+            .map(|timeout| Duration::from_secs(timeout.max(0) as u64))
+    }
 }
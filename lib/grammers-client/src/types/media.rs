@@ -5,19 +5,31 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
-use crate::types::photo_sizes::{PhotoSize, VecExt};
+use crate::types::photo_sizes::{PhotoSize, ThumbChoice, VecExt};
+use crate::types::StickerSetRef;
 use chrono::{DateTime, Utc};
+use grammers_session::PackedChat;
 use grammers_tl_types as tl;
 use std::fmt::Debug;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Photo {
     pub raw: tl::types::MessageMediaPhoto,
+    pub(crate) origin: Option<MediaOrigin>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Document {
     pub raw: tl::types::MessageMediaDocument,
+    pub(crate) origin: Option<MediaOrigin>,
+}
+
+/// The message where a photo or document was found, used to fetch it again if its file
+/// reference expires.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct MediaOrigin {
+    pub(crate) chat: PackedChat,
+    pub(crate) message_id: i32,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -70,6 +82,12 @@ pub struct WebPage {
     pub raw: tl::types::MessageMediaWebPage,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct Game {
+    pub raw: tl::types::Game,
+}
+
+#[allow(clippy::large_enum_variant)]
 #[derive(Clone, Debug, PartialEq)]
 #[non_exhaustive]
 pub enum Media {
@@ -83,6 +101,7 @@ pub enum Media {
     Venue(Venue),
     GeoLive(GeoLive),
     WebPage(WebPage),
+    Game(Game),
 }
 
 impl Photo {
@@ -92,12 +111,18 @@ impl Photo {
                 spoiler: false,
                 photo: Some(photo),
                 ttl_seconds: None,
+                live_photo: false,
+                video: None,
             },
+            origin: None,
         }
     }
 
     pub fn from_raw_media(photo: tl::types::MessageMediaPhoto) -> Self {
-        Self { raw: photo }
+        Self {
+            raw: photo,
+            origin: None,
+        }
     }
 
     pub fn to_raw_input_location(&self) -> Option<tl::enums::InputFileLocation> {
@@ -139,15 +164,20 @@ impl Photo {
                 _ => eInputPhoto::Empty,
             },
             ttl_seconds: self.raw.ttl_seconds,
+            live_photo: false,
+            video: None,
         }
     }
 
     pub fn id(&self) -> i64 {
-        use tl::enums::Photo as P;
+        self.raw.photo.as_ref().unwrap().id()
+    }
 
-        match self.raw.photo.as_ref().unwrap() {
-            P::Empty(photo) => photo.id,
-            P::Photo(photo) => photo.id,
+    /// When the photo was uploaded, or `None` if the photo is not available.
+    pub fn date(&self) -> Option<DateTime<Utc>> {
+        match self.raw.photo.as_ref()? {
+            tl::enums::Photo::Empty(_) => None,
+            tl::enums::Photo::Photo(photo) => Some(crate::utils::date(photo.date)),
         }
     }
 
@@ -188,6 +218,17 @@ impl Photo {
         }
     }
 
+    /// The thumbnail of the photo matching the choice.
+    ///
+    /// The thumbnail can be downloaded with [`Client::download_media`] by wrapping it in
+    /// [`Downloadable::PhotoSize`].
+    ///
+    /// [`Client::download_media`]: crate::Client::download_media
+    /// [`Downloadable::PhotoSize`]: crate::types::Downloadable::PhotoSize
+    pub fn thumb(&self, choice: &ThumbChoice) -> Option<PhotoSize> {
+        self.thumbs().choose(choice).cloned()
+    }
+
     /// Returns true if the photo is a spoiler.
     pub fn is_spoiler(&self) -> bool {
         self.raw.spoiler
@@ -200,8 +241,29 @@ impl Photo {
 }
 
 impl Document {
+    pub fn from_raw(document: tl::enums::Document) -> Self {
+        Self {
+            raw: tl::types::MessageMediaDocument {
+                nopremium: false,
+                spoiler: false,
+                video: false,
+                round: false,
+                voice: false,
+                document: Some(document),
+                alt_documents: None,
+                ttl_seconds: None,
+                video_cover: None,
+                video_timestamp: None,
+            },
+            origin: None,
+        }
+    }
+
     pub fn from_raw_media(document: tl::types::MessageMediaDocument) -> Self {
-        Self { raw: document }
+        Self {
+            raw: document,
+            origin: None,
+        }
     }
 
     pub fn to_raw_input_location(&self) -> Option<tl::enums::InputFileLocation> {
@@ -240,15 +302,32 @@ impl Document {
             },
             ttl_seconds: self.raw.ttl_seconds,
             query: None,
+            video_cover: None,
+            video_timestamp: None,
         }
     }
 
     pub fn id(&self) -> i64 {
-        use tl::enums::Document as D;
+        self.raw.document.as_ref().unwrap().id()
+    }
 
-        match self.raw.document.as_ref().unwrap() {
-            D::Empty(document) => document.id,
-            D::Document(document) => document.id,
+    /// The access hash of the document, needed along with its identifier and file reference
+    /// to send it again without uploading it.
+    ///
+    /// See [`InputMessage::document_by_id`](crate::InputMessage::document_by_id).
+    pub fn access_hash(&self) -> i64 {
+        match self.raw.document.as_ref() {
+            Some(tl::enums::Document::Document(document)) => document.access_hash,
+            _ => 0,
+        }
+    }
+
+    /// The file reference of the document, which Telegram requires to prove that the document
+    /// was seen recently.
+    pub fn file_reference(&self) -> &[u8] {
+        match self.raw.document.as_ref() {
+            Some(tl::enums::Document::Document(document)) => &document.file_reference,
+            _ => &[],
         }
     }
 
@@ -320,6 +399,18 @@ impl Document {
         }
     }
 
+    /// The thumbnail of the document matching the choice, such as the preview of a video or
+    /// the thumbnail of a sticker.
+    ///
+    /// The thumbnail can be downloaded with [`Client::download_media`] by wrapping it in
+    /// [`Downloadable::PhotoSize`].
+    ///
+    /// [`Client::download_media`]: crate::Client::download_media
+    /// [`Downloadable::PhotoSize`]: crate::types::Downloadable::PhotoSize
+    pub fn thumb(&self, choice: &ThumbChoice) -> Option<PhotoSize> {
+        self.thumbs().choose(choice).cloned()
+    }
+
     /// Duration of video/audio, in seconds
     pub fn duration(&self) -> Option<f64> {
         match self.raw.document.as_ref() {
@@ -354,37 +445,91 @@ impl Document {
         }
     }
 
-    /// Title of audio
-    pub fn audio_title(&self) -> Option<String> {
+    /// The audio attribute of the document, present in audio files and voice notes.
+    fn audio_attribute(&self) -> Option<&tl::types::DocumentAttributeAudio> {
         match self.raw.document.as_ref() {
             Some(tl::enums::Document::Document(d)) => {
-                for attr in &d.attributes {
-                    #[allow(clippy::single_match)]
-                    match attr {
-                        tl::enums::DocumentAttribute::Audio(a) => return a.title.clone(),
-                        _ => {}
-                    }
-                }
-                None
+                d.attributes.iter().find_map(|attr| match attr {
+                    tl::enums::DocumentAttribute::Audio(a) => Some(a),
+                    _ => None,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// The video attribute of the document, present in videos and round video messages.
+    fn video_attribute(&self) -> Option<&tl::types::DocumentAttributeVideo> {
+        match self.raw.document.as_ref() {
+            Some(tl::enums::Document::Document(d)) => {
+                d.attributes.iter().find_map(|attr| match attr {
+                    tl::enums::DocumentAttribute::Video(v) => Some(v),
+                    _ => None,
+                })
             }
             _ => None,
         }
     }
 
+    /// Returns true if the document is a video, including round video messages
+    pub fn is_video(&self) -> bool {
+        self.video_attribute().is_some()
+    }
+
+    /// Returns true if the document is a round video message
+    pub fn is_round_message(&self) -> bool {
+        self.video_attribute()
+            .map(|v| v.round_message)
+            .unwrap_or(false)
+    }
+
+    /// Returns true if the video can be played before it is fully downloaded
+    pub fn supports_streaming(&self) -> bool {
+        self.video_attribute()
+            .map(|v| v.supports_streaming)
+            .unwrap_or(false)
+    }
+
+    /// Title of audio
+    pub fn title(&self) -> Option<String> {
+        self.audio_attribute().and_then(|a| a.title.clone())
+    }
+
+    /// Title of audio
+    #[deprecated(note = "use `title` instead")]
+    pub fn audio_title(&self) -> Option<String> {
+        self.title()
+    }
+
     /// Performer (artist) of audio
     pub fn performer(&self) -> Option<String> {
+        self.audio_attribute().and_then(|a| a.performer.clone())
+    }
+
+    /// Returns true if the document is a voice note
+    pub fn is_voice(&self) -> bool {
+        self.audio_attribute().map(|a| a.voice).unwrap_or(false)
+    }
+
+    /// The waveform of a voice note, as 5-bit values packed together
+    pub fn waveform(&self) -> Option<&[u8]> {
+        self.audio_attribute().and_then(|a| a.waveform.as_deref())
+    }
+
+    /// Returns true if the document is an animation (a GIF, which Telegram stores as a silent
+    /// MP4 video)
+    pub fn is_animation(&self) -> bool {
         match self.raw.document.as_ref() {
             Some(tl::enums::Document::Document(d)) => {
-                for attr in &d.attributes {
-                    #[allow(clippy::single_match)]
-                    match attr {
-                        tl::enums::DocumentAttribute::Audio(a) => return a.performer.clone(),
-                        _ => {}
-                    }
-                }
-                None
+                d.attributes
+                    .iter()
+                    .any(|attr| matches!(attr, tl::enums::DocumentAttribute::Animated))
+                    && !d
+                        .attributes
+                        .iter()
+                        .any(|attr| matches!(attr, tl::enums::DocumentAttribute::Sticker(_)))
             }
-            _ => None,
+            _ => false,
         }
     }
 
@@ -441,7 +586,26 @@ impl Sticker {
 
     /// Is this sticker an animated sticker?
     pub fn is_animated(&self) -> bool {
-        self.animated
+        self.animated || self.document.mime_type() == Some("application/x-tgsticker")
+    }
+
+    /// Is this sticker a video sticker?
+    pub fn is_video(&self) -> bool {
+        self.document.mime_type() == Some("video/webm")
+    }
+
+    /// Is this sticker a mask, meant to be placed on top of photos?
+    pub fn is_mask(&self) -> bool {
+        self.raw_attrs.mask
+    }
+
+    /// The sticker set this sticker belongs to, if any.
+    ///
+    /// The set can be fetched with [`Client::get_sticker_set`].
+    ///
+    /// [`Client::get_sticker_set`]: crate::Client::get_sticker_set
+    pub fn set(&self) -> Option<StickerSetRef> {
+        StickerSetRef::from_raw(&self.raw_attrs.stickerset)
     }
 }
 
@@ -493,7 +657,7 @@ impl Poll {
                 tl::enums::Poll::Poll(poll) => poll,
             },
             raw_results: match poll.results {
-                tl::enums::PollResults::Results(results) => results,
+                tl::enums::PollResults::Results(results) => *results,
             },
         }
     }
@@ -504,9 +668,16 @@ impl Poll {
             correct_answers: None,
             solution: None,
             solution_entities: None,
+            attached_media: None,
+            solution_media: None,
         }
     }
 
+    /// The identifier of the poll, which updates about it refer to.
+    pub fn id(&self) -> i64 {
+        self.raw.id
+    }
+
     /// Return question of the poll
     pub fn question(&self) -> &grammers_tl_types::enums::TextWithEntities {
         &self.raw.question
@@ -524,8 +695,10 @@ impl Poll {
 
     /// Iterator over poll answer options
     pub fn iter_answers(&self) -> impl Iterator<Item = &tl::types::PollAnswer> {
-        self.raw.answers.iter().map(|answer| match answer {
-            tl::enums::PollAnswer::Answer(answer) => answer,
+        self.raw.answers.iter().filter_map(|answer| match answer {
+            tl::enums::PollAnswer::Answer(answer) => Some(answer),
+            // Only used when sending polls, never found in the ones Telegram sends.
+            tl::enums::PollAnswer::InputPollAnswer(_) => None,
         })
     }
 
@@ -583,11 +756,17 @@ impl Geo {
     }
 
     /// Get the latitude of the location.
-    pub fn latitue(&self) -> f64 {
+    pub fn latitude(&self) -> f64 {
         self.raw.lat
     }
 
     /// Get the latitude of the location.
+    #[deprecated(note = "use `latitude` instead")]
+    pub fn latitue(&self) -> f64 {
+        self.latitude()
+    }
+
+    /// Get the longitude of the location.
     pub fn longitude(&self) -> f64 {
         self.raw.long
     }
@@ -717,11 +896,75 @@ impl WebPage {
     }
 }
 
+impl Game {
+    pub fn from_raw_media(game: tl::types::MessageMediaGame) -> Self {
+        let tl::enums::Game::Game(game) = game.game;
+        Self { raw: game }
+    }
+
+    pub fn to_raw_input_media(&self) -> tl::types::InputMediaGame {
+        tl::types::InputMediaGame {
+            id: tl::types::InputGameId {
+                id: self.raw.id,
+                access_hash: self.raw.access_hash,
+            }
+            .into(),
+        }
+    }
+
+    pub fn id(&self) -> i64 {
+        self.raw.id
+    }
+
+    /// Get the short name of the game, used to share it.
+    pub fn short_name(&self) -> &str {
+        &self.raw.short_name
+    }
+
+    /// Get the title of the game.
+    pub fn title(&self) -> &str {
+        &self.raw.title
+    }
+
+    /// Get the description of the game.
+    pub fn description(&self) -> &str {
+        &self.raw.description
+    }
+
+    /// Get the photo shown in the game's preview.
+    pub fn photo(&self) -> Photo {
+        Photo::from_raw(self.raw.photo.clone())
+    }
+
+    /// Get the animation shown in the game's preview, if any.
+    pub fn document(&self) -> Option<Document> {
+        self.raw.document.clone().map(|document| {
+            Document::from_raw_media(tl::types::MessageMediaDocument {
+                nopremium: false,
+                spoiler: false,
+                video: false,
+                round: false,
+                voice: false,
+                document: Some(document),
+                alt_documents: None,
+                ttl_seconds: None,
+                video_cover: None,
+                video_timestamp: None,
+            })
+        })
+    }
+}
+
 impl Uploaded {
     pub fn from_raw(input_file: tl::enums::InputFile) -> Self {
         Self { raw: input_file }
     }
 
+    /// Whether the file was uploaded as a "big" file, in which case no checksum is sent.
+    pub fn is_big(&self) -> bool {
+        matches!(self.raw, tl::enums::InputFile::Big(_))
+    }
+
     pub(crate) fn name(&self) -> &str {
         match &self.raw {
             tl::enums::InputFile::File(f) => f.name.as_ref(),
@@ -752,15 +995,76 @@ impl Media {
             }
             M::WebPage(webpage) => Some(Self::WebPage(WebPage::from_raw_media(webpage))),
             M::Venue(venue) => Some(Self::Venue(Venue::from_raw_media(venue))),
-            M::Game(_) => None,
+            M::Game(game) => Some(Self::Game(Game::from_raw_media(game))),
             M::Invoice(_) => None,
             M::GeoLive(geolive) => Some(Self::GeoLive(GeoLive::from_raw_media(geolive))),
-            M::Poll(poll) => Some(Self::Poll(Poll::from_raw_media(poll))),
+            M::Poll(poll) => Some(Self::Poll(Poll::from_raw_media(*poll))),
             M::Dice(dice) => Some(Self::Dice(Dice::from_raw_media(dice))),
             M::Story(_) => None,
             M::Giveaway(_) => None,
             M::GiveawayResults(_) => None,
             M::PaidMedia(_) => None,
+            M::ToDo(_) => None,
+            M::VideoStream(_) => None,
+        }
+    }
+
+    /// Remember the message where this media was found.
+    pub(crate) fn with_origin(mut self, chat: PackedChat, message_id: i32) -> Self {
+        let origin = Some(MediaOrigin { chat, message_id });
+        match &mut self {
+            Media::Photo(photo) => photo.origin = origin,
+            Media::Document(document) => document.origin = origin,
+            Media::Sticker(sticker) => sticker.document.origin = origin,
+            _ => {}
+        }
+        self
+    }
+
+    /// The message where this media was found, if known.
+    pub(crate) fn origin(&self) -> Option<&MediaOrigin> {
+        match self {
+            Media::Photo(photo) => photo.origin.as_ref(),
+            Media::Document(document) => document.origin.as_ref(),
+            Media::Sticker(sticker) => sticker.document.origin.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The file reference of the photo or document with the given identifier in this media,
+    /// including the one in link previews.
+    pub(crate) fn file_reference(&self, id: i64) -> Option<Vec<u8>> {
+        let photo_reference = |photo: &tl::enums::Photo| match photo {
+            tl::enums::Photo::Photo(photo) if photo.id == id => Some(photo.file_reference.clone()),
+            _ => None,
+        };
+        let document_reference = |document: &tl::enums::Document| match document {
+            tl::enums::Document::Document(document) if document.id == id => {
+                Some(document.file_reference.clone())
+            }
+            _ => None,
+        };
+
+        match self {
+            Media::Photo(photo) => photo.raw.photo.as_ref().and_then(photo_reference),
+            Media::Document(document) => {
+                document.raw.document.as_ref().and_then(document_reference)
+            }
+            Media::Sticker(sticker) => sticker
+                .document
+                .raw
+                .document
+                .as_ref()
+                .and_then(document_reference),
+            Media::WebPage(web) => match &web.raw.webpage {
+                tl::enums::WebPage::Page(page) => page
+                    .photo
+                    .as_ref()
+                    .and_then(photo_reference)
+                    .or_else(|| page.document.as_ref().and_then(document_reference)),
+                _ => None,
+            },
+            _ => None,
         }
     }
 
@@ -776,6 +1080,7 @@ impl Media {
             Media::Venue(venue) => Some(venue.to_raw_input_media().into()),
             Media::GeoLive(geolive) => Some(geolive.to_raw_input_media().into()),
             Media::WebPage(_) => None,
+            Media::Game(game) => Some(game.to_raw_input_media().into()),
         }
     }
 
@@ -791,6 +1096,7 @@ impl Media {
             Media::Venue(_) => None,
             Media::GeoLive(_) => None,
             Media::WebPage(_) => None,
+            Media::Game(_) => None,
         }
     }
 }
@@ -7,10 +7,14 @@
 // except according to those terms.
 #[cfg(any(feature = "markdown", feature = "html"))]
 use crate::parsers;
+use crate::types::entity;
+use crate::types::media::{Contact, Document, Geo, GeoLive, Venue};
 use crate::types::reactions::InputReactions;
 use crate::types::{Downloadable, InputMessage, Media, Photo};
 use crate::ChatMap;
-use crate::{types, Client};
+use crate::{
+    types, Client, EditMessageError, ForwardMessagesError, SendAlbumError, SendMessageError,
+};
 use crate::{utils, InputMedia};
 use chrono::{DateTime, Utc};
 use grammers_mtsender::InvocationError;
@@ -98,6 +102,17 @@ impl Message {
                     offline: false,
                     effect: None,
                     factcheck: None,
+                    video_processing_pending: false,
+                    paid_suggested_post_stars: false,
+                    paid_suggested_post_ton: false,
+                    from_rank: None,
+                    guestchat_via_from: None,
+                    report_delivery_until_date: None,
+                    paid_message_stars: None,
+                    suggested_post: None,
+                    schedule_repeat_period: None,
+                    summary_from_language: None,
+                    rich_message: None,
                 },
                 raw_action: Some(msg.action),
                 client: client.clone(),
@@ -145,6 +160,9 @@ impl Message {
                         quote_text: None,
                         quote_entities: None,
                         quote_offset: None,
+                        reply_to_ephemeral: false,
+                        todo_item_id: None,
+                        poll_option: None,
                     }
                     .into()
                 }),
@@ -167,6 +185,17 @@ impl Message {
                 offline: false,
                 effect: None,
                 factcheck: None,
+                video_processing_pending: false,
+                paid_suggested_post_stars: false,
+                paid_suggested_post_ton: false,
+                from_rank: None,
+                guestchat_via_from: None,
+                report_delivery_until_date: None,
+                paid_message_stars: None,
+                suggested_post: None,
+                schedule_repeat_period: None,
+                summary_from_language: None,
+                rich_message: None,
             },
             raw_action: None,
             client: client.clone(),
@@ -223,6 +252,11 @@ impl Message {
         self.raw.pinned
     }
 
+    /// Whether this message is protected, and so cannot be forwarded or saved.
+    pub fn noforwards(&self) -> bool {
+        self.raw.noforwards
+    }
+
     /// The ID of this message.
     ///
     /// Message identifiers are counters that start at 1 and grow by 1 for each message produced.
@@ -271,8 +305,11 @@ impl Message {
 
     /// If this message was forwarded from a previous message, return the header with information
     /// about that forward.
-    pub fn forward_header(&self) -> Option<tl::enums::MessageFwdHeader> {
-        self.raw.fwd_from.clone()
+    pub fn forward_header(&self) -> Option<types::Forward> {
+        self.raw
+            .fwd_from
+            .clone()
+            .map(|header| types::Forward::from_raw(&self.client, header, &self.chats))
     }
 
     /// If this message was sent @via some inline bot, return the bot's user identifier.
@@ -333,12 +370,26 @@ impl Message {
         }
     }
 
+    /// Convert this message into the JSON object the [Bot API] would use to represent it.
+    ///
+    /// See the [`bot_api`](crate::types::bot_api) module for the caveats of the conversion.
+    ///
+    /// [Bot API]: https://core.telegram.org/bots/api#message
+    #[cfg(feature = "botapi")]
+    pub fn to_bot_api_json(&self) -> serde_json::Value {
+        types::bot_api::message(&self.raw, self.raw_action.as_ref(), &self.chats)
+    }
+
     /// The media displayed by this message, if any.
     ///
     /// This not only includes photos or videos, but also contacts, polls, documents, locations
     /// and many other types.
     pub fn media(&self) -> Option<types::Media> {
-        self.raw.media.clone().and_then(|x| Media::from_raw(x))
+        self.raw
+            .media
+            .clone()
+            .and_then(Media::from_raw)
+            .map(|media| media.with_origin(self.chat().pack(), self.raw.id))
     }
 
     /// If the message has a reply markup (which can happen for messages produced by bots),
@@ -354,6 +405,101 @@ impl Message {
         self.raw.entities.as_ref()
     }
 
+    /// The formatting entities used in this message, along with the portion of the text they
+    /// apply to.
+    ///
+    /// Entities whose range does not fit the text are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f(message: grammers_client::types::Message) {
+    /// for (entity, text) in message.entities() {
+    ///     println!("{text:?} is formatted with {entity:?}");
+    /// }
+    /// # }
+    /// ```
+    pub fn entities(&self) -> impl Iterator<Item = (&tl::enums::MessageEntity, &str)> {
+        let text = self.text();
+        self.raw
+            .entities
+            .iter()
+            .flatten()
+            .filter_map(move |entity| {
+                let (offset, length) = entity::entity_range(entity);
+                entity::utf16_slice(text, offset, length).map(|slice| (entity, slice))
+            })
+    }
+
+    /// The URLs present in this message, both those written in plain text and those hidden
+    /// behind a text link.
+    pub fn urls(&self) -> Vec<&str> {
+        use tl::enums::MessageEntity as E;
+
+        self.entities()
+            .filter_map(|(entity, text)| match entity {
+                E::Url(_) => Some(text),
+                E::TextUrl(e) => Some(e.url.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The users mentioned in this message.
+    ///
+    /// Mentions of users without a username are resolved using the chats that came along this
+    /// message.
+    pub fn mentions(&self) -> Vec<types::Mention<'_>> {
+        use tl::enums::MessageEntity as E;
+
+        self.entities()
+            .filter_map(|(entity, text)| match entity {
+                E::Mention(_) => Some(types::Mention::Username(text)),
+                E::MentionName(e) => Some(types::Mention::User {
+                    text,
+                    user: utils::always_find_entity(
+                        &tl::types::PeerUser { user_id: e.user_id }.into(),
+                        &self.chats,
+                        &self.client,
+                    ),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The hashtags present in this message, including the leading `#`.
+    pub fn hashtags(&self) -> Vec<&str> {
+        self.entities()
+            .filter_map(|(entity, text)| match entity {
+                tl::enums::MessageEntity::Hashtag(_) => Some(text),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The bot commands present in this message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f(message: grammers_client::types::Message) {
+    /// for command in message.bot_commands() {
+    ///     if command.command == "start" && command.bot.map_or(true, |bot| bot == "my_bot") {
+    ///         println!("someone wants to start the bot!");
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn bot_commands(&self) -> Vec<types::BotCommand<'_>> {
+        self.entities()
+            .filter_map(|(entity, text)| match entity {
+                tl::enums::MessageEntity::BotCommand(_) => Some(types::BotCommand::parse(text)),
+                _ => None,
+            })
+            .collect()
+    }
+
     /// How many views does this message have, when applicable.
     ///
     /// The same user account can contribute to increment this counter indefinitedly, however
@@ -378,6 +524,45 @@ impl Message {
         }
     }
 
+    /// Whether the replies to this message are comments in the discussion group linked to the
+    /// channel, rather than a reply thread within the same chat.
+    pub fn has_comments(&self) -> bool {
+        match &self.raw.replies {
+            None => false,
+            Some(tl::enums::MessageReplies::Replies(replies)) => replies.comments,
+        }
+    }
+
+    /// The most recent senders that replied to this message, when applicable.
+    pub fn recent_repliers(&self) -> Vec<types::Chat> {
+        match &self.raw.replies {
+            Some(tl::enums::MessageReplies::Replies(tl::types::MessageReplies {
+                recent_repliers: Some(repliers),
+                ..
+            })) => repliers
+                .iter()
+                .map(|peer| utils::always_find_entity(peer, &self.chats, &self.client))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Iterate over the replies to this message.
+    ///
+    /// Shorthand for `Client::iter_replies`.
+    pub fn iter_replies(&self) -> crate::client::messages::ReplyIter {
+        self.client.iter_replies(self.chat(), self.raw.id)
+    }
+
+    /// Get the message which starts the discussion thread of this message.
+    ///
+    /// Shorthand for `Client::get_discussion_message`.
+    pub async fn get_discussion_message(&self) -> Result<Option<Self>, InvocationError> {
+        self.client
+            .get_discussion_message(&self.chat(), self.raw.id)
+            .await
+    }
+
     /// React to this message.
     ///
     /// # Examples
@@ -446,6 +631,19 @@ impl Message {
         self.raw.edit_date.map(utils::date)
     }
 
+    /// Whether this message has an edit that users can see (such as a change of text or media).
+    ///
+    /// Telegram also sends [`Update::MessageEdited`] when only the reactions, the view counter
+    /// or the inline keyboard of a message change. Such changes don't touch the
+    /// [`Message::edit_date`], or are marked with [`Message::edit_hide`], so this method returns
+    /// `false` for them unless the message had already been visibly edited before. To detect
+    /// whether *this* update is a visible edit, compare the edit date with the one seen last.
+    ///
+    /// [`Update::MessageEdited`]: crate::Update::MessageEdited
+    pub fn is_visibly_edited(&self) -> bool {
+        self.raw.edit_date.is_some() && !self.raw.edit_hide
+    }
+
     /// If this message was sent to a channel, return the name used by the author to post it.
     pub fn post_author(&self) -> Option<&str> {
         self.raw.post_author.as_ref().map(|author| author.as_ref())
@@ -473,6 +671,26 @@ impl Message {
         self.raw_action.as_ref()
     }
 
+    /// If this message is a service message, render the service action as English text, such as
+    /// "Alice added Bob and 2 others".
+    ///
+    /// Use [`Message::action_text_with`] to customize the text.
+    pub fn action_text(&self) -> Option<String> {
+        self.action_text_with(&types::EnglishRenderer)
+    }
+
+    /// Like [`Message::action_text`], but using the given renderer, which may produce localized
+    /// text instead.
+    pub fn action_text_with<R: types::ActionRenderer>(&self, renderer: &R) -> Option<String> {
+        let action = self.raw_action.as_ref()?;
+        let actor = match &self.raw.from_id {
+            Some(peer) => self.chats.get(peer),
+            // Channels post service messages on their own behalf.
+            None => self.chats.get(&self.raw.peer_id),
+        };
+        Some(renderer.render(actor, action, &self.chats))
+    }
+
     /// If this message is replying to another message, return the replied message ID.
     pub fn reply_to_message_id(&self) -> Option<i32> {
         if let Some(tl::enums::MessageReplyHeader::Header(m)) = &self.raw.reply_to {
@@ -500,7 +718,7 @@ impl Message {
     pub async fn respond<M: Into<InputMessage>>(
         &self,
         message: M,
-    ) -> Result<Self, InvocationError> {
+    ) -> Result<Self, SendMessageError> {
         self.client.send_message(&self.chat(), message).await
     }
 
@@ -511,7 +729,7 @@ impl Message {
     pub async fn respond_album(
         &self,
         medias: Vec<InputMedia>,
-    ) -> Result<Vec<Option<Self>>, InvocationError> {
+    ) -> Result<Vec<Option<Self>>, SendAlbumError> {
         self.client.send_album(&self.chat(), medias).await
     }
 
@@ -519,7 +737,18 @@ impl Message {
     /// it. This methods overrides the `reply_to` on the `InputMessage` to point to `self`.
     ///
     /// Shorthand for `Client::send_message`.
-    pub async fn reply<M: Into<InputMessage>>(&self, message: M) -> Result<Self, InvocationError> {
+    ///
+    /// The sent message is returned, so it can be used right away.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(message: grammers_client::types::Message) -> Result<(), Box<dyn std::error::Error>> {
+    /// message.reply("Working on it…").await?.edit("Done!").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn reply<M: Into<InputMessage>>(&self, message: M) -> Result<Self, SendMessageError> {
         let message = message.into();
         self.client
             .send_message(&self.chat(), message.reply_to(Some(self.raw.id)))
@@ -533,7 +762,7 @@ impl Message {
     pub async fn reply_album(
         &self,
         mut medias: Vec<InputMedia>,
-    ) -> Result<Vec<Option<Self>>, InvocationError> {
+    ) -> Result<Vec<Option<Self>>, SendAlbumError> {
         medias.first_mut().unwrap().reply_to = Some(self.raw.id);
         self.client.send_album(&self.chat(), medias).await
     }
@@ -542,8 +771,10 @@ impl Message {
     ///
     /// Shorthand for `Client::forward_messages`. If you need to forward multiple messages
     /// at once, consider using that method instead.
-    pub async fn forward_to<C: Into<PackedChat>>(&self, chat: C) -> Result<Self, InvocationError> {
-        // TODO return `Message`
+    pub async fn forward_to<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+    ) -> Result<Self, ForwardMessagesError> {
         // When forwarding a single message, if it fails, Telegram should respond with RPC error.
         // If it succeeds we will have the single forwarded message present which we can unwrap.
         self.client
@@ -555,12 +786,40 @@ impl Message {
     /// Edit this message to change its text or media.
     ///
     /// Shorthand for `Client::edit_message`.
-    pub async fn edit<M: Into<InputMessage>>(&self, new_message: M) -> Result<(), InvocationError> {
+    pub async fn edit<M: Into<InputMessage>>(
+        &self,
+        new_message: M,
+    ) -> Result<(), EditMessageError> {
         self.client
             .edit_message(&self.chat(), self.raw.id, new_message)
             .await
     }
 
+    /// Remove the link preview from this message, without changing its text.
+    ///
+    /// The formatting entities, the reply markup and the position of the media are preserved.
+    pub async fn remove_link_preview(&self) -> Result<(), EditMessageError> {
+        let mut new_message = InputMessage::text(self.text())
+            .fmt_entities(self.raw.entities.clone().unwrap_or_default())
+            .invert_media(self.raw.invert_media)
+            .link_preview(false);
+        new_message.reply_markup = self.raw.reply_markup.clone();
+        self.edit(new_message).await
+    }
+
+    /// Replace the media in this message.
+    ///
+    /// Shorthand for `Client::edit_message_media`.
+    pub async fn edit_media(
+        &self,
+        media: InputMedia,
+        caption: types::EditCaption,
+    ) -> Result<(), EditMessageError> {
+        self.client
+            .edit_message_media(&self.chat(), self.raw.id, media, caption)
+            .await
+    }
+
     /// Delete this message for everyone.
     ///
     /// Shorthand for `Client::delete_messages`. If you need to delete multiple messages
@@ -587,16 +846,27 @@ impl Message {
                 .await
                 .map(drop)
         } else {
-            self.client
+            let affected = self
+                .client
                 .invoke(&tl::functions::messages::ReadHistory {
                     peer: chat.to_input_peer(),
                     max_id: self.raw.id,
                 })
-                .await
-                .map(drop)
+                .await?;
+            self.client.process_affected_messages(chat, &affected);
+            Ok(())
         }
     }
 
+    /// Mark the contents of this message as read, such as the voice note or round video in it.
+    ///
+    /// Shorthand for `Client::read_message_contents`.
+    pub async fn read_contents(&self) -> Result<(), InvocationError> {
+        self.client
+            .read_message_contents(self.chat(), &[self.raw.id])
+            .await
+    }
+
     /// Pin this message in the chat.
     ///
     /// Shorthand for `Client::pin_message`.
@@ -632,17 +902,95 @@ impl Message {
     ///
     /// Returns `true` if there was media to download, or `false` otherwise.
     ///
+    /// Photos are downloaded in their largest size. Contacts are saved as their vCard, and the
+    /// photo or document of link previews is downloaded. If the file reference of the media
+    /// expires during the download, the message is fetched again and the download continues.
+    ///
     /// Shorthand for `Client::download_media`.
     pub async fn download_media<P: AsRef<Path>>(&self, path: P) -> Result<bool, io::Error> {
-        // TODO probably encode failed download in error
-        if let Some(media) = self.media() {
-            self.client
-                .download_media(&Downloadable::Media(media), path)
-                .await
-                .map(|_| true)
-        } else {
-            Ok(false)
+        if let Some(vcard) = self.contact_vcard() {
+            tokio::fs::write(path, vcard).await?;
+            return Ok(true);
+        }
+        let downloadable = match self.downloadable() {
+            Some(downloadable) => downloadable,
+            None => return Ok(false),
+        };
+
+        self.client
+            .download_media(&downloadable, &path)
+            .await
+            .map(|_| true)
+    }
+
+    /// Download the message media in this message into memory, if applicable.
+    ///
+    /// Returns `None` if there was no media to download.
+    ///
+    /// See [`Message::download_media`] for details on which media is downloaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(message: grammers_client::types::Message) -> Result<(), Box<dyn std::error::Error>> {
+    /// if let Some(bytes) = message.download_media_bytes().await? {
+    ///     println!("Downloaded {} bytes", bytes.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn download_media_bytes(&self) -> Result<Option<Vec<u8>>, InvocationError> {
+        if let Some(vcard) = self.contact_vcard() {
+            return Ok(Some(vcard.into_bytes()));
+        }
+        let downloadable = match self.downloadable() {
+            Some(downloadable) => downloadable,
+            None => return Ok(None),
+        };
+
+        let mut bytes = Vec::new();
+        let mut download = self.client.iter_download(&downloadable);
+        while let Some(chunk) = download.next().await? {
+            bytes.extend(chunk);
+        }
+        Ok(Some(bytes))
+    }
+
+    /// The vCard of the contact in this message, generating one if it's missing.
+    fn contact_vcard(&self) -> Option<String> {
+        let contact = match self.media()? {
+            Media::Contact(contact) => contact.raw,
+            _ => return None,
+        };
+        if !contact.vcard.is_empty() {
+            return Some(contact.vcard);
         }
+        Some(format!(
+            "BEGIN:VCARD\r\nVERSION:3.0\r\nN:{};{};;;\r\nFN:{}\r\nTEL;TYPE=cell:{}\r\nEND:VCARD\r\n",
+            contact.last_name,
+            contact.first_name,
+            format!("{} {}", contact.first_name, contact.last_name).trim(),
+            contact.phone_number,
+        ))
+    }
+
+    /// The media in this message which can be downloaded through a file location, if any.
+    fn downloadable(&self) -> Option<Downloadable> {
+        let media = match self.media()? {
+            Media::WebPage(web) => match web.raw.webpage {
+                tl::enums::WebPage::Page(page) => match (page.photo, page.document) {
+                    (Some(photo), _) => Media::Photo(Photo::from_raw(photo)),
+                    (None, Some(document)) => Media::Document(Document::from_raw(document)),
+                    (None, None) => return None,
+                },
+                _ => return None,
+            },
+            media => media,
+        };
+        let media = media.with_origin(self.chat().pack(), self.raw.id);
+        media
+            .to_raw_input_location()
+            .map(|_| Downloadable::Media(media))
     }
 
     /// Get photo attached to the message if any.
@@ -653,6 +1001,48 @@ impl Message {
 
         None
     }
+
+    /// Get the contact attached to the message if any.
+    pub fn contact(&self) -> Option<Contact> {
+        if let Media::Contact(contact) = self.media()? {
+            return Some(contact);
+        }
+
+        None
+    }
+
+    /// Get the location attached to the message if any.
+    ///
+    /// This is also the location of venues and live locations.
+    pub fn geo(&self) -> Option<Geo> {
+        match self.media()? {
+            Media::Geo(geo) => Some(geo),
+            Media::Venue(venue) => venue.geo,
+            Media::GeoLive(geo_live) => geo_live.geo,
+            _ => None,
+        }
+    }
+
+    /// Get the venue attached to the message if any.
+    pub fn venue(&self) -> Option<Venue> {
+        if let Media::Venue(venue) = self.media()? {
+            return Some(venue);
+        }
+
+        None
+    }
+
+    /// Get the live location attached to the message if any.
+    ///
+    /// Live locations are updated by editing the message, so the message has to be fetched
+    /// again (or the edit received as an update) to know the latest position.
+    pub fn geo_live(&self) -> Option<GeoLive> {
+        if let Media::GeoLive(geo_live) = self.media()? {
+            return Some(geo_live);
+        }
+
+        None
+    }
 }
 
 impl fmt::Debug for Message {
@@ -11,7 +11,46 @@
 /// notify the clients that a message was deleted.
 ///
 /// When `MessageDeletion#channel_id` is Some, it means the message was deleted
-/// from a channel.
+/// from a channel (or megagroup).
+///
+/// Otherwise, the messages were deleted from a private conversation or a small group, and
+/// Telegram does not say which one. Message identifiers outside of channels are unique per
+/// account, so if the chat is needed, keep a map of the identifiers of the messages seen so far
+/// to the chat they belong to.
+///
+/// # Examples
+///
+/// ```
+/// # async fn f(client: grammers_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+/// use grammers_client::Update;
+/// use std::collections::HashMap;
+///
+/// let mut chat_of = HashMap::new();
+/// loop {
+///     match client.next_update().await? {
+///         Update::NewMessage(message) => {
+///             chat_of.insert((message.chat().id(), message.id()), message.chat());
+///         }
+///         Update::MessageDeleted(deletion) => {
+///             for id in deletion.messages() {
+///                 let chat = match deletion.channel_id() {
+///                     Some(channel_id) => chat_of.remove(&(channel_id, *id)),
+///                     None => {
+///                         // Any chat but a channel may contain it.
+///                         let key = chat_of.keys().find(|(_, msg_id)| msg_id == id).copied();
+///                         key.and_then(|key| chat_of.remove(&key))
+///                     }
+///                 };
+///                 if let Some(chat) = chat {
+///                     println!("Message {} deleted from {}", id, chat.name());
+///                 }
+///             }
+///         }
+///         _ => {}
+///     }
+/// }
+/// # }
+/// ```
 #[derive(Debug, Clone)]
 pub struct MessageDeletion {
     pub(crate) channel_id: Option<i64>,
@@ -37,6 +76,8 @@ impl MessageDeletion {
     }
 
     /// Returns the channel ID if the message was deleted from a channel.
+    ///
+    /// `None` means the chat is unknown, not that the messages belonged to no chat.
     pub fn channel_id(&self) -> Option<i64> {
         self.channel_id
     }
@@ -0,0 +1,37 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use grammers_tl_types as tl;
+
+/// Occurs whenever the view counter of a post in a channel changes.
+///
+/// Telegram only sends these for some channels, and not necessarily for every single view.
+#[derive(Debug, Clone)]
+pub struct MessageViews {
+    pub raw: tl::types::UpdateChannelMessageViews,
+}
+
+impl MessageViews {
+    pub(crate) fn new(raw: tl::types::UpdateChannelMessageViews) -> Self {
+        Self { raw }
+    }
+
+    /// Returns the ID of the channel where the post is.
+    pub fn channel_id(&self) -> i64 {
+        self.raw.channel_id
+    }
+
+    /// Returns the ID of the post whose views changed.
+    pub fn message_id(&self) -> i32 {
+        self.raw.id
+    }
+
+    /// Returns the new amount of views of the post.
+    pub fn views(&self) -> i32 {
+        self.raw.views
+    }
+}
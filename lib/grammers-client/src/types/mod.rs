@@ -11,54 +11,93 @@
 //! A lot of fields in the types exported from this module are currently public even though
 //! they directly uses `grammers-tl-types`. This will probably change before the 1.0 release.
 pub mod action;
+pub mod action_renderer;
+pub mod admin_log;
 pub mod attributes;
+pub mod authorization;
+#[cfg(feature = "botapi")]
+pub mod bot_api;
 pub mod button;
 pub mod callback_query;
 pub mod chat;
 pub mod chat_map;
+pub mod chat_member;
 pub mod chats;
 pub mod dialog;
 pub mod downloadable;
+pub mod entity;
+pub mod forward;
+pub mod full_chat;
 pub mod inline;
 pub mod input_media;
 pub mod input_message;
+pub mod invite_link;
 pub mod iter_buffer;
 pub mod login_token;
 pub mod media;
 pub mod message;
 pub mod message_deletion;
+pub mod message_views;
 pub mod participant;
 pub mod password_token;
 pub mod permissions;
+pub mod phone_call;
 pub mod photo_sizes;
+pub mod poll_update;
 pub mod reactions;
 pub mod reply_markup;
+#[cfg(feature = "secret-chats")]
+pub mod secret_chat;
+pub mod stats;
+pub mod sticker_set;
 pub mod terms_of_service;
+pub mod typing;
 pub mod update;
+pub mod update_filter;
+pub mod user_presence;
 
 pub use action::ActionSender;
+pub use action_renderer::{ActionRenderer, EnglishRenderer};
+pub use admin_log::{AdminLogAction, AdminLogEvent};
 pub use attributes::Attribute;
+pub use authorization::Authorization;
 pub use callback_query::CallbackQuery;
-pub use chat::{Channel, Chat, Group, PackedChat, Platform, RestrictionReason, User};
+pub use chat::{Channel, Chat, Group, PackedChat, Platform, RestrictionReason, User, UserStatus};
 pub use chat_map::ChatMap;
 pub(crate) use chat_map::Peer;
+pub use chat_member::ChatMemberUpdate;
 pub use chats::{AdminRightsBuilder, BannedRightsBuilder};
 pub use dialog::Dialog;
 pub use downloadable::{ChatPhoto, Downloadable, UserProfilePhoto};
+pub use entity::{BotCommand, Mention};
+pub use forward::Forward;
+pub use full_chat::{FullChat, RawFullChat};
 pub use inline::query::InlineQuery;
 pub use inline::send::InlineSend;
-pub use input_media::InputMedia;
+pub use input_media::{AlbumStrategy, EditCaption, InputMedia};
 pub use input_message::InputMessage;
+pub use invite_link::{ChatInvite, InviteLink, InviteLinkJoiner, InvitePreview};
 pub use iter_buffer::IterBuffer;
-pub use login_token::LoginToken;
+pub use login_token::{CodeType, LoginToken, SentCode};
 pub(crate) use media::Uploaded;
 pub use media::{Media, Photo};
 pub use message::Message;
 pub use message_deletion::MessageDeletion;
+pub use message_views::MessageViews;
 pub use participant::{Participant, Role};
 pub use password_token::PasswordToken;
 pub use permissions::{Permissions, Restrictions};
-pub use reactions::InputReactions;
+pub use phone_call::{CallState, PhoneCall};
+pub use photo_sizes::ThumbChoice;
+pub use poll_update::{PollUpdate, PollVote};
+pub use reactions::{AllowedReactions, InputReactions};
 pub(crate) use reply_markup::ReplyMarkup;
+#[cfg(feature = "secret-chats")]
+pub use secret_chat::{SecretChat, SecretChatStatus, SecretMessage};
+pub use stats::{BroadcastStats, MegagroupStats, MessageStats, StatsGraph, StatsValue};
+pub use sticker_set::{StickerSet, StickerSetInfo, StickerSetRef};
 pub use terms_of_service::TermsOfService;
-pub use update::Update;
+pub use typing::{ChatAction, Typing};
+pub use update::{RawUpdate, Update};
+pub use update_filter::UpdateFilter;
+pub use user_presence::UserPresence;
@@ -125,6 +125,19 @@ impl Banned {
 }
 
 impl Participant {
+    /// The date when the participant joined the chat, was promoted, or was banned, depending
+    /// on their role.
+    ///
+    /// This is `None` for the creator and for participants who left.
+    pub fn date(&self) -> Option<DateTime<Utc>> {
+        match &self.role {
+            Role::User(normal) => Some(normal.date()),
+            Role::Admin(admin) => Some(admin.date()),
+            Role::Banned(banned) => Some(banned.date()),
+            Role::Creator(_) | Role::Left(_) => None,
+        }
+    }
+
     pub(crate) fn from_raw_channel(
         chats: &mut ChatMap,
         participant: tl::enums::ChannelParticipant,
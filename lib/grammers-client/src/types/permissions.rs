@@ -38,6 +38,8 @@ impl Permissions {
                 post_stories: true,
                 edit_stories: true,
                 delete_stories: true,
+                manage_direct_messages: false,
+                manage_ranks: false,
             },
         }
     }
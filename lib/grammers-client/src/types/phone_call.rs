@@ -0,0 +1,166 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use crate::types::{Chat, ChatMap};
+use crate::Client;
+use grammers_mtsender::InvocationError;
+use grammers_tl_types as tl;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The state a phone call is in.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CallState {
+    /// The call was requested, but the other party hasn't received it yet.
+    Waiting,
+    /// The call is ringing, waiting for it to be accepted or discarded.
+    Requested,
+    /// The call was accepted, and the keys are being exchanged.
+    Accepted,
+    /// The call is ongoing.
+    Ongoing,
+    /// The call ended or was never accepted.
+    Discarded {
+        reason: Option<tl::enums::PhoneCallDiscardReason>,
+        duration: Option<Duration>,
+    },
+    /// The call is no longer known.
+    Empty,
+}
+
+/// Represents a change in the state of a phone call, either incoming or outgoing.
+///
+/// Only the signaling is exposed. The library cannot take part in the actual call, but it can
+/// be used to log calls and to discard them.
+#[derive(Clone)]
+pub struct PhoneCall {
+    pub raw: tl::enums::PhoneCall,
+    pub(crate) client: Client,
+    pub(crate) chats: Arc<ChatMap>,
+}
+
+impl PhoneCall {
+    pub fn from_raw(client: &Client, call: tl::enums::PhoneCall, chats: &Arc<ChatMap>) -> Self {
+        Self {
+            raw: call,
+            client: client.clone(),
+            chats: chats.clone(),
+        }
+    }
+
+    /// The unique identifier of this call.
+    pub fn id(&self) -> i64 {
+        self.raw.id()
+    }
+
+    /// The current state of this call.
+    pub fn state(&self) -> CallState {
+        use tl::enums::PhoneCall as C;
+
+        match &self.raw {
+            C::Empty(_) => CallState::Empty,
+            C::Waiting(_) => CallState::Waiting,
+            C::Requested(_) => CallState::Requested,
+            C::Accepted(_) => CallState::Accepted,
+            C::Call(_) => CallState::Ongoing,
+            C::Discarded(c) => CallState::Discarded {
+                reason: c.reason.clone(),
+                duration: c.duration.map(|d| Duration::from_secs(d.max(0) as u64)),
+            },
+        }
+    }
+
+    /// Whether this is a video call.
+    pub fn is_video(&self) -> bool {
+        use tl::enums::PhoneCall as C;
+
+        match &self.raw {
+            C::Empty(_) => false,
+            C::Waiting(c) => c.video,
+            C::Requested(c) => c.video,
+            C::Accepted(c) => c.video,
+            C::Call(c) => c.video,
+            C::Discarded(c) => c.video,
+        }
+    }
+
+    /// The identifiers of the user who started the call and the user who was called, if known.
+    fn participants(&self) -> Option<(i64, i64)> {
+        use tl::enums::PhoneCall as C;
+
+        match &self.raw {
+            C::Empty(_) | C::Discarded(_) => None,
+            C::Waiting(c) => Some((c.admin_id, c.participant_id)),
+            C::Requested(c) => Some((c.admin_id, c.participant_id)),
+            C::Accepted(c) => Some((c.admin_id, c.participant_id)),
+            C::Call(c) => Some((c.admin_id, c.participant_id)),
+        }
+    }
+
+    fn user(&self, user_id: i64) -> Option<&Chat> {
+        self.chats.get(&tl::types::PeerUser { user_id }.into())
+    }
+
+    /// The user who started the call.
+    ///
+    /// Not available once the call has been discarded.
+    pub fn caller(&self) -> Option<&Chat> {
+        self.participants().and_then(|(admin, _)| self.user(admin))
+    }
+
+    /// The user who was called.
+    ///
+    /// Not available once the call has been discarded.
+    pub fn callee(&self) -> Option<&Chat> {
+        self.participants()
+            .and_then(|(_, participant)| self.user(participant))
+    }
+
+    /// Whether the call was started by the logged-in account.
+    ///
+    /// Not available once the call has been discarded.
+    pub fn outgoing(&self) -> Option<bool> {
+        let self_id = self.client.0.state.read().unwrap().chat_hashes.self_id();
+        self.participants().map(|(admin, _)| admin == self_id)
+    }
+
+    pub(crate) fn to_input(&self) -> Option<tl::enums::InputPhoneCall> {
+        use tl::enums::PhoneCall as C;
+
+        let (id, access_hash) = match &self.raw {
+            C::Empty(_) | C::Discarded(_) => return None,
+            C::Waiting(c) => (c.id, c.access_hash),
+            C::Requested(c) => (c.id, c.access_hash),
+            C::Accepted(c) => (c.id, c.access_hash),
+            C::Call(c) => (c.id, c.access_hash),
+        };
+        Some(tl::types::InputPhoneCall { id, access_hash }.into())
+    }
+
+    /// Discard this call, such as declining it when it is ringing.
+    ///
+    /// Shorthand for `Client::discard_call`.
+    pub async fn discard(
+        &self,
+        reason: tl::enums::PhoneCallDiscardReason,
+    ) -> Result<(), InvocationError> {
+        self.client.discard_call(self, reason).await
+    }
+}
+
+impl fmt::Debug for PhoneCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PhoneCall")
+            .field("id", &self.id())
+            .field("state", &self.state())
+            .field("video", &self.is_video())
+            .field("caller", &self.caller())
+            .field("callee", &self.callee())
+            .finish()
+    }
+}
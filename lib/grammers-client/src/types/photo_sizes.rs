@@ -147,6 +147,16 @@ impl PhotoSize {
         }
     }
 
+    /// The width and height of the thumbnail, if known.
+    pub fn dimensions(&self) -> Option<(i32, i32)> {
+        match self {
+            PhotoSize::Size(size) => Some((size.width, size.height)),
+            PhotoSize::Cached(size) => Some((size.width, size.height)),
+            PhotoSize::Progressive(size) => Some((size.width, size.height)),
+            PhotoSize::Empty(_) | PhotoSize::Stripped(_) | PhotoSize::Path(_) => None,
+        }
+    }
+
     pub fn photo_type(&self) -> String {
         match self {
             PhotoSize::Empty(size) => size.photo_type.clone(),
@@ -372,13 +382,101 @@ viewBox="0 0 512 512" xml:space="preserve">
     }
 }
 
+/// Which of the thumbnails of a photo or document to pick.
+///
+/// See <https://core.telegram.org/api/files#image-thumbnail-types> for the meaning of each type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ThumbChoice {
+    /// The smallest thumbnail which is an actual image.
+    Smallest,
+    /// The largest thumbnail.
+    Largest,
+    /// The thumbnail of the given type, such as `"m"` or `"x"`.
+    Type(String),
+    /// The low-resolution thumbnail sent inline, which is decoded into a JPEG without making
+    /// any request.
+    Stripped,
+}
+
 pub trait VecExt {
     /// Helper method to get the largest photo thumb
     fn largest(&self) -> Option<&PhotoSize>;
+
+    /// Helper method to get the smallest photo thumb which is an actual image
+    fn smallest(&self) -> Option<&PhotoSize>;
+
+    /// Helper method to get the photo thumb matching the choice
+    fn choose(&self, choice: &ThumbChoice) -> Option<&PhotoSize>;
 }
 
 impl VecExt for Vec<PhotoSize> {
     fn largest(&self) -> Option<&PhotoSize> {
         self.iter().max_by_key(|x| x.size())
     }
+
+    fn smallest(&self) -> Option<&PhotoSize> {
+        self.iter()
+            .filter(|x| !matches!(x, PhotoSize::Empty(_) | PhotoSize::Path(_)))
+            .min_by_key(|x| x.size())
+    }
+
+    fn choose(&self, choice: &ThumbChoice) -> Option<&PhotoSize> {
+        match choice {
+            ThumbChoice::Smallest => self.smallest(),
+            ThumbChoice::Largest => self.largest(),
+            ThumbChoice::Type(ty) => self.iter().find(|x| x.photo_type() == *ty),
+            ThumbChoice::Stripped => self.iter().find(|x| matches!(x, PhotoSize::Stripped(_))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sizes() -> Vec<PhotoSize> {
+        let size = |photo_type: &str, size| {
+            PhotoSize::Size(Size {
+                photo_type: photo_type.to_string(),
+                width: 0,
+                height: 0,
+                size,
+                id: 0,
+                access_hash: 0,
+                file_reference: Vec::new(),
+                from_document: false,
+            })
+        };
+        vec![
+            PhotoSize::Stripped(StrippedSize {
+                photo_type: "i".to_string(),
+                bytes: vec![1, 40, 40],
+            }),
+            PhotoSize::Path(PathSize {
+                photo_type: "j".to_string(),
+                bytes: vec![1],
+            }),
+            size("m", 10_000),
+            size("x", 50_000),
+        ]
+    }
+
+    #[test]
+    fn check_thumb_choice() {
+        let sizes = sizes();
+        let chosen = |choice| sizes.choose(&choice).map(|x| x.photo_type());
+
+        assert_eq!(chosen(ThumbChoice::Largest).as_deref(), Some("x"));
+        assert_eq!(chosen(ThumbChoice::Smallest).as_deref(), Some("i"));
+        assert_eq!(chosen(ThumbChoice::Type("m".into())).as_deref(), Some("m"));
+        assert_eq!(chosen(ThumbChoice::Type("y".into())), None);
+        assert_eq!(chosen(ThumbChoice::Stripped).as_deref(), Some("i"));
+    }
+
+    #[test]
+    fn check_stripped_is_jpeg() {
+        let data = sizes()[0].data();
+        assert_eq!(&data[..2], &[0xff, 0xd8]);
+        assert_eq!(&data[data.len() - 2..], &[0xff, 0xd9]);
+    }
 }
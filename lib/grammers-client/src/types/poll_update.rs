@@ -0,0 +1,189 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use super::media::Poll;
+use super::{Chat, ChatMap};
+use grammers_tl_types as tl;
+
+/// Occurs when the results of a poll change, or when the poll itself changes (for example, when
+/// it's closed).
+#[derive(Clone, Debug)]
+pub struct PollUpdate {
+    pub raw: tl::types::UpdateMessagePoll,
+}
+
+/// Occurs when a user votes in a poll, or retracts their vote.
+///
+/// Telegram only sends these to the bot that sent the poll, and only for polls that are not
+/// anonymous.
+#[derive(Clone, Debug)]
+pub struct PollVote {
+    pub raw: tl::types::UpdateMessagePollVote,
+    voter: Option<Chat>,
+}
+
+impl PollUpdate {
+    pub(crate) fn new(raw: tl::types::UpdateMessagePoll) -> Self {
+        Self { raw }
+    }
+
+    /// The identifier of the poll that changed.
+    pub fn poll_id(&self) -> i64 {
+        self.raw.poll_id
+    }
+
+    /// The updated poll along with its results.
+    ///
+    /// Telegram only includes the poll when the poll itself changed. Otherwise, only its
+    /// [`PollUpdate::results`] are known.
+    pub fn poll(&self) -> Option<Poll> {
+        self.raw
+            .poll
+            .clone()
+            .map(|tl::enums::Poll::Poll(raw)| Poll {
+                raw,
+                raw_results: self.results().clone(),
+            })
+    }
+
+    /// The updated results of the poll.
+    pub fn results(&self) -> &tl::types::PollResults {
+        match &self.raw.results {
+            tl::enums::PollResults::Results(results) => results,
+        }
+    }
+
+    /// Total amount of users that voted in the poll, if known.
+    pub fn total_voters(&self) -> Option<i32> {
+        self.results().total_voters
+    }
+}
+
+impl PollVote {
+    pub(crate) fn new(raw: tl::types::UpdateMessagePollVote, chats: &ChatMap) -> Self {
+        let voter = chats.get(&raw.peer).cloned();
+        Self { raw, voter }
+    }
+
+    /// The identifier of the poll voted in.
+    pub fn poll_id(&self) -> i64 {
+        self.raw.poll_id
+    }
+
+    /// The user (or channel) that voted, if Telegram sent it along.
+    pub fn voter(&self) -> Option<&Chat> {
+        self.voter.as_ref()
+    }
+
+    /// The identifier of the user (or channel) that voted.
+    pub fn voter_id(&self) -> i64 {
+        match &self.raw.peer {
+            tl::enums::Peer::User(user) => user.user_id,
+            tl::enums::Peer::Chat(chat) => chat.chat_id,
+            tl::enums::Peer::Channel(channel) => channel.channel_id,
+        }
+    }
+
+    /// The options chosen, as the raw option bytes of the poll's answers.
+    ///
+    /// This is empty if the vote was retracted.
+    pub fn options(&self) -> &[Vec<u8>] {
+        &self.raw.options
+    }
+
+    /// Whether the voter retracted their vote.
+    pub fn retracted(&self) -> bool {
+        self.raw.options.is_empty()
+    }
+
+    /// The position of each chosen answer in the given poll, which must be the poll voted in.
+    ///
+    /// Options that are not part of the poll are ignored.
+    pub fn chosen_answers(&self, poll: &Poll) -> Vec<usize> {
+        poll.iter_answers()
+            .enumerate()
+            .filter(|(_, answer)| self.raw.options.contains(&answer.option))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> tl::enums::TextWithEntities {
+        tl::types::TextWithEntities {
+            text: text.to_string(),
+            entities: Vec::new(),
+        }
+        .into()
+    }
+
+    #[test]
+    fn check_chosen_answers() {
+        let poll = Poll {
+            raw: tl::types::Poll {
+                id: 1,
+                closed: false,
+                public_voters: true,
+                multiple_choice: true,
+                quiz: false,
+                question: text("?"),
+                answers: ["a", "b", "c"]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, answer)| {
+                        tl::types::PollAnswer {
+                            text: text(answer),
+                            option: vec![i as u8],
+                            media: None,
+                            added_by: None,
+                            date: None,
+                        }
+                        .into()
+                    })
+                    .collect(),
+                close_period: None,
+                close_date: None,
+                open_answers: false,
+                revoting_disabled: false,
+                shuffle_answers: false,
+                hide_results_until_close: false,
+                creator: false,
+                subscribers_only: false,
+                countries_iso2: None,
+                hash: 0,
+            },
+            raw_results: tl::types::PollResults {
+                min: false,
+                results: None,
+                total_voters: None,
+                recent_voters: None,
+                solution: None,
+                solution_entities: None,
+                has_unread_votes: false,
+                can_view_stats: false,
+                solution_media: None,
+            },
+        };
+        let vote = PollVote::new(
+            tl::types::UpdateMessagePollVote {
+                poll_id: 1,
+                peer: tl::types::PeerUser { user_id: 2 }.into(),
+                options: vec![vec![2], vec![0], vec![9]],
+                qts: 0,
+                positions: Vec::new(),
+            },
+            &ChatMap::empty(),
+        );
+
+        assert_eq!(vote.voter_id(), 2);
+        assert!(!vote.retracted());
+        assert_eq!(vote.chosen_answers(&poll), vec![0, 2]);
+    }
+}
@@ -9,7 +9,49 @@
 use grammers_tl_types as tl;
 use tl::enums::Reaction;
 
-#[derive(Clone, Debug)]
+/// The reactions members are allowed to use in a group or channel.
+///
+/// See [`Client::set_reactions`](crate::Client::set_reactions).
+#[derive(Clone, Debug, PartialEq)]
+pub enum AllowedReactions {
+    /// Every standard emoji reaction is allowed.
+    All,
+    /// Reactions are disabled.
+    None,
+    /// Only the given emoji can be used as reactions.
+    Some(Vec<String>),
+}
+
+impl AllowedReactions {
+    pub(crate) fn to_raw(&self) -> tl::enums::ChatReactions {
+        match self {
+            Self::All => tl::types::ChatReactionsAll {
+                allow_custom: false,
+            }
+            .into(),
+            Self::None => tl::enums::ChatReactions::None,
+            Self::Some(emoticons) => tl::types::ChatReactionsSome {
+                reactions: emoticons
+                    .iter()
+                    .map(|emoticon| {
+                        Reaction::Emoji(tl::types::ReactionEmoji {
+                            emoticon: emoticon.clone(),
+                        })
+                    })
+                    .collect(),
+            }
+            .into(),
+        }
+    }
+}
+
+impl<S: Into<String>> From<Vec<S>> for AllowedReactions {
+    fn from(emoticons: Vec<S>) -> Self {
+        Self::Some(emoticons.into_iter().map(Into::into).collect())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct InputReactions {
     pub(crate) reactions: Vec<Reaction>,
     pub(crate) add_to_recent: bool,
@@ -58,25 +100,15 @@ impl InputReactions {
     }
 }
 
-impl Default for InputReactions {
-    fn default() -> Self {
-        Self {
-            reactions: vec![],
-            add_to_recent: false,
-            big: false,
-        }
-    }
-}
-
-impl Into<InputReactions> for String {
-    fn into(self) -> InputReactions {
-        InputReactions::emoticon(self)
+impl From<String> for InputReactions {
+    fn from(val: String) -> Self {
+        InputReactions::emoticon(val)
     }
 }
 
-impl Into<InputReactions> for &str {
-    fn into(self) -> InputReactions {
-        InputReactions::emoticon(self)
+impl From<&str> for InputReactions {
+    fn from(val: &str) -> Self {
+        InputReactions::emoticon(val)
     }
 }
 
@@ -89,8 +121,8 @@ impl From<Vec<Reaction>> for InputReactions {
     }
 }
 
-impl Into<Vec<Reaction>> for InputReactions {
-    fn into(self) -> Vec<Reaction> {
-        self.reactions
+impl From<InputReactions> for Vec<Reaction> {
+    fn from(val: InputReactions) -> Self {
+        val.reactions
     }
 }
@@ -0,0 +1,225 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use crate::client::SecretChatError;
+use crate::types::{Chat, ChatMap, InputMessage};
+use crate::{utils, Client};
+use chrono::{DateTime, Utc};
+use grammers_tl_types as tl;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The state a secret chat is in.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SecretChatStatus {
+    /// The chat was requested by the logged-in account, and the other user hasn't accepted it.
+    Waiting,
+    /// The chat was requested by the other user, and can be accepted or discarded.
+    Requested,
+    /// The keys have been exchanged, and messages can be sent.
+    Ready,
+    /// The chat was discarded by either user.
+    Discarded {
+        /// Whether the history of the chat was deleted too.
+        history_deleted: bool,
+    },
+}
+
+/// An end-to-end encrypted conversation with another user.
+///
+/// Its state, including the key used to encrypt the messages, is kept in the session, so the
+/// session must be saved for the chat to be usable after the client is restarted.
+///
+/// Secret chats are experimental. Only text messages are supported.
+#[derive(Clone)]
+pub struct SecretChat {
+    pub(crate) id: i32,
+    pub(crate) access_hash: i64,
+    pub(crate) user_id: i64,
+    pub(crate) outgoing: bool,
+    pub(crate) status: SecretChatStatus,
+    // The public key of the user who requested the chat, needed to accept it.
+    pub(crate) g_a: Option<Vec<u8>>,
+    pub(crate) client: Client,
+    pub(crate) chats: Arc<ChatMap>,
+}
+
+impl SecretChat {
+    /// The unique identifier of this secret chat.
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    /// The current state of this secret chat.
+    pub fn status(&self) -> &SecretChatStatus {
+        &self.status
+    }
+
+    /// The identifier of the other user in this secret chat.
+    pub fn user_id(&self) -> i64 {
+        self.user_id
+    }
+
+    /// The other user in this secret chat, if it came along with the update.
+    pub fn user(&self) -> Option<&Chat> {
+        self.chats.get(
+            &tl::types::PeerUser {
+                user_id: self.user_id,
+            }
+            .into(),
+        )
+    }
+
+    /// Whether the secret chat was requested by the logged-in account.
+    pub fn outgoing(&self) -> bool {
+        self.outgoing
+    }
+
+    pub(crate) fn to_input(&self) -> tl::enums::InputEncryptedChat {
+        tl::types::InputEncryptedChat {
+            chat_id: self.id,
+            access_hash: self.access_hash,
+        }
+        .into()
+    }
+
+    /// Accept this secret chat, which must have been requested by the other user.
+    ///
+    /// Shorthand for `Client::accept_secret_chat`.
+    pub async fn accept(&self) -> Result<SecretChat, SecretChatError> {
+        self.client.accept_secret_chat(self).await
+    }
+
+    /// Send a text message to this secret chat.
+    ///
+    /// Shorthand for `Client::send_secret_message`.
+    pub async fn send_message<M: Into<InputMessage>>(
+        &self,
+        message: M,
+    ) -> Result<SecretMessage, SecretChatError> {
+        self.client.send_secret_message(self, message).await
+    }
+
+    /// Discard this secret chat, either declining it or closing it.
+    ///
+    /// Shorthand for `Client::discard_secret_chat`.
+    pub async fn discard(&self, delete_history: bool) -> Result<(), SecretChatError> {
+        self.client.discard_secret_chat(self, delete_history).await
+    }
+}
+
+impl fmt::Debug for SecretChat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretChat")
+            .field("id", &self.id)
+            .field("status", &self.status)
+            .field("user_id", &self.user_id)
+            .field("outgoing", &self.outgoing)
+            .finish()
+    }
+}
+
+/// A text message sent in a secret chat.
+///
+/// Messages in secret chats have no identifier assigned by Telegram, so they are told apart by
+/// the random identifier chosen by their sender.
+#[derive(Clone)]
+pub struct SecretMessage {
+    pub(crate) chat: SecretChat,
+    pub(crate) outgoing: bool,
+    pub(crate) random_id: i64,
+    pub(crate) date: i32,
+    pub(crate) silent: bool,
+    pub(crate) ttl: i32,
+    pub(crate) text: String,
+    pub(crate) entities: Vec<tl::enums::MessageEntity>,
+    pub(crate) has_media: bool,
+    pub(crate) reply_to_random_id: Option<i64>,
+}
+
+impl SecretMessage {
+    /// The secret chat where this message was sent.
+    pub fn chat(&self) -> &SecretChat {
+        &self.chat
+    }
+
+    /// Whether the message was sent by the logged-in account.
+    pub fn outgoing(&self) -> bool {
+        self.outgoing
+    }
+
+    /// The random identifier chosen by the sender of this message.
+    pub fn random_id(&self) -> i64 {
+        self.random_id
+    }
+
+    /// The date when this message was sent.
+    pub fn date(&self) -> DateTime<Utc> {
+        utils::date(self.date)
+    }
+
+    /// Whether the message was sent without a notification.
+    pub fn silent(&self) -> bool {
+        self.silent
+    }
+
+    /// How long the message should be kept after it is read, if it should self-destruct.
+    pub fn ttl(&self) -> Option<Duration> {
+        if self.ttl > 0 {
+            Some(Duration::from_secs(self.ttl as u64))
+        } else {
+            None
+        }
+    }
+
+    /// The message's text.
+    ///
+    /// If the message has media, this text is its caption.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The formatting entities used to format this message, such as bold, italic, with their
+    /// offsets and lengths.
+    pub fn fmt_entities(&self) -> &[tl::enums::MessageEntity] {
+        &self.entities
+    }
+
+    /// Whether the message had media, which is not supported in secret chats yet.
+    ///
+    /// The formatting entities of messages with media are not available either.
+    pub fn has_media(&self) -> bool {
+        self.has_media
+    }
+
+    /// The random identifier of the message this one replies to, if any.
+    pub fn reply_to_random_id(&self) -> Option<i64> {
+        self.reply_to_random_id
+    }
+
+    /// Respond to this message by sending a new message in the same secret chat.
+    pub async fn respond<M: Into<InputMessage>>(
+        &self,
+        message: M,
+    ) -> Result<SecretMessage, SecretChatError> {
+        self.chat.send_message(message).await
+    }
+}
+
+impl fmt::Debug for SecretMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretMessage")
+            .field("chat", &self.chat)
+            .field("outgoing", &self.outgoing)
+            .field("random_id", &self.random_id)
+            .field("date", &self.date())
+            .field("text", &self.text)
+            .field("has_media", &self.has_media)
+            .finish()
+    }
+}
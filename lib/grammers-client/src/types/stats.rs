@@ -0,0 +1,236 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use crate::utils;
+use chrono::{DateTime, Utc};
+use grammers_tl_types as tl;
+
+/// A graph found in the statistics of a channel or message.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StatsGraph {
+    /// The graph has not been generated yet, and must be fetched with
+    /// [`Client::load_async_graph`] using this token.
+    ///
+    /// [`Client::load_async_graph`]: crate::Client::load_async_graph
+    Async { token: String },
+    /// The graph could not be generated.
+    Error(String),
+    /// The graph data, in the JSON format understood by Telegram's charting library.
+    ///
+    /// If present, the zoom token can be used with [`Client::load_async_graph`] to load a more
+    /// detailed view around a point in time.
+    ///
+    /// [`Client::load_async_graph`]: crate::Client::load_async_graph
+    Loaded {
+        json: String,
+        zoom_token: Option<String>,
+    },
+}
+
+/// A value in the statistics, along with its value in the previous period.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StatsValue {
+    pub current: f64,
+    pub previous: f64,
+}
+
+/// Statistics of a broadcast channel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BroadcastStats {
+    pub raw: tl::types::stats::BroadcastStats,
+}
+
+/// Statistics of a supergroup.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MegagroupStats {
+    pub raw: tl::types::stats::MegagroupStats,
+}
+
+/// Statistics of a single post in a broadcast channel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MessageStats {
+    pub raw: tl::types::stats::MessageStats,
+}
+
+impl StatsGraph {
+    pub(crate) fn from_raw(graph: &tl::enums::StatsGraph) -> Self {
+        match graph {
+            tl::enums::StatsGraph::Async(graph) => Self::Async {
+                token: graph.token.clone(),
+            },
+            tl::enums::StatsGraph::Error(graph) => Self::Error(graph.error.clone()),
+            tl::enums::StatsGraph::Graph(graph) => {
+                let tl::enums::DataJson::Json(json) = &graph.json;
+                Self::Loaded {
+                    json: json.data.clone(),
+                    zoom_token: graph.zoom_token.clone(),
+                }
+            }
+        }
+    }
+}
+
+impl StatsValue {
+    fn from_raw(value: &tl::enums::StatsAbsValueAndPrev) -> Self {
+        let tl::enums::StatsAbsValueAndPrev::Prev(value) = value;
+        Self {
+            current: value.current,
+            previous: value.previous,
+        }
+    }
+}
+
+fn period(period: &tl::enums::StatsDateRangeDays) -> (DateTime<Utc>, DateTime<Utc>) {
+    let tl::enums::StatsDateRangeDays::Days(period) = period;
+    (utils::date(period.min_date), utils::date(period.max_date))
+}
+
+impl BroadcastStats {
+    /// The period of time these statistics cover.
+    pub fn period(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        period(&self.raw.period)
+    }
+
+    /// How many followers the channel has.
+    pub fn followers(&self) -> StatsValue {
+        StatsValue::from_raw(&self.raw.followers)
+    }
+
+    /// The average amount of views each post gets.
+    pub fn views_per_post(&self) -> StatsValue {
+        StatsValue::from_raw(&self.raw.views_per_post)
+    }
+
+    /// The average amount of times each post is shared.
+    pub fn shares_per_post(&self) -> StatsValue {
+        StatsValue::from_raw(&self.raw.shares_per_post)
+    }
+
+    /// The average amount of reactions each post gets.
+    pub fn reactions_per_post(&self) -> StatsValue {
+        StatsValue::from_raw(&self.raw.reactions_per_post)
+    }
+
+    /// The growth in followers over time.
+    pub fn growth_graph(&self) -> StatsGraph {
+        StatsGraph::from_raw(&self.raw.growth_graph)
+    }
+
+    /// The followers gained and lost over time.
+    pub fn followers_graph(&self) -> StatsGraph {
+        StatsGraph::from_raw(&self.raw.followers_graph)
+    }
+
+    /// The followers who muted and unmuted the channel over time.
+    pub fn mute_graph(&self) -> StatsGraph {
+        StatsGraph::from_raw(&self.raw.mute_graph)
+    }
+
+    /// The hours of the day at which followers view posts.
+    pub fn top_hours_graph(&self) -> StatsGraph {
+        StatsGraph::from_raw(&self.raw.top_hours_graph)
+    }
+
+    /// The views and shares of the posts over time.
+    pub fn interactions_graph(&self) -> StatsGraph {
+        StatsGraph::from_raw(&self.raw.interactions_graph)
+    }
+
+    /// Where the views of the posts come from.
+    pub fn views_by_source_graph(&self) -> StatsGraph {
+        StatsGraph::from_raw(&self.raw.views_by_source_graph)
+    }
+
+    /// Where new followers come from.
+    pub fn new_followers_by_source_graph(&self) -> StatsGraph {
+        StatsGraph::from_raw(&self.raw.new_followers_by_source_graph)
+    }
+
+    /// The languages of the followers.
+    pub fn languages_graph(&self) -> StatsGraph {
+        StatsGraph::from_raw(&self.raw.languages_graph)
+    }
+}
+
+impl MegagroupStats {
+    /// The period of time these statistics cover.
+    pub fn period(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        period(&self.raw.period)
+    }
+
+    /// How many members the group has.
+    pub fn members(&self) -> StatsValue {
+        StatsValue::from_raw(&self.raw.members)
+    }
+
+    /// How many messages were sent.
+    pub fn messages(&self) -> StatsValue {
+        StatsValue::from_raw(&self.raw.messages)
+    }
+
+    /// How many members viewed messages.
+    pub fn viewers(&self) -> StatsValue {
+        StatsValue::from_raw(&self.raw.viewers)
+    }
+
+    /// How many members sent messages.
+    pub fn posters(&self) -> StatsValue {
+        StatsValue::from_raw(&self.raw.posters)
+    }
+
+    /// The growth in members over time.
+    pub fn growth_graph(&self) -> StatsGraph {
+        StatsGraph::from_raw(&self.raw.growth_graph)
+    }
+
+    /// The members who joined and left over time.
+    pub fn members_graph(&self) -> StatsGraph {
+        StatsGraph::from_raw(&self.raw.members_graph)
+    }
+
+    /// Where new members come from.
+    pub fn new_members_by_source_graph(&self) -> StatsGraph {
+        StatsGraph::from_raw(&self.raw.new_members_by_source_graph)
+    }
+
+    /// The languages of the members.
+    pub fn languages_graph(&self) -> StatsGraph {
+        StatsGraph::from_raw(&self.raw.languages_graph)
+    }
+
+    /// The messages sent over time.
+    pub fn messages_graph(&self) -> StatsGraph {
+        StatsGraph::from_raw(&self.raw.messages_graph)
+    }
+
+    /// The actions performed by members over time.
+    pub fn actions_graph(&self) -> StatsGraph {
+        StatsGraph::from_raw(&self.raw.actions_graph)
+    }
+
+    /// The hours of the day at which members are active.
+    pub fn top_hours_graph(&self) -> StatsGraph {
+        StatsGraph::from_raw(&self.raw.top_hours_graph)
+    }
+
+    /// The days of the week at which members are active.
+    pub fn weekdays_graph(&self) -> StatsGraph {
+        StatsGraph::from_raw(&self.raw.weekdays_graph)
+    }
+}
+
+impl MessageStats {
+    /// The views of the post over time.
+    pub fn views_graph(&self) -> StatsGraph {
+        StatsGraph::from_raw(&self.raw.views_graph)
+    }
+
+    /// The reactions to the post, by emoji.
+    pub fn reactions_by_emotion_graph(&self) -> StatsGraph {
+        StatsGraph::from_raw(&self.raw.reactions_by_emotion_graph)
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use crate::types::media::{Document, Sticker};
+use crate::utils;
+use chrono::{DateTime, Utc};
+use grammers_tl_types as tl;
+
+/// A reference to a sticker set, either by its short name (as found in `t.me/addstickers/`
+/// links) or by its identifier and access hash.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StickerSetRef {
+    ShortName(String),
+    Id { id: i64, access_hash: i64 },
+}
+
+/// The information about a sticker set, without its stickers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StickerSetInfo {
+    pub raw: tl::types::StickerSet,
+}
+
+/// A sticker set, along with all of its stickers, as returned by [`Client::get_sticker_set`].
+///
+/// [`Client::get_sticker_set`]: crate::Client::get_sticker_set
+#[derive(Clone, Debug, PartialEq)]
+pub struct StickerSet {
+    pub raw: tl::types::messages::StickerSet,
+}
+
+impl StickerSetRef {
+    pub(crate) fn from_raw(set: &tl::enums::InputStickerSet) -> Option<Self> {
+        match set {
+            tl::enums::InputStickerSet::Id(set) => Some(Self::Id {
+                id: set.id,
+                access_hash: set.access_hash,
+            }),
+            tl::enums::InputStickerSet::ShortName(set) => {
+                Some(Self::ShortName(set.short_name.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn to_raw(&self) -> tl::enums::InputStickerSet {
+        match self {
+            Self::ShortName(short_name) => tl::types::InputStickerSetShortName {
+                short_name: short_name.clone(),
+            }
+            .into(),
+            Self::Id { id, access_hash } => tl::types::InputStickerSetId {
+                id: *id,
+                access_hash: *access_hash,
+            }
+            .into(),
+        }
+    }
+}
+
+impl From<&str> for StickerSetRef {
+    fn from(short_name: &str) -> Self {
+        Self::ShortName(short_name.to_string())
+    }
+}
+
+impl From<String> for StickerSetRef {
+    fn from(short_name: String) -> Self {
+        Self::ShortName(short_name)
+    }
+}
+
+impl From<&StickerSetInfo> for StickerSetRef {
+    fn from(info: &StickerSetInfo) -> Self {
+        info.to_ref()
+    }
+}
+
+impl StickerSetInfo {
+    pub(crate) fn from_raw(set: tl::enums::StickerSet) -> Self {
+        let tl::enums::StickerSet::Set(raw) = set;
+        Self { raw }
+    }
+
+    /// The identifier of the sticker set.
+    pub fn id(&self) -> i64 {
+        self.raw.id
+    }
+
+    /// The title of the sticker set, as shown to users.
+    pub fn title(&self) -> &str {
+        self.raw.title.as_str()
+    }
+
+    /// The short name of the sticker set, used in `t.me/addstickers/` links.
+    pub fn short_name(&self) -> &str {
+        self.raw.short_name.as_str()
+    }
+
+    /// How many stickers the set contains.
+    pub fn count(&self) -> i32 {
+        self.raw.count
+    }
+
+    /// Whether the set was archived.
+    pub fn is_archived(&self) -> bool {
+        self.raw.archived
+    }
+
+    /// Whether the set is an official set made by Telegram.
+    pub fn is_official(&self) -> bool {
+        self.raw.official
+    }
+
+    /// Whether the set contains masks, which are placed on top of photos.
+    pub fn is_masks(&self) -> bool {
+        self.raw.masks
+    }
+
+    /// Whether the set contains custom emoji rather than stickers.
+    pub fn is_emojis(&self) -> bool {
+        self.raw.emojis
+    }
+
+    /// When the set was installed by the logged-in account, if it is installed.
+    pub fn installed_date(&self) -> Option<DateTime<Utc>> {
+        self.raw.installed_date.map(utils::date)
+    }
+
+    /// A reference to this set, which can be used to fetch it again.
+    pub fn to_ref(&self) -> StickerSetRef {
+        StickerSetRef::Id {
+            id: self.raw.id,
+            access_hash: self.raw.access_hash,
+        }
+    }
+}
+
+impl StickerSet {
+    pub(crate) fn from_raw(set: tl::enums::messages::StickerSet) -> Self {
+        match set {
+            tl::enums::messages::StickerSet::Set(raw) => Self { raw },
+            tl::enums::messages::StickerSet::NotModified => {
+                panic!("API returned StickerSet::NotModified even though hash = 0")
+            }
+        }
+    }
+
+    /// The information about the sticker set itself.
+    pub fn info(&self) -> StickerSetInfo {
+        StickerSetInfo::from_raw(self.raw.set.clone())
+    }
+
+    /// The documents of every sticker in the set, in order.
+    pub fn documents(&self) -> Vec<Document> {
+        self.raw
+            .documents
+            .iter()
+            .cloned()
+            .map(Document::from_raw)
+            .collect()
+    }
+
+    /// The stickers in the set, in order.
+    pub fn stickers(&self) -> Vec<Sticker> {
+        self.documents()
+            .iter()
+            .filter_map(Sticker::from_document)
+            .collect()
+    }
+
+    /// The emoji associated with the sticker with the given document identifier.
+    ///
+    /// A sticker can be associated with more than one emoji, and the first one is also found
+    /// in [`Sticker::emoji`].
+    pub fn emojis(&self, document_id: i64) -> Vec<&str> {
+        self.raw
+            .packs
+            .iter()
+            .filter_map(|pack| {
+                let tl::enums::StickerPack::Pack(pack) = pack;
+                if pack.documents.contains(&document_id) {
+                    Some(pack.emoticon.as_str())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
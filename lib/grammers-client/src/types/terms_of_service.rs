@@ -23,6 +23,12 @@ impl TermsOfService {
         Self { raw: tos }
     }
 
+    /// The identifier of these terms, used to accept them.
+    pub fn id(&self) -> &str {
+        let tl::enums::DataJson::Json(id) = &self.raw.id;
+        &id.data
+    }
+
     /// Whether the terms should be shown as a popup dialog to the user.
     pub fn show_popup(&self) -> bool {
         self.raw.popup
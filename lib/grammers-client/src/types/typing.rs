@@ -0,0 +1,207 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use super::{Chat, ChatMap};
+use grammers_tl_types as tl;
+use tl::enums::SendMessageAction as A;
+
+/// An action someone is performing in a chat, such as typing or uploading a photo.
+///
+/// The progress of uploads is a percentage, from 0 to 100.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ChatAction {
+    Typing,
+    Cancel,
+    RecordVideo,
+    UploadVideo(i32),
+    RecordVoice,
+    UploadVoice(i32),
+    UploadPhoto(i32),
+    UploadDocument(i32),
+    ChooseLocation,
+    ChooseContact,
+    PlayGame,
+    RecordRound,
+    UploadRound(i32),
+    SpeakInGroupCall,
+    ImportHistory(i32),
+    ChooseSticker,
+    /// Any other action, such as interacting with an animated emoji.
+    Other(tl::enums::SendMessageAction),
+}
+
+impl ChatAction {
+    pub fn from_raw(action: tl::enums::SendMessageAction) -> Self {
+        match action {
+            A::SendMessageTypingAction => Self::Typing,
+            A::SendMessageCancelAction => Self::Cancel,
+            A::SendMessageRecordVideoAction => Self::RecordVideo,
+            A::SendMessageUploadVideoAction(a) => Self::UploadVideo(a.progress),
+            A::SendMessageRecordAudioAction => Self::RecordVoice,
+            A::SendMessageUploadAudioAction(a) => Self::UploadVoice(a.progress),
+            A::SendMessageUploadPhotoAction(a) => Self::UploadPhoto(a.progress),
+            A::SendMessageUploadDocumentAction(a) => Self::UploadDocument(a.progress),
+            A::SendMessageGeoLocationAction => Self::ChooseLocation,
+            A::SendMessageChooseContactAction => Self::ChooseContact,
+            A::SendMessageGamePlayAction => Self::PlayGame,
+            A::SendMessageRecordRoundAction => Self::RecordRound,
+            A::SendMessageUploadRoundAction(a) => Self::UploadRound(a.progress),
+            A::SpeakingInGroupCallAction => Self::SpeakInGroupCall,
+            A::SendMessageHistoryImportAction(a) => Self::ImportHistory(a.progress),
+            A::SendMessageChooseStickerAction => Self::ChooseSticker,
+            action => Self::Other(action),
+        }
+    }
+}
+
+impl From<ChatAction> for tl::enums::SendMessageAction {
+    fn from(action: ChatAction) -> Self {
+        match action {
+            ChatAction::Typing => A::SendMessageTypingAction,
+            ChatAction::Cancel => A::SendMessageCancelAction,
+            ChatAction::RecordVideo => A::SendMessageRecordVideoAction,
+            ChatAction::UploadVideo(progress) => {
+                tl::types::SendMessageUploadVideoAction { progress }.into()
+            }
+            ChatAction::RecordVoice => A::SendMessageRecordAudioAction,
+            ChatAction::UploadVoice(progress) => {
+                tl::types::SendMessageUploadAudioAction { progress }.into()
+            }
+            ChatAction::UploadPhoto(progress) => {
+                tl::types::SendMessageUploadPhotoAction { progress }.into()
+            }
+            ChatAction::UploadDocument(progress) => {
+                tl::types::SendMessageUploadDocumentAction { progress }.into()
+            }
+            ChatAction::ChooseLocation => A::SendMessageGeoLocationAction,
+            ChatAction::ChooseContact => A::SendMessageChooseContactAction,
+            ChatAction::PlayGame => A::SendMessageGamePlayAction,
+            ChatAction::RecordRound => A::SendMessageRecordRoundAction,
+            ChatAction::UploadRound(progress) => {
+                tl::types::SendMessageUploadRoundAction { progress }.into()
+            }
+            ChatAction::SpeakInGroupCall => A::SpeakingInGroupCallAction,
+            ChatAction::ImportHistory(progress) => {
+                tl::types::SendMessageHistoryImportAction { progress }.into()
+            }
+            ChatAction::ChooseSticker => A::SendMessageChooseStickerAction,
+            ChatAction::Other(action) => action,
+        }
+    }
+}
+
+/// Occurs when someone starts performing an action in a chat, such as typing or recording a
+/// voice message.
+///
+/// The action lasts for about six seconds, unless a new update extends it or
+/// [`ChatAction::Cancel`] ends it.
+///
+/// Telegram often sends these without the users and chats involved, in which case only their
+/// identifiers are known.
+#[derive(Clone, Debug)]
+pub struct Typing {
+    chat_id: i64,
+    chat: Option<Chat>,
+    sender_id: i64,
+    sender: Option<Chat>,
+    top_message_id: Option<i32>,
+    action: ChatAction,
+}
+
+impl Typing {
+    /// Returns `None` if the update is not about someone typing.
+    pub(crate) fn from_raw(update: tl::enums::Update, chats: &ChatMap) -> Option<Self> {
+        use tl::enums::Update as U;
+
+        let (chat, sender, top_message_id, action) = match update {
+            U::UserTyping(u) => {
+                let user: tl::enums::Peer = tl::types::PeerUser { user_id: u.user_id }.into();
+                (user.clone(), user, None, u.action)
+            }
+            U::ChatUserTyping(u) => (
+                tl::types::PeerChat { chat_id: u.chat_id }.into(),
+                u.from_id,
+                None,
+                u.action,
+            ),
+            U::ChannelUserTyping(u) => (
+                tl::types::PeerChannel {
+                    channel_id: u.channel_id,
+                }
+                .into(),
+                u.from_id,
+                u.top_msg_id,
+                u.action,
+            ),
+            _ => return None,
+        };
+
+        Some(Self {
+            chat_id: peer_id(&chat),
+            chat: chats.get(&chat).cloned(),
+            sender_id: peer_id(&sender),
+            sender: chats.get(&sender).cloned(),
+            top_message_id,
+            action: ChatAction::from_raw(action),
+        })
+    }
+
+    /// The identifier of the chat where the action is taking place.
+    ///
+    /// In private conversations, this is the same as [`Typing::sender_id`].
+    pub fn chat_id(&self) -> i64 {
+        self.chat_id
+    }
+
+    /// The chat where the action is taking place, if Telegram sent it along.
+    pub fn chat(&self) -> Option<&Chat> {
+        self.chat.as_ref()
+    }
+
+    /// The identifier of the user (or channel) performing the action.
+    pub fn sender_id(&self) -> i64 {
+        self.sender_id
+    }
+
+    /// The user (or channel) performing the action, if Telegram sent it along.
+    pub fn sender(&self) -> Option<&Chat> {
+        self.sender.as_ref()
+    }
+
+    /// The identifier of the forum topic where the action is taking place, if any.
+    pub fn top_message_id(&self) -> Option<i32> {
+        self.top_message_id
+    }
+
+    /// The action being performed.
+    pub fn action(&self) -> &ChatAction {
+        &self.action
+    }
+}
+
+fn peer_id(peer: &tl::enums::Peer) -> i64 {
+    match peer {
+        tl::enums::Peer::User(user) => user.user_id,
+        tl::enums::Peer::Chat(chat) => chat.chat_id,
+        tl::enums::Peer::Channel(channel) => channel.channel_id,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_action_roundtrip() {
+        let raw: tl::enums::SendMessageAction =
+            tl::types::SendMessageUploadPhotoAction { progress: 42 }.into();
+        let action = ChatAction::from_raw(raw.clone());
+        assert_eq!(action, ChatAction::UploadPhoto(42));
+        assert_eq!(tl::enums::SendMessageAction::from(action), raw);
+    }
+}
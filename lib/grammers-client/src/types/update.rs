@@ -6,12 +6,18 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::fmt;
+use std::ops::Deref;
 use std::sync::Arc;
 
-use super::{CallbackQuery, ChatMap, InlineQuery, InlineSend, Message};
-use crate::{types::MessageDeletion, Client};
+use super::{
+    CallbackQuery, ChatMap, ChatMemberUpdate, InlineQuery, InlineSend, Message, PhoneCall,
+    PollUpdate, PollVote, Typing,
+};
+use crate::{types::MessageDeletion, types::MessageViews, types::UserPresence, Client};
 use grammers_tl_types as tl;
 
+#[allow(clippy::large_enum_variant)]
 #[non_exhaustive]
 #[derive(Debug, Clone)]
 pub enum Update {
@@ -20,7 +26,11 @@ pub enum Update {
     /// Occurs when a message is updated.
     MessageEdited(Message),
     /// Occurs when a message is deleted.
+    ///
+    /// The chat is only known for channels. See [`MessageDeletion`] for how to find it otherwise.
     MessageDeleted(MessageDeletion),
+    /// Occurs when the view counter of a channel post changes.
+    MessageViews(MessageViews),
     /// Occurs when Telegram calls back into your bot because an inline callback
     /// button was pressed.
     CallbackQuery(CallbackQuery),
@@ -29,6 +39,25 @@ pub enum Update {
     InlineQuery(InlineQuery),
     /// Represents an update of user choosing the result of inline query and sending it to their chat partner.
     InlineSend(InlineSend),
+    /// Occurs when the state of an incoming or outgoing phone call changes.
+    PhoneCall(PhoneCall),
+    /// Occurs when a user goes online or offline.
+    UserStatus(UserPresence),
+    /// Occurs when a user joins or leaves a group or channel, or their role in it changes (for
+    /// example, when they are promoted or banned).
+    ChatMember(ChatMemberUpdate),
+    /// Occurs when someone is typing, or performing some other action, in a chat.
+    Typing(Typing),
+    /// Occurs when the results of a poll change.
+    Poll(PollUpdate),
+    /// Occurs when a user votes in a poll sent by the logged-in bot.
+    PollVote(PollVote),
+    /// Occurs when a secret chat is requested, accepted, or discarded.
+    #[cfg(feature = "secret-chats")]
+    SecretChat(super::SecretChat),
+    /// Occurs when a new message is received in a secret chat.
+    #[cfg(feature = "secret-chats")]
+    SecretMessage(super::SecretMessage),
     /// Raw events are not actual events.
     /// Instead, they are the raw Update object that Telegram sends. You
     /// normally shouldn’t need these.
@@ -36,7 +65,61 @@ pub enum Update {
     /// **NOTE**: the library can split raw updates into actual `Update`
     /// variants so use this only as the workaround when such variant is not
     /// available yet.
-    Raw(tl::enums::Update),
+    ///
+    /// If [`InitParams::raw_updates`](crate::InitParams::raw_updates) is enabled, every update
+    /// is also delivered in this form, right before its friendly variant.
+    Raw(RawUpdate),
+}
+
+/// A raw update as sent by Telegram, along with the users and chats that came with it.
+///
+/// It dereferences to the raw [`tl::enums::Update`].
+#[derive(Clone)]
+pub struct RawUpdate {
+    raw: tl::enums::Update,
+    chats: Arc<ChatMap>,
+}
+
+impl RawUpdate {
+    pub fn new(raw: tl::enums::Update, chats: Arc<ChatMap>) -> Self {
+        Self { raw, chats }
+    }
+
+    /// The raw update.
+    pub fn raw(&self) -> &tl::enums::Update {
+        &self.raw
+    }
+
+    /// Users and chats that were sent along with the update, which can be used to look up the
+    /// peers it refers to.
+    pub fn chats(&self) -> &ChatMap {
+        &self.chats
+    }
+
+    /// Take the raw update, discarding the users and chats.
+    pub fn into_raw(self) -> tl::enums::Update {
+        self.raw
+    }
+}
+
+impl Deref for RawUpdate {
+    type Target = tl::enums::Update;
+
+    fn deref(&self) -> &Self::Target {
+        &self.raw
+    }
+}
+
+impl From<RawUpdate> for tl::enums::Update {
+    fn from(update: RawUpdate) -> Self {
+        update.raw
+    }
+}
+
+impl fmt::Debug for RawUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.raw.fmt(f)
+    }
 }
 
 impl Update {
@@ -73,6 +156,11 @@ impl Update {
                 messages, channel_id,
             ))),
 
+            // MessageViews
+            tl::enums::Update::ChannelMessageViews(views) => {
+                Some(Self::MessageViews(MessageViews::new(views)))
+            }
+
             // CallbackQuery
             tl::enums::Update::BotCallbackQuery(query) => Some(Self::CallbackQuery(
                 CallbackQuery::from_raw(client, query, chats),
@@ -88,8 +176,41 @@ impl Update {
                 Some(Self::InlineSend(InlineSend::from_raw(query, client, chats)))
             }
 
+            // PhoneCall
+            tl::enums::Update::PhoneCall(tl::types::UpdatePhoneCall { phone_call }) => Some(
+                Self::PhoneCall(PhoneCall::from_raw(client, phone_call, chats)),
+            ),
+
+            // UserStatus
+            tl::enums::Update::UserStatus(status) => {
+                Some(Self::UserStatus(UserPresence::new(status)))
+            }
+
+            // ChatMember
+            tl::enums::Update::ChatParticipant(update) => {
+                ChatMemberUpdate::from_raw_chat(update, chats).map(Self::ChatMember)
+            }
+            tl::enums::Update::ChannelParticipant(update) => {
+                ChatMemberUpdate::from_raw_channel(update, chats).map(Self::ChatMember)
+            }
+
+            // Typing
+            update @ (tl::enums::Update::UserTyping(_)
+            | tl::enums::Update::ChatUserTyping(_)
+            | tl::enums::Update::ChannelUserTyping(_)) => {
+                Typing::from_raw(update, chats).map(Self::Typing)
+            }
+
+            // Poll
+            tl::enums::Update::MessagePoll(update) => Some(Self::Poll(PollUpdate::new(update))),
+
+            // PollVote
+            tl::enums::Update::MessagePollVote(update) => {
+                Some(Self::PollVote(PollVote::new(update, chats)))
+            }
+
             // Raw
-            update => Some(Self::Raw(update)),
+            update => Some(Self::Raw(RawUpdate::new(update, chats.clone()))),
         }
     }
 }
@@ -0,0 +1,126 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use grammers_tl_types as tl;
+use std::collections::HashSet;
+
+/// Decides which new and edited messages are returned as updates.
+///
+/// Messages that don't pass the filter are discarded as soon as they are received, before any
+/// work is done to turn them into a [`Message`](crate::types::Message). Other kinds of updates
+/// are never filtered.
+///
+/// Chats are identified by their bare identifier, as returned by
+/// [`Chat::id`](crate::types::Chat::id).
+///
+/// # Examples
+///
+/// ```
+/// # fn f(client: grammers_client::Client) {
+/// use grammers_client::types::UpdateFilter;
+///
+/// // Only handle messages in these groups, or those sent in private.
+/// client.set_update_filter(UpdateFilter::new().allow_chats([123, 456]).private_only(true));
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct UpdateFilter {
+    allowed_chats: Option<HashSet<i64>>,
+    blocked_chats: HashSet<i64>,
+    private_only: bool,
+    mentions_only: bool,
+}
+
+impl UpdateFilter {
+    /// Create a new filter which lets every message through.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only let through messages from the given chats (or from the private conversations and
+    /// mentions selected by [`UpdateFilter::private_only`] and [`UpdateFilter::mentions_only`]).
+    ///
+    /// May be called multiple times to allow more chats.
+    pub fn allow_chats(mut self, chat_ids: impl IntoIterator<Item = i64>) -> Self {
+        self.allowed_chats
+            .get_or_insert_with(HashSet::new)
+            .extend(chat_ids);
+        self
+    }
+
+    /// Never let through messages from the given chats.
+    ///
+    /// This takes precedence over every other option. May be called multiple times to block
+    /// more chats.
+    pub fn block_chats(mut self, chat_ids: impl IntoIterator<Item = i64>) -> Self {
+        self.blocked_chats.extend(chat_ids);
+        self
+    }
+
+    /// Only let through messages sent in private conversations.
+    ///
+    /// If [`UpdateFilter::mentions_only`] is also set, messages that mention the logged-in
+    /// account are let through too.
+    pub fn private_only(mut self, private_only: bool) -> Self {
+        self.private_only = private_only;
+        self
+    }
+
+    /// Only let through messages that mention the logged-in account, or reply to it.
+    ///
+    /// If [`UpdateFilter::private_only`] is also set, messages sent in private conversations
+    /// are let through too.
+    pub fn mentions_only(mut self, mentions_only: bool) -> Self {
+        self.mentions_only = mentions_only;
+        self
+    }
+
+    /// Whether the filter lets the update through.
+    pub(crate) fn allows(&self, update: &tl::enums::Update) -> bool {
+        use tl::enums::Update as U;
+
+        let message = match update {
+            U::NewMessage(u) => &u.message,
+            U::NewChannelMessage(u) => &u.message,
+            U::EditMessage(u) => &u.message,
+            U::EditChannelMessage(u) => &u.message,
+            _ => return true,
+        };
+        let (peer, mentioned) = match message {
+            tl::enums::Message::Message(m) => (&m.peer_id, m.mentioned),
+            tl::enums::Message::Service(m) => (&m.peer_id, m.mentioned),
+            tl::enums::Message::Empty(_) => return true,
+        };
+        let (chat_id, private) = match peer {
+            tl::enums::Peer::User(user) => (user.user_id, true),
+            tl::enums::Peer::Chat(chat) => (chat.chat_id, false),
+            tl::enums::Peer::Channel(channel) => (channel.channel_id, false),
+        };
+
+        if self.blocked_chats.contains(&chat_id) {
+            return false;
+        }
+
+        let allowed = self
+            .allowed_chats
+            .as_ref()
+            .map(|allowed| allowed.contains(&chat_id));
+        let selected = match (self.private_only, self.mentions_only) {
+            (false, false) => None,
+            (true, false) => Some(private),
+            (false, true) => Some(mentioned),
+            (true, true) => Some(private || mentioned),
+        };
+
+        match (allowed, selected) {
+            (None, None) => true,
+            (Some(allowed), None) => allowed,
+            (None, Some(selected)) => selected,
+            (Some(allowed), Some(selected)) => allowed || selected,
+        }
+    }
+}
@@ -0,0 +1,34 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use crate::types::UserStatus;
+use grammers_tl_types as tl;
+
+/// Occurs whenever a user goes online or offline.
+///
+/// Telegram only sends these for users whose status the logged-in account can see, such as
+/// contacts.
+#[derive(Debug, Clone)]
+pub struct UserPresence {
+    pub raw: tl::types::UpdateUserStatus,
+}
+
+impl UserPresence {
+    pub(crate) fn new(raw: tl::types::UpdateUserStatus) -> Self {
+        Self { raw }
+    }
+
+    /// Returns the ID of the user whose status changed.
+    pub fn user_id(&self) -> i64 {
+        self.raw.user_id
+    }
+
+    /// Returns the new status of the user.
+    pub fn status(&self) -> UserStatus {
+        UserStatus::from_raw(&self.raw.status)
+    }
+}
@@ -38,10 +38,90 @@ pub(crate) fn generate_random_ids(n: usize) -> Vec<i64> {
     (0..n).map(|_| generate_random_id()).collect()
 }
 
+/// Compute the hash Telegram uses to tell whether a list of items changed, given their
+/// identifiers in the order Telegram expects.
+pub(crate) fn hash_ids(ids: impl IntoIterator<Item = i64>) -> i64 {
+    ids.into_iter().fold(0u64, |hash, id| {
+        let hash = hash ^ (hash >> 21);
+        let hash = hash ^ (hash << 35);
+        let hash = hash ^ (hash >> 4);
+        hash.wrapping_add(id as u64)
+    }) as i64
+}
+
 pub(crate) fn date(date: i32) -> DateTime<Utc> {
     DateTime::<Utc>::from_timestamp(date as i64, 0).expect("date out of range")
 }
 
+/// Extensions of the files most commonly sent through Telegram, along with their mime types.
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("7z", "application/x-7z-compressed"),
+    ("aac", "audio/aac"),
+    ("apk", "application/vnd.android.package-archive"),
+    ("avi", "video/x-msvideo"),
+    ("bmp", "image/bmp"),
+    ("csv", "text/csv"),
+    ("doc", "application/msword"),
+    (
+        "docx",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    ),
+    ("flac", "audio/flac"),
+    ("gif", "image/gif"),
+    ("gz", "application/gzip"),
+    ("heic", "image/heic"),
+    ("htm", "text/html"),
+    ("html", "text/html"),
+    ("jpeg", "image/jpeg"),
+    ("jpg", "image/jpeg"),
+    ("json", "application/json"),
+    ("m4a", "audio/mp4"),
+    ("md", "text/markdown"),
+    ("mkv", "video/x-matroska"),
+    ("mov", "video/quicktime"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+    ("oga", "audio/ogg"),
+    ("ogg", "audio/ogg"),
+    ("ogv", "video/ogg"),
+    ("opus", "audio/ogg"),
+    ("pdf", "application/pdf"),
+    ("png", "image/png"),
+    ("ppt", "application/vnd.ms-powerpoint"),
+    (
+        "pptx",
+        "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+    ),
+    ("rar", "application/vnd.rar"),
+    ("svg", "image/svg+xml"),
+    ("tar", "application/x-tar"),
+    ("tgs", "application/x-tgsticker"),
+    ("tif", "image/tiff"),
+    ("tiff", "image/tiff"),
+    ("txt", "text/plain"),
+    ("wav", "audio/wav"),
+    ("webm", "video/webm"),
+    ("webp", "image/webp"),
+    ("xls", "application/vnd.ms-excel"),
+    (
+        "xlsx",
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    ),
+    ("xml", "application/xml"),
+    ("zip", "application/zip"),
+];
+
+/// Guess the mime type of a file from the extension in its name or path.
+pub(crate) fn guess_mime_type(name: &str) -> Option<&'static str> {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let (_, extension) = name.rsplit_once('.')?;
+    let extension = extension.to_ascii_lowercase();
+    MIME_TYPES
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, mime)| *mime)
+}
+
 pub(crate) fn extract_password_parameters(
     current_algo: &tl::enums::PasswordKdfAlgo,
 ) -> (&Vec<u8>, &Vec<u8>, &Vec<u8>, &i32) {
@@ -99,3 +179,23 @@ pub(crate) fn always_find_entity(
         None => types::Chat::unpack(get_packed()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_guess_mime_type() {
+        assert_eq!(guess_mime_type("photo.JPG"), Some("image/jpeg"));
+        assert_eq!(
+            guess_mime_type("/tmp/archive.tar.gz"),
+            Some("application/gzip")
+        );
+        assert_eq!(
+            guess_mime_type("https://example.com/video.mp4"),
+            Some("video/mp4")
+        );
+        assert_eq!(guess_mime_type("dir.d/README"), None);
+        assert_eq!(guess_mime_type("file.unknown"), None);
+    }
+}
@@ -35,6 +35,7 @@ define_benches!(
     fn encrypt_kb0016(ige_encrypt, 16 * 1024);
     fn encrypt_kb0128(ige_encrypt, 128 * 1024);
     fn encrypt_kb0512(ige_encrypt, 512 * 1024);
+    fn encrypt_mb0001(ige_encrypt, 1024 * 1024);
 
     fn decrypt_b0016(ige_decrypt, 16);
     fn decrypt_b0256(ige_decrypt, 256);
@@ -44,6 +45,7 @@ define_benches!(
     fn decrypt_kb0016(ige_decrypt, 16 * 1024);
     fn decrypt_kb0128(ige_decrypt, 128 * 1024);
     fn decrypt_kb0512(ige_decrypt, 512 * 1024);
+    fn decrypt_mb0001(ige_decrypt, 1024 * 1024);
 );
 
 benchmark_group!(
@@ -53,7 +55,13 @@ benchmark_group!(
     encrypt_b0512,
     encrypt_b1024
 );
-benchmark_group!(encrypt_big, encrypt_kb0016, encrypt_kb0128, encrypt_kb0512);
+benchmark_group!(
+    encrypt_big,
+    encrypt_kb0016,
+    encrypt_kb0128,
+    encrypt_kb0512,
+    encrypt_mb0001
+);
 benchmark_group!(
     decrypt_small,
     decrypt_b0016,
@@ -61,5 +69,11 @@ benchmark_group!(
     decrypt_b0512,
     decrypt_b1024
 );
-benchmark_group!(decrypt_big, decrypt_kb0016, decrypt_kb0128, decrypt_kb0512);
+benchmark_group!(
+    decrypt_big,
+    decrypt_kb0016,
+    decrypt_kb0128,
+    decrypt_kb0512,
+    decrypt_mb0001
+);
 benchmark_main!(encrypt_small, encrypt_big, decrypt_small, decrypt_big);
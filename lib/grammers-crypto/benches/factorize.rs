@@ -0,0 +1,101 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use bencher::{benchmark_group, benchmark_main, black_box, Bencher};
+use grammers_crypto::factorize::factorize;
+
+// Values of `pq` sent by the server during authentication.
+const SERVER_PQ: [u64; 3] = [
+    1470626929934143021,
+    2363612107535801713,
+    2804275833720261793,
+];
+
+// Products of two random primes between 2^30 and 2^32, which is the shape of the values sent by
+// the server, to get a better picture of the slower cases.
+const SYNTHETIC_PQ: [u64; 61] = [
+    2335505105284434301,
+    6865230840037877521,
+    5684070701701245289,
+    7552635287597037511,
+    5944226628113991883,
+    6259802472723006323,
+    4945474726793258303,
+    6623667776827536469,
+    3352176955661380663,
+    6515848031752883953,
+    3664491654256541629,
+    4653051039769447547,
+    6228997108106270287,
+    3983051159281083707,
+    4843812181729227821,
+    3277010865883042391,
+    4393197021422932487,
+    6108467497941101387,
+    6277521030649600763,
+    3617311332036343913,
+    6988622914633876409,
+    3848259838236365479,
+    5770068549896965729,
+    3079021875926001739,
+    3251313668538429259,
+    2731060783745585743,
+    4928649842538691987,
+    7055828330713616141,
+    4629797765122087601,
+    5523077598783156977,
+    3693629476003978087,
+    5892378628170550367,
+    6269308086258558101,
+    4751563503930804229,
+    3207836693425201147,
+    8478180912293272249,
+    2578297768937061947,
+    1271991827915493479,
+    3019703339832225643,
+    7830731223360256163,
+    1607121797495881241,
+    3794471599053760949,
+    4100191754895225607,
+    3262790587695153659,
+    5692809729864938573,
+    7929701362539652187,
+    6715543971811435973,
+    5011816009898858047,
+    6594185651534042137,
+    7882481147403465037,
+    5860093769173177151,
+    2807445421186109461,
+    2860599944953994491,
+    2601165429939931831,
+    7632879697642558717,
+    2474479966488366227,
+    5766442843941557081,
+    4874224807401314207,
+    8970678635851475209,
+    5735070597263612333,
+    3410375180359848509,
+];
+
+fn factorize_server(bench: &mut Bencher) {
+    bench.iter(|| {
+        for pq in SERVER_PQ {
+            black_box(factorize(black_box(pq)));
+        }
+    });
+}
+
+fn factorize_synthetic(bench: &mut Bencher) {
+    bench.iter(|| {
+        for pq in SYNTHETIC_PQ {
+            black_box(factorize(black_box(pq)));
+        }
+    });
+}
+
+benchmark_group!(benches, factorize_server, factorize_synthetic);
+benchmark_main!(benches);
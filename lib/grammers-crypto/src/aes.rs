@@ -5,82 +5,220 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
+use aes::cipher::consts::U16;
 use aes::cipher::generic_array::GenericArray;
-use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
-use std::mem;
+use aes::cipher::{BlockBackend, BlockClosure, BlockDecrypt, BlockEncrypt, BlockSizeUser, KeyInit};
+use aes::Block;
+
+/// A running AES-256 keystream in CTR mode, as used by the obfuscated transport.
+///
+/// Encryption and decryption are the same operation. The position in the keystream is kept
+/// between calls, so a stream of data may be processed in chunks of any size.
+pub struct Ctr256 {
+    cipher: aes::Aes256,
+    counter: [u8; 16],
+    keystream: [u8; 16],
+    used: usize,
+}
+
+impl Ctr256 {
+    pub fn new(key: &[u8; 32], iv: &[u8; 16]) -> Self {
+        Self {
+            cipher: aes::Aes256::new(GenericArray::from_slice(key)),
+            counter: *iv,
+            keystream: [0; 16],
+            used: 16,
+        }
+    }
+
+    /// Encrypt or decrypt the input buffer in-place, advancing the keystream.
+    pub fn apply(&mut self, buffer: &mut [u8]) {
+        for x in buffer.iter_mut() {
+            if self.used == 16 {
+                self.keystream = self.counter;
+                self.cipher
+                    .encrypt_block(GenericArray::from_mut_slice(&mut self.keystream));
+                self.counter = (u128::from_be_bytes(self.counter).wrapping_add(1)).to_be_bytes();
+                self.used = 0;
+            }
+            *x ^= self.keystream[self.used];
+            self.used += 1;
+        }
+    }
+}
 
 /// Encrypt the input plaintext in-place using the AES-IGE mode.
+///
+/// The chaining runs inside the cipher's backend closure, so the AES-NI backend is picked once
+/// for the whole buffer (instead of once per block), and the portable implementation of the
+/// `aes` crate is used when the CPU lacks it. On ARMv8, the hardware backend is only compiled in
+/// when building with `RUSTFLAGS="--cfg aes_armv8"`.
 pub fn ige_encrypt(buffer: &mut [u8], key: &[u8; 32], iv: &[u8; 32]) {
-    assert!(buffer.len() % 16 == 0);
-
-    let key = GenericArray::from_slice(key);
-    let cipher = aes::Aes256::new(key);
-
-    let mut plaintext_block = [0; 16];
-    let mut iv1 = [0; 16];
-    let mut iv2 = [0; 16];
-    iv1.copy_from_slice(&iv[..16]);
-    iv2.copy_from_slice(&iv[16..]);
-
-    for ciphertext_block in buffer.chunks_mut(16) {
-        plaintext_block.copy_from_slice(ciphertext_block);
-
-        // block = block XOR iv1
-        ciphertext_block
-            .iter_mut()
-            .zip(plaintext_block)
-            .zip(iv1.as_ref())
-            .for_each(|((x, a), b)| *x = a ^ b);
-
-        // block = encrypt(block);
-        let ciphertext_block = GenericArray::from_mut_slice(ciphertext_block);
-        cipher.encrypt_block(ciphertext_block);
-
-        // block = block XOR iv2
-        ciphertext_block
-            .iter_mut()
-            .zip(iv2.as_ref())
-            .for_each(|(x, a)| *x ^= a);
-
-        // save ciphertext and adjust iv
-        iv1.copy_from_slice(ciphertext_block);
-        mem::swap(&mut iv2, &mut plaintext_block);
-    }
+    assert!(buffer.len().is_multiple_of(16));
+
+    let cipher = aes::Aes256::new(GenericArray::from_slice(key));
+    cipher.encrypt_with_backend(IgeEncrypt { buffer, iv });
 }
 
 /// Decrypt the input ciphertext using the AES-IGE mode.
 pub fn ige_decrypt(ciphertext: &[u8], key: &[u8; 32], iv: &[u8; 32]) -> Vec<u8> {
-    let size = ciphertext.len();
-    assert!(size % 16 == 0);
-    let mut plaintext = vec![0; size];
-
-    let key = GenericArray::from_slice(key);
-    let cipher = aes::Aes256::new(key);
-    let mut iv = *iv;
-    let (iv1, iv2) = iv.split_at_mut(16);
-
-    for (ciphertext_block, plaintext_block) in ciphertext.chunks(16).zip(plaintext.chunks_mut(16)) {
-        // block = block XOR iv2
-        plaintext_block
-            .iter_mut()
-            .zip(ciphertext_block)
-            .zip(iv2.as_ref())
-            .for_each(|((a, x), b)| *a = x ^ b);
-
-        // block = decrypt(block);
-        let plaintext_block = GenericArray::from_mut_slice(plaintext_block);
-        cipher.decrypt_block(plaintext_block);
-
-        // block = block XOR iv1
-        plaintext_block
-            .iter_mut()
-            .zip(iv1.as_ref())
-            .for_each(|(a, b)| *a ^= b);
-
-        // save plaintext and adjust iv
-        iv1.copy_from_slice(ciphertext_block);
-        iv2.copy_from_slice(plaintext_block);
-    }
+    assert!(ciphertext.len().is_multiple_of(16));
+    let mut plaintext = ciphertext.to_vec();
+
+    let cipher = aes::Aes256::new(GenericArray::from_slice(key));
+    cipher.decrypt_with_backend(IgeDecrypt {
+        buffer: &mut plaintext,
+        iv,
+    });
 
     plaintext
 }
+
+fn xor_block(block: &mut Block, other: &Block) {
+    block.iter_mut().zip(other).for_each(|(a, b)| *a ^= b);
+}
+
+struct IgeEncrypt<'a> {
+    buffer: &'a mut [u8],
+    iv: &'a [u8; 32],
+}
+
+impl BlockSizeUser for IgeEncrypt<'_> {
+    type BlockSize = U16;
+}
+
+impl BlockClosure for IgeEncrypt<'_> {
+    #[inline(always)]
+    fn call<B: BlockBackend<BlockSize = U16>>(self, backend: &mut B) {
+        let mut iv1 = *Block::from_slice(&self.iv[..16]);
+        let mut iv2 = *Block::from_slice(&self.iv[16..]);
+
+        for block in self.buffer.chunks_exact_mut(16) {
+            let block = Block::from_mut_slice(block);
+            let plaintext = *block;
+
+            // block = encrypt(block XOR iv1) XOR iv2
+            xor_block(block, &iv1);
+            backend.proc_block(block.into());
+            xor_block(block, &iv2);
+
+            iv1 = *block;
+            iv2 = plaintext;
+        }
+    }
+}
+
+struct IgeDecrypt<'a> {
+    buffer: &'a mut [u8],
+    iv: &'a [u8; 32],
+}
+
+impl BlockSizeUser for IgeDecrypt<'_> {
+    type BlockSize = U16;
+}
+
+impl BlockClosure for IgeDecrypt<'_> {
+    #[inline(always)]
+    fn call<B: BlockBackend<BlockSize = U16>>(self, backend: &mut B) {
+        let mut iv1 = *Block::from_slice(&self.iv[..16]);
+        let mut iv2 = *Block::from_slice(&self.iv[16..]);
+
+        for block in self.buffer.chunks_exact_mut(16) {
+            let block = Block::from_mut_slice(block);
+            let ciphertext = *block;
+
+            // block = decrypt(block XOR iv2) XOR iv1
+            xor_block(block, &iv2);
+            backend.proc_block(block.into());
+            xor_block(block, &iv1);
+
+            iv1 = ciphertext;
+            iv2 = *block;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes::cipher::{BlockDecrypt, BlockEncrypt};
+    use std::mem;
+
+    // The straightforward block-by-block implementation, kept to check the backend-based one.
+    fn reference_ige_encrypt(buffer: &mut [u8], key: &[u8; 32], iv: &[u8; 32]) {
+        let cipher = aes::Aes256::new(GenericArray::from_slice(key));
+        let mut iv1 = [0; 16];
+        let mut iv2 = [0; 16];
+        iv1.copy_from_slice(&iv[..16]);
+        iv2.copy_from_slice(&iv[16..]);
+
+        for block in buffer.chunks_mut(16) {
+            let mut plaintext = [0; 16];
+            plaintext.copy_from_slice(block);
+            block.iter_mut().zip(iv1).for_each(|(x, a)| *x ^= a);
+            cipher.encrypt_block(GenericArray::from_mut_slice(block));
+            block.iter_mut().zip(iv2).for_each(|(x, a)| *x ^= a);
+            iv1.copy_from_slice(block);
+            mem::swap(&mut iv2, &mut plaintext);
+        }
+    }
+
+    fn reference_ige_decrypt(ciphertext: &[u8], key: &[u8; 32], iv: &[u8; 32]) -> Vec<u8> {
+        let cipher = aes::Aes256::new(GenericArray::from_slice(key));
+        let mut plaintext = ciphertext.to_vec();
+        let mut iv1 = [0; 16];
+        let mut iv2 = [0; 16];
+        iv1.copy_from_slice(&iv[..16]);
+        iv2.copy_from_slice(&iv[16..]);
+
+        for (block, ciphertext) in plaintext.chunks_mut(16).zip(ciphertext.chunks(16)) {
+            block.iter_mut().zip(iv2).for_each(|(x, a)| *x ^= a);
+            cipher.decrypt_block(GenericArray::from_mut_slice(block));
+            block.iter_mut().zip(iv1).for_each(|(x, a)| *x ^= a);
+            iv1.copy_from_slice(ciphertext);
+            iv2.copy_from_slice(block);
+        }
+
+        plaintext
+    }
+
+    fn get_test_data(len: usize) -> (Vec<u8>, [u8; 32], [u8; 32]) {
+        let data = (0..len).map(|i| (i * 31 + i / 7) as u8).collect();
+        let key = std::array::from_fn(|i| (i * 7 + 1) as u8);
+        let iv = std::array::from_fn(|i| (i * 13 + 5) as u8);
+        (data, key, iv)
+    }
+
+    #[test]
+    fn ige_encrypt_matches_reference() {
+        for len in [0, 16, 32, 256, 4096, 1024 * 1024] {
+            let (data, key, iv) = get_test_data(len);
+            let mut expected = data.clone();
+            reference_ige_encrypt(&mut expected, &key, &iv);
+            let mut actual = data;
+            ige_encrypt(&mut actual, &key, &iv);
+            assert_eq!(actual, expected, "mismatch for {len} bytes");
+        }
+    }
+
+    #[test]
+    fn ige_decrypt_matches_reference() {
+        for len in [0, 16, 32, 256, 4096, 1024 * 1024] {
+            let (data, key, iv) = get_test_data(len);
+            assert_eq!(
+                ige_decrypt(&data, &key, &iv),
+                reference_ige_decrypt(&data, &key, &iv),
+                "mismatch for {len} bytes"
+            );
+        }
+    }
+
+    #[test]
+    fn ige_roundtrip() {
+        let (data, key, iv) = get_test_data(1024 * 1024);
+        let mut buffer = data.clone();
+        ige_encrypt(&mut buffer, &key, &iv);
+        assert_ne!(buffer, data);
+        assert_eq!(ige_decrypt(&buffer, &key, &iv), data);
+    }
+}
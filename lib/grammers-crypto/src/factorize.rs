@@ -6,7 +6,25 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-fn gcd(mut a: u128, mut b: u128) -> u128 {
+use getrandom::getrandom;
+
+// How many values of `f(x)` are multiplied together before computing their GCD with `pq`.
+const BATCH_SIZE: u64 = 128;
+
+// How many times `f(x)` may be evaluated before giving up on a choice of `y` and `c`.
+//
+// The factors sent by Telegram are around 2^31, so the algorithm is expected to find one in
+// about 2^16 iterations. Allowing a few times that leaves plenty of room for unlucky choices
+// without getting stuck on one.
+const MAX_ITERATIONS: u64 = 1 << 20;
+
+// How many different choices of `y` and `c` are tried before giving up.
+const ATTEMPTS: usize = 16;
+
+// Factors below this bound are found by trial division, which is faster than Pollard's rho.
+const TRIAL_DIVISION_BOUND: u64 = 1 << 10;
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
     while b != 0 {
         let (na, nb) = (b, a % b);
         a = na;
@@ -15,102 +33,108 @@ fn gcd(mut a: u128, mut b: u128) -> u128 {
     a
 }
 
-fn modpow(mut n: u128, mut e: u128, m: u128) -> u128 {
-    if m == 1 {
-        return 0;
-    }
+fn mulmod(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 * b as u128) % m as u128) as u64
+}
 
-    let mut result = 1;
-    n %= m;
-    while e > 0 {
-        if e % 2 == 1 {
-            result = (result * n) % m;
-        }
-        e >>= 1;
-        n = (n * n) % m;
-    }
-    result
+fn random_below(n: u64) -> u64 {
+    let mut buffer = [0; 8];
+    getrandom(&mut buffer).expect("failed to generate a random value");
+    1 + u64::from_le_bytes(buffer) % (n - 1)
 }
 
 /// Factorize the given number into its two prime factors.
 ///
-/// The algorithm here is a faster variant of [Pollard's rho algorithm],
-/// published by [Richard Brent], based on
-/// <https://comeoncodeon.wordpress.com/2010/09/18/pollard-rho-brent-integer-factorization/>.
+/// Small factors are found by trial division. Otherwise, a faster variant of
+/// [Pollard's rho algorithm], published by [Richard Brent], is used. The products of several
+/// steps are accumulated before computing their GCD with `pq`, as described in the paper.
+///
+/// Each attempt is given a bounded amount of iterations. If it fails to find a factor in time,
+/// a different random starting point and polynomial are tried instead.
 ///
 /// Pollard's rho algorithm: <https://en.wikipedia.org/wiki/Pollard%27s_rho_algorithm>
 /// Richard Brent: <https://maths-people.anu.edu.au/~brent/pd/rpb051i.pdf>
-#[allow(clippy::many_single_char_names)]
 pub fn factorize(pq: u64) -> (u64, u64) {
-    const ATTEMPTS: [u64; 5] = [43, 47, 53, 59, 61];
-    for attempt in ATTEMPTS {
-        // > Note that this algorithm may not find the factors and will return failure for composite n.
-        // > In that case, use a different f(x) and try again [...] We choose f(x) = x*x + c
-        // Thus by choosing a different `c` we're changing `f(x)` and can try again.
-        // Prime factors are used for the attempts in the hopes they'll be more likely to work.
-        let c = attempt * (pq / 103);
-        let (p, q) = factorize_with_param(pq, c);
-        if p != 1 {
-            return (p, q);
+    let p = trial_division(pq).or_else(|| {
+        (0..ATTEMPTS).find_map(|_| {
+            // > Note that this algorithm may not find the factors and will return failure for composite n.
+            // > In that case, use a different f(x) and try again [...] We choose f(x) = x*x + c
+            // Thus by choosing a different `c` (and starting point `y`) we can try again.
+            brent(pq, random_below(pq), random_below(pq), MAX_ITERATIONS)
+        })
+    });
+
+    match p {
+        Some(p) => {
+            let q = pq / p;
+            (p.min(q), p.max(q))
         }
+        None => panic!("failed to factorize in a fixed amount of attempts"),
     }
-    panic!("failed to factorize in a fixed amount of attempts")
 }
 
-fn factorize_with_param(pq: u64, c: u64) -> (u64, u64) {
-    if pq % 2 == 0 {
-        return (2, pq / 2);
+fn trial_division(pq: u64) -> Option<u64> {
+    if pq.is_multiple_of(2) {
+        return Some(2);
     }
+    (3..TRIAL_DIVISION_BOUND.min(pq))
+        .step_by(2)
+        .find(|d| pq.is_multiple_of(*d))
+}
 
-    let pq = pq as u128;
-    fn abs_sub(a: u128, b: u128) -> u128 {
-        a.max(b) - a.min(b)
-    }
+#[allow(clippy::many_single_char_names)]
+fn brent(pq: u64, mut y: u64, c: u64, max_iterations: u64) -> Option<u64> {
+    let f = |x: u64| ((x as u128 * x as u128 + c as u128) % pq as u128) as u64;
 
-    // Random values in the range of 1..pq, chosen by fair dice roll.
-    // c is an input free to change in case the chosen value fails.
-    let mut y = 3 * (pq / 7);
-    let c = c as u128;
-    let m = 7 * (pq / 13);
-    let mut g = 1u128;
-    let mut r = 1u128;
-    let mut q = 1u128;
-    let mut x = 0u128;
-    let mut ys = 0u128;
+    let mut g = 1;
+    let mut r = 1;
+    let mut q = 1;
+    let mut x = 0;
+    let mut ys = 0;
+    let mut iterations = 0;
 
     while g == 1 {
+        if iterations > max_iterations {
+            return None;
+        }
+
         x = y;
         for _ in 0..r {
-            y = (modpow(y, 2, pq) + c) % pq;
+            y = f(y);
         }
 
         let mut k = 0;
         while k < r && g == 1 {
             ys = y;
-            for _ in 0..m.min(r - k) {
-                y = (modpow(y, 2, pq) + c) % pq;
-                q = (q * abs_sub(x, y)) % pq;
+            for _ in 0..BATCH_SIZE.min(r - k) {
+                y = f(y);
+                q = mulmod(q, x.abs_diff(y), pq);
             }
 
             g = gcd(q, pq);
-            k += m;
+            k += BATCH_SIZE;
         }
 
+        iterations += 2 * r;
         r *= 2;
     }
 
     if g == pq {
+        // The batch went past the factor (or the product became zero), so redo it step by step.
         loop {
-            ys = (modpow(ys, 2, pq) + c) % pq;
-            g = gcd(abs_sub(x, ys), pq);
+            ys = f(ys);
+            g = gcd(x.abs_diff(ys), pq);
             if g > 1 {
                 break;
             }
         }
     }
 
-    let (p, q) = (g as u64, (pq / g) as u64);
-    (p.min(q), p.max(q))
+    if g == pq {
+        None
+    } else {
+        Some(g)
+    }
 }
 
 #[cfg(test)]
@@ -134,4 +158,19 @@ mod tests {
         let pq = factorize(2804275833720261793);
         assert_eq!(pq, (1555252417, 1803100129));
     }
+
+    #[test]
+    fn test_factorization_small_factor() {
+        assert_eq!(factorize(2 * 1555252417), (2, 1555252417));
+        assert_eq!(factorize(1013 * 1555252417), (1013, 1555252417));
+    }
+
+    #[test]
+    fn test_brent_gives_up_after_max_iterations() {
+        let pq = 1470626929934143021;
+        assert_eq!(brent(pq, 3, 43, 0), None);
+
+        let p = brent(pq, 3, 43, MAX_ITERATIONS).unwrap();
+        assert!(p == 1206429347 || p == 1218991343);
+    }
 }
@@ -28,7 +28,7 @@ pub fn opt_from_hex(hex: &str) -> Option<Vec<u8>> {
         })
     }
 
-    if hex.len() % 2 != 0 {
+    if !hex.len().is_multiple_of(2) {
         return None;
     }
 
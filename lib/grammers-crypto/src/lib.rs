@@ -14,6 +14,7 @@ pub mod deque_buffer;
 pub mod factorize;
 pub mod hex;
 pub mod rsa;
+pub mod secret_chat;
 pub mod sha;
 pub mod two_factor_auth;
 
@@ -105,14 +106,27 @@ fn determine_padding_v2_length(len: usize) -> usize {
 }
 
 // Inner body of `encrypt_data_v2`, separated for testing purposes.
-fn do_encrypt_data_v2(buffer: &mut DequeBuffer<u8>, auth_key: &AuthKey, random_padding: &[u8; 32]) {
+fn do_encrypt_data_v2(
+    buffer: &mut DequeBuffer<u8>,
+    auth_key: &AuthKey,
+    random_padding: &[u8; 32],
+) -> u32 {
+    // Encryption is done by the client
+    encrypt_with_side(buffer, auth_key, random_padding, Side::Client)
+}
+
+// Shared with secret chats, where the side is the one of the user who sent the message.
+fn encrypt_with_side(
+    buffer: &mut DequeBuffer<u8>,
+    auth_key: &AuthKey,
+    random_padding: &[u8; 32],
+    side: Side,
+) -> u32 {
     // "Note that MTProto 2.0 requires from 12 to 1024 bytes of padding"
     // "[...] the resulting message length be divisible by 16 bytes"
     let padding_len = determine_padding_v2_length(buffer.len());
     buffer.extend(random_padding.iter().take(padding_len));
 
-    // Encryption is done by the client
-    let side = Side::Client;
     let x = side.x();
 
     // msg_key_large = SHA256 (substr (auth_key, 88+x, 32) + plaintext + random_padding);
@@ -132,13 +146,20 @@ fn do_encrypt_data_v2(buffer: &mut DequeBuffer<u8>, auth_key: &AuthKey, random_p
 
     buffer.extend_front(&msg_key);
     buffer.extend_front(&auth_key.key_id);
+
+    // The quick ack token is the first 32 bits of msg_key_large, with the highest bit set.
+    u32::from_le_bytes(msg_key_large[..4].try_into().unwrap()) | 0x8000_0000
 }
 
 /// This function implements the [MTProto 2.0 algorithm] for computing
 /// `aes_key` and `aes_iv` from `auth_key` and `msg_key` as specified
 ///
+/// Returns the token the server uses to acknowledge the receipt of the data when a
+/// [quick ack] is requested.
+///
 /// [MTProto 2.0 algorithm]: https://core.telegram.org/mtproto/description#defining-aes-key-and-initialization-vector
-pub fn encrypt_data_v2(buffer: &mut DequeBuffer<u8>, auth_key: &AuthKey) {
+/// [quick ack]: https://core.telegram.org/mtproto/mtproto-transports#quick-ack
+pub fn encrypt_data_v2(buffer: &mut DequeBuffer<u8>, auth_key: &AuthKey) -> u32 {
     let random_padding = {
         let mut rnd = [0; 32];
         getrandom(&mut rnd).expect("failed to generate a secure padding");
@@ -151,14 +172,18 @@ pub fn encrypt_data_v2(buffer: &mut DequeBuffer<u8>, auth_key: &AuthKey) {
 /// This method is the inverse of `encrypt_data_v2`.
 pub fn decrypt_data_v2(ciphertext: &[u8], auth_key: &AuthKey) -> Result<Vec<u8>, Error> {
     // Decryption is done from the server
-    let side = Side::Server;
+    // TODO Check salt, session_id and sequence_number
+    decrypt_with_side(ciphertext, auth_key, Side::Server)
+}
+
+// Shared with secret chats, where the side is the one of the user who sent the message.
+fn decrypt_with_side(ciphertext: &[u8], auth_key: &AuthKey, side: Side) -> Result<Vec<u8>, Error> {
     let x = side.x();
 
-    if ciphertext.len() < 24 || (ciphertext.len() - 24) % 16 != 0 {
+    if ciphertext.len() < 24 || !(ciphertext.len() - 24).is_multiple_of(16) {
         return Err(Error::InvalidBuffer);
     }
 
-    // TODO Check salt, session_id and sequence_number
     let key_id = &ciphertext[..8];
     if auth_key.key_id != *key_id {
         return Err(Error::AuthKeyMismatch);
@@ -170,7 +195,7 @@ pub fn decrypt_data_v2(ciphertext: &[u8], auth_key: &AuthKey) -> Result<Vec<u8>,
         buffer
     };
 
-    let (key, iv) = calc_key(auth_key, &msg_key, Side::Server);
+    let (key, iv) = calc_key(auth_key, &msg_key, side);
     let plaintext = decrypt_ige(&ciphertext[24..], &key, &iv);
 
     // https://core.telegram.org/mtproto/security_guidelines#mtproto-encrypted-messages
@@ -215,7 +240,7 @@ pub fn generate_key_data_from_nonce(
 
 /// Encrypt data using AES-IGE.
 pub fn encrypt_ige(plaintext: &[u8], key: &[u8; 32], iv: &[u8; 32]) -> Vec<u8> {
-    let mut padded = if plaintext.len() % 16 == 0 {
+    let mut padded = if plaintext.len().is_multiple_of(16) {
         plaintext.to_vec()
     } else {
         let pad_len = (16 - (plaintext.len() % 16)) % 16;
@@ -324,6 +349,24 @@ mod tests {
         assert_eq!(&buffer[..], expected);
     }
 
+    #[test]
+    fn encrypt_client_data_v2_quick_ack() {
+        let mut buffer = DequeBuffer::with_capacity(0, 0);
+        buffer.extend(b"Hello, world! This data should remain secure!");
+        let auth_key = get_test_auth_key();
+        let random_padding = [0; 32];
+
+        let mut plaintext = buffer[..].to_vec();
+        plaintext.extend(&random_padding[..determine_padding_v2_length(plaintext.len())]);
+        let msg_key_large = sha256!(&auth_key.data[88..120], &plaintext);
+        let expected = u32::from_le_bytes(msg_key_large[..4].try_into().unwrap()) | 0x8000_0000;
+
+        assert_eq!(
+            do_encrypt_data_v2(&mut buffer, &auth_key, &random_padding),
+            expected
+        );
+    }
+
     #[test]
     fn decrypt_server_data_v2() {
         let ciphertext = vec![
@@ -425,4 +468,22 @@ mod tests {
         ];
         assert_eq!(decrypt_ige(&ciphertext, &key, &iv), expected);
     }
+
+    #[test]
+    fn ctr_matches_known_vector() {
+        // F.5.5 CTR-AES256.Encrypt, from NIST SP 800-38A.
+        let key = hex::from_hex("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4");
+        let iv = hex::from_hex("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+        let mut buffer =
+            hex::from_hex("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51");
+        let expected =
+            hex::from_hex("601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5");
+
+        // Processing the data in uneven chunks must not change the result.
+        let mut ctr = aes::Ctr256::new(&key.try_into().unwrap(), &iv.try_into().unwrap());
+        let (first, second) = buffer.split_at_mut(7);
+        ctr.apply(first);
+        ctr.apply(second);
+        assert_eq!(buffer, expected);
+    }
 }
@@ -0,0 +1,205 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Key exchange and encryption used by [end-to-end encrypted] secret chats.
+//!
+//! [end-to-end encrypted]: https://core.telegram.org/api/end-to-end
+use crate::two_factor_auth::check_p_and_g;
+use crate::{decrypt_with_side, encrypt_with_side, AuthKey, DequeBuffer, Error, Side};
+use getrandom::getrandom;
+use num_bigint::BigUint;
+
+/// Generate the private key and the public `g_a` (or `g_b`) used in a Diffie-Hellman key
+/// exchange, given the parameters returned by `messages.getDhConfig`.
+///
+/// The private key is derived from both the `random` bytes provided by Telegram and local
+/// randomness. Returns `None` if `p` and `g` are not safe to use, or if the resulting public
+/// key is not.
+pub fn generate_key_pair(p: &[u8], g: i32, random: &[u8]) -> Option<([u8; 256], [u8; 256])> {
+    if !check_p_and_g(p, &g) {
+        return None;
+    }
+
+    let mut private = [0; 256];
+    getrandom(&mut private).expect("failed to generate a secure private key");
+    private.iter_mut().zip(random).for_each(|(a, b)| *a ^= b);
+
+    let p = BigUint::from_bytes_be(p);
+    let public = BigUint::from(g as u32).modpow(&BigUint::from_bytes_be(&private), &p);
+    if !check_public_key(&public, &p) {
+        return None;
+    }
+
+    Some((private, pad_to_256(&public.to_bytes_be())?))
+}
+
+/// Compute the shared key from the public key of the other party and the private key.
+///
+/// Returns `None` if `p` and `g` are not safe to use, or if the public key of the other party
+/// is not.
+pub fn compute_key(p: &[u8], g: i32, public: &[u8], private: &[u8]) -> Option<AuthKey> {
+    if !check_p_and_g(p, &g) {
+        return None;
+    }
+
+    let p = BigUint::from_bytes_be(p);
+    let public = BigUint::from_bytes_be(public);
+    if !check_public_key(&public, &p) {
+        return None;
+    }
+
+    let key = public.modpow(&BigUint::from_bytes_be(private), &p);
+    Some(AuthKey::from_bytes(pad_to_256(&key.to_bytes_be())?))
+}
+
+/// The fingerprint of a key, used to tell which key an encrypted message was encrypted with.
+pub fn key_fingerprint(key: &AuthKey) -> i64 {
+    i64::from_le_bytes(key.key_id)
+}
+
+/// Generate the `random_bytes` sent at the start of every message "to prevent content
+/// recognition in short encrypted messages".
+pub fn random_bytes() -> Vec<u8> {
+    let mut buffer = vec![0; 16];
+    getrandom(&mut buffer).expect("failed to generate secure random bytes");
+    buffer
+}
+
+/// Encrypt the serialized `DecryptedMessageLayer` with the key of a secret chat.
+///
+/// `originator` should be `true` if the message is sent by the user who created the chat.
+pub fn encrypt(plaintext: &[u8], key: &AuthKey, originator: bool) -> Vec<u8> {
+    let mut buffer = DequeBuffer::with_capacity(4 + plaintext.len() + 32, 24);
+    buffer.extend((plaintext.len() as u32).to_le_bytes());
+    buffer.extend(plaintext.iter().copied());
+
+    let mut random_padding = [0; 32];
+    getrandom(&mut random_padding).expect("failed to generate a secure padding");
+    encrypt_with_side(&mut buffer, key, &random_padding, side(originator));
+    buffer[..].to_vec()
+}
+
+/// Decrypt a message sent in a secret chat, returning the serialized `DecryptedMessageLayer`.
+///
+/// `originator` should be `true` if the message was sent by the user who created the chat.
+pub fn decrypt(ciphertext: &[u8], key: &AuthKey, originator: bool) -> Result<Vec<u8>, Error> {
+    let plaintext = decrypt_with_side(ciphertext, key, side(originator))?;
+
+    if plaintext.len() < 4 {
+        return Err(Error::InvalidBuffer);
+    }
+
+    // "[...] the length of the padding must be between 12 and 1024 bytes"
+    let len = u32::from_le_bytes(plaintext[..4].try_into().unwrap()) as usize;
+    let padding = plaintext
+        .len()
+        .checked_sub(4 + len)
+        .ok_or(Error::InvalidBuffer)?;
+    if !(12..=1024).contains(&padding) {
+        return Err(Error::InvalidBuffer);
+    }
+
+    Ok(plaintext[4..4 + len].to_vec())
+}
+
+// "x = 0 for messages from the originator of the secret chat, x = 8 for the other party"
+fn side(originator: bool) -> Side {
+    if originator {
+        Side::Client
+    } else {
+        Side::Server
+    }
+}
+
+// "both sides are to check that g, g_a and g_b are greater than one and smaller than p-1 [...]
+// we recommend checking that g_a and g_b are between 2^{2048-64} and p - 2^{2048-64} as well."
+//
+// `g` is known to be in range, because `check_p_and_g` only accepts values between 2 and 7 for a
+// 2048-bit `p`.
+fn check_public_key(public: &BigUint, p: &BigUint) -> bool {
+    let min = BigUint::from(1u8) << (2048 - 64);
+    p > &min && public >= &min && public <= &(p - &min)
+}
+
+fn pad_to_256(data: &[u8]) -> Option<[u8; 256]> {
+    let mut result = [0; 256];
+    result
+        .get_mut(256usize.checked_sub(data.len())?..)?
+        .copy_from_slice(data);
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex;
+
+    const P: &str =
+        "c71caeb9c6b1c9048e6c522f70f13f73980d40238e3e21c14934d037563d930f48198a0aa7c1405822\
+        9493d22530f4dbfa336f6e0ac925139543aed44cce7c3720fd51f69458705ac68cd4fe6b6b13abdc9746512969\
+        328454f18faf8c595f642477fe96bb2a941d5bcd1d4ac8cc49880708fa9b378e3c4f3a9060bee67cf9a4a4a695\
+        811051907e162753b56b0f6b410dba74d8a84b2a14b3144e0ef1284754fd17ed950d5965b4b9dd46582db1178d\
+        169c6bc465b0d6ff9ca3928fef5b9ae4e418fc15e83ebea0f87fa9ff5eed70050ded2849f47bf959d956850ce9\
+        29851f0d8115f635b105ee2e4e15d04b2454bf6f4fadf034b10403119cd8e3b92fcc5b";
+
+    #[test]
+    fn both_parties_compute_the_same_key() {
+        let p = hex::from_hex(P);
+        let (a, g_a) = generate_key_pair(&p, 3, &[1; 256]).unwrap();
+        let (b, g_b) = generate_key_pair(&p, 3, &[2; 256]).unwrap();
+
+        let key_a = compute_key(&p, 3, &g_b, &a).unwrap();
+        let key_b = compute_key(&p, 3, &g_a, &b).unwrap();
+        assert_eq!(key_a.to_bytes(), key_b.to_bytes());
+        assert_eq!(key_fingerprint(&key_a), key_fingerprint(&key_b));
+    }
+
+    #[test]
+    fn unsafe_public_keys_are_rejected() {
+        let p = hex::from_hex(P);
+        assert!(compute_key(&p, 3, &[1], &[2; 256]).is_none());
+        assert!(compute_key(&p, 3, &p, &[2; 256]).is_none());
+    }
+
+    #[test]
+    fn unsafe_parameters_are_rejected() {
+        let p = hex::from_hex(P);
+        let (a, g_a) = generate_key_pair(&p, 3, &[1; 256]).unwrap();
+        let mut not_prime = p.clone();
+        not_prime[255] ^= 2;
+
+        for g in [-1, 0, 1, 8] {
+            assert!(generate_key_pair(&p, g, &[1; 256]).is_none());
+            assert!(compute_key(&p, g, &g_a, &a).is_none());
+        }
+        assert!(generate_key_pair(&not_prime, 3, &[1; 256]).is_none());
+        assert!(generate_key_pair(&p[1..], 3, &[1; 256]).is_none());
+        assert!(compute_key(&p[1..], 3, &g_a, &a).is_none());
+    }
+
+    #[test]
+    fn oversized_values_are_not_padded() {
+        assert!(pad_to_256(&[1; 257]).is_none());
+        assert_eq!(pad_to_256(&[1; 256]), Some([1; 256]));
+        assert_eq!(pad_to_256(&[]), Some([0; 256]));
+    }
+
+    #[test]
+    fn messages_are_decrypted_with_the_sender_side() {
+        let key = AuthKey::from_bytes(std::array::from_fn(|i| i as u8));
+        let plaintext = b"secret layer".to_vec();
+
+        let ciphertext = encrypt(&plaintext, &key, true);
+        assert_eq!(ciphertext[..8], key.key_id);
+        assert_eq!(decrypt(&ciphertext, &key, true), Ok(plaintext));
+        assert_eq!(
+            decrypt(&ciphertext, &key, false),
+            Err(Error::MessageKeyMismatch)
+        );
+    }
+}
@@ -31,3 +31,26 @@ macro_rules! sha256 (
         sha
     })
 );
+
+/// Calculate the HMAC-SHA256 of the concatenation of the given data with the given key.
+pub fn hmac_sha256(key: &[u8], data: &[&[u8]]) -> [u8; 32] {
+    use hmac::{Hmac, Mac};
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(key).expect("hmac takes keys of any size");
+    data.iter().for_each(|data| mac.update(data));
+    mac.finalize().into_bytes().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex;
+
+    #[test]
+    fn check_hmac_sha256() {
+        // Test case 2 from RFC 4231.
+        assert_eq!(
+            hmac_sha256(b"Jefe", &[b"what do ya want ", b"for nothing?"]).to_vec(),
+            hex::from_hex("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+        );
+    }
+}
@@ -5,6 +5,22 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
+
+//! The [Secure Remote Password] protocol used by two-factor authentication.
+//!
+//! It has two halves:
+//!
+//! * [`calculate_2fa`] proves knowledge of the current password, without sending it, whenever
+//!   Telegram requires it (for example, to sign in or to change the password).
+//! * [`new_salt1`] and [`compute_password_hash`] register a new password, producing the
+//!   verifier Telegram stores instead of the password itself.
+//!
+//! Both take the parameters from the `passwordKdfAlgoSHA256SHA256PBKDF2HMACSHA512iter100000SHA256ModPow`
+//! algorithm returned by `account.getPassword`, which should first be validated with
+//! [`check_p_and_g`].
+//!
+//! [Secure Remote Password]: https://core.telegram.org/api/srp
+use getrandom::getrandom;
 use glass_pumpkin::safe_prime;
 use hmac::Hmac;
 use num_bigint::{BigInt, BigUint, Sign};
@@ -86,7 +102,45 @@ pub fn calculate_2fa(
     (m1, g_a)
 }
 
+/// Generate the `salt1` to use for a new password, from the `salt1` of the `new_algo` returned
+/// by `account.getPassword`.
+///
+/// As required by Telegram, 32 random bytes are appended to it.
+pub fn new_salt1(salt1: &[u8]) -> Vec<u8> {
+    let mut random = [0; 32];
+    getrandom(&mut random).expect("failed to generate a secure salt");
+
+    let mut salt = salt1.to_vec();
+    salt.extend(random);
+    salt
+}
+
+/// Compute the verifier of a new password, sent as `new_password_hash` when changing it.
+///
+/// `salt1` should be the one returned by [`new_salt1`], and the rest of the parameters those of
+/// the `new_algo` returned by `account.getPassword`.
+pub fn compute_password_hash(
+    salt1: &[u8],
+    salt2: &[u8],
+    p: &[u8],
+    g: &i32,
+    password: impl AsRef<[u8]>,
+) -> [u8; 256] {
+    let big_p = BigInt::from_bytes_be(Sign::Plus, p);
+    let big_g = BigInt::from(*g as u32);
+
+    // x := PH2(password, salt1, salt2)
+    let x = ph2(&password, salt1, salt2);
+    let x = BigInt::from_bytes_be(Sign::Plus, &x);
+
+    // v := pow(g, x) mod p
+    let big_v = big_g.modpow(&x, &big_p);
+    pad_to_256(&big_v.to_bytes_be().1)
+}
+
 /// Validation for parameters required for two-factor authentication
+///
+/// Values of `g` other than 2 through 7 are never considered valid.
 pub fn check_p_and_g(p: &[u8], g: &i32) -> bool {
     if !check_p_len(p) {
         return false;
@@ -98,11 +152,8 @@ pub fn check_p_and_g(p: &[u8], g: &i32) -> bool {
 fn check_p_prime_and_subgroup(p: &[u8], g: &i32) -> bool {
     let p = &BigUint::from_bytes_be(p);
 
-    if !safe_prime::check(p) {
-        return false;
-    }
-
-    match g {
+    // The subgroup is checked first, since it is much cheaper than checking for a safe prime.
+    let in_subgroup = match g {
         2 => p % 8u8 == BigUint::from(7u8),
         3 => p % 3u8 == BigUint::from(2u8),
         4 => true,
@@ -120,8 +171,10 @@ fn check_p_prime_and_subgroup(p: &[u8], g: &i32) -> bool {
                 || mod_value == BigUint::from(5u8)
                 || mod_value == BigUint::from(6u8)
         }
-        _ => panic!("Unexpected g parameter"),
-    }
+        _ => false,
+    };
+
+    in_subgroup && safe_prime::check(p)
 }
 
 fn check_p_len(p: &[u8]) -> bool {
@@ -302,6 +355,44 @@ mod tests {
         assert_eq!(expected_g_a, g_a);
     }
 
+    #[test]
+    fn registered_password_verifies_proof() {
+        let p = pad_to_256(&[0xff; 64]);
+        let g = 3;
+        let salt1 = new_salt1(&[1, 2, 3]);
+        let salt2 = vec![4, 5, 6];
+        let password = "hunter2";
+
+        assert_eq!(salt1.len(), 3 + 32);
+        assert_eq!(salt1[..3], [1, 2, 3]);
+
+        // Act as the server, which only knows the verifier `v`.
+        let v = compute_password_hash(&salt1, &salt2, &p, &g, password);
+        let big_p = BigInt::from_bytes_be(Sign::Plus, &p);
+        let big_v = BigInt::from_bytes_be(Sign::Plus, &v);
+        let b = BigInt::from(123456789u32);
+        let k = BigInt::from_bytes_be(Sign::Plus, &h!(&p, &pad_to_256(&[g as u8])));
+
+        // g_b := (k * v + pow(g, b)) mod p
+        let g_b = (k * &big_v + BigInt::from(g as u32).modpow(&b, &big_p)) % &big_p;
+        let g_b = pad_to_256(&g_b.to_bytes_be().1);
+
+        let (m1, g_a) = calculate_2fa(&salt1, &salt2, &p, &g, g_b.to_vec(), vec![7; 256], password);
+
+        // s_b := pow(g_a * pow(v, u), b) mod p
+        let u = BigInt::from_bytes_be(Sign::Plus, &h!(&g_a, &g_b));
+        let g_a_num = BigInt::from_bytes_be(Sign::Plus, &g_a);
+        let s_b = (g_a_num * big_v.modpow(&u, &big_p)).modpow(&b, &big_p);
+        let k_b = h!(&pad_to_256(&s_b.to_bytes_be().1));
+
+        let p_xor_g = xor(&h!(&p), &h!(&pad_to_256(&[g as u8])));
+        let expected_m1 = h!(&p_xor_g, &h!(&salt1), &h!(&salt2), &g_a, &g_b, &k_b);
+        assert_eq!(m1, expected_m1);
+
+        let wrong = compute_password_hash(&salt1, &salt2, &p, &g, "hunter3");
+        assert_ne!(v, wrong);
+    }
+
     #[test]
     fn test_check_p_and_g() {
         // Not prime
@@ -0,0 +1,57 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use bencher::{benchmark_group, benchmark_main, black_box, Bencher};
+use grammers_crypto::DequeBuffer;
+use grammers_mtproto::mtp::{self, Mtp};
+use grammers_mtproto::transport::{self, Transport};
+
+// Roughly the size of a small request, such as resolving a username.
+const REQUEST: [u8; 64] = [1; 64];
+
+/// Serialize `$n` requests, either all in a single container (one packet to write), or each
+/// in its own packet.
+macro_rules! define_benches {
+    ($(fn $func:ident($batched:expr, $n:expr);)+) => {
+        $(
+            fn $func(bench: &mut Bencher) {
+                let mut mtp = mtp::Encrypted::build().finish([2; 256]);
+                let mut transport = transport::Full::new();
+                let mut buffer = DequeBuffer::with_capacity(1024 * 1024, 32);
+
+                bench.iter(|| {
+                    let mut packets = 0;
+                    for i in 0..$n {
+                        black_box(mtp.push(&mut buffer, &REQUEST));
+                        if !$batched || i + 1 == $n {
+                            mtp.finalize(&mut buffer);
+                            transport.pack(&mut buffer);
+                            packets += 1;
+                            buffer.clear();
+                        }
+                    }
+                    black_box(packets)
+                });
+                bench.bytes = (REQUEST.len() * $n) as u64;
+            }
+        )+
+    };
+}
+
+define_benches!(
+    fn separate_r001(false, 1);
+    fn separate_r020(false, 20);
+    fn separate_r100(false, 100);
+
+    fn batched_r001(true, 1);
+    fn batched_r020(true, 20);
+    fn batched_r100(true, 100);
+);
+
+benchmark_group!(separate, separate_r001, separate_r020, separate_r100);
+benchmark_group!(batched, batched_r001, batched_r020, batched_r100);
+benchmark_main!(separate, batched);
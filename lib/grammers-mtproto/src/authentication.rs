@@ -32,6 +32,7 @@
 //!     Ok(())
 //! }
 //! ```
+use crate::utils::{SystemTime, UNIX_EPOCH};
 use getrandom::getrandom;
 use grammers_crypto::hex;
 use grammers_crypto::{factorize::factorize, rsa, AuthKey};
@@ -39,7 +40,6 @@ use grammers_tl_types::{self as tl, Cursor, Deserializable, RemoteCall, Serializ
 use num_bigint::{BigUint, ToBigUint};
 use sha1::{Digest, Sha1};
 use std::fmt;
-use std::time::{SystemTime, UNIX_EPOCH};
 
 // NOTE! Turning this on will leak the key generation process to stdout!
 // Should only be used for debugging purposes and generating test cases.
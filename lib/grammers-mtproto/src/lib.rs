@@ -24,6 +24,10 @@ mod utils;
 /// The default compression threshold to be used.
 pub const DEFAULT_COMPRESSION_THRESHOLD: Option<usize> = Some(512);
 
+/// The default time to wait for more messages to acknowledge before sending the acknowledgements
+/// on their own.
+pub const DEFAULT_ACK_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
 /// A Message Identifier.
 ///
 /// When requests are enqueued, a new associated message identifier is
@@ -33,3 +37,10 @@ pub const DEFAULT_COMPRESSION_THRESHOLD: Option<usize> = Some(512);
 /// you will know the response corresponds to it.
 #[derive(Copy, Clone, Debug, Hash, PartialEq, PartialOrd, Eq, Ord)]
 pub struct MsgId(i64);
+
+impl MsgId {
+    /// The raw value of the identifier, as used in the requests that refer to other messages.
+    pub fn value(self) -> i64 {
+        self.0
+    }
+}
@@ -9,13 +9,14 @@ use super::{
     Deserialization, DeserializationFailure, DeserializeError, Mtp, RpcResult, RpcResultError,
 };
 use crate::utils::StackBuffer;
+use crate::utils::{Instant, SystemTime, UNIX_EPOCH};
 use crate::{manual_tl, MsgId};
 use getrandom::getrandom;
 use grammers_crypto::{decrypt_data_v2, encrypt_data_v2, AuthKey, DequeBuffer};
 use grammers_tl_types::{self as tl, Cursor, Deserializable, Identifiable, Serializable};
 use log::info;
 use std::mem;
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::time::Duration;
 
 /// How many future salts to fetch or have stored at a given time.
 ///
@@ -23,6 +24,19 @@ use std::time::{Instant, SystemTime, UNIX_EPOCH};
 /// code expects to fetch more salts when going from two to one.
 const NUM_FUTURE_SALTS: i32 = 64;
 
+/// How many messages to acknowledge at most in a single `msgs_ack`.
+///
+/// The server accepts up to 8192, but sending them as soon as this many are pending prevents it
+/// from resending messages it thinks were lost.
+const MAX_PENDING_ACKS: usize = 1024;
+
+/// How many seconds may the time of the messages sent by the server differ from our own before
+/// our time offset is corrected.
+///
+/// The server rejects message identifiers more than 30 seconds in the future, so correcting any
+/// larger difference prevents it, while leaving some margin for the time messages take to arrive.
+const MAX_TIME_SKEW: i32 = 30;
+
 /// When switching to a different salt, how many seconds must have passed since its `valid_since`.
 ///
 /// Used to prevent small fluctuations in the system clock.
@@ -49,6 +63,7 @@ pub struct Builder {
     time_offset: i32,
     first_salt: i64,
     compression_threshold: Option<usize>,
+    ack_delay: Duration,
 }
 
 /// An implementation of the [Mobile Transport Protocol] for ciphertext
@@ -92,6 +107,13 @@ pub struct Encrypted {
     /// [Content-related Message]: https://core.telegram.org/mtproto/description#content-related-message
     pending_ack: Vec<i64>,
 
+    /// How long to wait for more messages to acknowledge before sending the pending ones on
+    /// their own, when there are no requests for them to go along with.
+    ack_delay: Duration,
+
+    /// When the pending acknowledgements must be sent, if there are any.
+    ack_deadline: Option<Instant>,
+
     /// If present, the threshold in bytes at which a message will be
     /// considered large enough to attempt compressing it. Otherwise,
     /// outgoing messages will never be compressed.
@@ -102,6 +124,9 @@ pub struct Encrypted {
 
     /// How many messages are there in the buffer.
     msg_count: usize,
+
+    /// The quick ack token of the last finalized payload.
+    quick_ack_token: Option<u32>,
 }
 
 impl Builder {
@@ -122,6 +147,13 @@ impl Builder {
         self
     }
 
+    /// Configures how long acknowledgements may be delayed waiting for more of them, or for a
+    /// request to go along with.
+    pub fn ack_delay(mut self, delay: Duration) -> Self {
+        self.ack_delay = delay;
+        self
+    }
+
     /// Finishes the builder and returns the `MTProto` instance with all
     /// the configuration changes applied.
     pub fn finish(self, auth_key: [u8; 256]) -> Encrypted {
@@ -143,9 +175,12 @@ impl Builder {
             sequence: 0,
             last_msg_id: 0,
             pending_ack: vec![],
+            ack_delay: self.ack_delay,
+            ack_deadline: None,
             compression_threshold: self.compression_threshold,
             deserialization: Vec::new(),
             msg_count: 0,
+            quick_ack_token: None,
         }
     }
 }
@@ -157,6 +192,7 @@ impl Encrypted {
             time_offset: 0,
             compression_threshold: crate::DEFAULT_COMPRESSION_THRESHOLD,
             first_salt: 0,
+            ack_delay: crate::DEFAULT_ACK_DELAY,
         }
     }
 
@@ -165,6 +201,28 @@ impl Encrypted {
         self.auth_key.to_bytes()
     }
 
+    /// Change the minimum size a request must have to be compressed, as with
+    /// [`Builder::compression_threshold`].
+    ///
+    /// Only the requests pushed after this call are affected.
+    pub fn set_compression_threshold(&mut self, threshold: Option<usize>) {
+        self.compression_threshold = threshold;
+    }
+
+    /// The difference, in seconds, between the server's time and our own.
+    ///
+    /// Message identifiers are generated by adding this to the system time, and it's corrected
+    /// as soon as the server's messages show the system clock is off.
+    pub fn time_offset(&self) -> i32 {
+        self.time_offset
+    }
+
+    /// Change the time offset to the server, as with [`Builder::time_offset`].
+    pub fn set_time_offset(&mut self, offset: i32) {
+        self.time_offset = offset;
+        self.last_msg_id = 0;
+    }
+
     /// Correct our time offset based on a known valid message ID.
     fn correct_time_offset(&mut self, msg_id: i64) {
         let now = SystemTime::now()
@@ -173,7 +231,30 @@ impl Encrypted {
             .as_secs() as i32;
 
         let correct = (msg_id >> 32) as i32;
-        self.time_offset = correct - now;
+        let offset = correct - now;
+        if offset != self.time_offset {
+            info!(
+                "correcting time offset from {}s to {}s",
+                self.time_offset, offset
+            );
+            // Otherwise, the message IDs would keep increasing from the last one, which was
+            // wrong, if the offset went back.
+            self.set_time_offset(offset);
+        }
+    }
+
+    /// Correct our time offset if the time of a message sent by the server is too far from the
+    /// time we would use for our own messages.
+    fn check_time_offset(&mut self, msg_id: i64) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time is before epoch")
+            .as_secs() as i32;
+
+        let skew = (msg_id >> 32) as i32 - (now + self.time_offset);
+        if skew.abs() > MAX_TIME_SKEW {
+            self.correct_time_offset(msg_id);
+        }
     }
 
     /// Generates a new unique message ID based on the current
@@ -227,10 +308,12 @@ impl Encrypted {
         self.salts.last().map(|s| s.salt).unwrap_or(0)
     }
 
-    fn try_request_salts(&mut self, buffer: &mut DequeBuffer<u8>) {
+    /// Request more salts if only one remains, returning `true` if the request was serialized.
+    fn try_request_salts(&mut self, buffer: &mut DequeBuffer<u8>) -> bool {
         if self.salts.len() == 1
             && self.salt_request_msg_id.is_none()
             && self.get_current_salt() != 0
+            && self.msg_count == 0
         {
             // If salts are requested in a container leading to bad_msg,
             // the bad_msg_id will refer to the container, not the salts request.
@@ -238,16 +321,89 @@ impl Encrypted {
             // We don't keep track of containers and content-related messages they contain for simplicity.
             // This would break, because we couldn't identify the response.
             //
-            // So salts are only requested once we have a valid salt to reduce the chances of this happening.
-            if self.salts.len() == 1 {
-                info!("only one future salt remaining; asking for more salts");
-            }
+            // So salts are only requested once we have a valid salt to reduce the chances of this happening,
+            // and the request is sent on its own.
+            info!("only one future salt remaining; asking for more salts");
             let body = tl::functions::GetFutureSalts {
                 num: NUM_FUTURE_SALTS,
             }
             .to_bytes();
-            self.serialize_msg(buffer, &body, true);
+            let msg_id = self.serialize_msg(buffer, &body, true);
+            self.salt_request_msg_id = Some(msg_id.0);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The server's current time, in seconds since epoch.
+    fn server_now(&self) -> i32 {
+        match self.start_salt_time {
+            Some((start_secs, start_instant)) => {
+                start_secs + start_instant.elapsed().as_secs() as i32
+            }
+            None => {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .expect("system time is before epoch")
+                    .as_secs() as i32
+                    + self.time_offset
+            }
+        }
+    }
+
+    /// Change how long acknowledgements may be delayed, as with [`Builder::ack_delay`].
+    pub fn set_ack_delay(&mut self, delay: Duration) {
+        self.ack_delay = delay;
+    }
+
+    /// Serialize a `msgs_ack` with the pending acknowledgements, up to `MAX_PENDING_ACKS`.
+    fn write_acks(&mut self, buffer: &mut DequeBuffer<u8>) {
+        let count = self.pending_ack.len().min(MAX_PENDING_ACKS);
+        // TODO avoid to_bytes here, serialize it in-place
+        let body = tl::enums::MsgsAck::Ack(tl::types::MsgsAck {
+            msg_ids: self.pending_ack.drain(..count).collect(),
+        })
+        .to_bytes();
+        self.serialize_msg(buffer, &body, false);
+
+        self.ack_deadline = if self.pending_ack.is_empty() {
+            None
+        } else {
+            Some(Instant::now())
+        };
+    }
+
+    /// The salts that may be used when encrypting payload, along with the time window in which
+    /// each one is valid.
+    ///
+    /// These can be stored and later given to [`Encrypted::set_salts`], so that a new instance
+    /// does not need to start with a salt that's no longer valid.
+    pub fn salts(&self) -> Vec<tl::types::FutureSalt> {
+        self.salts.clone()
+    }
+
+    /// Use the given salts, previously obtained from [`Encrypted::salts`], when encrypting
+    /// payload.
+    ///
+    /// Salts that are no longer valid are discarded. If none are left, the current salt is kept.
+    pub fn set_salts(&mut self, mut salts: Vec<tl::types::FutureSalt>) {
+        let now = self.server_now();
+        salts.retain(|salt| salt.valid_until > now);
+        if salts.is_empty() {
+            return;
+        }
+
+        self.start_salt_time = Some((now, Instant::now()));
+        self.salts = salts;
+        self.salts.sort_by_key(|salt| -salt.valid_since);
+        // Drop the salts that were replaced by one that can be used already.
+        while self.salts.len() > 1
+            && now >= self.salts[self.salts.len() - 2].valid_since + SALT_USE_DELAY
+        {
+            self.salts.pop();
         }
+        self.salt_request_msg_id = None;
     }
 
     /// `finalize`, but without encryption.
@@ -290,6 +446,12 @@ impl Encrypted {
     fn process_message(&mut self, message: manual_tl::Message) -> Result<(), DeserializeError> {
         if message.requires_ack() {
             self.pending_ack.push(message.msg_id);
+            self.ack_deadline = Some(if self.pending_ack.len() >= MAX_PENDING_ACKS {
+                Instant::now()
+            } else {
+                self.ack_deadline
+                    .unwrap_or_else(|| Instant::now() + self.ack_delay)
+            });
         }
 
         // Handle all the possible Service Messages:
@@ -464,17 +626,27 @@ impl Encrypted {
             },
 
             // Cancellation of an RPC Query
-            tl::types::RpcAnswerUnknown::CONSTRUCTOR_ID => {
-                // The `msg_id` corresponds to the `rpc_drop_answer` request.
-            }
-            tl::types::RpcAnswerDroppedRunning::CONSTRUCTOR_ID => {
-                // We will receive two `rpc_result`, one with the `msg_id` of
-                // `rpc_drop_answer` request and other for the original RPC.
-            }
-            tl::types::RpcAnswerDropped::CONSTRUCTOR_ID => {
-                // "the RPC response was removed from the server's outgoing
-                // queue, and its msg_id, seq_no, and length in bytes are
-                // transmitted to the client."
+            //
+            // The `msg_id` corresponds to the `rpc_drop_answer` request, which
+            // is answered like any other, so that it stops waiting.
+            //
+            // With `rpc_answer_unknown`, the answer may have already been sent.
+            // With `rpc_answer_dropped_running`, we will receive two `rpc_result`,
+            // one with the `msg_id` of `rpc_drop_answer` request and other for the
+            // original RPC. With `rpc_answer_dropped`, "the RPC response was removed
+            // from the server's outgoing queue, and its msg_id, seq_no, and length
+            // in bytes are transmitted to the client."
+            //
+            // Either way, a late answer to the original RPC is for a request that
+            // is no longer being waited on.
+            tl::types::RpcAnswerUnknown::CONSTRUCTOR_ID
+            | tl::types::RpcAnswerDroppedRunning::CONSTRUCTOR_ID
+            | tl::types::RpcAnswerDropped::CONSTRUCTOR_ID => {
+                self.deserialization
+                    .push(Deserialization::RpcResult(RpcResult {
+                        msg_id,
+                        body: result,
+                    }))
             }
 
             // Response to an RPC query
@@ -523,7 +695,7 @@ impl Encrypted {
     fn store_own_updates(&mut self, body: &[u8]) {
         match u32::from_bytes(body) {
             Ok(body_id) => {
-                if UPDATE_IDS.iter().any(|&id| body_id == id) {
+                if UPDATE_IDS.contains(&body_id) {
                     // TODO somehow signal that this updates is our own, to avoid getting into nasty loops
                     self.deserialization
                         .push(Deserialization::Update(body.to_vec()));
@@ -1198,32 +1370,24 @@ impl Mtp for Encrypted {
 
         // Check to see if the next salt can be used already. If it can, drop the current one and,
         // if the next salt is the last one, fetch more.
-        if let Some((start_secs, start_instant)) = self.start_salt_time {
-            if self.salts.len() > 1 {
-                let salt = &self.salts[self.salts.len() - 2];
-                let now = start_secs + start_instant.elapsed().as_secs() as i32;
-                if now >= salt.valid_since + SALT_USE_DELAY {
-                    self.salts.pop();
-                }
+        if self.start_salt_time.is_some() && self.salts.len() > 1 {
+            let salt = &self.salts[self.salts.len() - 2];
+            if self.server_now() >= salt.valid_since + SALT_USE_DELAY {
+                self.salts.pop();
             }
         }
 
-        self.try_request_salts(buffer);
-        if self.salt_request_msg_id.is_some() {
-            // Don't add anything else to the container while we still need new salts.
+        if self.try_request_salts(buffer) {
+            // Don't add anything else to the container, so that the salts request goes alone.
+            // Requests made while waiting for the response still use the current salt.
             return None;
         }
 
         // If we need to acknowledge messages, this notification goes in with the rest of requests
         // so that we can also include it. It has priority over user requests because these should
         // be sent out as soon as possible.
-        if !self.pending_ack.is_empty() {
-            // TODO avoid to_bytes here, serialize it in-place
-            let body = tl::enums::MsgsAck::Ack(tl::types::MsgsAck {
-                msg_ids: mem::take(&mut self.pending_ack),
-            })
-            .to_bytes();
-            self.serialize_msg(buffer, &body, false);
+        if !self.pending_ack.is_empty() && self.msg_count == 0 {
+            self.write_acks(buffer);
         }
 
         // Serialize `MAXIMUM_LENGTH` requests at most.
@@ -1239,7 +1403,7 @@ impl Mtp for Encrypted {
         );
 
         // Serialized requests will always be correctly padded.
-        assert!(request.len() % 4 == 0);
+        assert!(request.len().is_multiple_of(4));
 
         // Payload provided by the user is always considered to be
         // content-related, which means we can apply compression.
@@ -1265,11 +1429,18 @@ impl Mtp for Encrypted {
     }
 
     fn finalize(&mut self, buffer: &mut DequeBuffer<u8>) -> Option<MsgId> {
+        if self.msg_count == 0
+            && self
+                .ack_deadline
+                .is_some_and(|deadline| deadline <= Instant::now())
+        {
+            self.write_acks(buffer);
+        }
         self.finalize_plain(buffer);
         if buffer.is_empty() {
             None
         } else {
-            encrypt_data_v2(buffer, &self.auth_key);
+            self.quick_ack_token = Some(encrypt_data_v2(buffer, &self.auth_key));
             Some(MsgId(self.last_msg_id))
         }
     }
@@ -1278,7 +1449,9 @@ impl Mtp for Encrypted {
     fn deserialize(&mut self, payload: &[u8]) -> Result<Vec<Deserialization>, DeserializeError> {
         crate::utils::check_message_buffer(payload)?;
 
-        let plaintext = decrypt_data_v2(payload, &self.auth_key)?;
+        // Padded transports may leave up to 15 bytes of random padding after the ciphertext.
+        let len = payload.len() - payload.len().saturating_sub(24) % 16;
+        let plaintext = decrypt_data_v2(&payload[..len], &self.auth_key)?;
         let mut buffer = Cursor::from_slice(&plaintext[..]);
 
         let _salt = i64::deserialize(&mut buffer)?;
@@ -1287,7 +1460,9 @@ impl Mtp for Encrypted {
             panic!("wrong session id");
         }
 
-        self.process_message(manual_tl::Message::deserialize(&mut buffer)?)?;
+        let message = manual_tl::Message::deserialize(&mut buffer)?;
+        self.check_time_offset(message.msg_id);
+        self.process_message(message)?;
 
         // For simplicity, and to avoid passing too much stuff around (RPC results, updates),
         // the processing result is stored in self. After processing is done, that temporary
@@ -1305,8 +1480,27 @@ impl Mtp for Encrypted {
         self.sequence = 0;
         self.last_msg_id = 0;
         self.pending_ack.clear();
+        self.ack_deadline = None;
         self.msg_count = 0;
     }
+
+    fn ack_deadline(&self) -> Option<Instant> {
+        self.ack_deadline
+    }
+
+    fn flush_acks(&mut self) {
+        if !self.pending_ack.is_empty() {
+            self.ack_deadline = Some(Instant::now());
+        }
+    }
+
+    fn time_offset(&self) -> i32 {
+        self.time_offset
+    }
+
+    fn quick_ack_token(&self) -> Option<u32> {
+        self.quick_ack_token
+    }
 }
 
 #[cfg(test)]
@@ -1497,4 +1691,386 @@ mod tests {
             assert!(buffer.as_ref().windows(4).any(|w| w == GZIP_PACKED_HEADER));
         }
     }
+
+    #[test]
+    fn ensure_compression_only_when_smaller() {
+        // Random-looking data does not compress, so it should be sent as-is
+        let mut buffer = DequeBuffer::with_capacity(0, 0);
+        let mut mtproto = Encrypted::build()
+            .compression_threshold(Some(0))
+            .finish(auth_key());
+        let request = (0..1024u32)
+            .flat_map(|i| i.wrapping_mul(2_654_435_761).to_le_bytes())
+            .collect::<Vec<_>>();
+        mtproto.push(&mut buffer, &request);
+        mtproto.finalize_plain(&mut buffer);
+        assert!(!buffer.as_ref().windows(4).any(|w| w == GZIP_PACKED_HEADER));
+    }
+
+    #[test]
+    fn ensure_set_compression_threshold_is_honored() {
+        let mut buffer = DequeBuffer::with_capacity(0, 0);
+        let mut mtproto = Encrypted::build().finish(auth_key());
+        mtproto.set_compression_threshold(None);
+        mtproto.push(&mut buffer, &vec![0; 512 * 1024]);
+        mtproto.finalize_plain(&mut buffer);
+        assert!(!buffer.as_ref().windows(4).any(|w| w == GZIP_PACKED_HEADER));
+    }
+
+    fn rpc_result(req_msg_id: i64, result: &[u8]) -> Vec<u8> {
+        let mut body = manual_tl::RpcResult::CONSTRUCTOR_ID.to_bytes();
+        req_msg_id.serialize(&mut body);
+        body.extend(result);
+        body
+    }
+
+    fn ensure_single_rpc_result(
+        deserialization: Vec<Deserialization>,
+        req_msg_id: i64,
+        result: &[u8],
+    ) {
+        assert_eq!(deserialization.len(), 1);
+        match &deserialization[0] {
+            Deserialization::RpcResult(rpc_result) => {
+                assert_eq!(rpc_result.msg_id, MsgId(req_msg_id));
+                assert_eq!(rpc_result.body, result);
+            }
+            _ => panic!("expected rpc result"),
+        }
+    }
+
+    #[test]
+    fn ensure_gzip_packed_rpc_result_is_unpacked() {
+        let mut mtproto = Encrypted::build().finish(auth_key());
+        let result = tl::types::Pong {
+            msg_id: 1,
+            ping_id: 2,
+        }
+        .to_bytes();
+
+        mtproto
+            .process_message(manual_tl::Message {
+                msg_id: 4,
+                seq_no: 1,
+                body: rpc_result(123, &manual_tl::GzipPacked::new(&result).to_bytes()),
+            })
+            .unwrap();
+
+        ensure_single_rpc_result(mem::take(&mut mtproto.deserialization), 123, &result);
+    }
+
+    #[test]
+    fn ensure_gzip_packed_message_in_container_is_unpacked() {
+        let mut mtproto = Encrypted::build().finish(auth_key());
+        let result = tl::types::Pong {
+            msg_id: 1,
+            ping_id: 2,
+        }
+        .to_bytes();
+
+        let inner = manual_tl::Message {
+            msg_id: 8,
+            seq_no: 1,
+            body: manual_tl::GzipPacked::new(&rpc_result(123, &result)).to_bytes(),
+        };
+        let mut container = MSG_CONTAINER_HEADER.to_vec();
+        1i32.serialize(&mut container);
+        inner.serialize(&mut container);
+
+        mtproto
+            .process_message(manual_tl::Message {
+                msg_id: 12,
+                seq_no: 0,
+                body: container,
+            })
+            .unwrap();
+
+        ensure_single_rpc_result(mem::take(&mut mtproto.deserialization), 123, &result);
+    }
+
+    fn future_salt(valid_since: i32, valid_until: i32, salt: i64) -> tl::types::FutureSalt {
+        tl::types::FutureSalt {
+            valid_since,
+            valid_until,
+            salt,
+        }
+    }
+
+    #[test]
+    fn ensure_salts_are_requested_alone() {
+        let get_future_salts = tl::functions::GetFutureSalts::CONSTRUCTOR_ID.to_le_bytes();
+        let mut mtproto = Encrypted::build().first_salt(1).finish(auth_key());
+
+        let mut buffer = DequeBuffer::with_capacity(0, 0);
+        assert_eq!(mtproto.push(&mut buffer, REQUEST), None);
+        mtproto.finalize_plain(&mut buffer);
+        assert!(buffer.as_ref().windows(4).any(|w| w == get_future_salts));
+        let salt_request_msg_id = mtproto.salt_request_msg_id.unwrap();
+
+        // Requests made while waiting for the salts are not held back.
+        let mut buffer = DequeBuffer::with_capacity(0, 0);
+        assert!(mtproto.push(&mut buffer, REQUEST).is_some());
+        mtproto.finalize_plain(&mut buffer);
+        assert!(!buffer.as_ref().windows(4).any(|w| w == get_future_salts));
+
+        // The response rotates the salts, and is not propagated.
+        let now = mtproto.server_now();
+        let body = tl::enums::FutureSalts::Salts(tl::types::FutureSalts {
+            req_msg_id: salt_request_msg_id,
+            now,
+            salts: tl::RawVec(vec![
+                future_salt(now - 3600, now + 60, 2),
+                future_salt(now + 30, now + 3600, 3),
+            ]),
+        })
+        .to_bytes();
+        mtproto
+            .process_message(manual_tl::Message {
+                msg_id: 4,
+                seq_no: 1,
+                body,
+            })
+            .unwrap();
+
+        assert!(mtproto.deserialization.is_empty());
+        assert_eq!(mtproto.salt_request_msg_id, None);
+        assert_eq!(mtproto.get_current_salt(), 2);
+        assert_eq!(mtproto.salts().len(), 2);
+    }
+
+    #[test]
+    fn ensure_bad_server_salt_is_recovered() {
+        let mut mtproto = Encrypted::build().first_salt(1).finish(auth_key());
+        mtproto
+            .process_message(manual_tl::Message {
+                msg_id: 4,
+                seq_no: 1,
+                body: tl::enums::BadMsgNotification::BadServerSalt(tl::types::BadServerSalt {
+                    bad_msg_id: 8,
+                    bad_msg_seqno: 1,
+                    error_code: 48,
+                    new_server_salt: 5,
+                })
+                .to_bytes(),
+            })
+            .unwrap();
+
+        assert_eq!(mtproto.get_current_salt(), 5);
+        match &mtproto.deserialization[..] {
+            [Deserialization::BadMessage(bad_msg)] => {
+                assert_eq!(bad_msg.msg_id, MsgId(8));
+                assert!(bad_msg.retryable());
+            }
+            _ => panic!("expected bad message"),
+        }
+    }
+
+    #[test]
+    fn ensure_set_salts_discards_expired() {
+        let mut mtproto = Encrypted::build().first_salt(1).finish(auth_key());
+        let now = mtproto.server_now();
+
+        mtproto.set_salts(vec![future_salt(now - 7200, now - 3600, 2)]);
+        assert_eq!(mtproto.get_current_salt(), 1);
+
+        mtproto.set_salts(vec![
+            future_salt(now - 7200, now - 3600, 2),
+            future_salt(now - 3600, now + 60, 3),
+            future_salt(now, now + 3600, 4),
+            future_salt(now + 3600, now + 7200, 5),
+        ]);
+        assert_eq!(mtproto.get_current_salt(), 3);
+        assert_eq!(mtproto.salts().len(), 3);
+    }
+
+    fn message_requiring_ack(msg_id: i64) -> manual_tl::Message {
+        manual_tl::Message {
+            msg_id,
+            seq_no: 1,
+            body: tl::enums::Pong::Pong(tl::types::Pong {
+                msg_id: 0,
+                ping_id: 0,
+            })
+            .to_bytes(),
+        }
+    }
+
+    fn ensure_buffer_acks(buffer: &DequeBuffer<u8>, acks: bool) {
+        let msgs_ack = tl::types::MsgsAck::CONSTRUCTOR_ID.to_le_bytes();
+        assert_eq!(buffer.as_ref().windows(4).any(|w| w == msgs_ack), acks);
+    }
+
+    #[test]
+    fn ensure_acks_wait_for_delay() {
+        let mut mtproto = Encrypted::build()
+            .ack_delay(Duration::from_secs(3600))
+            .finish(auth_key());
+        assert_eq!(mtproto.ack_deadline(), None);
+
+        mtproto.process_message(message_requiring_ack(4)).unwrap();
+        let deadline = mtproto.ack_deadline().unwrap();
+        assert!(deadline > Instant::now());
+
+        // More messages don't postpone the deadline.
+        mtproto.process_message(message_requiring_ack(8)).unwrap();
+        assert_eq!(mtproto.ack_deadline(), Some(deadline));
+
+        let mut buffer = DequeBuffer::with_capacity(0, 0);
+        mtproto.finalize_plain(&mut buffer);
+        assert!(buffer.is_empty());
+
+        // Once the deadline is reached, they're sent on their own.
+        mtproto.set_ack_delay(Duration::ZERO);
+        mtproto.ack_deadline = Some(Instant::now());
+        assert!(mtproto.finalize(&mut buffer).is_some());
+        assert_eq!(mtproto.ack_deadline(), None);
+        assert!(mtproto.pending_ack.is_empty());
+    }
+
+    #[test]
+    fn ensure_acks_ride_along_requests() {
+        let mut mtproto = Encrypted::build()
+            .ack_delay(Duration::from_secs(3600))
+            .finish(auth_key());
+        mtproto.process_message(message_requiring_ack(4)).unwrap();
+
+        let mut buffer = DequeBuffer::with_capacity(0, 0);
+        mtproto.push(&mut buffer, REQUEST);
+        mtproto.push(&mut buffer, REQUEST_B);
+        mtproto.finalize_plain(&mut buffer);
+        ensure_buffer_acks(&buffer, true);
+        assert_eq!(mtproto.ack_deadline(), None);
+
+        // Only once.
+        let mut buffer = DequeBuffer::with_capacity(0, 0);
+        mtproto.push(&mut buffer, REQUEST);
+        mtproto.finalize_plain(&mut buffer);
+        ensure_buffer_acks(&buffer, false);
+    }
+
+    #[test]
+    fn ensure_full_acks_are_sent_in_batches() {
+        let mut mtproto = Encrypted::build()
+            .ack_delay(Duration::from_secs(3600))
+            .finish(auth_key());
+        for i in 0..(MAX_PENDING_ACKS + 1) {
+            mtproto
+                .process_message(message_requiring_ack(i as i64 * 4))
+                .unwrap();
+        }
+        assert!(mtproto.ack_deadline().unwrap() <= Instant::now());
+
+        let mut buffer = DequeBuffer::with_capacity(0, 0);
+        mtproto.finalize_plain(&mut buffer);
+        assert!(buffer.is_empty());
+        assert!(mtproto.finalize(&mut buffer).is_some());
+        assert_eq!(mtproto.pending_ack.len(), 1);
+        assert!(mtproto.ack_deadline().is_some());
+    }
+
+    #[test]
+    fn ensure_flushed_acks_are_sent() {
+        let mut mtproto = Encrypted::build()
+            .ack_delay(Duration::from_secs(3600))
+            .finish(auth_key());
+
+        // Nothing to flush.
+        mtproto.flush_acks();
+        assert_eq!(mtproto.ack_deadline(), None);
+
+        mtproto.process_message(message_requiring_ack(4)).unwrap();
+        mtproto.flush_acks();
+
+        let mut buffer = DequeBuffer::with_capacity(0, 0);
+        assert!(mtproto.finalize(&mut buffer).is_some());
+        assert!(mtproto.pending_ack.is_empty());
+    }
+
+    #[test]
+    fn ensure_drop_answer_outcomes_are_results() {
+        let mut mtproto = Encrypted::build().finish(auth_key());
+        let outcomes = [
+            tl::enums::RpcDropAnswer::RpcAnswerUnknown.to_bytes(),
+            tl::enums::RpcDropAnswer::RpcAnswerDroppedRunning.to_bytes(),
+            tl::enums::RpcDropAnswer::RpcAnswerDropped(tl::types::RpcAnswerDropped {
+                msg_id: 8,
+                seq_no: 1,
+                bytes: 4,
+            })
+            .to_bytes(),
+        ];
+
+        for outcome in outcomes {
+            mtproto
+                .process_message(manual_tl::Message {
+                    msg_id: 4,
+                    seq_no: 1,
+                    body: rpc_result(123, &outcome),
+                })
+                .unwrap();
+            ensure_single_rpc_result(mem::take(&mut mtproto.deserialization), 123, &outcome);
+        }
+    }
+
+    fn unix_now() -> i32 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i32
+    }
+
+    fn msg_id_secs(msg_id: MsgId) -> i32 {
+        (msg_id.0 >> 32) as i32
+    }
+
+    #[test]
+    fn ensure_time_offset_is_corrected_on_bad_msg() {
+        // Our clock is ten minutes ahead of the server's.
+        let mut buffer = DequeBuffer::with_capacity(0, 0);
+        let mut mtproto = Encrypted::build().time_offset(600).finish(auth_key());
+        let skewed = mtproto.push(&mut buffer, REQUEST).unwrap();
+        assert!(msg_id_secs(skewed) >= unix_now() + 599);
+
+        mtproto
+            .process_message(manual_tl::Message {
+                msg_id: (unix_now() as i64) << 32,
+                seq_no: 1,
+                body: tl::enums::BadMsgNotification::Notification(tl::types::BadMsgNotification {
+                    bad_msg_id: skewed.0,
+                    bad_msg_seqno: 1,
+                    error_code: 17,
+                })
+                .to_bytes(),
+            })
+            .unwrap();
+
+        assert!(mtproto.time_offset().abs() <= 1);
+        match &mtproto.deserialization[..] {
+            [Deserialization::BadMessage(bad_msg)] => assert!(bad_msg.retryable()),
+            _ => panic!("expected bad message"),
+        }
+
+        // The request is sent again with a message ID that uses the server's time, even though
+        // it's lower than the one used before.
+        let mut buffer = DequeBuffer::with_capacity(0, 0);
+        let corrected = mtproto.push(&mut buffer, REQUEST).unwrap();
+        assert!(corrected.0 < skewed.0);
+        assert!((msg_id_secs(corrected) - unix_now()).abs() <= 1);
+    }
+
+    #[test]
+    fn ensure_time_offset_follows_server_messages() {
+        // Our clock is two minutes behind the server's.
+        let mut mtproto = Encrypted::build().finish(auth_key());
+
+        mtproto.check_time_offset(((unix_now() + 5) as i64) << 32);
+        assert_eq!(mtproto.time_offset(), 0);
+
+        mtproto.check_time_offset(((unix_now() + 120) as i64) << 32);
+        assert!((mtproto.time_offset() - 120).abs() <= 1);
+
+        let mut buffer = DequeBuffer::with_capacity(0, 0);
+        let msg_id = mtproto.push(&mut buffer, REQUEST).unwrap();
+        assert!((msg_id_secs(msg_id) - (unix_now() + 120)).abs() <= 1);
+    }
 }
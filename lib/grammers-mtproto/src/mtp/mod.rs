@@ -19,6 +19,7 @@
 mod encrypted;
 mod plain;
 
+use crate::utils::Instant;
 use crate::MsgId;
 use crypto::DequeBuffer;
 pub use encrypted::{
@@ -115,6 +116,10 @@ pub enum DeserializeError {
     /// within this variant.
     UnexpectedConstructor { id: u32 },
 
+    /// While deserializing one of the response types, its data did not match our expectations.
+    /// The inner error contains the type, offset and bytes where this happened.
+    InvalidData(tl::deserialize::Error),
+
     /// Attempting to decrypt the message failed in some way.
     DecryptionError(crypto::Error),
 }
@@ -141,6 +146,7 @@ impl fmt::Display for DeserializeError {
             ),
             Self::DecompressionFailed => write!(f, "failed to decompress server's data"),
             Self::UnexpectedConstructor { id } => write!(f, "unexpected constructor: {id:08x}"),
+            Self::InvalidData(ref error) => write!(f, "invalid response data: {error}"),
             Self::DecryptionError(ref error) => write!(f, "failed to decrypt message: {error}"),
         }
     }
@@ -153,6 +159,7 @@ impl From<tl::deserialize::Error> for DeserializeError {
         match error {
             Err::UnexpectedEof => DeserializeError::MessageBufferTooSmall,
             Err::UnexpectedConstructor { id } => DeserializeError::UnexpectedConstructor { id },
+            error @ Err::InType(_) => DeserializeError::InvalidData(error),
         }
     }
 }
@@ -206,4 +213,30 @@ pub trait Mtp {
 
     /// Reset the state, as if a new instance was just created.
     fn reset(&mut self);
+
+    /// The instant at which pending acknowledgements should be sent, even if there are no
+    /// requests for them to go along with, if any.
+    ///
+    /// Once reached, `finalize` will produce them.
+    fn ack_deadline(&self) -> Option<Instant> {
+        None
+    }
+
+    /// Produce the pending acknowledgements on the next call to `finalize`, without waiting
+    /// for their deadline (for example, because the connection is about to be closed).
+    fn flush_acks(&mut self) {}
+
+    /// The token the server will use to [quickly acknowledge] the receipt of the payload produced
+    /// by the last call to `finalize`, if known.
+    ///
+    /// [quickly acknowledge]: https://core.telegram.org/mtproto/mtproto-transports#quick-ack
+    fn quick_ack_token(&self) -> Option<u32> {
+        None
+    }
+
+    /// The difference, in seconds, between the server's time and our own, as used to generate
+    /// message identifiers.
+    fn time_offset(&self) -> i32 {
+        0
+    }
 }
@@ -5,7 +5,7 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
-use super::{Error, Transport, UnpackedOffset};
+use super::{Error, Tagged, Transport, UnpackedOffset, QUICK_ACK_FLAG};
 use grammers_crypto::DequeBuffer;
 
 /// A light MTProto transport protocol available that guarantees data padded
@@ -36,25 +36,46 @@ impl Intermediate {
     }
 }
 
-impl Transport for Intermediate {
-    fn pack(&mut self, buffer: &mut DequeBuffer<u8>) {
+impl Intermediate {
+    /// Packs the buffer, setting the given flags in the length.
+    fn pack_with_flags(&mut self, buffer: &mut DequeBuffer<u8>, flags: u32) {
         let len = buffer.len();
         assert_eq!(len % 4, 0);
 
-        buffer.extend_front(&(len as i32).to_le_bytes());
+        buffer.extend_front(&(len as u32 | flags).to_le_bytes());
 
         if !self.init {
             buffer.extend_front(&0xee_ee_ee_ee_u32.to_le_bytes());
             self.init = true;
         }
     }
+}
+
+impl Transport for Intermediate {
+    fn pack(&mut self, buffer: &mut DequeBuffer<u8>) {
+        self.pack_with_flags(buffer, 0)
+    }
+
+    fn pack_quick_ack(&mut self, buffer: &mut DequeBuffer<u8>) -> bool {
+        self.pack_with_flags(buffer, QUICK_ACK_FLAG);
+        true
+    }
 
-    fn unpack(&mut self, buffer: &[u8]) -> Result<UnpackedOffset, Error> {
+    fn unpack(&mut self, buffer: &mut [u8]) -> Result<UnpackedOffset, Error> {
         if buffer.len() < 4 {
             return Err(Error::MissingBytes);
         }
 
         let len = i32::from_le_bytes(buffer[0..4].try_into().unwrap());
+        if len < 0 {
+            // Quick acks are sent on their own, without length, and have the highest bit set.
+            return Ok(UnpackedOffset {
+                data_start: 4,
+                data_end: 4,
+                next_offset: 4,
+                quick_ack: Some(len as u32),
+            });
+        }
         if (buffer.len() as i32) < len {
             return Err(Error::MissingBytes);
         }
@@ -75,6 +96,7 @@ impl Transport for Intermediate {
             data_start: 4,
             data_end: 4 + len,
             next_offset: 4 + len,
+            quick_ack: None,
         })
     }
 
@@ -84,6 +106,13 @@ impl Transport for Intermediate {
     }
 }
 
+impl Tagged for Intermediate {
+    fn init_tag(&mut self) -> [u8; 4] {
+        self.init = true;
+        [0xee; 4]
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -123,7 +152,7 @@ mod tests {
         let mut transport = Intermediate::new();
         let mut buffer = DequeBuffer::with_capacity(1, 0);
         buffer.extend([1]);
-        assert_eq!(transport.unpack(&buffer[..],), Err(Error::MissingBytes));
+        assert_eq!(transport.unpack(&mut buffer[..],), Err(Error::MissingBytes));
     }
 
     #[test]
@@ -132,7 +161,7 @@ mod tests {
         let orig = buffer.clone();
         transport.pack(&mut buffer);
         let n = 4; // init bytes
-        let offset = transport.unpack(&buffer[n..]).unwrap();
+        let offset = transport.unpack(&mut buffer[n..]).unwrap();
         assert_eq!(&buffer[n..][offset.data_start..offset.data_end], &orig[..]);
     }
 
@@ -150,15 +179,35 @@ mod tests {
         transport.pack(&mut buffer);
         two_buffer.extend(&buffer[..]);
 
-        let offset = transport.unpack(&two_buffer[..]).unwrap();
+        let offset = transport.unpack(&mut two_buffer[..]).unwrap();
         assert_eq!(&buffer[offset.data_start..offset.data_end], &orig[..]);
         assert_eq!(offset.next_offset, single_size);
 
         let n = offset.next_offset;
-        let offset = transport.unpack(&two_buffer[n..]).unwrap();
+        let offset = transport.unpack(&mut two_buffer[n..]).unwrap();
         assert_eq!(&buffer[offset.data_start..offset.data_end], &orig[..]);
     }
 
+    #[test]
+    fn pack_quick_ack() {
+        let (mut transport, mut buffer) = setup_pack(128);
+        assert!(transport.pack_quick_ack(&mut buffer));
+        assert_eq!(&buffer[..8], &[0xee, 0xee, 0xee, 0xee, 128, 0, 0, 0x80]);
+    }
+
+    #[test]
+    fn unpack_quick_ack() {
+        let mut transport = Intermediate::new();
+        let mut buffer = DequeBuffer::with_capacity(8, 0);
+        buffer.extend(0x8765_4321_u32.to_le_bytes());
+        buffer.extend(4_i32.to_le_bytes());
+
+        let offset = transport.unpack(&mut buffer[..]).unwrap();
+        assert_eq!(offset.quick_ack, Some(0x8765_4321));
+        assert_eq!(offset.data_start, offset.data_end);
+        assert_eq!(offset.next_offset, 4);
+    }
+
     #[test]
     fn unpack_bad_status() {
         let mut transport = Intermediate::new();
@@ -167,7 +216,7 @@ mod tests {
         buffer.extend(&(-404_i32).to_le_bytes());
 
         assert_eq!(
-            transport.unpack(&buffer[..]),
+            transport.unpack(&mut buffer[..]),
             Err(Error::BadStatus { status: 404 })
         );
     }
@@ -14,11 +14,15 @@
 mod abridged;
 mod full;
 mod intermediate;
+mod obfuscated;
+mod padded_intermediate;
 
 pub use abridged::Abridged;
 pub use full::Full;
 use grammers_crypto::DequeBuffer;
 pub use intermediate::Intermediate;
+pub use obfuscated::Obfuscated;
+pub use padded_intermediate::PaddedIntermediate;
 use std::fmt;
 
 /// The error type reported by the different transports when something is wrong.
@@ -60,6 +64,10 @@ pub struct UnpackedOffset {
     pub data_start: usize,
     pub data_end: usize,
     pub next_offset: usize,
+    /// If the packet was a [quick ack] rather than data, the token it acknowledges.
+    ///
+    /// [quick ack]: https://core.telegram.org/mtproto/mtproto-transports#quick-ack
+    pub quick_ack: Option<u32>,
 }
 
 impl std::error::Error for Error {}
@@ -83,6 +91,10 @@ impl fmt::Display for Error {
     }
 }
 
+/// The bit set in the length of a packet to request a quick ack, and in the quick acks sent by
+/// the server, by the transports that support them.
+pub(crate) const QUICK_ACK_FLAG: u32 = 0x8000_0000;
+
 /// The trait used by the transports to create instances of themselves.
 pub trait Transport {
     /// Packs the input buffer in-place.
@@ -91,8 +103,49 @@ pub trait Transport {
     fn pack(&mut self, buffer: &mut DequeBuffer<u8>);
 
     /// Unpacks the input buffer in-place.
-    fn unpack(&mut self, buffer: &[u8]) -> Result<UnpackedOffset, Error>;
+    ///
+    /// If the transport needs more bytes, the same data will be provided again on the next call,
+    /// with the newly-received bytes appended at the end.
+    fn unpack(&mut self, buffer: &mut [u8]) -> Result<UnpackedOffset, Error>;
 
     /// Reset the state, as if a new instance was just created.
     fn reset(&mut self);
+
+    /// Packs the input buffer in-place like [`Transport::pack`], additionally asking the server
+    /// to send a [quick ack] as soon as it receives the packet.
+    ///
+    /// Returns `false` if the transport does not support quick acks, in which case the packet
+    /// is packed as usual.
+    ///
+    /// [quick ack]: https://core.telegram.org/mtproto/mtproto-transports#quick-ack
+    fn pack_quick_ack(&mut self, buffer: &mut DequeBuffer<u8>) -> bool {
+        self.pack(buffer);
+        false
+    }
+}
+
+/// The trait used by the transports that start the connection by sending a tag identifying
+/// them, which lets them be wrapped by the [`Obfuscated`] transport.
+pub trait Tagged {
+    /// Returns the tag that identifies the transport, and stops the transport from sending it
+    /// on its own, as if it had been sent already.
+    fn init_tag(&mut self) -> [u8; 4];
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn pack(&mut self, buffer: &mut DequeBuffer<u8>) {
+        (**self).pack(buffer)
+    }
+
+    fn unpack(&mut self, buffer: &mut [u8]) -> Result<UnpackedOffset, Error> {
+        (**self).unpack(buffer)
+    }
+
+    fn reset(&mut self) {
+        (**self).reset()
+    }
+
+    fn pack_quick_ack(&mut self, buffer: &mut DequeBuffer<u8>) -> bool {
+        (**self).pack_quick_ack(buffer)
+    }
 }
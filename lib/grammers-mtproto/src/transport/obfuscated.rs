@@ -0,0 +1,237 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use super::{Error, Tagged, Transport, UnpackedOffset};
+use grammers_crypto::aes::Ctr256;
+use grammers_crypto::{sha256, DequeBuffer};
+
+/// The values the first four bytes of the header can't take, so that the connection is not
+/// mistaken for a different protocol.
+const FORBIDDEN_HEADER_STARTS: [[u8; 4]; 7] = [
+    *b"HEAD",
+    *b"POST",
+    *b"GET ",
+    *b"OPTI",
+    [0xdd, 0xdd, 0xdd, 0xdd],
+    [0xee, 0xee, 0xee, 0xee],
+    [0x16, 0x03, 0x01, 0x02],
+];
+
+/// A wrapper over another transport which encrypts everything it sends and receives with
+/// AES-256-CTR, so that the traffic can't be identified as MTProto. This is an implementation
+/// of the [transport obfuscation].
+///
+/// The connection starts with a random 64-byte header from which the keys are derived, and
+/// which also carries the tag of the wrapped transport and the datacenter to connect to. The
+/// latter is used by [MTProxy] servers to decide where to forward the connection. When going
+/// through such a proxy, its secret must be provided.
+///
+/// Only transports which start with a tag can be wrapped. [`Abridged`](super::Abridged) and
+/// [`Intermediate`](super::Intermediate) work for every proxy, but proxies whose secret starts
+/// with `dd` require [`PaddedIntermediate`](super::PaddedIntermediate).
+///
+/// [transport obfuscation]: https://core.telegram.org/mtproto/mtproto-transports#transport-obfuscation
+/// [MTProxy]: https://core.telegram.org/mtproto/mtproto-transports#mtproxy
+pub struct Obfuscated<T: Transport + Tagged> {
+    inner: T,
+    dc_id: i16,
+    secret: Option<[u8; 16]>,
+    encryptor: Option<Ctr256>,
+    decryptor: Option<Ctr256>,
+    decrypted: usize,
+}
+
+impl<T: Transport + Tagged> Obfuscated<T> {
+    /// Wrap the given transport to connect to the datacenter with the given identifier.
+    ///
+    /// The secret is the 16-byte key of the proxy, without any prefix, or `None` if no proxy is
+    /// being used.
+    pub fn new(inner: T, dc_id: i16, secret: Option<[u8; 16]>) -> Self {
+        Self {
+            inner,
+            dc_id,
+            secret,
+            encryptor: None,
+            decryptor: None,
+            decrypted: 0,
+        }
+    }
+
+    /// Generate the header which starts the connection, and the keys derived from it.
+    fn init(&mut self) -> [u8; 64] {
+        let mut header = [0u8; 64];
+        loop {
+            getrandom::getrandom(&mut header).expect("failed to generate a secure header");
+            if header[0] != 0xef
+                && !FORBIDDEN_HEADER_STARTS
+                    .iter()
+                    .any(|start| header[..4] == *start)
+                && header[4..8] != [0; 4]
+            {
+                break;
+            }
+        }
+
+        header[56..60].copy_from_slice(&self.inner.init_tag());
+        header[60..62].copy_from_slice(&self.dc_id.to_le_bytes());
+
+        let mut reversed = header;
+        reversed.reverse();
+
+        let mut encryptor = self.cipher(&header[8..40], &header[40..56]);
+        let decryptor = self.cipher(&reversed[8..40], &reversed[40..56]);
+
+        // Only the end of the header is sent encrypted, but the whole header advances the stream.
+        let mut encrypted = header;
+        encryptor.apply(&mut encrypted);
+        header[56..].copy_from_slice(&encrypted[56..]);
+
+        self.encryptor = Some(encryptor);
+        self.decryptor = Some(decryptor);
+        self.decrypted = 0;
+        header
+    }
+
+    fn cipher(&self, key: &[u8], iv: &[u8]) -> Ctr256 {
+        let key = match self.secret {
+            Some(secret) => sha256!(key, secret),
+            None => key.try_into().unwrap(),
+        };
+        Ctr256::new(&key, iv.try_into().unwrap())
+    }
+
+    /// Packs the buffer with the inner transport, asking it for a quick ack if needed.
+    fn pack_inner(&mut self, buffer: &mut DequeBuffer<u8>, quick_ack: bool) -> bool {
+        let header = if self.encryptor.is_none() {
+            Some(self.init())
+        } else {
+            None
+        };
+
+        let quick_ack = if quick_ack {
+            self.inner.pack_quick_ack(buffer)
+        } else {
+            self.inner.pack(buffer);
+            false
+        };
+        self.encryptor
+            .as_mut()
+            .expect("encryptor should be initialized")
+            .apply(&mut buffer[..]);
+
+        if let Some(header) = header {
+            buffer.extend_front(&header);
+        }
+        quick_ack
+    }
+}
+
+impl<T: Transport + Tagged> Transport for Obfuscated<T> {
+    fn pack(&mut self, buffer: &mut DequeBuffer<u8>) {
+        self.pack_inner(buffer, false);
+    }
+
+    fn pack_quick_ack(&mut self, buffer: &mut DequeBuffer<u8>) -> bool {
+        self.pack_inner(buffer, true)
+    }
+
+    fn unpack(&mut self, buffer: &mut [u8]) -> Result<UnpackedOffset, Error> {
+        // Nothing can be received before the header is sent.
+        let decryptor = match self.decryptor.as_mut() {
+            Some(decryptor) => decryptor,
+            None => return Err(Error::MissingBytes),
+        };
+
+        // The bytes which were already decrypted are provided again if more were needed.
+        decryptor.apply(&mut buffer[self.decrypted..]);
+        self.decrypted = buffer.len();
+
+        let offset = self.inner.unpack(buffer)?;
+        self.decrypted -= offset.next_offset;
+        Ok(offset)
+    }
+
+    fn reset(&mut self) {
+        log::info!("resetting keys in obfuscated transport");
+        self.inner.reset();
+        self.encryptor = None;
+        self.decryptor = None;
+        self.decrypted = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::Intermediate;
+
+    const SECRET: [u8; 16] = [7; 16];
+
+    /// Returns the ciphers the proxy would use to talk to a client that sent the given header.
+    fn server_ciphers(header: &[u8]) -> (Ctr256, Ctr256) {
+        let mut reversed = header[8..56].to_vec();
+        reversed.reverse();
+        let cipher = |data: &[u8]| {
+            Ctr256::new(
+                &sha256!(&data[..32], SECRET),
+                data[32..48].try_into().unwrap(),
+            )
+        };
+        (cipher(&header[8..56]), cipher(&reversed))
+    }
+
+    #[test]
+    fn pack_header() {
+        let mut transport = Obfuscated::new(Intermediate::new(), -2, Some(SECRET));
+        let mut buffer = DequeBuffer::with_capacity(8, 0);
+        buffer.extend([1, 2, 3, 4, 5, 6, 7, 8]);
+        transport.pack(&mut buffer);
+        assert_eq!(buffer.len(), 64 + 4 + 8);
+
+        let (mut decryptor, _) = server_ciphers(&buffer[..64]);
+        let mut data = buffer[..].to_vec();
+        decryptor.apply(&mut data);
+        assert_eq!(&data[56..60], &[0xee; 4]);
+        assert_eq!(&data[60..62], &(-2_i16).to_le_bytes());
+        assert_eq!(&data[64..], &[8, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8]);
+
+        // The header is only sent once.
+        let mut buffer = DequeBuffer::with_capacity(4, 0);
+        buffer.extend([9, 9, 9, 9]);
+        transport.pack(&mut buffer);
+        let mut data = buffer[..].to_vec();
+        decryptor.apply(&mut data);
+        assert_eq!(&data[..], &[4, 0, 0, 0, 9, 9, 9, 9]);
+    }
+
+    #[test]
+    fn unpack_in_parts() {
+        let mut transport = Obfuscated::new(Intermediate::new(), 2, Some(SECRET));
+        let mut buffer = DequeBuffer::with_capacity(4, 0);
+        buffer.extend([0; 4]);
+        transport.pack(&mut buffer);
+
+        let (_, mut encryptor) = server_ciphers(&buffer[..64]);
+        let mut response = Vec::new();
+        for payload in [[1u8; 8], [2u8; 8]] {
+            response.extend((payload.len() as i32).to_le_bytes());
+            response.extend(payload);
+        }
+        encryptor.apply(&mut response);
+
+        assert_eq!(
+            transport.unpack(&mut response[..6]),
+            Err(Error::MissingBytes)
+        );
+        let offset = transport.unpack(&mut response[..]).unwrap();
+        assert_eq!(&response[offset.data_start..offset.data_end], &[1; 8]);
+
+        let response = &mut response[offset.next_offset..];
+        let offset = transport.unpack(response).unwrap();
+        assert_eq!(&response[offset.data_start..offset.data_end], &[2; 8]);
+    }
+}
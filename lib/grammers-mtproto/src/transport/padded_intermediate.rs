@@ -0,0 +1,187 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use super::{Error, Tagged, Transport, UnpackedOffset, QUICK_ACK_FLAG};
+use grammers_crypto::DequeBuffer;
+
+/// A variant of the [`Intermediate`](super::Intermediate) transport which appends up to 15
+/// bytes of random padding to every packet, making packet sizes harder to recognise. This is
+/// an implementation of the [padded intermediate transport].
+///
+/// The padding can't be told apart from the payload when unpacking, so it is left for the MTP
+/// to ignore.
+///
+/// It is meant to be used with the [`Obfuscated`](super::Obfuscated) transport, and is
+/// required by proxies whose secret starts with `dd`.
+///
+/// * Overhead: small.
+/// * Minimum envelope length: 4 bytes.
+/// * Maximum envelope length: 19 bytes.
+///
+/// It serializes the input payload as follows:
+///
+/// ```text
+/// +----+----...----+----...----+
+/// | len|  payload  |  padding  |
+/// +----+----...----+----...----+
+///  ^^^^ 4 bytes     ^^^^^^^^^^^ 0 to 15 bytes
+/// ```
+///
+/// [padded intermediate transport]: https://core.telegram.org/mtproto/mtproto-transports#padded-intermediate
+pub struct PaddedIntermediate {
+    init: bool,
+}
+
+#[allow(clippy::new_without_default)]
+impl PaddedIntermediate {
+    pub fn new() -> Self {
+        Self { init: false }
+    }
+}
+
+impl PaddedIntermediate {
+    /// Packs the buffer, setting the given flags in the length.
+    fn pack_with_flags(&mut self, buffer: &mut DequeBuffer<u8>, flags: u32) {
+        let len = buffer.len();
+        assert_eq!(len % 4, 0);
+
+        let mut padding = [0u8; 16];
+        getrandom::getrandom(&mut padding).expect("failed to generate a secure padding");
+        let padding = &padding[..(padding[15] % 16) as usize];
+
+        buffer.extend(padding);
+        buffer.extend_front(&((len + padding.len()) as u32 | flags).to_le_bytes());
+
+        if !self.init {
+            buffer.extend_front(&0xdd_dd_dd_dd_u32.to_le_bytes());
+            self.init = true;
+        }
+    }
+}
+
+impl Transport for PaddedIntermediate {
+    fn pack(&mut self, buffer: &mut DequeBuffer<u8>) {
+        self.pack_with_flags(buffer, 0)
+    }
+
+    fn pack_quick_ack(&mut self, buffer: &mut DequeBuffer<u8>) -> bool {
+        self.pack_with_flags(buffer, QUICK_ACK_FLAG);
+        true
+    }
+
+    fn unpack(&mut self, buffer: &mut [u8]) -> Result<UnpackedOffset, Error> {
+        if buffer.len() < 4 {
+            return Err(Error::MissingBytes);
+        }
+
+        let len = i32::from_le_bytes(buffer[0..4].try_into().unwrap());
+        if len < 0 {
+            // Quick acks are sent on their own, without length, and have the highest bit set.
+            return Ok(UnpackedOffset {
+                data_start: 4,
+                data_end: 4,
+                next_offset: 4,
+                quick_ack: Some(len as u32),
+            });
+        }
+        if len <= 4 {
+            if len >= 4 {
+                if buffer.len() < 8 {
+                    return Err(Error::MissingBytes);
+                }
+                let data = i32::from_le_bytes(buffer[4..8].try_into().unwrap());
+                return Err(Error::BadStatus {
+                    status: (-data) as u32,
+                });
+            }
+            return Err(Error::BadLen { got: len });
+        }
+
+        let len = len as usize;
+        if buffer.len() < 4 + len {
+            return Err(Error::MissingBytes);
+        }
+
+        Ok(UnpackedOffset {
+            data_start: 4,
+            data_end: 4 + len,
+            next_offset: 4 + len,
+            quick_ack: None,
+        })
+    }
+
+    fn reset(&mut self) {
+        log::info!("resetting sending of header in padded intermediate transport");
+        self.init = false;
+    }
+}
+
+impl Tagged for PaddedIntermediate {
+    fn init_tag(&mut self) -> [u8; 4] {
+        self.init = true;
+        [0xdd; 4]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a padded intermediate transport, and `n` bytes of input data for it.
+    fn setup_pack(n: usize) -> (PaddedIntermediate, DequeBuffer<u8>) {
+        let mut buffer = DequeBuffer::with_capacity(n, 0);
+        buffer.extend((0..n).map(|x| (x & 0xff) as u8));
+        (PaddedIntermediate::new(), buffer)
+    }
+
+    #[test]
+    fn pack_normal() {
+        let (mut transport, mut buffer) = setup_pack(128);
+        let orig = buffer.clone();
+        transport.pack(&mut buffer);
+        let len = i32::from_le_bytes(buffer[4..8].try_into().unwrap()) as usize;
+        assert_eq!(&buffer[..4], &[0xdd, 0xdd, 0xdd, 0xdd]);
+        assert!((128..128 + 16).contains(&len));
+        assert_eq!(buffer.len(), 8 + len);
+        assert_eq!(&buffer[8..8 + 128], &orig[..]);
+    }
+
+    #[test]
+    fn unpack_normal() {
+        let (mut transport, mut buffer) = setup_pack(128);
+        let orig = buffer.clone();
+        transport.pack(&mut buffer);
+        let n = 4; // init bytes
+        let offset = transport.unpack(&mut buffer[n..]).unwrap();
+        assert_eq!(&buffer[n..][offset.data_start..][..orig.len()], &orig[..]);
+        assert_eq!(offset.next_offset, buffer.len() - n);
+    }
+
+    #[test]
+    fn unpack_missing_padding() {
+        let (mut transport, mut buffer) = setup_pack(128);
+        transport.pack(&mut buffer);
+        let len = buffer.len();
+        assert_eq!(
+            transport.unpack(&mut buffer[4..len - 1]),
+            Err(Error::MissingBytes)
+        );
+    }
+
+    #[test]
+    fn unpack_bad_status() {
+        let mut transport = PaddedIntermediate::new();
+        let mut buffer = DequeBuffer::with_capacity(8, 0);
+        buffer.extend(&(4_i32).to_le_bytes());
+        buffer.extend(&(-404_i32).to_le_bytes());
+
+        assert_eq!(
+            transport.unpack(&mut buffer[..]),
+            Err(Error::BadStatus { status: 404 })
+        );
+    }
+}
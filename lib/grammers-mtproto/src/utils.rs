@@ -7,6 +7,12 @@
 // except according to those terms.
 use crate::mtp::DeserializeError;
 
+// The clock of the standard library is not available in the browser.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) use std::time::{Instant, SystemTime, UNIX_EPOCH};
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) use web_time::{Instant, SystemTime, UNIX_EPOCH};
+
 /// Checks a message buffer for common errors
 pub(crate) fn check_message_buffer(message: &[u8]) -> Result<(), DeserializeError> {
     if message.len() < 20 {
@@ -9,6 +9,7 @@ use grammers_mtproto::{authentication, mtp, transport};
 use grammers_tl_types as tl;
 use std::fmt;
 use std::io;
+use std::time::Duration;
 
 #[derive(Debug)]
 pub enum ReadError {
@@ -24,7 +25,7 @@ impl Clone for ReadError {
         match self {
             Self::Io(e) => Self::Io(
                 e.raw_os_error()
-                    .map(|raw| io::Error::from_raw_os_error(raw))
+                    .map(io::Error::from_raw_os_error)
                     .unwrap_or_else(|| io::Error::new(e.kind(), e.to_string())),
             ),
             Self::Transport(e) => Self::Transport(e.clone()),
@@ -173,10 +174,38 @@ pub enum InvocationError {
     Rpc(RpcError),
 
     /// The request was cancelled or dropped, and the results won't arrive.
+    ///
+    /// This also occurs when the connection is lost after the request was sent, and the
+    /// [`ReconnectionPolicy`](crate::ReconnectionPolicy) does not allow sending it again.
     Dropped,
 
     /// The error occured while reading the response.
     Read(ReadError),
+
+    /// The request could not be made because of a flood wait, and the given time must pass before
+    /// it can be made again.
+    ///
+    /// This is never returned by the [`Sender`](crate::Sender) itself, which reports flood waits
+    /// as any other [`InvocationError::Rpc`]. Clients may use it instead, for example when the
+    /// wait is too long to sleep through. The `error` is the one reported by the server, such as
+    /// `FLOOD_WAIT` or `FLOOD_PREMIUM_WAIT`.
+    Flood { wait: Duration, error: RpcError },
+
+    /// The response did not arrive in time, and the request was cancelled.
+    Timeout,
+
+    /// The request was cancelled on purpose before its response arrived.
+    ///
+    /// This is never returned by the [`Sender`](crate::Sender) itself, which simply stops
+    /// waiting for the responses of requests whose result is no longer wanted.
+    Cancelled,
+
+    /// The server acknowledged the receipt of the request, but the connection was lost before
+    /// its response arrived.
+    ///
+    /// The request may have taken effect, so it is not sent again when it's not safe to repeat
+    /// (for example, when sending a message, which could then be sent twice).
+    Unanswered,
 }
 
 impl std::error::Error for InvocationError {}
@@ -187,6 +216,12 @@ impl fmt::Display for InvocationError {
             Self::Rpc(err) => write!(f, "request error: {err}"),
             Self::Dropped => write!(f, "request error: dropped (cancelled)"),
             Self::Read(err) => write!(f, "request error: {err}"),
+            Self::Flood { wait, error } => {
+                write!(f, "request error: {error} (flood wait of {wait:?})")
+            }
+            Self::Timeout => write!(f, "request error: timed out"),
+            Self::Cancelled => write!(f, "request error: cancelled"),
+            Self::Unanswered => write!(f, "request error: received by the server but unanswered"),
         }
     }
 }
@@ -215,7 +250,8 @@ impl InvocationError {
     /// Useful in `match` arm guards. A single trailing or leading asterisk (`'*'`) is allowed,
     /// and will instead check if the error name starts (or ends with) the input parameter.
     ///
-    /// If the error is not a RPC error, returns `false`.
+    /// Flood waits are matched on the name of the RPC error which caused them. If the error is
+    /// not a RPC error, returns `false`.
     ///
     /// # Examples
     ///
@@ -232,7 +268,7 @@ impl InvocationError {
     #[inline]
     pub fn is(&self, rpc_error: &str) -> bool {
         match self {
-            Self::Rpc(rpc) => rpc.is(rpc_error),
+            Self::Rpc(rpc) | Self::Flood { error: rpc, .. } => rpc.is(rpc_error),
             _ => false,
         }
     }
@@ -324,4 +360,21 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn check_flood_error_matching() {
+        let error = InvocationError::Flood {
+            wait: Duration::from_secs(31),
+            error: RpcError {
+                code: 420,
+                name: "FLOOD_PREMIUM_WAIT".into(),
+                value: Some(31),
+                caused_by: None,
+            },
+        };
+
+        assert!(error.is("FLOOD_PREMIUM_WAIT"));
+        assert!(error.is("FLOOD_*"));
+        assert!(!error.is("FLOOD_WAIT"));
+    }
 }
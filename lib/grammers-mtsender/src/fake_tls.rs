@@ -0,0 +1,514 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A [`Connector`] which disguises the connection to an MTProxy server as a TLS connection.
+use crate::time::SystemTime;
+use crate::{Connector, Stream};
+use futures_util::future::BoxFuture;
+use grammers_crypto::sha::hmac_sha256;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+/// The length of the ClientHello, which proxies expect to be exactly this long.
+const HELLO_LEN: usize = 517;
+
+/// Where the random value of both the ClientHello and the ServerHello starts.
+const RANDOM_OFFSET: usize = 11;
+
+const RECORD_HEADER_LEN: usize = 5;
+
+/// The maximum amount of data which can be sent in a single TLS record.
+const MAX_RECORD_LEN: usize = 1 << 14;
+
+const HANDSHAKE_HEADER: [u8; 3] = [0x16, 0x03, 0x03];
+const CHANGE_CIPHER_SPEC_HEADER: [u8; 3] = [0x14, 0x03, 0x03];
+const APPLICATION_DATA_HEADER: [u8; 3] = [0x17, 0x03, 0x03];
+const CHANGE_CIPHER_SPEC: [u8; 6] = [0x14, 0x03, 0x03, 0x00, 0x01, 0x01];
+
+/// A [`Connector`] for [MTProxy] servers whose secret starts with `ee`, which only accept
+/// connections that look like TLS ones.
+///
+/// The connection starts with a TLS ClientHello for the domain of the secret, which the proxy
+/// answers as if it was that domain, proving that it knows the secret too. After that, the
+/// data is sent and received inside TLS application data records. The data itself must still
+/// be packed by the [`Obfuscated`](grammers_mtproto::transport::Obfuscated) transport, using
+/// the [`PaddedIntermediate`](grammers_mtproto::transport::PaddedIntermediate) transport.
+///
+/// [MTProxy]: https://core.telegram.org/mtproto/mtproto-transports#mtproxy
+#[derive(Clone)]
+pub struct FakeTlsConnector {
+    secret: [u8; 16],
+    domain: String,
+    connector: Option<Arc<dyn Connector>>,
+}
+
+impl FakeTlsConnector {
+    /// Create a connector for a proxy with the given secret (its 16-byte key, without the `ee`
+    /// prefix or the domain) and the domain that follows it in the secret.
+    pub fn new(secret: [u8; 16], domain: impl Into<String>) -> Self {
+        Self {
+            secret,
+            domain: domain.into(),
+            connector: None,
+        }
+    }
+
+    /// Open the underlying stream to the proxy with the given connector, instead of over TCP.
+    pub fn with_connector(mut self, connector: Arc<dyn Connector>) -> Self {
+        self.connector = Some(connector);
+        self
+    }
+
+    async fn open(&self, addr: SocketAddr) -> io::Result<Box<dyn Stream>> {
+        if let Some(connector) = &self.connector {
+            return connector.connect(addr).await;
+        }
+
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        {
+            Ok(Box::new(tokio::net::TcpStream::connect(addr).await?))
+        }
+        #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+        {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "tcp connections are not available in the browser",
+            ))
+        }
+    }
+
+    async fn handshake(&self, addr: SocketAddr) -> io::Result<Box<dyn Stream>> {
+        let (hello, digest) = client_hello(&self.secret, &self.domain, unix_time())?;
+        let mut stream = self.open(addr).await?;
+        stream.write_all(&hello).await?;
+        stream.flush().await?;
+
+        // The ServerHello, the change of cipher spec, and the first application data (which
+        // stands in for the encrypted certificate) all come at once.
+        let mut response = Vec::new();
+        for header in [
+            HANDSHAKE_HEADER,
+            CHANGE_CIPHER_SPEC_HEADER,
+            APPLICATION_DATA_HEADER,
+        ] {
+            read_record(&mut stream, header, &mut response).await?;
+        }
+        check_server_hello(&self.secret, &digest, &mut response)?;
+
+        Ok(Box::new(FakeTlsStream::new(stream)))
+    }
+}
+
+impl Connector for FakeTlsConnector {
+    fn connect(&self, addr: SocketAddr) -> BoxFuture<'_, io::Result<Box<dyn Stream>>> {
+        Box::pin(self.handshake(addr))
+    }
+}
+
+fn unix_time() -> u32 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs() as u32)
+        .unwrap_or(0)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Build the ClientHello for the given domain, signed with the secret at the given time.
+///
+/// Returns the hello and its random value, which the proxy uses to sign its response.
+fn client_hello(
+    secret: &[u8; 16],
+    domain: &str,
+    time: u32,
+) -> io::Result<([u8; HELLO_LEN], [u8; 32])> {
+    fn extension(hello: &mut Vec<u8>, id: [u8; 2], data: &[u8]) {
+        hello.extend(id);
+        hello.extend((data.len() as u16).to_be_bytes());
+        hello.extend(data);
+    }
+
+    if domain.len() > HELLO_LEN {
+        return Err(invalid_data("the domain of the proxy secret is too long"));
+    }
+
+    let mut random = [0u8; 32 + 32 + 5];
+    getrandom::getrandom(&mut random).expect("failed to generate a secure client hello");
+    let (session_id, rest) = random.split_at_mut(32);
+    let (key, grease) = rest.split_at_mut(32);
+    // A public key of X25519 never has the highest bit set.
+    key[31] &= 0x7f;
+    // GREASE values (RFC 8701) are like 0x?a?a. The extensions which use them must differ.
+    let grease = grease.iter().map(|b| (b & 0xf0) | 0x0a).collect::<Vec<_>>();
+    let grease = |i: usize| [grease[i], grease[i]];
+    let last_grease = if grease(3) == grease(2) {
+        [grease(2)[0] ^ 0x10; 2]
+    } else {
+        grease(3)
+    };
+
+    let mut hello = Vec::with_capacity(HELLO_LEN);
+    // The headers of the record and of the handshake, and the version.
+    hello.extend([
+        0x16, 0x03, 0x01, 0x02, 0x00, 0x01, 0x00, 0x01, 0xfc, 0x03, 0x03,
+    ]);
+    hello.extend([0; 32]);
+    hello.push(session_id.len() as u8);
+    hello.extend(&*session_id);
+    hello.extend([0x00, 0x20]);
+    hello.extend(grease(0));
+    hello.extend([
+        0x13, 0x01, 0x13, 0x02, 0x13, 0x03, 0xc0, 0x2b, 0xc0, 0x2f, 0xc0, 0x2c, 0xc0, 0x30, 0xcc,
+        0xa9, 0xcc, 0xa8, 0xc0, 0x13, 0xc0, 0x14, 0x00, 0x9c, 0x00, 0x9d, 0x00, 0x2f, 0x00, 0x35,
+    ]);
+    hello.extend([0x01, 0x00]);
+
+    let extensions_start = hello.len();
+    hello.extend([0, 0]);
+    extension(&mut hello, grease(2), &[]);
+    let mut server_name = Vec::with_capacity(domain.len() + 5);
+    server_name.extend((domain.len() as u16 + 3).to_be_bytes());
+    server_name.push(0x00);
+    server_name.extend((domain.len() as u16).to_be_bytes());
+    server_name.extend(domain.as_bytes());
+    extension(&mut hello, [0x00, 0x00], &server_name);
+    extension(&mut hello, [0x00, 0x17], &[]);
+    extension(&mut hello, [0xff, 0x01], &[0x00]);
+    let [g0, g1] = grease(1);
+    extension(
+        &mut hello,
+        [0x00, 0x0a],
+        &[0x00, 0x08, g0, g1, 0x00, 0x1d, 0x00, 0x17, 0x00, 0x18],
+    );
+    extension(&mut hello, [0x00, 0x0b], &[0x01, 0x00]);
+    extension(&mut hello, [0x00, 0x23], &[]);
+    extension(&mut hello, [0x00, 0x10], b"\x00\x0c\x02h2\x08http/1.1");
+    extension(&mut hello, [0x00, 0x05], &[0x01, 0x00, 0x00, 0x00, 0x00]);
+    extension(
+        &mut hello,
+        [0x00, 0x0d],
+        &[
+            0x00, 0x12, 0x04, 0x03, 0x08, 0x04, 0x04, 0x01, 0x05, 0x03, 0x08, 0x05, 0x05, 0x01,
+            0x08, 0x06, 0x06, 0x01, 0x02, 0x01,
+        ],
+    );
+    extension(&mut hello, [0x00, 0x12], &[]);
+    let mut key_share = vec![0x00, 0x29, g0, g1, 0x00, 0x01, 0x00, 0x00, 0x1d, 0x00, 0x20];
+    key_share.extend(&*key);
+    extension(&mut hello, [0x00, 0x33], &key_share);
+    extension(&mut hello, [0x00, 0x2d], &[0x01, 0x01]);
+    let [v0, v1] = grease(4);
+    extension(
+        &mut hello,
+        [0x00, 0x2b],
+        &[0x06, v0, v1, 0x03, 0x04, 0x03, 0x03],
+    );
+    extension(&mut hello, [0x00, 0x1b], &[0x02, 0x00, 0x02]);
+    extension(&mut hello, last_grease, &[0x00]);
+
+    // Padding fills the rest of the hello.
+    let padding = HELLO_LEN
+        .checked_sub(hello.len() + 4)
+        .ok_or_else(|| invalid_data("the domain of the proxy secret is too long"))?;
+    extension(&mut hello, [0x00, 0x15], &vec![0; padding]);
+    let extensions_len = (hello.len() - extensions_start - 2) as u16;
+    hello[extensions_start..extensions_start + 2].copy_from_slice(&extensions_len.to_be_bytes());
+
+    let mut hello: [u8; HELLO_LEN] = hello.try_into().unwrap();
+    let mut digest = hmac_sha256(secret, &[&hello]);
+    // The proxy rejects hellos which are too old, or that it has seen before.
+    for (d, t) in digest[28..].iter_mut().zip(time.to_le_bytes()) {
+        *d ^= t;
+    }
+    hello[RANDOM_OFFSET..RANDOM_OFFSET + 32].copy_from_slice(&digest);
+    Ok((hello, digest))
+}
+
+/// Read a whole TLS record from the stream into the buffer, if its header is the expected one.
+async fn read_record(
+    stream: &mut Box<dyn Stream>,
+    expected: [u8; 3],
+    buffer: &mut Vec<u8>,
+) -> io::Result<()> {
+    let mut header = [0; RECORD_HEADER_LEN];
+    stream.read_exact(&mut header).await?;
+    if header[..3] != expected {
+        return Err(invalid_data(
+            "the proxy did not respond with the expected tls records",
+        ));
+    }
+    buffer.extend(header);
+
+    let start = buffer.len();
+    buffer.resize(
+        start + u16::from_be_bytes([header[3], header[4]]) as usize,
+        0,
+    );
+    stream.read_exact(&mut buffer[start..]).await?;
+    Ok(())
+}
+
+/// Check that the random value of the response of the proxy was signed with the secret.
+fn check_server_hello(secret: &[u8; 16], digest: &[u8; 32], response: &mut [u8]) -> io::Result<()> {
+    let random = response
+        .get_mut(RANDOM_OFFSET..RANDOM_OFFSET + 32)
+        .ok_or_else(|| invalid_data("the server hello of the proxy is too short"))?;
+    let expected: [u8; 32] = random.try_into().unwrap();
+    random.fill(0);
+
+    if hmac_sha256(secret, &[digest, response]) == expected {
+        Ok(())
+    } else {
+        Err(invalid_data("the proxy does not know the secret"))
+    }
+}
+
+/// A stream which sends and receives the data inside TLS application data records.
+struct FakeTlsStream<S> {
+    inner: S,
+    // The record being written, and how much of it has been written already.
+    record: Vec<u8>,
+    record_written: usize,
+    // How much of the data given to write is in the record.
+    record_data_len: usize,
+    // The header of the record being read, and how much of it has been read already.
+    header: [u8; RECORD_HEADER_LEN],
+    header_read: usize,
+    // How much of the data in the record being read is left to read.
+    data_left: usize,
+}
+
+impl<S> FakeTlsStream<S> {
+    fn new(inner: S) -> Self {
+        Self {
+            inner,
+            // The client must change the cipher spec too before sending any data.
+            record: CHANGE_CIPHER_SPEC.to_vec(),
+            record_written: 0,
+            record_data_len: 0,
+            header: [0; RECORD_HEADER_LEN],
+            header_read: 0,
+            data_left: 0,
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for FakeTlsStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        while this.data_left == 0 {
+            let mut header = ReadBuf::new(&mut this.header[this.header_read..]);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut header))?;
+            let n = header.filled().len();
+            if n == 0 {
+                return Poll::Ready(match this.header_read {
+                    0 => Ok(()),
+                    _ => Err(io::ErrorKind::UnexpectedEof.into()),
+                });
+            }
+
+            this.header_read += n;
+            if this.header_read == RECORD_HEADER_LEN {
+                if this.header[..3] != APPLICATION_DATA_HEADER {
+                    return Poll::Ready(Err(invalid_data(
+                        "the proxy sent an unexpected tls record",
+                    )));
+                }
+                this.header_read = 0;
+                this.data_left = u16::from_be_bytes([this.header[3], this.header[4]]) as usize;
+            }
+        }
+
+        let mut data =
+            ReadBuf::new(buf.initialize_unfilled_to(this.data_left.min(buf.remaining())));
+        ready!(Pin::new(&mut this.inner).poll_read(cx, &mut data))?;
+        let n = data.filled().len();
+        if n == 0 {
+            return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+        }
+        this.data_left -= n;
+        buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for FakeTlsStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        // A record that could not be written at once is written before taking more data. The
+        // data is only reported as written once its whole record is, so it is given again.
+        if this.record_data_len == 0 {
+            if buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            let data = &buf[..buf.len().min(MAX_RECORD_LEN)];
+            this.record.extend(APPLICATION_DATA_HEADER);
+            this.record.extend((data.len() as u16).to_be_bytes());
+            this.record.extend(data);
+            this.record_data_len = data.len();
+        }
+
+        while this.record_written < this.record.len() {
+            let n = ready!(
+                Pin::new(&mut this.inner).poll_write(cx, &this.record[this.record_written..])
+            )?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            this.record_written += n;
+        }
+
+        this.record.clear();
+        this.record_written = 0;
+        Poll::Ready(Ok(std::mem::take(&mut this.record_data_len)))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::future::join;
+    use tokio::io::{duplex, DuplexStream};
+
+    const SECRET: [u8; 16] = [7; 16];
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    struct DuplexConnector(std::sync::Mutex<Option<DuplexStream>>);
+
+    impl Connector for DuplexConnector {
+        fn connect(&self, _: SocketAddr) -> BoxFuture<'_, io::Result<Box<dyn Stream>>> {
+            let stream = self.0.lock().unwrap().take().unwrap();
+            Box::pin(async move { Ok(Box::new(stream) as Box<dyn Stream>) })
+        }
+    }
+
+    /// Answer the hello like a proxy would, signing the response with the given secret.
+    async fn serve_hello(server: &mut DuplexStream, secret: [u8; 16]) -> [u8; HELLO_LEN] {
+        let mut hello = [0; HELLO_LEN];
+        server.read_exact(&mut hello).await.unwrap();
+
+        let mut response = vec![0x16, 0x03, 0x03, 0x00, 0x30];
+        response.extend([2; 0x30]);
+        response[RANDOM_OFFSET..RANDOM_OFFSET + 32].fill(0);
+        response.extend(CHANGE_CIPHER_SPEC);
+        response.extend([0x17, 0x03, 0x03, 0x00, 0x04, 3, 3, 3, 3]);
+        let random = hmac_sha256(&secret, &[&hello[RANDOM_OFFSET..][..32], &response]);
+        response[RANDOM_OFFSET..RANDOM_OFFSET + 32].copy_from_slice(&random);
+        server.write_all(&response).await.unwrap();
+        hello
+    }
+
+    /// A connector whose underlying stream is connected to the returned one.
+    fn connector() -> (FakeTlsConnector, DuplexStream) {
+        let (client, server) = duplex(1 << 16);
+        let connector = FakeTlsConnector::new(SECRET, "example.com").with_connector(Arc::new(
+            DuplexConnector(std::sync::Mutex::new(Some(client))),
+        ));
+        (connector, server)
+    }
+
+    #[test]
+    fn client_hello_is_signed() {
+        let (hello, digest) = client_hello(&SECRET, "example.com", 0x01020304).unwrap();
+        assert_eq!(hello.len(), HELLO_LEN);
+        assert_eq!(
+            hello[..RANDOM_OFFSET],
+            [0x16, 0x03, 0x01, 0x02, 0x00, 0x01, 0x00, 0x01, 0xfc, 0x03, 0x03]
+        );
+        assert!(hello.windows(11).any(|w| w == b"example.com"));
+
+        let mut unsigned = hello;
+        unsigned[RANDOM_OFFSET..RANDOM_OFFSET + 32].fill(0);
+        let mut expected = hmac_sha256(&SECRET, &[&unsigned]);
+        for (d, t) in expected[28..].iter_mut().zip(0x01020304u32.to_le_bytes()) {
+            *d ^= t;
+        }
+        assert_eq!(digest, expected);
+        assert_eq!(hello[RANDOM_OFFSET..RANDOM_OFFSET + 32], digest);
+
+        assert!(client_hello(&SECRET, &"a".repeat(300), 0).is_err());
+    }
+
+    #[test]
+    fn records_are_framed() {
+        block_on(async {
+            let (connector, mut server) = connector();
+            let addr = SocketAddr::from(([127, 0, 0, 1], 443));
+            let (stream, _) = join(connector.connect(addr), serve_hello(&mut server, SECRET)).await;
+            let mut stream = stream.unwrap();
+
+            stream.write_all(&[1, 2, 3, 4]).await.unwrap();
+            let mut sent = [0; 6 + 5 + 4];
+            server.read_exact(&mut sent).await.unwrap();
+            assert_eq!(sent[..6], CHANGE_CIPHER_SPEC);
+            assert_eq!(sent[6..], [0x17, 0x03, 0x03, 0x00, 0x04, 1, 2, 3, 4]);
+
+            // Records may be split anywhere, and data spans several of them.
+            server
+                .write_all(&[0x17, 0x03, 0x03, 0x00, 0x02, 5, 6, 0x17, 0x03])
+                .await
+                .unwrap();
+            server.write_all(&[0x03, 0x00, 0x01, 7]).await.unwrap();
+            let mut received = [0; 3];
+            stream.read_exact(&mut received).await.unwrap();
+            assert_eq!(received, [5, 6, 7]);
+
+            server
+                .write_all(&[0x15, 0x03, 0x03, 0x00, 0x00])
+                .await
+                .unwrap();
+            assert!(stream.read(&mut received).await.is_err());
+        });
+    }
+
+    #[test]
+    fn wrong_secret_is_rejected() {
+        block_on(async {
+            let (connector, mut server) = connector();
+            let addr = SocketAddr::from(([127, 0, 0, 1], 443));
+            let (stream, _) =
+                join(connector.connect(addr), serve_hello(&mut server, [8; 16])).await;
+            assert_eq!(
+                stream.err().map(|e| e.kind()),
+                Some(io::ErrorKind::InvalidData)
+            );
+        });
+    }
+}
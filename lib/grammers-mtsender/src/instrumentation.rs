@@ -0,0 +1,52 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use std::time::Duration;
+
+/// Hooks called as the sender performs network operations, to collect metrics about them.
+///
+/// Every method does nothing by default, so only those of interest need to be implemented.
+/// They are called while the network is being driven, so they should return quickly (for
+/// example, by updating counters) rather than block.
+///
+/// Requests and responses are identified by the constructor identifier of the request, which
+/// can be turned into a name with [`grammers_tl_types::name_for_id`].
+pub trait Instrumentation: Send + Sync {
+    /// Called after some bytes are written to the network.
+    fn on_bytes_sent(&self, len: usize) {
+        let _ = len;
+    }
+
+    /// Called after some bytes are read from the network.
+    fn on_bytes_received(&self, len: usize) {
+        let _ = len;
+    }
+
+    /// Called once a request of the given length (in bytes) has been written to the network.
+    fn on_request_sent(&self, constructor_id: u32, len: usize) {
+        let _ = (constructor_id, len);
+    }
+
+    /// Called when the response to a request arrives, be it a result or an error, along with
+    /// how long it took since the request was sent.
+    fn on_response(&self, constructor_id: u32, latency: Duration) {
+        let _ = (constructor_id, latency);
+    }
+
+    /// Called when the amount of requests waiting for a response changes.
+    fn on_requests_in_flight(&self, count: usize) {
+        let _ = count;
+    }
+
+    /// Called after a connection that was lost is successfully made again.
+    fn on_reconnect(&self) {}
+
+    /// Called before sleeping because a request of the given type was flood-limited.
+    fn on_flood_wait(&self, constructor_id: u32, wait: Duration) {
+        let _ = (constructor_id, wait);
+    }
+}
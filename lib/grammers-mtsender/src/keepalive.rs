@@ -0,0 +1,203 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use crate::time::Instant;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Every how often are pings sent by default?
+pub(crate) const PING_DELAY: Duration = Duration::from_secs(60);
+
+/// How long to wait by default for the response to a ping before considering the connection dead.
+pub(crate) const PING_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// How many seconds after the ping is due should the server close the connection when we send a
+/// ping?
+///
+/// What this value essentially means is that we have this many seconds of margin to keep sending
+/// pings, or the server will close the connection.
+///
+/// Pings ensure the connection is kept active, and the delayed disconnect ensures the messages
+/// are getting through consistently enough.
+const NO_PING_DISCONNECT_MARGIN: i32 = 15;
+
+/// The round-trip time to the server, as measured by the keep-alive pings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Latency {
+    /// The round-trip time of the most recent ping.
+    pub last: Duration,
+    /// A moving average of the round-trip time of the pings, which changes slowly and is not as
+    /// affected by a single slow ping.
+    pub average: Duration,
+}
+
+impl Latency {
+    fn record(latency: Option<Self>, rtt: Duration) -> Self {
+        Self {
+            last: rtt,
+            average: match latency {
+                Some(latency) => (latency.average * 7 + rtt) / 8,
+                None => rtt,
+            },
+        }
+    }
+}
+
+/// Keeps track of the keep-alive pings sent through a connection, in order to detect when it
+/// silently dies.
+pub(crate) struct KeepAlive {
+    interval: Option<Duration>,
+    timeout: Duration,
+    next_ping: Instant,
+    // The ping waiting for a response, and when it was sent.
+    pending: Option<(i64, Instant)>,
+    last_read: Instant,
+    latency: Arc<Mutex<Option<Latency>>>,
+}
+
+/// What needs to be done once the [`KeepAlive::deadline`] is reached.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Due {
+    Nothing,
+    Ping,
+    Dead,
+}
+
+impl KeepAlive {
+    pub(crate) fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            interval: Some(PING_DELAY),
+            timeout: PING_TIMEOUT,
+            next_ping: now + PING_DELAY,
+            pending: None,
+            last_read: now,
+            latency: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub(crate) fn configure(&mut self, interval: Option<Duration>, timeout: Duration) {
+        self.interval = interval;
+        self.timeout = timeout;
+        self.reset();
+    }
+
+    /// Start over, as if the connection was just made.
+    pub(crate) fn reset(&mut self) {
+        let now = Instant::now();
+        self.next_ping = now + self.interval.unwrap_or_default();
+        self.pending = None;
+        self.last_read = now;
+    }
+
+    pub(crate) fn latency_handle(&self) -> Arc<Mutex<Option<Latency>>> {
+        Arc::clone(&self.latency)
+    }
+
+    /// The instant at which either a new ping must be sent, or the connection is dead if the
+    /// pending ping has not been answered nor anything else has been received.
+    ///
+    /// There is no deadline if pings are disabled.
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.interval?;
+        Some(match self.pending {
+            Some((_, sent)) => self.next_ping.min(sent.max(self.last_read) + self.timeout),
+            None => self.next_ping,
+        })
+    }
+
+    pub(crate) fn due(&self, now: Instant) -> Due {
+        match (self.interval, self.pending) {
+            (None, _) => Due::Nothing,
+            (Some(_), Some((_, sent))) if now >= sent.max(self.last_read) + self.timeout => {
+                Due::Dead
+            }
+            (Some(_), _) if now >= self.next_ping => Due::Ping,
+            (Some(_), _) => Due::Nothing,
+        }
+    }
+
+    /// Record that a new ping was sent, returning the delay after which the server should close
+    /// the connection if no other ping is sent.
+    pub(crate) fn on_ping(&mut self, ping_id: i64, now: Instant) -> i32 {
+        let interval = self.interval.unwrap_or(PING_DELAY);
+        // A lost ping is detected by the one still pending, so it's not replaced.
+        if self.pending.is_none() {
+            self.pending = Some((ping_id, now));
+        }
+        self.next_ping = now + interval;
+        (interval.as_secs() as i32).saturating_add(NO_PING_DISCONNECT_MARGIN)
+    }
+
+    pub(crate) fn on_pong(&mut self, ping_id: i64, now: Instant) {
+        if let Some((id, sent)) = self.pending {
+            if id == ping_id {
+                self.pending = None;
+                let mut latency = self.latency.lock().unwrap();
+                *latency = Some(Latency::record(*latency, now - sent));
+            }
+        }
+    }
+
+    pub(crate) fn on_read(&mut self, now: Instant) {
+        self.last_read = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_dead_connection() {
+        let mut keepalive = KeepAlive::new();
+        keepalive.configure(Some(Duration::from_secs(10)), Duration::from_secs(5));
+        let start = Instant::now();
+        keepalive.on_read(start);
+
+        assert_eq!(keepalive.due(start), Due::Nothing);
+        assert_eq!(keepalive.due(start + Duration::from_secs(10)), Due::Ping);
+
+        let sent = start + Duration::from_secs(10);
+        assert_eq!(keepalive.on_ping(1, sent), 25);
+        assert_eq!(keepalive.deadline(), Some(sent + Duration::from_secs(5)));
+        assert_eq!(keepalive.due(sent + Duration::from_secs(5)), Due::Dead);
+
+        // Receiving anything else means the connection is still alive.
+        keepalive.on_read(sent + Duration::from_secs(4));
+        assert_eq!(keepalive.due(sent + Duration::from_secs(5)), Due::Nothing);
+
+        keepalive.on_pong(1, sent + Duration::from_millis(200));
+        assert_eq!(keepalive.due(sent + Duration::from_secs(9)), Due::Nothing);
+        assert_eq!(
+            *keepalive.latency.lock().unwrap(),
+            Some(Latency {
+                last: Duration::from_millis(200),
+                average: Duration::from_millis(200),
+            })
+        );
+    }
+
+    #[test]
+    fn check_disabled_pings() {
+        let mut keepalive = KeepAlive::new();
+        keepalive.configure(None, Duration::from_secs(5));
+        assert_eq!(keepalive.deadline(), None);
+        assert_eq!(
+            keepalive.due(Instant::now() + Duration::from_secs(3600)),
+            Due::Nothing
+        );
+    }
+
+    #[test]
+    fn check_latency_average() {
+        let first = Latency::record(None, Duration::from_millis(100));
+        let second = Latency::record(Some(first), Duration::from_millis(900));
+        assert_eq!(second.last, Duration::from_millis(900));
+        assert_eq!(second.average, Duration::from_millis(200));
+    }
+}
@@ -8,43 +8,59 @@
 
 #![deny(unsafe_code)]
 
+#[cfg(all(target_arch = "wasm32", target_os = "unknown", not(feature = "wasm")))]
+compile_error!("the `wasm` feature must be enabled to run in the browser");
+
 mod errors;
+mod fake_tls;
+mod instrumentation;
+mod keepalive;
 mod reconnection;
+mod time;
+#[cfg(feature = "wasm")]
+mod websocket;
 
 pub use crate::reconnection::*;
 pub use errors::{AuthorizationError, InvocationError, ReadError, RpcError};
-use futures_util::future::{pending, select, Either};
+pub use fake_tls::FakeTlsConnector;
+use futures_util::future::{pending, select, BoxFuture, Either};
 use grammers_crypto::DequeBuffer;
 use grammers_mtproto::mtp::{
     self, BadMessage, Deserialization, DeserializationFailure, Mtp, RpcResult, RpcResultError,
 };
-use grammers_mtproto::transport::{self, Transport};
+use grammers_mtproto::transport::{self, Transport, UnpackedOffset};
 use grammers_mtproto::{authentication, MsgId};
-use grammers_tl_types::{self as tl, Deserializable, RemoteCall};
+use grammers_tl_types::{self as tl, Deserializable, Identifiable, RemoteCall};
+pub use instrumentation::Instrumentation;
+pub use keepalive::Latency;
+use keepalive::{Due, KeepAlive};
 use log::{debug, error, info, trace, warn};
 use std::io;
 use std::io::Error;
+use std::net::SocketAddr;
 use std::ops::ControlFlow;
 use std::pin::pin;
-use std::sync::atomic::{AtomicI64, Ordering};
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicI32, AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use time::{sleep_until, Instant, SystemTime};
 use tl::Serializable;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::tcp::{ReadHalf, WriteHalf};
-use tokio::net::TcpStream;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use tokio::net::{
+    tcp::{ReadHalf, WriteHalf},
+    TcpStream,
+};
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
 use tokio::sync::oneshot::error::TryRecvError;
-use tokio::time::{sleep_until, Duration, Instant};
+#[cfg(feature = "wasm")]
+pub use websocket::WebSocketConnector;
 
 #[cfg(feature = "proxy")]
 use {
-    std::io::ErrorKind,
-    std::net::{IpAddr, SocketAddr},
-    tokio_socks::tcp::Socks5Stream,
-    trust_dns_resolver::config::{ResolverConfig, ResolverOpts},
-    trust_dns_resolver::AsyncResolver,
-    url::Host,
+    percent_encoding::percent_decode_str, std::io::ErrorKind, std::net::IpAddr,
+    tokio_socks::tcp::Socks5Stream, url::Host,
 };
 
 /// The maximum data that we're willing to send or receive at once.
@@ -64,18 +80,6 @@ const LEADING_BUFFER_SPACE: usize = mtp::MAX_TRANSPORT_HEADER_LEN
     + mtp::PLAIN_PACKET_HEADER_LEN
     + mtp::MESSAGE_CONTAINER_HEADER_LEN;
 
-/// Every how often are pings sent?
-const PING_DELAY: Duration = Duration::from_secs(60);
-
-/// After how many seconds should the server close the connection when we send a ping?
-///
-/// What this value essentially means is that we have `NO_PING_DISCONNECT - PING_DELAY` seconds
-/// to keep sending pings, or the server will close the connection.
-///
-/// Pings ensure the connection is kept active, and the delayed disconnect ensures the messages
-/// are getting through consistently enough.
-const NO_PING_DISCONNECT: i32 = 75;
-
 /// Generate a "random" ping ID.
 pub(crate) fn generate_random_id() -> i64 {
     static LAST_ID: AtomicI64 = AtomicI64::new(0);
@@ -94,22 +98,113 @@ pub(crate) fn generate_random_id() -> i64 {
     LAST_ID.fetch_add(1, Ordering::SeqCst)
 }
 
+/// A stream of bytes through which the sender can talk to Telegram.
+///
+/// This is implemented for any type that can be read from and written to asynchronously, such
+/// as a TLS stream or one end of [`tokio::io::duplex`].
+pub trait Stream: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<S: AsyncRead + AsyncWrite + Send + Unpin> Stream for S {}
+
+/// Opens the [`Stream`]s used by the sender, both when first connecting and when reconnecting.
+///
+/// This can be used to reach Telegram through a custom tunnel, or to talk to an in-memory server
+/// during tests.
+pub trait Connector: Send + Sync {
+    /// Open a new stream to the server at the given address.
+    fn connect(&self, addr: SocketAddr) -> BoxFuture<'_, io::Result<Box<dyn Stream>>>;
+}
+
 pub enum NetStream {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
     Tcp(TcpStream),
     #[cfg(feature = "proxy")]
     ProxySocks5(Socks5Stream<TcpStream>),
+    Custom(
+        tokio::io::ReadHalf<Box<dyn Stream>>,
+        tokio::io::WriteHalf<Box<dyn Stream>>,
+    ),
+}
+
+enum NetReadHalf<'a> {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    Tcp(ReadHalf<'a>),
+    Custom(&'a mut tokio::io::ReadHalf<Box<dyn Stream>>),
+}
+
+enum NetWriteHalf<'a> {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    Tcp(WriteHalf<'a>),
+    Custom(&'a mut tokio::io::WriteHalf<Box<dyn Stream>>),
 }
 
 impl NetStream {
-    fn split(&mut self) -> (ReadHalf, WriteHalf) {
+    fn custom(stream: Box<dyn Stream>) -> Self {
+        let (reader, writer) = tokio::io::split(stream);
+        Self::Custom(reader, writer)
+    }
+
+    fn split(&mut self) -> (NetReadHalf<'_>, NetWriteHalf<'_>) {
         match self {
-            Self::Tcp(stream) => stream.split(),
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            Self::Tcp(stream) => {
+                let (reader, writer) = stream.split();
+                (NetReadHalf::Tcp(reader), NetWriteHalf::Tcp(writer))
+            }
             #[cfg(feature = "proxy")]
-            Self::ProxySocks5(stream) => stream.split(),
+            Self::ProxySocks5(stream) => {
+                let (reader, writer) = stream.split();
+                (NetReadHalf::Tcp(reader), NetWriteHalf::Tcp(writer))
+            }
+            Self::Custom(reader, writer) => {
+                (NetReadHalf::Custom(reader), NetWriteHalf::Custom(writer))
+            }
+        }
+    }
+
+    async fn shutdown(&mut self) -> io::Result<()> {
+        match self {
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            Self::Tcp(stream) => stream.shutdown().await,
+            #[cfg(feature = "proxy")]
+            Self::ProxySocks5(stream) => stream.shutdown().await,
+            Self::Custom(_, writer) => writer.shutdown().await,
+        }
+    }
+}
+
+impl NetReadHalf<'_> {
+    async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            Self::Tcp(reader) => reader.read(buf).await,
+            Self::Custom(reader) => reader.read(buf).await,
         }
     }
 }
 
+impl NetWriteHalf<'_> {
+    async fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            Self::Tcp(writer) => writer.write(buf).await,
+            Self::Custom(writer) => writer.write(buf).await,
+        }
+    }
+}
+
+/// Requests whose effects would be repeated if they were processed twice.
+///
+/// The server is asked to quickly acknowledge the packets containing these, so that they're not
+/// sent again after reconnecting if they were received.
+const NON_IDEMPOTENT_REQUESTS: [u32; 5] = [
+    tl::functions::messages::SendMessage::CONSTRUCTOR_ID,
+    tl::functions::messages::SendMedia::CONSTRUCTOR_ID,
+    tl::functions::messages::SendMultiMedia::CONSTRUCTOR_ID,
+    tl::functions::messages::ForwardMessages::CONSTRUCTOR_ID,
+    tl::functions::messages::SendInlineBotResult::CONSTRUCTOR_ID,
+];
+
 // Manages enqueuing requests, matching them to their response, and IO.
 
 pub struct Sender<T: Transport, M: Mtp> {
@@ -119,9 +214,17 @@ pub struct Sender<T: Transport, M: Mtp> {
     addr: std::net::SocketAddr,
     #[cfg(feature = "proxy")]
     proxy_url: Option<String>,
+    connector: Option<Arc<dyn Connector>>,
     requests: Vec<Request>,
     request_rx: mpsc::UnboundedReceiver<Request>,
-    next_ping: Instant,
+    keepalive: KeepAlive,
+    instrumentation: Option<Arc<dyn Instrumentation>>,
+    // The amount of requests in flight last reported to the instrumentation.
+    in_flight: usize,
+    // How long to hold new requests for, so that more can be sent along in the same container.
+    batch_delay: Option<Duration>,
+    // The time offset of the mtp, shared with the enqueuer.
+    time_offset: Arc<AtomicI32>,
     reconnection_policy: &'static dyn ReconnectionPolicy,
 
     // Transport-level buffers and positions
@@ -135,6 +238,15 @@ struct Request {
     body: Vec<u8>,
     state: RequestState,
     result: oneshot::Sender<Result<Vec<u8>, InvocationError>>,
+    // Internal requests, such as pings, are sent even if nobody waits for their result.
+    cancellable: bool,
+    // When the request was enqueued, and then when it was last written to the network, to
+    // measure how long its response takes.
+    sent_at: Instant,
+    // The quick ack token of the packet the request was last serialized in, if one was asked.
+    quick_ack: Option<u32>,
+    // Whether the server acknowledged the receipt of the packet through a quick ack.
+    received: bool,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -149,7 +261,22 @@ enum RequestState {
     Sent(MsgIdPair),
 }
 
-pub struct Enqueuer(mpsc::UnboundedSender<Request>);
+pub struct Enqueuer {
+    tx: mpsc::UnboundedSender<Request>,
+    latency: Arc<Mutex<Option<Latency>>>,
+    time_offset: Arc<AtomicI32>,
+}
+
+impl Request {
+    fn constructor_id(&self) -> u32 {
+        u32::from_le_bytes([self.body[0], self.body[1], self.body[2], self.body[3]])
+    }
+
+    /// Whether sending the request twice could repeat its effects.
+    fn is_idempotent(&self) -> bool {
+        !NON_IDEMPOTENT_REQUESTS.contains(&self.constructor_id())
+    }
+}
 
 impl MsgIdPair {
     fn new(msg_id: MsgId) -> Self {
@@ -176,19 +303,37 @@ impl Enqueuer {
         );
 
         let (tx, rx) = oneshot::channel();
-        if let Err(err) = self.0.send(Request {
+        if let Err(err) = self.tx.send(Request {
             body,
             state: RequestState::NotSerialized,
             result: tx,
+            cancellable: true,
+            sent_at: Instant::now(),
+            quick_ack: None,
+            received: false,
         }) {
             err.0.result.send(Err(InvocationError::Dropped)).unwrap();
         }
         rx
     }
+
+    /// The round-trip time to the server, as measured by the keep-alive pings.
+    ///
+    /// Returns `None` until the first ping is answered.
+    pub fn latency(&self) -> Option<Latency> {
+        *self.latency.lock().unwrap()
+    }
+
+    /// The difference, in seconds, between the server's time and the system time.
+    ///
+    /// It is corrected as soon as the messages from the server show the system clock is off.
+    pub fn time_offset(&self) -> i32 {
+        self.time_offset.load(Ordering::Relaxed)
+    }
 }
 
 impl<T: Transport, M: Mtp> Sender<T, M> {
-    async fn connect<'a>(
+    async fn connect(
         transport: T,
         mtp: M,
         addr: std::net::SocketAddr,
@@ -196,6 +341,9 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
     ) -> Result<(Self, Enqueuer), io::Error> {
         let stream = connect_stream(&addr).await?;
         let (tx, rx) = mpsc::unbounded_channel();
+        let keepalive = KeepAlive::new();
+        let latency = keepalive.latency_handle();
+        let time_offset = Arc::new(AtomicI32::new(mtp.time_offset()));
         Ok((
             Self {
                 stream,
@@ -204,9 +352,14 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
                 addr,
                 #[cfg(feature = "proxy")]
                 proxy_url: None,
+                connector: None,
                 requests: vec![],
                 request_rx: rx,
-                next_ping: Instant::now() + PING_DELAY,
+                keepalive,
+                instrumentation: None,
+                in_flight: 0,
+                batch_delay: None,
+                time_offset: Arc::clone(&time_offset),
                 reconnection_policy,
 
                 read_buffer: vec![0; MAXIMUM_DATA],
@@ -214,12 +367,16 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
                 write_buffer: DequeBuffer::with_capacity(MAXIMUM_DATA, LEADING_BUFFER_SPACE),
                 write_head: 0,
             },
-            Enqueuer(tx),
+            Enqueuer {
+                tx,
+                latency,
+                time_offset,
+            },
         ))
     }
 
     #[cfg(feature = "proxy")]
-    async fn connect_via_proxy<'a>(
+    async fn connect_via_proxy(
         transport: T,
         mtp: M,
         addr: SocketAddr,
@@ -230,6 +387,9 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
 
         let stream = connect_proxy_stream(&addr, proxy_url).await?;
         let (tx, rx) = mpsc::unbounded_channel();
+        let keepalive = KeepAlive::new();
+        let latency = keepalive.latency_handle();
+        let time_offset = Arc::new(AtomicI32::new(mtp.time_offset()));
         Ok((
             Self {
                 stream,
@@ -237,9 +397,59 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
                 mtp,
                 addr,
                 proxy_url: Some(proxy_url.to_string()),
+                connector: None,
+                requests: vec![],
+                request_rx: rx,
+                keepalive,
+                instrumentation: None,
+                in_flight: 0,
+                batch_delay: None,
+                time_offset: Arc::clone(&time_offset),
+                reconnection_policy,
+
+                read_buffer: vec![0; MAXIMUM_DATA],
+                read_tail: 0,
+                write_buffer: DequeBuffer::with_capacity(MAXIMUM_DATA, LEADING_BUFFER_SPACE),
+                write_head: 0,
+            },
+            Enqueuer {
+                tx,
+                latency,
+                time_offset,
+            },
+        ))
+    }
+
+    async fn connect_via_connector(
+        transport: T,
+        mtp: M,
+        addr: SocketAddr,
+        connector: Arc<dyn Connector>,
+        reconnection_policy: &'static dyn ReconnectionPolicy,
+    ) -> Result<(Self, Enqueuer), io::Error> {
+        info!("connecting through custom connector...");
+
+        let stream = NetStream::custom(connector.connect(addr).await?);
+        let (tx, rx) = mpsc::unbounded_channel();
+        let keepalive = KeepAlive::new();
+        let latency = keepalive.latency_handle();
+        let time_offset = Arc::new(AtomicI32::new(mtp.time_offset()));
+        Ok((
+            Self {
+                stream,
+                transport,
+                mtp,
+                addr,
+                #[cfg(feature = "proxy")]
+                proxy_url: None,
+                connector: Some(connector),
                 requests: vec![],
                 request_rx: rx,
-                next_ping: Instant::now() + PING_DELAY,
+                keepalive,
+                instrumentation: None,
+                in_flight: 0,
+                batch_delay: None,
+                time_offset: Arc::clone(&time_offset),
                 reconnection_policy,
 
                 read_buffer: vec![0; MAXIMUM_DATA],
@@ -247,7 +457,11 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
                 write_buffer: DequeBuffer::with_capacity(MAXIMUM_DATA, LEADING_BUFFER_SPACE),
                 write_head: 0,
             },
-            Enqueuer(tx),
+            Enqueuer {
+                tx,
+                latency,
+                time_offset,
+            },
         ))
     }
 
@@ -278,10 +492,52 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
             body,
             state: RequestState::NotSerialized,
             result: tx,
+            cancellable: true,
+            sent_at: Instant::now(),
+            quick_ack: None,
+            received: false,
         });
         rx
     }
 
+    /// Like `enqueue_body`, but for requests whose result is not needed.
+    fn enqueue_internal(&mut self, body: Vec<u8>) {
+        drop(self.enqueue_body(body));
+        if let Some(request) = self.requests.last_mut() {
+            request.cancellable = false;
+        }
+    }
+
+    /// Forget about the requests whose result is no longer wanted, because whoever made them
+    /// stopped waiting. The server is asked to not send the result of those already sent.
+    fn drop_cancelled_requests(&mut self) {
+        let mut sent = Vec::new();
+        self.requests.retain(|request| {
+            if !request.cancellable || !request.result.is_closed() {
+                return true;
+            }
+            match request.state {
+                RequestState::NotSerialized => false,
+                // Still waiting in the write buffer, so it can only be cancelled once sent.
+                RequestState::Serialized(_) => true,
+                RequestState::Sent(pair) => {
+                    sent.push(pair.msg_id);
+                    false
+                }
+            }
+        });
+
+        for msg_id in sent {
+            debug!("cancelling request {:?}", msg_id);
+            self.enqueue_internal(
+                tl::functions::RpcDropAnswer {
+                    req_msg_id: msg_id.value(),
+                }
+                .to_bytes(),
+            );
+        }
+    }
+
     async fn step_until_receive(
         &mut self,
         mut rx: oneshot::Receiver<Result<Vec<u8>, InvocationError>>,
@@ -298,6 +554,81 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
         }
     }
 
+    /// Configure the keep-alive pings sent through the connection.
+    ///
+    /// A ping is sent every `interval`. If it's not answered (and nothing else is received)
+    /// within `timeout`, the connection is considered dead, and the reconnection policy is used
+    /// to connect again. Pings can be disabled entirely with an `interval` of `None`, in which
+    /// case dead connections are only detected when the operating system notices.
+    pub fn set_keepalive(&mut self, interval: Option<Duration>, timeout: Duration) {
+        self.keepalive.configure(interval, timeout);
+    }
+
+    /// Report the network operations performed by the sender to the given hooks.
+    pub fn set_instrumentation(&mut self, instrumentation: Option<Arc<dyn Instrumentation>>) {
+        self.instrumentation = instrumentation;
+    }
+
+    /// Hold new requests for up to `delay` before sending them, so that requests made in quick
+    /// succession are sent together in a single container.
+    ///
+    /// Requests enqueued by the time the sender is stepped are always sent together (as long as
+    /// they fit in a container), so this is only useful when they are made a bit further apart.
+    /// Waiting slightly longer can save writes on high-latency links, at the cost of delaying
+    /// every request. By default, there is no delay.
+    pub fn set_batch_delay(&mut self, delay: Option<Duration>) {
+        self.batch_delay = delay;
+    }
+
+    /// The instant at which [`Sender::step`] will wake up on its own if no network activity
+    /// occurs, which is when the next keep-alive ping is due, when the connection should be
+    /// considered dead if the last one was not answered, when held requests must be sent, or
+    /// when pending acknowledgements must be sent, whichever comes first.
+    ///
+    /// This lets callers that drive the sender from their own event loop know when `step` needs
+    /// to be polled again. There is no such instant if pings are disabled and nothing is pending.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        [
+            self.keepalive.deadline(),
+            self.batch_deadline(),
+            self.mtp.ack_deadline().map(time::from_mtp),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
+
+    /// Gracefully close the connection.
+    ///
+    /// Requests which have not received a response yet fail with [`InvocationError::Dropped`].
+    pub async fn disconnect(&mut self) -> io::Result<()> {
+        self.request_rx.close();
+        while let Ok(request) = self.request_rx.try_recv() {
+            self.requests.push(request);
+        }
+        for request in self.requests.drain(..) {
+            drop(request.result.send(Err(InvocationError::Dropped)));
+        }
+
+        // Acknowledge everything received, so that the server doesn't keep it around to resend.
+        self.mtp.flush_acks();
+        self.try_fill_write();
+        while !self.write_buffer.is_empty() {
+            let (_, mut writer) = self.stream.split();
+            match writer.write(&self.write_buffer[self.write_head..]).await {
+                Ok(0) => break,
+                Ok(n) => self.on_net_write(n),
+                Err(e) => {
+                    info!("failed to send the pending acknowledgements: {}", e);
+                    break;
+                }
+            }
+            self.try_fill_write();
+        }
+
+        self.stream.shutdown().await
+    }
+
     /// Step network events, writing and reading at the same time.
     ///
     /// Updates received during this step, if any, are returned.
@@ -309,6 +640,12 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
             Write(io::Result<usize>),
         }
 
+        // Take every request enqueued so far, so that they can be sent in the same container.
+        while let Ok(request) = self.request_rx.try_recv() {
+            self.requests.push(request);
+        }
+
+        self.drop_cancelled_requests();
         self.try_fill_write();
         let write_len = self.write_buffer.len() - self.write_head;
         trace!(
@@ -316,9 +653,15 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
             write_len
         );
 
+        let deadline = self.next_deadline();
         let (mut reader, mut writer) = self.stream.split();
         let sel = {
-            let sleep = pin!(async { sleep_until(self.next_ping).await });
+            let sleep = pin!(async {
+                match deadline {
+                    Some(deadline) => sleep_until(deadline).await,
+                    None => pending().await,
+                }
+            });
             let recv_req = pin!(async { self.request_rx.recv().await });
             let recv_data =
                 pin!(async { reader.read(&mut self.read_buffer[self.read_tail..]).await });
@@ -348,16 +691,34 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
                 self.on_net_write(n);
                 Vec::new()
             }),
-            Sel::Sleep => {
-                self.on_ping_timeout();
-                Ok(Vec::new())
-            }
+            Sel::Sleep => match self.keepalive.due(Instant::now()) {
+                Due::Nothing => Ok(Vec::new()),
+                Due::Ping => {
+                    self.on_ping_timeout();
+                    Ok(Vec::new())
+                }
+                Due::Dead => {
+                    warn!("keepalive ping was not answered in time; the connection is dead");
+                    Err(ReadError::Io(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "keepalive ping timed out",
+                    )))
+                }
+            },
         };
 
-        match res {
+        let res = match res {
             Ok(ok) => Ok(ok),
             Err(err) => self.on_error(err).await,
+        };
+
+        if let Some(instrumentation) = &self.instrumentation {
+            if self.in_flight != self.requests.len() {
+                self.in_flight = self.requests.len();
+                instrumentation.on_requests_in_flight(self.in_flight);
+            }
         }
+        res
     }
 
     #[allow(unused_variables)]
@@ -365,14 +726,20 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
         let mut attempts = 0;
         loop {
             #[cfg(feature = "proxy")]
-            let res = if self.proxy_url.is_some() {
-                connect_proxy_stream(&self.addr, self.proxy_url.as_ref().unwrap()).await
+            let res = if let Some(connector) = self.connector.as_ref() {
+                connector.connect(self.addr).await.map(NetStream::custom)
+            } else if let Some(proxy_url) = self.proxy_url.as_ref() {
+                connect_proxy_stream(&self.addr, proxy_url).await
             } else {
                 connect_stream(&self.addr).await
             };
 
             #[cfg(not(feature = "proxy"))]
-            let res = connect_stream(&self.addr).await;
+            let res = if let Some(connector) = self.connector.as_ref() {
+                connector.connect(self.addr).await.map(NetStream::custom)
+            } else {
+                connect_stream(&self.addr).await
+            };
 
             match res {
                 Ok(result) => {
@@ -381,12 +748,14 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
                         attempts
                     );
                     self.stream = result;
+                    if let Some(instrumentation) = &self.instrumentation {
+                        instrumentation.on_reconnect();
+                    }
                     return Ok(());
                 }
                 Err(e) => {
                     attempts += 1;
                     log::warn!("auto-reconnect failed {} time(s): {}", attempts, e);
-                    tokio::time::sleep(Duration::from_secs(1)).await;
 
                     match self.reconnection_policy.should_retry(attempts) {
                         ControlFlow::Break(_) => {
@@ -397,7 +766,7 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
                             return Err(e);
                         }
                         ControlFlow::Continue(duration) => {
-                            tokio::time::sleep(duration).await;
+                            time::sleep(duration).await;
                         }
                     }
                 }
@@ -405,11 +774,27 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
         }
     }
 
+    /// The instant at which the requests held by the batch delay must be sent, if any is held.
+    fn batch_deadline(&self) -> Option<Instant> {
+        let delay = self.batch_delay?;
+        self.requests
+            .iter()
+            .filter(|r| matches!(r.state, RequestState::NotSerialized))
+            .map(|r| r.sent_at + delay)
+            .min()
+    }
+
     /// Setup the write buffer for the transport, unless a write is already pending.
     fn try_fill_write(&mut self) {
         if !self.write_buffer.is_empty() {
             return;
         }
+        if self
+            .batch_deadline()
+            .is_some_and(|deadline| deadline > Instant::now())
+        {
+            return;
+        }
 
         // TODO add a test to make sure we only ever send the same request once
         for request in self
@@ -436,21 +821,39 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
                 // Nasty bugs that take ~2h to find occur otherwise!
                 // (e.g. infinite loops leading to transport flood.)
                 request.state = RequestState::Serialized(MsgIdPair::new(msg_id));
+                request.quick_ack = None;
+                request.received = false;
             } else {
                 break;
             }
         }
 
         if let Some(container_msg_id) = self.mtp.finalize(&mut self.write_buffer) {
+            let mut wants_quick_ack = false;
             for request in self.requests.iter_mut() {
                 match request.state {
-                    RequestState::Serialized(mut pair) => {
+                    RequestState::Serialized(ref mut pair) => {
                         pair.container_msg_id = container_msg_id;
+                        wants_quick_ack |= !request.is_idempotent();
                     }
                     RequestState::NotSerialized | RequestState::Sent(..) => {}
                 }
             }
-            self.transport.pack(&mut self.write_buffer)
+
+            let quick_ack = if !wants_quick_ack {
+                self.transport.pack(&mut self.write_buffer);
+                None
+            } else if self.transport.pack_quick_ack(&mut self.write_buffer) {
+                self.mtp.quick_ack_token()
+            } else {
+                None
+            };
+            if quick_ack.is_some() {
+                self.requests
+                    .iter_mut()
+                    .filter(|r| matches!(r.state, RequestState::Serialized(_)))
+                    .for_each(|r| r.quick_ack = quick_ack);
+            }
         }
     }
 
@@ -466,6 +869,10 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
         }
 
         self.read_tail += n;
+        self.keepalive.on_read(Instant::now());
+        if let Some(instrumentation) = &self.instrumentation {
+            instrumentation.on_bytes_received(n);
+        }
         trace!("read {} bytes from the network", n);
         trace!("trying to unpack buffer of {} bytes...", self.read_tail);
 
@@ -476,14 +883,24 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
         while next_offset != self.read_tail {
             match self
                 .transport
-                .unpack(&self.read_buffer[next_offset..self.read_tail])
+                .unpack(&mut self.read_buffer[next_offset..self.read_tail])
             {
+                Ok(UnpackedOffset {
+                    quick_ack: Some(token),
+                    next_offset: len,
+                    ..
+                }) => {
+                    self.process_quick_ack(token);
+                    next_offset += len;
+                }
                 Ok(offset) => {
                     debug!("deserializing valid transport packet...");
                     let result = self.mtp.deserialize(
                         &self.read_buffer[next_offset..][offset.data_start..offset.data_end],
                     )?;
 
+                    self.time_offset
+                        .store(self.mtp.time_offset(), Ordering::Relaxed);
                     self.process_mtp_buffer(result, &mut updates);
                     next_offset += offset.next_offset;
                 }
@@ -501,6 +918,9 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
     /// Handle `n` more written bytes being ready to process by the transport.
     fn on_net_write(&mut self, n: usize) {
         self.write_head += n;
+        if let Some(instrumentation) = &self.instrumentation {
+            instrumentation.on_bytes_sent(n);
+        }
         trace!(
             "written {} bytes to the network ({}/{})",
             n,
@@ -514,12 +934,17 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
 
         self.write_buffer.clear();
         self.write_head = 0;
+        let now = Instant::now();
         for req in self.requests.iter_mut() {
             match req.state {
                 RequestState::NotSerialized | RequestState::Sent(_) => {}
                 RequestState::Serialized(pair) => {
                     debug!("sent request with {:?}", pair);
                     req.state = RequestState::Sent(pair);
+                    req.sent_at = now;
+                    if let Some(instrumentation) = &self.instrumentation {
+                        instrumentation.on_request_sent(req.constructor_id(), req.body.len());
+                    }
                 }
             }
         }
@@ -529,16 +954,14 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
     fn on_ping_timeout(&mut self) {
         let ping_id = generate_random_id();
         debug!("enqueueing keepalive ping {}", ping_id);
-        drop(
-            self.enqueue_body(
-                tl::functions::PingDelayDisconnect {
-                    ping_id,
-                    disconnect_delay: NO_PING_DISCONNECT,
-                }
-                .to_bytes(),
-            ),
+        let disconnect_delay = self.keepalive.on_ping(ping_id, Instant::now());
+        self.enqueue_internal(
+            tl::functions::PingDelayDisconnect {
+                ping_id,
+                disconnect_delay,
+            }
+            .to_bytes(),
         );
-        self.next_ping = Instant::now() + PING_DELAY;
     }
 
     /// Handle errors that occured while performing I/O.
@@ -567,12 +990,12 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
             {
                 match self.try_connect().await {
                     Ok(_) => {
-                        // Reconnect success means everything can be retried.
-                        self.requests
-                            .iter_mut()
-                            .for_each(|r| r.state = RequestState::NotSerialized);
+                        self.prepare_resend();
 
-                        return Ok(Vec::new());
+                        // Updates may have been missed while disconnected, which is the same as
+                        // being told that there were too many to be sent.
+                        self.keepalive.reset();
+                        return Ok(vec![tl::enums::Updates::TooLong]);
                     }
                     Err(e) => ReadError::from(e),
                 }
@@ -593,6 +1016,45 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
         Err(error)
     }
 
+    /// Prepare the requests to be sent again after reconnecting, failing those that can't be.
+    fn prepare_resend(&mut self) {
+        // Requests which may have reached the server are only retried if allowed, and never if
+        // they're known to have reached it and doing so could repeat their effects.
+        let retry_sent = self.reconnection_policy.retry_sent_requests();
+        let (requests, failed): (Vec<_>, Vec<_>) = self.requests.drain(..).partition(|r| {
+            matches!(r.state, RequestState::NotSerialized)
+                || (retry_sent && (r.is_idempotent() || !r.received))
+        });
+        self.requests = requests;
+        self.requests
+            .iter_mut()
+            .for_each(|r| r.state = RequestState::NotSerialized);
+
+        if !failed.is_empty() {
+            log::warn!(
+                "dropping {} request(s) sent before reconnecting",
+                failed.len()
+            );
+        }
+        failed.into_iter().for_each(|r| {
+            let error = if r.received && !r.is_idempotent() {
+                InvocationError::Unanswered
+            } else {
+                InvocationError::Dropped
+            };
+            drop(r.result.send(Err(error)));
+        });
+    }
+
+    /// Process a quick ack, confirming the receipt of the requests sent along with its token.
+    fn process_quick_ack(&mut self, token: u32) {
+        debug!("got quick ack {:08x}", token);
+        self.requests
+            .iter_mut()
+            .filter(|r| r.quick_ack == Some(token))
+            .for_each(|r| r.received = true);
+    }
+
     /// Process the result of deserializing an MTP buffer.
     fn process_mtp_buffer(
         &mut self,
@@ -651,8 +1113,16 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
         }
     }
 
+    /// Report the response to a request to the instrumentation.
+    fn instrument_response(&self, req: &Request) {
+        if let Some(instrumentation) = &self.instrumentation {
+            instrumentation.on_response(req.constructor_id(), req.sent_at.elapsed());
+        }
+    }
+
     fn process_result(&mut self, result: RpcResult) {
         if let Some(req) = self.pop_request(result.msg_id) {
+            self.instrument_response(&req);
             let x = result.body;
             assert!(x.len() >= 4);
             let res_id = u32::from_le_bytes([x[0], x[1], x[2], x[3]]);
@@ -662,6 +1132,11 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
                 tl::name_for_id(res_id),
                 result.msg_id
             );
+            if res_id == tl::types::Pong::CONSTRUCTOR_ID {
+                if let Ok(tl::enums::Pong::Pong(pong)) = tl::enums::Pong::from_bytes(&x) {
+                    self.keepalive.on_pong(pong.ping_id, Instant::now());
+                }
+            }
             drop(req.result.send(Ok(x)));
         } else {
             info!(
@@ -673,6 +1148,7 @@ impl<T: Transport, M: Mtp> Sender<T, M> {
 
     fn process_error(&mut self, error: RpcResultError) {
         if let Some(req) = self.pop_request(error.msg_id) {
+            self.instrument_response(&req);
             debug!("got rpc error {:?}", error.error);
             let x = req.body.as_slice();
             drop(
@@ -773,6 +1249,36 @@ impl<T: Transport> Sender<T, mtp::Encrypted> {
     pub fn auth_key(&self) -> [u8; 256] {
         self.mtp.auth_key()
     }
+
+    /// Change how long acknowledgements for the messages received may be delayed, waiting for
+    /// more of them or for a request to go along with.
+    pub fn set_ack_delay(&mut self, delay: Duration) {
+        self.mtp.set_ack_delay(delay);
+    }
+
+    /// The server salts known to be valid, which can be stored and restored with
+    /// [`Sender::set_salts`] when connecting again.
+    pub fn salts(&self) -> Vec<tl::types::FutureSalt> {
+        self.mtp.salts()
+    }
+
+    /// Use previously-stored salts, so that the first requests don't fail with a bad salt.
+    pub fn set_salts(&mut self, salts: Vec<tl::types::FutureSalt>) {
+        self.mtp.set_salts(salts);
+    }
+
+    /// Use a previously-known time offset to the server (for example, the one of a different
+    /// connection), so that the first requests don't fail because the system clock is off.
+    pub fn set_time_offset(&mut self, offset: i32) {
+        self.mtp.set_time_offset(offset);
+        self.time_offset.store(offset, Ordering::Relaxed);
+    }
+
+    /// Change the minimum size (in bytes) requests must have to be sent compressed, or disable
+    /// compression with `None`.
+    pub fn set_compression_threshold(&mut self, threshold: Option<usize>) {
+        self.mtp.set_compression_threshold(threshold);
+    }
 }
 
 pub async fn connect<T: Transport>(
@@ -785,7 +1291,7 @@ pub async fn connect<T: Transport>(
 }
 
 #[cfg(feature = "proxy")]
-pub async fn connect_via_proxy<'a, T: Transport>(
+pub async fn connect_via_proxy<T: Transport>(
     transport: T,
     addr: std::net::SocketAddr,
     proxy_url: &str,
@@ -796,11 +1302,35 @@ pub async fn connect_via_proxy<'a, T: Transport>(
     generate_auth_key(sender, enqueuer).await
 }
 
+/// Like [`connect`], but the stream to the server is opened by the given [`Connector`].
+pub async fn connect_via_connector<T: Transport>(
+    transport: T,
+    addr: SocketAddr,
+    connector: Arc<dyn Connector>,
+    rc_policy: &'static dyn ReconnectionPolicy,
+) -> Result<(Sender<T, mtp::Encrypted>, Enqueuer), AuthorizationError> {
+    let (sender, enqueuer) =
+        Sender::connect_via_connector(transport, mtp::Plain::new(), addr, connector, rc_policy)
+            .await?;
+    generate_auth_key(sender, enqueuer).await
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 async fn connect_stream(addr: &std::net::SocketAddr) -> Result<NetStream, std::io::Error> {
     info!("connecting...");
     Ok(NetStream::Tcp(TcpStream::connect(addr).await?))
 }
 
+/// The browser cannot open TCP connections, so a [`Connector`] such as [`WebSocketConnector`]
+/// must be used instead.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+async fn connect_stream(_addr: &std::net::SocketAddr) -> Result<NetStream, std::io::Error> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "TCP connections are not available in the browser, a connector must be used",
+    ))
+}
+
 #[cfg(feature = "proxy")]
 async fn connect_proxy_stream(
     addr: &SocketAddr,
@@ -817,18 +1347,19 @@ async fn connect_proxy_stream(
         ErrorKind::NotFound,
         format!("proxy port is missing from url: {}", proxy_url),
     ))?;
-    let username = proxy.username();
-    let password = proxy.password().unwrap_or("");
+    // Credentials may contain characters which must be percent-encoded in the URL.
+    let username = percent_decode_str(proxy.username()).decode_utf8_lossy();
+    let password = percent_decode_str(proxy.password().unwrap_or("")).decode_utf8_lossy();
     let socks_addr = match host {
-        Host::Domain(domain) => {
-            let resolver = AsyncResolver::tokio(ResolverConfig::default(), ResolverOpts::default());
-            let response = resolver.lookup_ip(domain).await?;
-            let socks_ip_addr = response.into_iter().next().ok_or(io::Error::new(
+        // Use the system's resolver, which honors the local configuration (such as the hosts
+        // file), rather than querying third-party DNS servers.
+        Host::Domain(domain) => tokio::net::lookup_host((domain, port))
+            .await?
+            .next()
+            .ok_or(io::Error::new(
                 ErrorKind::NotFound,
                 format!("proxy host did not return any ip address: {}", domain),
-            ))?;
-            SocketAddr::new(socks_ip_addr, port)
-        }
+            ))?,
         Host::Ipv4(v4) => SocketAddr::new(IpAddr::from(v4), port),
         Host::Ipv6(v6) => SocketAddr::new(IpAddr::from(v6), port),
     };
@@ -844,7 +1375,7 @@ async fn connect_proxy_stream(
             } else {
                 Ok(NetStream::ProxySocks5(
                     tokio_socks::tcp::Socks5Stream::connect_with_password(
-                        socks_addr, addr, username, password,
+                        socks_addr, addr, &username, &password,
                     )
                     .await
                     .map_err(|err| io::Error::new(ErrorKind::ConnectionAborted, err))?,
@@ -881,6 +1412,7 @@ pub async fn generate_auth_key<T: Transport>(
         first_salt,
     } = authentication::create_key(data, &response)?;
     info!("authorization key generated successfully");
+    sender.time_offset.store(time_offset, Ordering::Relaxed);
 
     Ok((
         Sender {
@@ -892,7 +1424,11 @@ pub async fn generate_auth_key<T: Transport>(
                 .finish(auth_key),
             requests: sender.requests,
             request_rx: sender.request_rx,
-            next_ping: Instant::now() + PING_DELAY,
+            keepalive: sender.keepalive,
+            instrumentation: sender.instrumentation,
+            in_flight: sender.in_flight,
+            batch_delay: sender.batch_delay,
+            time_offset: sender.time_offset,
             read_buffer: sender.read_buffer,
             read_tail: sender.read_tail,
             write_buffer: sender.write_buffer,
@@ -900,6 +1436,7 @@ pub async fn generate_auth_key<T: Transport>(
             addr: sender.addr,
             #[cfg(feature = "proxy")]
             proxy_url: sender.proxy_url,
+            connector: sender.connector,
             reconnection_policy: sender.reconnection_policy,
         },
         enqueuer,
@@ -922,7 +1459,7 @@ pub async fn connect_with_auth<T: Transport>(
 }
 
 #[cfg(feature = "proxy")]
-pub async fn connect_via_proxy_with_auth<'a, T: Transport>(
+pub async fn connect_via_proxy_with_auth<T: Transport>(
     transport: T,
     addr: std::net::SocketAddr,
     auth_key: [u8; 256],
@@ -938,3 +1475,209 @@ pub async fn connect_via_proxy_with_auth<'a, T: Transport>(
     )
     .await
 }
+
+/// Like [`connect_with_auth`], but the stream to the server is opened by the given
+/// [`Connector`].
+pub async fn connect_via_connector_with_auth<T: Transport>(
+    transport: T,
+    addr: SocketAddr,
+    auth_key: [u8; 256],
+    connector: Arc<dyn Connector>,
+    rc_policy: &'static dyn ReconnectionPolicy,
+) -> Result<(Sender<T, mtp::Encrypted>, Enqueuer), io::Error> {
+    Sender::connect_via_connector(
+        transport,
+        mtp::Encrypted::build().finish(auth_key),
+        addr,
+        connector,
+        rc_policy,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grammers_mtproto::transport::Intermediate;
+    use std::sync::Mutex;
+    use tokio::io::DuplexStream;
+
+    /// Hands out a single in-memory stream, whose other end is kept by the test.
+    struct DuplexConnector(Mutex<Option<DuplexStream>>);
+
+    impl Connector for DuplexConnector {
+        fn connect(&self, _: SocketAddr) -> BoxFuture<'_, io::Result<Box<dyn Stream>>> {
+            let stream = self.0.lock().unwrap().take();
+            Box::pin(async move {
+                stream
+                    .map(|s| Box::new(s) as Box<dyn Stream>)
+                    .ok_or_else(|| io::Error::from(io::ErrorKind::ConnectionRefused))
+            })
+        }
+    }
+
+    #[test]
+    fn check_duplex_connector() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let (client, mut server) = tokio::io::duplex(MAXIMUM_DATA);
+            let connector = Arc::new(DuplexConnector(Mutex::new(Some(client))));
+            let (mut sender, _enqueuer) = Sender::connect_via_connector(
+                Intermediate::new(),
+                mtp::Plain::new(),
+                SocketAddr::from(([127, 0, 0, 1], 443)),
+                connector,
+                &NoReconnect,
+            )
+            .await
+            .unwrap();
+
+            let request = vec![1, 2, 3, 4];
+            let response = vec![5, 6, 7, 8];
+            let server = async {
+                // Intermediate tag, packet length, and the plain message.
+                let mut buf = [0; 4 + 4 + 20 + 4];
+                server.read_exact(&mut buf).await.unwrap();
+                assert_eq!(&buf[..4], &[0xee; 4]);
+                assert_eq!(&buf[4..8], &24u32.to_le_bytes());
+                assert_eq!(&buf[28..], &request[..]);
+
+                let mut reply = Vec::new();
+                reply.extend(24u32.to_le_bytes());
+                reply.extend(0i64.to_le_bytes());
+                reply.extend(1i64.to_le_bytes());
+                reply.extend(4i32.to_le_bytes());
+                reply.extend(&response);
+                server.write_all(&reply).await.unwrap();
+            };
+
+            let (result, ()) =
+                futures_util::future::join(sender.send(request.clone()), server).await;
+            assert_eq!(result.unwrap(), response);
+        });
+    }
+
+    #[test]
+    fn check_batch_delay() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let (client, _server) = tokio::io::duplex(MAXIMUM_DATA);
+            let connector = Arc::new(DuplexConnector(Mutex::new(Some(client))));
+            let (mut sender, enqueuer) = Sender::connect_via_connector(
+                Intermediate::new(),
+                mtp::Plain::new(),
+                SocketAddr::from(([127, 0, 0, 1], 443)),
+                connector,
+                &NoReconnect,
+            )
+            .await
+            .unwrap();
+
+            let _rx = enqueuer.enqueue(&tl::functions::Ping { ping_id: 1 });
+            while let Ok(request) = sender.request_rx.try_recv() {
+                sender.requests.push(request);
+            }
+
+            // The request is held until the delay passes.
+            sender.set_batch_delay(Some(Duration::from_secs(3600)));
+            sender.try_fill_write();
+            assert!(sender.write_buffer.is_empty());
+            assert!(sender.batch_deadline().is_some());
+
+            sender.set_batch_delay(None);
+            sender.try_fill_write();
+            assert!(!sender.write_buffer.is_empty());
+            assert_eq!(sender.batch_deadline(), None);
+        });
+    }
+
+    /// A plain MTP which pretends to know the quick ack token of the payloads.
+    struct QuickAckMtp(mtp::Plain);
+
+    const QUICK_ACK_TOKEN: u32 = 0x8000_1234;
+
+    impl Mtp for QuickAckMtp {
+        fn push(&mut self, buffer: &mut DequeBuffer<u8>, request: &[u8]) -> Option<MsgId> {
+            self.0.push(buffer, request)
+        }
+
+        fn finalize(&mut self, buffer: &mut DequeBuffer<u8>) -> Option<MsgId> {
+            self.0.finalize(buffer)
+        }
+
+        fn deserialize(
+            &mut self,
+            payload: &[u8],
+        ) -> Result<Vec<Deserialization>, mtp::DeserializeError> {
+            self.0.deserialize(payload)
+        }
+
+        fn reset(&mut self) {
+            self.0.reset()
+        }
+
+        fn quick_ack_token(&self) -> Option<u32> {
+            Some(QUICK_ACK_TOKEN)
+        }
+    }
+
+    #[test]
+    fn check_quick_acked_requests_are_not_resent() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let (client, _server) = tokio::io::duplex(MAXIMUM_DATA);
+            let connector = Arc::new(DuplexConnector(Mutex::new(Some(client))));
+            let (mut sender, _enqueuer) = Sender::connect_via_connector(
+                Intermediate::new(),
+                QuickAckMtp(mtp::Plain::new()),
+                SocketAddr::from(([127, 0, 0, 1], 443)),
+                connector,
+                &NoReconnect,
+            )
+            .await
+            .unwrap();
+
+            let send = |sender: &mut Sender<_, _>, constructor_id: u32| {
+                let mut body = constructor_id.to_le_bytes().to_vec();
+                body.extend([0; 4]);
+                let rx = sender.enqueue_body(body);
+                sender.try_fill_write();
+                // The highest byte of the length, right before the plain message of 28 bytes.
+                let len = sender.write_buffer.len();
+                let quick_ack = sender.write_buffer[len - 28 - 1] & 0x80 != 0;
+                sender.on_net_write(len);
+                (rx, quick_ack)
+            };
+
+            // Reading requests can be repeated, so no quick ack is needed for them.
+            let (mut ping_rx, quick_ack) = send(&mut sender, tl::functions::Ping::CONSTRUCTOR_ID);
+            assert!(!quick_ack);
+
+            let (mut send_rx, quick_ack) = send(
+                &mut sender,
+                tl::functions::messages::SendMessage::CONSTRUCTOR_ID,
+            );
+            assert!(quick_ack);
+
+            sender.read_buffer[..4].copy_from_slice(&QUICK_ACK_TOKEN.to_le_bytes());
+            assert!(sender.on_net_read(4).unwrap().is_empty());
+
+            sender.prepare_resend();
+            assert_eq!(sender.requests.len(), 1);
+            assert!(ping_rx.try_recv().is_err());
+            assert!(matches!(
+                send_rx.try_recv(),
+                Ok(Err(InvocationError::Unanswered))
+            ));
+        });
+    }
+}
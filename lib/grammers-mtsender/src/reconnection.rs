@@ -23,6 +23,23 @@ pub trait ReconnectionPolicy: Send + Sync {
     ///
     /// it accepts a `attempts` which is the amount of reconnection tries that has been made already
     fn should_retry(&self, attempts: usize) -> ControlFlow<(), Duration>;
+
+    /// whether requests that were already sent when the connection was lost should be sent again after reconnecting.
+    ///
+    /// Telegram may have processed them before the connection was lost, so sending them again may cause them to be
+    /// processed twice (for example, sending the same message twice). when this returns `false`, those requests fail
+    /// with [`InvocationError::Dropped`] instead, and the caller can decide whether to retry them.
+    ///
+    /// requests that had not been sent yet are always sent after reconnecting. the default implementation returns `true`.
+    ///
+    /// requests which the server acknowledged receiving, and which would repeat their effects if sent again, are never
+    /// sent again, and fail with [`InvocationError::Unanswered`] instead.
+    ///
+    /// [`InvocationError::Dropped`]: crate::InvocationError::Dropped
+    /// [`InvocationError::Unanswered`]: crate::InvocationError::Unanswered
+    fn retry_sent_requests(&self) -> bool {
+        true
+    }
 }
 
 /// the default implementation of the **ReconnectionPolicy**.
@@ -34,6 +51,43 @@ pub struct FixedReconnect {
     pub delay: Duration,
 }
 
+/// implementation of the **ReconnectionPolicy** trait which waits exponentially longer after each failed attempt.
+///
+/// the first attempt waits for `initial_delay`, and the delay doubles after every failed attempt, up to `max_delay`.
+pub struct ExponentialReconnect {
+    /// how many attempts to make before giving up, or `None` to never give up.
+    pub max_attempts: Option<usize>,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl ExponentialReconnect {
+    /// never gives up, waiting one second after the first failure and at most one minute between attempts.
+    pub const DEFAULT: Self = Self {
+        max_attempts: None,
+        initial_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(60),
+    };
+}
+
+impl Default for ExponentialReconnect {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl ReconnectionPolicy for ExponentialReconnect {
+    fn should_retry(&self, attempts: usize) -> ControlFlow<(), Duration> {
+        if self.max_attempts.is_some_and(|max| attempts > max) {
+            return ControlFlow::Break(());
+        }
+
+        let exponent = attempts.saturating_sub(1).min(31) as u32;
+        let delay = self.initial_delay.saturating_mul(1 << exponent);
+        ControlFlow::Continue(delay.min(self.max_delay))
+    }
+}
+
 impl ReconnectionPolicy for FixedReconnect {
     fn should_retry(&self, attempts: usize) -> ControlFlow<(), Duration> {
         if attempts <= self.attempts {
@@ -49,3 +103,34 @@ impl ReconnectionPolicy for NoReconnect {
         ControlFlow::Break(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_exponential_delays() {
+        let policy = ExponentialReconnect {
+            max_attempts: Some(4),
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+        };
+        let delays = (1..=5)
+            .map(|attempts| policy.should_retry(attempts))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            delays,
+            vec![
+                ControlFlow::Continue(Duration::from_secs(1)),
+                ControlFlow::Continue(Duration::from_secs(2)),
+                ControlFlow::Continue(Duration::from_secs(4)),
+                ControlFlow::Continue(Duration::from_secs(5)),
+                ControlFlow::Break(()),
+            ]
+        );
+        assert!(ExponentialReconnect::DEFAULT
+            .should_retry(1000)
+            .is_continue());
+    }
+}
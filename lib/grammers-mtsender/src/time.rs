@@ -0,0 +1,47 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Clocks and timers which work both natively and in the browser, where neither the clock of
+//! the standard library nor the timers of `tokio` are available.
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub use native::*;
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub use web::*;
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+mod native {
+    pub use std::time::SystemTime;
+    pub use tokio::time::{sleep, sleep_until, Instant};
+
+    /// Convert the instants used by `grammers-mtproto`.
+    pub(crate) fn from_mtp(instant: std::time::Instant) -> Instant {
+        Instant::from_std(instant)
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+mod web {
+    use send_wrapper::SendWrapper;
+    use std::time::Duration;
+    pub use web_time::{Instant, SystemTime};
+
+    /// Convert the instants used by `grammers-mtproto`, which are the same in the browser.
+    pub(crate) fn from_mtp(instant: Instant) -> Instant {
+        instant
+    }
+
+    pub async fn sleep(duration: Duration) {
+        // The browser runs everything in a single thread, so the timer is never sent anywhere.
+        SendWrapper::new(gloo_timers::future::sleep(duration)).await
+    }
+
+    pub async fn sleep_until(deadline: Instant) {
+        sleep(deadline.saturating_duration_since(Instant::now())).await
+    }
+}
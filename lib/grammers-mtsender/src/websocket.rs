@@ -0,0 +1,173 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A [`Connector`] which reaches Telegram through WebSockets, for use in the browser.
+use crate::{Connector, Stream};
+use futures_util::future::BoxFuture;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+/// Addresses of the datacenters, indexed by their identifier.
+const DC_ADDRESSES: [Ipv4Addr; 6] = [
+    Ipv4Addr::UNSPECIFIED,
+    Ipv4Addr::new(149, 154, 175, 53),
+    Ipv4Addr::new(149, 154, 167, 51),
+    Ipv4Addr::new(149, 154, 175, 100),
+    Ipv4Addr::new(149, 154, 167, 92),
+    Ipv4Addr::new(91, 108, 56, 190),
+];
+
+/// Like [`DC_ADDRESSES`], but for the test servers.
+const TEST_DC_ADDRESSES: [Ipv4Addr; 4] = [
+    Ipv4Addr::UNSPECIFIED,
+    Ipv4Addr::new(149, 154, 175, 10),
+    Ipv4Addr::new(149, 154, 167, 40),
+    Ipv4Addr::new(149, 154, 175, 117),
+];
+
+/// Names of the datacenters in the domains of their WebSocket endpoints, indexed by identifier.
+const DC_NAMES: [&str; 6] = ["", "pluto", "venus", "aurora", "vesta", "flora"];
+
+/// A [`Connector`] which opens a WebSocket to the web endpoint of the datacenter, since the
+/// browser cannot open TCP connections.
+///
+/// The sender must connect to one of the IPv4 addresses of the datacenters, which is used to
+/// find the matching endpoint (see [`WebSocketConnector::endpoint`]). The endpoints only speak
+/// the obfuscated protocol, so the sender must also use the
+/// [`Obfuscated`](grammers_mtproto::transport::Obfuscated) transport.
+///
+/// Connecting fails with [`io::ErrorKind::Unsupported`] outside of the browser.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WebSocketConnector;
+
+impl WebSocketConnector {
+    /// The URL of the WebSocket endpoint of the datacenter at the given address, if it is one of
+    /// the known addresses of either the production or the test datacenters.
+    pub fn endpoint(addr: SocketAddr) -> Option<String> {
+        let ip = match addr.ip() {
+            IpAddr::V4(ip) => ip,
+            IpAddr::V6(_) => return None,
+        };
+
+        if let Some(dc_id) = (1..DC_ADDRESSES.len()).find(|&i| DC_ADDRESSES[i] == ip) {
+            Some(format!("wss://{}.web.telegram.org/apiws", DC_NAMES[dc_id]))
+        } else {
+            (1..TEST_DC_ADDRESSES.len())
+                .find(|&i| TEST_DC_ADDRESSES[i] == ip)
+                .map(|dc_id| format!("wss://{}.web.telegram.org/apiws_test", DC_NAMES[dc_id]))
+        }
+    }
+}
+
+impl Connector for WebSocketConnector {
+    fn connect(&self, addr: SocketAddr) -> BoxFuture<'_, io::Result<Box<dyn Stream>>> {
+        Box::pin(web::connect(addr))
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+mod web {
+    use super::WebSocketConnector;
+    use crate::Stream;
+    use send_wrapper::SendWrapper;
+    use std::future::Future;
+    use std::io;
+    use std::net::SocketAddr;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+    use ws_stream_wasm::WsMeta;
+
+    // The browser runs everything in a single thread, so the socket is never sent anywhere.
+    struct WebSocketStream<S>(SendWrapper<S>);
+
+    pub(super) fn connect(
+        addr: SocketAddr,
+    ) -> impl Future<Output = io::Result<Box<dyn Stream>>> + Send {
+        SendWrapper::new(async move {
+            let url = WebSocketConnector::endpoint(addr).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no websocket endpoint is known for {addr}"),
+                )
+            })?;
+            let (_, stream) = WsMeta::connect(url, vec!["binary"])
+                .await
+                .map_err(|e| io::Error::new(io::ErrorKind::ConnectionRefused, e.to_string()))?;
+
+            Ok(Box::new(WebSocketStream(SendWrapper::new(stream.into_io()))) as Box<dyn Stream>)
+        })
+    }
+
+    impl<S: AsyncRead + Unpin> AsyncRead for WebSocketStream<S> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            Pin::new(&mut *self.0).poll_read(cx, buf)
+        }
+    }
+
+    impl<S: AsyncWrite + Unpin> AsyncWrite for WebSocketStream<S> {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut *self.0).poll_write(cx, buf)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut *self.0).poll_flush(cx)
+        }
+
+        fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut *self.0).poll_shutdown(cx)
+        }
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+mod web {
+    use crate::Stream;
+    use std::io;
+    use std::net::SocketAddr;
+
+    pub(super) async fn connect(_addr: SocketAddr) -> io::Result<Box<dyn Stream>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "websockets are only available in the browser",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_endpoints() {
+        let endpoint = |ip: [u8; 4]| WebSocketConnector::endpoint(SocketAddr::from((ip, 443)));
+
+        assert_eq!(
+            endpoint([149, 154, 167, 51]).as_deref(),
+            Some("wss://venus.web.telegram.org/apiws")
+        );
+        assert_eq!(
+            endpoint([91, 108, 56, 190]).as_deref(),
+            Some("wss://flora.web.telegram.org/apiws")
+        );
+        assert_eq!(
+            endpoint([149, 154, 175, 10]).as_deref(),
+            Some("wss://pluto.web.telegram.org/apiws_test")
+        );
+        assert_eq!(endpoint([0, 0, 0, 0]), None);
+        assert_eq!(endpoint([127, 0, 0, 1]), None);
+    }
+}
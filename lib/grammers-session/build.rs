@@ -20,13 +20,19 @@ fn main() -> std::io::Result<()> {
     )?);
 
     // Using boxed variants in the definitions so that deserialization fails if any constructor ID changes.
+    // The session constructor ID is pinned so that adding optional fields keeps old sessions loadable.
+    // The data center constructor ID is pinned to the one inferred before `bytes` were treated as
+    // `string` when inferring identifiers, for the same reason.
     let definitions = parse_tl_file(
         r#"
-        dataCenter flags:# id:int ipv4:flags.0?int ipv6:flags.1?int128 port:int auth:flags.2?bytes = DataCenter;
+        dataCenter#756d16b6 flags:# id:int ipv4:flags.0?int ipv6:flags.1?int128 port:int auth:flags.2?bytes = DataCenter;
         user id:long dc:int bot:Bool = User;
         channelState channel_id:long pts:int = ChannelState;
         updateState pts:int qts:int date:int seq:int channels:Vector<ChannelState> = UpdateState;
-        session flags:# dcs:Vector<DataCenter> user:flags.0?User state:flags.1?UpdateState = Session;
+        serverSalt dc:int valid_since:int valid_until:int salt:long = ServerSalt;
+        cachedPeer flags:# id:long ty:int access_hash:flags.0?long username:flags.1?string phone:flags.2?string = CachedPeer;
+        secretChatState flags:# id:int access_hash:long originator:flags.0?true user_id:long key:flags.1?bytes private_key:flags.2?bytes layer:int received:int sent:int key_uses:int exchange_id:flags.3?long exchange_private_key:flags.4?bytes exchange_key:flags.5?bytes previous_key:flags.6?bytes = SecretChatState;
+        session#a73eb8ce flags:# dcs:Vector<DataCenter> user:flags.0?User state:flags.1?UpdateState future_auth_token:flags.2?bytes salts:flags.3?Vector<ServerSalt> peers:flags.4?Vector<CachedPeer> dc:flags.5?int state_version:flags.6?int state_data:flags.7?bytes secret_chats:flags.8?Vector<SecretChatState> = Session;
         "#,
     )
     .map(Result::unwrap)
@@ -0,0 +1,42 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use super::PackedChat;
+
+/// A chat remembered by the session, so that it can be used again after a restart without
+/// having to fetch it first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CachedPeer {
+    pub chat: PackedChat,
+    /// The public username of the chat, without the "@" prefix, if it had one.
+    pub username: Option<String>,
+    /// The phone number of the user, if it was visible.
+    pub phone: Option<String>,
+}
+
+impl CachedPeer {
+    /// Merge what's known about the same chat into this one, returning `true` if anything
+    /// changed.
+    ///
+    /// A peer without access hash comes from a "min" constructor, which may lack some of the
+    /// information, so it only fills in what's missing.
+    pub(crate) fn merge(&mut self, peer: CachedPeer) -> bool {
+        let old = self.clone();
+        if peer.chat.access_hash.is_some() {
+            *self = peer;
+        } else {
+            self.chat.ty = peer.chat.ty;
+            if self.username.is_none() {
+                self.username = peer.username;
+            }
+            if self.phone.is_none() {
+                self.phone = peer.phone;
+            }
+        }
+        *self != old
+    }
+}
@@ -32,6 +32,10 @@ impl ChatHashCache {
             .expect("tried to query self_id before it's known")
     }
 
+    pub fn try_self_id(&self) -> Option<i64> {
+        self.self_id
+    }
+
     pub fn is_self_bot(&self) -> bool {
         self.self_bot
     }
@@ -45,6 +49,13 @@ impl ChatHashCache {
         self.self_id = Some(user.id);
     }
 
+    /// Remember the access hash of the given chat, if it has one.
+    pub fn insert(&mut self, chat: PackedChat) {
+        if let Some(hash) = chat.access_hash {
+            self.hash_map.insert(chat.id, (hash, chat.ty));
+        }
+    }
+
     pub fn get(&self, id: i64) -> Option<PackedChat> {
         self.hash_map.get(&id).map(|&(hash, ty)| PackedChat {
             ty,
@@ -357,8 +368,6 @@ impl ChatHashCache {
                 U::RecentReactions => true,
                 U::MoveStickerSetToTop(_) => true,
                 U::MessageExtendedMedia(u) => self.has_peer(&u.peer),
-                U::ChannelPinnedTopic(u) => self.has(u.channel_id),
-                U::ChannelPinnedTopics(u) => self.has(u.channel_id),
                 U::User(u) => self.has(u.user_id),
                 U::AutoSaveSettings => true,
                 U::Story(u) => self.has_peer(&u.peer),
@@ -387,12 +396,41 @@ impl ChatHashCache {
                 U::BotBusinessConnect(u) => match &u.connection {
                     tl::enums::BotBusinessConnection::Connection(con) => self.has(con.user_id),
                 },
-                U::BroadcastRevenueTransactions(u) => self.has_peer(&u.peer),
                 U::StarsBalance(_) => true,
                 U::BusinessBotCallbackQuery(u) => {
                     self.has(u.user_id) && self.extend_from_message(&u.message)
                 }
                 U::StarsRevenueStatus(u) => self.has_peer(&u.peer),
+                U::BotPurchasedPaidMedia(u) => self.has(u.user_id),
+                U::PaidReactionPrivacy(_) => true,
+                U::SentPhoneCode(_) => true,
+                U::GroupCallChainBlocks(_) => true,
+                U::ReadMonoForumInbox(u) => {
+                    self.has(u.channel_id) && self.has_peer(&u.saved_peer_id)
+                }
+                U::ReadMonoForumOutbox(u) => {
+                    self.has(u.channel_id) && self.has_peer(&u.saved_peer_id)
+                }
+                U::MonoForumNoPaidException(u) => {
+                    self.has(u.channel_id) && self.has_peer(&u.saved_peer_id)
+                }
+                U::GroupCallMessage(_) => true,
+                U::GroupCallEncryptedMessage(u) => self.has_peer(&u.from_id),
+                U::PinnedForumTopic(u) => self.has_peer(&u.peer),
+                U::PinnedForumTopics(u) => self.has_peer(&u.peer),
+                U::DeleteGroupCallMessages(_) => true,
+                U::StarGiftAuctionState(_) => true,
+                U::StarGiftAuctionUserState(_) => true,
+                U::EmojiGameInfo(_) => true,
+                U::StarGiftCraftFail => true,
+                U::ChatParticipantRank(u) => self.has(u.user_id),
+                U::ManagedBot(u) => self.has(u.user_id) && self.has(u.bot_id),
+                U::NewBotConnection(u) => self.has(u.bot_id),
+                U::BotGuestChatQuery(u) => self.extend_from_message(&u.message),
+                U::JoinChatWebViewDecision(u) => self.has_peer(&u.peer),
+                U::AiComposeTones => true,
+                U::WebBrowserSettings(_) => true,
+                U::WebBrowserException(_) => true,
             },
             // Telegram should be including all the peers referenced in the updates in
             // `.users` and `.chats`, so no instrospection is done (unlike for `UpdateShort`).
@@ -576,11 +614,36 @@ impl ChatHashCache {
                             Some(p) => self.has_peer(p),
                             None => true,
                         },
-                        MA::GiveawayLaunch => true,
+                        MA::GiveawayLaunch(_) => true,
                         MA::GiveawayResults(_) => true,
                         MA::BoostApply(_) => true,
                         MA::PaymentRefunded(c) => self.has_peer(&c.peer),
                         MA::GiftStars(_) => true,
+                        MA::PrizeStars(c) => self.has_peer(&c.boost_peer),
+                        MA::StarGift(_) => true,
+                        MA::StarGiftUnique(_) => true,
+                        MA::PaidMessagesRefunded(_) => true,
+                        MA::PaidMessagesPrice(_) => true,
+                        MA::ConferenceCall(c) => match &c.other_participants {
+                            Some(peers) => peers.iter().all(|p| self.has_peer(p)),
+                            None => true,
+                        },
+                        MA::TodoCompletions(_) => true,
+                        MA::TodoAppendTasks(_) => true,
+                        MA::SuggestedPostApproval(_) => true,
+                        MA::SuggestedPostSuccess(_) => true,
+                        MA::SuggestedPostRefund(_) => true,
+                        MA::GiftTon(_) => true,
+                        MA::SuggestBirthday(_) => true,
+                        MA::StarGiftPurchaseOffer(_) => true,
+                        MA::StarGiftPurchaseOfferDeclined(_) => true,
+                        MA::NewCreatorPending(c) => self.has(c.new_creator_id),
+                        MA::ChangeCreator(c) => self.has(c.new_creator_id),
+                        MA::NoForwardsToggle(_) => true,
+                        MA::NoForwardsRequest(_) => true,
+                        MA::PollAppendAnswer(_) => true,
+                        MA::PollDeleteAnswer(_) => true,
+                        MA::ManagedBotCreated(c) => self.has(c.bot_id),
                     }
             }
         }
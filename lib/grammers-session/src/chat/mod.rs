@@ -5,8 +5,10 @@
 // <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
+mod cached;
 mod hash_cache;
 mod packed;
 
+pub use cached::CachedPeer;
 pub use hash_cache::ChatHashCache;
 pub use packed::{PackedChat, PackedType};
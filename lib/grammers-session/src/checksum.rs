@@ -0,0 +1,41 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use crate::Error;
+use sha2::{Digest, Sha256};
+
+/// Prefix of sessions with a checksum, which can't be confused with the start of one without.
+const MAGIC: &[u8; 8] = b"GRSSUM01";
+
+const CHECKSUM_LEN: usize = 32;
+
+/// Prefix the serialized session with a checksum of its contents.
+pub(crate) fn seal(body: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(MAGIC.len() + CHECKSUM_LEN + body.len());
+    result.extend(MAGIC);
+    result.extend(Sha256::digest(body));
+    result.extend(body);
+    result
+}
+
+/// Verify the checksum of the session, and return its contents.
+///
+/// Sessions saved before the checksum existed are returned as-is.
+pub(crate) fn unseal(data: &[u8]) -> Result<&[u8], Error> {
+    if !data.starts_with(MAGIC) {
+        return Ok(data);
+    }
+    if data.len() < MAGIC.len() + CHECKSUM_LEN {
+        return Err(Error::Corrupted);
+    }
+    let (checksum, body) = data[MAGIC.len()..].split_at(CHECKSUM_LEN);
+    if Sha256::digest(body).as_slice() == checksum {
+        Ok(body)
+    } else {
+        Err(Error::Corrupted)
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use sha2::Sha256;
+use std::fmt;
+use std::io;
+use std::sync::Mutex;
+
+/// Prefix of encrypted sessions, which can't be confused with the start of a serialized one.
+pub(crate) const MAGIC: &[u8; 8] = b"GRSENC01";
+
+/// PBKDF2 rounds used to derive the key from the passphrase of new files.
+pub(crate) const ITERATIONS: u32 = 600_000;
+
+/// PBKDF2 rounds above which the header is considered corrupted, instead of deriving the key
+/// for what could be hours.
+const MAX_ITERATIONS: u32 = ITERATIONS * 10;
+
+const SALT_LEN: usize = 16;
+const CHECK_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+// magic, iterations, salt, passphrase check, nonce
+const HEADER_LEN: usize = MAGIC.len() + 4 + SALT_LEN + CHECK_LEN + NONCE_LEN;
+
+/// The error type which is returned, wrapped in an [`io::Error`], when an encrypted session
+/// can't be loaded.
+///
+/// It can be retrieved with [`io::Error::get_ref`] and downcasting it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EncryptionError {
+    /// The session is encrypted, but no passphrase was provided to decrypt it.
+    PassphraseRequired,
+    /// A passphrase was provided, but the session is not encrypted.
+    ///
+    /// [`FileSession::encrypt_in_place`](crate::FileSession::encrypt_in_place) can be used to
+    /// encrypt it.
+    NotEncrypted,
+    /// The passphrase is not the one the session was encrypted with.
+    WrongPassphrase,
+    /// The passphrase is correct, but the encrypted data is damaged.
+    Corrupted,
+}
+
+impl fmt::Display for EncryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PassphraseRequired => {
+                write!(
+                    f,
+                    "session encryption error: the session needs a passphrase"
+                )
+            }
+            Self::NotEncrypted => {
+                write!(f, "session encryption error: the session is not encrypted")
+            }
+            Self::WrongPassphrase => write!(f, "session encryption error: wrong passphrase"),
+            Self::Corrupted => write!(f, "session encryption error: the session is corrupted"),
+        }
+    }
+}
+
+impl std::error::Error for EncryptionError {}
+
+impl From<EncryptionError> for io::Error {
+    fn from(error: EncryptionError) -> Self {
+        let kind = match error {
+            EncryptionError::WrongPassphrase => io::ErrorKind::PermissionDenied,
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, error)
+    }
+}
+
+pub(crate) fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// A key derived from the passphrase, along with the parameters used to derive it.
+struct DerivedKey {
+    iterations: u32,
+    salt: [u8; SALT_LEN],
+    key: [u8; 32],
+    check: [u8; CHECK_LEN],
+}
+
+impl DerivedKey {
+    fn derive(passphrase: &str, iterations: u32, salt: [u8; SALT_LEN]) -> Self {
+        let mut output = [0; 32 + CHECK_LEN];
+        pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), &salt, iterations, &mut output);
+        let (key, check) = output.split_at(32);
+        Self {
+            iterations,
+            salt,
+            key: key.try_into().unwrap(),
+            check: check.try_into().unwrap(),
+        }
+    }
+}
+
+/// Encrypts and decrypts sessions with a key derived from a passphrase.
+///
+/// Deriving the key is deliberately slow, so the key is kept and reused as long as the salt
+/// doesn't change.
+pub(crate) struct Cipher {
+    passphrase: String,
+    iterations: u32,
+    derived: Mutex<Option<DerivedKey>>,
+}
+
+impl Cipher {
+    pub(crate) fn new(passphrase: String, iterations: u32) -> Self {
+        Self {
+            passphrase,
+            iterations,
+            derived: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn encrypt(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut derived = self.derived.lock().unwrap();
+        if derived.is_none() {
+            let mut salt = [0; SALT_LEN];
+            getrandom::getrandom(&mut salt).map_err(|e| io::Error::other(e.to_string()))?;
+            *derived = Some(DerivedKey::derive(&self.passphrase, self.iterations, salt));
+        }
+        let derived = derived.as_ref().unwrap();
+
+        let mut nonce = [0; NONCE_LEN];
+        getrandom::getrandom(&mut nonce).map_err(|e| io::Error::other(e.to_string()))?;
+        let ciphertext = Aes256Gcm::new(&derived.key.into())
+            .encrypt(Nonce::from_slice(&nonce), data)
+            .map_err(|_| io::Error::other("failed to encrypt session"))?;
+
+        let mut result = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        result.extend(MAGIC);
+        result.extend(derived.iterations.to_le_bytes());
+        result.extend(derived.salt);
+        result.extend(derived.check);
+        result.extend(nonce);
+        result.extend(ciphertext);
+        Ok(result)
+    }
+
+    pub(crate) fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        if !is_encrypted(data) {
+            return Err(EncryptionError::NotEncrypted);
+        }
+        if data.len() < HEADER_LEN {
+            return Err(EncryptionError::Corrupted);
+        }
+        let (header, ciphertext) = data.split_at(HEADER_LEN);
+        let (iterations, rest) = header[MAGIC.len()..].split_at(4);
+        let (salt, rest) = rest.split_at(SALT_LEN);
+        let (check, nonce) = rest.split_at(CHECK_LEN);
+        let iterations = u32::from_le_bytes(iterations.try_into().unwrap());
+        let salt: [u8; SALT_LEN] = salt.try_into().unwrap();
+        if iterations == 0 || iterations > MAX_ITERATIONS {
+            return Err(EncryptionError::Corrupted);
+        }
+
+        let mut derived = self.derived.lock().unwrap();
+        let reuse = derived
+            .as_ref()
+            .is_some_and(|d| d.iterations == iterations && d.salt == salt);
+        if !reuse {
+            *derived = Some(DerivedKey::derive(&self.passphrase, iterations, salt));
+        }
+        let key = derived.as_ref().unwrap();
+
+        if key.check != check {
+            *derived = None;
+            return Err(EncryptionError::WrongPassphrase);
+        }
+        Aes256Gcm::new(&key.key.into())
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| EncryptionError::Corrupted)
+    }
+}
+
+impl fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The passphrase is deliberately left out.
+        f.debug_struct("Cipher")
+            .field("iterations", &self.iterations)
+            .finish_non_exhaustive()
+    }
+}
@@ -9,10 +9,20 @@
 #![deny(unsafe_code)]
 
 mod chat;
+mod checksum;
+mod encryption;
 mod generated;
 mod message_box;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod storage;
+mod string_session;
+mod update_state;
 
-pub use chat::{ChatHashCache, PackedChat, PackedType};
+pub use chat::{CachedPeer, ChatHashCache, PackedChat, PackedType};
+pub use encryption::EncryptionError;
+pub use generated::types::SecretChatState;
+pub use generated::types::ServerSalt;
 pub use generated::types::UpdateState;
 pub use generated::types::User;
 pub use generated::LAYER as VERSION;
@@ -20,18 +30,24 @@ use generated::{enums, types};
 use grammers_tl_types::deserialize::Error as DeserializeError;
 pub use message_box::{channel_id, PrematureEndReason};
 pub use message_box::{Gap, MessageBox};
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteSession;
+use std::collections::HashMap;
 use std::fmt;
-use std::fs::{File, OpenOptions};
-use std::io::{self, Read, Seek, Write};
+use std::io;
 use std::net::{SocketAddr, SocketAddrV4, SocketAddrV6};
 use std::path::Path;
 use std::sync::Mutex;
+pub use storage::{FileSession, MemorySession, SessionStore, StoreFuture};
+pub use string_session::StringSessionError;
 
 // Needed for auto-generated definitions.
-use grammers_tl_types::{deserialize, Deserializable, Identifiable, Serializable};
+use grammers_tl_types::{debug, deserialize, Deserializable, Identifiable, Serializable};
 
 pub struct Session {
     session: Mutex<types::Session>,
+    // Kept apart from the rest of the session so that chats can be looked up quickly.
+    peers: Mutex<HashMap<i64, CachedPeer>>,
 }
 
 #[allow(clippy::new_without_default)]
@@ -42,43 +58,100 @@ impl Session {
                 dcs: Vec::new(),
                 user: None,
                 state: None,
+                future_auth_token: None,
+                salts: None,
+                peers: None,
+                dc: None,
+                state_version: None,
+                state_data: None,
+                secret_chats: None,
             }),
+            peers: Mutex::new(HashMap::new()),
         }
     }
 
     /// Load a previous session instance from a file,
     /// creating one if it doesn't exist
+    ///
+    /// If the file is corrupted, the backup kept by [`Session::save_to_file`] is loaded instead.
     pub fn load_file_or_create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
         let path = path.as_ref();
-        if !path.exists() {
-            File::create(path)?;
-            let session = Session::new();
-            session.save_to_file(path)?;
-            Ok(session)
-        } else {
-            Self::load_file(path)
+        match storage::read_file(path, |data| Self::load_bytes(&data))? {
+            Some(session) => Ok(session),
+            None => {
+                let session = Session::new();
+                session.save_to_file(path)?;
+                Ok(session)
+            }
         }
     }
 
     /// Load a previous session instance from a file.
+    ///
+    /// If the file is corrupted, the backup kept by [`Session::save_to_file`] is loaded instead.
     pub fn load_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        let mut data = Vec::new();
-        File::open(path.as_ref())?.read_to_end(&mut data)?;
+        let path = path.as_ref();
+        storage::read_file(path, |data| Self::load_bytes(&data))?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("session file not found: {}", path.display()),
+            )
+        })
+    }
 
-        Self::load(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    fn load_bytes(data: &[u8]) -> io::Result<Self> {
+        Self::load(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Load a previous session instance from the given store, creating one if nothing was saved
+    /// in it yet.
+    pub async fn load_from(store: &dyn SessionStore) -> io::Result<Self> {
+        match store.load().await? {
+            Some(data) => Self::load_bytes(&data),
+            None => Ok(Self::new()),
+        }
     }
 
     pub fn load(data: &[u8]) -> Result<Self, Error> {
-        Ok(Self {
-            session: Mutex::new(
-                enums::Session::from_bytes(data)
-                    .map_err(|e| match e {
-                        DeserializeError::UnexpectedEof => Error::MalformedData,
-                        DeserializeError::UnexpectedConstructor { .. } => Error::UnsupportedVersion,
-                    })?
-                    .into(),
-            ),
-        })
+        if encryption::is_encrypted(data) {
+            return Err(Error::Encrypted);
+        }
+        let data = checksum::unseal(data)?;
+        Ok(Self::from_raw(
+            enums::Session::from_bytes(data)
+                .map_err(|e| match e.root_cause() {
+                    DeserializeError::UnexpectedConstructor { .. } => Error::UnsupportedVersion,
+                    _ => Error::MalformedData,
+                })?
+                .into(),
+        ))
+    }
+
+    fn from_raw(mut session: types::Session) -> Self {
+        update_state::unpack(&mut session);
+        let peers = session
+            .peers
+            .take()
+            .into_iter()
+            .flatten()
+            .filter_map(|enums::CachedPeer::Peer(peer)| {
+                Some(CachedPeer {
+                    chat: PackedChat {
+                        ty: PackedType::from_bits(peer.ty as u8)?,
+                        id: peer.id,
+                        access_hash: peer.access_hash,
+                    },
+                    username: peer.username,
+                    phone: peer.phone,
+                })
+            })
+            .map(|peer| (peer.chat.id, peer))
+            .collect();
+
+        Self {
+            session: Mutex::new(session),
+            peers: Mutex::new(peers),
+        }
     }
 
     pub fn signed_in(&self) -> bool {
@@ -116,6 +189,8 @@ impl Session {
         {
             session.dcs.remove(pos);
         }
+        // Salts belong to the authorization key, which may be a different one now.
+        remove_dc_salts(&mut session, id);
 
         let (ip_v4, ip_v6): (Option<&SocketAddrV4>, Option<&SocketAddrV6>) = match &addr {
             SocketAddr::V4(ip_v4) => (Some(ip_v4), None),
@@ -148,6 +223,26 @@ impl Session {
             .map(|enums::User::User(user)| user.clone())
     }
 
+    /// Returns the datacenter the account lives in, which is where the client should connect.
+    ///
+    /// This is the datacenter of the stored user, if any.
+    pub fn home_dc(&self) -> Option<i32> {
+        let session = self.session.lock().unwrap();
+        match &session.user {
+            Some(enums::User::User(user)) => Some(user.dc),
+            None => session.dc,
+        }
+    }
+
+    /// Set the datacenter to connect to while the logged-in user is not known yet, such as
+    /// when the session was imported from a format which doesn't include the user.
+    pub fn set_home_dc(&self, dc: i32) {
+        self.session.lock().unwrap().dc = Some(dc)
+    }
+
+    /// Returns the update state, used to catch up on the updates missed while offline.
+    ///
+    /// This is `None` if the state is not known yet, or if the one saved could not be loaded.
     pub fn get_state(&self) -> Option<UpdateState> {
         let session = self.session.lock().unwrap();
         let enums::UpdateState::State(state) = session.state.clone()?;
@@ -158,6 +253,132 @@ impl Session {
         self.session.lock().unwrap().state = Some(state.into())
     }
 
+    /// Returns the `pts` of the channel in the update state, if it is known.
+    pub fn channel_pts(&self, channel_id: i64) -> Option<i32> {
+        let session = self.session.lock().unwrap();
+        let enums::UpdateState::State(state) = session.state.as_ref()?;
+        state
+            .channels
+            .iter()
+            .find_map(|enums::ChannelState::State(channel)| {
+                (channel.channel_id == channel_id).then_some(channel.pts)
+            })
+    }
+
+    /// Set the `pts` of the channel in the update state, so that the updates missed in it can be
+    /// caught up on. Does nothing if the account-wide update state is not known yet.
+    pub fn set_channel_pts(&self, channel_id: i64, pts: i32) {
+        let mut session = self.session.lock().unwrap();
+        if let Some(enums::UpdateState::State(state)) = session.state.as_mut() {
+            match state
+                .channels
+                .iter_mut()
+                .find(|enums::ChannelState::State(channel)| channel.channel_id == channel_id)
+            {
+                Some(enums::ChannelState::State(channel)) => channel.pts = pts,
+                None => state
+                    .channels
+                    .push(types::ChannelState { channel_id, pts }.into()),
+            }
+        }
+    }
+
+    /// Forget the logged-in user, the update state and every authorization key, so that the
+    /// session can no longer be used to act on behalf of the account.
+    pub fn clear_authorization(&self) {
+        let mut session = self.session.lock().unwrap();
+        session.user = None;
+        session.dc = None;
+        session.state = None;
+        session
+            .dcs
+            .iter_mut()
+            .for_each(|enums::DataCenter::Center(dc)| {
+                dc.auth = None;
+            });
+        session.salts = None;
+        session.secret_chats = None;
+        // Access hashes are only valid for the account that received them.
+        self.peers.lock().unwrap().clear();
+    }
+
+    /// Returns the token Telegram provided on log out, which can be used to log in again faster.
+    pub fn future_auth_token(&self) -> Option<Vec<u8>> {
+        self.session.lock().unwrap().future_auth_token.clone()
+    }
+
+    pub fn set_future_auth_token(&self, token: Option<Vec<u8>>) {
+        self.session.lock().unwrap().future_auth_token = token
+    }
+
+    /// Returns the server salts known to be valid for the authorization key of the given
+    /// datacenter, along with the time window in which each can be used.
+    pub fn dc_salts(&self, dc_id: i32) -> Vec<ServerSalt> {
+        self.session
+            .lock()
+            .unwrap()
+            .salts
+            .iter()
+            .flatten()
+            .map(|enums::ServerSalt::Salt(salt)| salt.clone())
+            .filter(|salt| salt.dc == dc_id)
+            .collect()
+    }
+
+    /// Replace the server salts stored for the given datacenter.
+    pub fn set_dc_salts(&self, dc_id: i32, salts: Vec<ServerSalt>) {
+        let mut session = self.session.lock().unwrap();
+        remove_dc_salts(&mut session, dc_id);
+        session.salts.get_or_insert_with(Vec::new).extend(
+            salts
+                .into_iter()
+                .map(|salt| ServerSalt { dc: dc_id, ..salt }.into()),
+        );
+    }
+
+    /// Returns the state of the secret chat with the given identifier, if it is known.
+    pub fn secret_chat(&self, id: i32) -> Option<SecretChatState> {
+        self.session
+            .lock()
+            .unwrap()
+            .secret_chats
+            .iter()
+            .flatten()
+            .map(|enums::SecretChatState::State(chat)| chat)
+            .find(|chat| chat.id == id)
+            .cloned()
+    }
+
+    /// Returns the state of every known secret chat.
+    pub fn secret_chats(&self) -> Vec<SecretChatState> {
+        self.session
+            .lock()
+            .unwrap()
+            .secret_chats
+            .iter()
+            .flatten()
+            .map(|enums::SecretChatState::State(chat)| chat.clone())
+            .collect()
+    }
+
+    /// Remember the state of a secret chat, replacing the previous state of the same chat.
+    ///
+    /// The state includes the key used to encrypt its messages, so it must be kept in order to
+    /// keep using the chat.
+    pub fn set_secret_chat(&self, chat: SecretChatState) {
+        let mut session = self.session.lock().unwrap();
+        let chats = session.secret_chats.get_or_insert_with(Vec::new);
+        chats.retain(|enums::SecretChatState::State(c)| c.id != chat.id);
+        chats.push(chat.into());
+    }
+
+    /// Forget the secret chat with the given identifier, along with its key.
+    pub fn remove_secret_chat(&self, id: i32) {
+        if let Some(chats) = self.session.lock().unwrap().secret_chats.as_mut() {
+            chats.retain(|enums::SecretChatState::State(chat)| chat.id != id);
+        }
+    }
+
     pub fn get_dcs(&self) -> Vec<types::DataCenter> {
         self.session
             .lock()
@@ -168,18 +389,100 @@ impl Session {
             .collect()
     }
 
+    /// Remember the given chats, so that they can be used again after the session is loaded
+    /// without having to fetch them first.
+    ///
+    /// Returns `true` if anything new was learnt about the chats.
+    pub fn cache_peers<I: IntoIterator<Item = CachedPeer>>(&self, peers: I) -> bool {
+        let mut cache = self.peers.lock().unwrap();
+        let mut changed = false;
+        for peer in peers {
+            match cache.get_mut(&peer.chat.id) {
+                Some(cached) => changed |= cached.merge(peer),
+                None => {
+                    cache.insert(peer.chat.id, peer);
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Returns the cached chat with the given identifier, if any.
+    pub fn cached_peer(&self, id: i64) -> Option<CachedPeer> {
+        self.peers.lock().unwrap().get(&id).cloned()
+    }
+
+    /// Returns the cached chat with the given username (without the "@" prefix), if any.
+    ///
+    /// Usernames are compared without regard to case, like Telegram does.
+    pub fn cached_peer_by_username(&self, username: &str) -> Option<CachedPeer> {
+        self.peers
+            .lock()
+            .unwrap()
+            .values()
+            .find(|peer| {
+                peer.username
+                    .as_deref()
+                    .is_some_and(|u| u.eq_ignore_ascii_case(username))
+            })
+            .cloned()
+    }
+
+    /// Returns every cached chat.
+    pub fn cached_peers(&self) -> Vec<CachedPeer> {
+        self.peers.lock().unwrap().values().cloned().collect()
+    }
+
     #[must_use]
     pub fn save(&self) -> Vec<u8> {
-        enums::Session::Session(self.session.lock().unwrap().clone()).to_bytes()
+        let mut session = self.to_raw();
+        update_state::pack(&mut session);
+        checksum::seal(&enums::Session::Session(session).to_bytes())
+    }
+
+    fn to_raw(&self) -> types::Session {
+        let mut session = self.session.lock().unwrap().clone();
+        let mut peers = self
+            .peers
+            .lock()
+            .unwrap()
+            .values()
+            .map(|peer| {
+                types::CachedPeer {
+                    id: peer.chat.id,
+                    ty: peer.chat.ty as i32,
+                    access_hash: peer.chat.access_hash,
+                    username: peer.username.clone(),
+                    phone: peer.phone.clone(),
+                }
+                .into()
+            })
+            .collect::<Vec<_>>();
+        if !peers.is_empty() {
+            peers.sort_by_key(|enums::CachedPeer::Peer(peer)| peer.id);
+            session.peers = Some(peers);
+        }
+        session
     }
 
     /// Saves the session to a file.
+    ///
+    /// The session is written to a temporary file which then replaces the original, so that it
+    /// is never left half-written. The previous contents are kept in a `.bak` file next to it.
     pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        let mut file = OpenOptions::new().write(true).open(path.as_ref())?;
-        file.seek(io::SeekFrom::Start(0))?;
-        file.set_len(0)?;
-        file.write_all(&self.save())?;
-        file.sync_data()
+        storage::replace_file(path.as_ref(), &self.save())
+    }
+
+    /// Saves the session to the given store.
+    pub async fn save_to(&self, store: &dyn SessionStore) -> io::Result<()> {
+        store.save(self.save()).await
+    }
+}
+
+fn remove_dc_salts(session: &mut types::Session, dc_id: i32) {
+    if let Some(salts) = session.salts.as_mut() {
+        salts.retain(|enums::ServerSalt::Salt(salt)| salt.dc != dc_id);
     }
 }
 
@@ -187,6 +490,11 @@ impl Session {
 pub enum Error {
     MalformedData,
     UnsupportedVersion,
+    /// The data is an encrypted session, which must be loaded with the passphrase through
+    /// [`FileSession::encrypted`].
+    Encrypted,
+    /// The data does not match the checksum it was saved with, so it was damaged after that.
+    Corrupted,
 }
 
 impl fmt::Display for Error {
@@ -194,8 +502,211 @@ impl fmt::Display for Error {
         match self {
             Error::MalformedData => write!(f, "malformed data"),
             Error::UnsupportedVersion => write!(f, "unsupported version"),
+            Error::Encrypted => write!(f, "encrypted session"),
+            Error::Corrupted => write!(f, "corrupted data (checksum mismatch)"),
         }
     }
 }
 
 impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_session_without_future_auth_token() {
+        // A session saved before `future_auth_token` existed, with no data centers.
+        let data = [
+            0xce, 0xb8, 0x3e, 0xa7, 0, 0, 0, 0, 0x15, 0xc4, 0xb5, 0x1c, 0, 0, 0, 0,
+        ];
+        let session = Session::load(&data).unwrap();
+        assert!(session.get_dcs().is_empty());
+        assert_eq!(session.future_auth_token(), None);
+    }
+
+    #[test]
+    fn clear_authorization_keeps_future_auth_token() {
+        let session = Session::new();
+        session.insert_dc(2, "127.0.0.1:443".parse().unwrap(), [1; 256]);
+        session.set_user(123, 2, false);
+        session.set_future_auth_token(Some(vec![1, 2, 3]));
+
+        session.clear_authorization();
+
+        let session = Session::load(&session.save()).unwrap();
+        assert!(!session.signed_in());
+        assert_eq!(session.dc_auth_key(2), None);
+        assert_eq!(session.get_dcs().len(), 1);
+        assert_eq!(session.future_auth_token(), Some(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn secret_chats_are_saved_until_log_out() {
+        let session = Session::new();
+        let chat = SecretChatState {
+            id: 1,
+            access_hash: 2,
+            originator: false,
+            user_id: 3,
+            key: Some(vec![4; 256]),
+            private_key: None,
+            layer: 101,
+            received: 5,
+            sent: 6,
+            key_uses: 11,
+            exchange_id: None,
+            exchange_private_key: None,
+            exchange_key: None,
+            previous_key: None,
+        };
+        session.set_secret_chat(chat.clone());
+        session.set_secret_chat(SecretChatState { sent: 7, ..chat });
+
+        let session = Session::load(&session.save()).unwrap();
+        assert_eq!(session.secret_chats().len(), 1);
+        assert_eq!(session.secret_chat(1).map(|chat| chat.sent), Some(7));
+
+        session.clear_authorization();
+        assert!(session.secret_chats().is_empty());
+    }
+
+    #[test]
+    fn salts_are_stored_per_dc() {
+        let salt = |dc, salt| ServerSalt {
+            dc,
+            valid_since: 10,
+            valid_until: 20,
+            salt,
+        };
+
+        let session = Session::new();
+        session.insert_dc(2, "127.0.0.1:443".parse().unwrap(), [1; 256]);
+        session.set_dc_salts(2, vec![salt(0, 1), salt(0, 2)]);
+        session.set_dc_salts(4, vec![salt(0, 3)]);
+
+        let session = Session::load(&session.save()).unwrap();
+        assert_eq!(session.dc_salts(2), vec![salt(2, 1), salt(2, 2)]);
+        assert_eq!(session.dc_salts(4), vec![salt(4, 3)]);
+
+        // A new authorization key invalidates the salts.
+        session.insert_dc(2, "127.0.0.1:443".parse().unwrap(), [2; 256]);
+        assert!(session.dc_salts(2).is_empty());
+        assert_eq!(session.dc_salts(4), vec![salt(4, 3)]);
+
+        session.clear_authorization();
+        assert!(session.dc_salts(4).is_empty());
+    }
+
+    #[test]
+    fn cached_peers_survive_save() {
+        let peer = |access_hash, username: Option<&str>| CachedPeer {
+            chat: PackedChat {
+                ty: PackedType::User,
+                id: 123,
+                access_hash,
+            },
+            username: username.map(str::to_owned),
+            phone: None,
+        };
+
+        let session = Session::new();
+        assert!(session.cache_peers([peer(Some(456), None)]));
+        // A "min" user only fills in what's missing.
+        assert!(session.cache_peers([peer(None, Some("Someone"))]));
+        assert!(!session.cache_peers([peer(None, Some("other"))]));
+
+        let session = Session::load(&session.save()).unwrap();
+        assert_eq!(
+            session.cached_peer(123),
+            Some(peer(Some(456), Some("Someone")))
+        );
+        assert_eq!(
+            session.cached_peer_by_username("someone"),
+            session.cached_peer(123)
+        );
+
+        session.clear_authorization();
+        assert!(session.cached_peers().is_empty());
+    }
+
+    fn update_state(pts: i32, channels: &[(i64, i32)]) -> UpdateState {
+        UpdateState {
+            pts,
+            qts: 0,
+            date: 1,
+            seq: 2,
+            channels: channels
+                .iter()
+                .map(|&(channel_id, pts)| types::ChannelState { channel_id, pts }.into())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn update_state_survives_save() {
+        let session = Session::new();
+        session.set_channel_pts(10, 5);
+        assert_eq!(session.get_state(), None);
+
+        session.set_state(update_state(100, &[(10, 5)]));
+        session.set_channel_pts(10, 7);
+        session.set_channel_pts(20, 3);
+
+        let session = Session::load(&session.save()).unwrap();
+        assert_eq!(
+            session.get_state(),
+            Some(update_state(100, &[(10, 7), (20, 3)]))
+        );
+        assert_eq!(session.channel_pts(20), Some(3));
+        assert_eq!(session.channel_pts(30), None);
+    }
+
+    #[test]
+    fn load_update_state_from_older_versions() {
+        // Older versions stored the state directly in the session.
+        let mut raw = Session::new().to_raw();
+        raw.state = Some(update_state(100, &[(10, 5)]).into());
+        let data = enums::Session::Session(raw).to_bytes();
+
+        let session = Session::load(&data).unwrap();
+        assert_eq!(session.get_state(), Some(update_state(100, &[(10, 5)])));
+    }
+
+    #[test]
+    fn unusable_update_state_is_discarded() {
+        let load = |version, data: Vec<u8>| {
+            let mut raw = Session::new().to_raw();
+            raw.user = Some(
+                types::User {
+                    id: 1,
+                    dc: 2,
+                    bot: false,
+                }
+                .into(),
+            );
+            raw.state_version = Some(version);
+            raw.state_data = Some(data);
+            Session::load(&enums::Session::Session(raw).to_bytes()).unwrap()
+        };
+        let valid = enums::UpdateState::from(update_state(100, &[])).to_bytes();
+
+        assert!(load(update_state::STATE_VERSION, valid.clone())
+            .get_state()
+            .is_some());
+
+        // A newer layout, truncated data, or values Telegram would never send.
+        let newer = load(update_state::STATE_VERSION + 1, valid.clone());
+        assert_eq!(newer.get_state(), None);
+        assert!(newer.signed_in());
+        assert_eq!(
+            load(update_state::STATE_VERSION, valid[..8].to_vec()).get_state(),
+            None
+        );
+        let corrupted = enums::UpdateState::from(update_state(-5, &[(10, 0)])).to_bytes();
+        assert_eq!(
+            load(update_state::STATE_VERSION, corrupted).get_state(),
+            None
+        );
+    }
+}
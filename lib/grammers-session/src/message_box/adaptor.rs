@@ -94,6 +94,17 @@ pub(super) fn update_short_message(
                 via_business_bot_id: None,
                 effect: None,
                 factcheck: None,
+                video_processing_pending: false,
+                paid_suggested_post_stars: false,
+                paid_suggested_post_ton: false,
+                from_rank: None,
+                guestchat_via_from: None,
+                report_delivery_until_date: None,
+                paid_message_stars: None,
+                suggested_post: None,
+                schedule_repeat_period: None,
+                summary_from_language: None,
+                rich_message: None,
             }
             .into(),
             pts: short.pts,
@@ -156,6 +167,17 @@ pub(super) fn update_short_chat_message(
                 via_business_bot_id: None,
                 effect: None,
                 factcheck: None,
+                video_processing_pending: false,
+                paid_suggested_post_stars: false,
+                paid_suggested_post_ton: false,
+                from_rank: None,
+                guestchat_via_from: None,
+                report_delivery_until_date: None,
+                paid_message_stars: None,
+                suggested_post: None,
+                schedule_repeat_period: None,
+                summary_from_language: None,
+                rich_message: None,
             }
             .into(),
             pts: short.pts,
@@ -477,8 +499,6 @@ impl PtsInfo {
             RecentReactions => None,
             MoveStickerSetToTop(_) => None,
             MessageExtendedMedia(_) => None,
-            ChannelPinnedTopic(_) => None,
-            ChannelPinnedTopics(_) => None,
             User(_) => None,
             AutoSaveSettings => None,
             Story(_) => None,
@@ -529,10 +549,45 @@ impl PtsInfo {
                 pts_count: u.messages.len() as i32,
                 entry: Entry::SecretChats,
             }),
-            BroadcastRevenueTransactions(_) => None,
             StarsBalance(_) => None,
             BusinessBotCallbackQuery(_) => None,
             StarsRevenueStatus(_) => None,
+            BotPurchasedPaidMedia(u) => Some(Self {
+                pts: u.qts,
+                pts_count: 1,
+                entry: Entry::SecretChats,
+            }),
+            PaidReactionPrivacy(_) => None,
+            SentPhoneCode(_) => None,
+            GroupCallChainBlocks(_) => None,
+            ReadMonoForumInbox(_) => None,
+            ReadMonoForumOutbox(_) => None,
+            MonoForumNoPaidException(_) => None,
+            GroupCallMessage(_) => None,
+            GroupCallEncryptedMessage(_) => None,
+            PinnedForumTopic(_) => None,
+            PinnedForumTopics(_) => None,
+            DeleteGroupCallMessages(_) => None,
+            StarGiftAuctionState(_) => None,
+            StarGiftAuctionUserState(_) => None,
+            EmojiGameInfo(_) => None,
+            StarGiftCraftFail => None,
+            ChatParticipantRank(_) => None,
+            ManagedBot(u) => Some(Self {
+                pts: u.qts,
+                pts_count: 1,
+                entry: Entry::SecretChats,
+            }),
+            NewBotConnection(_) => None,
+            BotGuestChatQuery(u) => Some(Self {
+                pts: u.qts,
+                pts_count: 1,
+                entry: Entry::SecretChats,
+            }),
+            JoinChatWebViewDecision(_) => None,
+            AiComposeTones => None,
+            WebBrowserSettings(_) => None,
+            WebBrowserException(_) => None,
         }
         .filter(|info| info.pts != NO_PTS)
     }
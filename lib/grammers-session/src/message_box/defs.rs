@@ -89,6 +89,10 @@ pub struct MessageBox {
 
     /// This field is merely an optimization, to reuse the same allocation.
     pub(super) tmp_entries: HashSet<Entry>,
+
+    /// How many updates to request at most when getting a channel's difference, if limited
+    /// further than what Telegram allows.
+    pub(super) channel_diff_limit: Option<i32>,
 }
 
 /// Represents the information needed to correctly handle a specific `tl::enums::Update`.
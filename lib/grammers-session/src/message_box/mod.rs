@@ -61,10 +61,15 @@ impl MessageBox {
             getting_diff_for: HashSet::new(),
             next_deadline: None,
             tmp_entries: HashSet::new(),
+            channel_diff_limit: None,
         }
     }
 
     /// Create a [`MessageBox`] from a previously known update state.
+    ///
+    /// The account-wide difference will be fetched right away, so that any updates that occurred
+    /// since the state was saved are "caught up" on (including those for channels, as Telegram
+    /// reports them as part of the difference).
     pub fn load(state: UpdateState) -> Self {
         trace!("created new message box with state: {:?}", state);
         let deadline = next_updates_deadline();
@@ -93,17 +98,36 @@ impl MessageBox {
             )
         }));
 
+        // Without a known pts there is nothing to get the difference from, and the state will be
+        // fetched as usual once the client signs in.
+        let mut getting_diff_for = HashSet::new();
+        if state.pts != NO_PTS {
+            getting_diff_for.insert(Entry::AccountWide);
+        }
+
         Self {
             map,
             date: state.date,
             seq: state.seq,
             possible_gaps: HashMap::new(),
-            getting_diff_for: HashSet::new(),
+            getting_diff_for,
             next_deadline: Some(Entry::AccountWide),
             tmp_entries: HashSet::new(),
+            channel_diff_limit: None,
         }
     }
 
+    /// Limit how many updates are requested at once when getting a channel's difference.
+    ///
+    /// When a channel has more missing updates than this, Telegram considers the difference too
+    /// long, and only the most recent state is fetched, skipping the missed updates. This avoids
+    /// replaying a very large amount of messages after a long time offline.
+    ///
+    /// The limit is clamped to the maximum Telegram allows, which depends on the account type.
+    pub fn set_channel_diff_limit(&mut self, limit: Option<i32>) {
+        self.channel_diff_limit = limit.map(|limit| limit.max(1));
+    }
+
     /// Return the current state in a format that sessions understand.
     ///
     /// This should be used for persisting the state.
@@ -819,10 +843,13 @@ impl MessageBox {
                     channel,
                     filter: tl::enums::ChannelMessagesFilter::Empty,
                     pts: state.pts,
-                    limit: if chat_hashes.is_self_bot() {
-                        defs::BOT_CHANNEL_DIFF_LIMIT
-                    } else {
-                        defs::USER_CHANNEL_DIFF_LIMIT
+                    limit: {
+                        let max = if chat_hashes.is_self_bot() {
+                            defs::BOT_CHANNEL_DIFF_LIMIT
+                        } else {
+                            defs::USER_CHANNEL_DIFF_LIMIT
+                        };
+                        self.channel_diff_limit.map_or(max, |limit| limit.min(max))
                     },
                 };
                 trace!("requesting {:?}", gd);
@@ -990,3 +1017,24 @@ pub enum PrematureEndReason {
     TemporaryServerIssues,
     Banned,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_load_catches_up() {
+        let mut message_box = MessageBox::load(UpdateState {
+            pts: 10,
+            qts: 2,
+            date: 123,
+            seq: 4,
+            channels: Vec::new(),
+        });
+        let request = message_box.get_difference().unwrap();
+        assert_eq!((request.pts, request.qts, request.date), (10, 2, 123));
+        assert!(message_box.check_deadlines() <= Instant::now());
+
+        assert!(MessageBox::new().get_difference().is_none());
+    }
+}
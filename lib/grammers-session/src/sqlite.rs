@@ -0,0 +1,489 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use crate::generated::{enums, types};
+use crate::storage::{SessionStore, StoreFuture};
+use crate::Session;
+use log::warn;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How long to wait for other connections to release the database before failing.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS datacenter (
+    id INTEGER PRIMARY KEY,
+    ipv4 INTEGER,
+    ipv6 BLOB,
+    port INTEGER NOT NULL,
+    auth_key BLOB
+);
+CREATE TABLE IF NOT EXISTS user (
+    id INTEGER NOT NULL,
+    dc INTEGER NOT NULL,
+    bot INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS home_dc (
+    dc INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS update_state (
+    pts INTEGER NOT NULL,
+    qts INTEGER NOT NULL,
+    date INTEGER NOT NULL,
+    seq INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS channel_state (
+    channel_id INTEGER PRIMARY KEY,
+    pts INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS server_salt (
+    dc INTEGER NOT NULL,
+    valid_since INTEGER NOT NULL,
+    valid_until INTEGER NOT NULL,
+    salt INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS future_auth_token (
+    token BLOB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS chat (
+    id INTEGER PRIMARY KEY,
+    ty INTEGER NOT NULL,
+    access_hash INTEGER,
+    username TEXT,
+    phone TEXT
+);
+CREATE TABLE IF NOT EXISTS secret_chat (
+    id INTEGER PRIMARY KEY,
+    access_hash INTEGER NOT NULL,
+    originator INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    key BLOB,
+    private_key BLOB,
+    layer INTEGER NOT NULL,
+    received INTEGER NOT NULL,
+    sent INTEGER NOT NULL,
+    key_uses INTEGER NOT NULL,
+    exchange_id INTEGER,
+    exchange_private_key BLOB,
+    exchange_key BLOB,
+    previous_key BLOB
+);
+";
+
+/// A [`SessionStore`] which persists the session to a SQLite database.
+///
+/// Unlike [`FileSession`](crate::FileSession), the data is stored in tables rather than as a
+/// single serialized blob, which makes it possible to inspect and query the session, and to
+/// cache the chats seen by the client along with it:
+///
+/// * `datacenter(id, ipv4, ipv6, port, auth_key)`, with the authorization key of each datacenter.
+/// * `user(id, dc, bot)`, with the logged-in user and their home datacenter, if any.
+/// * `home_dc(dc)`, with the datacenter to use while the user is not known yet, if any.
+/// * `update_state(pts, qts, date, seq)` and `channel_state(channel_id, pts)`, with the state
+///   used to catch up on missed updates.
+/// * `server_salt(dc, valid_since, valid_until, salt)`, with the known server salts.
+/// * `future_auth_token(token)`, with the token to log in again faster after logging out.
+/// * `chat(id, ty, access_hash, username, phone)`, with the chats cached by the session (see
+///   [`Session::cache_peers`]).
+/// * `secret_chat(id, access_hash, originator, user_id, key, ...)`, with the keys and sequence
+///   numbers of the secret chats (see [`Session::set_secret_chat`]).
+///
+/// The database is opened in write-ahead logging mode, so it can be read while it's being
+/// written to, and every save is done in a single transaction, so it can also be shared by
+/// several processes.
+pub struct SqliteSession {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteSession {
+    /// Open the database at the given path, creating it (and its tables) if it does not exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let conn = Connection::open(path).map_err(io::Error::other)?;
+        conn.busy_timeout(BUSY_TIMEOUT).map_err(io::Error::other)?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))
+            .map_err(io::Error::other)?;
+        conn.execute_batch(SCHEMA).map_err(io::Error::other)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Load the session stored in the database, which is empty if none was saved yet.
+    pub fn load_session(&self) -> io::Result<Session> {
+        let conn = self.conn.lock().unwrap();
+        load_session(&conn).map_err(io::Error::other)
+    }
+
+    /// Save the session to the database, replacing the one that was saved before.
+    pub fn save_session(&self, session: &Session) -> io::Result<()> {
+        let session = session.to_raw();
+        let mut conn = self.conn.lock().unwrap();
+        save_session(&mut conn, &session).map_err(io::Error::other)
+    }
+}
+
+impl SessionStore for SqliteSession {
+    fn load(&self) -> StoreFuture<'_, Option<Vec<u8>>> {
+        let result = self.load_session().map(|session| Some(session.save()));
+        Box::pin(async move { result })
+    }
+
+    fn save(&self, data: Vec<u8>) -> StoreFuture<'_, ()> {
+        let result = Session::load(&data)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+            .and_then(|session| self.save_session(&session));
+        Box::pin(async move { result })
+    }
+}
+
+fn load_session(conn: &Connection) -> rusqlite::Result<Session> {
+    let mut stmt = conn.prepare("SELECT id, ipv4, ipv6, port, auth_key FROM datacenter")?;
+    let dcs = stmt
+        .query_map([], |row| {
+            Ok(types::DataCenter {
+                id: row.get(0)?,
+                ipv4: row.get(1)?,
+                ipv6: row.get(2)?,
+                port: row.get(3)?,
+                auth: row.get(4)?,
+            }
+            .into())
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let user = conn
+        .query_row("SELECT id, dc, bot FROM user", [], |row| {
+            Ok(types::User {
+                id: row.get(0)?,
+                dc: row.get(1)?,
+                bot: row.get(2)?,
+            }
+            .into())
+        })
+        .optional()?;
+
+    let dc = conn
+        .query_row("SELECT dc FROM home_dc", [], |row| row.get(0))
+        .optional()?;
+
+    // A state which can't be read only disables catching up, as with the file format.
+    let state = load_state(conn).unwrap_or_else(|e| {
+        warn!("discarding update state which failed to load: {}", e);
+        None
+    });
+
+    let mut stmt = conn.prepare("SELECT dc, valid_since, valid_until, salt FROM server_salt")?;
+    let salts = stmt
+        .query_map([], |row| {
+            Ok(types::ServerSalt {
+                dc: row.get(0)?,
+                valid_since: row.get(1)?,
+                valid_until: row.get(2)?,
+                salt: row.get(3)?,
+            }
+            .into())
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let future_auth_token = conn
+        .query_row("SELECT token FROM future_auth_token", [], |row| row.get(0))
+        .optional()?;
+
+    let mut stmt = conn.prepare("SELECT id, ty, access_hash, username, phone FROM chat")?;
+    let peers = stmt
+        .query_map([], |row| {
+            Ok(types::CachedPeer {
+                id: row.get(0)?,
+                ty: row.get(1)?,
+                access_hash: row.get(2)?,
+                username: row.get(3)?,
+                phone: row.get(4)?,
+            }
+            .into())
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, access_hash, originator, user_id, key, private_key, layer, received, sent,
+        key_uses, exchange_id, exchange_private_key, exchange_key, previous_key FROM secret_chat",
+    )?;
+    let secret_chats = stmt
+        .query_map([], |row| {
+            Ok(types::SecretChatState {
+                id: row.get(0)?,
+                access_hash: row.get(1)?,
+                originator: row.get(2)?,
+                user_id: row.get(3)?,
+                key: row.get(4)?,
+                private_key: row.get(5)?,
+                layer: row.get(6)?,
+                received: row.get(7)?,
+                sent: row.get(8)?,
+                key_uses: row.get(9)?,
+                exchange_id: row.get(10)?,
+                exchange_private_key: row.get(11)?,
+                exchange_key: row.get(12)?,
+                previous_key: row.get(13)?,
+            }
+            .into())
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(Session::from_raw(types::Session {
+        dcs,
+        user,
+        state,
+        future_auth_token,
+        salts: if salts.is_empty() { None } else { Some(salts) },
+        peers: Some(peers),
+        dc,
+        state_version: None,
+        state_data: None,
+        secret_chats: if secret_chats.is_empty() {
+            None
+        } else {
+            Some(secret_chats)
+        },
+    }))
+}
+
+fn load_state(conn: &Connection) -> rusqlite::Result<Option<enums::UpdateState>> {
+    let mut stmt = conn.prepare("SELECT channel_id, pts FROM channel_state")?;
+    let channels = stmt
+        .query_map([], |row| {
+            Ok(types::ChannelState {
+                channel_id: row.get(0)?,
+                pts: row.get(1)?,
+            }
+            .into())
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    conn.query_row("SELECT pts, qts, date, seq FROM update_state", [], |row| {
+        Ok(types::UpdateState {
+            pts: row.get(0)?,
+            qts: row.get(1)?,
+            date: row.get(2)?,
+            seq: row.get(3)?,
+            channels,
+        }
+        .into())
+    })
+    .optional()
+}
+
+fn save_session(conn: &mut Connection, session: &types::Session) -> rusqlite::Result<()> {
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    tx.execute_batch(
+        "DELETE FROM datacenter;
+        DELETE FROM user;
+        DELETE FROM home_dc;
+        DELETE FROM update_state;
+        DELETE FROM channel_state;
+        DELETE FROM server_salt;
+        DELETE FROM future_auth_token;
+        DELETE FROM chat;
+        DELETE FROM secret_chat;",
+    )?;
+
+    for enums::DataCenter::Center(dc) in session.dcs.iter() {
+        tx.execute(
+            "INSERT INTO datacenter (id, ipv4, ipv6, port, auth_key) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![dc.id, dc.ipv4, dc.ipv6, dc.port, dc.auth],
+        )?;
+    }
+
+    if let Some(enums::User::User(user)) = &session.user {
+        tx.execute(
+            "INSERT INTO user (id, dc, bot) VALUES (?1, ?2, ?3)",
+            params![user.id, user.dc, user.bot],
+        )?;
+    }
+
+    if let Some(dc) = session.dc {
+        tx.execute("INSERT INTO home_dc (dc) VALUES (?1)", [dc])?;
+    }
+
+    if let Some(enums::UpdateState::State(state)) = &session.state {
+        tx.execute(
+            "INSERT INTO update_state (pts, qts, date, seq) VALUES (?1, ?2, ?3, ?4)",
+            params![state.pts, state.qts, state.date, state.seq],
+        )?;
+        for enums::ChannelState::State(channel) in state.channels.iter() {
+            tx.execute(
+                "INSERT INTO channel_state (channel_id, pts) VALUES (?1, ?2)",
+                params![channel.channel_id, channel.pts],
+            )?;
+        }
+    }
+
+    for enums::ServerSalt::Salt(salt) in session.salts.iter().flatten() {
+        tx.execute(
+            "INSERT INTO server_salt (dc, valid_since, valid_until, salt) VALUES (?1, ?2, ?3, ?4)",
+            params![salt.dc, salt.valid_since, salt.valid_until, salt.salt],
+        )?;
+    }
+
+    if let Some(token) = &session.future_auth_token {
+        tx.execute("INSERT INTO future_auth_token (token) VALUES (?1)", [token])?;
+    }
+
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO chat (id, ty, access_hash, username, phone) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for enums::CachedPeer::Peer(peer) in session.peers.iter().flatten() {
+            stmt.execute(params![
+                peer.id,
+                peer.ty,
+                peer.access_hash,
+                peer.username,
+                peer.phone
+            ])?;
+        }
+    }
+
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO secret_chat (id, access_hash, originator, user_id, key, private_key, layer,
+            received, sent, key_uses, exchange_id, exchange_private_key, exchange_key, previous_key)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        )?;
+        for enums::SecretChatState::State(chat) in session.secret_chats.iter().flatten() {
+            stmt.execute(params![
+                chat.id,
+                chat.access_hash,
+                chat.originator,
+                chat.user_id,
+                chat.key,
+                chat.private_key,
+                chat.layer,
+                chat.received,
+                chat.sent,
+                chat.key_uses,
+                chat.exchange_id,
+                chat.exchange_private_key,
+                chat.exchange_key,
+                chat.previous_key
+            ])?;
+        }
+    }
+
+    tx.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CachedPeer, PackedChat, PackedType, SecretChatState, UpdateState};
+    use std::path::PathBuf;
+
+    struct TempDb(PathBuf);
+
+    impl TempDb {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "grammers-session-{name}-{}.sqlite",
+                std::process::id()
+            ));
+            let db = Self(path);
+            db.remove();
+            db
+        }
+
+        fn remove(&self) {
+            for suffix in ["", "-wal", "-shm"] {
+                let mut path = self.0.clone().into_os_string();
+                path.push(suffix);
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+
+    impl Drop for TempDb {
+        fn drop(&mut self) {
+            self.remove();
+        }
+    }
+
+    #[test]
+    fn sqlite_session_round_trip() {
+        let db = TempDb::new("round-trip");
+        let user = CachedPeer {
+            chat: PackedChat {
+                ty: PackedType::User,
+                id: 123,
+                access_hash: Some(456),
+            },
+            username: Some("someone".to_string()),
+            phone: None,
+        };
+
+        {
+            let store = SqliteSession::open(&db.0).unwrap();
+            let session = store.load_session().unwrap();
+            assert!(session.get_dcs().is_empty());
+            assert!(!session.signed_in());
+
+            session.insert_dc(2, "127.0.0.1:443".parse().unwrap(), [7; 256]);
+            session.set_user(123, 2, false);
+            session.set_state(UpdateState {
+                pts: 1,
+                qts: 2,
+                date: 3,
+                seq: 4,
+                channels: vec![types::ChannelState {
+                    channel_id: 5,
+                    pts: 6,
+                }
+                .into()],
+            });
+            session.cache_peers([user.clone()]);
+            session.set_secret_chat(SecretChatState {
+                id: 7,
+                access_hash: 8,
+                originator: true,
+                user_id: 123,
+                key: Some(vec![9; 256]),
+                private_key: None,
+                layer: 101,
+                received: 1,
+                sent: 2,
+                key_uses: 3,
+                exchange_id: None,
+                exchange_private_key: None,
+                exchange_key: None,
+                previous_key: None,
+            });
+            store.save_session(&session).unwrap();
+        }
+
+        let store = SqliteSession::open(&db.0).unwrap();
+        let session = store.load_session().unwrap();
+        assert_eq!(session.dc_auth_key(2), Some([7; 256]));
+        assert_eq!(session.get_dcs()[0].port, 443);
+        assert_eq!(session.get_user().map(|user| user.id), Some(123));
+        assert_eq!(
+            session.get_state().map(|state| state.channels.len()),
+            Some(1)
+        );
+        assert_eq!(session.cached_peer(123), Some(user));
+        assert_eq!(
+            session.secret_chat(7).map(|chat| chat.key),
+            Some(Some(vec![9; 256]))
+        );
+
+        // Forgotten chats are removed from the database too.
+        session.clear_authorization();
+        store.save_session(&session).unwrap();
+        assert!(store.load_session().unwrap().cached_peers().is_empty());
+    }
+}
@@ -0,0 +1,467 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use crate::encryption::{self, Cipher, EncryptionError};
+use crate::{Error, Session};
+use log::warn;
+use std::fs::{self, File, OpenOptions};
+use std::future::Future;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+/// The future returned by the methods of a [`SessionStore`].
+pub type StoreFuture<'a, T> = Pin<Box<dyn Future<Output = io::Result<T>> + Send + 'a>>;
+
+/// Where the data of a [`Session`](crate::Session) is persisted, as produced by
+/// [`Session::save`](crate::Session::save).
+///
+/// Implementing this trait allows keeping the session anywhere, such as in a database or a
+/// secret manager, instead of a file.
+pub trait SessionStore: Send + Sync {
+    /// Load the data that was last saved, or `None` if nothing was saved yet.
+    fn load(&self) -> StoreFuture<'_, Option<Vec<u8>>>;
+
+    /// Save the data, replacing whatever was saved before.
+    fn save(&self, data: Vec<u8>) -> StoreFuture<'_, ()>;
+}
+
+/// A [`SessionStore`] which persists the session to a file.
+///
+/// The file contains the authorization key, which grants full access to the account, so it
+/// can optionally be [encrypted](FileSession::encrypted) with a passphrase.
+///
+/// Saving writes a temporary file which then replaces the original, keeping the previous
+/// contents in a `.bak` file next to it. If the file is later found to be corrupted, the backup
+/// is loaded instead. A missing file is never restored from its backup.
+///
+/// The backup of an encrypted session is only kept if it is encrypted too.
+#[derive(Clone, Debug)]
+pub struct FileSession {
+    path: PathBuf,
+    cipher: Option<Arc<Cipher>>,
+}
+
+impl FileSession {
+    /// Persist the session to the file at the given path, which is created when the session is
+    /// first saved if it does not exist.
+    ///
+    /// Loading fails with [`EncryptionError::PassphraseRequired`] if the file is encrypted.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            cipher: None,
+        }
+    }
+
+    /// Like [`FileSession::new`], but the file is encrypted with AES-256-GCM, using a key
+    /// derived from the passphrase with PBKDF2-HMAC-SHA256.
+    ///
+    /// Loading fails with [`EncryptionError::WrongPassphrase`] if the passphrase is not the one
+    /// the file was encrypted with, [`EncryptionError::Corrupted`] if the file was damaged, and
+    /// [`EncryptionError::NotEncrypted`] if the file is not encrypted.
+    pub fn encrypted<P: Into<PathBuf>, S: Into<String>>(path: P, passphrase: S) -> Self {
+        Self {
+            path: path.into(),
+            cipher: Some(Arc::new(Cipher::new(
+                passphrase.into(),
+                encryption::ITERATIONS,
+            ))),
+        }
+    }
+
+    /// Encrypt the existing, unencrypted file with the passphrase of this session, so that it
+    /// can be loaded from now on.
+    ///
+    /// Does nothing if the file does not exist or is already encrypted with this passphrase.
+    /// The unencrypted backup of the file, if any, is removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the session was not created with [`FileSession::encrypted`].
+    pub fn encrypt_in_place(&self) -> io::Result<()> {
+        let cipher = self
+            .cipher
+            .as_ref()
+            .expect("only encrypted sessions can encrypt their file");
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        if encryption::is_encrypted(&data) {
+            cipher.decrypt(&data).map_err(io::Error::from)?;
+        } else {
+            replace_file(&self.path, &cipher.encrypt(&data)?)?;
+        }
+        remove_plaintext_backup(&self.path)
+    }
+}
+
+fn write_file(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(path)?;
+    file.write_all(data)?;
+    file.sync_data()
+}
+
+/// The path of a file next to `path`, with the given suffix appended to its name.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut sibling = path.as_os_str().to_owned();
+    sibling.push(suffix);
+    sibling.into()
+}
+
+/// Replace the contents of the file with `data`, keeping its previous contents in a `.bak` file
+/// next to it.
+///
+/// The data is written to a temporary file first, which is then renamed over the original, so
+/// the file is never left half-written or missing even if the process dies while saving.
+pub(crate) fn replace_file(path: &Path, data: &[u8]) -> io::Result<()> {
+    let temp = sibling(path, ".tmp");
+    write_file(&temp, data)?;
+    let backup = sibling(path, ".bak");
+    match fs::copy(path, &backup) {
+        Ok(_) => {}
+        // A backup of some other file which used to be there is not worth keeping.
+        Err(e) if e.kind() == io::ErrorKind::NotFound => remove_file(&backup)?,
+        Err(e) => return Err(e),
+    }
+    fs::rename(&temp, path)?;
+    sync_parent(path)
+}
+
+/// Remove the file, if it exists.
+fn remove_file(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Remove the `.bak` file made by [`replace_file`] if it is not encrypted, so that the keys of
+/// an encrypted session are not left in plain text next to it.
+fn remove_plaintext_backup(path: &Path) -> io::Result<()> {
+    let backup = sibling(path, ".bak");
+    match fs::read(&backup) {
+        Ok(data) if !encryption::is_encrypted(&data) => remove_file(&backup),
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Make the renames in the directory containing the file durable.
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => File::open(parent)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Read the file and decode its contents, falling back to the `.bak` file made by
+/// [`replace_file`] if its contents are corrupted.
+///
+/// Returns `None` if the file does not exist, even if its backup does, since it may have been
+/// removed on purpose.
+pub(crate) fn read_file<T>(
+    path: &Path,
+    decode: impl Fn(Vec<u8>) -> io::Result<T>,
+) -> io::Result<Option<T>> {
+    let read = |path: &Path| match fs::read(path) {
+        Ok(data) => decode(data).map(Some),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    };
+
+    let error = match read(path) {
+        Ok(value) => return Ok(value),
+        Err(e) if is_corrupted(&e) => e,
+        Err(e) => return Err(e),
+    };
+    match read(&sibling(path, ".bak")) {
+        Ok(Some(value)) => {
+            warn!(
+                "session file {:?} is corrupted ({}), using its backup",
+                path, error
+            );
+            Ok(Some(value))
+        }
+        _ => Err(error),
+    }
+}
+
+/// Whether the error means the data was damaged, rather than unusable for a different reason
+/// (such as requiring a passphrase).
+fn is_corrupted(error: &io::Error) -> bool {
+    match error.get_ref() {
+        Some(inner) => {
+            matches!(
+                inner.downcast_ref::<Error>(),
+                Some(Error::MalformedData | Error::Corrupted)
+            ) || matches!(
+                inner.downcast_ref::<EncryptionError>(),
+                Some(EncryptionError::Corrupted)
+            )
+        }
+        None => false,
+    }
+}
+
+impl SessionStore for FileSession {
+    fn load(&self) -> StoreFuture<'_, Option<Vec<u8>>> {
+        Box::pin(async move {
+            read_file(&self.path, |data| {
+                let data = match &self.cipher {
+                    Some(cipher) => cipher.decrypt(&data)?,
+                    None if encryption::is_encrypted(&data) => {
+                        return Err(EncryptionError::PassphraseRequired.into())
+                    }
+                    None => data,
+                };
+                // Checked here so that a corrupted file falls back to its backup.
+                Session::load(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                Ok(data)
+            })
+        })
+    }
+
+    fn save(&self, data: Vec<u8>) -> StoreFuture<'_, ()> {
+        Box::pin(async move {
+            match &self.cipher {
+                Some(cipher) => {
+                    replace_file(&self.path, &cipher.encrypt(&data)?)?;
+                    remove_plaintext_backup(&self.path)
+                }
+                None => replace_file(&self.path, &data),
+            }
+        })
+    }
+}
+
+/// A [`SessionStore`] which keeps the session in memory, and is lost once dropped.
+///
+/// Useful for short-lived clients and tests, which don't need the session to outlive them.
+#[derive(Debug, Default)]
+pub struct MemorySession {
+    data: Mutex<Option<Vec<u8>>>,
+}
+
+impl MemorySession {
+    /// Create a new store with nothing saved in it.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SessionStore for MemorySession {
+    fn load(&self) -> StoreFuture<'_, Option<Vec<u8>>> {
+        let data = self.data.lock().unwrap().clone();
+        Box::pin(async move { Ok(data) })
+    }
+
+    fn save(&self, data: Vec<u8>) -> StoreFuture<'_, ()> {
+        *self.data.lock().unwrap() = Some(data);
+        Box::pin(async move { Ok(()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::task::{Context, Poll, Waker};
+
+    /// Drive a future which never waits to completion.
+    fn ready<T>(future: impl Future<Output = T>) -> T {
+        match std::pin::pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(value) => value,
+            Poll::Pending => panic!("future should be ready"),
+        }
+    }
+
+    fn check_round_trip(store: &dyn SessionStore) {
+        let session = ready(Session::load_from(store)).unwrap();
+        assert!(session.get_dcs().is_empty());
+
+        session.insert_dc(2, "127.0.0.1:443".parse().unwrap(), [1; 256]);
+        ready(session.save_to(store)).unwrap();
+
+        let session = ready(Session::load_from(store)).unwrap();
+        assert_eq!(session.dc_auth_key(2), Some([1; 256]));
+    }
+
+    #[test]
+    fn memory_session_round_trip() {
+        check_round_trip(&MemorySession::new());
+    }
+
+    #[test]
+    fn file_session_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "grammers-session-test-{}.session",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        check_round_trip(&FileSession::new(&path));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// An encrypted session which derives its key quickly, to keep the tests fast.
+    fn encrypted(path: &Path, passphrase: &str) -> FileSession {
+        FileSession {
+            path: path.to_path_buf(),
+            cipher: Some(Arc::new(Cipher::new(passphrase.to_string(), 1000))),
+        }
+    }
+
+    fn encryption_error(store: &FileSession) -> Option<EncryptionError> {
+        let error = ready(store.load()).unwrap_err();
+        error.get_ref()?.downcast_ref().cloned()
+    }
+
+    #[test]
+    fn encrypted_file_session() {
+        let path = std::env::temp_dir().join(format!(
+            "grammers-session-test-{}.encrypted.session",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        // Start with an unencrypted session, which needs to be migrated.
+        let session = Session::new();
+        session.insert_dc(2, "127.0.0.1:443".parse().unwrap(), [1; 256]);
+        ready(session.save_to(&FileSession::new(&path))).unwrap();
+        let store = encrypted(&path, "secret");
+        assert_eq!(
+            encryption_error(&store),
+            Some(EncryptionError::NotEncrypted)
+        );
+
+        store.encrypt_in_place().unwrap();
+        store.encrypt_in_place().unwrap();
+        let data = std::fs::read(&path).unwrap();
+        assert!(!data.windows(256).any(|w| w == [1; 256]));
+        // The unencrypted contents are not kept as a backup.
+        for suffix in [".bak", ".tmp"] {
+            assert!(!sibling(&path, suffix).exists());
+        }
+        assert!(matches!(Session::load(&data), Err(crate::Error::Encrypted)));
+
+        let session = ready(Session::load_from(&store)).unwrap();
+        session.insert_dc(4, "127.0.0.1:443".parse().unwrap(), [4; 256]);
+        ready(session.save_to(&store)).unwrap();
+        let store = encrypted(&path, "secret");
+        assert_eq!(
+            ready(Session::load_from(&store)).unwrap().dc_auth_key(2),
+            Some([1; 256])
+        );
+
+        assert_eq!(
+            encryption_error(&FileSession::new(&path)),
+            Some(EncryptionError::PassphraseRequired)
+        );
+        assert_eq!(
+            encryption_error(&encrypted(&path, "wrong")),
+            Some(EncryptionError::WrongPassphrase)
+        );
+
+        let mut data = std::fs::read(&path).unwrap();
+        *data.last_mut().unwrap() ^= 1;
+        std::fs::write(&path, data).unwrap();
+        // The backup of the previous save is used while it exists.
+        let session = ready(Session::load_from(&encrypted(&path, "secret"))).unwrap();
+        assert_eq!(session.dc_auth_key(2), Some([1; 256]));
+        assert_eq!(session.dc_auth_key(4), None);
+        std::fs::remove_file(sibling(&path, ".bak")).unwrap();
+        assert_eq!(
+            encryption_error(&encrypted(&path, "secret")),
+            Some(EncryptionError::Corrupted)
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn encrypted_file_session_with_excessive_iterations() {
+        let path = std::env::temp_dir().join(format!(
+            "grammers-session-test-{}.iterations.session",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(sibling(&path, ".bak"));
+
+        let store = encrypted(&path, "secret");
+        ready(Session::new().save_to(&store)).unwrap();
+
+        // Tampered with so that deriving the key would take hours.
+        let mut data = std::fs::read(&path).unwrap();
+        data[encryption::MAGIC.len()..][..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&path, data).unwrap();
+        assert_eq!(
+            encryption_error(&encrypted(&path, "secret")),
+            Some(EncryptionError::Corrupted)
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn file_session_falls_back_to_backup() {
+        let path = std::env::temp_dir().join(format!(
+            "grammers-session-test-{}.backup.session",
+            std::process::id()
+        ));
+        let backup = sibling(&path, ".bak");
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&backup);
+
+        let session = Session::new();
+        session.insert_dc(2, "127.0.0.1:443".parse().unwrap(), [1; 256]);
+        session.save_to_file(&path).unwrap();
+        session.insert_dc(4, "127.0.0.1:443".parse().unwrap(), [4; 256]);
+        session.save_to_file(&path).unwrap();
+        assert!(!sibling(&path, ".tmp").exists());
+
+        // Truncated as if the process died while writing it.
+        let data = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &data[..data.len() / 2]).unwrap();
+        assert!(matches!(
+            Session::load(&std::fs::read(&path).unwrap()),
+            Err(Error::Corrupted)
+        ));
+
+        let session = Session::load_file(&path).unwrap();
+        assert_eq!(session.dc_auth_key(2), Some([1; 256]));
+        assert_eq!(session.dc_auth_key(4), None);
+        let session = ready(Session::load_from(&FileSession::new(&path))).unwrap();
+        assert_eq!(session.dc_auth_key(2), Some([1; 256]));
+
+        // Removed on purpose, so the backup must not bring it back.
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            Session::load_file(&path),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound
+        ));
+        let session = Session::load_file_or_create(&path).unwrap();
+        assert_eq!(session.dc_auth_key(2), None);
+        assert!(path.exists());
+        assert!(!backup.exists());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
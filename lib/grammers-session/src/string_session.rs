@@ -0,0 +1,475 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Conversion from and to the string sessions of other libraries.
+use crate::Session;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// The version of the Telethon string session format, which prefixes the string.
+const TELETHON_VERSION: char = '1';
+
+/// Addresses of the datacenters, indexed by their identifier, for the formats which don't
+/// include them.
+const DC_ADDRESSES: [Ipv4Addr; 6] = [
+    Ipv4Addr::UNSPECIFIED,
+    Ipv4Addr::new(149, 154, 175, 53),
+    Ipv4Addr::new(149, 154, 167, 51),
+    Ipv4Addr::new(149, 154, 175, 100),
+    Ipv4Addr::new(149, 154, 167, 92),
+    Ipv4Addr::new(91, 108, 56, 190),
+];
+
+/// Like [`DC_ADDRESSES`], but for the test servers.
+const TEST_DC_ADDRESSES: [Ipv4Addr; 4] = [
+    Ipv4Addr::UNSPECIFIED,
+    Ipv4Addr::new(149, 154, 175, 10),
+    Ipv4Addr::new(149, 154, 167, 40),
+    Ipv4Addr::new(149, 154, 175, 117),
+];
+
+const DC_PORT: u16 = 443;
+
+/// The error type which is returned when a string session can't be converted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StringSessionError {
+    /// The string was produced by a version of the format which is not supported.
+    UnsupportedVersion,
+    /// The string is not valid base64, the first invalid character being at the given position.
+    InvalidBase64 { position: usize },
+    /// The decoded data does not have the length of any known layout of the format.
+    InvalidLength { len: usize },
+    /// The field with the given name has an invalid value.
+    InvalidField {
+        field: &'static str,
+        reason: &'static str,
+    },
+    /// The session has no authorization key for the datacenter of the account, so there is
+    /// nothing to convert.
+    MissingAuthKey,
+}
+
+impl fmt::Display for StringSessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedVersion => write!(f, "string session error: unsupported version"),
+            Self::InvalidBase64 { position } => write!(
+                f,
+                "string session error: invalid base64 character at position {position}"
+            ),
+            Self::InvalidLength { len } => write!(
+                f,
+                "string session error: decoded data has an unknown length of {len} bytes"
+            ),
+            Self::InvalidField { field, reason } => {
+                write!(f, "string session error: invalid field `{field}`: {reason}")
+            }
+            Self::MissingAuthKey => write!(
+                f,
+                "string session error: no authorization key for the home datacenter"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StringSessionError {}
+
+/// Reads the fixed-size fields of a decoded string session, in order.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> &'a [u8] {
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        bytes
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.bytes(1)[0]
+    }
+
+    fn bool(&mut self, field: &'static str) -> Result<bool, StringSessionError> {
+        match self.u8() {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StringSessionError::InvalidField {
+                field,
+                reason: "not a boolean",
+            }),
+        }
+    }
+
+    fn u16(&mut self) -> u16 {
+        u16::from_be_bytes(self.bytes(2).try_into().unwrap())
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_be_bytes(self.bytes(4).try_into().unwrap())
+    }
+
+    fn u64(&mut self) -> u64 {
+        u64::from_be_bytes(self.bytes(8).try_into().unwrap())
+    }
+
+    fn auth_key(&mut self) -> Result<[u8; 256], StringSessionError> {
+        let key: [u8; 256] = self.bytes(256).try_into().unwrap();
+        if key.iter().all(|&b| b == 0) {
+            return Err(StringSessionError::InvalidField {
+                field: "auth_key",
+                reason: "the key is empty",
+            });
+        }
+        Ok(key)
+    }
+}
+
+fn check_dc_id(dc_id: u8, max: usize) -> Result<i32, StringSessionError> {
+    if dc_id == 0 || dc_id as usize >= max {
+        return Err(StringSessionError::InvalidField {
+            field: "dc_id",
+            reason: "unknown datacenter",
+        });
+    }
+    Ok(dc_id as i32)
+}
+
+impl Session {
+    /// Create a session from a string session produced by Telethon's `StringSession`.
+    ///
+    /// The string only contains the authorization key of the account's datacenter, and not
+    /// who the logged-in user is. The user will be known once it's fetched, for example, with
+    /// `Client::get_me`.
+    pub fn from_telethon_string(string: &str) -> Result<Self, StringSessionError> {
+        let mut chars = string.trim().chars();
+        if chars.next() != Some(TELETHON_VERSION) {
+            return Err(StringSessionError::UnsupportedVersion);
+        }
+        let data = base64_decode(chars.as_str()).map_err(|position| {
+            StringSessionError::InvalidBase64 {
+                position: position + 1,
+            }
+        })?;
+
+        // dc_id:u8, ip:4 or 16 bytes, port:u16, auth_key:256 bytes
+        let ip_len = match data.len() {
+            263 => 4,
+            275 => 16,
+            len => return Err(StringSessionError::InvalidLength { len }),
+        };
+        let mut reader = Reader { data: &data };
+        let dc_id = reader.u8();
+        let ip = reader.bytes(ip_len);
+        let port = reader.u16();
+        let auth_key = reader.auth_key()?;
+
+        let dc_id = check_dc_id(dc_id, DC_ADDRESSES.len())?;
+        let ip = match ip_len {
+            4 => Ipv4Addr::from(<[u8; 4]>::try_from(ip).unwrap()).into(),
+            _ => Ipv6Addr::from(<[u8; 16]>::try_from(ip).unwrap()).into(),
+        };
+        if port == 0 {
+            return Err(StringSessionError::InvalidField {
+                field: "port",
+                reason: "the port is zero",
+            });
+        }
+
+        let session = Session::new();
+        session.insert_dc(dc_id, SocketAddr::new(ip, port), auth_key);
+        session.set_home_dc(dc_id);
+        Ok(session)
+    }
+
+    /// Create a session from a string session produced by Pyrogram's `export_session_string`.
+    ///
+    /// The string doesn't contain the address of the datacenter, so the well-known address of
+    /// the datacenter is used. Sessions for the test servers also need
+    /// `InitParams::test_dc` to be enabled in the client.
+    pub fn from_pyrogram_string(string: &str) -> Result<Self, StringSessionError> {
+        let data = base64_decode(string.trim())
+            .map_err(|position| StringSessionError::InvalidBase64 { position })?;
+
+        let mut reader = Reader { data: &data };
+        let (dc_id, test_mode, auth_key, user_id, is_bot) = match data.len() {
+            // dc_id:u8, api_id:u32, test_mode:bool, auth_key, user_id:u64, is_bot:bool
+            271 => {
+                let dc_id = reader.u8();
+                let _api_id = reader.u32();
+                let test_mode = reader.bool("test_mode")?;
+                let auth_key = reader.auth_key()?;
+                let user_id = reader.u64();
+                (dc_id, test_mode, auth_key, user_id, reader.bool("is_bot")?)
+            }
+            // dc_id:u8, test_mode:bool, auth_key, user_id:u64 or u32, is_bot:bool
+            267 | 263 => {
+                let dc_id = reader.u8();
+                let test_mode = reader.bool("test_mode")?;
+                let auth_key = reader.auth_key()?;
+                let user_id = if data.len() == 267 {
+                    reader.u64()
+                } else {
+                    reader.u32() as u64
+                };
+                (dc_id, test_mode, auth_key, user_id, reader.bool("is_bot")?)
+            }
+            len => return Err(StringSessionError::InvalidLength { len }),
+        };
+
+        let addresses = if test_mode {
+            &TEST_DC_ADDRESSES[..]
+        } else {
+            &DC_ADDRESSES[..]
+        };
+        let dc_id = check_dc_id(dc_id, addresses.len())?;
+        let user_id = match i64::try_from(user_id) {
+            Ok(id) if id != 0 => id,
+            _ => {
+                return Err(StringSessionError::InvalidField {
+                    field: "user_id",
+                    reason: "not a valid user identifier",
+                })
+            }
+        };
+
+        let session = Session::new();
+        session.insert_dc(
+            dc_id,
+            SocketAddr::new(addresses[dc_id as usize].into(), DC_PORT),
+            auth_key,
+        );
+        session.set_user(user_id, dc_id, is_bot);
+        Ok(session)
+    }
+
+    /// Convert the session into a string session which Telethon's `StringSession` can load.
+    ///
+    /// Only the authorization key of the account's datacenter is included.
+    pub fn to_telethon_string(&self) -> Result<String, StringSessionError> {
+        let dc_id = self.home_dc().ok_or(StringSessionError::MissingAuthKey)?;
+        let dc = self
+            .get_dcs()
+            .into_iter()
+            .find(|dc| dc.id == dc_id)
+            .ok_or(StringSessionError::MissingAuthKey)?;
+        let auth_key = dc
+            .auth
+            .filter(|key| key.len() == 256)
+            .ok_or(StringSessionError::MissingAuthKey)?;
+
+        let mut data = Vec::with_capacity(275);
+        data.push(dc_id as u8);
+        match (dc.ipv4, dc.ipv6) {
+            (Some(ipv4), _) => data.extend(ipv4.to_le_bytes()),
+            (None, Some(ipv6)) => data.extend(ipv6),
+            (None, None) => data.extend(DC_ADDRESSES[0].octets()),
+        }
+        data.extend((dc.port as u16).to_be_bytes());
+        data.extend(auth_key);
+
+        let mut string = String::with_capacity(1 + data.len().div_ceil(3) * 4);
+        string.push(TELETHON_VERSION);
+        string.push_str(&base64_encode(&data));
+        Ok(string)
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Encode the data as URL-safe base64, with padding.
+fn base64_encode(data: &[u8]) -> String {
+    let mut result = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+/// Decode base64 in either its standard or URL-safe alphabet, with or without padding.
+///
+/// Returns the position of the first invalid character on error.
+fn base64_decode(string: &str) -> Result<Vec<u8>, usize> {
+    let trimmed = string.trim_end_matches('=');
+    let mut result = Vec::with_capacity(trimmed.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for (position, c) in trimmed.bytes().enumerate() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'-' | b'+' => 62,
+            b'_' | b'/' => 63,
+            _ => return Err(position),
+        };
+        acc = (acc << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((acc >> bits) as u8);
+        }
+    }
+    // A single leftover character can't encode a whole byte.
+    if trimmed.len() % 4 == 1 {
+        return Err(trimmed.len() - 1);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pyrogram_data(dc_id: u8, test_mode: u8, user_id: u64, is_bot: u8) -> Vec<u8> {
+        let mut data = vec![dc_id];
+        data.extend(12345u32.to_be_bytes());
+        data.push(test_mode);
+        data.extend([7; 256]);
+        data.extend(user_id.to_be_bytes());
+        data.push(is_bot);
+        data
+    }
+
+    #[test]
+    fn check_base64() {
+        assert_eq!(base64_encode(b"hello"), "aGVsbG8=");
+        assert_eq!(base64_encode(&[0xfb, 0xff]), "-_8=");
+        assert_eq!(base64_decode("aGVsbG8=").unwrap(), b"hello");
+        assert_eq!(base64_decode("aGVsbG8").unwrap(), b"hello");
+        assert_eq!(base64_decode("+/8").unwrap(), [0xfb, 0xff]);
+        assert_eq!(base64_decode("aGV*bG8"), Err(3));
+    }
+
+    #[test]
+    fn telethon_string_round_trip() {
+        let session = Session::new();
+        session.insert_dc(4, "149.154.167.92:443".parse().unwrap(), [7; 256]);
+        session.set_user(123, 4, false);
+
+        let string = session.to_telethon_string().unwrap();
+        assert_eq!(string.len(), 353);
+        let data = base64_decode(&string[1..]).unwrap();
+        assert_eq!(data[..7], [4, 149, 154, 167, 92, 0x01, 0xbb]);
+        assert_eq!(data[7..], [7; 256]);
+
+        let session = Session::from_telethon_string(&string).unwrap();
+        assert_eq!(session.home_dc(), Some(4));
+        assert_eq!(session.dc_auth_key(4), Some([7; 256]));
+        assert_eq!(session.get_dcs()[0].port, 443);
+        assert!(!session.signed_in());
+        assert_eq!(session.to_telethon_string().unwrap(), string);
+    }
+
+    #[test]
+    fn telethon_string_with_ipv6() {
+        let mut data = vec![2];
+        data.extend(Ipv6Addr::LOCALHOST.octets());
+        data.extend(443u16.to_be_bytes());
+        data.extend([7; 256]);
+        let string = format!("1{}", base64_encode(&data));
+
+        let session = Session::from_telethon_string(&string).unwrap();
+        assert_eq!(
+            session.get_dcs()[0].ipv6,
+            Some(Ipv6Addr::LOCALHOST.octets())
+        );
+        assert_eq!(session.to_telethon_string().unwrap(), string);
+    }
+
+    #[test]
+    fn telethon_string_errors() {
+        let mut data = vec![9, 127, 0, 0, 1, 0x01, 0xbb];
+        data.extend([7; 256]);
+        let string = format!("1{}", base64_encode(&data));
+
+        assert_eq!(
+            Session::from_telethon_string(&string).err(),
+            Some(StringSessionError::InvalidField {
+                field: "dc_id",
+                reason: "unknown datacenter"
+            })
+        );
+        assert_eq!(
+            Session::from_telethon_string(&string[1..]).err(),
+            Some(StringSessionError::UnsupportedVersion)
+        );
+        assert_eq!(
+            Session::from_telethon_string("1AAA.").err(),
+            Some(StringSessionError::InvalidBase64 { position: 4 })
+        );
+        assert_eq!(
+            Session::from_telethon_string("1AAAA").err(),
+            Some(StringSessionError::InvalidLength { len: 3 })
+        );
+        assert_eq!(
+            Session::new().to_telethon_string().err(),
+            Some(StringSessionError::MissingAuthKey)
+        );
+    }
+
+    #[test]
+    fn pyrogram_string() {
+        let string = base64_encode(&pyrogram_data(2, 0, 123, 1));
+        let session = Session::from_pyrogram_string(string.trim_end_matches('=')).unwrap();
+        let user = session.get_user().unwrap();
+        assert_eq!((user.id, user.dc, user.bot), (123, 2, true));
+        assert_eq!(session.dc_auth_key(2), Some([7; 256]));
+        assert_eq!(
+            session.get_dcs()[0].ipv4,
+            Some(i32::from_le_bytes([149, 154, 167, 51]))
+        );
+
+        // Older versions had no API ID, and 32-bit user identifiers.
+        let mut data = vec![1, 1];
+        data.extend([7; 256]);
+        data.extend(456u32.to_be_bytes());
+        data.push(0);
+        let session = Session::from_pyrogram_string(&base64_encode(&data)).unwrap();
+        let user = session.get_user().unwrap();
+        assert_eq!((user.id, user.dc, user.bot), (456, 1, false));
+        assert_eq!(
+            session.get_dcs()[0].ipv4,
+            Some(i32::from_le_bytes([149, 154, 175, 10]))
+        );
+    }
+
+    #[test]
+    fn pyrogram_string_errors() {
+        let error = |data: &[u8]| Session::from_pyrogram_string(&base64_encode(data)).err();
+        let field = |field| {
+            Some(StringSessionError::InvalidField {
+                field,
+                reason: match field {
+                    "test_mode" | "is_bot" => "not a boolean",
+                    "dc_id" => "unknown datacenter",
+                    _ => "not a valid user identifier",
+                },
+            })
+        };
+
+        assert_eq!(error(&pyrogram_data(2, 2, 123, 0)), field("test_mode"));
+        assert_eq!(error(&pyrogram_data(2, 0, 123, 5)), field("is_bot"));
+        assert_eq!(error(&pyrogram_data(5, 1, 123, 0)), field("dc_id"));
+        assert_eq!(error(&pyrogram_data(2, 0, 0, 0)), field("user_id"));
+        assert_eq!(
+            error(&[0; 10]),
+            Some(StringSessionError::InvalidLength { len: 10 })
+        );
+    }
+}
@@ -0,0 +1,78 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The update state is stored apart from the rest of the session, along with the version of its
+//! layout, so that a state which can't be understood only disables catching up on updates,
+//! rather than making the whole session fail to load.
+use crate::generated::{enums, types};
+use grammers_tl_types::{Deserializable, Serializable};
+use log::warn;
+use std::collections::HashSet;
+
+/// Version of the layout used to store the update state.
+pub(crate) const STATE_VERSION: i32 = 1;
+
+/// Store the update state of the session in its versioned field.
+pub(crate) fn pack(session: &mut types::Session) {
+    match session.state.take() {
+        Some(state) => {
+            session.state_version = Some(STATE_VERSION);
+            session.state_data = Some(state.to_bytes());
+        }
+        None => {
+            session.state_version = None;
+            session.state_data = None;
+        }
+    }
+}
+
+/// Restore the update state of the session from its versioned field.
+///
+/// Sessions written before the field existed keep the state they had. States that are
+/// corrupted or written by a newer version are discarded with a warning, so the updates missed
+/// while offline won't be caught up on.
+pub(crate) fn unpack(session: &mut types::Session) {
+    let version = session.state_version.take();
+    if let Some(data) = session.state_data.take() {
+        session.state = match version {
+            Some(STATE_VERSION) => match enums::UpdateState::from_bytes(&data) {
+                Ok(state) => Some(state),
+                Err(e) => {
+                    warn!("discarding update state which failed to load: {}", e);
+                    None
+                }
+            },
+            _ => {
+                warn!("discarding update state of unknown version {:?}", version);
+                None
+            }
+        };
+    }
+
+    if let Some(enums::UpdateState::State(state)) = &session.state {
+        if !is_valid(state) {
+            warn!("discarding corrupted update state: {:?}", state);
+            session.state = None;
+        }
+    }
+}
+
+/// Whether the state is something Telegram could have sent.
+fn is_valid(state: &types::UpdateState) -> bool {
+    let mut seen = HashSet::with_capacity(state.channels.len());
+    state.pts >= 0
+        && state.qts >= 0
+        && state.seq >= 0
+        && state.date >= 0
+        && state
+            .channels
+            .iter()
+            .all(|enums::ChannelState::State(channel)| {
+                channel.channel_id > 0 && channel.pts > 0 && seen.insert(channel.channel_id)
+            })
+}
@@ -38,6 +38,12 @@ fn write_enum<W: Write>(
     }
 
     writeln!(file, "{indent}#[derive(Clone, PartialEq)]")?;
+    if config.impl_serde {
+        writeln!(
+            file,
+            "{indent}#[derive(serde::Serialize, serde::Deserialize)]"
+        )?;
+    }
     writeln!(
         file,
         "{}pub enum {} {{",
@@ -45,6 +51,15 @@ fn write_enum<W: Write>(
         rustifier::types::type_name(ty)
     )?;
     for d in metadata.defs_with_type(ty) {
+        // Variants are tagged with the name of their constructor.
+        if config.impl_serde {
+            writeln!(
+                file,
+                "{}    #[serde(rename = \"{}\")]",
+                indent,
+                d.full_name()
+            )?;
+        }
         write!(
             file,
             "{}    {}",
@@ -74,18 +89,31 @@ fn write_enum<W: Write>(
     Ok(())
 }
 
-/// Writes impl for getting common fields from enum variants
+/// Whether values of the type can be copied rather than cloned.
+fn is_copy(qual_name: &str) -> bool {
+    let inner = qual_name
+        .strip_prefix("Option<")
+        .and_then(|name| name.strip_suffix('>'))
+        .unwrap_or(qual_name);
+    COPY_TYPES.contains(&inner)
+}
+
+/// Writes impl for getting common fields from enum variants, that is, fields with
+/// the same name and type in every variant, even if they are optional:
 ///
 /// ```ignore
 /// enum Enum {
-///     A { id: i64, other: i64 },
-///     B { id: i64 }
+///     A { id: i64, other: i64, name: Option<String> },
+///     B { id: i64, name: Option<String> }
 /// }
 ///
 /// impl Enum {
 ///     pub fn id(&self) -> i64 {
 ///         self.id
 ///     }
+///     pub fn name(&self) -> Option<String> {
+///         self.name.clone()
+///     }
 /// }
 /// ```
 fn write_common_field_impl<W: Write>(
@@ -100,18 +128,19 @@ fn write_common_field_impl<W: Write>(
     if definitions.len() <= 1 {
         return Ok(());
     }
-    // Get common parameters
-    let mut common_params = HashSet::new();
+    // Get common parameters by name and type, since their flag may differ,
+    // in the order they're defined in the first variant
+    let mut common_params = Vec::new();
     for (i, d) in definitions.iter().enumerate() {
-        // Filter out Options and flags parameters
-        let params: HashSet<_> = d
+        let params: Vec<_> = d
             .params
             .iter()
-            .filter(|p| match p.ty {
-                ParameterType::Flags => false,
-                ParameterType::Normal { .. } => {
-                    !rustifier::parameters::qual_name(p).contains("Option<")
-                }
+            .filter(|p| matches!(p.ty, ParameterType::Normal { .. }))
+            .map(|p| {
+                (
+                    rustifier::parameters::attr_name(p),
+                    rustifier::parameters::qual_name(p),
+                )
             })
             .collect();
         // Faster
@@ -123,7 +152,8 @@ fn write_common_field_impl<W: Write>(
             common_params = params;
             continue;
         }
-        common_params = common_params.intersection(&params).copied().collect();
+        let params: HashSet<_> = params.into_iter().collect();
+        common_params.retain(|param| params.contains(param));
     }
     if common_params.is_empty() {
         return Ok(());
@@ -135,15 +165,11 @@ fn write_common_field_impl<W: Write>(
         indent,
         rustifier::types::type_name(ty)
     )?;
-    for param in common_params {
-        let qual_name = rustifier::parameters::qual_name(param);
+    for (attr_name, qual_name) in common_params {
         writeln!(
             file,
             "{}    pub fn {}(&self) -> {} {{\n{}        match self {{",
-            indent,
-            rustifier::parameters::attr_name(param),
-            qual_name,
-            indent,
+            indent, attr_name, qual_name, indent,
         )?;
         // Match cases
         for d in definitions {
@@ -152,13 +178,9 @@ fn write_common_field_impl<W: Write>(
                 "{}            Self::{}(i) => i.{}{},",
                 indent,
                 rustifier::definitions::variant_name(d),
-                rustifier::parameters::attr_name(param),
+                attr_name,
                 // Clone non Copy types
-                if COPY_TYPES.contains(&qual_name.as_ref()) {
-                    ""
-                } else {
-                    ".clone()"
-                }
+                if is_copy(&qual_name) { "" } else { ".clone()" }
             )?;
         }
         writeln!(file, "{indent}        }}\n{indent}    }}")?;
@@ -232,10 +254,15 @@ fn write_serializable<W: Write>(
 /// impl crate::Deserializable for Name {
 ///     fn deserialize(buf: crate::deserialize::Buffer) -> crate::deserialize::Result<Self> {
 ///         use crate::Identifiable;
-///         Ok(match u32::deserialize(buf)? {
-///             crate::types::Name::CONSTRUCTOR_ID => Self::Variant(crate::types::Name::deserialize(buf)?),
-///             _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, ...)),
-///         })
+///         let start = buf.pos();
+///         let result = (|| -> crate::deserialize::Result<Self> {
+///             let id = u32::deserialize(buf)?;
+///             Ok(match id {
+///                 crate::types::Name::CONSTRUCTOR_ID => Self::Variant(crate::types::Name::deserialize(buf)?),
+///                 _ => return Err(crate::deserialize::Error::UnexpectedConstructor { id }),
+///             })
+///         })();
+///         result.map_err(|e| e.in_type("Name", buf, start))
 ///     }
 /// }
 /// ```
@@ -256,12 +283,17 @@ fn write_deserializable<W: Write>(
         "{indent}    fn deserialize(buf: crate::deserialize::Buffer) -> crate::deserialize::Result<Self> {{"
     )?;
     writeln!(file, "{indent}        use crate::Identifiable;")?;
-    writeln!(file, "{indent}        let id = u32::deserialize(buf)?;")?;
-    writeln!(file, "{indent}        Ok(match id {{")?;
+    writeln!(file, "{indent}        let start = buf.pos();")?;
+    writeln!(
+        file,
+        "{indent}        let result = (|| -> crate::deserialize::Result<Self> {{"
+    )?;
+    writeln!(file, "{indent}            let id = u32::deserialize(buf)?;")?;
+    writeln!(file, "{indent}            Ok(match id {{")?;
     for d in metadata.defs_with_type(ty) {
         write!(
             file,
-            "{}            {}::CONSTRUCTOR_ID => Self::{}",
+            "{}                {}::CONSTRUCTOR_ID => Self::{}",
             indent,
             rustifier::definitions::qual_name(d),
             rustifier::definitions::variant_name(d),
@@ -289,10 +321,16 @@ fn write_deserializable<W: Write>(
     }
     writeln!(
         file,
-        "{indent}            _ => return Err(\
+        "{indent}                _ => return Err(\
          crate::deserialize::Error::UnexpectedConstructor {{ id }}),"
     )?;
-    writeln!(file, "{indent}        }})")?;
+    writeln!(file, "{indent}            }})")?;
+    writeln!(file, "{indent}        }})();")?;
+    writeln!(
+        file,
+        "{indent}        result.map_err(|e| e.in_type({:?}, buf, start))",
+        ty.to_string()
+    )?;
     writeln!(file, "{indent}    }}")?;
     writeln!(file, "{indent}}}")?;
     Ok(())
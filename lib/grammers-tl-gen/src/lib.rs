@@ -24,8 +24,14 @@ pub struct Config {
     pub gen_name_for_id: bool,
     pub deserializable_functions: bool,
     pub impl_debug: bool,
+    /// Implement `Debug` by hand, so that byte strings are shortened and the parameters in
+    /// [`SECRET_PARAMS`] are hidden, rather than deriving it. Only used along `impl_debug`.
+    pub redact_debug: bool,
     pub impl_from_type: bool,
     pub impl_from_enum: bool,
+    pub impl_serde: bool,
+    /// Put the functions of every namespace behind a `tl-{namespace}` cargo feature.
+    pub feature_gated_functions: bool,
 }
 
 impl Default for Config {
@@ -34,8 +40,11 @@ impl Default for Config {
             gen_name_for_id: false,
             deserializable_functions: false,
             impl_debug: true,
+            redact_debug: true,
             impl_from_type: true,
             impl_from_enum: true,
+            impl_serde: false,
+            feature_gated_functions: false,
         }
     }
 }
@@ -44,6 +53,26 @@ impl Default for Config {
 /// since they are "core" types and treated differently.
 const SPECIAL_CASED_TYPES: [&str; 1] = ["Bool"];
 
+/// Parameters holding secrets, such as credentials or password proofs, by the name of their
+/// definition and their own name in the `.tl` file. Their value is hidden when `redact_debug`.
+pub const SECRET_PARAMS: [(&str, &str); 15] = [
+    ("account.passwordInputSettings", "new_password_hash"),
+    ("account.tmpPassword", "tmp_password"),
+    ("auth.acceptLoginToken", "token"),
+    ("auth.exportedAuthorization", "bytes"),
+    ("auth.importAuthorization", "bytes"),
+    ("auth.importBotAuthorization", "bot_auth_token"),
+    ("auth.importLoginToken", "token"),
+    ("auth.importWebTokenAuthorization", "web_auth_token"),
+    ("auth.loginToken", "token"),
+    ("auth.loginTokenMigrateTo", "token"),
+    ("inputCheckPasswordSRP", "M1"),
+    ("p_q_inner_data", "new_nonce"),
+    ("p_q_inner_data_dc", "new_nonce"),
+    ("p_q_inner_data_temp", "new_nonce"),
+    ("p_q_inner_data_temp_dc", "new_nonce"),
+];
+
 fn ignore_type(ty: &Type) -> bool {
     SPECIAL_CASED_TYPES.iter().any(|&x| x == ty.name)
 }
@@ -11,7 +11,7 @@
 use crate::grouper;
 use crate::metadata::Metadata;
 use crate::rustifier;
-use crate::{ignore_type, Config};
+use crate::{ignore_type, Config, SECRET_PARAMS};
 use grammers_tl_parser::tl::{Category, Definition, ParameterType};
 use std::io::{self, Write};
 
@@ -44,6 +44,77 @@ fn get_generic_param_list(def: &Definition, trait_bounds: &str) -> String {
     result
 }
 
+/// Whether the definition has parameters and all of them are behind a flag,
+/// so that the definition can be created with none of them set.
+fn is_all_optional(def: &Definition) -> bool {
+    let mut params = def
+        .params
+        .iter()
+        .filter_map(|param| match &param.ty {
+            ParameterType::Flags => None,
+            ParameterType::Normal { flag, .. } => Some(flag),
+        })
+        .peekable();
+    params.peek().is_some() && params.all(|flag| flag.is_some())
+}
+
+/// Whether the parameter type is made up of byte strings.
+fn is_bytes(qual_name: &str) -> bool {
+    matches!(
+        qual_name,
+        "Vec<u8>" | "Option<Vec<u8>>" | "Vec<Vec<u8>>" | "Option<Vec<Vec<u8>>>"
+    )
+}
+
+/// Writes the text as a doc comment, one line at a time.
+fn write_doc<W: Write>(file: &mut W, indent: &str, text: &str) -> io::Result<()> {
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            writeln!(file, "{indent}///")?;
+        } else {
+            writeln!(file, "{indent}/// {}", line.trim_end())?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the documentation of the definition from the schema, if any:
+///
+/// ```ignore
+/// /// Description.
+/// ///
+/// /// # Errors
+/// ///
+/// /// Telegram may return the following errors:
+/// ///
+/// /// * `ERROR_NAME`: Description.
+/// ```
+fn write_definition_doc<W: Write>(file: &mut W, indent: &str, def: &Definition) -> io::Result<()> {
+    if let Some(description) = &def.doc.description {
+        write_doc(file, indent, description)?;
+    }
+    if !def.doc.errors.is_empty() {
+        if def.doc.description.is_some() {
+            writeln!(file, "{indent}///")?;
+        }
+        writeln!(file, "{indent}/// # Errors")?;
+        writeln!(file, "{indent}///")?;
+        writeln!(
+            file,
+            "{indent}/// Telegram may return the following errors:"
+        )?;
+        writeln!(file, "{indent}///")?;
+        for (name, description) in def.doc.errors.iter() {
+            if description.is_empty() {
+                writeln!(file, "{indent}/// * `{name}`")?;
+            } else {
+                writeln!(file, "{indent}/// * `{name}`: {description}")?;
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Defines the `struct` corresponding to the definition:
 ///
 /// ```ignore
@@ -59,11 +130,21 @@ fn write_struct<W: Write>(
     config: &Config,
 ) -> io::Result<()> {
     // Define struct
-    if config.impl_debug {
+    write_definition_doc(file, indent, def)?;
+    if config.impl_debug && !config.redact_debug {
         writeln!(file, "{indent}#[derive(Debug)]")?;
     }
 
     writeln!(file, "{indent}#[derive(Clone, PartialEq)]")?;
+    if is_all_optional(def) {
+        writeln!(file, "{indent}#[derive(Default)]")?;
+    }
+    if config.impl_serde {
+        writeln!(
+            file,
+            "{indent}#[derive(serde::Serialize, serde::Deserialize)]"
+        )?;
+    }
     write!(
         file,
         "{}pub struct {}{} {{",
@@ -79,16 +160,87 @@ fn write_struct<W: Write>(
                 // Flags are computed on-the-fly, not stored
             }
             ParameterType::Normal { .. } => {
+                let qual_name = rustifier::parameters::qual_name(param);
+                if let Some(description) = def.doc.param(&param.name) {
+                    write_doc(file, &format!("{indent}    "), description)?;
+                }
+                // Byte strings are encoded as base64, rather than as a sequence of numbers.
+                if config.impl_serde && is_bytes(&qual_name) {
+                    writeln!(file, "{indent}    #[serde(with = \"crate::serde_bytes\")]")?;
+                }
                 writeln!(
                     file,
                     "{}    pub {}: {},",
                     indent,
                     rustifier::parameters::attr_name(param),
-                    rustifier::parameters::qual_name(param),
+                    qual_name,
+                )?;
+            }
+        }
+    }
+    writeln!(file, "{indent}}}")?;
+    Ok(())
+}
+
+/// Defines the `impl Debug` corresponding to the definition, which shortens byte strings and
+/// hides secrets:
+///
+/// ```ignore
+/// impl std::fmt::Debug for Name {
+///     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+///         f.debug_struct("Name")
+///             .field("field", &self.field)
+///             .field("bytes", &crate::debug::Bytes(&self.bytes))
+///             .field("secret", &crate::debug::Redacted)
+///             .finish()
+///     }
+/// }
+/// ```
+fn write_debug<W: Write>(
+    file: &mut W,
+    indent: &str,
+    def: &Definition,
+    _metadata: &Metadata,
+) -> io::Result<()> {
+    let type_name = rustifier::definitions::type_name(def);
+    writeln!(
+        file,
+        "{}impl{} std::fmt::Debug for {}{} {{",
+        indent,
+        get_generic_param_list(def, ": std::fmt::Debug"),
+        type_name,
+        get_generic_param_list(def, ""),
+    )?;
+    writeln!(
+        file,
+        "{indent}    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {{"
+    )?;
+    writeln!(file, "{indent}        f.debug_struct(\"{type_name}\")")?;
+    let name = def.full_name();
+    for param in def.params.iter() {
+        match param.ty {
+            ParameterType::Flags => {}
+            ParameterType::Normal { .. } => {
+                let attr_name = rustifier::parameters::attr_name(param);
+                let value = if SECRET_PARAMS.contains(&(name.as_str(), param.name.as_str())) {
+                    "crate::debug::Redacted".to_string()
+                } else if is_bytes(&rustifier::parameters::qual_name(param)) {
+                    format!("crate::debug::Bytes(&self.{attr_name})")
+                } else {
+                    format!("self.{attr_name}")
+                };
+                writeln!(
+                    file,
+                    "{}            .field(\"{}\", &{})",
+                    indent,
+                    attr_name.trim_start_matches("r#"),
+                    value,
                 )?;
             }
         }
     }
+    writeln!(file, "{indent}            .finish()")?;
+    writeln!(file, "{indent}    }}")?;
     writeln!(file, "{indent}}}")?;
     Ok(())
 }
@@ -448,6 +600,9 @@ fn write_definition<W: Write>(
     config: &Config,
 ) -> io::Result<()> {
     write_struct(file, indent, def, metadata, config)?;
+    if config.impl_debug && config.redact_debug {
+        write_debug(file, indent, def, metadata)?;
+    }
     write_identifiable(file, indent, def, metadata)?;
     write_serializable(file, indent, def, metadata)?;
     if def.category == Category::Types || config.deserializable_functions {
@@ -518,6 +673,9 @@ pub(crate) fn write_category_mod<W: Write>(
         let indent = if key.is_empty() {
             "    "
         } else {
+            if category == Category::Functions && config.feature_gated_functions {
+                writeln!(file, "    #[cfg(feature = \"tl-{key}\")]")?;
+            }
             writeln!(file, "    #[allow(clippy::unreadable_literal)]")?;
             writeln!(file, "    pub mod {key} {{")?;
             "        "
@@ -26,8 +26,11 @@ fn gen_rust_code(definitions: &[Definition]) -> io::Result<String> {
             gen_name_for_id: false,
             deserializable_functions: true,
             impl_debug: true,
+            redact_debug: false,
             impl_from_enum: true,
             impl_from_type: true,
+            impl_serde: false,
+            feature_gated_functions: false,
         },
     )?;
     Ok(String::from_utf8(file).unwrap())
@@ -119,3 +122,160 @@ fn recursive_types_vec_indirect_not_boxed() -> io::Result<()> {
     assert!(result.contains("JsonObject(crate::types::JsonObject)"));
     Ok(())
 }
+
+#[test]
+fn serde_tags_variants_and_encodes_bytes() -> io::Result<()> {
+    let definitions = get_definitions(
+        "
+        chatPhotoEmpty#37c1011c = ChatPhoto;
+        chatPhoto#1c6e1c11 flags:# has_video:flags.0?true photo_id:long stripped_thumb:flags.1?bytes dc_id:int = ChatPhoto;
+        ---functions---
+        messages.getFileHashes#1 files:Vector<bytes> = Vector<bytes>;
+    ",
+    );
+    let mut file = Vec::new();
+    generate_rust_code(
+        &mut file,
+        &definitions,
+        LAYER,
+        &Config {
+            impl_serde: true,
+            ..Default::default()
+        },
+    )?;
+    let result = String::from_utf8(file).unwrap();
+    eprintln!("{result}");
+    assert!(result.contains("#[derive(serde::Serialize, serde::Deserialize)]"));
+    assert!(result.contains("#[serde(rename = \"chatPhotoEmpty\")]"));
+    assert!(result.contains("#[serde(rename = \"chatPhoto\")]"));
+    assert!(result.contains(
+        "#[serde(with = \"crate::serde_bytes\")]\n        pub stripped_thumb: Option<Vec<u8>>,"
+    ));
+    assert!(result
+        .contains("#[serde(with = \"crate::serde_bytes\")]\n            pub files: Vec<Vec<u8>>,"));
+    assert!(!result.contains("#[serde(with = \"crate::serde_bytes\")]\n        pub photo_id"));
+    Ok(())
+}
+
+#[test]
+fn feature_gated_functions_only_gate_namespaced_functions() -> io::Result<()> {
+    let definitions = get_definitions(
+        "
+        auth.loggedOut#c3a2835f flags:# future_auth_token:flags.0?bytes = auth.LoggedOut;
+        ---functions---
+        ping#7abe77ec ping_id:long = Pong;
+        auth.logOut#3e72ba19 = auth.LoggedOut;
+    ",
+    );
+    let mut file = Vec::new();
+    generate_rust_code(
+        &mut file,
+        &definitions,
+        LAYER,
+        &Config {
+            feature_gated_functions: true,
+            ..Default::default()
+        },
+    )?;
+    let result = String::from_utf8(file).unwrap();
+    eprintln!("{result}");
+    assert_eq!(result.matches("#[cfg(feature = \"tl-auth\")]").count(), 1);
+    let gate = result.find("#[cfg(feature = \"tl-auth\")]").unwrap();
+    assert!(result[gate..].contains("pub struct LogOut"));
+    assert!(!result[gate..].contains("pub struct LoggedOut "));
+    assert!(!result[gate..].contains("pub struct Ping"));
+    Ok(())
+}
+
+#[test]
+fn redacted_debug_shortens_bytes_and_hides_secrets() -> io::Result<()> {
+    let definitions = get_definitions(
+        "
+        ---functions---
+        auth.importBotAuthorization#67a3ff2c flags:int api_id:int api_hash:string bot_auth_token:string = auth.Authorization;
+        upload.saveFilePart#b304a621 file_id:long file_part:int bytes:bytes = Bool;
+    ",
+    );
+    let mut file = Vec::new();
+    generate_rust_code(&mut file, &definitions, LAYER, &Config::default())?;
+    let result = String::from_utf8(file).unwrap();
+    eprintln!("{result}");
+    assert!(!result.contains("#[derive(Debug)]"));
+    assert!(result.contains("impl std::fmt::Debug for ImportBotAuthorization {"));
+    assert!(result.contains(".field(\"api_hash\", &self.api_hash)"));
+    assert!(result.contains(".field(\"bot_auth_token\", &crate::debug::Redacted)"));
+    assert!(result.contains(".field(\"bytes\", &crate::debug::Bytes(&self.bytes))"));
+    Ok(())
+}
+
+#[test]
+fn schema_documentation_becomes_rustdoc() -> io::Result<()> {
+    let definitions = get_definitions(
+        "
+        //@description A message @id Identifier of the message
+        message#1 id:int text:string = Message;
+        ---functions---
+        //@description Sends a message
+        //@peer The chat where the message
+        //-should be sent
+        //@error PEER_ID_INVALID The provided peer is invalid
+        sendMessage#2 peer:int = Message;
+    ",
+    );
+    let result = gen_rust_code(&definitions)?;
+    eprintln!("{result}");
+    assert!(result.contains("    /// A message\n    #[derive(Debug)]"));
+    assert!(result.contains("        /// Identifier of the message\n        pub id: i32,"));
+    assert!(result.contains("        pub id: i32,\n        pub text: String,"));
+    assert!(result.contains(
+        "    /// Sends a message
+    ///
+    /// # Errors
+    ///
+    /// Telegram may return the following errors:
+    ///
+    /// * `PEER_ID_INVALID`: The provided peer is invalid
+    #[derive(Debug)]
+    #[derive(Clone, PartialEq)]
+    pub struct SendMessage {
+        /// The chat where the message should be sent
+        pub peer: i32,"
+    ));
+    Ok(())
+}
+
+#[test]
+fn common_fields_include_optional_ones() -> io::Result<()> {
+    let definitions = get_definitions(
+        "
+        userEmpty#1 flags:# id:long username:flags.0?string = User;
+        user#2 flags:# bot:flags.1?true id:long first_name:string username:flags.3?string lang:flags.4?int = User;
+    ",
+    );
+    let result = gen_rust_code(&definitions)?;
+    eprintln!("{result}");
+    assert!(result.contains("pub fn id(&self) -> i64 {"));
+    assert!(result.contains("Self::Empty(i) => i.id,"));
+    assert!(result.contains("pub fn username(&self) -> Option<String> {"));
+    assert!(result.contains("Self::User(i) => i.username.clone(),"));
+    assert!(!result.contains("pub fn first_name(&self)"));
+    assert!(!result.contains("pub fn lang(&self)"));
+    assert!(result.find("pub fn id(").unwrap() < result.find("pub fn username(").unwrap());
+    Ok(())
+}
+
+#[test]
+fn all_optional_definitions_implement_default() -> io::Result<()> {
+    let definitions = get_definitions(
+        "
+        inputReplyToMessage#1 flags:# reply_to_msg_id:flags.0?int quote:flags.1?true = InputReplyTo;
+        inputPeerUser#2 user_id:long = InputPeer;
+        inputPeerEmpty#3 = InputPeer;
+    ",
+    );
+    let result = gen_rust_code(&definitions)?;
+    eprintln!("{result}");
+    assert_eq!(result.matches("#[derive(Default)]").count(), 1);
+    assert!(result.contains("#[derive(Default)]\n    pub struct InputReplyToMessage {"));
+    Ok(())
+}
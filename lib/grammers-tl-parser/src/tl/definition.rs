@@ -9,7 +9,7 @@ use std::fmt;
 use std::str::FromStr;
 
 use crate::errors::{ParamParseError, ParseError};
-use crate::tl::{Category, Flag, Parameter, ParameterType, Type};
+use crate::tl::{Category, Documentation, Flag, Parameter, ParameterType, Type};
 use crate::utils::infer_id;
 
 /// A [Type Language] definition.
@@ -38,6 +38,9 @@ pub struct Definition {
 
     /// The category to which this definition belongs to.
     pub category: Category,
+
+    /// The documentation found in the comments preceding this definition.
+    pub doc: Documentation,
 }
 
 impl fmt::Display for Definition {
@@ -217,6 +220,7 @@ impl FromStr for Definition {
             params,
             ty,
             category: Category::Types,
+            doc: Documentation::default(),
         })
     }
 }
@@ -437,6 +441,7 @@ mod tests {
                     generic_arg: None,
                 },
                 category: Category::Types,
+                doc: Documentation::default(),
             })
         );
     }
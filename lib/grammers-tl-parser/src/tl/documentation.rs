@@ -0,0 +1,123 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// The documentation of a definition, taken from the comments preceding it.
+///
+/// Comments starting with `//@` contain `@tag text` pairs, and comments starting with `//-`
+/// continue the text of the previous tag:
+///
+/// ```text
+/// //@description Sends a message @peer The chat where the message
+/// //-should be sent @message The text of the message
+/// //@error PEER_ID_INVALID The provided peer is invalid
+/// ```
+///
+/// Other tags, such as `@class`, are ignored.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Documentation {
+    /// The description of the definition itself.
+    pub description: Option<String>,
+
+    /// The description of the parameters, by name, in the order they were documented.
+    pub params: Vec<(String, String)>,
+
+    /// The errors Telegram may return when the function is invoked, by name, along with
+    /// their description.
+    pub errors: Vec<(String, String)>,
+}
+
+impl Documentation {
+    /// Parses the documentation out of the comments, without their leading `//`.
+    pub(crate) fn from_comments<'a>(comments: impl Iterator<Item = &'a str>) -> Self {
+        let mut text = String::new();
+        for comment in comments {
+            if let Some(continuation) = comment.strip_prefix('-') {
+                text.push(' ');
+                text.push_str(continuation.trim());
+            } else {
+                text.push(' ');
+                text.push_str(comment.trim());
+            }
+        }
+
+        let mut doc = Self::default();
+        for part in text.split(" @").filter(|part| !part.trim().is_empty()) {
+            let (tag, value) = match part.find(char::is_whitespace) {
+                Some(pos) => (&part[..pos], part[pos..].trim()),
+                None => (part, ""),
+            };
+            match tag {
+                "description" => doc.description = Some(value.to_string()),
+                "class" => {}
+                "error" => {
+                    let (name, value) = match value.find(char::is_whitespace) {
+                        Some(pos) => (&value[..pos], value[pos..].trim()),
+                        None => (value, ""),
+                    };
+                    doc.errors.push((name.to_string(), value.to_string()));
+                }
+                param => doc.params.push((param.to_string(), value.to_string())),
+            }
+        }
+        doc
+    }
+
+    /// Whether there is no documentation at all.
+    pub fn is_empty(&self) -> bool {
+        self.description.is_none() && self.params.is_empty() && self.errors.is_empty()
+    }
+
+    /// The description of the parameter with the given name, if any.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, description)| description.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tags() {
+        let doc = Documentation::from_comments(
+            [
+                "@description Sends a message @peer The chat where",
+                "-the message should be sent @message",
+                "@error PEER_ID_INVALID The provided peer is invalid",
+                "@error FLOOD",
+            ]
+            .into_iter(),
+        );
+        assert_eq!(doc.description.as_deref(), Some("Sends a message"));
+        assert_eq!(
+            doc.param("peer"),
+            Some("The chat where the message should be sent")
+        );
+        assert_eq!(doc.param("message"), Some(""));
+        assert_eq!(doc.param("random_id"), None);
+        assert_eq!(
+            doc.errors,
+            vec![
+                (
+                    "PEER_ID_INVALID".to_string(),
+                    "The provided peer is invalid".to_string()
+                ),
+                ("FLOOD".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_nothing() {
+        assert!(Documentation::from_comments(std::iter::empty()).is_empty());
+        assert!(Documentation::from_comments(["@class Message"].into_iter()).is_empty());
+    }
+}
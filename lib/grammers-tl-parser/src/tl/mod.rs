@@ -12,6 +12,7 @@
 //! [Type Language]: https://core.telegram.org/mtproto/TL
 mod category;
 mod definition;
+mod documentation;
 mod flag;
 mod parameter;
 mod parameter_type;
@@ -19,6 +20,7 @@ mod ty;
 
 pub use category::Category;
 pub use definition::Definition;
+pub use documentation::Documentation;
 pub use flag::Flag;
 pub use parameter::Parameter;
 pub use parameter_type::ParameterType;
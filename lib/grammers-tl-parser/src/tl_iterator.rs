@@ -6,7 +6,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 use crate::errors::ParseError;
-use crate::tl::{Category, Definition};
+use crate::tl::{Category, Definition, Documentation};
 use crate::utils::remove_tl_comments;
 
 const DEFINITION_SEP: &str = ";";
@@ -18,14 +18,17 @@ const TYPES_SEP: &str = "---types---";
 /// [Type Language]: https://core.telegram.org/mtproto/TL
 pub struct TlIterator {
     contents: String,
+    docs: Vec<(usize, String)>,
     index: usize,
     category: Category,
 }
 
 impl TlIterator {
     pub(crate) fn new(contents: &str) -> Self {
+        let (contents, docs) = remove_tl_comments(contents);
         TlIterator {
-            contents: remove_tl_comments(contents),
+            contents,
+            docs,
             index: 0,
             category: Category::Types,
         }
@@ -36,10 +39,11 @@ impl Iterator for TlIterator {
     type Item = Result<Definition, ParseError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let definition = loop {
+        let (definition, start) = loop {
             if self.index >= self.contents.len() {
                 return None;
             }
+            let start = self.index;
             let end = if let Some(end) = self.contents[self.index..].find(DEFINITION_SEP) {
                 self.index + end
             } else {
@@ -50,7 +54,7 @@ impl Iterator for TlIterator {
             self.index = end + DEFINITION_SEP.len();
 
             if !definition.is_empty() {
-                break definition;
+                break (definition, start);
             }
         };
 
@@ -73,6 +77,12 @@ impl Iterator for TlIterator {
         Some(match definition.parse::<Definition>() {
             Ok(mut d) => {
                 d.category = self.category;
+                d.doc = Documentation::from_comments(
+                    self.docs
+                        .iter()
+                        .filter(|(pos, _)| (start..self.index).contains(pos))
+                        .map(|(_, comment)| comment.as_str()),
+                );
                 Ok(d)
             }
             x => x,
@@ -109,4 +119,28 @@ mod tests {
         assert_eq!(it.next().unwrap().unwrap().id, 3);
         assert_eq!(it.next(), None);
     }
+
+    #[test]
+    fn parse_file_docs() {
+        let mut it = TlIterator::new(
+            "
+            //@description The first @a Its parameter
+            first#1 a:int = t;
+            second#2 = t;
+            ---functions---
+            //@description The third
+            //@error BAD_REQUEST It was bad
+            third#3 = t;
+        ",
+        );
+
+        let first = it.next().unwrap().unwrap();
+        assert_eq!(first.doc.description.as_deref(), Some("The first"));
+        assert_eq!(first.doc.param("a"), Some("Its parameter"));
+        assert!(it.next().unwrap().unwrap().doc.is_empty());
+        let third = it.next().unwrap().unwrap();
+        assert_eq!(third.doc.description.as_deref(), Some("The third"));
+        assert_eq!(third.doc.errors.len(), 1);
+        assert_eq!(it.next(), None);
+    }
 }
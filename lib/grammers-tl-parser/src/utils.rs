@@ -11,32 +11,38 @@
 use crc32fast::Hasher;
 
 /// Removes all single-line comments from the contents.
-pub(crate) fn remove_tl_comments(contents: &str) -> String {
+///
+/// The documentation comments, those starting with `//@` or `//-`, are returned too, along
+/// with the position in the result where they were found.
+pub(crate) fn remove_tl_comments(contents: &str) -> (String, Vec<(usize, String)>) {
     let mut result = String::with_capacity(contents.len());
-    let mut in_comment = false;
-
-    contents.chars().enumerate().for_each(|(i, c)| {
-        if contents[i..contents.len().min(i + 2)] == *"//" {
-            in_comment = true;
-        } else if in_comment && c == '\n' {
-            in_comment = false;
-        }
-
-        if !in_comment {
-            result.push(c);
+    let mut docs = Vec::new();
+
+    for line in contents.split_inclusive('\n') {
+        if let Some(pos) = line.find("//") {
+            result.push_str(&line[..pos]);
+            let comment = line[pos + 2..].trim_end();
+            if comment.starts_with('@') || comment.starts_with('-') {
+                docs.push((result.len(), comment.to_string()));
+            }
+            if line.ends_with('\n') {
+                result.push('\n');
+            }
+        } else {
+            result.push_str(line);
         }
-    });
+    }
 
     result.shrink_to_fit();
-    result
+    (result, docs)
 }
 
 /// Infers the identifier for a definition.
 pub(crate) fn infer_id(definition: &str) -> u32 {
     #[allow(clippy::collapsible_str_replace)]
     let mut representation = definition
-        .replace(":bytes ", ": string")
-        .replace("?bytes ", "? string")
+        .replace(":bytes ", ":string ")
+        .replace("?bytes ", "?string ")
         .replace('<', " ")
         .replace('>', "")
         .replace('{', "")
@@ -60,31 +66,46 @@ mod tests {
     #[test]
     fn remove_comments_noop() {
         let data = "hello\nworld";
-        assert_eq!(remove_tl_comments(data), data);
+        assert_eq!(remove_tl_comments(data).0, data);
 
         let data = " \nhello\nworld\n ";
-        assert_eq!(remove_tl_comments(data), data);
+        assert_eq!(remove_tl_comments(data).0, data);
     }
 
     #[test]
     fn remove_comments_leading() {
         let input = " // hello\n world ";
         let expected = " \n world ";
-        assert_eq!(remove_tl_comments(input), expected);
+        assert_eq!(remove_tl_comments(input).0, expected);
     }
 
     #[test]
     fn remove_comments_trailing() {
         let input = " \nhello \n // world \n \n ";
         let expected = " \nhello \n \n \n ";
-        assert_eq!(remove_tl_comments(input), expected);
+        assert_eq!(remove_tl_comments(input).0, expected);
     }
 
     #[test]
     fn remove_comments_many() {
         let input = "no\n//yes\nno\n//yes\nno\n";
         let expected = "no\n\nno\n\nno\n";
-        assert_eq!(remove_tl_comments(input), expected);
+        assert_eq!(remove_tl_comments(input).0, expected);
+    }
+
+    #[test]
+    fn remove_comments_keeps_docs() {
+        let input = "//@description Café\n//- ünïcode\na = A; // no\n/// no\n";
+        let expected = "\n\na = A; \n\n";
+        let (result, docs) = remove_tl_comments(input);
+        assert_eq!(result, expected);
+        assert_eq!(
+            docs,
+            vec![
+                (0, "@description Café".to_string()),
+                (1, "- ünïcode".to_string())
+            ]
+        );
     }
 
     #[test]
@@ -93,6 +114,10 @@ mod tests {
         let def = "rpc_answer_dropped msg_id:long seq_no:int bytes:int = RpcDropAnswer";
         assert_eq!(infer_id(def), 0xa43ad8b7);
 
+        // Note the parameter of type `bytes`, which is treated as `string`
+        let def = "upload.saveFilePart file_id:long file_part:int bytes:bytes = Bool";
+        assert_eq!(infer_id(def), 0xb304a621);
+
         // Note the use of angle brackets
         let def = "msgs_ack msg_ids:Vector<long> = MsgsAck";
         assert_eq!(infer_id(def), 0x62d6b459);
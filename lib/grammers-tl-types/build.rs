@@ -74,8 +74,11 @@ fn main() -> std::io::Result<()> {
         gen_name_for_id: true,
         deserializable_functions: cfg!(feature = "deserializable-functions"),
         impl_debug: cfg!(feature = "impl-debug"),
+        redact_debug: !cfg!(feature = "full-debug"),
         impl_from_enum: cfg!(feature = "impl-from-enum"),
         impl_from_type: cfg!(feature = "impl-from-type"),
+        impl_serde: cfg!(feature = "serde"),
+        feature_gated_functions: true,
     };
 
     generate_rust_code(&mut file, &definitions, layer, &config)?;
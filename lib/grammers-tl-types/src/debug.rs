@@ -0,0 +1,88 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Helpers used by the generated `Debug` implementations to keep logs short and free of secrets.
+//!
+//! Unless the `full-debug` feature is enabled, byte strings are formatted as their length and
+//! the first few bytes in hexadecimal, and credentials are hidden entirely.
+use std::fmt;
+
+/// How many bytes of a byte string are shown.
+const PREFIX_LEN: usize = 4;
+
+/// Formats byte strings as their length and a short hexadecimal prefix, such as
+/// `<1024 bytes: 0a1b2c3d…>`.
+pub struct Bytes<'a, T>(pub &'a T);
+
+/// Formats a secret value as `<redacted>`, without looking at it.
+pub struct Redacted;
+
+fn fmt_bytes(bytes: &[u8], f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "<{} bytes", bytes.len())?;
+    if !bytes.is_empty() {
+        f.write_str(": ")?;
+        for byte in bytes.iter().take(PREFIX_LEN) {
+            write!(f, "{byte:02x}")?;
+        }
+        if bytes.len() > PREFIX_LEN {
+            f.write_str("…")?;
+        }
+    }
+    f.write_str(">")
+}
+
+impl fmt::Debug for Bytes<'_, Vec<u8>> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_bytes(self.0, f)
+    }
+}
+
+impl fmt::Debug for Bytes<'_, Vec<Vec<u8>>> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.0.iter().map(Bytes)).finish()
+    }
+}
+
+impl<'a, T> fmt::Debug for Bytes<'a, Option<T>>
+where
+    Bytes<'a, T>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(value) => f.debug_tuple("Some").field(&Bytes(value)).finish(),
+            None => f.write_str("None"),
+        }
+    }
+}
+
+impl fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_are_shortened() {
+        assert_eq!(format!("{:?}", Bytes(&Vec::<u8>::new())), "<0 bytes>");
+        assert_eq!(format!("{:?}", Bytes(&vec![1, 2])), "<2 bytes: 0102>");
+        assert_eq!(
+            format!("{:?}", Bytes(&vec![0xab; 256])),
+            "<256 bytes: abababab…>"
+        );
+        assert_eq!(
+            format!("{:?}", Bytes(&Some(vec![vec![0xff; 5]]))),
+            "Some([<5 bytes: ffffffff…>])"
+        );
+        assert_eq!(format!("{:?}", Bytes(&None::<Vec<u8>>)), "None");
+        assert_eq!(format!("{:?}", Redacted), "<redacted>");
+    }
+}
@@ -30,16 +30,118 @@ pub enum Error {
         /// The unexpected constructor identifier.
         id: u32,
     },
+
+    /// One of the other errors occurred while deserializing a boxed type, and this variant
+    /// contains the details about where it occurred.
+    ///
+    /// Only the innermost boxed type being deserialized is reported, which is the one whose
+    /// definition most likely disagrees with the data (for example, after a layer change).
+    InType(Box<ErrorContext>),
+}
+
+/// Where in the buffer a deserialization [`Error`] occurred.
+///
+/// # Examples
+///
+/// ```
+/// use grammers_tl_types::{deserialize::Error, enums, Deserializable};
+///
+/// // inputPeerUser with a truncated user_id.
+/// let error = enums::InputPeer::from_bytes(&[0x4c, 0xa5, 0xe8, 0xdd, 1, 0, 0]).unwrap_err();
+/// assert_eq!(error.root_cause(), &Error::UnexpectedEof);
+/// match error {
+///     Error::InType(context) => {
+///         assert_eq!(context.ty, "InputPeer");
+///         assert_eq!(context.constructor_id, Some(0xdde8a54c));
+///         assert_eq!(context.offset, 4);
+///     }
+///     _ => unreachable!(),
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ErrorContext {
+    /// The error that occurred, which is never [`Error::InType`].
+    pub error: Error,
+    /// The name of the type that was expected, as it appears in the schema.
+    pub ty: &'static str,
+    /// The constructor identifier of the type being deserialized, if it could be read.
+    pub constructor_id: Option<u32>,
+    /// The byte offset into the buffer where the error occurred.
+    pub offset: usize,
+    /// The bytes surrounding the offset, starting at `offset - dump_offset`.
+    pub dump: Vec<u8>,
+    /// Where the offset is within the `dump`.
+    pub dump_offset: usize,
+}
+
+/// How many bytes to include in the [`ErrorContext::dump`] before and after the error.
+const DUMP_MARGIN: usize = 16;
+
+impl Error {
+    /// Attach the context of the boxed type that was being deserialized when this error
+    /// occurred, starting at `start`, unless it already has a more specific one.
+    pub fn in_type(self, ty: &'static str, buf: &Cursor, start: usize) -> Self {
+        match self {
+            Self::InType(_) => self,
+            error => {
+                let offset = buf.pos.min(buf.buf.len());
+                let dump_start = offset.saturating_sub(DUMP_MARGIN);
+                let dump_end = (offset + DUMP_MARGIN).min(buf.buf.len());
+                Self::InType(Box::new(ErrorContext {
+                    error,
+                    ty,
+                    constructor_id: buf
+                        .buf
+                        .get(start..start + 4)
+                        .map(|id| u32::from_le_bytes([id[0], id[1], id[2], id[3]])),
+                    offset,
+                    dump: buf.buf[dump_start..dump_end].to_vec(),
+                    dump_offset: offset - dump_start,
+                }))
+            }
+        }
+    }
+
+    /// The error that occurred, without the context of where it did.
+    pub fn root_cause(&self) -> &Self {
+        match self {
+            Self::InType(context) => &context.error,
+            error => error,
+        }
+    }
 }
 
 impl std::error::Error for Error {}
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {
+        match self {
             Self::UnexpectedEof => write!(f, "unexpected eof"),
             Self::UnexpectedConstructor { id } => write!(f, "unexpected constructor: {id:08x}"),
+            Self::InType(context) => write!(f, "{context}"),
+        }
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} while reading {}", self.error, self.ty)?;
+        if let Some(id) = self.constructor_id {
+            write!(f, " ({})", crate::name_for_id(id))?;
+        }
+        write!(f, " at offset {}:", self.offset)?;
+        for (i, byte) in self.dump.iter().enumerate() {
+            if i == self.dump_offset {
+                write!(f, " >")?;
+            } else {
+                write!(f, " ")?;
+            }
+            write!(f, "{byte:02x}")?;
+        }
+        if self.dump_offset == self.dump.len() {
+            write!(f, " >")?;
         }
+        Ok(())
     }
 }
 
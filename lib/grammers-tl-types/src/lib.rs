@@ -43,6 +43,7 @@
 //!
 //! The default feature set includes:
 //!
+//! * `full`.
 //! * `impl-debug`.
 //! * `impl-from-enum`.
 //! * `impl-from-type`.
@@ -50,17 +51,36 @@
 //!
 //! The available features are:
 //!
+//! * `full`: enables the `tl-{namespace}` feature of every namespace.
+//!
+//! * `tl-{namespace}`, such as `tl-messages` or `tl-channels`: generates the
+//!   [`functions`] in that namespace. The [`types`] of all namespaces, and the
+//!   functions without a namespace, are always generated, since they can be
+//!   used from any namespace. Libraries should enable only the namespaces they
+//!   use, and leave `full` to the final application if it needs it.
+//!
 //! * `deserializable-functions`: implements [`Deserializable`] for
 //!   [`functions`]. This might be of interest for server implementations,
 //!   which need to deserialize the client's requests, but is otherwise not
 //!   required.
 //!
-//! * `impl-debug`: implements `Debug` for the generated code.
+//! * `impl-debug`: implements `Debug` for the generated code. Byte strings are
+//!   shortened to their length and first few bytes, and secrets such as login
+//!   tokens or password proofs are hidden, so that requests and responses can
+//!   be logged safely.
+//!
+//! * `full-debug`: makes `Debug` print every field in full, including byte
+//!   strings and secrets. Useful to debug the protocol itself.
 //!
 //! * `impl-from-enum`: implements `TryFrom<Enum> for Type`.
 //!
 //! * `impl-from-type`: implements `From<Type> for Enum`.
 //!
+//! * `serde`: implements `Serialize` and `Deserialize` for the generated code.
+//!   Byte strings are represented as base64 strings, and the variants of the
+//!   [`enums`] are tagged by the name of their constructor in the `.tl` file,
+//!   such as `"messages.dialogsSlice"`.
+//!
 //! * `tl-api`: generates code for the `api.tl`.
 //!   This is what high-level libraries often need.
 //!
@@ -76,8 +96,12 @@
 
 #![deny(unsafe_code)]
 
+#[doc(hidden)]
+pub mod debug;
 pub mod deserialize;
 mod generated;
+#[cfg(feature = "serde")]
+mod serde_bytes;
 pub mod serialize;
 
 pub use deserialize::{Cursor, Deserializable};
@@ -90,13 +114,23 @@ pub use serialize::Serializable;
 /// as Rust's `Vec` (as we would do with auto-generated code),
 /// a new-type for `vector` is used instead.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RawVec<T>(pub Vec<T>);
 
 /// This struct represents an unparsed blob, which should not be deserialized
 /// as a bytes string. Used by functions returning generic objects which pass
 /// the underlying result without any modification or interpretation.
-#[derive(Clone, Debug, PartialEq)]
-pub struct Blob(pub Vec<u8>);
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "full-debug", derive(Debug))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Blob(#[cfg_attr(feature = "serde", serde(with = "crate::serde_bytes"))] pub Vec<u8>);
+
+#[cfg(not(feature = "full-debug"))]
+impl std::fmt::Debug for Blob {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_tuple("Blob").field(&debug::Bytes(&self.0)).finish()
+    }
+}
 
 impl From<Vec<u8>> for Blob {
     fn from(value: Vec<u8>) -> Self {
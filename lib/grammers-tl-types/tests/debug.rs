@@ -0,0 +1,47 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+#![cfg(all(feature = "impl-debug", not(feature = "full-debug")))]
+
+use grammers_tl_types::{enums, functions, types};
+
+#[test]
+fn debug_hides_secrets() {
+    let request = functions::auth::ImportBotAuthorization {
+        flags: 0,
+        api_id: 1,
+        api_hash: "hash".into(),
+        bot_auth_token: "123:secret".into(),
+    };
+    let output = format!("{request:?}");
+    assert!(output.contains("api_hash: \"hash\""));
+    assert!(output.contains("bot_auth_token: <redacted>"));
+    assert!(!output.contains("secret"));
+}
+
+#[test]
+fn debug_shortens_bytes() {
+    let authorization =
+        enums::auth::ExportedAuthorization::Authorization(types::auth::ExportedAuthorization {
+            id: 1,
+            bytes: vec![0x42; 1024],
+        });
+    assert_eq!(
+        format!("{authorization:?}"),
+        "Authorization(ExportedAuthorization { id: 1, bytes: <redacted> })"
+    );
+
+    let part = functions::upload::SaveFilePart {
+        file_id: 1,
+        file_part: 0,
+        bytes: vec![0xab; 512 * 1024],
+    };
+    assert_eq!(
+        format!("{part:?}"),
+        "SaveFilePart { file_id: 1, file_part: 0, bytes: <524288 bytes: abababab…> }"
+    );
+}
@@ -0,0 +1,51 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+use std::collections::BTreeSet;
+use std::fs;
+
+/// The namespaces with functions in the given `.tl` file.
+fn function_namespaces(path: &str) -> BTreeSet<String> {
+    let contents = fs::read_to_string(path).unwrap();
+    let mut functions = false;
+    let mut namespaces = BTreeSet::new();
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with("---") {
+            functions = line == "---functions---";
+        } else if functions && !line.is_empty() && !line.starts_with("//") {
+            let name = line.split(['#', ' ']).next().unwrap();
+            if let Some((namespace, _)) = name.split_once('.') {
+                namespaces.insert(namespace.to_string());
+            }
+        }
+    }
+    namespaces
+}
+
+#[test]
+fn every_namespace_has_a_feature() {
+    let manifest = fs::read_to_string("Cargo.toml")
+        .unwrap()
+        .parse::<toml::Table>()
+        .unwrap();
+    let features = manifest["features"].as_table().unwrap();
+    let full = features["full"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|feature| feature.as_str().unwrap())
+        .collect::<BTreeSet<_>>();
+
+    let mut namespaces = function_namespaces("tl/api.tl");
+    namespaces.extend(function_namespaces("tl/mtproto.tl"));
+    assert!(namespaces.contains("messages"));
+    for namespace in namespaces {
+        let feature = format!("tl-{namespace}");
+        assert!(features.contains_key(&feature), "missing feature {feature}");
+        assert!(full.contains(feature.as_str()), "{feature} not in full");
+    }
+}
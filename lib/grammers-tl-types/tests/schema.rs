@@ -0,0 +1,85 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Checks that the schema files are consistent, and that the generated code matches them.
+//! These are the tests to run after replacing the files in `tl/` with a newer layer.
+#![cfg(feature = "tl-api")]
+
+use grammers_tl_parser::errors::ParseError;
+use grammers_tl_parser::parse_tl_file;
+use grammers_tl_parser::tl::Definition;
+use std::fs;
+
+/// The definitions in the schema file, skipping those which are not meant to be generated.
+fn definitions(path: &str) -> Vec<Definition> {
+    parse_tl_file(&fs::read_to_string(path).unwrap())
+        .filter(|definition| definition != &Err(ParseError::NotImplemented))
+        .map(|definition| definition.unwrap())
+        .collect()
+}
+
+#[test]
+fn layer_matches_schema() {
+    let contents = fs::read_to_string("tl/api.tl").unwrap();
+    let first_line = contents.lines().next().unwrap();
+    assert_eq!(
+        first_line.trim(),
+        format!("// LAYER {}", grammers_tl_types::LAYER),
+        "the first line of api.tl must be the layer comment"
+    );
+}
+
+#[test]
+fn constructor_ids_match_definitions() {
+    // The identifier written in the schema must be the one inferred from the rest of the
+    // definition, or the definition was edited by hand without updating it (or vice versa).
+    let contents = fs::read_to_string("tl/api.tl").unwrap();
+    let mut mismatches = Vec::new();
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with("//") || line.starts_with("---") {
+            continue;
+        }
+        let line = line.trim_end_matches(';');
+        let (written, start) = match (line.parse::<Definition>(), line.find('#')) {
+            (Ok(written), Some(start)) => (written, start),
+            _ => continue,
+        };
+        let end = line[start..]
+            .find(' ')
+            .map_or(line.len(), |end| start + end);
+        let inferred = format!("{}{}", &line[..start], &line[end..])
+            .parse::<Definition>()
+            .unwrap();
+        if inferred.id != written.id {
+            mismatches.push(written.full_name());
+        }
+    }
+    assert!(mismatches.is_empty(), "wrong identifiers: {mismatches:?}");
+}
+
+#[test]
+fn generated_code_matches_schema() {
+    // Every definition must have been generated with the identifier found in the schema.
+    // If this fails, the generated code is stale or was edited by hand.
+    let mut paths = vec!["tl/api.tl"];
+    if cfg!(feature = "tl-mtproto") {
+        paths.push("tl/mtproto.tl");
+    }
+    for path in paths {
+        for definition in definitions(path) {
+            if definition.ty.name == "Bool" {
+                continue;
+            }
+            assert_eq!(
+                grammers_tl_types::name_for_id(definition.id),
+                definition.full_name(),
+                "{path}: {definition}"
+            );
+        }
+    }
+}
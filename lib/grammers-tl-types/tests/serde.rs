@@ -0,0 +1,197 @@
+// Copyright 2020 - developers of the `grammers` project.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+#![cfg(feature = "serde")]
+
+use grammers_tl_types::{enums, types, Deserializable, Serializable};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+
+/// Check that going from TL to JSON and back produces the same object and bytes.
+fn round_trip<T>(value: T) -> String
+where
+    T: Serializable + Deserializable + Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let bytes = value.to_bytes();
+    let json = serde_json::to_string(&T::from_bytes(&bytes).unwrap()).unwrap();
+    let back = serde_json::from_str::<T>(&json).unwrap();
+    assert_eq!(back, value);
+    assert_eq!(back.to_bytes(), bytes);
+    json
+}
+
+fn chat() -> enums::Chat {
+    types::Chat {
+        creator: true,
+        left: false,
+        deactivated: false,
+        call_active: false,
+        call_not_empty: false,
+        noforwards: true,
+        id: 123,
+        title: "Test \"group\"".to_string(),
+        photo: types::ChatPhoto {
+            has_video: false,
+            photo_id: 456,
+            stripped_thumb: Some(vec![1, 40, 40, 0, 255]),
+            dc_id: 2,
+        }
+        .into(),
+        participants_count: 2,
+        date: 1_700_000_000,
+        version: 1,
+        migrated_to: None,
+        admin_rights: None,
+        default_banned_rights: None,
+    }
+    .into()
+}
+
+fn message() -> enums::Message {
+    types::Message {
+        out: true,
+        mentioned: false,
+        media_unread: false,
+        silent: false,
+        post: false,
+        from_scheduled: false,
+        legacy: false,
+        edit_hide: false,
+        pinned: false,
+        noforwards: false,
+        invert_media: false,
+        offline: false,
+        id: 10,
+        from_id: Some(types::PeerUser { user_id: 789 }.into()),
+        from_boosts_applied: None,
+        peer_id: types::PeerChat { chat_id: 123 }.into(),
+        saved_peer_id: None,
+        fwd_from: None,
+        via_bot_id: None,
+        via_business_bot_id: None,
+        reply_to: None,
+        date: 1_700_000_001,
+        message: "hello 🌍".to_string(),
+        media: None,
+        reply_markup: None,
+        entities: Some(vec![types::MessageEntityBold {
+            offset: 0,
+            length: 5,
+        }
+        .into()]),
+        views: None,
+        forwards: None,
+        replies: None,
+        edit_date: None,
+        post_author: None,
+        grouped_id: Some(-1),
+        reactions: None,
+        restriction_reason: None,
+        ttl_period: None,
+        quick_reply_shortcut_id: None,
+        effect: None,
+        factcheck: None,
+        video_processing_pending: false,
+        paid_suggested_post_stars: false,
+        paid_suggested_post_ton: false,
+        from_rank: None,
+        guestchat_via_from: None,
+        report_delivery_until_date: None,
+        paid_message_stars: None,
+        suggested_post: None,
+        schedule_repeat_period: None,
+        summary_from_language: None,
+        rich_message: None,
+    }
+    .into()
+}
+
+#[test]
+fn dialogs_round_trip() {
+    let dialogs: enums::messages::Dialogs = types::messages::DialogsSlice {
+        count: 50,
+        dialogs: vec![types::Dialog {
+            pinned: true,
+            unread_mark: false,
+            view_forum_as_messages: false,
+            peer: types::PeerChat { chat_id: 123 }.into(),
+            top_message: 10,
+            read_inbox_max_id: 9,
+            read_outbox_max_id: 10,
+            unread_count: 1,
+            unread_mentions_count: 0,
+            unread_reactions_count: 0,
+            notify_settings: types::PeerNotifySettings {
+                show_previews: Some(true),
+                silent: None,
+                mute_until: Some(0),
+                ios_sound: None,
+                android_sound: Some(enums::NotificationSound::Default),
+                other_sound: None,
+                stories_muted: None,
+                stories_hide_sender: None,
+                stories_ios_sound: None,
+                stories_android_sound: None,
+                stories_other_sound: None,
+            }
+            .into(),
+            pts: None,
+            draft: None,
+            folder_id: None,
+            ttl_period: None,
+            unread_poll_votes_count: 0,
+        }
+        .into()],
+        messages: vec![message()],
+        chats: vec![chat()],
+        users: vec![types::UserEmpty { id: 789 }.into()],
+    }
+    .into();
+
+    let json = round_trip(dialogs);
+    assert!(json.starts_with(r#"{"messages.dialogsSlice":"#));
+    assert!(json.contains(r#""notificationSoundDefault""#));
+    assert!(json.contains(r#""stripped_thumb":"ASgoAP8=""#));
+}
+
+#[test]
+fn updates_round_trip() {
+    let updates: enums::Updates = types::Updates {
+        updates: vec![
+            types::UpdateNewMessage {
+                message: message(),
+                pts: 100,
+                pts_count: 1,
+            }
+            .into(),
+            types::UpdateDeleteMessages {
+                messages: vec![1, 2, 3],
+                pts: 101,
+                pts_count: 1,
+            }
+            .into(),
+        ],
+        users: vec![types::UserEmpty { id: 789 }.into()],
+        chats: vec![chat()],
+        date: 1_700_000_002,
+        seq: 0,
+    }
+    .into();
+
+    let json = round_trip(updates);
+    assert!(json.contains(r#"{"updateNewMessage":{"message":{"message":{"out":true"#));
+
+    round_trip(enums::Updates::TooLong);
+}
+
+#[test]
+fn invalid_base64_is_rejected() {
+    let json =
+        r#"{"chatPhoto":{"has_video":false,"photo_id":1,"stripped_thumb":"A*==","dc_id":2}}"#;
+    assert!(serde_json::from_str::<enums::ChatPhoto>(json).is_err());
+}
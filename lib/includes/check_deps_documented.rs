@@ -20,10 +20,20 @@ fn check_deps_documented() {
 
         match toml.parse::<toml::Value>() {
             Ok(Value::Table(mut map)) => {
-                for &key in ["dependencies", "build-dependencies", "dev-dependencies"].iter() {
-                    if let Some(Value::Table(build)) = map.remove(key) {
-                        for (dep, _) in build {
-                            deps.insert(dep);
+                // Dependencies only used on some targets are listed under `[target.'cfg(...)']`.
+                let mut tables = vec![map.clone()];
+                if let Some(Value::Table(targets)) = map.remove("target") {
+                    tables.extend(targets.into_iter().filter_map(|(_, target)| match target {
+                        Value::Table(target) => Some(target),
+                        _ => None,
+                    }));
+                }
+                for mut map in tables {
+                    for &key in ["dependencies", "build-dependencies", "dev-dependencies"].iter() {
+                        if let Some(Value::Table(build)) = map.remove(key) {
+                            for (dep, _) in build {
+                                deps.insert(dep);
+                            }
                         }
                     }
                 }